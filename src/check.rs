@@ -0,0 +1,1214 @@
+//! The `check` command: run every diagnostic rule over a workspace and report
+//! the problems found, for people or for CI pipelines.
+
+use crate::config::{find_rule, Config, DIAGNOSTIC_RULES, RULES_HELP_URI};
+use crate::fixtures::{apply_fixes, Finding, Fix, FixtureDatabase};
+use colored::Colorize;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tower_lsp_server::ls_types::Uri;
+
+/// Version of the JSON report format, bumped on incompatible changes.
+pub const REPORT_VERSION: u32 = 1;
+
+/// Version of the SARIF specification the SARIF report follows.
+const SARIF_VERSION: &str = "2.1.0";
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// How many times a file is checked again to apply fixes that overlapped
+/// fixes applied before them.
+const MAX_FIX_PASSES: usize = 10;
+
+/// Lines of context around the changes of a diff.
+const DIFF_CONTEXT: usize = 3;
+
+/// Output formats of the `check` command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckFormat {
+    Text,
+    Json,
+    /// SARIF 2.1.0, for GitHub code scanning and other SARIF consumers.
+    Sarif,
+    /// GitHub Actions workflow commands, annotating pull requests inline.
+    Github,
+    /// JUnit XML, for the test report views of Jenkins, GitLab and others.
+    Junit,
+}
+
+impl std::str::FromStr for CheckFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "sarif" => Ok(Self::Sarif),
+            "github" => Ok(Self::Github),
+            "junit" => Ok(Self::Junit),
+            other => Err(format!(
+                "unknown check format '{}', expected 'text', 'json', 'sarif', 'github' or 'junit'",
+                other
+            )),
+        }
+    }
+}
+
+/// Lowest severity of the problems failing the `check` command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum FailOn {
+    Error,
+    Warning,
+    Information,
+    /// Any problem (the default).
+    Hint,
+    /// No problem fails the check, only `--max-warnings` does.
+    None,
+}
+
+impl std::str::FromStr for FailOn {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "error" => Ok(Self::Error),
+            "warning" => Ok(Self::Warning),
+            "information" | "info" => Ok(Self::Information),
+            "hint" => Ok(Self::Hint),
+            "none" => Ok(Self::None),
+            other => Err(format!(
+                "unknown severity '{}', expected 'error', 'warning', 'information', 'hint' or 'none'",
+                other
+            )),
+        }
+    }
+}
+
+/// The problems found in a workspace.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    /// Version of the report format ([`REPORT_VERSION`]).
+    pub version: u32,
+    pub tool: Tool,
+    /// The checked directory, which paths are relative to.
+    #[serde(skip)]
+    pub root: PathBuf,
+    /// The checked files, relative to the checked directory.
+    #[serde(skip)]
+    pub files: Vec<String>,
+    pub diagnostics: Vec<ReportDiagnostic>,
+    pub summary: Summary,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Tool {
+    pub name: &'static str,
+    pub version: &'static str,
+}
+
+/// A problem found by a rule.
+#[derive(Debug, Serialize)]
+pub struct ReportDiagnostic {
+    /// Stable rule code (e.g. `PTL001`).
+    pub code: &'static str,
+    /// Rule name (e.g. `undeclared-fixture`).
+    pub rule: &'static str,
+    pub severity: &'static str,
+    /// Path of the file, relative to the checked directory, with `/` separators.
+    pub path: String,
+    pub range: Range,
+    pub message: String,
+    /// Identifies the problem across runs while the code around it changes
+    /// (see [`Finding::fingerprint`]).
+    pub fingerprint: String,
+}
+
+/// A range of a line, 1-based, with columns counted in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Counts of the files checked and of the problems found, by severity.
+#[derive(Debug, Default, Serialize)]
+pub struct Summary {
+    pub files: usize,
+    pub errors: usize,
+    pub warnings: usize,
+    pub information: usize,
+    pub hints: usize,
+    /// Problems not reported because they are in the baseline.
+    pub baselined: usize,
+}
+
+impl Summary {
+    fn count(&mut self, severity: &str) {
+        match severity {
+            "error" => self.errors += 1,
+            "warning" => self.warnings += 1,
+            "information" => self.information += 1,
+            _ => self.hints += 1,
+        }
+    }
+}
+
+/// The problems recorded with `check --baseline`, which
+/// `check --baseline-check` does not report again.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Baseline {
+    /// Version of the report format the baseline was recorded with.
+    pub version: u32,
+    pub diagnostics: Vec<BaselineEntry>,
+}
+
+/// A recorded problem. Only its fingerprint is compared; the rest helps
+/// reviewing the baseline.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BaselineEntry {
+    pub code: String,
+    pub path: String,
+    pub message: String,
+    pub fingerprint: String,
+}
+
+impl Baseline {
+    /// Record the problems of a report.
+    pub fn from_report(report: &Report) -> Self {
+        Self {
+            version: REPORT_VERSION,
+            diagnostics: report
+                .diagnostics
+                .iter()
+                .map(|diagnostic| BaselineEntry {
+                    code: diagnostic.code.to_string(),
+                    path: diagnostic.path.clone(),
+                    message: diagnostic.message.clone(),
+                    fingerprint: diagnostic.fingerprint.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Read a baseline file.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("cannot read baseline {}: {}", path.display(), e))?;
+        let baseline: Self = serde_json::from_str(&content)
+            .map_err(|e| format!("invalid baseline {}: {}", path.display(), e))?;
+        if baseline.version != REPORT_VERSION {
+            return Err(format!(
+                "baseline {} has version {}, expected {}; record it again",
+                path.display(),
+                baseline.version,
+                REPORT_VERSION
+            ));
+        }
+        Ok(baseline)
+    }
+
+    /// Write the baseline to a file.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("failed to serialize baseline: {}", e))?;
+        std::fs::write(path, content + "\n")
+            .map_err(|e| format!("cannot write baseline {}: {}", path.display(), e))
+    }
+}
+
+impl Report {
+    /// Run the rules enabled by `config` over `files`, of the workspace
+    /// scanned at `root`.
+    pub fn collect(
+        fixture_db: &FixtureDatabase,
+        config: &Config,
+        root: &Path,
+        files: &[PathBuf],
+    ) -> Self {
+        fixture_db.reanalyze_files(files);
+        let mut summary = Summary {
+            files: files.len(),
+            ..Default::default()
+        };
+        let mut diagnostics = Vec::new();
+        // Identical problems on identical lines of a file are numbered
+        let mut occurrences: HashMap<String, usize> = HashMap::new();
+        for file in files {
+            // Rules report in turn; list a file's problems in source order
+            let mut findings =
+                fixture_db.check_file(file, |rule| config.effective_severity(rule).is_some());
+            findings.sort_by_key(|finding| (finding.line, finding.start_char));
+            for finding in findings {
+                let Some(severity) = config.effective_severity(finding.rule) else {
+                    continue;
+                };
+                summary.count(severity.as_str());
+                let path = relative_path(&finding.file_path, root);
+                let line_text = line_text(fixture_db, &finding).unwrap_or_default();
+                let mut fingerprint = finding.fingerprint(&path, &line_text);
+                let occurrence = occurrences.entry(fingerprint.clone()).or_default();
+                *occurrence += 1;
+                if *occurrence > 1 {
+                    fingerprint = format!("{}:{}", fingerprint, occurrence);
+                }
+                diagnostics.push(ReportDiagnostic {
+                    code: find_rule(finding.rule).map_or(finding.rule, |rule| rule.id),
+                    rule: finding.rule,
+                    severity: severity.as_str(),
+                    path,
+                    range: finding_range(&finding, &line_text),
+                    message: finding.message,
+                    fingerprint,
+                });
+            }
+        }
+        Self {
+            version: REPORT_VERSION,
+            tool: Tool {
+                name: env!("CARGO_PKG_NAME"),
+                version: env!("CARGO_PKG_VERSION"),
+            },
+            root: root.to_path_buf(),
+            files: files.iter().map(|file| relative_path(file, root)).collect(),
+            diagnostics,
+            summary,
+        }
+    }
+
+    /// Drop the problems recorded in `baseline`, counting them apart.
+    pub fn apply_baseline(&mut self, baseline: &Baseline) {
+        let known: HashSet<&str> = baseline
+            .diagnostics
+            .iter()
+            .map(|entry| entry.fingerprint.as_str())
+            .collect();
+        let mut summary = Summary {
+            files: self.summary.files,
+            ..Default::default()
+        };
+        self.diagnostics.retain(|diagnostic| {
+            if known.contains(diagnostic.fingerprint.as_str()) {
+                summary.baselined += 1;
+                false
+            } else {
+                summary.count(diagnostic.severity);
+                true
+            }
+        });
+        self.summary = summary;
+    }
+
+    /// Whether the report fails the check: it has problems of `fail_on`
+    /// severity or above, or more warnings than `max_warnings`.
+    pub fn fails(&self, fail_on: FailOn, max_warnings: Option<usize>) -> bool {
+        let summary = &self.summary;
+        let failing = match fail_on {
+            FailOn::Error => summary.errors,
+            FailOn::Warning => summary.errors + summary.warnings,
+            FailOn::Information => summary.errors + summary.warnings + summary.information,
+            FailOn::Hint => summary.errors + summary.warnings + summary.information + summary.hints,
+            FailOn::None => 0,
+        };
+        failing > 0 || max_warnings.is_some_and(|max| summary.warnings > max)
+    }
+
+    /// Render the report in `format`.
+    pub fn render(&self, format: CheckFormat) -> Result<String, serde_json::Error> {
+        match format {
+            CheckFormat::Text => Ok(self.to_text()),
+            CheckFormat::Json => serde_json::to_string_pretty(self),
+            CheckFormat::Sarif => serde_json::to_string_pretty(&self.to_sarif()),
+            CheckFormat::Github => {
+                // Annotations name files relative to the repository
+                let workspace = std::env::var_os("GITHUB_WORKSPACE")
+                    .filter(|value| !value.is_empty())
+                    .map(|value| {
+                        let path = PathBuf::from(value);
+                        path.canonicalize().unwrap_or(path)
+                    });
+                Ok(self.to_github(workspace.as_deref()))
+            }
+            CheckFormat::Junit => Ok(self.to_junit()),
+        }
+    }
+
+    /// A JUnit XML report with a test case per checked file, failing with
+    /// the problems found in it.
+    fn to_junit(&self) -> String {
+        let failed = self
+            .files
+            .iter()
+            .filter(|file| self.diagnostics.iter().any(|d| &d.path == *file))
+            .count();
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str(&format!(
+            "<testsuites name=\"{name}\" tests=\"{tests}\" failures=\"{failed}\" errors=\"0\">\n\
+             \x20 <testsuite name=\"{name}\" tests=\"{tests}\" failures=\"{failed}\" errors=\"0\" skipped=\"0\">\n",
+            name = self.tool.name,
+            tests = self.files.len(),
+            failed = failed,
+        ));
+        for file in &self.files {
+            let path = escape_xml(file);
+            let diagnostics: Vec<&ReportDiagnostic> = self
+                .diagnostics
+                .iter()
+                .filter(|d| &d.path == file)
+                .collect();
+            if diagnostics.is_empty() {
+                out.push_str(&format!(
+                    "    <testcase classname=\"{}\" name=\"{}\" file=\"{}\"/>\n",
+                    self.tool.name, path, path
+                ));
+                continue;
+            }
+            // The failure is as severe as the most severe problem
+            let severity = ["error", "warning", "information", "hint"]
+                .into_iter()
+                .find(|severity| diagnostics.iter().any(|d| d.severity == *severity))
+                .unwrap_or("hint");
+            let details: String = diagnostics
+                .iter()
+                .map(|d| {
+                    format!(
+                        "{}:{}:{}: {} [{}] {}\n",
+                        d.path,
+                        d.range.start.line,
+                        d.range.start.column,
+                        d.code,
+                        d.severity,
+                        d.message
+                    )
+                })
+                .collect();
+            out.push_str(&format!(
+                "    <testcase classname=\"{}\" name=\"{}\" file=\"{}\">\n\
+                 \x20     <failure type=\"{}\" message=\"{} problem(s)\">{}</failure>\n\
+                 \x20   </testcase>\n",
+                self.tool.name,
+                path,
+                path,
+                severity,
+                diagnostics.len(),
+                escape_xml(&details)
+            ));
+        }
+        out.push_str("  </testsuite>\n</testsuites>\n");
+        out
+    }
+
+    /// One `::warning file=...,line=...::message` workflow command per
+    /// problem, with paths relative to `workspace` when given (and the
+    /// checked directory is inside it), to the checked directory otherwise.
+    fn to_github(&self, workspace: Option<&Path>) -> String {
+        let mut out = String::new();
+        for diagnostic in &self.diagnostics {
+            let path = match workspace {
+                Some(workspace) if self.root.starts_with(workspace) => {
+                    relative_path(&self.root.join(&diagnostic.path), workspace)
+                }
+                _ => diagnostic.path.clone(),
+            };
+            let command = match diagnostic.severity {
+                "error" => "error",
+                "warning" => "warning",
+                _ => "notice",
+            };
+            out.push_str(&format!(
+                "::{} file={},line={},endLine={},col={},endColumn={},title={}::{}\n",
+                command,
+                escape_property(&path),
+                diagnostic.range.start.line,
+                diagnostic.range.end.line,
+                diagnostic.range.start.column,
+                diagnostic.range.end.column,
+                escape_property(&format!("{} {}", diagnostic.code, diagnostic.rule)),
+                escape_data(&diagnostic.message)
+            ));
+        }
+        out
+    }
+
+    /// A SARIF log with a single run: every rule, with its metadata, and a
+    /// result per problem.
+    fn to_sarif(&self) -> serde_json::Value {
+        let rules: Vec<serde_json::Value> = DIAGNOSTIC_RULES
+            .iter()
+            .map(|rule| {
+                serde_json::json!({
+                    "id": rule.id,
+                    "name": rule.name,
+                    "shortDescription": { "text": rule.description },
+                    "helpUri": RULES_HELP_URI,
+                    "defaultConfiguration": { "level": sarif_level(rule.default_severity.as_str()) },
+                })
+            })
+            .collect();
+        let results: Vec<serde_json::Value> = self
+            .diagnostics
+            .iter()
+            .map(|diagnostic| {
+                serde_json::json!({
+                    "ruleId": diagnostic.code,
+                    "ruleIndex": DIAGNOSTIC_RULES.iter().position(|rule| rule.id == diagnostic.code),
+                    "level": sarif_level(diagnostic.severity),
+                    "message": { "text": diagnostic.message },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": {
+                                "uri": diagnostic.path,
+                                "uriBaseId": "%SRCROOT%",
+                            },
+                            "region": {
+                                "startLine": diagnostic.range.start.line,
+                                "startColumn": diagnostic.range.start.column,
+                                "endLine": diagnostic.range.end.line,
+                                "endColumn": diagnostic.range.end.column,
+                            },
+                        },
+                    }],
+                    "partialFingerprints": {
+                        "pytestLanguageServer/v1": diagnostic.fingerprint,
+                    },
+                })
+            })
+            .collect();
+
+        let mut run = serde_json::json!({
+            "tool": {
+                "driver": {
+                    "name": self.tool.name,
+                    "version": self.tool.version,
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "rules": rules,
+                },
+            },
+            "columnKind": "unicodeCodePoints",
+            "results": results,
+        });
+        // Base URIs must end with a slash
+        if let Some(root_uri) = Uri::from_file_path(&self.root) {
+            let root_uri = format!("{}/", root_uri.as_str().trim_end_matches('/'));
+            run["originalUriBaseIds"] = serde_json::json!({ "%SRCROOT%": { "uri": root_uri } });
+        }
+        serde_json::json!({
+            "$schema": SARIF_SCHEMA,
+            "version": SARIF_VERSION,
+            "runs": [run],
+        })
+    }
+
+    /// One `path:line:column: code [severity] message` line per problem,
+    /// then a summary.
+    fn to_text(&self) -> String {
+        let mut out = String::new();
+        for diagnostic in &self.diagnostics {
+            let severity = match diagnostic.severity {
+                "error" => diagnostic.severity.red().bold(),
+                "warning" => diagnostic.severity.yellow().bold(),
+                _ => diagnostic.severity.blue().bold(),
+            };
+            out.push_str(&format!(
+                "{}:{}:{}: {} [{}] {}\n",
+                diagnostic.path.bold(),
+                diagnostic.range.start.line,
+                diagnostic.range.start.column,
+                diagnostic.code.dimmed(),
+                severity,
+                diagnostic.message
+            ));
+        }
+        if self.diagnostics.is_empty() {
+            out.push_str(&format!(
+                "{} ({} files checked)\n",
+                "No problems found.".green(),
+                self.summary.files
+            ));
+        } else {
+            out.push_str(&format!(
+                "\n{} {} problem(s) in {} files checked: {} error(s), {} warning(s), {} information, {} hint(s)\n",
+                "Found".red().bold(),
+                self.diagnostics.len(),
+                self.summary.files,
+                self.summary.errors,
+                self.summary.warnings,
+                self.summary.information,
+                self.summary.hints
+            ));
+        }
+        if self.summary.baselined > 0 {
+            out.push_str(&format!(
+                "{} known problem(s) in the baseline\n",
+                self.summary.baselined
+            ));
+        }
+        out
+    }
+}
+
+/// The fixes applied to a file.
+#[derive(Debug)]
+pub struct FileFixes {
+    pub file_path: PathBuf,
+    /// Path of the file, relative to the checked directory.
+    pub path: String,
+    pub original: String,
+    pub fixed: String,
+    /// Number of problems fixed.
+    pub count: usize,
+}
+
+impl FileFixes {
+    /// A unified diff of the fixes.
+    pub fn diff(&self) -> String {
+        unified_diff(&self.path, &self.original, &self.fixed)
+    }
+}
+
+/// Apply the fixes of the problems the rules enabled by `config` find in
+/// `files`, of the workspace scanned at `root`, in memory: the database is
+/// updated, the files are not written. A file is checked again after its
+/// fixes are applied, for the fixes that overlapped them.
+pub fn fix_files(
+    fixture_db: &FixtureDatabase,
+    config: &Config,
+    root: &Path,
+    files: &[PathBuf],
+) -> Vec<FileFixes> {
+    fixture_db.reanalyze_files(files);
+    let mut fixed_files = Vec::new();
+    for file in files.iter().cloned() {
+        let Some(original) = fixture_db.get_file_content(&file) else {
+            continue;
+        };
+        let mut content = original.to_string();
+        let mut count = 0;
+        for _ in 0..MAX_FIX_PASSES {
+            let findings =
+                fixture_db.check_file(&file, |rule| config.effective_severity(rule).is_some());
+            let fixes: Vec<&Fix> = findings.iter().filter_map(|f| f.fix.as_ref()).collect();
+            let (fixed, applied) = apply_fixes(&content, fixes);
+            if applied == 0 {
+                break;
+            }
+            content = fixed;
+            count += applied;
+            // Without previous contents, the whole file is analyzed again
+            fixture_db.uncache_file_content(&file);
+            fixture_db.analyze_file(file.clone(), &content);
+        }
+        if count > 0 {
+            fixed_files.push(FileFixes {
+                path: relative_path(&file, root),
+                file_path: file,
+                original: original.to_string(),
+                fixed: content,
+                count,
+            });
+        }
+    }
+    fixed_files
+}
+
+/// An operation of a line diff, with the indices of the lines in the old and
+/// new texts it is at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DiffOp {
+    Equal(usize, usize),
+    Delete(usize, usize),
+    Insert(usize, usize),
+}
+
+impl DiffOp {
+    fn indices(self) -> (usize, usize) {
+        match self {
+            Self::Equal(old, new) | Self::Delete(old, new) | Self::Insert(old, new) => (old, new),
+        }
+    }
+}
+
+/// The shortest edit script turning `old` into `new` (Myers' algorithm).
+fn diff_lines(old: &[&str], new: &[&str]) -> Vec<DiffOp> {
+    let (n, m) = (old.len() as isize, new.len() as isize);
+    let offset = n + m + 1;
+    let mut v = vec![0isize; (2 * offset + 1) as usize];
+    let at = |k: isize| (k + offset) as usize;
+    let mut trace = Vec::new();
+    'search: for d in 0..=(n + m) {
+        trace.push(v.clone());
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]) {
+                v[at(k + 1)]
+            } else {
+                v[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            v[at(k)] = x;
+            if x >= n && y >= m {
+                break 'search;
+            }
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut x, mut y) = (n, m);
+    for (d, v) in trace.iter().enumerate().rev() {
+        let (d, k) = (d as isize, x - y);
+        let prev_k = if k == -d || (k != d && v[at(k - 1)] < v[at(k + 1)]) {
+            k + 1
+        } else {
+            k - 1
+        };
+        let prev_x = v[at(prev_k)];
+        let prev_y = prev_x - prev_k;
+        while x > prev_x && y > prev_y {
+            x -= 1;
+            y -= 1;
+            ops.push(DiffOp::Equal(x as usize, y as usize));
+        }
+        if d > 0 {
+            if x == prev_x {
+                ops.push(DiffOp::Insert(x as usize, (y - 1) as usize));
+            } else {
+                ops.push(DiffOp::Delete((x - 1) as usize, y as usize));
+            }
+        }
+        (x, y) = (prev_x, prev_y);
+    }
+    ops.reverse();
+    ops
+}
+
+/// A unified diff between two versions of the file at `path`.
+fn unified_diff(path: &str, old: &str, new: &str) -> String {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let ops = diff_lines(&old_lines, &new_lines);
+    let changes: Vec<usize> = (0..ops.len())
+        .filter(|&i| !matches!(ops[i], DiffOp::Equal(..)))
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // Changes closer than twice the context share a hunk
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for &change in &changes {
+        let start = change.saturating_sub(DIFF_CONTEXT);
+        let end = (change + DIFF_CONTEXT + 1).min(ops.len());
+        match hunks.last_mut() {
+            Some(last) if start <= last.1 => last.1 = end,
+            _ => hunks.push((start, end)),
+        }
+    }
+
+    let mut out = format!("--- a/{}\n+++ b/{}\n", path, path);
+    for (start, end) in hunks {
+        let hunk = &ops[start..end];
+        let old_count = hunk
+            .iter()
+            .filter(|op| !matches!(op, DiffOp::Insert(..)))
+            .count();
+        let new_count = hunk
+            .iter()
+            .filter(|op| !matches!(op, DiffOp::Delete(..)))
+            .count();
+        // An empty range starts at the line before it
+        let (old_start, new_start) = hunk[0].indices();
+        let line = |index: usize, count: usize| if count == 0 { index } else { index + 1 };
+        out.push_str(&format!(
+            "@@ -{},{} +{},{} @@\n",
+            line(old_start, old_count),
+            old_count,
+            line(new_start, new_count),
+            new_count
+        ));
+        for op in hunk {
+            match *op {
+                DiffOp::Equal(old, _) => out.push_str(&format!(" {}\n", old_lines[old])),
+                DiffOp::Delete(old, _) => out.push_str(&format!("-{}\n", old_lines[old])),
+                DiffOp::Insert(_, new) => out.push_str(&format!("+{}\n", new_lines[new])),
+            }
+        }
+    }
+    out
+}
+
+/// `file_path` relative to `root`, with `/` separators on every platform.
+fn relative_path(file_path: &Path, root: &Path) -> String {
+    match file_path.strip_prefix(root) {
+        Ok(relative) => relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+        Err(_) => file_path.to_string_lossy().into_owned(),
+    }
+}
+
+/// Escape text and attribute values of an XML document.
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Escape the message of a workflow command.
+fn escape_data(value: &str) -> String {
+    value
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a property of a workflow command, which also ends at `,` and `:`.
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// The SARIF level of a severity.
+fn sarif_level(severity: &str) -> &'static str {
+    match severity {
+        "error" => "error",
+        "warning" => "warning",
+        _ => "note",
+    }
+}
+
+/// The text of the line of a finding.
+fn line_text(fixture_db: &FixtureDatabase, finding: &Finding) -> Option<String> {
+    let content = fixture_db.get_file_content(&finding.file_path)?;
+    content
+        .lines()
+        .nth(finding.line.saturating_sub(1))
+        .map(str::to_string)
+}
+
+/// The 1-based range of a finding on `line_text`, converting its byte
+/// offsets to character columns.
+fn finding_range(finding: &Finding, line_text: &str) -> Range {
+    let column = |byte: usize| match line_text.get(..byte) {
+        Some(prefix) => prefix.chars().count() + 1,
+        None => byte + 1,
+    };
+    Range {
+        start: Position {
+            line: finding.line,
+            column: column(finding.start_char),
+        },
+        end: Position {
+            line: finding.line,
+            column: column(finding.end_char),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn checked_report(files: &[(&str, &str)]) -> Report {
+        let tmp = tempdir().unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+        for (name, content) in files {
+            let path = root.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+        let config = Config::load(&root);
+        let fixture_db = FixtureDatabase::new();
+        fixture_db.scan_workspace(&root);
+        Report::collect(
+            &fixture_db,
+            &config,
+            &root,
+            &fixture_db.checked_files(&root),
+        )
+    }
+
+    #[test]
+    fn test_json_report() {
+        let report = checked_report(&[
+            (
+                "conftest.py",
+                "import pytest\n\n@pytest.fixture\ndef db():\n    pass\n",
+            ),
+            (
+                "tests/test_a.py",
+                "def test_a():\n    café = db\n\ndef test_b(db):\n    pass\n",
+            ),
+        ]);
+        let json: serde_json::Value =
+            serde_json::from_str(&report.render(CheckFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["version"], REPORT_VERSION);
+        assert_eq!(json["tool"]["name"], "pytest-language-server");
+        assert_eq!(
+            json["diagnostics"],
+            serde_json::json!([{
+                "code": "PTL001",
+                "rule": "undeclared-fixture",
+                "severity": "warning",
+                "path": "tests/test_a.py",
+                "range": {
+                    "start": { "line": 2, "column": 12 },
+                    "end": { "line": 2, "column": 14 }
+                },
+                "message": "Fixture 'db' is used but not declared as a parameter",
+                "fingerprint": report.diagnostics[0].fingerprint,
+            }])
+        );
+        assert_eq!(json["summary"]["files"], 2);
+        assert_eq!(json["summary"]["warnings"], 1);
+    }
+
+    #[test]
+    fn test_report_honors_configured_severities() {
+        let files = [
+            (
+                "conftest.py",
+                "import pytest\n\n@pytest.fixture\ndef db():\n    pass\n\n@pytest.fixture(scope=\"session\")\ndef app(db):\n    pass\n",
+            ),
+            (
+                "test_a.py",
+                "def test_a():\n    result = db\n\ndef test_b(app):\n    pass\n",
+            ),
+            (
+                "pyproject.toml",
+                "[tool.pytest-language-server]\ndisabled_diagnostics = [\"undeclared-fixture\"]\n\n[tool.pytest-language-server.severity]\nPTL002 = \"error\"\n",
+            ),
+        ];
+        let report = checked_report(&files);
+        let codes: Vec<(&str, &str)> = report
+            .diagnostics
+            .iter()
+            .map(|d| (d.code, d.severity))
+            .collect();
+        assert_eq!(codes, vec![("PTL002", "error")]);
+        assert_eq!(report.summary.errors, 1);
+        assert_eq!(report.summary.warnings, 0);
+    }
+
+    #[test]
+    fn test_text_report_in_source_order_with_every_severity() {
+        let files = [
+            (
+                "conftest.py",
+                "import pytest\n\n@pytest.fixture\ndef db():\n    pass\n\n@pytest.fixture(scope=\"session\")\ndef app(db):\n    pass\n\n@pytest.fixture\ndef other():\n    return db\n",
+            ),
+            (
+                "pyproject.toml",
+                "[tool.pytest-language-server.severity]\nPTL001 = \"hint\"\nPTL009 = \"information\"\n",
+            ),
+        ];
+        let report = checked_report(&files);
+        let lines: Vec<(&str, usize)> = report
+            .diagnostics
+            .iter()
+            .map(|d| (d.code, d.range.start.line))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![("PTL002", 8), ("PTL009", 8), ("PTL009", 12), ("PTL001", 13)]
+        );
+
+        colored::control::set_override(false);
+        let text = report.render(CheckFormat::Text).unwrap();
+        assert!(
+            text.ends_with(
+                "Found 4 problem(s) in 1 files checked: 0 error(s), 1 warning(s), 2 information, 1 hint(s)\n"
+            ),
+            "{}",
+            text
+        );
+    }
+
+    #[test]
+    fn test_check_format_from_str() {
+        assert_eq!("JSON".parse::<CheckFormat>(), Ok(CheckFormat::Json));
+        assert_eq!("text".parse::<CheckFormat>(), Ok(CheckFormat::Text));
+        assert_eq!("sarif".parse::<CheckFormat>(), Ok(CheckFormat::Sarif));
+        assert_eq!("github".parse::<CheckFormat>(), Ok(CheckFormat::Github));
+        assert_eq!("junit".parse::<CheckFormat>(), Ok(CheckFormat::Junit));
+        assert!("xml".parse::<CheckFormat>().is_err());
+    }
+
+    #[test]
+    fn test_fails() {
+        let mut report = checked_report(&[("tests/test_a.py", "def test_a():\n    pass\n")]);
+        assert!(!report.fails(FailOn::Hint, None));
+        assert!(!report.fails(FailOn::None, Some(0)));
+
+        report.summary.warnings = 2;
+        report.summary.hints = 1;
+        assert!(report.fails(FailOn::Hint, None));
+        assert!(report.fails(FailOn::Warning, None));
+        assert!(!report.fails(FailOn::Error, None));
+        assert!(!report.fails(FailOn::Error, Some(2)));
+        assert!(report.fails(FailOn::Error, Some(1)));
+        assert!(report.fails(FailOn::None, Some(1)));
+
+        report.summary.errors = 1;
+        assert!(report.fails(FailOn::Error, Some(10)));
+        assert!(!report.fails(FailOn::None, None));
+
+        assert_eq!("info".parse::<FailOn>(), Ok(FailOn::Information));
+        assert_eq!("None".parse::<FailOn>(), Ok(FailOn::None));
+        assert!("fatal".parse::<FailOn>().is_err());
+    }
+
+    #[test]
+    fn test_sarif_report() {
+        let report = checked_report(&[
+            (
+                "conftest.py",
+                "import pytest\n\n@pytest.fixture\ndef db():\n    pass\n",
+            ),
+            (
+                "tests/test_a.py",
+                "def test_a():\n    result = db\n\ndef test_b():\n    result = db\n\ndef test_c(db):\n    pass\n",
+            ),
+        ]);
+        let sarif: serde_json::Value =
+            serde_json::from_str(&report.render(CheckFormat::Sarif).unwrap()).unwrap();
+        assert_eq!(sarif["version"], "2.1.0");
+        let run = &sarif["runs"][0];
+        let rules = run["tool"]["driver"]["rules"].as_array().unwrap();
+        assert_eq!(rules.len(), DIAGNOSTIC_RULES.len());
+        assert_eq!(rules[0]["id"], "PTL001");
+        assert_eq!(rules[0]["name"], "undeclared-fixture");
+        assert_eq!(rules[0]["defaultConfiguration"]["level"], "warning");
+        assert_eq!(rules[0]["helpUri"], RULES_HELP_URI);
+        assert!(run["originalUriBaseIds"]["%SRCROOT%"]["uri"]
+            .as_str()
+            .unwrap()
+            .ends_with('/'));
+
+        let results = run["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["ruleId"], "PTL001");
+        assert_eq!(results[0]["ruleIndex"], 0);
+        assert_eq!(results[0]["level"], "warning");
+        let location = &results[0]["locations"][0]["physicalLocation"];
+        assert_eq!(location["artifactLocation"]["uri"], "tests/test_a.py");
+        assert_eq!(
+            location["region"],
+            serde_json::json!({ "startLine": 2, "startColumn": 14, "endLine": 2, "endColumn": 16 })
+        );
+        // The same problem on two identical lines gets distinct fingerprints
+        let fingerprint = |result: &serde_json::Value| {
+            result["partialFingerprints"]["pytestLanguageServer/v1"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+        assert_eq!(
+            fingerprint(&results[1]),
+            format!("{}:2", fingerprint(&results[0]))
+        );
+    }
+
+    #[test]
+    fn test_github_report() {
+        let report = checked_report(&[
+            (
+                "conftest.py",
+                "import pytest\n\n@pytest.fixture\ndef db():\n    pass\n\n@pytest.fixture(scope=\"session\")\ndef app(db):\n    pass\n",
+            ),
+            (
+                "tests/test_a.py",
+                "def test_a():\n    result = db\n\ndef test_b(app):\n    pass\n",
+            ),
+            (
+                "pyproject.toml",
+                "[tool.pytest-language-server.severity]\nPTL002 = \"error\"\n",
+            ),
+        ]);
+        assert_eq!(
+            report.to_github(None),
+            "::error file=conftest.py,line=8,endLine=8,col=5,endColumn=8,title=PTL002 scope-mismatch::session-scoped fixture 'app' depends on function-scoped fixture 'db'\n\
+             ::warning file=tests/test_a.py,line=2,endLine=2,col=14,endColumn=16,title=PTL001 undeclared-fixture::Fixture 'db' is used but not declared as a parameter\n"
+        );
+
+        // Paths are relative to the repository the checked directory is in
+        let workspace = report.root.parent().unwrap();
+        let name = report.root.file_name().unwrap().to_string_lossy();
+        assert!(report
+            .to_github(Some(workspace))
+            .contains(&format!("file={}/tests/test_a.py,", name)));
+    }
+
+    #[test]
+    fn test_escape_workflow_command() {
+        assert_eq!(escape_data("50% of a\r\nb: c,d"), "50%25 of a%0D%0Ab: c,d");
+        assert_eq!(escape_property("a: b,c%"), "a%3A b%2Cc%25");
+    }
+
+    #[test]
+    fn test_junit_report() {
+        let report = checked_report(&[
+            (
+                "conftest.py",
+                "import pytest\n\n@pytest.fixture\ndef db():\n    pass\n",
+            ),
+            (
+                "tests/test_a.py",
+                "def test_a():\n    result = db\n\ndef test_b(db):\n    pass\n",
+            ),
+        ]);
+        assert_eq!(
+            report.to_junit(),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites name="pytest-language-server" tests="2" failures="1" errors="0">
+  <testsuite name="pytest-language-server" tests="2" failures="1" errors="0" skipped="0">
+    <testcase classname="pytest-language-server" name="conftest.py" file="conftest.py"/>
+    <testcase classname="pytest-language-server" name="tests/test_a.py" file="tests/test_a.py">
+      <failure type="warning" message="1 problem(s)">tests/test_a.py:2:14: PTL001 [warning] Fixture &apos;db&apos; is used but not declared as a parameter
+</failure>
+    </testcase>
+  </testsuite>
+</testsuites>
+"#
+        );
+        assert_eq!(escape_xml("<a & \"b\">"), "&lt;a &amp; &quot;b&quot;&gt;");
+    }
+
+    #[test]
+    fn test_baseline() {
+        let tmp = tempdir().unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+        fs::write(
+            root.join("conftest.py"),
+            "import pytest\n\n@pytest.fixture\ndef db():\n    pass\n",
+        )
+        .unwrap();
+        fs::write(root.join("test_a.py"), "def test_a():\n    result = db\n").unwrap();
+        fs::write(root.join("test_b.py"), "def test_b(db):\n    pass\n").unwrap();
+        let check = || {
+            let fixture_db = FixtureDatabase::new();
+            fixture_db.scan_workspace(&root);
+            let files = fixture_db.checked_files(&root);
+            Report::collect(&fixture_db, &Config::load(&root), &root, &files)
+        };
+
+        let baseline_path = root.join("baseline.json");
+        Baseline::from_report(&check())
+            .save(&baseline_path)
+            .unwrap();
+        let baseline = Baseline::load(&baseline_path).unwrap();
+        assert_eq!(baseline.diagnostics.len(), 1);
+        assert_eq!(baseline.diagnostics[0].code, "PTL001");
+
+        // Known problems are not reported, even after their line moved
+        fs::write(
+            root.join("test_a.py"),
+            "import os\n\ndef test_a():\n    result = db\n",
+        )
+        .unwrap();
+        let mut report = check();
+        report.apply_baseline(&baseline);
+        assert!(report.diagnostics.is_empty());
+        assert_eq!((report.summary.warnings, report.summary.baselined), (0, 1));
+
+        // New ones are, including another occurrence of a known problem
+        fs::write(
+            root.join("test_a.py"),
+            "def test_a():\n    result = db\n\ndef test_b():\n    result = db\n",
+        )
+        .unwrap();
+        let mut report = check();
+        report.apply_baseline(&baseline);
+        let lines: Vec<usize> = report
+            .diagnostics
+            .iter()
+            .map(|d| d.range.start.line)
+            .collect();
+        assert_eq!(lines, vec![5]);
+        assert_eq!((report.summary.warnings, report.summary.baselined), (1, 1));
+
+        fs::write(&baseline_path, "{}").unwrap();
+        assert!(Baseline::load(&baseline_path)
+            .unwrap_err()
+            .starts_with("invalid baseline"));
+    }
+
+    #[test]
+    fn test_fix_files() {
+        let tmp = tempdir().unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+        let test_file = "import pytest\n\n\
+@pytest.fixture\ndef db():\n    pass\n\n\
+@pytest.mark.usefixtures(\"db\")\ndef test_a(db):\n    pass\n\n\
+@pytest.mark.usefixtures(\"db\", \"db\", \"db\")\ndef test_b():\n    pass\n";
+        fs::write(root.join("test_a.py"), test_file).unwrap();
+        let fixture_db = FixtureDatabase::new();
+        fixture_db.scan_workspace(&root);
+        let config = Config::load(&root);
+
+        let files = fixture_db.checked_files(&root);
+        let fixed = fix_files(&fixture_db, &config, &root, &files);
+        assert_eq!(fixed.len(), 1);
+        assert_eq!(fixed[0].count, 3);
+        assert_eq!(
+            fixed[0].fixed,
+            "import pytest\n\n\
+@pytest.fixture\ndef db():\n    pass\n\n\
+def test_a(db):\n    pass\n\n\
+@pytest.mark.usefixtures(\"db\")\ndef test_b():\n    pass\n"
+        );
+        assert_eq!(
+            fixed[0].diff(),
+            r#"--- a/test_a.py
++++ b/test_a.py
+@@ -4,10 +4,9 @@
+ def db():
+     pass
+ 
+-@pytest.mark.usefixtures("db")
+ def test_a(db):
+     pass
+ 
+-@pytest.mark.usefixtures("db", "db", "db")
++@pytest.mark.usefixtures("db")
+ def test_b():
+     pass
+"#
+        );
+
+        // The database has the fixed contents
+        assert!(Report::collect(&fixture_db, &config, &root, &files)
+            .diagnostics
+            .is_empty());
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        let old: String = (1..=20).map(|i| format!("{}\n", i)).collect();
+        let new = old.replacen("2\n", "two\n", 1).replace("\n19\n", "\n");
+        assert_eq!(
+            unified_diff("f.py", &old, &new),
+            "--- a/f.py\n+++ b/f.py\n\
+@@ -1,5 +1,5 @@\n 1\n-2\n+two\n 3\n 4\n 5\n\
+@@ -16,5 +16,4 @@\n 16\n 17\n 18\n-19\n 20\n"
+        );
+        assert_eq!(unified_diff("f.py", &old, &old), "");
+        assert_eq!(
+            unified_diff("f.py", "", "a\n"),
+            "--- a/f.py\n+++ b/f.py\n@@ -0,0 +1,1 @@\n+a\n"
+        );
+    }
+}
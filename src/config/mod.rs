@@ -4,18 +4,185 @@
 
 use glob::Pattern;
 use serde::Deserialize;
-use std::path::Path;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 use tracing::{debug, warn};
 
+/// Section of the editor settings holding this server's settings.
+pub const SETTINGS_SECTION: &str = "pytest-language-server";
+
+/// Default quiet period, in milliseconds, before re-analyzing edited documents.
+pub const DEFAULT_DEBOUNCE_MS: u64 = 200;
+
+/// Default memory budget, in MiB, of the file contents kept in memory.
+pub const DEFAULT_FILE_CACHE_MB: usize = 128;
+
+/// A diagnostic rule with a stable identifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiagnosticRule {
+    /// Stable rule code (e.g. `PTL001`), published as the LSP diagnostic code.
+    pub id: &'static str,
+    /// Human-readable rule name (e.g. `undeclared-fixture`).
+    pub name: &'static str,
+    /// Severity of its diagnostics unless configured otherwise.
+    pub default_severity: RuleSeverity,
+    /// One-line description of what the rule reports.
+    pub description: &'static str,
+}
+
+/// All diagnostic rules, in rule-code order. Codes are never reused.
+pub const DIAGNOSTIC_RULES: &[DiagnosticRule] = &[
+    DiagnosticRule {
+        id: "PTL001",
+        name: "undeclared-fixture",
+        default_severity: RuleSeverity::Warning,
+        description: "Fixture used in function body but not declared as parameter",
+    },
+    DiagnosticRule {
+        id: "PTL002",
+        name: "scope-mismatch",
+        default_severity: RuleSeverity::Warning,
+        description: "Broader-scoped fixture depends on narrower-scoped fixture",
+    },
+    DiagnosticRule {
+        id: "PTL003",
+        name: "circular-dependency",
+        default_severity: RuleSeverity::Error,
+        description: "Circular fixture dependency detected",
+    },
+    DiagnosticRule {
+        id: "PTL004",
+        name: "invalid-indirect",
+        default_severity: RuleSeverity::Error,
+        description: "`parametrize(..., indirect=...)` names a non-argname or a missing fixture",
+    },
+    DiagnosticRule {
+        id: "PTL005",
+        name: "unknown-marker",
+        default_severity: RuleSeverity::Error,
+        description: "`pytest.mark.<name>` is not registered while `--strict-markers` is enabled",
+    },
+    DiagnosticRule {
+        id: "PTL006",
+        name: "unittest-fixture",
+        default_severity: RuleSeverity::Warning,
+        description: "Test method of a `unittest.TestCase` subclass declares a fixture parameter, which pytest does not inject",
+    },
+    DiagnosticRule {
+        id: "PTL007",
+        name: "redundant-usefixtures",
+        default_severity: RuleSeverity::Hint,
+        description: "`usefixtures` entry naming a fixture the function already takes as a parameter or lists",
+    },
+    DiagnosticRule {
+        id: "PTL008",
+        name: "unused-fixture-parameter",
+        default_severity: RuleSeverity::Hint,
+        description: "Fixture parameter never referenced in the function body",
+    },
+    DiagnosticRule {
+        id: "PTL009",
+        name: "unused-fixture",
+        default_severity: RuleSeverity::Hint,
+        description: "Fixture defined in the workspace but never requested anywhere",
+    },
+    DiagnosticRule {
+        id: "PTL010",
+        name: "shadowed-plugin-fixture",
+        default_severity: RuleSeverity::Information,
+        description: "Project fixture replaces a fixture of pytest or a plugin without requesting it",
+    },
+    DiagnosticRule {
+        id: "PTL011",
+        name: "deprecated-yield-fixture",
+        default_severity: RuleSeverity::Warning,
+        description: "`@pytest.yield_fixture` is deprecated in favor of `@pytest.fixture`",
+    },
+];
+
+/// Documentation of the diagnostic rules.
+pub const RULES_HELP_URI: &str =
+    "https://github.com/bellini666/pytest-language-server#diagnostic-codes";
+
+/// Look up a rule by its code (`PTL002`, case-insensitive) or its name (`scope-mismatch`).
+pub fn find_rule(code: &str) -> Option<&'static DiagnosticRule> {
+    DIAGNOSTIC_RULES
+        .iter()
+        .find(|rule| rule.name == code || rule.id.eq_ignore_ascii_case(code))
+}
+
+/// Configured severity for a diagnostic rule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleSeverity {
+    Error,
+    Warning,
+    Information,
+    Hint,
+    /// The rule is disabled.
+    Off,
+}
+
+impl RuleSeverity {
+    /// Parse a severity name as written in configuration.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_lowercase().as_str() {
+            "error" => Some(Self::Error),
+            "warning" | "warn" => Some(Self::Warning),
+            "information" | "info" => Some(Self::Information),
+            "hint" => Some(Self::Hint),
+            "off" | "none" => Some(Self::Off),
+            _ => None,
+        }
+    }
+
+    /// The name of the severity, as written in configuration.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Information => "information",
+            Self::Hint => "hint",
+            Self::Off => "off",
+        }
+    }
+}
+
 /// Configuration for pytest-language-server.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone)]
 pub struct Config {
     /// Glob patterns for directories/files to exclude from scanning.
     pub exclude: Vec<Pattern>,
 
+    /// Skip paths ignored by `.gitignore` (and `.ignore`) files when scanning.
+    pub respect_gitignore: bool,
+
+    /// Virtual environment to use (absolute, or relative to the workspace root)
+    /// instead of discovering one.
+    pub venv: Option<PathBuf>,
+
+    /// Python interpreter (absolute, or relative to the workspace root) whose
+    /// environment is used instead of `venv` or discovering one.
+    pub python_path: Option<PathBuf>,
+
+    /// Virtual environments of subdirectories of the workspace (both relative
+    /// to the workspace root), used for their files instead of the
+    /// workspace's.
+    pub venvs: BTreeMap<PathBuf, PathBuf>,
+
+    /// Decorators wrapping `pytest.fixture` (names like `db_fixture` or dotted
+    /// paths like `myproject.testing.db_fixture`) whose functions are fixtures.
+    pub fixture_decorators: Vec<String>,
+
+    /// Glob patterns matched against file names (e.g. `spec_*.py`) that
+    /// replace the default `test_*.py` / `*_test.py` test file detection.
+    pub test_file_patterns: Vec<Pattern>,
+
     /// Diagnostic codes to disable (e.g., "undeclared-fixture", "scope-mismatch").
     pub disabled_diagnostics: Vec<String>,
 
+    /// Per-rule severity overrides, keyed by rule name (e.g. "scope-mismatch").
+    pub severity: HashMap<String, RuleSeverity>,
+
     /// Additional directories to scan for fixtures (beyond conftest.py hierarchy).
     #[allow(dead_code)] // Planned feature
     pub fixture_paths: Vec<String>,
@@ -23,6 +190,167 @@ pub struct Config {
     /// Third-party plugins to skip when scanning virtual environment.
     #[allow(dead_code)] // Used in tests, venv scanning integration planned
     pub skip_plugins: Vec<String>,
+
+    /// Which inlay hints to show on fixture parameters.
+    pub inlay_hints: InlayHintSettings,
+
+    /// What fixture completions offer.
+    pub completion: CompletionSettings,
+
+    /// Quiet period, in milliseconds, that bursts of edits to a document are
+    /// coalesced over before it is re-analyzed.
+    pub debounce_ms: u64,
+
+    /// Persist the fixture index between runs, so a restart re-analyzes only
+    /// the files that changed.
+    pub index_cache: bool,
+
+    /// Directory the index is persisted in (absolute, or relative to the
+    /// workspace root) instead of the user's cache directory.
+    pub cache_dir: Option<PathBuf>,
+
+    /// Memory budget, in MiB, of the file contents kept in memory; the least
+    /// recently used are dropped and re-read from disk when needed.
+    pub file_cache_mb: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            exclude: Vec::new(),
+            respect_gitignore: true,
+            venv: None,
+            python_path: None,
+            venvs: BTreeMap::new(),
+            fixture_decorators: Vec::new(),
+            test_file_patterns: Vec::new(),
+            disabled_diagnostics: Vec::new(),
+            severity: HashMap::new(),
+            fixture_paths: Vec::new(),
+            skip_plugins: Vec::new(),
+            inlay_hints: InlayHintSettings::default(),
+            completion: CompletionSettings::default(),
+            debounce_ms: DEFAULT_DEBOUNCE_MS,
+            index_cache: true,
+            cache_dir: None,
+            file_cache_mb: DEFAULT_FILE_CACHE_MB,
+        }
+    }
+}
+
+/// Kinds of inlay hints shown on fixture parameters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InlayHintSettings {
+    /// The fixture's return type (`: Database`). On by default.
+    pub types: bool,
+    /// Where the resolved fixture is defined (`conftest ../..`, `same file`).
+    pub origin: bool,
+}
+
+impl Default for InlayHintSettings {
+    fn default() -> Self {
+        Self {
+            types: true,
+            origin: false,
+        }
+    }
+}
+
+/// Raw `inlay_hints` table; unset keys keep their defaults.
+#[derive(Debug, Deserialize, Default)]
+struct RawInlayHints {
+    types: Option<bool>,
+    origin: Option<bool>,
+}
+
+impl InlayHintSettings {
+    fn apply(&mut self, raw: RawInlayHints) {
+        if let Some(types) = raw.types {
+            self.types = types;
+        }
+        if let Some(origin) = raw.origin {
+            self.origin = origin;
+        }
+    }
+}
+
+/// What fixture completions offer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletionSettings {
+    /// Completing a fixture in a function body also adds it to the
+    /// function's parameters. On by default.
+    pub auto_add_parameter: bool,
+    /// Complete fixture names in `usefixtures("...")` and
+    /// `parametrize(..., indirect=[...])` strings. On by default.
+    pub decorator_strings: bool,
+    /// Most items returned at once, the best matches for the word being
+    /// typed; `None` (or `0` in configuration) for no limit.
+    pub max_items: Option<usize>,
+    /// Offer fixtures of installed third-party packages. On by default.
+    pub third_party: bool,
+    /// Third-party packages (e.g. `pytest-mock`) whose fixtures are offered;
+    /// when not empty, those of every other package are not.
+    pub third_party_allow: Vec<String>,
+    /// Third-party packages whose fixtures are not offered.
+    pub third_party_deny: Vec<String>,
+}
+
+impl Default for CompletionSettings {
+    fn default() -> Self {
+        Self {
+            auto_add_parameter: true,
+            decorator_strings: true,
+            max_items: None,
+            third_party: true,
+            third_party_allow: Vec::new(),
+            third_party_deny: Vec::new(),
+        }
+    }
+}
+
+/// Raw `completion` table; unset keys keep their defaults.
+#[derive(Debug, Deserialize, Default)]
+struct RawCompletion {
+    auto_add_parameter: Option<bool>,
+    decorator_strings: Option<bool>,
+    max_items: Option<usize>,
+    third_party: Option<bool>,
+    third_party_allow: Option<Vec<String>>,
+    third_party_deny: Option<Vec<String>>,
+}
+
+impl CompletionSettings {
+    fn apply(&mut self, raw: RawCompletion) {
+        if let Some(auto_add_parameter) = raw.auto_add_parameter {
+            self.auto_add_parameter = auto_add_parameter;
+        }
+        if let Some(decorator_strings) = raw.decorator_strings {
+            self.decorator_strings = decorator_strings;
+        }
+        if let Some(max_items) = raw.max_items {
+            self.max_items = (max_items > 0).then_some(max_items);
+        }
+        if let Some(third_party) = raw.third_party {
+            self.third_party = third_party;
+        }
+        if let Some(allow) = raw.third_party_allow {
+            self.third_party_allow = allow;
+        }
+        if let Some(deny) = raw.third_party_deny {
+            self.third_party_deny = deny;
+        }
+    }
+
+    /// Whether fixtures of the third-party `package` are offered. Package
+    /// names compare case-insensitively, with `-` and `_` equivalent.
+    pub fn offers_third_party_package(&self, package: &str) -> bool {
+        let normalize = |name: &str| name.to_lowercase().replace('_', "-");
+        let package = normalize(package);
+        let listed = |names: &[String]| names.iter().any(|name| normalize(name) == package);
+        self.third_party
+            && (self.third_party_allow.is_empty() || listed(&self.third_party_allow))
+            && !listed(&self.third_party_deny)
+    }
 }
 
 /// Raw configuration as parsed from TOML (before validation).
@@ -31,14 +359,48 @@ struct RawConfig {
     #[serde(default)]
     exclude: Vec<String>,
 
+    respect_gitignore: Option<bool>,
+
+    #[serde(alias = "venv_path", alias = "venvPath")]
+    venv: Option<PathBuf>,
+
+    #[serde(alias = "pythonPath")]
+    python_path: Option<PathBuf>,
+
+    #[serde(default)]
+    venvs: BTreeMap<PathBuf, PathBuf>,
+
+    #[serde(default)]
+    fixture_decorators: Vec<String>,
+
+    #[serde(default)]
+    test_file_patterns: Vec<String>,
+
     #[serde(default)]
     disabled_diagnostics: Vec<String>,
 
+    #[serde(default)]
+    severity: HashMap<String, String>,
+
     #[serde(default)]
     fixture_paths: Vec<String>,
 
     #[serde(default)]
     skip_plugins: Vec<String>,
+
+    #[serde(default)]
+    inlay_hints: RawInlayHints,
+
+    #[serde(default)]
+    completion: RawCompletion,
+
+    debounce_ms: Option<u64>,
+
+    index_cache: Option<bool>,
+
+    cache_dir: Option<PathBuf>,
+
+    file_cache_mb: Option<usize>,
 }
 
 /// Wrapper for the pyproject.toml structure.
@@ -96,40 +458,17 @@ impl Config {
 
     /// Convert raw config to validated config.
     fn from_raw(raw: RawConfig, path: &Path) -> Self {
-        // Parse exclude patterns, warning on invalid ones
-        let exclude: Vec<Pattern> = raw
-            .exclude
-            .into_iter()
-            .filter_map(|pattern| match Pattern::new(&pattern) {
-                Ok(p) => Some(p),
-                Err(e) => {
-                    warn!("Invalid exclude pattern '{}' in {:?}: {}", pattern, path, e);
-                    None
-                }
-            })
-            .collect();
+        let exclude = parse_patterns(raw.exclude, "exclude", &format!("{:?}", path));
+        let test_file_patterns = parse_patterns(
+            raw.test_file_patterns,
+            "test_file_patterns",
+            &format!("{:?}", path),
+        );
 
-        // Validate diagnostic codes
-        let valid_diagnostics = [
-            "undeclared-fixture",
-            "scope-mismatch",
-            "circular-dependency",
-        ];
-        let disabled_diagnostics: Vec<String> = raw
-            .disabled_diagnostics
-            .into_iter()
-            .filter(|code| {
-                if valid_diagnostics.contains(&code.as_str()) {
-                    true
-                } else {
-                    warn!(
-                        "Unknown diagnostic code '{}' in {:?}, valid codes are: {:?}",
-                        code, path, valid_diagnostics
-                    );
-                    false
-                }
-            })
-            .collect();
+        let disabled_diagnostics =
+            parse_disabled_diagnostics(raw.disabled_diagnostics, &format!("{:?}", path));
+
+        let severity = parse_severity_table(raw.severity, &format!("{:?}", path));
 
         // These options are accepted but not implemented yet; warn instead of
         // silently ignoring the user's configuration.
@@ -153,17 +492,183 @@ impl Config {
             disabled_diagnostics.len()
         );
 
+        let mut inlay_hints = InlayHintSettings::default();
+        inlay_hints.apply(raw.inlay_hints);
+        let mut completion = CompletionSettings::default();
+        completion.apply(raw.completion);
+
         Self {
             exclude,
+            respect_gitignore: raw.respect_gitignore.unwrap_or(true),
+            venv: raw.venv,
+            python_path: raw.python_path,
+            venvs: raw.venvs,
+            fixture_decorators: raw.fixture_decorators,
+            test_file_patterns,
             disabled_diagnostics,
+            severity,
             fixture_paths: raw.fixture_paths,
             skip_plugins: raw.skip_plugins,
+            inlay_hints,
+            completion,
+            debounce_ms: raw.debounce_ms.unwrap_or(DEFAULT_DEBOUNCE_MS),
+            index_cache: raw.index_cache.unwrap_or(true),
+            cache_dir: raw.cache_dir,
+            file_cache_mb: raw.file_cache_mb.unwrap_or(DEFAULT_FILE_CACHE_MB),
+        }
+    }
+
+    /// Apply editor-provided settings on top of the pyproject configuration.
+    ///
+    /// Accepts every option of the `[tool.pytest-language-server]` table, as
+    /// JSON, either directly or under a `"pytest-language-server"` key:
+    /// `{"severity": {"PTL002": "hint"}, "inlay_hints": {"origin": true},
+    /// "completion": {"max_items": 50}, "exclude": ["generated/**"],
+    /// "disabled_diagnostics": ["PTL001"],
+    /// "respect_gitignore": false, "venv": ".venv-py312", "debounce_ms": 300}`.
+    /// Entries override the values set in `pyproject.toml`; `exclude` patterns,
+    /// `fixture_decorators` and `disabled_diagnostics` are added to its own.
+    /// Invalid entries are skipped with a warning.
+    pub fn apply_settings(&mut self, settings: &serde_json::Value) {
+        const SOURCE: &str = "workspace settings";
+        let settings = settings.get(SETTINGS_SECTION).unwrap_or(settings);
+        let Some(settings) = settings.as_object() else {
+            return;
+        };
+
+        for (key, value) in settings {
+            match key.as_str() {
+                "exclude" => match serde_json::from_value::<Vec<String>>(value.clone()) {
+                    Ok(raw) => self.exclude.extend(parse_patterns(raw, "exclude", SOURCE)),
+                    Err(e) => warn!("Invalid 'exclude' in {}: {}", SOURCE, e),
+                },
+                "respect_gitignore" => match value.as_bool() {
+                    Some(respect) => self.respect_gitignore = respect,
+                    None => warn!("'respect_gitignore' in {} must be a boolean", SOURCE),
+                },
+                "venv" | "venv_path" | "venvPath" => match value.as_str() {
+                    Some(venv) => self.venv = Some(PathBuf::from(venv)),
+                    None => warn!("'{}' in {} must be a string", key, SOURCE),
+                },
+                "python_path" | "pythonPath" => match value.as_str() {
+                    Some(python) => self.python_path = Some(PathBuf::from(python)),
+                    None => warn!("'{}' in {} must be a string", key, SOURCE),
+                },
+                "venvs" => {
+                    match serde_json::from_value::<BTreeMap<PathBuf, PathBuf>>(value.clone()) {
+                        Ok(venvs) => self.venvs.extend(venvs),
+                        Err(e) => warn!("Invalid 'venvs' in {}: {}", SOURCE, e),
+                    }
+                }
+                "fixture_decorators" => {
+                    match serde_json::from_value::<Vec<String>>(value.clone()) {
+                        Ok(decorators) => self.fixture_decorators.extend(decorators),
+                        Err(e) => warn!("Invalid 'fixture_decorators' in {}: {}", SOURCE, e),
+                    }
+                }
+                "test_file_patterns" => {
+                    match serde_json::from_value::<Vec<String>>(value.clone()) {
+                        Ok(raw) => {
+                            self.test_file_patterns =
+                                parse_patterns(raw, "test_file_patterns", SOURCE)
+                        }
+                        Err(e) => warn!("Invalid 'test_file_patterns' in {}: {}", SOURCE, e),
+                    }
+                }
+                "disabled_diagnostics" => {
+                    match serde_json::from_value::<Vec<String>>(value.clone()) {
+                        Ok(raw) => self
+                            .disabled_diagnostics
+                            .extend(parse_disabled_diagnostics(raw, SOURCE)),
+                        Err(e) => warn!("Invalid 'disabled_diagnostics' in {}: {}", SOURCE, e),
+                    }
+                }
+                "severity" => {
+                    let Some(table) = value.as_object() else {
+                        warn!("'severity' in {} must be a table", SOURCE);
+                        continue;
+                    };
+                    let raw: HashMap<String, String> = table
+                        .iter()
+                        .filter_map(|(code, value)| match value.as_str() {
+                            Some(v) => Some((code.clone(), v.to_string())),
+                            None => {
+                                warn!("Severity for '{}' in {} must be a string", code, SOURCE);
+                                None
+                            }
+                        })
+                        .collect();
+                    self.severity.extend(parse_severity_table(raw, SOURCE));
+                }
+                "inlay_hints" => match serde_json::from_value::<RawInlayHints>(value.clone()) {
+                    Ok(raw) => self.inlay_hints.apply(raw),
+                    Err(e) => warn!("Invalid 'inlay_hints' in {}: {}", SOURCE, e),
+                },
+                "completion" => match serde_json::from_value::<RawCompletion>(value.clone()) {
+                    Ok(raw) => self.completion.apply(raw),
+                    Err(e) => warn!("Invalid 'completion' in {}: {}", SOURCE, e),
+                },
+                "debounce_ms" => match value.as_u64() {
+                    Some(debounce_ms) => self.debounce_ms = debounce_ms,
+                    None => warn!("'debounce_ms' in {} must be a non-negative integer", SOURCE),
+                },
+                "index_cache" => match value.as_bool() {
+                    Some(index_cache) => self.index_cache = index_cache,
+                    None => warn!("'index_cache' in {} must be a boolean", SOURCE),
+                },
+                "cache_dir" => match value.as_str() {
+                    Some(cache_dir) => self.cache_dir = Some(PathBuf::from(cache_dir)),
+                    None => warn!("'cache_dir' in {} must be a string", SOURCE),
+                },
+                "file_cache_mb" => match value.as_u64() {
+                    Some(file_cache_mb) => self.file_cache_mb = file_cache_mb as usize,
+                    None => warn!(
+                        "'file_cache_mb' in {} must be a non-negative integer",
+                        SOURCE
+                    ),
+                },
+                "fixture_paths" | "skip_plugins" => {
+                    warn!(
+                        "'{}' in {} is not implemented yet and will be ignored",
+                        key, SOURCE
+                    );
+                }
+                _ => warn!("Unknown setting '{}' in {}", key, SOURCE),
+            }
         }
     }
 
-    /// Check if a diagnostic code is disabled.
+    /// Check if a diagnostic code is disabled, either through
+    /// `disabled_diagnostics` or a severity of `off`.
     pub fn is_diagnostic_disabled(&self, code: &str) -> bool {
-        self.disabled_diagnostics.iter().any(|d| d == code)
+        let Some(rule) = find_rule(code) else {
+            return self.disabled_diagnostics.iter().any(|d| d == code);
+        };
+        self.rule_severity(rule.name) == Some(RuleSeverity::Off)
+            || self
+                .disabled_diagnostics
+                .iter()
+                .any(|d| find_rule(d) == Some(rule))
+    }
+
+    /// Configured severity override for a rule (by name or PTL code), if any.
+    pub fn rule_severity(&self, code: &str) -> Option<RuleSeverity> {
+        let rule = find_rule(code)?;
+        self.severity.get(rule.name).copied()
+    }
+
+    /// Severity the diagnostics of a rule (by name or PTL code) are reported
+    /// with: the configured one, otherwise the rule's default. `None` when the
+    /// rule is disabled.
+    pub fn effective_severity(&self, code: &str) -> Option<RuleSeverity> {
+        let rule = find_rule(code)?;
+        if self.is_diagnostic_disabled(rule.name) {
+            return None;
+        }
+        Some(
+            self.rule_severity(rule.name)
+                .unwrap_or(rule.default_severity),
+        )
     }
 
     /// Check if a path should be excluded from scanning.
@@ -182,6 +687,77 @@ impl Config {
     }
 }
 
+/// Keep the known diagnostic codes (rule names or PTL codes), warning about
+/// unknown ones.
+fn parse_disabled_diagnostics(raw: Vec<String>, source: &str) -> Vec<String> {
+    raw.into_iter()
+        .filter(|code| {
+            if find_rule(code).is_some() {
+                true
+            } else {
+                warn!(
+                    "Unknown diagnostic code '{}' in {}, valid codes are: {:?}",
+                    code,
+                    source,
+                    valid_rule_codes()
+                );
+                false
+            }
+        })
+        .collect()
+}
+
+/// Compile the glob patterns of the `key` setting, warning about (and
+/// dropping) invalid ones.
+fn parse_patterns(raw: Vec<String>, key: &str, source: &str) -> Vec<Pattern> {
+    raw.into_iter()
+        .filter_map(|pattern| match Pattern::new(&pattern) {
+            Ok(p) => Some(p),
+            Err(e) => {
+                warn!("Invalid {} pattern '{}' in {}: {}", key, pattern, source, e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// All accepted rule codes, for warning messages.
+fn valid_rule_codes() -> Vec<&'static str> {
+    DIAGNOSTIC_RULES
+        .iter()
+        .flat_map(|rule| [rule.id, rule.name])
+        .collect()
+}
+
+/// Validate a `code -> severity` table, keying the result by rule name.
+/// Unknown codes and severities are dropped with a warning naming `source`.
+fn parse_severity_table(
+    raw: HashMap<String, String>,
+    source: &str,
+) -> HashMap<String, RuleSeverity> {
+    raw.into_iter()
+        .filter_map(|(code, value)| {
+            let Some(rule) = find_rule(&code) else {
+                warn!(
+                    "Unknown diagnostic code '{}' in {}, valid codes are: {:?}",
+                    code,
+                    source,
+                    valid_rule_codes()
+                );
+                return None;
+            };
+            let Some(severity) = RuleSeverity::parse(&value) else {
+                warn!(
+                    "Invalid severity '{}' for '{}' in {}, expected one of: error, warning, information, hint, off",
+                    value, code, source
+                );
+                return None;
+            };
+            Some((rule.name.to_string(), severity))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -268,6 +844,412 @@ disabled_diagnostics = ["undeclared-fixture"]
         assert!(!config.is_diagnostic_disabled("scope-mismatch"));
     }
 
+    #[test]
+    fn test_is_diagnostic_disabled_by_rule_code() {
+        let content = r#"
+[tool.pytest-language-server]
+disabled_diagnostics = ["PTL002"]
+"#;
+        let config = Config::parse(content, Path::new("pyproject.toml"));
+        assert!(config.is_diagnostic_disabled("scope-mismatch"));
+        assert!(config.is_diagnostic_disabled("PTL002"));
+        assert!(!config.is_diagnostic_disabled("undeclared-fixture"));
+    }
+
+    #[test]
+    fn test_parse_severity_table() {
+        let content = r#"
+[tool.pytest-language-server.severity]
+PTL001 = "hint"
+scope-mismatch = "error"
+circular-dependency = "off"
+invalid-indirect = "loud"
+PTL999 = "error"
+"#;
+        let config = Config::parse(content, Path::new("pyproject.toml"));
+        assert_eq!(config.severity.len(), 3);
+        assert_eq!(
+            config.rule_severity("undeclared-fixture"),
+            Some(RuleSeverity::Hint)
+        );
+        assert_eq!(config.rule_severity("PTL002"), Some(RuleSeverity::Error));
+        assert_eq!(config.rule_severity("invalid-indirect"), None);
+        assert!(config.is_diagnostic_disabled("circular-dependency"));
+        assert!(!config.is_diagnostic_disabled("scope-mismatch"));
+    }
+
+    #[test]
+    fn test_apply_settings_overrides_pyproject_severity() {
+        let content = r#"
+[tool.pytest-language-server.severity]
+scope-mismatch = "error"
+"#;
+        let mut config = Config::parse(content, Path::new("pyproject.toml"));
+        config.apply_settings(&serde_json::json!({
+            "severity": { "PTL002": "hint", "PTL004": "off", "PTL001": 3 }
+        }));
+        assert_eq!(
+            config.rule_severity("scope-mismatch"),
+            Some(RuleSeverity::Hint)
+        );
+        assert!(config.is_diagnostic_disabled("invalid-indirect"));
+        assert_eq!(config.rule_severity("undeclared-fixture"), None);
+    }
+
+    #[test]
+    fn test_apply_settings_accepts_all_options() {
+        let content = r#"
+[tool.pytest-language-server]
+disabled_diagnostics = ["scope-mismatch"]
+"#;
+        let mut config = Config::parse(content, Path::new("pyproject.toml"));
+
+        // Settings nested under the server's section, as some clients send them
+        config.apply_settings(&serde_json::json!({
+            "pytest-language-server": {
+                "disabled_diagnostics": ["PTL001", "PTL999"],
+                "exclude": ["generated/**"],
+                "respect_gitignore": false,
+                "venv": ".venv-py312",
+                "inlay_hints": { "origin": true },
+                "unknown_option": 1
+            }
+        }));
+        assert!(config.is_diagnostic_disabled("scope-mismatch"));
+        assert!(config.is_diagnostic_disabled("undeclared-fixture"));
+        assert_eq!(config.disabled_diagnostics.len(), 2);
+        assert_eq!(config.exclude.len(), 1);
+        assert!(!config.respect_gitignore);
+        assert_eq!(config.venv, Some(PathBuf::from(".venv-py312")));
+        assert!(config.inlay_hints.origin);
+
+        // Non-object settings are ignored
+        config.apply_settings(&serde_json::Value::Null);
+        assert!(!config.respect_gitignore);
+    }
+
+    #[test]
+    fn test_inlay_hint_settings() {
+        let config = Config::parse("", Path::new("pyproject.toml"));
+        assert_eq!(config.inlay_hints, InlayHintSettings::default());
+        assert!(config.inlay_hints.types && !config.inlay_hints.origin);
+
+        let content = r#"
+[tool.pytest-language-server.inlay_hints]
+origin = true
+"#;
+        let mut config = Config::parse(content, Path::new("pyproject.toml"));
+        assert!(config.inlay_hints.types && config.inlay_hints.origin);
+
+        config.apply_settings(&serde_json::json!({ "inlay_hints": { "types": false } }));
+        assert!(!config.inlay_hints.types && config.inlay_hints.origin);
+
+        // Invalid values are ignored
+        config.apply_settings(&serde_json::json!({ "inlay_hints": { "origin": "yes" } }));
+        assert!(config.inlay_hints.origin);
+    }
+
+    #[test]
+    fn test_fixture_decorators() {
+        let content = r#"
+[tool.pytest-language-server]
+fixture_decorators = ["acceptance_fixture"]
+"#;
+        let mut config = Config::parse(content, Path::new("pyproject.toml"));
+        assert_eq!(config.fixture_decorators, ["acceptance_fixture"]);
+
+        config.apply_settings(&serde_json::json!({
+            "fixture_decorators": ["myproject.testing.db_fixture"]
+        }));
+        assert_eq!(
+            config.fixture_decorators,
+            ["acceptance_fixture", "myproject.testing.db_fixture"]
+        );
+
+        // Invalid values are ignored
+        config.apply_settings(&serde_json::json!({ "fixture_decorators": "db_fixture" }));
+        assert_eq!(config.fixture_decorators.len(), 2);
+    }
+
+    #[test]
+    fn test_test_file_patterns() {
+        let content = r#"
+[tool.pytest-language-server]
+test_file_patterns = ["spec_*.py", "[invalid"]
+"#;
+        let mut config = Config::parse(content, Path::new("pyproject.toml"));
+        assert_eq!(config.test_file_patterns.len(), 1);
+        assert!(config.test_file_patterns[0].matches("spec_login.py"));
+
+        // Settings replace the patterns of pyproject.toml
+        config.apply_settings(&serde_json::json!({
+            "test_file_patterns": ["it_*.py", "*_spec.py"]
+        }));
+        let patterns: Vec<&str> = config
+            .test_file_patterns
+            .iter()
+            .map(|p| p.as_str())
+            .collect();
+        assert_eq!(patterns, ["it_*.py", "*_spec.py"]);
+    }
+
+    #[test]
+    fn test_completion_settings() {
+        let config = Config::parse("", Path::new("pyproject.toml"));
+        assert_eq!(config.completion, CompletionSettings::default());
+        assert!(config.completion.auto_add_parameter && config.completion.third_party);
+        assert_eq!(config.completion.max_items, None);
+
+        let content = r#"
+[tool.pytest-language-server.completion]
+auto_add_parameter = false
+max_items = 50
+"#;
+        let mut config = Config::parse(content, Path::new("pyproject.toml"));
+        assert!(!config.completion.auto_add_parameter);
+        assert!(config.completion.decorator_strings);
+        assert_eq!(config.completion.max_items, Some(50));
+
+        config.apply_settings(&serde_json::json!({
+            "completion": { "decorator_strings": false, "third_party": false, "max_items": 0 }
+        }));
+        assert!(!config.completion.auto_add_parameter);
+        assert!(!config.completion.decorator_strings && !config.completion.third_party);
+        assert_eq!(config.completion.max_items, None, "0 means no limit");
+
+        // Invalid values are ignored
+        config.apply_settings(&serde_json::json!({ "completion": { "max_items": -1 } }));
+        assert_eq!(config.completion.max_items, None);
+    }
+
+    #[test]
+    fn test_completion_third_party_packages() {
+        let content = r#"
+[tool.pytest-language-server.completion]
+third_party_deny = ["pytest_django"]
+"#;
+        let mut config = Config::parse(content, Path::new("pyproject.toml"));
+        assert!(config.completion.offers_third_party_package("pytest-mock"));
+        assert!(!config
+            .completion
+            .offers_third_party_package("pytest-django"));
+
+        config.apply_settings(&serde_json::json!({
+            "completion": { "third_party_allow": ["Pytest-Mock", "pytest-django"] }
+        }));
+        assert!(config.completion.offers_third_party_package("pytest-mock"));
+        assert!(!config
+            .completion
+            .offers_third_party_package("pytest-asyncio"));
+        // Denying wins over allowing
+        assert!(!config
+            .completion
+            .offers_third_party_package("pytest-django"));
+
+        config.apply_settings(&serde_json::json!({ "completion": { "third_party": false } }));
+        assert!(!config.completion.offers_third_party_package("pytest-mock"));
+    }
+
+    #[test]
+    fn test_scan_settings() {
+        let config = Config::parse("", Path::new("pyproject.toml"));
+        assert!(config.respect_gitignore);
+
+        let content = r#"
+[tool.pytest-language-server]
+exclude = ["build"]
+respect_gitignore = false
+venv = ".venv-py312"
+"#;
+        let mut config = Config::parse(content, Path::new("pyproject.toml"));
+        assert!(!config.respect_gitignore);
+        assert_eq!(config.venv, Some(PathBuf::from(".venv-py312")));
+
+        config.apply_settings(&serde_json::json!({
+            "exclude": ["generated/**", "[invalid"],
+            "respect_gitignore": true,
+            "venv": "/opt/venvs/project"
+        }));
+        assert!(config.respect_gitignore);
+        assert_eq!(config.venv, Some(PathBuf::from("/opt/venvs/project")));
+        assert_eq!(config.exclude.len(), 2);
+        assert!(config.should_exclude(Path::new("generated/test_api.py")));
+    }
+
+    #[test]
+    fn test_interpreter_settings() {
+        let config = Config::parse("", Path::new("pyproject.toml"));
+        assert_eq!(config.python_path, None);
+
+        let content = r#"
+[tool.pytest-language-server]
+venvPath = "envs/test"
+python_path = "envs/test/bin/python3"
+"#;
+        let mut config = Config::parse(content, Path::new("pyproject.toml"));
+        assert_eq!(config.venv, Some(PathBuf::from("envs/test")));
+        assert_eq!(
+            config.python_path,
+            Some(PathBuf::from("envs/test/bin/python3"))
+        );
+
+        config.apply_settings(&serde_json::json!({
+            "venv_path": "/opt/venvs/project",
+            "pythonPath": "/usr/bin/python3"
+        }));
+        assert_eq!(config.venv, Some(PathBuf::from("/opt/venvs/project")));
+        assert_eq!(config.python_path, Some(PathBuf::from("/usr/bin/python3")));
+
+        // Invalid values are ignored
+        config.apply_settings(&serde_json::json!({ "pythonPath": 3 }));
+        assert_eq!(config.python_path, Some(PathBuf::from("/usr/bin/python3")));
+    }
+
+    #[test]
+    fn test_subproject_venvs_setting() {
+        let config = Config::parse("", Path::new("pyproject.toml"));
+        assert!(config.venvs.is_empty());
+
+        let content = r#"
+[tool.pytest-language-server.venvs]
+"packages/api" = "packages/api/.venv"
+"services/worker" = "envs/worker"
+"#;
+        let mut config = Config::parse(content, Path::new("pyproject.toml"));
+        assert_eq!(
+            config.venvs.get(Path::new("packages/api")),
+            Some(&PathBuf::from("packages/api/.venv"))
+        );
+        assert_eq!(config.venvs.len(), 2);
+
+        config.apply_settings(&serde_json::json!({
+            "venvs": { "services/worker": "/opt/venvs/worker", "tools": "tools/.venv" }
+        }));
+        assert_eq!(
+            config.venvs.get(Path::new("services/worker")),
+            Some(&PathBuf::from("/opt/venvs/worker"))
+        );
+        assert_eq!(config.venvs.len(), 3);
+
+        // Invalid values are ignored
+        config.apply_settings(&serde_json::json!({ "venvs": ["tools/.venv"] }));
+        assert_eq!(config.venvs.len(), 3);
+    }
+
+    #[test]
+    fn test_debounce_setting() {
+        let config = Config::parse("", Path::new("pyproject.toml"));
+        assert_eq!(config.debounce_ms, DEFAULT_DEBOUNCE_MS);
+
+        let content = r#"
+[tool.pytest-language-server]
+debounce_ms = 500
+"#;
+        let mut config = Config::parse(content, Path::new("pyproject.toml"));
+        assert_eq!(config.debounce_ms, 500);
+
+        config.apply_settings(&serde_json::json!({ "debounce_ms": 0 }));
+        assert_eq!(config.debounce_ms, 0);
+
+        // Invalid values are ignored
+        config.apply_settings(&serde_json::json!({ "debounce_ms": -1 }));
+        assert_eq!(config.debounce_ms, 0);
+    }
+
+    #[test]
+    fn test_file_cache_setting() {
+        let config = Config::parse("", Path::new("pyproject.toml"));
+        assert_eq!(config.file_cache_mb, DEFAULT_FILE_CACHE_MB);
+
+        let content = r#"
+[tool.pytest-language-server]
+file_cache_mb = 512
+"#;
+        let mut config = Config::parse(content, Path::new("pyproject.toml"));
+        assert_eq!(config.file_cache_mb, 512);
+
+        config.apply_settings(&serde_json::json!({ "file_cache_mb": 32 }));
+        assert_eq!(config.file_cache_mb, 32);
+
+        // Invalid values are ignored
+        config.apply_settings(&serde_json::json!({ "file_cache_mb": "1GB" }));
+        assert_eq!(config.file_cache_mb, 32);
+    }
+
+    #[test]
+    fn test_index_cache_settings() {
+        let config = Config::parse("", Path::new("pyproject.toml"));
+        assert!(config.index_cache);
+        assert_eq!(config.cache_dir, None);
+
+        let content = r#"
+[tool.pytest-language-server]
+index_cache = false
+cache_dir = ".cache/pytest-language-server"
+"#;
+        let mut config = Config::parse(content, Path::new("pyproject.toml"));
+        assert!(!config.index_cache);
+        assert_eq!(
+            config.cache_dir,
+            Some(PathBuf::from(".cache/pytest-language-server"))
+        );
+
+        config.apply_settings(&serde_json::json!({
+            "index_cache": true,
+            "cache_dir": "/tmp/pytest-language-server"
+        }));
+        assert!(config.index_cache);
+        assert_eq!(
+            config.cache_dir,
+            Some(PathBuf::from("/tmp/pytest-language-server"))
+        );
+
+        // Invalid values are ignored
+        config.apply_settings(&serde_json::json!({ "index_cache": "no", "cache_dir": 1 }));
+        assert!(config.index_cache);
+        assert_eq!(
+            config.cache_dir,
+            Some(PathBuf::from("/tmp/pytest-language-server"))
+        );
+    }
+
+    #[test]
+    fn test_find_rule() {
+        assert_eq!(
+            find_rule("ptl003").map(|r| r.name),
+            Some("circular-dependency")
+        );
+        assert_eq!(
+            find_rule("undeclared-fixture").map(|r| r.id),
+            Some("PTL001")
+        );
+        assert!(find_rule("unknown").is_none());
+    }
+
+    #[test]
+    fn test_effective_severity() {
+        let content = r#"
+[tool.pytest-language-server]
+disabled_diagnostics = ["PTL005"]
+
+[tool.pytest-language-server.severity]
+undeclared-fixture = "error"
+PTL003 = "off"
+"#;
+        let config = Config::parse(content, Path::new("pyproject.toml"));
+        assert_eq!(
+            config.effective_severity("PTL001"),
+            Some(RuleSeverity::Error)
+        );
+        assert_eq!(
+            config.effective_severity("scope-mismatch"),
+            Some(RuleSeverity::Warning)
+        );
+        assert_eq!(config.effective_severity("circular-dependency"), None);
+        assert_eq!(config.effective_severity("unknown-marker"), None);
+        assert_eq!(config.effective_severity("unknown"), None);
+    }
+
     #[test]
     fn test_should_exclude() {
         let content = r#"
@@ -304,7 +1286,9 @@ skip_plugins = ["pytest-xdist", "pytest-cov"]
     fn test_default_config() {
         let config = Config::default();
         assert!(config.exclude.is_empty());
+        assert!(config.respect_gitignore);
         assert!(config.disabled_diagnostics.is_empty());
+        assert!(config.severity.is_empty());
         assert!(config.fixture_paths.is_empty());
         assert!(config.skip_plugins.is_empty());
     }
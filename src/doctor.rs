@@ -0,0 +1,400 @@
+//! The `doctor` command: report what the server detects in a workspace (pytest
+//! rootdir, Python environment, plugins, settings), to troubleshoot missing
+//! fixtures.
+
+use crate::config::Config;
+use crate::fixtures::{builtins, find_venv_python, FixtureDatabase};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+
+/// A titled group of `key: value` lines.
+struct Section {
+    title: &'static str,
+    entries: Vec<(&'static str, String)>,
+}
+
+impl Section {
+    fn new(title: &'static str) -> Self {
+        Self {
+            title,
+            entries: Vec::new(),
+        }
+    }
+
+    fn entry(&mut self, key: &'static str, value: impl Into<String>) {
+        self.entries.push((key, value.into()));
+    }
+}
+
+/// What the server detected in the workspace scanned at `root`.
+pub fn report(fixture_db: &FixtureDatabase, config: &Config, root: &Path) -> String {
+    let mut problems: Vec<String> = Vec::new();
+
+    let mut workspace = Section::new("Workspace");
+    workspace.entry("root", display(root));
+    match fixture_db.pytest_root() {
+        Some(pytest_root) => {
+            workspace.entry("pytest rootdir", display(&pytest_root.rootdir));
+            workspace.entry(
+                "pytest config file",
+                pytest_root
+                    .inifile
+                    .as_deref()
+                    .map(display)
+                    .unwrap_or_else(|| "none".to_string()),
+            );
+            workspace.entry("testpaths", list(&pytest_root.testpaths));
+            workspace.entry("pythonpath", list(&pytest_root.pythonpath));
+        }
+        None => workspace.entry("pytest rootdir", "not found"),
+    }
+    let nested: Vec<PathBuf> = fixture_db
+        .nested_pytest_roots
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|nested| nested.rootdir.clone())
+        .collect();
+    workspace.entry("nested pytest roots", list(&nested));
+
+    let mut environment = Section::new("Python environment");
+    let venv = fixture_db.workspace_venv(root);
+    if config.python_path.is_some() && fixture_db.configured_python(root).is_none() {
+        problems.push("The configured `python` interpreter does not exist".to_string());
+    }
+    if let Some(configured) = &config.venv {
+        if !root.join(configured).is_dir() {
+            problems.push(format!(
+                "The configured `venv` does not exist: {}",
+                display(&root.join(configured))
+            ));
+        }
+    }
+    match &venv {
+        Some(venv) => {
+            let source =
+                if config.python_path.is_some() && fixture_db.configured_python(root).is_some() {
+                    "from the `python` setting"
+                } else if config
+                    .venv
+                    .as_ref()
+                    .is_some_and(|configured| root.join(configured).is_dir())
+                {
+                    "from the `venv` setting"
+                } else if std::env::var_os("VIRTUAL_ENV")
+                    .map(PathBuf::from)
+                    .and_then(|path| path.canonicalize().ok())
+                    .is_some_and(|path| venv.canonicalize().ok() == Some(path))
+                {
+                    "from VIRTUAL_ENV"
+                } else {
+                    "discovered"
+                };
+            environment.entry(
+                "virtual environment",
+                format!("{} ({})", display(venv), source),
+            );
+            match find_venv_python(venv) {
+                Some(python) => environment.entry("python", display(&python)),
+                None => {
+                    environment.entry("python", "not found");
+                    problems.push(format!(
+                        "The virtual environment has no Python interpreter: {}",
+                        display(venv)
+                    ));
+                }
+            }
+        }
+        None => {
+            environment.entry("virtual environment", "not found");
+            problems.push(
+                "No virtual environment found: plugin fixtures are not available (set `venv` or \
+                 `python` in [tool.pytest-language-server])"
+                    .to_string(),
+            );
+        }
+    }
+    let site_packages = fixture_db.site_packages_paths.lock().unwrap().clone();
+    environment.entry("site-packages", list(&site_packages));
+    if venv.is_some() && site_packages.is_empty() {
+        problems.push("No site-packages directory found in the virtual environment".to_string());
+    }
+    let subprojects: Vec<String> = fixture_db
+        .subproject_venvs
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|subproject| {
+            format!(
+                "{} -> {}",
+                display(&subproject.dir),
+                display(&subproject.venv)
+            )
+        })
+        .collect();
+    environment.entry("subproject environments", join(&subprojects));
+    let editables = fixture_db.editable_install_roots.lock().unwrap().len();
+    environment.entry("editable installs", editables.to_string());
+
+    // Fixtures by the plugin providing them: the top-level package or module
+    // in site-packages, or the file of a workspace plugin
+    let mut plugins: BTreeMap<String, usize> = BTreeMap::new();
+    let mut project_fixtures = 0;
+    let mut pytest_found = false;
+    for entry in fixture_db.definitions.iter() {
+        for def in entry.value() {
+            if builtins::is_builtin_definition(def) {
+                pytest_found = true;
+            } else if def.is_third_party {
+                let name = site_packages
+                    .iter()
+                    .find_map(|sp| def.file_path.strip_prefix(sp).ok())
+                    .and_then(|relative| relative.components().next())
+                    .map(|first| {
+                        let first = first.as_os_str().to_string_lossy();
+                        first.strip_suffix(".py").unwrap_or(&first).to_string()
+                    })
+                    .unwrap_or_else(|| display(&def.file_path));
+                *plugins.entry(name).or_default() += 1;
+            } else if def.is_plugin {
+                let name = def
+                    .file_path
+                    .strip_prefix(root)
+                    .map(display)
+                    .unwrap_or_else(|_| display(&def.file_path));
+                *plugins.entry(name).or_default() += 1;
+            } else {
+                project_fixtures += 1;
+            }
+        }
+    }
+    let mut fixtures = Section::new("Fixtures");
+    fixtures.entry(
+        "files analyzed",
+        fixture_db.checked_files(root).len().to_string(),
+    );
+    fixtures.entry("project fixtures", project_fixtures.to_string());
+    fixtures.entry(
+        "pytest",
+        if pytest_found {
+            "installed"
+        } else {
+            "not installed (using bundled builtin fixtures)"
+        },
+    );
+    fixtures.entry("plugins with fixtures", plugins.len().to_string());
+    for (plugin, count) in &plugins {
+        fixtures.entry("", format!("{} ({} fixture(s))", plugin, count));
+    }
+
+    let mut scan = Section::new("Scan");
+    let exclude: Vec<String> = config
+        .exclude
+        .iter()
+        .map(|pattern| pattern.as_str().to_string())
+        .collect();
+    scan.entry("exclude", join(&exclude));
+    scan.entry("respect gitignore", yes_no(config.respect_gitignore));
+    scan.entry("fixture paths", join(&config.fixture_paths));
+    scan.entry("skipped plugins", join(&config.skip_plugins));
+    scan.entry(
+        "index cache",
+        if config.index_cache {
+            match &config.cache_dir {
+                Some(cache_dir) => format!("enabled ({})", display(&root.join(cache_dir))),
+                None => "enabled".to_string(),
+            }
+        } else {
+            "disabled".to_string()
+        },
+    );
+
+    let mut settings = Section::new("Settings");
+    settings.entry(
+        "configuration",
+        if root.join("pyproject.toml").is_file() {
+            display(&root.join("pyproject.toml"))
+        } else {
+            "defaults (no pyproject.toml)".to_string()
+        },
+    );
+    settings.entry("fixture decorators", join(&config.fixture_decorators));
+    settings.entry(
+        "test file patterns",
+        if config.test_file_patterns.is_empty() {
+            "test_*.py, *_test.py (default)".to_string()
+        } else {
+            let patterns: Vec<String> = config
+                .test_file_patterns
+                .iter()
+                .map(|p| p.as_str().to_string())
+                .collect();
+            patterns.join(", ")
+        },
+    );
+    settings.entry("disabled diagnostics", join(&config.disabled_diagnostics));
+    let mut severities: Vec<String> = config
+        .severity
+        .iter()
+        .map(|(rule, severity)| format!("{} = {}", rule, severity.as_str()))
+        .collect();
+    severities.sort();
+    settings.entry("severity overrides", join(&severities));
+    settings.entry(
+        "inlay hints",
+        format!(
+            "types {}, origin {}",
+            yes_no(config.inlay_hints.types),
+            yes_no(config.inlay_hints.origin)
+        ),
+    );
+    let completion = &config.completion;
+    settings.entry(
+        "completion",
+        format!(
+            "auto-add parameter {}, decorator strings {}, third-party {}, max items {}",
+            yes_no(completion.auto_add_parameter),
+            yes_no(completion.decorator_strings),
+            yes_no(completion.third_party),
+            completion
+                .max_items
+                .map_or_else(|| "unlimited".to_string(), |max| max.to_string())
+        ),
+    );
+    settings.entry(
+        "third-party completions",
+        format!(
+            "allow {}, deny {}",
+            if completion.third_party_allow.is_empty() {
+                "all".to_string()
+            } else {
+                join(&completion.third_party_allow)
+            },
+            join(&completion.third_party_deny)
+        ),
+    );
+    settings.entry("debounce", format!("{} ms", config.debounce_ms));
+    settings.entry("file cache", format!("{} MiB", config.file_cache_mb));
+
+    let mut out = format!("pytest-language-server {}\n", env!("CARGO_PKG_VERSION"));
+    for section in [workspace, environment, fixtures, scan, settings] {
+        let _ = writeln!(out, "\n{}", section.title);
+        for (key, value) in &section.entries {
+            let key = if key.is_empty() {
+                String::new()
+            } else {
+                format!("{}:", key)
+            };
+            let _ = writeln!(out, "  {:<25} {}", key, value);
+        }
+    }
+    out.push_str("\nProblems\n");
+    if problems.is_empty() {
+        out.push_str("  none found\n");
+    }
+    for problem in &problems {
+        let _ = writeln!(out, "  - {}", problem);
+    }
+    out
+}
+
+fn display(path: &Path) -> String {
+    path.display().to_string()
+}
+
+fn list(paths: &[PathBuf]) -> String {
+    let paths: Vec<String> = paths.iter().map(|path| display(path)).collect();
+    join(&paths)
+}
+
+fn join(values: &[String]) -> String {
+    if values.is_empty() {
+        "none".to_string()
+    } else {
+        values.join(", ")
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_report() {
+        let tmp = tempdir().unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+        fs::write(
+            root.join("pyproject.toml"),
+            "[tool.pytest.ini_options]\ntestpaths = [\"tests\"]\n\n\
+             [tool.pytest-language-server]\nexclude = [\"build\"]\n\
+             disabled_diagnostics = [\"scope-mismatch\"]\n",
+        )
+        .unwrap();
+        fs::create_dir_all(root.join("tests")).unwrap();
+        fs::write(
+            root.join("tests/conftest.py"),
+            "import pytest\n\n@pytest.fixture\ndef db():\n    pass\n",
+        )
+        .unwrap();
+        let site_packages = root.join(".venv/lib/python3.12/site-packages");
+        let dist_info = site_packages.join("pytest_mock-1.0.dist-info");
+        fs::create_dir_all(&dist_info).unwrap();
+        fs::write(
+            dist_info.join("entry_points.txt"),
+            "[pytest11]\npytest_mock = pytest_mock\n",
+        )
+        .unwrap();
+        fs::write(
+            site_packages.join("pytest_mock.py"),
+            "import pytest\n\n@pytest.fixture\ndef mocker():\n    pass\n",
+        )
+        .unwrap();
+
+        let config = Config::load(&root);
+        let fixture_db = FixtureDatabase::new();
+        fixture_db.set_venv_path(config.venv.clone());
+        fixture_db.scan_workspace_with_excludes(&root, &config.exclude, config.respect_gitignore);
+        let report = report(&fixture_db, &config, &root);
+
+        let line = |key: &str| {
+            report
+                .lines()
+                .find(|line| line.trim_start().starts_with(&format!("{}:", key)))
+                .unwrap_or_else(|| panic!("No {} line in:\n{}", key, report))
+                .split_once(':')
+                .unwrap()
+                .1
+                .trim()
+                .to_string()
+        };
+        assert_eq!(line("pytest rootdir"), display(&root));
+        assert_eq!(
+            line("pytest config file"),
+            display(&root.join("pyproject.toml"))
+        );
+        assert_eq!(line("testpaths"), display(&root.join("tests")));
+        assert_eq!(
+            line("virtual environment"),
+            format!("{} (discovered)", display(&root.join(".venv")))
+        );
+        assert_eq!(line("site-packages"), display(&site_packages));
+        assert_eq!(line("project fixtures"), "1");
+        assert_eq!(line("plugins with fixtures"), "1");
+        assert!(report.contains("  pytest_mock (1 fixture(s))\n"));
+        assert_eq!(line("exclude"), "build");
+        assert_eq!(line("disabled diagnostics"), "scope-mismatch");
+        // The fake environment has no interpreter
+        assert!(report.contains("The virtual environment has no Python interpreter"));
+    }
+}
@@ -4,26 +4,53 @@
 //! fixture definitions and usages. Docstring extraction is in `docstring.rs`
 //! and undeclared fixture scanning is in `undeclared.rs`.
 
+use super::bdd;
 use super::decorators;
-use super::types::{FixtureDefinition, FixtureUsage, TypeImportSpec};
+use super::types::{AnalysisFailure, ClassScope, FixtureDefinition, FixtureUsage, TypeImportSpec};
 use super::FixtureDatabase;
-use rustpython_parser::ast::{ArgWithDefault, Arguments, Expr, Stmt};
-use rustpython_parser::{parse, Mode};
+use ruff_python_ast::visitor::{self, Visitor};
+use ruff_python_ast::{Expr, ParameterWithDefault, Parameters, Stmt};
+use ruff_text_size::TextRange;
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use tracing::{debug, info};
+use tracing::{debug, error, info};
 
 impl FixtureDatabase {
     /// Analyze a Python file for fixtures and usages.
     /// This is the public API - it cleans up previous definitions before analyzing.
     pub fn analyze_file(&self, file_path: PathBuf, content: &str) {
-        self.analyze_file_internal(file_path, content, true);
+        self.analyze_file_guarded(file_path, content, true);
     }
 
     /// Analyze a file without cleaning up previous definitions.
     /// Used during initial workspace scan when we know the database is empty.
     pub(crate) fn analyze_file_fresh(&self, file_path: PathBuf, content: &str) {
-        self.analyze_file_internal(file_path, content, false);
+        self.analyze_file_guarded(file_path, content, false);
+    }
+
+    /// Analyze a file, recording a panic in [`Self::analysis_failures`]
+    /// instead of letting it take down the scan or the server.
+    fn analyze_file_guarded(&self, file_path: PathBuf, content: &str, cleanup_previous: bool) {
+        let file_path = self.get_canonical_path(file_path);
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            self.analyze_file_internal(file_path.clone(), content, cleanup_previous)
+        }));
+        match result {
+            Ok(()) => {
+                self.analysis_failures.remove(&file_path);
+            }
+            Err(payload) => {
+                let message = panic_message(payload.as_ref());
+                error!("Analysis of {:?} panicked: {}", file_path, message);
+                // The same failure is only reported once
+                let reported = self
+                    .analysis_failures
+                    .get(&file_path)
+                    .is_some_and(|failure| failure.message == message && failure.reported);
+                self.analysis_failures
+                    .insert(file_path, AnalysisFailure { message, reported });
+            }
+        }
     }
 
     /// Internal file analysis with optional cleanup of previous definitions
@@ -33,18 +60,48 @@ impl FixtureDatabase {
 
         debug!("Analyzing file: {:?}", file_path);
 
+        // An edit inside a single function of an open file only needs that
+        // function re-analyzed
+        if cleanup_previous {
+            let previous = self
+                .file_cache
+                .get(&file_path)
+                .map(|cached| std::sync::Arc::clone(cached.value()));
+            if let Some(previous) = previous {
+                if self.reanalyze_edited_function(&file_path, &previous, content) {
+                    self.cache_file_content(file_path, std::sync::Arc::new(content.to_string()));
+                    return;
+                }
+            }
+        }
+
         // Cache the file content for later use (e.g., in find_fixture_definition)
-        // Use Arc for efficient sharing without cloning
-        self.file_cache
-            .insert(file_path.clone(), std::sync::Arc::new(content.to_string()));
+        // Use Arc for efficient sharing without cloning. Third-party files are
+        // analyzed once: only what they define is kept.
+        let retains_contents = self.retains_contents(&file_path);
+        if retains_contents {
+            self.cache_file_content(file_path.clone(), std::sync::Arc::new(content.to_string()));
+        } else {
+            self.uncache_file_content(&file_path);
+        }
+
+        // A file that did not change since the last run is restored from the
+        // persisted index
+        if self.restore_cached_analysis(&file_path, content, cleanup_previous) {
+            self.evict_cache_if_needed(&file_path);
+            return;
+        }
 
         // Parse the Python code and populate the AST cache so follow-up
         // requests (completion, hover, code actions) don't re-parse.
-        let parsed = match parse(content, Mode::Module, "") {
+        let parsed = match super::parse_module(content) {
             Ok(ast) => std::sync::Arc::new(ast),
             Err(e) => {
                 // Keep existing fixture data when parse fails (user is likely editing)
-                // This provides better LSP experience during editing with syntax errors
+                // This provides better LSP experience during editing with syntax errors.
+                // That data no longer matches the cached content, so the next
+                // edit analyzes the whole file.
+                self.module_outlines.remove(&file_path);
                 debug!(
                     "Failed to parse Python file {:?}: {} - keeping previous data",
                     file_path, e
@@ -53,10 +110,12 @@ impl FixtureDatabase {
             }
         };
         let content_hash = Self::hash_content(content);
-        self.ast_cache.insert(
-            file_path.clone(),
-            (content_hash, std::sync::Arc::clone(&parsed)),
-        );
+        if retains_contents {
+            self.ast_cache.insert(
+                file_path.clone(),
+                (content_hash, std::sync::Arc::clone(&parsed)),
+            );
+        }
 
         // Clear previous usages for this file (only after successful parse)
         self.cleanup_usages_for_file(&file_path);
@@ -68,6 +127,8 @@ impl FixtureDatabase {
         // Clear previous imports for this file
         self.imports.remove(&file_path);
 
+        self.record_registered_markers(&file_path, content);
+
         // Note: line_index_cache uses content-hash-based invalidation,
         // so we don't need to clear it here - get_line_index will detect
         // if the content has changed and rebuild if necessary.
@@ -89,7 +150,7 @@ impl FixtureDatabase {
         let line_index = self.get_line_index(&file_path, content);
 
         // Process each statement in the module
-        if let rustpython_parser::ast::Mod::Module(module) = parsed.as_ref() {
+        if let ruff_python_ast::Mod::Module(module) = parsed.as_ref() {
             debug!("Module has {} statements", module.body.len());
 
             // First pass: collect all module-level names (imports, assignments, function/class defs)
@@ -105,14 +166,27 @@ impl FixtureDatabase {
             self.imports
                 .insert(file_path.clone(), module_level_names.clone());
 
+            // Class statements scope the fixtures defined as their methods.
+            // Recorded before the second pass so definitions pick up their class.
+            let mut class_scopes = Vec::new();
+            self.collect_class_scopes(&module.body, None, &line_index, &mut class_scopes);
+            if class_scopes.is_empty() {
+                self.class_scopes.remove(&file_path);
+            } else {
+                self.class_scopes.insert(file_path.clone(), class_scopes);
+            }
+
             // Build a name→TypeImportSpec map from every import statement in the file.
             // Used during fixture analysis to resolve return-type annotation imports,
             // and cached for code-action and inlay-hint requests.
-            let import_map = self.build_name_to_import_map(&module.body, &file_path);
-            self.name_import_map_cache.insert(
-                file_path.clone(),
-                (content_hash, std::sync::Arc::new(import_map.clone())),
-            );
+            let import_map =
+                std::sync::Arc::new(self.build_name_to_import_map(&module.body, &file_path));
+            if retains_contents {
+                self.name_import_map_cache.insert(
+                    file_path.clone(),
+                    (content_hash, std::sync::Arc::clone(&import_map)),
+                );
+            }
 
             // Collect type aliases so that `-> MyType` can be expanded to the
             // underlying type before import resolution.
@@ -131,6 +205,39 @@ impl FixtureDatabase {
                     &type_aliases,
                 );
             }
+
+            // Third pass: lazy_fixture references, which appear in parametrize
+            // values, fixture `params` and module-level constants alike
+            if content.contains("lazy_fixture") || content.contains("lf(") {
+                self.visit_lazy_fixture_references(&module.body, &file_path, content, &line_index);
+            }
+
+            // Plugins' type stubs annotate their fixtures better than their
+            // runtime code
+            if self.is_third_party_file(&file_path) {
+                self.apply_stub_return_types(&file_path, &module.body, content, &line_index);
+            }
+
+            // Fixtures generated at runtime by pytest-factoryboy's register()
+            if content.contains("pytest_factoryboy") {
+                self.record_factoryboy_fixtures(&module.body, &file_path, content, &line_index);
+            }
+
+            // pytest-cases fixture unions and fixture_ref() references
+            if content.contains("pytest_cases") {
+                self.record_pytest_cases_fixtures(&module.body, &file_path, content, &line_index);
+            }
+
+            if self.open_files.contains_key(&file_path) {
+                self.record_module_outline(&file_path, &module.body, import_map, type_aliases);
+            } else {
+                self.module_outlines.remove(&file_path);
+            }
+        }
+
+        // Undeclared fixtures are only reported in the user's own files
+        if !retains_contents {
+            self.undeclared_fixtures.remove(&file_path);
         }
 
         debug!("Analysis complete for {:?}", file_path);
@@ -139,6 +246,41 @@ impl FixtureDatabase {
         self.evict_cache_if_needed(&file_path);
     }
 
+    /// Forget every file below `dir` (except those below one of `keep`), as
+    /// when a workspace folder is removed: its definitions, usages and caches.
+    pub fn remove_files_under(&self, dir: &Path, keep: &[PathBuf]) {
+        let files: HashSet<PathBuf> = self
+            .file_cache
+            .iter()
+            .map(|entry| entry.key().clone())
+            .chain(
+                self.file_definitions
+                    .iter()
+                    .map(|entry| entry.key().clone()),
+            )
+            .chain(self.usages.iter().map(|entry| entry.key().clone()))
+            .filter(|file| file.starts_with(dir) && !keep.iter().any(|k| file.starts_with(k)))
+            .collect();
+        if files.is_empty() {
+            return;
+        }
+        info!("Removing {} files under {:?}", files.len(), dir);
+
+        for file_path in &files {
+            self.cleanup_definitions_for_file(file_path);
+            self.cleanup_usages_for_file(file_path);
+            self.usages.remove(file_path);
+            self.undeclared_fixtures.remove(file_path);
+            self.imports.remove(file_path);
+            self.class_scopes.remove(file_path);
+            self.registered_markers.remove(file_path);
+            self.plugin_fixture_files.remove(file_path);
+            self.content_hashes.remove(file_path);
+            self.cleanup_file_cache(file_path);
+        }
+        self.invalidate_cycle_cache();
+    }
+
     /// Remove definitions that were in a specific file.
     /// Uses the file_definitions reverse index for efficient O(m) cleanup
     /// where m = number of fixtures in this file, rather than O(n) where
@@ -148,7 +290,7 @@ impl FixtureDatabase {
     /// 1. Atomically remove the set of fixture names from file_definitions
     /// 2. For each fixture name, get a mutable reference, modify, then drop
     /// 3. Only after dropping the reference, remove empty entries
-    fn cleanup_definitions_for_file(&self, file_path: &PathBuf) {
+    pub(crate) fn cleanup_definitions_for_file(&self, file_path: &PathBuf) {
         // Step 1: Atomically remove and get the fixture names for this file
         let fixture_names = match self.file_definitions.remove(file_path) {
             Some((_, names)) => names,
@@ -182,7 +324,7 @@ impl FixtureDatabase {
     ///
     /// Collects all keys first (without filtering) to avoid holding read locks
     /// while doing the filter check, which could cause deadlocks.
-    fn cleanup_usages_for_file(&self, file_path: &PathBuf) {
+    pub(crate) fn cleanup_usages_for_file(&self, file_path: &PathBuf) {
         // Collect all keys first to avoid holding any locks during iteration
         let all_keys: Vec<String> = self
             .usage_by_fixture
@@ -223,6 +365,18 @@ impl FixtureDatabase {
         line_index
     }
 
+    /// The text of 1-based `line`, without its line ending, using the line
+    /// index of `content`.
+    pub(crate) fn line_text<'a>(
+        content: &'a str,
+        line_index: &[usize],
+        line: usize,
+    ) -> Option<&'a str> {
+        let start = *line_index.get(line.checked_sub(1)?)?;
+        let end = line_index.get(line).copied().unwrap_or(content.len());
+        Some(content[start..end].trim_end_matches(['\r', '\n']))
+    }
+
     /// Get line number (1-based) from byte offset
     pub(crate) fn get_line_from_offset(&self, offset: usize, line_index: &[usize]) -> usize {
         match line_index.binary_search(&offset) {
@@ -245,7 +399,7 @@ impl FixtureDatabase {
     /// Returns an iterator over all function arguments including positional-only,
     /// regular positional, and keyword-only arguments.
     /// This is needed because pytest fixtures can be declared as any of these types.
-    pub(crate) fn all_args(args: &Arguments) -> impl Iterator<Item = &ArgWithDefault> {
+    pub(crate) fn all_args(args: &Parameters) -> impl Iterator<Item = &ParameterWithDefault> {
         args.posonlyargs
             .iter()
             .chain(args.args.iter())
@@ -255,7 +409,7 @@ impl FixtureDatabase {
     /// Helper to record a fixture usage in the database.
     /// Reduces code duplication across multiple call sites.
     /// Also maintains usage_by_fixture reverse index for efficient reference lookups.
-    fn record_fixture_usage(
+    pub(crate) fn record_fixture_usage(
         &self,
         file_path: &Path,
         fixture_name: String,
@@ -311,7 +465,7 @@ impl FixtureDatabase {
 
     /// Visit a statement and extract fixture definitions and usages
     #[allow(clippy::too_many_arguments)]
-    fn visit_stmt(
+    pub(crate) fn visit_stmt(
         &self,
         stmt: &Stmt,
         file_path: &PathBuf,
@@ -361,7 +515,8 @@ impl FixtureDatabase {
         if let Stmt::ClassDef(class_def) = stmt {
             // Check for @pytest.mark.usefixtures decorator on the class
             for decorator in &class_def.decorator_list {
-                let usefixtures = decorators::extract_usefixtures_names(decorator, content);
+                let usefixtures =
+                    decorators::extract_usefixtures_names(&decorator.expression, content);
                 for (fixture_name, range) in usefixtures {
                     let usage_line =
                         self.get_line_from_offset(range.start().to_usize(), line_index);
@@ -402,30 +557,25 @@ impl FixtureDatabase {
         }
 
         // Handle both regular and async function definitions
-        let (func_name, decorator_list, args, range, body, returns) = match stmt {
-            Stmt::FunctionDef(func_def) => (
-                func_def.name.as_str(),
-                &func_def.decorator_list,
-                &func_def.args,
-                func_def.range,
-                &func_def.body,
-                &func_def.returns,
-            ),
-            Stmt::AsyncFunctionDef(func_def) => (
-                func_def.name.as_str(),
-                &func_def.decorator_list,
-                &func_def.args,
-                func_def.range,
-                &func_def.body,
-                &func_def.returns,
-            ),
-            _ => return,
+        let Stmt::FunctionDef(func_def) = stmt else {
+            return;
         };
+        let func_name = func_def.name.as_str();
+        let decorator_list: Vec<&Expr> = func_def
+            .decorator_list
+            .iter()
+            .map(|decorator| &decorator.expression)
+            .collect();
+        let args = &func_def.parameters;
+        let range = func_def.range;
+        let def_start = super::definition_start(stmt, content).to_usize();
+        let body = &func_def.body;
+        let returns = &func_def.returns;
 
         debug!("Found function: {}", func_name);
 
         // Check for @pytest.mark.usefixtures decorator on the function
-        for decorator in decorator_list {
+        for decorator in &decorator_list {
             let usefixtures = decorators::extract_usefixtures_names(decorator, content);
             for (fixture_name, range) in usefixtures {
                 let usage_line = self.get_line_from_offset(range.start().to_usize(), line_index);
@@ -451,7 +601,7 @@ impl FixtureDatabase {
         }
 
         // Check for @pytest.mark.parametrize with indirect=True on the function
-        for decorator in decorator_list {
+        for decorator in &decorator_list {
             let indirect_fixtures =
                 decorators::extract_parametrize_indirect_fixtures(decorator, content);
             for (fixture_name, range) in indirect_fixtures {
@@ -477,28 +627,66 @@ impl FixtureDatabase {
             }
         }
 
+        // Check for request.getfixturevalue("name") calls in the body. The text
+        // check skips the AST walk for the vast majority of functions.
+        let func_source = content
+            .get(range.start().to_usize()..range.end().to_usize())
+            .unwrap_or("");
+        if func_source.contains("getfixturevalue") {
+            let mut collector = GetfixturevalueCollector {
+                content,
+                calls: Vec::new(),
+            };
+            collector.visit_body(body);
+            for (fixture_name, range) in collector.calls {
+                let usage_line = self.get_line_from_offset(range.start().to_usize(), line_index);
+                let start_char =
+                    self.get_char_position_from_offset(range.start().to_usize(), line_index);
+                let end_char =
+                    self.get_char_position_from_offset(range.end().to_usize(), line_index);
+
+                info!(
+                    "Found getfixturevalue usage: {} at {:?}:{}:{}",
+                    fixture_name, file_path, usage_line, start_char
+                );
+
+                self.record_fixture_usage(
+                    file_path,
+                    fixture_name,
+                    usage_line,
+                    start_char,
+                    end_char,
+                    false, // getfixturevalue string — not a function parameter
+                );
+            }
+        }
+
         // Check if this is a fixture definition
         debug!(
             "Function {} has {} decorators",
             func_name,
             decorator_list.len()
         );
+        let aliases = self.fixture_decorator_aliases();
         let fixture_decorator = decorator_list
             .iter()
-            .find(|dec| decorators::is_fixture_decorator(dec));
+            .copied()
+            .find(|dec| decorators::is_fixture_decorator(dec, &aliases));
 
         if let Some(decorator) = fixture_decorator {
             debug!("  Decorator matched as fixture!");
 
             // Check if the fixture has a custom name
-            let fixture_name = decorators::extract_fixture_name_from_decorator(decorator)
+            let fixture_name = decorators::extract_fixture_name_from_decorator(decorator, &aliases)
                 .unwrap_or_else(|| func_name.to_string());
 
             // Extract scope from decorator (defaults to function scope)
-            let scope = decorators::extract_fixture_scope(decorator).unwrap_or_default();
-            let autouse = decorators::extract_fixture_autouse(decorator);
+            let scope = decorators::extract_fixture_scope(decorator, &aliases).unwrap_or_default();
+            let autouse = decorators::extract_fixture_autouse(decorator, &aliases);
+            let params =
+                decorators::extract_fixture_params(decorator, &aliases, content, &fixture_name);
 
-            let line = self.get_line_from_offset(range.start().to_usize(), line_index);
+            let line = self.get_line_from_offset(def_start, line_index);
             let docstring = self.extract_docstring(body);
             let raw_return_type = self.extract_return_type(returns, body, content);
             let return_type = raw_return_type.map(|rt| {
@@ -527,10 +715,10 @@ impl FixtureDatabase {
                 fixture_name, func_name, scope, file_path, line
             );
 
-            let (start_char, end_char) = self.find_function_name_position(content, line, func_name);
+            let (start_char, end_char) =
+                self.find_function_name_position(content, line_index, line, func_name);
 
-            let is_third_party = file_path.to_string_lossy().contains("site-packages")
-                || self.is_editable_install_third_party(file_path);
+            let is_third_party = self.is_third_party_file(file_path);
             let is_plugin = self.plugin_fixture_files.contains_key(file_path);
 
             // Fixtures can depend on other fixtures - collect dependencies first
@@ -541,7 +729,7 @@ impl FixtureDatabase {
             declared_params.insert(func_name.to_string());
 
             for arg in Self::all_args(args) {
-                let arg_name = arg.def.arg.as_str();
+                let arg_name = arg.parameter.name.as_str();
                 declared_params.insert(arg_name.to_string());
                 // Track as dependency if it's not self/request (these are special)
                 if arg_name != "self" && arg_name != "request" {
@@ -562,12 +750,15 @@ impl FixtureDatabase {
                 docstring,
                 return_type,
                 return_type_imports,
+                inferred_return_type: self.infer_return_type(returns, body, content),
                 is_third_party,
                 is_plugin,
                 dependencies: dependencies.clone(),
                 scope,
                 yield_line: self.find_yield_line(body, line_index),
                 autouse,
+                class_name: self.enclosing_class(file_path, line),
+                params,
             };
 
             self.record_fixture_definition(definition);
@@ -575,16 +766,16 @@ impl FixtureDatabase {
             // Record each parameter as a usage (dependencies + special builtins like
             // `request` that are not fixture dependencies but need inlay hints / code actions)
             for arg in Self::all_args(args) {
-                let arg_name = arg.def.arg.as_str();
+                let arg_name = arg.parameter.name.as_str();
 
                 // `request` is excluded from *dependencies* (it is a special pytest
                 // injection, not a regular fixture), but we DO record it as a usage
                 // so that inlay hints and type-annotation code actions work on it.
                 if arg_name != "self" {
-                    let arg_line =
-                        self.get_line_from_offset(arg.def.range.start().to_usize(), line_index);
+                    let arg_line = self
+                        .get_line_from_offset(arg.parameter.range.start().to_usize(), line_index);
                     let start_char = self.get_char_position_from_offset(
-                        arg.def.range.start().to_usize(),
+                        arg.parameter.range.start().to_usize(),
                         line_index,
                     );
                     // Use parameter name length, not AST range (which includes type annotation)
@@ -606,7 +797,7 @@ impl FixtureDatabase {
                 }
             }
 
-            let function_line = self.get_line_from_offset(range.start().to_usize(), line_index);
+            let function_line = self.get_line_from_offset(def_start, line_index);
             self.scan_function_body_for_undeclared_fixtures(
                 body,
                 file_path,
@@ -628,11 +819,11 @@ impl FixtureDatabase {
             declared_params.insert("request".to_string());
 
             for arg in Self::all_args(args) {
-                let arg_name = arg.def.arg.as_str();
+                let arg_name = arg.parameter.name.as_str();
                 declared_params.insert(arg_name.to_string());
 
                 if arg_name != "self" {
-                    let arg_offset = arg.def.range.start().to_usize();
+                    let arg_offset = arg.parameter.range.start().to_usize();
                     let arg_line = self.get_line_from_offset(arg_offset, line_index);
                     let start_char = self.get_char_position_from_offset(arg_offset, line_index);
                     // Use parameter name length, not AST range (which includes type annotation)
@@ -658,7 +849,106 @@ impl FixtureDatabase {
                 }
             }
 
-            let function_line = self.get_line_from_offset(range.start().to_usize(), line_index);
+            let function_line = self.get_line_from_offset(def_start, line_index);
+            self.scan_function_body_for_undeclared_fixtures(
+                body,
+                file_path,
+                line_index,
+                &declared_params,
+                func_name,
+                function_line,
+            );
+            return;
+        }
+
+        // pytest-bdd step functions request fixtures through their parameters
+        // like tests do, and may provide one with `target_fixture=`
+        let steps: Vec<_> = decorator_list
+            .iter()
+            .copied()
+            .filter_map(bdd::step_decorator)
+            .collect();
+        if fixture_decorator.is_none() && !steps.is_empty() {
+            debug!("Found pytest-bdd step function: {}", func_name);
+
+            let step_arguments: HashSet<String> = steps
+                .iter()
+                .flat_map(|step| bdd::step_arguments(step))
+                .collect();
+
+            let mut declared_params: HashSet<String> = HashSet::new();
+            declared_params.insert("self".to_string());
+            declared_params.insert("request".to_string());
+            let mut dependencies = Vec::new();
+
+            for arg in Self::all_args(args) {
+                let arg_name = arg.parameter.name.as_str();
+                declared_params.insert(arg_name.to_string());
+                if arg_name == "self" || step_arguments.contains(arg_name) {
+                    continue;
+                }
+                if arg_name != "request" {
+                    dependencies.push(arg_name.to_string());
+                }
+
+                let arg_offset = arg.parameter.range.start().to_usize();
+                let arg_line = self.get_line_from_offset(arg_offset, line_index);
+                let start_char = self.get_char_position_from_offset(arg_offset, line_index);
+                info!(
+                    "Found step fixture usage: {} at {:?}:{}:{}",
+                    arg_name, file_path, arg_line, start_char
+                );
+                self.record_fixture_usage(
+                    file_path,
+                    arg_name.to_string(),
+                    arg_line,
+                    start_char,
+                    start_char + arg_name.len(),
+                    true, // actual function parameter — can receive a type annotation
+                );
+            }
+
+            let end_line = self.get_line_from_offset(range.end().to_usize(), line_index);
+            for (fixture_name, name_range) in steps
+                .iter()
+                .filter_map(|step| bdd::target_fixture(step, content))
+            {
+                let line = self.get_line_from_offset(name_range.start().to_usize(), line_index);
+                info!(
+                    "Found step target fixture: {} (function: {}) at {:?}:{}",
+                    fixture_name, func_name, file_path, line
+                );
+                let return_type = self.extract_return_type(returns, body, content);
+                let return_type_imports = match &return_type {
+                    Some(rt) => self.resolve_return_type_imports(
+                        rt,
+                        import_map,
+                        module_level_names,
+                        file_path,
+                    ),
+                    None => vec![],
+                };
+                self.record_fixture_definition(FixtureDefinition {
+                    name: fixture_name,
+                    file_path: file_path.clone(),
+                    line,
+                    end_line,
+                    start_char: self
+                        .get_char_position_from_offset(name_range.start().to_usize(), line_index),
+                    end_char: self
+                        .get_char_position_from_offset(name_range.end().to_usize(), line_index),
+                    docstring: self.extract_docstring(body),
+                    return_type,
+                    return_type_imports,
+                    inferred_return_type: self.infer_return_type(returns, body, content),
+                    is_third_party: self.is_third_party_file(file_path),
+                    is_plugin: self.plugin_fixture_files.contains_key(file_path),
+                    dependencies: dependencies.clone(),
+                    ..Default::default()
+                });
+            }
+
+            let function_line = self.get_line_from_offset(def_start, line_index);
             self.scan_function_body_for_undeclared_fixtures(
                 body,
                 file_path,
@@ -673,14 +963,15 @@ impl FixtureDatabase {
     /// Handle assignment-style fixtures: fixture_name = pytest.fixture()(func)
     fn visit_assignment_fixture(
         &self,
-        assign: &rustpython_parser::ast::StmtAssign,
+        assign: &ruff_python_ast::StmtAssign,
         file_path: &PathBuf,
-        _content: &str,
+        content: &str,
         line_index: &[usize],
     ) {
         if let Expr::Call(outer_call) = &*assign.value {
             if let Expr::Call(inner_call) = &*outer_call.func {
-                if decorators::is_fixture_decorator(&inner_call.func) {
+                let aliases = self.fixture_decorator_aliases();
+                if decorators::is_fixture_decorator(&inner_call.func, &aliases) {
                     for target in &assign.targets {
                         if let Expr::Name(name) = target {
                             let fixture_name = name.id.as_str();
@@ -701,9 +992,7 @@ impl FixtureDatabase {
                                 fixture_name, file_path, line, start_char, end_char
                             );
 
-                            let is_third_party =
-                                file_path.to_string_lossy().contains("site-packages")
-                                    || self.is_editable_install_third_party(file_path);
+                            let is_third_party = self.is_third_party_file(file_path);
                             let is_plugin = self.plugin_fixture_files.contains_key(file_path);
                             let definition = FixtureDefinition {
                                 name: fixture_name.to_string(),
@@ -715,13 +1004,24 @@ impl FixtureDatabase {
                                 docstring: None,
                                 return_type: None,
                                 return_type_imports: vec![],
+                                inferred_return_type: None,
                                 is_third_party,
                                 is_plugin,
                                 dependencies: Vec::new(), // Assignment-style fixtures don't have explicit dependencies
-                                scope: decorators::extract_fixture_scope(&outer_call.func)
-                                    .unwrap_or_default(),
+                                scope: decorators::extract_fixture_scope(
+                                    &outer_call.func,
+                                    &aliases,
+                                )
+                                .unwrap_or_default(),
                                 yield_line: None, // Assignment-style fixtures don't have yield statements
                                 autouse: false,   // Assignment-style fixtures are never autouse
+                                class_name: self.enclosing_class(file_path, line),
+                                params: decorators::extract_fixture_params(
+                                    &outer_call.func,
+                                    &aliases,
+                                    content,
+                                    fixture_name,
+                                ),
                             };
 
                             self.record_fixture_definition(definition);
@@ -732,6 +1032,42 @@ impl FixtureDatabase {
         }
     }
 
+    /// Record the fixtures named by `lazy_fixture("...")` / `lf("...")` calls as usages.
+    fn visit_lazy_fixture_references(
+        &self,
+        body: &[Stmt],
+        file_path: &PathBuf,
+        content: &str,
+        line_index: &[usize],
+    ) {
+        let mut collector = LazyFixtureCollector {
+            content,
+            names: Vec::new(),
+        };
+        collector.visit_body(body);
+
+        for (fixture_name, range) in collector.names {
+            let usage_line = self.get_line_from_offset(range.start().to_usize(), line_index);
+            let start_char =
+                self.get_char_position_from_offset(range.start().to_usize(), line_index);
+            let end_char = self.get_char_position_from_offset(range.end().to_usize(), line_index);
+
+            info!(
+                "Found lazy_fixture usage: {} at {:?}:{}:{}",
+                fixture_name, file_path, usage_line, start_char
+            );
+
+            self.record_fixture_usage(
+                file_path,
+                fixture_name,
+                usage_line,
+                start_char,
+                end_char,
+                false, // lazy_fixture string — not a function parameter
+            );
+        }
+    }
+
     /// Handle pytestmark usefixtures — covers both plain and annotated assignments:
     ///   pytestmark = pytest.mark.usefixtures("fix1", "fix2")
     ///   pytestmark = [pytest.mark.usefixtures("fix1"), pytest.mark.skip]
@@ -774,6 +1110,65 @@ impl FixtureDatabase {
     }
 }
 
+/// The message of a caught panic: its `&str` or `String` payload.
+pub(crate) fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Collects `<expr>.getfixturevalue("name")` calls with a string-literal
+/// argument, paired with the range of the literal's content.
+struct GetfixturevalueCollector<'a> {
+    content: &'a str,
+    calls: Vec<(String, TextRange)>,
+}
+
+impl<'a> Visitor<'a> for GetfixturevalueCollector<'_> {
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        if let Expr::Call(call) = expr {
+            let is_getfixturevalue = matches!(
+                call.func.as_ref(),
+                Expr::Attribute(attr) if attr.attr.as_str() == "getfixturevalue"
+            );
+            if is_getfixturevalue {
+                if let Some(Expr::StringLiteral(s)) = call.arguments.args.first() {
+                    let literal = self
+                        .content
+                        .get(s.range.start().to_usize()..s.range.end().to_usize())
+                        .unwrap_or("");
+                    self.calls.push((
+                        s.value.to_string(),
+                        decorators::literal_content_range(literal, s.range),
+                    ));
+                }
+            }
+        }
+        visitor::walk_expr(self, expr);
+    }
+}
+
+/// Collects the fixture names referenced by `lazy_fixture("name")` calls
+/// (see [`decorators::extract_lazy_fixture_names`]) anywhere they appear.
+struct LazyFixtureCollector<'a> {
+    content: &'a str,
+    names: Vec<(String, TextRange)>,
+}
+
+impl<'a> Visitor<'a> for LazyFixtureCollector<'_> {
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        if let Expr::Call(call) = expr {
+            self.names
+                .extend(decorators::extract_lazy_fixture_names(call, self.content));
+        }
+        visitor::walk_expr(self, expr);
+    }
+}
+
 /// Python builtin types that never require an import statement.
 /// Uses O(1) `HashSet` lookup, consistent with `is_standard_library_module()`.
 static BUILTINS: std::sync::LazyLock<HashSet<&'static str>> = std::sync::LazyLock::new(|| {
@@ -923,7 +1318,7 @@ impl FixtureDatabase {
             Expr::Subscript(_) => true,
             // Union: int | str
             Expr::BinOp(binop) => {
-                matches!(binop.op, rustpython_parser::ast::Operator::BitOr)
+                matches!(binop.op, ruff_python_ast::Operator::BitOr)
                     && Self::expr_looks_like_type(&binop.left)
                     && Self::expr_looks_like_type(&binop.right)
             }
@@ -935,10 +1330,7 @@ impl FixtureDatabase {
             // Attribute: pathlib.Path
             Expr::Attribute(_) => true,
             // None literal or string literal (forward reference)
-            Expr::Constant(c) => matches!(
-                c.value,
-                rustpython_parser::ast::Constant::None | rustpython_parser::ast::Constant::Str(_)
-            ),
+            Expr::NoneLiteral(_) | Expr::StringLiteral(_) => true,
             _ => false,
         }
     }
@@ -1039,7 +1431,7 @@ impl FixtureDatabase {
     /// 4. Otherwise skip.
     ///
     /// Results are deduplicated by `check_name`.
-    fn resolve_return_type_imports(
+    pub(crate) fn resolve_return_type_imports(
         &self,
         return_type: &str,
         import_map: &HashMap<String, TypeImportSpec>,
@@ -1082,10 +1474,59 @@ impl FixtureDatabase {
         specs
     }
 
+    // ============ Class scopes ============
+
+    /// Collect the class statements of `body` and of the classes nested in
+    /// them, outermost first.
+    fn collect_class_scopes(
+        &self,
+        body: &[Stmt],
+        parent: Option<&str>,
+        line_index: &[usize],
+        scopes: &mut Vec<ClassScope>,
+    ) {
+        for stmt in body {
+            let Stmt::ClassDef(class_def) = stmt else {
+                continue;
+            };
+            let name = match parent {
+                Some(parent) => format!("{}.{}", parent, class_def.name),
+                None => class_def.name.to_string(),
+            };
+            let bases = class_def
+                .bases()
+                .iter()
+                .filter_map(|base| match base {
+                    Expr::Name(n) => Some(n.id.to_string()),
+                    Expr::Attribute(attr) => Some(attr.attr.to_string()),
+                    _ => None,
+                })
+                .collect();
+            scopes.push(ClassScope {
+                name: name.clone(),
+                start_line: self
+                    .get_line_from_offset(class_def.range.start().to_usize(), line_index),
+                end_line: self.get_line_from_offset(class_def.range.end().to_usize(), line_index),
+                bases,
+            });
+            self.collect_class_scopes(&class_def.body, Some(&name), line_index, scopes);
+        }
+    }
+
+    /// The innermost class containing `line`, if any.
+    pub(crate) fn enclosing_class(&self, file_path: &Path, line: usize) -> Option<String> {
+        let scopes = self.class_scopes.get(file_path)?;
+        scopes
+            .iter()
+            .rev()
+            .find(|scope| scope.start_line <= line && line <= scope.end_line)
+            .map(|scope| scope.name.clone())
+    }
+
     // ============ Module-level name collection ============
 
     /// Collect all module-level names (imports, assignments, function/class defs)
-    fn collect_module_level_names(&self, stmt: &Stmt, names: &mut HashSet<String>) {
+    pub(crate) fn collect_module_level_names(&self, stmt: &Stmt, names: &mut HashSet<String>) {
         match stmt {
             Stmt::Import(import_stmt) => {
                 for alias in &import_stmt.names {
@@ -1100,19 +1541,11 @@ impl FixtureDatabase {
                 }
             }
             Stmt::FunctionDef(func_def) => {
+                let aliases = self.fixture_decorator_aliases();
                 let is_fixture = func_def
                     .decorator_list
                     .iter()
-                    .any(decorators::is_fixture_decorator);
-                if !is_fixture {
-                    names.insert(func_def.name.to_string());
-                }
-            }
-            Stmt::AsyncFunctionDef(func_def) => {
-                let is_fixture = func_def
-                    .decorator_list
-                    .iter()
-                    .any(decorators::is_fixture_decorator);
+                    .any(|dec| decorators::is_fixture_decorator(&dec.expression, &aliases));
                 if !is_fixture {
                     names.insert(func_def.name.to_string());
                 }
@@ -1158,10 +1591,11 @@ impl FixtureDatabase {
     fn find_function_name_position(
         &self,
         content: &str,
+        line_index: &[usize],
         line: usize,
         func_name: &str,
     ) -> (usize, usize) {
-        super::string_utils::find_function_name_position(content, line, func_name)
+        super::string_utils::find_function_name_position(content, line_index, line, func_name)
     }
 
     /// Find the line number of the first yield statement in a function body.
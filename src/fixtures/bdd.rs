@@ -0,0 +1,148 @@
+//! pytest-bdd step functions: `@given` / `@when` / `@then` / `@step` request
+//! fixtures through their parameters and may provide one themselves with
+//! `target_fixture="name"`. Parameters filled from the step text (`{count:d}`
+//! in `parsers.parse(...)`, `(?P<count>...)` in `parsers.re(...)`) are step
+//! arguments rather than fixtures.
+
+use super::decorators;
+use ruff_python_ast::{Expr, ExprCall};
+use ruff_text_size::TextRange;
+use std::collections::HashSet;
+
+const STEP_DECORATORS: &[&str] = &["given", "when", "then", "step"];
+
+/// The call of a pytest-bdd step decorator (`@given(...)`, `@pytest_bdd.when(...)`).
+pub(crate) fn step_decorator(expr: &Expr) -> Option<&ExprCall> {
+    let Expr::Call(call) = expr else { return None };
+    let is_step = match call.func.as_ref() {
+        Expr::Name(name) => STEP_DECORATORS.contains(&name.id.as_str()),
+        Expr::Attribute(attr) => {
+            STEP_DECORATORS.contains(&attr.attr.as_str())
+                && matches!(attr.value.as_ref(), Expr::Name(n) if n.id.as_str() == "pytest_bdd")
+        }
+        _ => false,
+    };
+    is_step.then_some(call)
+}
+
+/// The `target_fixture="name"` of a step decorator, with the range of the name.
+pub(crate) fn target_fixture(call: &ExprCall, content: &str) -> Option<(String, TextRange)> {
+    let value = &call
+        .arguments
+        .keywords
+        .iter()
+        .find(|kw| {
+            kw.arg
+                .as_ref()
+                .is_some_and(|a| a.as_str() == "target_fixture")
+        })?
+        .value;
+    let Expr::StringLiteral(name) = value else {
+        return None;
+    };
+    let literal = content
+        .get(name.range.start().to_usize()..name.range.end().to_usize())
+        .unwrap_or("");
+    Some((
+        name.value.to_string(),
+        decorators::literal_content_range(literal, name.range),
+    ))
+}
+
+/// Names of the step arguments parsed from a step decorator's step text.
+pub(crate) fn step_arguments(call: &ExprCall) -> HashSet<String> {
+    let mut names = HashSet::new();
+    let Some(Expr::Call(parser)) = call.arguments.args.first() else {
+        // A plain string step matches literally and has no arguments
+        return names;
+    };
+    let parser_name = match parser.func.as_ref() {
+        Expr::Name(name) => name.id.as_str(),
+        Expr::Attribute(attr) => attr.attr.as_str(),
+        _ => return names,
+    };
+    let Some(Expr::StringLiteral(text)) = parser.arguments.args.first() else {
+        return names;
+    };
+
+    match parser_name {
+        "re" => {
+            // Named groups: (?P<name>...)
+            let mut rest = text.value.to_str();
+            while let Some(pos) = rest.find("(?P<") {
+                rest = &rest[pos + 4..];
+                if let Some(end) = rest.find('>') {
+                    names.insert(rest[..end].to_string());
+                    rest = &rest[end..];
+                }
+            }
+        }
+        "parse" | "cfparse" => {
+            // Fields: {name}, {name:d}; `{{` is an escaped brace
+            let mut rest = text.value.to_str();
+            while let Some(pos) = rest.find('{') {
+                rest = &rest[pos + 1..];
+                if let Some(stripped) = rest.strip_prefix('{') {
+                    rest = stripped;
+                    continue;
+                }
+                let end = rest.find(['}', ':']).unwrap_or(rest.len());
+                let name = rest[..end].trim();
+                if !name.is_empty() {
+                    names.insert(name.to_string());
+                }
+                rest = &rest[end..];
+            }
+        }
+        _ => {}
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::parse_module;
+
+    fn first_decorator(code: &str) -> Expr {
+        let parsed = parse_module(code).unwrap();
+        let ruff_python_ast::Mod::Module(module) = parsed else {
+            unreachable!()
+        };
+        match &module.body[0] {
+            ruff_python_ast::Stmt::FunctionDef(f) => f.decorator_list[0].expression.clone(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn sorted(names: HashSet<String>) -> Vec<String> {
+        let mut names: Vec<_> = names.into_iter().collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_step_decorator() {
+        assert!(step_decorator(&first_decorator("@given('x')\ndef f(): pass")).is_some());
+        assert!(step_decorator(&first_decorator("@pytest_bdd.then('x')\ndef f(): pass")).is_some());
+        assert!(step_decorator(&first_decorator("@other.when('x')\ndef f(): pass")).is_none());
+        assert!(step_decorator(&first_decorator("@given\ndef f(): pass")).is_none());
+    }
+
+    #[test]
+    fn test_step_arguments() {
+        let args = |code: &str| {
+            let decorator = first_decorator(code);
+            sorted(step_arguments(step_decorator(&decorator).unwrap()))
+        };
+        assert_eq!(
+            args("@given(parsers.parse('I have {count:d} {fruit} and {{braces}}'))\ndef f(): pass"),
+            vec!["count", "fruit"]
+        );
+        assert_eq!(
+            args("@when(parsers.re(r'I eat (?P<eat>\\d+) of (?P<total>\\d+)'))\ndef f(): pass"),
+            vec!["eat", "total"]
+        );
+        assert!(args("@then('I have {count} cucumbers')\ndef f(): pass").is_empty());
+    }
+}
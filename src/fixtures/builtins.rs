@@ -0,0 +1,251 @@
+//! Registry of fixtures that pytest itself provides.
+//!
+//! When pytest is installed in the detected virtual environment these fixtures
+//! are also picked up by scanning `_pytest/`; the registry covers projects
+//! where no environment was found and lets features recognize builtins
+//! regardless of where their definitions came from.
+
+use super::types::{FixtureDefinition, FixtureScope, TypeImportSpec};
+use std::path::Path;
+
+/// A fixture provided by pytest.
+#[derive(Debug, Clone, Copy)]
+pub struct BuiltinFixture {
+    pub name: &'static str,
+    pub scope: FixtureScope,
+    /// Module under `_pytest/` that defines the fixture (e.g. `tmpdir.py`).
+    pub module: &'static str,
+    pub return_type: &'static str,
+    /// Import needed to annotate a parameter with `return_type`.
+    pub import: Option<&'static str>,
+    pub doc: &'static str,
+}
+
+/// pytest's builtin fixtures, as listed by `pytest --fixtures`.
+pub const BUILTIN_FIXTURES: &[BuiltinFixture] = &[
+    BuiltinFixture {
+        name: "cache",
+        scope: FixtureScope::Function,
+        module: "cacheprovider.py",
+        return_type: "Cache",
+        import: Some("from pytest import Cache"),
+        doc: "Return a cache object that can persist state between testing sessions.",
+    },
+    BuiltinFixture {
+        name: "capsys",
+        scope: FixtureScope::Function,
+        module: "capture.py",
+        return_type: "CaptureFixture[str]",
+        import: Some("from pytest import CaptureFixture"),
+        doc: "Enable text capturing of writes to `sys.stdout` and `sys.stderr`.",
+    },
+    BuiltinFixture {
+        name: "capsysbinary",
+        scope: FixtureScope::Function,
+        module: "capture.py",
+        return_type: "CaptureFixture[bytes]",
+        import: Some("from pytest import CaptureFixture"),
+        doc: "Enable bytes capturing of writes to `sys.stdout` and `sys.stderr`.",
+    },
+    BuiltinFixture {
+        name: "capfd",
+        scope: FixtureScope::Function,
+        module: "capture.py",
+        return_type: "CaptureFixture[str]",
+        import: Some("from pytest import CaptureFixture"),
+        doc: "Enable text capturing of writes to file descriptors `1` and `2`.",
+    },
+    BuiltinFixture {
+        name: "capfdbinary",
+        scope: FixtureScope::Function,
+        module: "capture.py",
+        return_type: "CaptureFixture[bytes]",
+        import: Some("from pytest import CaptureFixture"),
+        doc: "Enable bytes capturing of writes to file descriptors `1` and `2`.",
+    },
+    BuiltinFixture {
+        name: "caplog",
+        scope: FixtureScope::Function,
+        module: "logging.py",
+        return_type: "LogCaptureFixture",
+        import: Some("from pytest import LogCaptureFixture"),
+        doc: "Access and control log capturing.",
+    },
+    BuiltinFixture {
+        name: "doctest_namespace",
+        scope: FixtureScope::Session,
+        module: "doctest.py",
+        return_type: "dict[str, Any]",
+        import: Some("from typing import Any"),
+        doc: "Dictionary injected into the namespace of doctests.",
+    },
+    BuiltinFixture {
+        name: "monkeypatch",
+        scope: FixtureScope::Function,
+        module: "monkeypatch.py",
+        return_type: "MonkeyPatch",
+        import: Some("from pytest import MonkeyPatch"),
+        doc: "Modify objects, dictionaries or `os.environ` for the duration of a test.",
+    },
+    BuiltinFixture {
+        name: "pytestconfig",
+        scope: FixtureScope::Session,
+        module: "fixtures.py",
+        return_type: "Config",
+        import: Some("from pytest import Config"),
+        doc: "Session-scoped fixture that returns the session's `pytest.Config` object.",
+    },
+    BuiltinFixture {
+        name: "record_property",
+        scope: FixtureScope::Function,
+        module: "junitxml.py",
+        return_type: "Callable[[str, object], None]",
+        import: Some("from collections.abc import Callable"),
+        doc: "Add extra properties to the calling test, reported in the JUnit XML.",
+    },
+    BuiltinFixture {
+        name: "record_xml_attribute",
+        scope: FixtureScope::Function,
+        module: "junitxml.py",
+        return_type: "Callable[[str, object], None]",
+        import: Some("from collections.abc import Callable"),
+        doc: "Add extra XML attributes to the tag for the calling test.",
+    },
+    BuiltinFixture {
+        name: "record_testsuite_property",
+        scope: FixtureScope::Session,
+        module: "junitxml.py",
+        return_type: "Callable[[str, object], None]",
+        import: Some("from collections.abc import Callable"),
+        doc: "Record a new `<property>` tag as child of the root `<testsuite>`.",
+    },
+    BuiltinFixture {
+        name: "recwarn",
+        scope: FixtureScope::Function,
+        module: "recwarn.py",
+        return_type: "WarningsRecorder",
+        import: Some("from pytest import WarningsRecorder"),
+        doc: "Return a `WarningsRecorder` instance that records all warnings emitted by test functions.",
+    },
+    BuiltinFixture {
+        name: "request",
+        scope: FixtureScope::Function,
+        module: "fixtures.py",
+        return_type: "FixtureRequest",
+        import: Some("from pytest import FixtureRequest"),
+        doc: "Special fixture providing information about the requesting test context.",
+    },
+    BuiltinFixture {
+        name: "tmp_path",
+        scope: FixtureScope::Function,
+        module: "tmpdir.py",
+        return_type: "Path",
+        import: Some("from pathlib import Path"),
+        doc: "Return a temporary directory path object which is unique to each test function invocation.",
+    },
+    BuiltinFixture {
+        name: "tmp_path_factory",
+        scope: FixtureScope::Session,
+        module: "tmpdir.py",
+        return_type: "TempPathFactory",
+        import: Some("from pytest import TempPathFactory"),
+        doc: "Return a `pytest.TempPathFactory` instance for the test session.",
+    },
+    BuiltinFixture {
+        name: "tmpdir",
+        scope: FixtureScope::Function,
+        module: "legacypath.py",
+        return_type: "LocalPath",
+        import: Some("from py.path import local as LocalPath"),
+        doc: "Return a temporary directory (as `legacy_path`) unique to each test function invocation.",
+    },
+    BuiltinFixture {
+        name: "tmpdir_factory",
+        scope: FixtureScope::Session,
+        module: "legacypath.py",
+        return_type: "TempdirFactory",
+        import: Some("from pytest import TempdirFactory"),
+        doc: "Return a `pytest.TempdirFactory` instance for the test session.",
+    },
+];
+
+/// Whether a definition comes from pytest itself (lives under `_pytest/`).
+pub fn is_builtin_definition(def: &FixtureDefinition) -> bool {
+    def.file_path
+        .components()
+        .any(|c| c.as_os_str() == "_pytest")
+}
+
+/// Distribution-style name of the package defining a plugin fixture
+/// (`site-packages/pytest_mock/plugin.py` → `pytest-mock`).
+pub fn plugin_name(def: &FixtureDefinition) -> String {
+    if is_builtin_definition(def) {
+        return "pytest".to_string();
+    }
+    let mut components = def.file_path.components().map(|c| c.as_os_str());
+    let package = components
+        .by_ref()
+        .find(|c| *c == "site-packages")
+        .and_then(|_| components.next())
+        .or_else(|| def.file_path.parent().and_then(|p| p.file_name()));
+    package
+        .map(|p| {
+            p.to_string_lossy()
+                .trim_end_matches(".py")
+                .replace('_', "-")
+        })
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+impl BuiltinFixture {
+    /// Synthesize a definition for this fixture.
+    ///
+    /// The path points at the `_pytest/` module that defines it, which is
+    /// only used for display and for [`is_builtin_definition`].
+    pub fn to_definition(self) -> FixtureDefinition {
+        FixtureDefinition {
+            name: self.name.to_string(),
+            file_path: Path::new("_pytest").join(self.module),
+            line: 1,
+            end_line: 1,
+            start_char: 0,
+            end_char: self.name.len(),
+            docstring: Some(self.doc.to_string()),
+            return_type: Some(self.return_type.to_string()),
+            return_type_imports: self
+                .import
+                .map(|statement| TypeImportSpec {
+                    check_name: statement
+                        .rsplit(' ')
+                        .next()
+                        .unwrap_or(statement)
+                        .to_string(),
+                    import_statement: statement.to_string(),
+                })
+                .into_iter()
+                .collect(),
+            is_third_party: true,
+            is_plugin: true,
+            scope: self.scope,
+            ..Default::default()
+        }
+    }
+}
+
+/// The synthesized definition of the builtin fixture `name`, for when
+/// scanning did not find pytest.
+pub fn builtin_definition(name: &str) -> Option<FixtureDefinition> {
+    BUILTIN_FIXTURES
+        .iter()
+        .find(|b| b.name == name)
+        .map(|b| b.to_definition())
+}
+
+/// Builtin fixtures not already present in `available`, as synthesized definitions.
+pub fn missing_builtin_definitions(available: &[FixtureDefinition]) -> Vec<FixtureDefinition> {
+    BUILTIN_FIXTURES
+        .iter()
+        .filter(|b| !available.iter().any(|def| def.name == b.name))
+        .map(|b| b.to_definition())
+        .collect()
+}
@@ -0,0 +1,495 @@
+//! Diagnostic rules run over a file and the fixes of their findings, shared
+//! by the language server's diagnostics and code actions and the `check`
+//! command.
+
+use super::builtins::plugin_name;
+use super::index_cache::stable_hash;
+use super::types::{IndirectIssue, RedundancyReason, UnusedFixtureParam};
+use super::FixtureDatabase;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+/// A problem reported by a diagnostic rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// Name of the rule (e.g. `scope-mismatch`).
+    pub rule: &'static str,
+    pub file_path: PathBuf,
+    /// 1-based line number.
+    pub line: usize,
+    /// Byte offsets of the reported range in the line.
+    pub start_char: usize,
+    pub end_char: usize,
+    pub message: String,
+    /// A rewrite resolving the problem, when one is safe to apply without
+    /// review.
+    pub fix: Option<Fix>,
+    /// Other locations explaining the problem, in order.
+    pub related: Vec<RelatedLocation>,
+}
+
+/// A location explaining a finding, e.g. a step of a dependency cycle.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelatedLocation {
+    pub file_path: PathBuf,
+    /// 1-based line number.
+    pub line: usize,
+    /// Byte offsets of the range in the line.
+    pub start_char: usize,
+    pub end_char: usize,
+    pub message: String,
+}
+
+/// A rewrite of a file resolving a finding.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+    /// What the fix does, as offered to the user.
+    pub title: String,
+    pub edits: Vec<FixEdit>,
+}
+
+/// A replacement of a byte range of a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixEdit {
+    pub start: usize,
+    pub end: usize,
+    pub new_text: String,
+}
+
+impl FixEdit {
+    fn overlaps(&self, other: &FixEdit) -> bool {
+        // Insertions at the same point conflict too
+        self.start < other.end && other.start < self.end || self.start == other.start
+    }
+}
+
+/// Apply `fixes` to `content`, in order, skipping those that overlap a fix
+/// applied before them or do not fit `content`. Returns the new content and
+/// the number of fixes applied.
+pub fn apply_fixes<'a>(content: &str, fixes: impl IntoIterator<Item = &'a Fix>) -> (String, usize) {
+    let mut edits: Vec<&FixEdit> = Vec::new();
+    let mut applied = 0;
+    for fix in fixes {
+        let fits = fix.edits.iter().all(|edit| {
+            edit.start <= edit.end
+                && content.is_char_boundary(edit.start)
+                && content.is_char_boundary(edit.end)
+        });
+        let overlaps = fix
+            .edits
+            .iter()
+            .any(|edit| edits.iter().any(|other| edit.overlaps(other)));
+        if fits && !overlaps {
+            edits.extend(&fix.edits);
+            applied += 1;
+        }
+    }
+    edits.sort_by_key(|edit| std::cmp::Reverse(edit.start));
+    let mut content = content.to_string();
+    for edit in edits {
+        content.replace_range(edit.start..edit.end, &edit.new_text);
+    }
+    (content, applied)
+}
+
+impl UnusedFixtureParam {
+    /// Drop the parameter.
+    pub fn removal_fix(&self) -> Option<Fix> {
+        let (start, end) = self.removal?;
+        Some(Fix {
+            title: format!("Remove unused parameter '{}'", self.name),
+            edits: vec![FixEdit {
+                start,
+                end,
+                new_text: String::new(),
+            }],
+        })
+    }
+
+    /// Drop the parameter and request the fixture with `usefixtures`
+    /// instead, keeping its side effects.
+    pub fn usefixtures_fix(&self) -> Option<Fix> {
+        let (offset, decorator) = self.usefixtures.clone()?;
+        let mut fix = self.removal_fix()?;
+        fix.title = format!("Request '{}' with usefixtures", self.name);
+        fix.edits.insert(
+            0,
+            FixEdit {
+                start: offset,
+                end: offset,
+                new_text: decorator,
+            },
+        );
+        Some(fix)
+    }
+}
+
+impl Finding {
+    /// Identify the finding across runs, as long as its line keeps its
+    /// contents: a hash of its rule, file (`path`, as reported), message and
+    /// the text of its line, but not of the line number.
+    pub fn fingerprint(&self, path: &str, line_text: &str) -> String {
+        let key = [self.rule, path, &self.message, line_text.trim()].join("\0");
+        format!("{:016x}", stable_hash(key.as_bytes()))
+    }
+}
+
+impl FixtureDatabase {
+    /// Analyze `files` again now that every fixture of the workspace and its
+    /// environments is indexed. The workspace scan analyzes files in
+    /// parallel, before the virtual environment, so a use of a fixture
+    /// defined in a file analyzed later is not recognized as one. Files are
+    /// re-analyzed one at a time, so none misses the definitions of another.
+    pub fn reanalyze_files(&self, files: &[PathBuf]) {
+        for file in files {
+            let Some(content) = self.get_file_content(file) else {
+                continue;
+            };
+            // Without previous contents, the whole file is analyzed again
+            self.uncache_file_content(file);
+            self.analyze_file(file.clone(), &content);
+        }
+    }
+
+    /// Run the diagnostic rules for which `enabled` (given the rule name)
+    /// returns true over a file, in rule order.
+    pub fn check_file(&self, file_path: &Path, enabled: impl Fn(&str) -> bool) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        let finding = |rule, line, start_char, end_char, message| Finding {
+            rule,
+            file_path: file_path.to_path_buf(),
+            line,
+            start_char,
+            end_char,
+            message,
+            fix: None,
+            related: Vec::new(),
+        };
+
+        if enabled("undeclared-fixture") {
+            for fixture in self.get_undeclared_fixtures(file_path) {
+                findings.push(finding(
+                    "undeclared-fixture",
+                    fixture.line,
+                    fixture.start_char,
+                    fixture.end_char,
+                    format!(
+                        "Fixture '{}' is used but not declared as a parameter",
+                        fixture.name
+                    ),
+                ));
+            }
+        }
+
+        if enabled("circular-dependency") {
+            for cycle in self.detect_fixture_cycles_in_file(file_path) {
+                // Walk the cycle: each fixture requesting the next one
+                let related = cycle
+                    .definitions
+                    .iter()
+                    .zip(cycle.cycle_path.iter().skip(1))
+                    .map(|(def, next)| RelatedLocation {
+                        file_path: def.file_path.clone(),
+                        line: def.line,
+                        start_char: def.start_char,
+                        end_char: def.end_char,
+                        message: format!("'{}' requests '{}'", def.name, next),
+                    })
+                    .collect();
+                findings.push(Finding {
+                    rule: "circular-dependency",
+                    file_path: file_path.to_path_buf(),
+                    line: cycle.fixture.line,
+                    start_char: cycle.fixture.start_char,
+                    end_char: cycle.fixture.end_char,
+                    message: format!(
+                        "Circular fixture dependency detected: {}",
+                        cycle.cycle_path.join(" → ")
+                    ),
+                    fix: None,
+                    related,
+                });
+            }
+        }
+
+        if enabled("scope-mismatch") {
+            for mismatch in self.detect_scope_mismatches_in_file(file_path) {
+                findings.push(finding(
+                    "scope-mismatch",
+                    mismatch.fixture.line,
+                    mismatch.fixture.start_char,
+                    mismatch.fixture.end_char,
+                    format!(
+                        "{}-scoped fixture '{}' depends on {}-scoped fixture '{}'",
+                        mismatch.fixture.scope.as_str(),
+                        mismatch.fixture.name,
+                        mismatch.dependency.scope.as_str(),
+                        mismatch.dependency.name
+                    ),
+                ));
+            }
+        }
+
+        if enabled("invalid-indirect") {
+            for entry in self.detect_invalid_indirect_in_file(file_path) {
+                let message = match entry.issue {
+                    IndirectIssue::NotAnArgname => format!(
+                        "'{}' is marked indirect but is not an argname of this parametrize",
+                        entry.name
+                    ),
+                    IndirectIssue::UnknownFixture => {
+                        format!("Indirect fixture '{}' does not exist", entry.name)
+                    }
+                };
+                findings.push(Finding {
+                    file_path: entry.file_path.clone(),
+                    ..finding(
+                        "invalid-indirect",
+                        entry.line,
+                        entry.start_char,
+                        entry.end_char,
+                        message,
+                    )
+                });
+            }
+        }
+
+        // Only reported under --strict-markers
+        if enabled("unknown-marker") {
+            for usage in self.detect_unknown_markers_in_file(file_path) {
+                findings.push(finding(
+                    "unknown-marker",
+                    usage.line,
+                    usage.start_char,
+                    usage.end_char,
+                    format!(
+                        "'{}' not found in `markers` configuration option",
+                        usage.name
+                    ),
+                ));
+            }
+        }
+
+        if enabled("unittest-fixture") {
+            for param in self.detect_testcase_fixture_params_in_file(file_path) {
+                findings.push(Finding {
+                    file_path: param.file_path.clone(),
+                    ..finding(
+                        "unittest-fixture",
+                        param.line,
+                        param.start_char,
+                        param.end_char,
+                        format!(
+                            "'{}' is a unittest.TestCase subclass: pytest does not inject fixtures into its test methods, so '{}' will not be provided",
+                            param.class_name, param.name
+                        ),
+                    )
+                });
+            }
+        }
+
+        if enabled("redundant-usefixtures") {
+            for entry in self.detect_redundant_usefixtures_in_file(file_path) {
+                let message = match entry.reason {
+                    RedundancyReason::Parameter => format!(
+                        "'{}' is already requested as a parameter; listing it in usefixtures has no effect",
+                        entry.name
+                    ),
+                    RedundancyReason::Duplicate => {
+                        format!("'{}' is already listed in usefixtures", entry.name)
+                    }
+                };
+                let fix = entry.removal.map(|(start, end)| Fix {
+                    title: format!("Remove '{}' from usefixtures", entry.name),
+                    edits: vec![FixEdit {
+                        start,
+                        end,
+                        new_text: String::new(),
+                    }],
+                });
+                findings.push(Finding {
+                    rule: "redundant-usefixtures",
+                    file_path: entry.file_path.clone(),
+                    line: entry.line,
+                    start_char: entry.start_char,
+                    end_char: entry.end_char,
+                    message,
+                    fix,
+                    related: Vec::new(),
+                });
+            }
+        }
+
+        // Removing a fixture may drop a side effect, so `check --fix` moves
+        // it to `usefixtures`; the editor also offers removing it
+        if enabled("unused-fixture-parameter") {
+            for param in self.detect_unused_fixture_params_in_file(file_path) {
+                findings.push(Finding {
+                    rule: "unused-fixture-parameter",
+                    file_path: param.file_path.clone(),
+                    line: param.line,
+                    start_char: param.start_char,
+                    end_char: param.end_char,
+                    message: format!(
+                        "Fixture '{}' is requested but never used in the function body",
+                        param.name
+                    ),
+                    fix: param.usefixtures_fix(),
+                    related: Vec::new(),
+                });
+            }
+        }
+
+        if enabled("unused-fixture") {
+            for def in self.detect_unused_fixtures_in_file(file_path) {
+                findings.push(Finding {
+                    rule: "unused-fixture",
+                    file_path: file_path.to_path_buf(),
+                    line: def.line,
+                    start_char: def.start_char,
+                    end_char: def.end_char,
+                    message: format!("Fixture '{}' is never requested", def.name),
+                    fix: None,
+                    related: Vec::new(),
+                });
+            }
+        }
+
+        if enabled("shadowed-plugin-fixture") {
+            for (def, shadowed) in self.detect_shadowed_plugin_fixtures_in_file(file_path) {
+                findings.push(Finding {
+                    rule: "shadowed-plugin-fixture",
+                    file_path: file_path.to_path_buf(),
+                    line: def.line,
+                    start_char: def.start_char,
+                    end_char: def.end_char,
+                    message: format!(
+                        "Fixture '{}' shadows the fixture of the same name from '{}' without requesting it",
+                        def.name,
+                        plugin_name(&shadowed)
+                    ),
+                    fix: None,
+                    related: Vec::new(),
+                });
+            }
+        }
+
+        if enabled("deprecated-yield-fixture") {
+            for deprecated in self.detect_deprecated_yield_fixtures_in_file(file_path) {
+                let fix = deprecated.replacement.map(|(start, end)| Fix {
+                    title: "Replace with 'pytest.fixture'".to_string(),
+                    edits: vec![FixEdit {
+                        start,
+                        end,
+                        new_text: "fixture".to_string(),
+                    }],
+                });
+                findings.push(Finding {
+                    rule: "deprecated-yield-fixture",
+                    file_path: deprecated.file_path.clone(),
+                    line: deprecated.line,
+                    start_char: deprecated.start_char,
+                    end_char: deprecated.end_char,
+                    message: format!(
+                        "Fixture '{}' uses the deprecated 'pytest.yield_fixture', use 'pytest.fixture' instead",
+                        deprecated.name
+                    ),
+                    fix,
+                    related: Vec::new(),
+                });
+            }
+        }
+
+        findings
+    }
+
+    /// The analyzed Python files below `root`, outside of virtual
+    /// environments, sorted by path.
+    pub fn checked_files(&self, root: &Path) -> Vec<PathBuf> {
+        let site_packages = self.site_packages_paths.lock().unwrap().clone();
+        let files: BTreeSet<PathBuf> = self
+            .file_cache
+            .iter()
+            .map(|entry| entry.key().clone())
+            .chain(self.imports.iter().map(|entry| entry.key().clone()))
+            .chain(
+                self.file_definitions
+                    .iter()
+                    .map(|entry| entry.key().clone()),
+            )
+            .chain(self.usages.iter().map(|entry| entry.key().clone()))
+            .filter(|file| file.starts_with(root))
+            .filter(|file| !site_packages.iter().any(|sp| file.starts_with(sp)))
+            .collect();
+        files.into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_check_files() {
+        let tmp = tempdir().unwrap();
+        let root = tmp.path().canonicalize().unwrap();
+        fs::write(
+            root.join("conftest.py"),
+            "import pytest\n\n@pytest.fixture\ndef db():\n    pass\n\n@pytest.fixture(scope=\"session\")\ndef app(db):\n    pass\n",
+        )
+        .unwrap();
+        fs::write(root.join("test_a.py"), "def test_a():\n    result = db\n").unwrap();
+
+        let db = FixtureDatabase::new();
+        db.scan_workspace(&root);
+        let files = db.checked_files(&root);
+        assert_eq!(
+            files,
+            vec![root.join("conftest.py"), root.join("test_a.py")]
+        );
+        db.reanalyze_files(&files);
+
+        let check = |enabled: fn(&str) -> bool| -> Vec<Finding> {
+            db.checked_files(&root)
+                .iter()
+                .flat_map(|file| db.check_file(file, enabled))
+                .collect()
+        };
+        let findings = check(|_| true);
+        let rules: Vec<(&str, PathBuf, usize)> = findings
+            .iter()
+            .map(|f| (f.rule, f.file_path.clone(), f.line))
+            .collect();
+        assert_eq!(
+            rules,
+            vec![
+                ("scope-mismatch", root.join("conftest.py"), 8),
+                ("unused-fixture", root.join("conftest.py"), 8),
+                ("undeclared-fixture", root.join("test_a.py"), 2),
+            ]
+        );
+        assert_eq!(
+            findings[2].message,
+            "Fixture 'db' is used but not declared as a parameter"
+        );
+        assert_eq!((findings[2].start_char, findings[2].end_char), (13, 15));
+
+        // Fingerprints ignore where the line moved
+        let fingerprint = findings[2].fingerprint("test_a.py", "    result = db");
+        let moved = Finding {
+            line: 12,
+            ..findings[2].clone()
+        };
+        assert_eq!(moved.fingerprint("test_a.py", "result = db"), fingerprint);
+        assert_ne!(
+            findings[2].fingerprint("test_a.py", "result = db()"),
+            fingerprint
+        );
+
+        // Disabled rules are not run
+        let findings = check(|rule| rule != "scope-mismatch");
+        assert_eq!(findings.len(), 2);
+    }
+}
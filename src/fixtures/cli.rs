@@ -1,6 +1,7 @@
 //! CLI-related methods for fixture display and tree printing.
 
-use super::types::FixtureDefinition;
+use super::builtins;
+use super::types::{CandidateSource, FixtureDefinition, FixtureOrigin, VisibleFixture};
 use super::FixtureDatabase;
 use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::path::{Path, PathBuf};
@@ -19,7 +20,8 @@ impl FixtureDatabase {
         }
 
         // Cache for resolved definitions
-        let mut resolution_cache: HashMap<(PathBuf, String), Option<PathBuf>> = HashMap::new();
+        let mut resolution_cache: HashMap<(PathBuf, Option<String>, String), Option<PathBuf>> =
+            HashMap::new();
 
         // Pre-compute fixture definition lines per file
         let mut fixture_def_lines: HashMap<PathBuf, HashMap<usize, FixtureDefinition>> =
@@ -52,10 +54,16 @@ impl FixtureDatabase {
                     self.find_closest_definition_excluding(
                         file_path,
                         &usage.name,
+                        usage.line,
                         fixture_def_at_line.as_ref(),
                     )
                 } else {
-                    let cache_key = (file_path.clone(), usage.name.clone());
+                    // Resolution depends on the enclosing test class, if any
+                    let cache_key = (
+                        file_path.clone(),
+                        self.enclosing_class(file_path, usage.line),
+                        usage.name.clone(),
+                    );
                     if let Some(cached) = resolution_cache.get(&cache_key) {
                         cached.as_ref().and_then(|def_path| {
                             self.definitions.get(&usage.name).and_then(|defs| {
@@ -63,7 +71,7 @@ impl FixtureDatabase {
                             })
                         })
                     } else {
-                        let def = self.find_closest_definition(file_path, &usage.name);
+                        let def = self.find_closest_definition(file_path, &usage.name, usage.line);
                         resolution_cache
                             .insert(cache_key, def.as_ref().map(|d| d.file_path.clone()));
                         def
@@ -466,32 +474,32 @@ impl FixtureDatabase {
 
     /// Get all unused fixtures (fixtures with zero usages).
     /// Returns a vector of (file_path, fixture_name) tuples sorted by path then name.
-    /// Excludes third-party fixtures from site-packages.
+    /// Excludes fixtures used implicitly: third-party, autouse and those
+    /// overriding a fixture of pytest or a plugin.
     pub fn get_unused_fixtures(&self) -> Vec<(PathBuf, String)> {
         let definition_usage_counts = self.compute_definition_usage_counts();
         let mut unused: Vec<(PathBuf, String)> = Vec::new();
 
-        for entry in self.definitions.iter() {
-            let fixture_name = entry.key();
-            for def in entry.value().iter() {
-                // Skip third-party fixtures
-                if def.is_third_party {
-                    continue;
-                }
+        // Snapshot first: is_implicitly_used reads `definitions` again, and
+        // holding an iterator guard across that lookup can deadlock.
+        let all_definitions: Vec<FixtureDefinition> = self
+            .definitions
+            .iter()
+            .flat_map(|entry| entry.value().clone())
+            .collect();
 
-                // Skip autouse fixtures (they're used implicitly)
-                if def.autouse {
-                    continue;
-                }
+        for def in &all_definitions {
+            if self.is_implicitly_used(def) {
+                continue;
+            }
 
-                let usage_count = definition_usage_counts
-                    .get(&(def.file_path.clone(), fixture_name.clone()))
-                    .copied()
-                    .unwrap_or(0);
+            let usage_count = definition_usage_counts
+                .get(&(def.file_path.clone(), def.name.clone()))
+                .copied()
+                .unwrap_or(0);
 
-                if usage_count == 0 {
-                    unused.push((def.file_path.clone(), fixture_name.clone()));
-                }
+            if usage_count == 0 {
+                unused.push((def.file_path.clone(), def.name.clone()));
             }
         }
 
@@ -499,4 +507,109 @@ impl FixtureDatabase {
         unused.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
         unused
     }
+
+    /// List the fixtures visible from a test module, like `pytest --fixtures`:
+    /// pytest's builtins, plugin, conftest and module fixtures, each with the
+    /// definitions it overrides. For a directory, lists the fixtures visible
+    /// to the test modules directly inside it. Sorted by name.
+    pub fn visible_fixtures(&self, path: &Path) -> Vec<VisibleFixture> {
+        let file_path = self.lookup_path(path);
+        let available = self.get_available_fixtures(&file_path);
+        let mut visible: Vec<VisibleFixture> = available
+            .iter()
+            .cloned()
+            .chain(builtins::missing_builtin_definitions(&available))
+            .map(|definition| {
+                let overrides = self.overridden_definitions(&file_path, &definition);
+                let origin = if builtins::is_builtin_definition(&definition) {
+                    FixtureOrigin::Builtin
+                } else {
+                    match self.definition_source(&file_path, &definition) {
+                        CandidateSource::Conftest { .. }
+                        | CandidateSource::ImportedByConftest { .. } => FixtureOrigin::Conftest,
+                        CandidateSource::Plugin | CandidateSource::ThirdParty => {
+                            FixtureOrigin::Plugin
+                        }
+                        // Defined in, or imported into, the module itself
+                        CandidateSource::SameFile | CandidateSource::NotVisible => {
+                            FixtureOrigin::Module
+                        }
+                    }
+                };
+                VisibleFixture {
+                    definition,
+                    origin,
+                    overrides,
+                }
+            })
+            .collect();
+        visible.sort_by(|a, b| a.definition.name.cmp(&b.definition.name));
+        visible
+    }
+
+    /// The module fixtures are looked up from for a CLI path: the file
+    /// itself, or a test module directly inside a directory.
+    pub(crate) fn lookup_path(&self, path: &Path) -> PathBuf {
+        if path.is_dir() {
+            // Any module name works: only its directory matters for lookup
+            self.get_canonical_path(path.to_path_buf())
+                .join("test___fixtures__.py")
+        } else {
+            self.get_canonical_path(path.to_path_buf())
+        }
+    }
+
+    /// The other definitions of `winner`'s name visible from `file_path`,
+    /// closest first, ending with pytest's own when it is overridden.
+    fn overridden_definitions(
+        &self,
+        file_path: &Path,
+        winner: &FixtureDefinition,
+    ) -> Vec<FixtureDefinition> {
+        let is_winner =
+            |def: &FixtureDefinition| def.file_path == winner.file_path && def.line == winner.line;
+        let mut overridden: Vec<(usize, FixtureDefinition)> = self
+            .definitions
+            .get(&winner.name)
+            .map(|defs| {
+                defs.iter()
+                    .filter(|def| def.class_name.is_none() && !is_winner(def))
+                    .filter_map(|def| {
+                        let rank = match self.definition_source(file_path, def) {
+                            CandidateSource::SameFile => 0,
+                            CandidateSource::Conftest { distance } => 1 + distance * 2,
+                            CandidateSource::ImportedByConftest { distance, .. } => {
+                                2 + distance * 2
+                            }
+                            CandidateSource::Plugin => usize::MAX - 2,
+                            CandidateSource::ThirdParty if builtins::is_builtin_definition(def) => {
+                                usize::MAX
+                            }
+                            CandidateSource::ThirdParty => usize::MAX - 1,
+                            CandidateSource::NotVisible => return None,
+                        };
+                        Some((rank, def.clone()))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        // Definitions in the same file: the last one wins
+        overridden.sort_by_key(|(rank, def)| (*rank, std::cmp::Reverse(def.line)));
+
+        // pytest itself may not be installed in the environment that was scanned
+        let builtin_overridden = !builtins::is_builtin_definition(winner)
+            && !overridden
+                .iter()
+                .any(|(_, def)| builtins::is_builtin_definition(def));
+        overridden
+            .into_iter()
+            .map(|(_, def)| def)
+            .chain(
+                builtins::BUILTIN_FIXTURES
+                    .iter()
+                    .filter(|b| builtin_overridden && b.name == winner.name)
+                    .map(|b| b.to_definition()),
+            )
+            .collect()
+    }
 }
@@ -0,0 +1,247 @@
+//! Conda and mamba environment discovery.
+//!
+//! A conda environment is a prefix holding a `conda-meta` directory. The
+//! activated one is `$CONDA_PREFIX`; otherwise a project's
+//! `environment.yml` names its environment, either by `prefix:` or by
+//! `name:`, looked up in the conda installation's `envs` directories.
+//!
+//! Packages live in `lib/pythonX.Y/site-packages` on Unix, as in a venv, but
+//! in `Lib/site-packages` on Windows, with `python.exe` at the root of the
+//! prefix rather than in `Scripts`.
+
+use super::env_path;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+/// Conda installations commonly found in the home directory.
+const HOME_INSTALLATIONS: &[&str] = &[
+    "miniconda3",
+    "anaconda3",
+    "miniforge3",
+    "mambaforge",
+    "micromamba",
+    ".conda",
+];
+
+/// Whether `path` is the prefix of a conda environment.
+pub(crate) fn is_conda_env(path: &Path) -> bool {
+    path.join("conda-meta").is_dir()
+}
+
+/// Whether the conda environment at `prefix` is a base installation rather
+/// than an environment created in it.
+fn is_base_env(prefix: &Path) -> bool {
+    prefix.join("condabin").is_dir() || prefix.join("envs").is_dir()
+}
+
+/// The `name:` and `prefix:` of an `environment.yml` file.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct EnvironmentFile {
+    pub(crate) name: Option<String>,
+    pub(crate) prefix: Option<PathBuf>,
+}
+
+/// Read the top-level `name:` and `prefix:` keys of an `environment.yml`.
+/// Only those scalar keys are needed, so the YAML is not fully parsed.
+pub(crate) fn parse_environment_file(content: &str) -> EnvironmentFile {
+    let mut environment = EnvironmentFile::default();
+    for line in content.lines() {
+        // Nested keys are indented
+        if line.starts_with([' ', '\t', '-', '#']) {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = match value.find(" #") {
+            Some(comment) => &value[..comment],
+            None => value,
+        };
+        let value = value.trim().trim_matches(['"', '\'']);
+        if value.is_empty() {
+            continue;
+        }
+        match key.trim() {
+            "name" => environment.name = Some(value.to_string()),
+            "prefix" => environment.prefix = Some(PathBuf::from(value)),
+            _ => {}
+        }
+    }
+    environment
+}
+
+/// The directories conda creates named environments in: `$CONDA_ENVS_PATH`
+/// (or `$CONDA_ENVS_DIRS`), the `envs` directory of the conda installation
+/// running (from `$CONDA_EXE`, `$MAMBA_ROOT_PREFIX` or `$CONDA_PREFIX`), and
+/// those of the usual installations in the home directory.
+pub(crate) fn conda_envs_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    for var in ["CONDA_ENVS_PATH", "CONDA_ENVS_DIRS"] {
+        if let Some(paths) = std::env::var_os(var) {
+            dirs.extend(std::env::split_paths(&paths));
+        }
+    }
+
+    let mut roots = Vec::new();
+    // `<root>/bin/conda`, or `<root>\Scripts\conda.exe` on Windows
+    if let Some(conda_exe) = env_path("CONDA_EXE") {
+        roots.extend(
+            conda_exe
+                .parent()
+                .and_then(Path::parent)
+                .map(Path::to_path_buf),
+        );
+    }
+    roots.extend(env_path("MAMBA_ROOT_PREFIX"));
+    if let Some(prefix) = env_path("CONDA_PREFIX") {
+        if is_base_env(&prefix) {
+            roots.push(prefix);
+        } else if let Some(envs_dir) = prefix.parent() {
+            dirs.push(envs_dir.to_path_buf());
+        }
+    }
+    if let Some(home) = env_path("HOME").or_else(|| env_path("USERPROFILE")) {
+        roots.extend(HOME_INSTALLATIONS.iter().map(|name| home.join(name)));
+    }
+    dirs.extend(roots.into_iter().map(|root| root.join("envs")));
+
+    let mut seen = std::collections::HashSet::new();
+    dirs.retain(|dir| seen.insert(dir.clone()));
+    dirs
+}
+
+/// The conda environment named by the `environment.yml` (or
+/// `environment.yaml`) in `root_path`, looking named environments up in
+/// `envs_dirs`.
+pub(crate) fn find_environment_file_env(
+    root_path: &Path,
+    envs_dirs: &[PathBuf],
+) -> Option<PathBuf> {
+    let (file, content) = ["environment.yml", "environment.yaml"]
+        .iter()
+        .map(|name| root_path.join(name))
+        .find_map(|file| std::fs::read_to_string(&file).ok().map(|c| (file, c)))?;
+    let environment = parse_environment_file(&content);
+    debug!("Read {:?}: {:?}", file, environment);
+
+    if let Some(prefix) = environment.prefix {
+        let prefix = root_path.join(prefix);
+        if is_conda_env(&prefix) {
+            return Some(prefix);
+        }
+        debug!("  No conda environment at prefix {:?}", prefix);
+    }
+    let name = environment.name?;
+    let found = envs_dirs
+        .iter()
+        .map(|dir| dir.join(&name))
+        .find(|prefix| is_conda_env(prefix));
+    if found.is_none() {
+        warn!(
+            "Conda environment {:?} of {:?} is not created; run `conda env create`",
+            name, file
+        );
+    }
+    found
+}
+
+/// Locate the conda environment of a workspace: the activated one unless it
+/// is the base installation, then the one named by the project's
+/// `environment.yml`, then the activated base installation.
+pub(crate) fn find_conda_env(root_path: &Path) -> Option<PathBuf> {
+    let active = env_path("CONDA_PREFIX").filter(|prefix| is_conda_env(prefix));
+    if let Some(prefix) = active.as_ref().filter(|prefix| !is_base_env(prefix)) {
+        info!("Using CONDA_PREFIX: {:?}", prefix);
+        return Some(prefix.clone());
+    }
+    if let Some(prefix) = find_environment_file_env(root_path, &conda_envs_dirs()) {
+        info!("Using conda environment of environment.yml: {:?}", prefix);
+        return Some(prefix);
+    }
+    if let Some(prefix) = active {
+        info!("Using base conda environment: {:?}", prefix);
+        return Some(prefix);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_environment_file() {
+        let content = r#"# Project environment
+name: "my-project"  # the name
+channels:
+  - conda-forge
+dependencies:
+  - python=3.12
+  - pytest
+  - pip:
+      - name: not-the-env
+"#;
+        assert_eq!(
+            parse_environment_file(content),
+            EnvironmentFile {
+                name: Some("my-project".to_string()),
+                prefix: None,
+            }
+        );
+
+        let content = "prefix: /opt/envs/proj\nname:\n";
+        assert_eq!(
+            parse_environment_file(content),
+            EnvironmentFile {
+                name: None,
+                prefix: Some(PathBuf::from("/opt/envs/proj")),
+            }
+        );
+    }
+
+    #[test]
+    fn test_find_environment_file_env_by_name() {
+        let root = tempdir().unwrap();
+        let envs = tempdir().unwrap();
+        fs::write(root.path().join("environment.yml"), "name: proj\n").unwrap();
+
+        // Not created yet
+        let envs_dirs = vec![envs.path().to_path_buf()];
+        assert_eq!(find_environment_file_env(root.path(), &envs_dirs), None);
+
+        let prefix = envs.path().join("proj");
+        fs::create_dir_all(prefix.join("conda-meta")).unwrap();
+        assert_eq!(
+            find_environment_file_env(root.path(), &envs_dirs),
+            Some(prefix)
+        );
+    }
+
+    #[test]
+    fn test_find_environment_file_env_by_prefix() {
+        let root = tempdir().unwrap();
+        fs::write(
+            root.path().join("environment.yaml"),
+            "name: proj\nprefix: ./.conda-env\n",
+        )
+        .unwrap();
+        let prefix = root.path().join("./.conda-env");
+        fs::create_dir_all(prefix.join("conda-meta")).unwrap();
+
+        assert_eq!(find_environment_file_env(root.path(), &[]), Some(prefix));
+    }
+
+    #[test]
+    fn test_is_base_env() {
+        let base = tempdir().unwrap();
+        fs::create_dir_all(base.path().join("conda-meta")).unwrap();
+        fs::create_dir_all(base.path().join("envs")).unwrap();
+        let env = base.path().join("envs").join("proj");
+        fs::create_dir_all(env.join("conda-meta")).unwrap();
+
+        assert!(is_conda_env(base.path()) && is_base_env(base.path()));
+        assert!(is_conda_env(&env) && !is_base_env(&env));
+    }
+}
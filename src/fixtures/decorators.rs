@@ -3,42 +3,93 @@
 //! This module contains shared logic for recognizing and extracting information
 //! from pytest decorators like @pytest.fixture, @pytest.mark.usefixtures, etc.
 
-use rustpython_parser::ast::Expr;
-
-/// Check if an expression is a @pytest.fixture or @pytest_asyncio.fixture decorator
-pub fn is_fixture_decorator(expr: &Expr) -> bool {
+use ruff_python_ast::Expr;
+use ruff_text_size::{Ranged, TextRange};
+
+/// Check if an expression is a @pytest.fixture, @pytest_asyncio.fixture,
+/// @pytest_cases.fixture or deprecated @pytest.yield_fixture decorator, or
+/// one of the configured `aliases` wrapping `pytest.fixture` (see
+/// [`is_decorator_alias`]).
+pub fn is_fixture_decorator(expr: &Expr, aliases: &[String]) -> bool {
     match expr {
-        Expr::Name(name) => name.id.as_str() == "fixture",
+        Expr::Name(name) => {
+            matches!(name.id.as_str(), "fixture" | "yield_fixture")
+                || is_decorator_alias(expr, aliases)
+        }
         Expr::Attribute(attr) => {
-            if let Expr::Name(value) = &*attr.value {
-                (value.id.as_str() == "pytest" || value.id.as_str() == "pytest_asyncio")
-                    && attr.attr.as_str() == "fixture"
+            let is_pytest_fixture = if let Expr::Name(value) = &*attr.value {
+                matches!(
+                    value.id.as_str(),
+                    "pytest" | "pytest_asyncio" | "pytest_cases"
+                ) && attr.attr.as_str() == "fixture"
             } else {
                 false
-            }
+            };
+            is_pytest_fixture
+                || deprecated_yield_fixture(expr).is_some_and(|(_, qualified)| qualified)
+                || is_decorator_alias(expr, aliases)
         }
-        Expr::Call(call) => is_fixture_decorator(&call.func),
+        Expr::Call(call) => is_fixture_decorator(&call.func, aliases),
         _ => false,
     }
 }
 
+/// The range of `yield_fixture` in a deprecated `@pytest.yield_fixture`
+/// decorator (called or not), and whether it is qualified with `pytest.`
+/// rather than imported from pytest.
+pub fn deprecated_yield_fixture(expr: &Expr) -> Option<(TextRange, bool)> {
+    match expr {
+        Expr::Name(name) if name.id.as_str() == "yield_fixture" => Some((name.range, false)),
+        Expr::Attribute(attr) if attr.attr.as_str() == "yield_fixture" => {
+            matches!(&*attr.value, Expr::Name(value) if value.id.as_str() == "pytest")
+                .then_some((attr.attr.range, true))
+        }
+        Expr::Call(call) => deprecated_yield_fixture(&call.func),
+        _ => None,
+    }
+}
+
+/// The dotted name of a `Name` or `Attribute` chain (`helpers.db_fixture`).
+fn dotted_name(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Name(name) => Some(name.id.to_string()),
+        Expr::Attribute(attr) => Some(format!("{}.{}", dotted_name(&attr.value)?, attr.attr)),
+        _ => None,
+    }
+}
+
+/// Whether `expr` names one of the `aliases`. A decorator and an alias match
+/// when either dotted path ends with the other at a `.`, so the alias
+/// `myproject.testing.db_fixture` matches `@db_fixture` once imported, and
+/// the alias `db_fixture` matches `@testing.db_fixture`.
+fn is_decorator_alias(expr: &Expr, aliases: &[String]) -> bool {
+    if aliases.is_empty() {
+        return false;
+    }
+    let Some(name) = dotted_name(expr) else {
+        return false;
+    };
+    let ends_with_path = |path: &str, suffix: &str| {
+        path.strip_suffix(suffix)
+            .is_some_and(|rest| rest.is_empty() || rest.ends_with('.'))
+    };
+    aliases
+        .iter()
+        .any(|alias| ends_with_path(alias, &name) || ends_with_path(&name, alias))
+}
+
 /// Extracts the fixture name from a decorator's `name=` argument if present.
-pub fn extract_fixture_name_from_decorator(expr: &Expr) -> Option<String> {
+pub fn extract_fixture_name_from_decorator(expr: &Expr, aliases: &[String]) -> Option<String> {
     let Expr::Call(call) = expr else { return None };
-    if !is_fixture_decorator(&call.func) {
+    if !is_fixture_decorator(&call.func, aliases) {
         return None;
     }
 
-    call.keywords
+    call.arguments
+        .keywords
         .iter()
         .filter(|kw| kw.arg.as_ref().is_some_and(|a| a.as_str() == "name"))
-        .find_map(|kw| match &kw.value {
-            Expr::Constant(c) => match &c.value {
-                rustpython_parser::ast::Constant::Str(s) => Some(s.to_string()),
-                _ => None,
-            },
-            _ => None,
-        })
+        .find_map(|kw| string_constant(&kw.value))
 }
 
 /// Checks if an expression is a pytest.mark.* decorator with the given marker name.
@@ -75,11 +126,11 @@ pub fn is_usefixtures_decorator(expr: &Expr) -> bool {
 ///
 /// `literal` is the literal's exact source text and `range` its full range;
 /// falls back to the full range when the text doesn't look like a string.
-fn literal_content_range(
+pub(crate) fn literal_content_range(
     literal: &str,
-    range: rustpython_parser::text_size::TextRange,
-) -> rustpython_parser::text_size::TextRange {
-    use rustpython_parser::text_size::{TextRange, TextSize};
+    range: ruff_text_size::TextRange,
+) -> ruff_text_size::TextRange {
+    use ruff_text_size::{TextRange, TextSize};
 
     let bytes = literal.as_bytes();
     let mut prefix = 0;
@@ -123,7 +174,7 @@ fn literal_content_range(
 pub fn extract_usefixtures_names(
     expr: &Expr,
     content: &str,
-) -> Vec<(String, rustpython_parser::text_size::TextRange)> {
+) -> Vec<(String, ruff_text_size::TextRange)> {
     let Expr::Call(call) = expr else {
         return vec![];
     };
@@ -131,18 +182,17 @@ pub fn extract_usefixtures_names(
         return vec![];
     }
 
-    call.args
+    call.arguments
+        .args
         .iter()
         .filter_map(|arg| {
-            if let Expr::Constant(c) = arg {
-                if let rustpython_parser::ast::Constant::Str(s) = &c.value {
-                    let literal = content
-                        .get(c.range.start().to_usize()..c.range.end().to_usize())
-                        .unwrap_or("");
-                    return Some((s.to_string(), literal_content_range(literal, c.range)));
-                }
-            }
-            None
+            let Expr::StringLiteral(s) = arg else {
+                return None;
+            };
+            let literal = content
+                .get(s.range.start().to_usize()..s.range.end().to_usize())
+                .unwrap_or("");
+            Some((s.value.to_string(), literal_content_range(literal, s.range)))
         })
         .collect()
 }
@@ -156,7 +206,7 @@ pub fn extract_usefixtures_names(
 pub fn extract_usefixtures_from_expr(
     expr: &Expr,
     content: &str,
-) -> Vec<(String, rustpython_parser::text_size::TextRange)> {
+) -> Vec<(String, ruff_text_size::TextRange)> {
     match expr {
         // Direct call: pytest.mark.usefixtures("fix1", "fix2")
         Expr::Call(_) => extract_usefixtures_names(expr, content),
@@ -176,9 +226,29 @@ pub fn extract_usefixtures_from_expr(
     }
 }
 
-/// Checks if an expression is a pytest.mark.parametrize decorator.
+/// Checks if an expression is a `pytest_cases.<name>` decorator, also matching
+/// the bare name as imported with `from pytest_cases import <name>`.
+fn is_pytest_cases_decorator(expr: &Expr, name: &str) -> bool {
+    match expr {
+        Expr::Call(call) => is_pytest_cases_decorator(&call.func, name),
+        Expr::Name(n) => n.id.as_str() == name,
+        Expr::Attribute(attr) => {
+            attr.attr.as_str() == name
+                && matches!(&*attr.value, Expr::Name(n) if n.id.as_str() == "pytest_cases")
+        }
+        _ => false,
+    }
+}
+
+/// Checks if an expression is a pytest.mark.parametrize decorator, or pytest-cases'
+/// `@parametrize` which takes the same arguments.
 pub fn is_parametrize_decorator(expr: &Expr) -> bool {
-    is_pytest_mark_decorator(expr, "parametrize")
+    is_pytest_mark_decorator(expr, "parametrize") || is_pytest_cases_decorator(expr, "parametrize")
+}
+
+/// Checks if an expression is pytest-cases' `@parametrize_with_cases(argnames, cases=...)`.
+pub fn is_parametrize_with_cases_decorator(expr: &Expr) -> bool {
+    is_pytest_cases_decorator(expr, "parametrize_with_cases")
 }
 
 /// Returns true if `name` is a plain Python identifier (the only thing a parametrize argname can
@@ -200,9 +270,9 @@ fn is_plain_identifier(name: &str) -> bool {
 /// (`'`, `"`, or their triple variants).
 fn split_argnames_from_source(
     literal: &str,
-    range_start: rustpython_parser::text_size::TextSize,
-) -> Vec<(String, rustpython_parser::text_size::TextRange)> {
-    use rustpython_parser::text_size::{TextRange, TextSize};
+    range_start: ruff_text_size::TextSize,
+) -> Vec<(String, ruff_text_size::TextRange)> {
+    use ruff_text_size::{TextRange, TextSize};
 
     let bytes = literal.as_bytes();
     let mut prefix = 0;
@@ -252,6 +322,8 @@ fn split_argnames_from_source(
 /// Extracts the declared parameter names from a `@pytest.mark.parametrize(...)` decorator, each
 /// paired with the precise [`TextRange`] of its name token.
 ///
+/// pytest-cases' `@parametrize_with_cases(...)` names its argnames the same way.
+///
 /// Handles every argnames form pytest accepts: a single name, a comma-separated string
 /// (`"a,b"` / `"a, b"`), a list or tuple of strings, and `argnames=` passed as a keyword.
 /// `content` is the full source of the file the decorator came from, used to read each string
@@ -259,16 +331,17 @@ fn split_argnames_from_source(
 pub fn extract_parametrize_argnames(
     expr: &Expr,
     content: &str,
-) -> Vec<(String, rustpython_parser::text_size::TextRange)> {
+) -> Vec<(String, ruff_text_size::TextRange)> {
     let Expr::Call(call) = expr else {
         return vec![];
     };
-    if !is_parametrize_decorator(&call.func) {
+    if !is_parametrize_decorator(&call.func) && !is_parametrize_with_cases_decorator(&call.func) {
         return vec![];
     }
 
-    let argnames = call.args.first().or_else(|| {
-        call.keywords
+    let argnames = call.arguments.args.first().or_else(|| {
+        call.arguments
+            .keywords
             .iter()
             .find(|kw| kw.arg.as_ref().is_some_and(|a| a.as_str() == "argnames"))
             .map(|kw| &kw.value)
@@ -279,7 +352,7 @@ pub fn extract_parametrize_argnames(
     };
 
     match argnames {
-        Expr::Constant(_) => parametrize_name_element_ranges(argnames, content),
+        Expr::StringLiteral(_) => parametrize_name_element_ranges(argnames, content),
         Expr::List(list) => list
             .elts
             .iter()
@@ -298,13 +371,10 @@ pub fn extract_parametrize_argnames(
 fn parametrize_name_element_ranges(
     elt: &Expr,
     content: &str,
-) -> Vec<(String, rustpython_parser::text_size::TextRange)> {
-    let Expr::Constant(c) = elt else {
+) -> Vec<(String, ruff_text_size::TextRange)> {
+    let Expr::StringLiteral(c) = elt else {
         return vec![];
     };
-    if !matches!(c.value, rustpython_parser::ast::Constant::Str(_)) {
-        return vec![];
-    }
     let start = c.range.start().to_usize();
     let end = c.range.end().to_usize();
     let Some(literal) = content.get(start..end) else {
@@ -323,56 +393,83 @@ pub fn extract_parametrize_indirect_names(
 ) -> std::collections::HashSet<String> {
     use std::collections::HashSet;
 
-    let Expr::Call(call) = expr else {
+    let Some(indirect) = parametrize_indirect_arg(expr) else {
         return HashSet::new();
     };
+
+    match indirect {
+        Expr::BooleanLiteral(b) if b.value => argnames.iter().cloned().collect(),
+        Expr::List(list) => collect_string_constants(&list.elts),
+        Expr::Tuple(tuple) => collect_string_constants(&tuple.elts),
+        _ => HashSet::new(),
+    }
+}
+
+/// Returns the `indirect` argument of a `@pytest.mark.parametrize(...)` decorator, passed either
+/// by keyword or as the third positional argument.
+fn parametrize_indirect_arg(expr: &Expr) -> Option<&Expr> {
+    let Expr::Call(call) = expr else {
+        return None;
+    };
     if !is_parametrize_decorator(&call.func) {
-        return HashSet::new();
+        return None;
     }
 
-    let indirect = call
+    call.arguments
         .keywords
         .iter()
         .find(|kw| kw.arg.as_ref().is_some_and(|a| a.as_str() == "indirect"))
         .map(|kw| &kw.value)
-        .or_else(|| call.args.get(2));
+        .or_else(|| call.arguments.args.get(2))
+}
 
-    let Some(indirect) = indirect else {
-        return HashSet::new();
+/// Extracts the names listed explicitly in `indirect=[...]` / `indirect=(...)`, each paired with
+/// the range of its string literal's content.
+///
+/// Unlike [`extract_parametrize_indirect_names`], the entries are returned as written — including
+/// names that are not argnames of the decorator — so callers can validate them. `indirect=True`
+/// yields nothing since it lists no names of its own.
+pub fn extract_parametrize_indirect_list(
+    expr: &Expr,
+    content: &str,
+) -> Vec<(String, ruff_text_size::TextRange)> {
+    let elts = match parametrize_indirect_arg(expr) {
+        Some(Expr::List(list)) => &list.elts,
+        Some(Expr::Tuple(tuple)) => &tuple.elts,
+        _ => return vec![],
     };
 
-    match indirect {
-        Expr::Constant(c) if matches!(c.value, rustpython_parser::ast::Constant::Bool(true)) => {
-            argnames.iter().cloned().collect()
-        }
-        Expr::List(list) => collect_string_constants(&list.elts),
-        Expr::Tuple(tuple) => collect_string_constants(&tuple.elts),
-        _ => HashSet::new(),
-    }
-}
-
-fn collect_string_constants(elts: &[Expr]) -> std::collections::HashSet<String> {
     elts.iter()
-        .filter_map(|elt| match elt {
-            Expr::Constant(c) => match &c.value {
-                rustpython_parser::ast::Constant::Str(s) => Some(s.to_string()),
-                _ => None,
-            },
-            _ => None,
+        .filter_map(|elt| {
+            let Expr::StringLiteral(s) = elt else {
+                return None;
+            };
+            let literal = content
+                .get(s.range.start().to_usize()..s.range.end().to_usize())
+                .unwrap_or("");
+            Some((s.value.to_string(), literal_content_range(literal, s.range)))
         })
         .collect()
 }
 
+fn collect_string_constants(elts: &[Expr]) -> std::collections::HashSet<String> {
+    elts.iter().filter_map(string_constant).collect()
+}
+
 /// Extracts fixture names from @pytest.mark.parametrize when they are marked
 /// indirect (`indirect=True` or `indirect=[names]`, keyword or positional).
 ///
 /// Each name is paired with the precise range of its token inside the
 /// argnames literal (via [`extract_parametrize_argnames`]), so usages point
 /// at the parameter name itself rather than a whole string literal.
+///
+/// Entries of an explicit `indirect=[...]` list that name an argname are
+/// references too and are returned with the range of their own literal.
+/// Entries that match no argname are left to the invalid-indirect diagnostic.
 pub fn extract_parametrize_indirect_fixtures(
     expr: &Expr,
     content: &str,
-) -> Vec<(String, rustpython_parser::text_size::TextRange)> {
+) -> Vec<(String, ruff_text_size::TextRange)> {
     let argnames = extract_parametrize_argnames(expr, content);
     if argnames.is_empty() {
         return vec![];
@@ -380,43 +477,364 @@ pub fn extract_parametrize_indirect_fixtures(
 
     let names: Vec<String> = argnames.iter().map(|(name, _)| name.clone()).collect();
     let indirect = extract_parametrize_indirect_names(expr, &names);
+    let list_entries: Vec<_> = extract_parametrize_indirect_list(expr, content)
+        .into_iter()
+        .filter(|(name, _)| names.contains(name))
+        .collect();
 
     argnames
         .into_iter()
         .filter(|(name, _)| indirect.contains(name))
+        .chain(list_entries)
         .collect()
 }
 
+/// Functions whose first string argument names a fixture: `request.getfixturevalue`,
+/// pytest-lazy-fixture's `lazy_fixture` and pytest-lazy-fixtures' `lf`.
+pub(crate) const FIXTURE_NAME_CALLS: &[&str] = &["getfixturevalue", "lazy_fixture", "lf"];
+
+/// Checks if a call's function is `lazy_fixture` / `pytest.lazy_fixture`
+/// (pytest-lazy-fixture) or `lf` / `pytest_lazy_fixtures.lf` (pytest-lazy-fixtures).
+pub fn is_lazy_fixture_call(func: &Expr) -> bool {
+    match func {
+        Expr::Name(name) => matches!(name.id.as_str(), "lazy_fixture" | "lf"),
+        Expr::Attribute(attr) => match attr.attr.as_str() {
+            "lazy_fixture" => true,
+            "lf" => matches!(
+                attr.value.as_ref(),
+                Expr::Name(n) if n.id.as_str() == "pytest_lazy_fixtures"
+            ),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Extracts the fixture names referenced by a `lazy_fixture(...)` call, paired
+/// with the range of each name inside its string literal. Accepts a single
+/// name or a list/tuple of names (`lazy_fixture(["a", "b"])`).
+pub fn extract_lazy_fixture_names(
+    call: &ruff_python_ast::ExprCall,
+    content: &str,
+) -> Vec<(String, ruff_text_size::TextRange)> {
+    if !is_lazy_fixture_call(&call.func) {
+        return vec![];
+    }
+    let elts = match call.arguments.args.first() {
+        Some(Expr::List(list)) => list.elts.iter().collect(),
+        Some(Expr::Tuple(tuple)) => tuple.elts.iter().collect(),
+        Some(arg) => vec![arg],
+        None => vec![],
+    };
+    elts.into_iter()
+        .filter_map(|elt| match elt {
+            Expr::StringLiteral(s) => {
+                let literal = content
+                    .get(s.range.start().to_usize()..s.range.end().to_usize())
+                    .unwrap_or("");
+                Some((s.value.to_string(), literal_content_range(literal, s.range)))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Computes the test IDs pytest generates for a `@pytest.mark.parametrize(...)` decorator, one per
+/// parameter set, in order.
+///
+/// Follows pytest's ID rules: explicit `ids=[...]` entries and `pytest.param(..., id=...)` win;
+/// otherwise strings, numbers, booleans and `None` are used verbatim and anything else becomes
+/// `<argname><index>`, with the per-argname parts joined by `-`. Duplicate IDs get an index suffix.
+/// Returns `None` when the decorator isn't parametrize or its values aren't a literal list/tuple.
+pub fn extract_parametrize_ids(expr: &Expr, content: &str) -> Option<Vec<String>> {
+    let Expr::Call(call) = expr else {
+        return None;
+    };
+    if !is_parametrize_decorator(&call.func) {
+        return None;
+    }
+
+    let keyword = |name: &str| {
+        call.arguments
+            .keywords
+            .iter()
+            .find(|kw| kw.arg.as_ref().is_some_and(|a| a.as_str() == name))
+            .map(|kw| &kw.value)
+    };
+
+    let argnames: Vec<String> = extract_parametrize_argnames(expr, content)
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    let values = match call
+        .arguments
+        .args
+        .get(1)
+        .or_else(|| keyword("argvalues"))?
+    {
+        Expr::List(list) => &list.elts,
+        Expr::Tuple(tuple) => &tuple.elts,
+        _ => return None,
+    };
+    let explicit_ids: Vec<Option<String>> =
+        match call.arguments.args.get(3).or_else(|| keyword("ids")) {
+            Some(Expr::List(list)) => list.elts.iter().map(string_constant).collect(),
+            Some(Expr::Tuple(tuple)) => tuple.elts.iter().map(string_constant).collect(),
+            _ => Vec::new(),
+        };
+
+    Some(param_set_ids(values, &explicit_ids, &argnames))
+}
+
+/// The IDs of parameter sets `values` for `argnames`, following pytest's ID
+/// rules (see [`extract_parametrize_ids`]).
+fn param_set_ids(
+    values: &[Expr],
+    explicit_ids: &[Option<String>],
+    argnames: &[String],
+) -> Vec<String> {
+    let mut ids: Vec<String> = values
+        .iter()
+        .enumerate()
+        .map(|(idx, value)| {
+            if let Some(Some(id)) = explicit_ids.get(idx) {
+                return id.clone();
+            }
+            let (parts, param_id) = parametrize_value_parts(value, argnames.len());
+            if let Some(id) = param_id {
+                return id;
+            }
+            argnames
+                .iter()
+                .enumerate()
+                .map(|(i, argname)| {
+                    parts
+                        .get(i)
+                        .and_then(|part| literal_id(part))
+                        .unwrap_or_else(|| format!("{}{}", argname, idx))
+                })
+                .collect::<Vec<_>>()
+                .join("-")
+        })
+        .collect();
+
+    // Disambiguate duplicates the way pytest does: `a0`, `a1`, or `v1_0`, `v1_1`
+    let mut counts: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    for id in &ids {
+        *counts.entry(id.clone()).or_default() += 1;
+    }
+    let mut seen: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    for id in ids.iter_mut() {
+        if counts[id.as_str()] > 1 {
+            let n = seen.entry(id.clone()).or_default();
+            let sep = if id.ends_with(|c: char| c.is_ascii_digit()) {
+                "_"
+            } else {
+                ""
+            };
+            let suffixed = format!("{}{}{}", id, sep, n);
+            *n += 1;
+            *id = suffixed;
+        }
+    }
+    ids
+}
+
+fn string_constant(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::StringLiteral(s) => Some(s.value.to_string()),
+        _ => None,
+    }
+}
+
+/// Splits one parameter set into a value per argname, unwrapping
+/// `pytest.param(*values, id=...)` and returning its explicit ID if any.
+fn parametrize_value_parts(expr: &Expr, argname_count: usize) -> (Vec<&Expr>, Option<String>) {
+    match expr {
+        Expr::Call(call) if is_pytest_param(&call.func) => {
+            let id = call
+                .arguments
+                .keywords
+                .iter()
+                .find(|kw| kw.arg.as_ref().is_some_and(|a| a.as_str() == "id"))
+                .and_then(|kw| string_constant(&kw.value));
+            (call.arguments.args.iter().collect(), id)
+        }
+        Expr::Tuple(tuple) if argname_count > 1 => (tuple.elts.iter().collect(), None),
+        Expr::List(list) if argname_count > 1 => (list.elts.iter().collect(), None),
+        _ => (vec![expr], None),
+    }
+}
+
+fn is_pytest_param(func: &Expr) -> bool {
+    match func {
+        Expr::Attribute(attr) => {
+            attr.attr.as_str() == "param"
+                && matches!(attr.value.as_ref(), Expr::Name(n) if n.id.as_str() == "pytest")
+        }
+        Expr::Name(name) => name.id.as_str() == "param",
+        _ => false,
+    }
+}
+
+/// The ID pytest derives from a literal parameter value, if it uses the value itself.
+fn literal_id(expr: &Expr) -> Option<String> {
+    use ruff_python_ast::{Number, UnaryOp};
+
+    match expr {
+        Expr::StringLiteral(s) => Some(s.value.to_string()),
+        Expr::NumberLiteral(n) => match &n.value {
+            Number::Int(i) => Some(i.to_string()),
+            Number::Float(f) => Some(format!("{:?}", f)),
+            Number::Complex { .. } => None,
+        },
+        Expr::BooleanLiteral(b) => Some(if b.value { "True" } else { "False" }.to_string()),
+        Expr::NoneLiteral(_) => Some("None".to_string()),
+        Expr::UnaryOp(op) if matches!(op.op, UnaryOp::USub) => {
+            let inner = literal_id(&op.operand)?;
+            matches!(
+                op.operand.as_ref(),
+                Expr::NumberLiteral(n) if !matches!(n.value, Number::Complex { .. })
+            )
+            .then(|| format!("-{}", inner))
+        }
+        _ => None,
+    }
+}
+
+/// Extracts the `params=` of a `@pytest.fixture(params=[...], ids=[...])`
+/// decorator, with the ID pytest gives each value when `params` is a literal
+/// list or tuple (a value without an explicit ID is named after the fixture,
+/// like `db0`). Returns `None` when the fixture is not parametrized.
+pub fn extract_fixture_params(
+    expr: &Expr,
+    aliases: &[String],
+    content: &str,
+    fixture_name: &str,
+) -> Option<super::types::FixtureParams> {
+    let Expr::Call(call) = expr else { return None };
+    if !is_fixture_decorator(&call.func, aliases) {
+        return None;
+    }
+
+    let keyword = |name: &str| {
+        call.arguments
+            .keywords
+            .iter()
+            .find(|kw| kw.arg.as_ref().is_some_and(|a| a.as_str() == name))
+            .map(|kw| &kw.value)
+    };
+    let params = keyword("params")?;
+    let values = match params {
+        Expr::List(list) => &list.elts,
+        Expr::Tuple(tuple) => &tuple.elts,
+        _ => {
+            return Some(super::types::FixtureParams {
+                ids: Vec::new(),
+                expression: content
+                    .get(params.range().start().to_usize()..params.range().end().to_usize())
+                    .map(str::to_string),
+            })
+        }
+    };
+    let explicit_ids: Vec<Option<String>> = match keyword("ids") {
+        Some(Expr::List(list)) => list.elts.iter().map(string_constant).collect(),
+        Some(Expr::Tuple(tuple)) => tuple.elts.iter().map(string_constant).collect(),
+        _ => Vec::new(),
+    };
+
+    Some(super::types::FixtureParams {
+        ids: param_set_ids(values, &explicit_ids, &[fixture_name.to_string()]),
+        expression: None,
+    })
+}
+
 /// Extracts whether autouse=True is set on a @pytest.fixture decorator.
 /// Returns false if no autouse keyword is specified or if autouse=False.
-pub fn extract_fixture_autouse(expr: &Expr) -> bool {
+pub fn extract_fixture_autouse(expr: &Expr, aliases: &[String]) -> bool {
     let Expr::Call(call) = expr else { return false };
-    if !is_fixture_decorator(&call.func) {
+    if !is_fixture_decorator(&call.func, aliases) {
         return false;
     }
 
-    call.keywords
+    call.arguments
+        .keywords
         .iter()
         .filter(|kw| kw.arg.as_ref().is_some_and(|a| a.as_str() == "autouse"))
-        .any(|kw| matches!(&kw.value, Expr::Constant(c) if matches!(c.value, rustpython_parser::ast::Constant::Bool(true))))
+        .any(|kw| matches!(&kw.value, Expr::BooleanLiteral(b) if b.value))
 }
 
 /// Extracts the scope from a @pytest.fixture(scope="...") decorator.
 /// Returns None if no scope is specified (defaults to "function" at call site).
-pub fn extract_fixture_scope(expr: &Expr) -> Option<super::types::FixtureScope> {
+pub fn extract_fixture_scope(
+    expr: &Expr,
+    aliases: &[String],
+) -> Option<super::types::FixtureScope> {
     let Expr::Call(call) = expr else { return None };
-    if !is_fixture_decorator(&call.func) {
+    if !is_fixture_decorator(&call.func, aliases) {
         return None;
     }
 
-    call.keywords
+    call.arguments
+        .keywords
         .iter()
         .filter(|kw| kw.arg.as_ref().is_some_and(|a| a.as_str() == "scope"))
         .find_map(|kw| match &kw.value {
-            Expr::Constant(c) => match &c.value {
-                rustpython_parser::ast::Constant::Str(s) => super::types::FixtureScope::parse(s),
-                _ => None,
-            },
+            Expr::StringLiteral(s) => super::types::FixtureScope::parse(s.value.to_str()),
             _ => None,
         })
 }
+
+/// Computes the edit that sets `scope="<scope>"` on a `@pytest.fixture` decorator.
+///
+/// Returns the range to replace and its replacement text:
+/// - an existing `scope=` keyword has its value replaced,
+/// - a call without `scope=` gets the keyword appended to its arguments,
+/// - a bare `@pytest.fixture` gets a `(scope=...)` call appended.
+///
+/// Returns `None` when `expr` is not a fixture decorator.
+pub fn fixture_scope_edit(
+    expr: &Expr,
+    scope: super::types::FixtureScope,
+    aliases: &[String],
+) -> Option<(ruff_text_size::TextRange, String)> {
+    use ruff_text_size::{Ranged, TextRange, TextSize};
+
+    let quoted = format!("\"{}\"", scope.as_str());
+    match expr {
+        Expr::Call(call) => {
+            if !is_fixture_decorator(&call.func, aliases) {
+                return None;
+            }
+            if let Some(kw) = call
+                .arguments
+                .keywords
+                .iter()
+                .find(|kw| kw.arg.as_ref().is_some_and(|a| a.as_str() == "scope"))
+            {
+                return Some((kw.value.range(), quoted));
+            }
+
+            let last_arg_end = call
+                .arguments
+                .args
+                .iter()
+                .map(|arg| arg.range().end())
+                .chain(call.arguments.keywords.iter().map(|kw| kw.range.end()))
+                .max();
+            match last_arg_end {
+                Some(end) => Some((TextRange::empty(end), format!(", scope={}", quoted))),
+                None => {
+                    // Insert just before the closing `)` of the empty call.
+                    let close_paren = call.range().end() - TextSize::from(1);
+                    Some((TextRange::empty(close_paren), format!("scope={}", quoted)))
+                }
+            }
+        }
+        Expr::Name(_) | Expr::Attribute(_) if is_fixture_decorator(expr, aliases) => Some((
+            TextRange::empty(expr.range().end()),
+            format!("(scope={})", quoted),
+        )),
+        _ => None,
+    }
+}
@@ -1,10 +1,30 @@
 //! Docstring and return type extraction from Python AST.
 //!
 //! This module handles extracting documentation and type information
-//! from Python function definitions.
+//! from Python function definitions, and inferring the return type of
+//! unannotated ones from their body.
 
 use super::FixtureDatabase;
-use rustpython_parser::ast::{Expr, Stmt};
+use ruff_python_ast::visitor::{self, Visitor};
+use ruff_python_ast::{Expr, Number, Stmt};
+use ruff_text_size::Ranged;
+
+/// Builtins whose call returns an instance of themselves.
+const BUILTIN_CONSTRUCTORS: &[&str] = &[
+    "bool",
+    "bytearray",
+    "bytes",
+    "complex",
+    "dict",
+    "float",
+    "frozenset",
+    "int",
+    "list",
+    "object",
+    "set",
+    "str",
+    "tuple",
+];
 
 /// Find the byte offset of the first `yield`/`yield from` in a function body.
 ///
@@ -18,30 +38,35 @@ pub(crate) fn find_yield_offset(body: &[Stmt]) -> Option<usize> {
             Expr::Yield(y) => Some(y.range.start().to_usize()),
             Expr::YieldFrom(y) => Some(y.range.start().to_usize()),
             Expr::Await(a) => in_expr(&a.value),
-            Expr::NamedExpr(n) => in_expr(&n.value),
+            Expr::Named(n) => in_expr(&n.value),
             // A yield may legally nest inside other expressions, e.g.
             // `x = f((yield 1))`. Recurse into the common containers, but not
             // into lambdas — a yield there belongs to the lambda, not to the
             // enclosing function.
             Expr::Call(c) => in_expr(&c.func)
-                .or_else(|| c.args.iter().find_map(in_expr))
-                .or_else(|| c.keywords.iter().find_map(|kw| in_expr(&kw.value))),
+                .or_else(|| c.arguments.args.iter().find_map(in_expr))
+                .or_else(|| {
+                    c.arguments
+                        .keywords
+                        .iter()
+                        .find_map(|kw| in_expr(&kw.value))
+                }),
             Expr::BinOp(e) => in_expr(&e.left).or_else(|| in_expr(&e.right)),
             Expr::UnaryOp(e) => in_expr(&e.operand),
             Expr::BoolOp(e) => e.values.iter().find_map(in_expr),
             Expr::Compare(e) => in_expr(&e.left).or_else(|| e.comparators.iter().find_map(in_expr)),
-            Expr::IfExp(e) => in_expr(&e.test)
+            Expr::If(e) => in_expr(&e.test)
                 .or_else(|| in_expr(&e.body))
                 .or_else(|| in_expr(&e.orelse)),
             Expr::Tuple(e) => e.elts.iter().find_map(in_expr),
             Expr::List(e) => e.elts.iter().find_map(in_expr),
             Expr::Set(e) => e.elts.iter().find_map(in_expr),
-            Expr::Dict(e) => e
-                .keys
-                .iter()
-                .flatten()
-                .find_map(in_expr)
-                .or_else(|| e.values.iter().find_map(in_expr)),
+            Expr::Dict(e) => e.items.iter().find_map(|item| {
+                item.key
+                    .as_ref()
+                    .and_then(in_expr)
+                    .or_else(|| in_expr(&item.value))
+            }),
             Expr::Subscript(e) => in_expr(&e.value).or_else(|| in_expr(&e.slice)),
             Expr::Starred(e) => in_expr(&e.value),
             Expr::Attribute(e) => in_expr(&e.value),
@@ -56,18 +81,18 @@ pub(crate) fn find_yield_offset(body: &[Stmt]) -> Option<usize> {
             Stmt::AugAssign(s) => in_expr(&s.value),
             Stmt::AnnAssign(s) => s.value.as_deref().and_then(in_expr),
             Stmt::Return(s) => s.value.as_deref().and_then(in_expr),
-            Stmt::If(s) => find_yield_offset(&s.body).or_else(|| find_yield_offset(&s.orelse)),
+            Stmt::If(s) => find_yield_offset(&s.body).or_else(|| {
+                s.elif_else_clauses
+                    .iter()
+                    .find_map(|clause| find_yield_offset(&clause.body))
+            }),
             Stmt::For(s) => find_yield_offset(&s.body).or_else(|| find_yield_offset(&s.orelse)),
-            Stmt::AsyncFor(s) => {
-                find_yield_offset(&s.body).or_else(|| find_yield_offset(&s.orelse))
-            }
             Stmt::While(s) => find_yield_offset(&s.body).or_else(|| find_yield_offset(&s.orelse)),
             Stmt::With(s) => find_yield_offset(&s.body),
-            Stmt::AsyncWith(s) => find_yield_offset(&s.body),
             Stmt::Try(s) => find_yield_offset(&s.body)
                 .or_else(|| {
                     s.handlers.iter().find_map(|handler| {
-                        let rustpython_parser::ast::ExceptHandler::ExceptHandler(h) = handler;
+                        let ruff_python_ast::ExceptHandler::ExceptHandler(h) = handler;
                         find_yield_offset(&h.body)
                     })
                 })
@@ -84,15 +109,85 @@ pub(crate) fn find_yield_offset(body: &[Stmt]) -> Option<usize> {
     body.iter().find_map(in_stmt)
 }
 
+/// Collects the values a function body returns and yields, outside of nested
+/// functions, lambdas and classes. A bare `return` or `yield` gives `None`.
+#[derive(Default)]
+struct ReturnValueCollector<'a> {
+    returns: Vec<Option<&'a Expr>>,
+    yields: Vec<Option<&'a Expr>>,
+    /// Whether the body delegates with `yield from`, whose values are unknown
+    yields_from: bool,
+}
+
+impl<'a> Visitor<'a> for ReturnValueCollector<'a> {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        match stmt {
+            Stmt::FunctionDef(_) | Stmt::ClassDef(_) => {}
+            Stmt::Return(ret) => {
+                self.returns.push(ret.value.as_deref());
+                if let Some(value) = &ret.value {
+                    self.visit_expr(value);
+                }
+            }
+            _ => visitor::walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        match expr {
+            Expr::Lambda(_) => {}
+            Expr::Yield(yield_expr) => {
+                self.yields.push(yield_expr.value.as_deref());
+                visitor::walk_expr(self, expr);
+            }
+            Expr::YieldFrom(_) => self.yields_from = true,
+            _ => visitor::walk_expr(self, expr),
+        }
+    }
+}
+
+/// The type of a value whose type is apparent from its syntax: a literal, a
+/// display or comprehension, or a call to a builtin constructor or to a
+/// capitalized (class-like) name such as `TestClient(app)`.
+fn literal_or_constructor_type(expr: &Expr, db: &FixtureDatabase, content: &str) -> Option<String> {
+    let name = match expr {
+        Expr::StringLiteral(_) | Expr::FString(_) => "str",
+        Expr::BytesLiteral(_) => "bytes",
+        Expr::NumberLiteral(number) => match number.value {
+            Number::Int(_) => "int",
+            Number::Float(_) => "float",
+            Number::Complex { .. } => "complex",
+        },
+        Expr::BooleanLiteral(_) => "bool",
+        Expr::NoneLiteral(_) => "None",
+        Expr::List(_) | Expr::ListComp(_) => "list",
+        Expr::Dict(_) | Expr::DictComp(_) => "dict",
+        Expr::Set(_) | Expr::SetComp(_) => "set",
+        Expr::Tuple(_) => "tuple",
+        Expr::Call(call) => {
+            let callee = match &*call.func {
+                Expr::Name(name) => name.id.as_str(),
+                Expr::Attribute(attr) => attr.attr.as_str(),
+                _ => return None,
+            };
+            let is_class = callee.starts_with(|c: char| c.is_ascii_uppercase());
+            if !is_class && !BUILTIN_CONSTRUCTORS.contains(&callee) {
+                return None;
+            }
+            return Some(db.expr_to_string(&call.func, content));
+        }
+        _ => return None,
+    };
+    Some(name.to_string())
+}
+
 impl FixtureDatabase {
     /// Extract docstring from a function body.
     /// The docstring is the first statement if it's a string literal.
     pub(crate) fn extract_docstring(&self, body: &[Stmt]) -> Option<String> {
         if let Some(Stmt::Expr(expr_stmt)) = body.first() {
-            if let Expr::Constant(constant) = &*expr_stmt.value {
-                if let rustpython_parser::ast::Constant::Str(s) = &constant.value {
-                    return Some(super::string_utils::format_docstring(s.to_string()));
-                }
+            if let Expr::StringLiteral(s) = &*expr_stmt.value {
+                return Some(super::string_utils::format_docstring(s.value.to_string()));
             }
         }
         None
@@ -102,7 +197,7 @@ impl FixtureDatabase {
     /// For yield fixtures (generators), extracts the yielded type from Generator[T, ...].
     pub(crate) fn extract_return_type(
         &self,
-        returns: &Option<Box<rustpython_parser::ast::Expr>>,
+        returns: &Option<Box<ruff_python_ast::Expr>>,
         body: &[Stmt],
         content: &str,
     ) -> Option<String> {
@@ -116,13 +211,55 @@ impl FixtureDatabase {
         None
     }
 
-    /// Extract the yielded type from a Generator/Iterator type annotation.
-    /// For Generator[T, None, None] or Iterator[T], returns T.
-    fn extract_yielded_type(
+    /// Infer the type an unannotated function provides from its body: what it
+    /// yields for generators, what it returns otherwise. Each value must have
+    /// an apparent type (see [`literal_or_constructor_type`]); differing types
+    /// are joined into a union, `None` last. Returns `None` when the function
+    /// is annotated or any value's type is unknown.
+    pub(crate) fn infer_return_type(
         &self,
-        expr: &rustpython_parser::ast::Expr,
+        returns: &Option<Box<Expr>>,
+        body: &[Stmt],
         content: &str,
     ) -> Option<String> {
+        if returns.is_some() {
+            return None;
+        }
+        let mut collector = ReturnValueCollector::default();
+        collector.visit_body(body);
+        let values = if collector.yields_from {
+            return None;
+        } else if collector.yields.is_empty() {
+            collector.returns
+        } else {
+            collector.yields
+        };
+        if values.is_empty() {
+            return None;
+        }
+
+        let mut types: Vec<String> = Vec::new();
+        for value in values {
+            let ty = match value {
+                Some(expr) => literal_or_constructor_type(expr, self, content)?,
+                None => "None".to_string(),
+            };
+            if !types.contains(&ty) {
+                types.push(ty);
+            }
+        }
+        if let Some(none) = types.iter().position(|ty| ty == "None") {
+            if types.len() > 1 {
+                let none = types.remove(none);
+                types.push(none);
+            }
+        }
+        Some(types.join(" | "))
+    }
+
+    /// Extract the yielded type from a Generator/Iterator type annotation.
+    /// For Generator[T, None, None] or Iterator[T], returns T.
+    fn extract_yielded_type(&self, expr: &ruff_python_ast::Expr, content: &str) -> Option<String> {
         if let Expr::Subscript(subscript) = expr {
             if let Expr::Tuple(tuple) = &*subscript.slice {
                 if let Some(first_elem) = tuple.elts.first() {
@@ -137,11 +274,7 @@ impl FixtureDatabase {
 
     /// Convert a Python type expression AST node to a string representation.
     #[allow(clippy::only_used_in_recursion)]
-    pub(crate) fn expr_to_string(
-        &self,
-        expr: &rustpython_parser::ast::Expr,
-        content: &str,
-    ) -> String {
+    pub(crate) fn expr_to_string(&self, expr: &ruff_python_ast::Expr, content: &str) -> String {
         match expr {
             Expr::Name(name) => name.id.to_string(),
             Expr::Attribute(attr) => {
@@ -164,10 +297,18 @@ impl FixtureDatabase {
                     .collect();
                 elements.join(", ")
             }
-            Expr::Constant(constant) => {
-                format!("{:?}", constant.value)
+            Expr::NoneLiteral(_) => "None".to_string(),
+            Expr::StringLiteral(_)
+            | Expr::NumberLiteral(_)
+            | Expr::BooleanLiteral(_)
+            | Expr::EllipsisLiteral(_) => {
+                let range = expr.range();
+                content
+                    .get(range.start().to_usize()..range.end().to_usize())
+                    .unwrap_or("Any")
+                    .to_string()
             }
-            Expr::BinOp(binop) if matches!(binop.op, rustpython_parser::ast::Operator::BitOr) => {
+            Expr::BinOp(binop) if matches!(binop.op, ruff_python_ast::Operator::BitOr) => {
                 format!(
                     "{} | {}",
                     self.expr_to_string(&binop.left, content),
@@ -179,6 +320,456 @@ impl FixtureDatabase {
     }
 }
 
+/// How the body of a docstring section is rendered.
+#[derive(Clone, Copy, PartialEq)]
+enum SectionKind {
+    /// One entry per name: `Args`, `Attributes`, `Raises`...
+    Entries,
+    /// What the function returns or yields, possibly with its type
+    Returns,
+    /// Prose with doctests
+    Examples,
+    /// Prose kept as is: `Note`, `Warning`...
+    Text,
+}
+
+/// The kind of a Google or NumPy section from its title.
+fn section_kind(title: &str) -> Option<SectionKind> {
+    Some(match title {
+        "Args" | "Arguments" | "Parameters" | "Params" | "Other Parameters" | "Keyword Args"
+        | "Keyword Arguments" | "Attributes" | "Raises" | "Warns" => SectionKind::Entries,
+        "Returns" | "Return" | "Yields" | "Yield" => SectionKind::Returns,
+        "Examples" | "Example" => SectionKind::Examples,
+        "Note" | "Notes" | "Warning" | "Warnings" | "See Also" | "References" | "Todo" => {
+            SectionKind::Text
+        }
+        _ => return None,
+    })
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Remove the common indentation of the non-blank `lines`.
+fn dedent(lines: &[&str]) -> Vec<String> {
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| indent_of(line))
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end().to_string())
+        .collect()
+}
+
+/// Whether `line` underlines a NumPy section title (`----------`).
+fn is_underline(line: &str) -> bool {
+    let line = line.trim();
+    line.len() >= 3 && line.chars().all(|c| c == '-')
+}
+
+/// Whether a NumPy section title starts at `lines[i]`.
+fn is_numpy_header(lines: &[&str], i: usize) -> bool {
+    section_kind(lines[i].trim()).is_some() && lines.get(i + 1).is_some_and(|l| is_underline(l))
+}
+
+/// Join an entry's description lines into one line of text.
+fn join_description<S: AsRef<str>>(lines: &[S]) -> String {
+    lines
+        .iter()
+        .map(|line| line.as_ref().trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Whether `text` reads like a type rather than prose: no spaces outside
+/// brackets.
+fn looks_like_type(text: &str) -> bool {
+    let mut depth = 0i32;
+    !text.is_empty()
+        && text.chars().all(|c| {
+            match c {
+                '[' | '(' => depth += 1,
+                ']' | ')' => depth -= 1,
+                ' ' if depth == 0 => return false,
+                _ => {}
+            }
+            true
+        })
+}
+
+/// A Markdown list item for a name, its type and description.
+fn entry_item(name: &str, ty: Option<&str>, description: &str) -> String {
+    let mut item = format!("- `{}`", name);
+    if let Some(ty) = ty.filter(|ty| !ty.is_empty()) {
+        item.push_str(&format!(" (`{}`)", ty));
+    }
+    if !description.is_empty() {
+        item.push_str(&format!(": {}", description));
+    }
+    item
+}
+
+/// Split the dedented body of a section into entries: a line at the body's
+/// indentation and the more indented lines continuing it.
+fn split_entries(body: &[String]) -> Vec<(String, Vec<String>)> {
+    let mut entries: Vec<(String, Vec<String>)> = Vec::new();
+    for line in body {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match entries.last_mut() {
+            Some((_, continuation)) if line.starts_with(char::is_whitespace) => {
+                continuation.push(line.clone());
+            }
+            _ => entries.push((line.clone(), Vec::new())),
+        }
+    }
+    entries
+}
+
+/// Render the entries of a section: `name (type): description` (Google) or
+/// `name : type` followed by an indented description (NumPy).
+fn render_entries(body: &[String], out: &mut Vec<String>) {
+    for (head, continuation) in split_entries(body) {
+        let description = join_description(&continuation);
+        if let Some((name, ty)) = head.split_once(" : ") {
+            out.push(entry_item(name.trim(), Some(ty.trim()), &description));
+            continue;
+        }
+        let (head, first) = match head.split_once(':') {
+            Some((head, first)) => (head.trim(), first.trim()),
+            None => (head.trim(), ""),
+        };
+        let description = join_description(&[first, &description]);
+        match head.strip_suffix(')').and_then(|h| h.split_once(" (")) {
+            Some((name, ty)) => out.push(entry_item(name, Some(ty), &description)),
+            None => out.push(entry_item(head, None, &description)),
+        }
+    }
+}
+
+/// Render what a function returns or yields: `type: description` (Google),
+/// `type` or `name : type` followed by an indented description (NumPy), or
+/// plain prose.
+fn render_returns(body: &[String], numpy: bool, out: &mut Vec<String>) {
+    if numpy {
+        for (head, continuation) in split_entries(body) {
+            let description = join_description(&continuation);
+            match head.split_once(" : ") {
+                Some((name, ty)) => {
+                    out.push(entry_item(name.trim(), Some(ty.trim()), &description))
+                }
+                None => out.push(entry_item(head.trim(), None, &description)),
+            }
+        }
+        return;
+    }
+    let text = join_description(body);
+    match text.split_once(':') {
+        Some((ty, description)) if looks_like_type(ty.trim()) => {
+            out.push(entry_item(ty.trim(), None, description.trim()));
+        }
+        _ => out.push(text),
+    }
+}
+
+/// Render prose, putting doctests (`>>>` and their output) and the indented
+/// blocks introduced by `::` in Python code blocks. A body without prose is
+/// code when `all_code`.
+fn render_text<S: AsRef<str>>(lines: &[S], all_code: bool, out: &mut Vec<String>) {
+    let lines: Vec<&str> = lines.iter().map(|l| l.as_ref()).collect();
+    if all_code && !lines.iter().any(|l| l.trim_start().starts_with(">>>")) {
+        out.push("```python".to_string());
+        out.extend(dedent(&lines));
+        out.push("```".to_string());
+        return;
+    }
+
+    let mut in_fence = false;
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") {
+            in_fence = !in_fence;
+        } else if !in_fence && trimmed.starts_with(">>>") {
+            let end = lines[i..]
+                .iter()
+                .position(|l| l.trim().is_empty())
+                .map_or(lines.len(), |n| i + n);
+            out.push("```python".to_string());
+            out.extend(dedent(&lines[i..end]));
+            out.push("```".to_string());
+            i = end;
+            continue;
+        } else if !in_fence && line.trim_end().ends_with("::") {
+            let base = indent_of(line);
+            let mut end = i + 1;
+            while end < lines.len()
+                && (lines[end].trim().is_empty() || indent_of(lines[end]) > base)
+            {
+                end += 1;
+            }
+            while end > i + 1 && lines[end - 1].trim().is_empty() {
+                end -= 1;
+            }
+            if lines[i + 1..end].iter().any(|l| !l.trim().is_empty()) {
+                let intro = line.trim_end().trim_end_matches(':');
+                if !intro.trim().is_empty() {
+                    out.push(format!("{}:", intro));
+                }
+                out.push("```python".to_string());
+                let block: Vec<&str> = lines[i + 1..end]
+                    .iter()
+                    .copied()
+                    .skip_while(|l| l.trim().is_empty())
+                    .collect();
+                out.extend(dedent(&block));
+                out.push("```".to_string());
+                i = end;
+                continue;
+            }
+        }
+        out.push(line.to_string());
+        i += 1;
+    }
+}
+
+/// Push a section title, separated from what precedes it by a blank line.
+fn push_heading(title: &str, out: &mut Vec<String>) {
+    if out.last().is_some_and(|line| !line.is_empty()) {
+        out.push(String::new());
+    }
+    out.push(format!("**{}:**", title));
+    out.push(String::new());
+}
+
+fn render_section(
+    title: &str,
+    kind: SectionKind,
+    body: &[String],
+    numpy: bool,
+    out: &mut Vec<String>,
+) {
+    push_heading(title, out);
+    match kind {
+        SectionKind::Entries => render_entries(body, out),
+        SectionKind::Returns => render_returns(body, numpy, out),
+        SectionKind::Examples => render_text(body, true, out),
+        SectionKind::Text => render_text(body, false, out),
+    }
+    while out.last().is_some_and(|line| line.is_empty()) {
+        out.pop();
+    }
+    out.push(String::new());
+}
+
+/// A reST field (`:param int x: description`): its directive, argument and
+/// the text after it.
+fn parse_field(line: &str) -> Option<(&str, &str, &str)> {
+    let rest = line.trim_start().strip_prefix(':')?;
+    let (field, text) = rest.split_once(':')?;
+    let (directive, argument) = field.split_once(' ').unwrap_or((field, ""));
+    matches!(
+        directive,
+        "param"
+            | "parameter"
+            | "arg"
+            | "argument"
+            | "key"
+            | "keyword"
+            | "type"
+            | "returns"
+            | "return"
+            | "rtype"
+            | "yields"
+            | "yield"
+            | "ytype"
+            | "raises"
+            | "raise"
+            | "except"
+            | "exception"
+    )
+    .then_some((directive, argument.trim(), text.trim()))
+}
+
+/// Render a run of reST fields as Parameters, Returns, Yields and Raises
+/// sections.
+fn render_fields(fields: &[(String, String, String)], out: &mut Vec<String>) {
+    // Parameters in order of appearance, with their type and description
+    let mut params: Vec<(String, Option<String>, String)> = Vec::new();
+    let mut returns: (Option<String>, String) = (None, String::new());
+    let mut yields: (Option<String>, String) = (None, String::new());
+    let mut raises: Vec<(String, String)> = Vec::new();
+    for (directive, argument, text) in fields {
+        let param = |params: &mut Vec<(String, Option<String>, String)>, name: &str| match params
+            .iter()
+            .position(|(n, _, _)| n == name)
+        {
+            Some(index) => index,
+            None => {
+                params.push((name.to_string(), None, String::new()));
+                params.len() - 1
+            }
+        };
+        match directive.as_str() {
+            "param" | "parameter" | "arg" | "argument" | "key" | "keyword" => {
+                // `:param int x:` declares the type inline
+                let (ty, name) = match argument.rsplit_once(' ') {
+                    Some((ty, name)) => (Some(ty.to_string()), name),
+                    None => (None, argument.as_str()),
+                };
+                let index = param(&mut params, name);
+                if ty.is_some() {
+                    params[index].1 = ty;
+                }
+                params[index].2 = text.clone();
+            }
+            "type" => {
+                let index = param(&mut params, argument);
+                params[index].1 = Some(text.clone());
+            }
+            "returns" | "return" => returns.1 = text.clone(),
+            "rtype" => returns.0 = Some(text.clone()),
+            "yields" | "yield" => yields.1 = text.clone(),
+            "ytype" => yields.0 = Some(text.clone()),
+            _ => raises.push((argument.clone(), text.clone())),
+        }
+    }
+
+    if !params.is_empty() {
+        push_heading("Parameters", out);
+        for (name, ty, description) in &params {
+            out.push(entry_item(name, ty.as_deref(), description));
+        }
+        out.push(String::new());
+    }
+    for (title, (ty, description)) in [("Returns", returns), ("Yields", yields)] {
+        if ty.is_none() && description.is_empty() {
+            continue;
+        }
+        push_heading(title, out);
+        match ty {
+            Some(ty) => out.push(entry_item(&ty, None, &description)),
+            None => out.push(description),
+        }
+        out.push(String::new());
+    }
+    if !raises.is_empty() {
+        push_heading("Raises", out);
+        for (exception, description) in &raises {
+            out.push(entry_item(exception, None, description));
+        }
+        out.push(String::new());
+    }
+}
+
+/// Render a docstring (as stored, dedented) as Markdown for hovers. Google
+/// (`Args:` and an indented body), NumPy (`Parameters` underlined with
+/// dashes) and reST (`:param x:`) sections become headed lists, examples and
+/// `::` literal blocks become Python code blocks. Everything else, Markdown
+/// included, is kept as is.
+pub(crate) fn docstring_to_markdown(docstring: &str) -> String {
+    let lines: Vec<&str> = docstring.lines().collect();
+    let mut out: Vec<String> = Vec::new();
+    // Prose between sections, rendered when a section or the end is reached
+    let mut prose: Vec<&str> = Vec::new();
+    let mut in_fence = false;
+
+    let mut i = 0;
+    while i < lines.len() {
+        let line = lines[i];
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+        }
+        if in_fence || line.trim_start().starts_with("```") {
+            prose.push(line);
+            i += 1;
+            continue;
+        }
+
+        // Google: `Args:` with an indented body
+        let title = line
+            .trim_end()
+            .strip_suffix(':')
+            .filter(|_| indent_of(line) == 0);
+        let google = title
+            .and_then(|t| section_kind(t).map(|kind| (t, kind)))
+            .filter(|_| {
+                lines[i + 1..]
+                    .iter()
+                    .find(|l| !l.trim().is_empty())
+                    .is_some_and(|l| indent_of(l) > 0)
+            });
+        if let Some((title, kind)) = google {
+            let mut end = i + 1;
+            while end < lines.len() && (lines[end].trim().is_empty() || indent_of(lines[end]) > 0) {
+                end += 1;
+            }
+            render_text(&prose, false, &mut out);
+            prose.clear();
+            render_section(title, kind, &dedent(&lines[i + 1..end]), false, &mut out);
+            i = end;
+            continue;
+        }
+
+        // NumPy: `Parameters` underlined with dashes
+        if is_numpy_header(&lines, i) {
+            let title = line.trim();
+            let mut end = i + 2;
+            while end < lines.len() && !is_numpy_header(&lines, end) {
+                end += 1;
+            }
+            render_text(&prose, false, &mut out);
+            prose.clear();
+            let kind = section_kind(title).unwrap_or(SectionKind::Text);
+            render_section(title, kind, &dedent(&lines[i + 2..end]), true, &mut out);
+            i = end;
+            continue;
+        }
+
+        // reST: a run of `:param x:` fields and their continuation lines
+        if parse_field(line).is_some() {
+            let mut fields: Vec<(String, String, String)> = Vec::new();
+            let base = indent_of(line);
+            while i < lines.len() {
+                if let Some((directive, argument, text)) = parse_field(lines[i]) {
+                    fields.push((
+                        directive.to_string(),
+                        argument.to_string(),
+                        text.to_string(),
+                    ));
+                } else if !lines[i].trim().is_empty() && indent_of(lines[i]) > base {
+                    if let Some(field) = fields.last_mut() {
+                        field.2 = join_description(&[field.2.as_str(), lines[i]]);
+                    }
+                } else {
+                    break;
+                }
+                i += 1;
+            }
+            render_text(&prose, false, &mut out);
+            prose.clear();
+            render_fields(&fields, &mut out);
+            continue;
+        }
+
+        prose.push(line);
+        i += 1;
+    }
+    render_text(&prose, false, &mut out);
+
+    while out.last().is_some_and(|line| line.is_empty()) {
+        out.pop();
+    }
+    out.join("\n")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -383,4 +974,139 @@ mod tests {
         let ret = fixture_return_type("import pytest\n@pytest.fixture\ndef fx():\n    return 1\n");
         assert!(ret.is_none());
     }
+
+    /// Analyze a Python snippet defining a single fixture and return its
+    /// recorded `inferred_return_type`.
+    fn fixture_inferred_type(body: &str) -> Option<String> {
+        let db = FixtureDatabase::new();
+        let path = std::env::temp_dir()
+            .join("pls_docstring_unit")
+            .join("conftest_inferred.py");
+        db.analyze_file(
+            path,
+            &format!("import pytest\n@pytest.fixture\ndef fx(app):\n{}", body),
+        );
+        db.definitions
+            .get("fx")
+            .and_then(|defs| defs.value().first().cloned())
+            .and_then(|d| d.inferred_return_type)
+    }
+
+    #[test]
+    fn test_inferred_return_type_literals_and_constructors() {
+        let cases = [
+            ("    return 'x'\n", Some("str")),
+            ("    return f'{app}'\n", Some("str")),
+            ("    return 1.5\n", Some("float")),
+            ("    return [app]\n", Some("list")),
+            ("    return {k: 1 for k in app}\n", Some("dict")),
+            ("    return TestClient(app)\n", Some("TestClient")),
+            (
+                "    return httpx.AsyncClient()\n",
+                Some("httpx.AsyncClient"),
+            ),
+            ("    return dict(a=1)\n", Some("dict")),
+            ("    return make_client(app)\n", None),
+            ("    return app\n", None),
+            ("    pass\n", None),
+        ];
+        for (body, expected) in cases {
+            assert_eq!(
+                fixture_inferred_type(body).as_deref(),
+                expected,
+                "body: {}",
+                body
+            );
+        }
+    }
+
+    #[test]
+    fn test_inferred_return_type_yields_and_unions() {
+        // Generators provide what they yield, not what they return
+        assert_eq!(
+            fixture_inferred_type("    client = 1\n    yield Client(app)\n    return None\n")
+                .as_deref(),
+            Some("Client")
+        );
+        assert_eq!(
+            fixture_inferred_type("    if app:\n        return None\n    return 1\n").as_deref(),
+            Some("int | None")
+        );
+        // Nested functions return on their own behalf
+        assert_eq!(
+            fixture_inferred_type(
+                "    def inner():\n        return 1\n    return Factory(inner)\n"
+            )
+            .as_deref(),
+            Some("Factory")
+        );
+        assert_eq!(fixture_inferred_type("    yield from range(3)\n"), None);
+    }
+
+    #[test]
+    fn test_no_inferred_return_type_when_annotated() {
+        let db = FixtureDatabase::new();
+        let path = std::env::temp_dir()
+            .join("pls_docstring_unit")
+            .join("conftest_annotated.py");
+        db.analyze_file(
+            path,
+            "import pytest\n@pytest.fixture\ndef fx() -> int:\n    return 1\n",
+        );
+        let def = db.definitions.get("fx").unwrap()[0].clone();
+        assert_eq!(def.return_type.as_deref(), Some("int"));
+        assert_eq!(def.inferred_return_type, None);
+    }
+
+    #[test]
+    fn test_docstring_to_markdown_google() {
+        let doc = "Create a user.\n\nArgs:\n    name (str): The user name,\n        lowercased.\n    admin: Whether they are an admin.\n\nReturns:\n    User: The new user.\n\nRaises:\n    ValueError: If the name is taken.";
+        assert_eq!(
+            docstring_to_markdown(doc),
+            "Create a user.\n\n**Args:**\n\n- `name` (`str`): The user name, lowercased.\n- `admin`: Whether they are an admin.\n\n**Returns:**\n\n- `User`: The new user.\n\n**Raises:**\n\n- `ValueError`: If the name is taken."
+        );
+    }
+
+    #[test]
+    fn test_docstring_to_markdown_numpy() {
+        let doc = "Create a user.\n\nParameters\n----------\nname : str\n    The user name.\n\nYields\n------\nUser\n    The new user.";
+        assert_eq!(
+            docstring_to_markdown(doc),
+            "Create a user.\n\n**Parameters:**\n\n- `name` (`str`): The user name.\n\n**Yields:**\n\n- `User`: The new user."
+        );
+    }
+
+    #[test]
+    fn test_docstring_to_markdown_rest() {
+        let doc = "Create a user.\n\n:param str name: The user name.\n:param admin: Whether they are\n    an admin.\n:type admin: bool\n:returns: The new user.\n:rtype: User\n:raises ValueError: If the name is taken.";
+        assert_eq!(
+            docstring_to_markdown(doc),
+            "Create a user.\n\n**Parameters:**\n\n- `name` (`str`): The user name.\n- `admin` (`bool`): Whether they are an admin.\n\n**Returns:**\n\n- `User`: The new user.\n\n**Raises:**\n\n- `ValueError`: If the name is taken."
+        );
+    }
+
+    #[test]
+    fn test_docstring_to_markdown_examples() {
+        let doc = "A user.\n\nExamples:\n    Use it in a test:\n\n    >>> user.name\n    'alice'\n\nExample:\n    def test_it(user):\n        assert user";
+        assert_eq!(
+            docstring_to_markdown(doc),
+            "A user.\n\n**Examples:**\n\nUse it in a test:\n\n```python\n>>> user.name\n'alice'\n```\n\n**Example:**\n\n```python\ndef test_it(user):\n    assert user\n```"
+        );
+    }
+
+    #[test]
+    fn test_docstring_to_markdown_literal_block() {
+        let doc =
+            "Use it like this::\n\n    def test_it(user):\n        assert user\n\nThat's all.";
+        assert_eq!(
+            docstring_to_markdown(doc),
+            "Use it like this:\n```python\ndef test_it(user):\n    assert user\n```\n\nThat's all."
+        );
+    }
+
+    #[test]
+    fn test_docstring_to_markdown_keeps_plain_text_and_markdown() {
+        let doc = "A user.\n\nNote: this is prose, not a section.\n\n```python\nArgs:\n    x: inside a fence\n```\n\n- a *Markdown* list";
+        assert_eq!(docstring_to_markdown(doc), doc);
+    }
 }
@@ -0,0 +1,321 @@
+//! pytest-factoryboy support: `register(UserFactory)` generates a `user_factory`
+//! fixture returning the factory and a `user` fixture returning a model instance
+//! at runtime. The generated names are derived here the way pytest-factoryboy
+//! does, and recorded as definitions located at the `register` call.
+
+use super::decorators;
+use super::types::FixtureDefinition;
+use super::FixtureDatabase;
+use ruff_python_ast::{Expr, ExprCall, Stmt};
+use ruff_text_size::{Ranged, TextRange};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tracing::info;
+
+/// A `register(...)` call and the fixtures it generates.
+#[derive(Debug, PartialEq)]
+struct Registration {
+    /// Name of the factory class, e.g. `UserFactory`.
+    factory_class: String,
+    /// The `user_factory` fixture.
+    factory_fixture: String,
+    /// The `user` fixture, or the custom `_name=`.
+    model_fixture: String,
+    /// Where the factory fixture is reported: the factory class argument.
+    factory_range: TextRange,
+    /// Where the model fixture is reported: the `_name` string, if any.
+    model_range: TextRange,
+}
+
+/// Convert a class name to snake_case like `inflection.underscore`
+/// (`UserFactory` → `user_factory`, `HTTPClient` → `http_client`).
+fn underscore(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_lower) {
+                out.push('_');
+            }
+        }
+        if c == '-' {
+            out.push('_');
+        } else {
+            out.extend(c.to_lowercase());
+        }
+    }
+    out
+}
+
+/// Whether `func` is pytest-factoryboy's `register` (bare or `pytest_factoryboy.register`).
+fn is_register(func: &Expr) -> bool {
+    match func {
+        Expr::Name(name) => name.id.as_str() == "register",
+        Expr::Attribute(attr) => {
+            attr.attr.as_str() == "register"
+                && matches!(attr.value.as_ref(), Expr::Name(n) if n.id.as_str() == "pytest_factoryboy")
+        }
+        _ => false,
+    }
+}
+
+/// Map factory classes defined in `body` to the name of their `Meta.model`
+/// (`model = User`, `model = models.User` or Django's `model = "app.User"`).
+fn factory_models(body: &[Stmt]) -> HashMap<String, String> {
+    let mut models = HashMap::new();
+    for stmt in body {
+        let Stmt::ClassDef(class_def) = stmt else {
+            continue;
+        };
+        let meta = class_def.body.iter().find_map(|s| match s {
+            Stmt::ClassDef(meta) if meta.name.as_str() == "Meta" => Some(meta),
+            _ => None,
+        });
+        let Some(meta) = meta else { continue };
+        for s in &meta.body {
+            let Stmt::Assign(assign) = s else { continue };
+            let is_model = assign
+                .targets
+                .iter()
+                .any(|t| matches!(t, Expr::Name(n) if n.id.as_str() == "model"));
+            if !is_model {
+                continue;
+            }
+            let model = match assign.value.as_ref() {
+                Expr::Name(n) => Some(n.id.to_string()),
+                Expr::Attribute(attr) => Some(attr.attr.to_string()),
+                Expr::StringLiteral(s) => s.value.to_str().rsplit('.').next().map(str::to_string),
+                _ => None,
+            };
+            if let Some(model) = model {
+                models.insert(class_def.name.to_string(), model);
+            }
+        }
+    }
+    models
+}
+
+/// The model fixture name for a factory: its `Meta.model` when known, otherwise
+/// the class name without its `Factory` suffix.
+fn model_fixture_name(factory_class: &str, models: &HashMap<String, String>) -> String {
+    match models.get(factory_class) {
+        Some(model) => underscore(model),
+        None => underscore(
+            factory_class
+                .strip_suffix("Factory")
+                .unwrap_or(factory_class),
+        ),
+    }
+}
+
+/// The `_name` override of a `register(...)` call (second positional argument
+/// or `_name=` keyword), with the range of its content.
+fn custom_name(call: &ExprCall, content: &str) -> Option<(String, TextRange)> {
+    let expr = call.arguments.args.get(1).or_else(|| {
+        call.arguments
+            .keywords
+            .iter()
+            .find(|kw| kw.arg.as_ref().is_some_and(|a| a.as_str() == "_name"))
+            .map(|kw| &kw.value)
+    })?;
+    let Expr::StringLiteral(name) = expr else {
+        return None;
+    };
+    let literal = content
+        .get(name.range.start().to_usize()..name.range.end().to_usize())
+        .unwrap_or("");
+    Some((
+        name.value.to_string(),
+        decorators::literal_content_range(literal, name.range),
+    ))
+}
+
+/// Registrations made by a module: `register(UserFactory)` statements and
+/// `@register` / `@register(_name="admin")` class decorators.
+fn registrations(body: &[Stmt], content: &str) -> Vec<Registration> {
+    let models = factory_models(body);
+    let make =
+        |factory_class: &str, factory_range: TextRange, name: Option<(String, TextRange)>| {
+            let (model_fixture, model_range) =
+                name.unwrap_or_else(|| (model_fixture_name(factory_class, &models), factory_range));
+            Registration {
+                factory_class: factory_class.to_string(),
+                factory_fixture: underscore(factory_class),
+                model_fixture,
+                factory_range,
+                model_range,
+            }
+        };
+
+    let mut found = Vec::new();
+    for stmt in body {
+        match stmt {
+            Stmt::Expr(expr_stmt) => {
+                let Expr::Call(call) = expr_stmt.value.as_ref() else {
+                    continue;
+                };
+                if !is_register(&call.func) {
+                    continue;
+                }
+                let factory = match call.arguments.args.first() {
+                    Some(Expr::Name(n)) => (n.id.as_str(), n.range),
+                    Some(Expr::Attribute(attr)) => (attr.attr.as_str(), attr.range),
+                    _ => continue,
+                };
+                found.push(make(factory.0, factory.1, custom_name(call, content)));
+            }
+            Stmt::ClassDef(class_def) => {
+                for decorator in &class_def.decorator_list {
+                    let decorator = &decorator.expression;
+                    let name = match decorator {
+                        Expr::Call(call) if is_register(&call.func) => custom_name(call, content),
+                        expr if is_register(expr) => None,
+                        _ => continue,
+                    };
+                    found.push(make(class_def.name.as_str(), decorator.range(), name));
+                }
+            }
+            _ => {}
+        }
+    }
+    found
+}
+
+impl FixtureDatabase {
+    /// Record the fixtures generated by pytest-factoryboy `register(...)` calls
+    /// at module level.
+    pub(crate) fn record_factoryboy_fixtures(
+        &self,
+        body: &[Stmt],
+        file_path: &PathBuf,
+        content: &str,
+        line_index: &[usize],
+    ) {
+        let is_third_party = self.is_third_party_file(file_path);
+        let is_plugin = self.plugin_fixture_files.contains_key(file_path);
+
+        for registration in registrations(body, content) {
+            info!(
+                "Found pytest-factoryboy registration of {} in {:?}: {}, {}",
+                registration.factory_class,
+                file_path,
+                registration.factory_fixture,
+                registration.model_fixture
+            );
+
+            let fixtures = [
+                (
+                    registration.factory_fixture.clone(),
+                    registration.factory_range,
+                    format!(
+                        "Factory fixture generated by pytest-factoryboy; returns `{}`.",
+                        registration.factory_class
+                    ),
+                    vec![],
+                ),
+                (
+                    registration.model_fixture,
+                    registration.model_range,
+                    format!(
+                        "Model fixture generated by pytest-factoryboy; returns an instance built by `{}`.",
+                        registration.factory_class
+                    ),
+                    vec![registration.factory_fixture],
+                ),
+            ];
+            for (name, range, docstring, dependencies) in fixtures {
+                let line = self.get_line_from_offset(range.start().to_usize(), line_index);
+                self.record_fixture_definition(FixtureDefinition {
+                    name,
+                    file_path: file_path.clone(),
+                    line,
+                    end_line: line,
+                    start_char: self
+                        .get_char_position_from_offset(range.start().to_usize(), line_index),
+                    end_char: self
+                        .get_char_position_from_offset(range.end().to_usize(), line_index),
+                    docstring: Some(docstring),
+                    is_third_party,
+                    is_plugin,
+                    dependencies,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::parse_module;
+
+    fn parse_registrations(content: &str) -> Vec<(String, String)> {
+        let parsed = parse_module(content).unwrap();
+        let ruff_python_ast::Mod::Module(module) = parsed else {
+            unreachable!()
+        };
+        registrations(&module.body, content)
+            .into_iter()
+            .map(|r| (r.factory_fixture, r.model_fixture))
+            .collect()
+    }
+
+    #[test]
+    fn test_underscore() {
+        assert_eq!(underscore("UserFactory"), "user_factory");
+        assert_eq!(underscore("HTTPClientFactory"), "http_client_factory");
+        assert_eq!(underscore("OAuth2Token"), "o_auth2_token");
+        assert_eq!(underscore("User"), "user");
+    }
+
+    #[test]
+    fn test_registration_names() {
+        let content = r#"
+import factory
+from pytest_factoryboy import register
+import pytest_factoryboy
+
+class Account:
+    pass
+
+class AccountFactory(factory.Factory):
+    class Meta:
+        model = Account
+
+class BookFactory(factory.django.DjangoModelFactory):
+    class Meta:
+        model = "library.Novel"
+
+register(UserFactory)
+register(UserFactory, "admin")
+register(AccountFactory, _name="owner")
+pytest_factoryboy.register(BookFactory)
+register(factories.ShopFactory)
+other.register(IgnoredFactory)
+
+@register
+class AuthorFactory(factory.Factory):
+    pass
+
+@register(_name="editor")
+class EditorFactory(factory.Factory):
+    pass
+"#;
+        let pairs = |f: &str, m: &str| (f.to_string(), m.to_string());
+        assert_eq!(
+            parse_registrations(content),
+            vec![
+                pairs("user_factory", "user"),
+                pairs("user_factory", "admin"),
+                pairs("account_factory", "owner"),
+                pairs("book_factory", "novel"),
+                pairs("shop_factory", "shop"),
+                pairs("author_factory", "author"),
+                pairs("editor_factory", "editor"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,694 @@
+//! Fixture dependency graph export.
+//!
+//! Builds the graph of "requester → requested fixture" edges, either for every
+//! project fixture or around a single root, and renders it as Graphviz DOT or
+//! Mermaid. A fixture root shows its transitive dependencies and everything
+//! (fixtures and tests) that transitively requests it; a test root (given as a
+//! pytest node ID) shows every fixture the test pulls in.
+//!
+//! The same resolution backs [`FixtureTree`], the text tree of a fixture's
+//! transitive dependencies (or, reversed, of everything requesting it), and
+//! [`FixtureUses`], the direct requesters of each definition of a fixture.
+
+use super::types::{FixtureDefinition, FixtureScope, FixtureUsage, TestItem, TestItemKind};
+use super::FixtureDatabase;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Output format of [`FixtureGraph::render`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    Mermaid,
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "dot" | "graphviz" => Ok(GraphFormat::Dot),
+            "mermaid" => Ok(GraphFormat::Mermaid),
+            other => Err(format!(
+                "Unknown graph format '{}' (expected \"dot\" or \"mermaid\")",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphNodeKind {
+    Fixture,
+    /// A test function, test class or module requesting fixtures.
+    Test,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct GraphNode {
+    pub name: String,
+    /// Defining file for fixtures (relative to the root when inside it),
+    /// pytest node ID for tests.
+    pub location: String,
+    pub kind: GraphNodeKind,
+    pub is_root: bool,
+}
+
+/// A fixture dependency graph. Edges point from the requester to the
+/// requested fixture, as indices into `nodes`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FixtureGraph {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<(usize, usize)>,
+}
+
+/// A node of a [`FixtureTree`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TreeNode {
+    /// Fixture name, or test name.
+    pub name: String,
+    /// `file:line` of fixtures (relative to the root when inside it), pytest
+    /// node ID of tests.
+    pub location: String,
+    pub kind: GraphNodeKind,
+    /// `None` for tests.
+    pub scope: Option<FixtureScope>,
+    /// Already expanded elsewhere in the tree: its children are omitted.
+    pub repeated: bool,
+    pub children: Vec<TreeNode>,
+}
+
+/// The transitive dependencies of a fixture as a tree, or with `reverse`,
+/// the fixtures and tests that transitively request it. There is one root
+/// per matching definition.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FixtureTree {
+    pub roots: Vec<TreeNode>,
+}
+
+/// A place requesting a fixture, see [`FixtureUses`].
+#[derive(Debug, Clone)]
+pub struct FixtureUse {
+    pub usage: FixtureUsage,
+    /// Requesting fixture name, or test node ID.
+    pub requester: String,
+    pub kind: GraphNodeKind,
+}
+
+/// A definition of a fixture and the usages resolving to it, in source order.
+#[derive(Debug, Clone)]
+pub struct FixtureUses {
+    pub definition: FixtureDefinition,
+    pub uses: Vec<FixtureUse>,
+}
+
+/// Identity of a graph node while building the graph.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum NodeKey {
+    Fixture(PathBuf, usize),
+    Test(String),
+}
+
+struct GraphBuilder<'a> {
+    root_path: &'a Path,
+    graph: FixtureGraph,
+    index: HashMap<NodeKey, usize>,
+}
+
+impl GraphBuilder<'_> {
+    /// Add a fixture node, returning its index and whether it is new.
+    fn fixture(&mut self, def: &FixtureDefinition) -> (usize, bool) {
+        let location = display_path(&def.file_path, self.root_path);
+        self.node(
+            NodeKey::Fixture(def.file_path.clone(), def.line),
+            GraphNode {
+                name: def.name.clone(),
+                location,
+                kind: GraphNodeKind::Fixture,
+                is_root: false,
+            },
+        )
+    }
+
+    fn test(&mut self, node_id: String, name: String) -> (usize, bool) {
+        self.node(
+            NodeKey::Test(node_id.clone()),
+            GraphNode {
+                name,
+                location: node_id,
+                kind: GraphNodeKind::Test,
+                is_root: false,
+            },
+        )
+    }
+
+    fn node(&mut self, key: NodeKey, node: GraphNode) -> (usize, bool) {
+        if let Some(&idx) = self.index.get(&key) {
+            return (idx, false);
+        }
+        let idx = self.graph.nodes.len();
+        self.graph.nodes.push(node);
+        self.index.insert(key, idx);
+        (idx, true)
+    }
+
+    fn edge(&mut self, from: usize, to: usize) {
+        if !self.graph.edges.contains(&(from, to)) {
+            self.graph.edges.push((from, to));
+        }
+    }
+}
+
+/// Something that requests a fixture.
+enum Requester {
+    Fixture(Box<FixtureDefinition>),
+    Test { node_id: String, name: String },
+}
+
+impl FixtureDatabase {
+    /// Build the fixture dependency graph.
+    ///
+    /// Without a root, every project fixture (third-party ones only appear as
+    /// dependencies) and its direct dependencies. `root` is either a fixture
+    /// name or a pytest node ID (`tests/test_x.py::TestA::test_b`) relative to
+    /// `root_path`. Returns an error message when the root can't be found.
+    pub fn fixture_graph(
+        &self,
+        root: Option<&str>,
+        root_path: &Path,
+    ) -> Result<FixtureGraph, String> {
+        let mut builder = GraphBuilder {
+            root_path,
+            graph: FixtureGraph::default(),
+            index: HashMap::new(),
+        };
+
+        match root {
+            None => self.build_full_graph(&mut builder),
+            Some(root) if root.contains("::") => self.build_test_graph(&mut builder, root)?,
+            Some(root) => self.build_fixture_graph(&mut builder, root)?,
+        }
+        Ok(builder.graph)
+    }
+
+    fn build_full_graph(&self, builder: &mut GraphBuilder) {
+        let mut defs: Vec<FixtureDefinition> = self
+            .definitions
+            .iter()
+            .flat_map(|entry| entry.value().clone())
+            .filter(|def| !def.is_third_party)
+            .collect();
+        defs.sort_by(|a, b| (&a.file_path, a.line, &a.name).cmp(&(&b.file_path, b.line, &b.name)));
+
+        for def in &defs {
+            let (from, _) = builder.fixture(def);
+            for dep in self.resolve_dependencies(def) {
+                let (to, _) = builder.fixture(&dep);
+                builder.edge(from, to);
+            }
+        }
+    }
+
+    fn build_fixture_graph(&self, builder: &mut GraphBuilder, name: &str) -> Result<(), String> {
+        let defs: Vec<FixtureDefinition> = self
+            .definitions
+            .get(name)
+            .map(|defs| defs.value().clone())
+            .unwrap_or_default();
+        if defs.is_empty() {
+            return Err(format!("No fixture named '{}'", name));
+        }
+        // Prefer project definitions; a third-party fixture is only the root
+        // when the project doesn't define (or override) it.
+        let project: Vec<FixtureDefinition> =
+            defs.iter().filter(|d| !d.is_third_party).cloned().collect();
+        let roots = if project.is_empty() { defs } else { project };
+
+        for def in &roots {
+            let (idx, _) = builder.fixture(def);
+            builder.graph.nodes[idx].is_root = true;
+        }
+        self.add_dependencies(builder, &roots);
+        self.add_requesters(builder, &roots);
+        Ok(())
+    }
+
+    fn build_test_graph(&self, builder: &mut GraphBuilder, node_id: &str) -> Result<(), String> {
+        // Parametrized case IDs select the same function
+        let node_id = node_id.split('[').next().unwrap_or(node_id);
+        let mut parts = node_id.split("::");
+        let file_part = parts.next().unwrap_or_default();
+        let names: Vec<&str> = parts.collect();
+
+        let not_found = || format!("No test found for '{}'", node_id);
+        let file_path = if Path::new(file_part).is_absolute() {
+            PathBuf::from(file_part)
+        } else {
+            builder.root_path.join(file_part)
+        };
+        let file_path = self.get_canonical_path(file_path);
+
+        let items = self.get_test_items(&file_path);
+        let mut level: &[TestItem] = &items;
+        let mut item = None;
+        for name in &names {
+            let found = level
+                .iter()
+                .find(|i| i.name == *name)
+                .ok_or_else(not_found)?;
+            level = &found.children;
+            item = Some(found);
+        }
+        let item = item.ok_or_else(not_found)?;
+        if item.kind != TestItemKind::Function {
+            return Err(format!("'{}' is not a test function", node_id));
+        }
+
+        let test_fixtures = self
+            .get_test_fixtures_at_position(&file_path, item.line, item.start_char)
+            .ok_or_else(not_found)?;
+
+        let (test_idx, _) = builder.test(node_id.to_string(), item.name.clone());
+        builder.graph.nodes[test_idx].is_root = true;
+
+        let mut direct = Vec::new();
+        for fixture in test_fixtures.fixtures {
+            let Some(def) = fixture.definition else {
+                continue;
+            };
+            let (idx, _) = builder.fixture(&def);
+            builder.edge(test_idx, idx);
+            direct.push(def);
+        }
+        self.add_dependencies(builder, &direct);
+        Ok(())
+    }
+
+    /// Add the transitive dependencies of `defs`.
+    fn add_dependencies(&self, builder: &mut GraphBuilder, defs: &[FixtureDefinition]) {
+        let mut queue: VecDeque<FixtureDefinition> = defs.iter().cloned().collect();
+        while let Some(def) = queue.pop_front() {
+            let (from, _) = builder.fixture(&def);
+            for dep in self.resolve_dependencies(&def) {
+                let (to, is_new) = builder.fixture(&dep);
+                builder.edge(from, to);
+                if is_new {
+                    queue.push_back(dep);
+                }
+            }
+        }
+    }
+
+    /// Add everything that transitively requests `defs`.
+    fn add_requesters(&self, builder: &mut GraphBuilder, defs: &[FixtureDefinition]) {
+        let mut queue: VecDeque<FixtureDefinition> = defs.iter().cloned().collect();
+        while let Some(def) = queue.pop_front() {
+            let (to, _) = builder.fixture(&def);
+            for (_, requester) in self.requesters_of(&def, builder.root_path) {
+                match requester {
+                    Requester::Fixture(caller) => {
+                        let (from, is_new) = builder.fixture(&caller);
+                        builder.edge(from, to);
+                        if is_new {
+                            queue.push_back(*caller);
+                        }
+                    }
+                    Requester::Test { node_id, name } => {
+                        let (from, _) = builder.test(node_id, name);
+                        builder.edge(from, to);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Usages resolving to `def`, in source order, with the fixture or test
+    /// each belongs to.
+    fn requesters_of(
+        &self,
+        def: &FixtureDefinition,
+        root_path: &Path,
+    ) -> Vec<(FixtureUsage, Requester)> {
+        let mut usages = self.find_references_for_definition(def);
+        usages.sort_by(|a, b| (&a.file_path, a.line).cmp(&(&b.file_path, b.line)));
+
+        let mut test_items: HashMap<PathBuf, Vec<TestItem>> = HashMap::new();
+        let mut requesters = Vec::new();
+        for usage in usages {
+            if usage.file_path == def.file_path && usage.line == def.line {
+                continue;
+            }
+
+            if let Some(caller) = self.fixture_at_line(&usage.file_path, usage.line) {
+                if caller.file_path != def.file_path || caller.line != def.line {
+                    requesters.push((usage, Requester::Fixture(Box::new(caller))));
+                }
+                continue;
+            }
+
+            let items = test_items
+                .entry(usage.file_path.clone())
+                .or_insert_with(|| self.get_test_items(&usage.file_path));
+            let file_id = relative_node_path(&usage.file_path, root_path);
+            let mut names: Vec<&str> = Vec::new();
+            let mut level: &[TestItem] = items;
+            while let Some(item) = level
+                .iter()
+                .find(|i| i.start_line <= usage.line && usage.line <= i.end_line)
+            {
+                names.push(&item.name);
+                level = &item.children;
+            }
+
+            // Outside any test: a module-level `pytestmark`
+            let name = names.last().map(|n| n.to_string()).unwrap_or_else(|| {
+                usage
+                    .file_path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| file_id.clone())
+            });
+            let node_id = std::iter::once(file_id.as_str())
+                .chain(names.iter().copied())
+                .collect::<Vec<_>>()
+                .join("::");
+            requesters.push((usage, Requester::Test { node_id, name }));
+        }
+        requesters
+    }
+
+    /// Build the dependency tree of the fixture `name`, or with `reverse` the
+    /// tree of what requests it.
+    ///
+    /// With `from` (a test file or directory), the tree starts at the
+    /// definition visible from there; otherwise at every project definition
+    /// of the fixture (or its third-party ones when the project has none).
+    /// Returns an error message when there is no such fixture.
+    pub fn fixture_tree(
+        &self,
+        name: &str,
+        from: Option<&Path>,
+        reverse: bool,
+        root_path: &Path,
+    ) -> Result<FixtureTree, String> {
+        let roots = match from {
+            Some(from) => {
+                let available = self.get_available_fixtures(&self.lookup_path(from));
+                let def = available
+                    .iter()
+                    .find(|def| def.name == name)
+                    .ok_or_else(|| {
+                        format!(
+                            "No fixture named '{}' visible from {}",
+                            name,
+                            from.display()
+                        )
+                    })?;
+                vec![def.clone()]
+            }
+            None => {
+                let defs: Vec<FixtureDefinition> = self
+                    .definitions
+                    .get(name)
+                    .map(|defs| defs.value().clone())
+                    .unwrap_or_default();
+                if defs.is_empty() {
+                    return Err(format!("No fixture named '{}'", name));
+                }
+                let project: Vec<FixtureDefinition> =
+                    defs.iter().filter(|d| !d.is_third_party).cloned().collect();
+                if project.is_empty() {
+                    defs
+                } else {
+                    project
+                }
+            }
+        };
+
+        let mut expanded = HashSet::new();
+        Ok(FixtureTree {
+            roots: roots
+                .iter()
+                .map(|def| self.tree_node(def, reverse, root_path, &mut expanded))
+                .collect(),
+        })
+    }
+
+    /// Every definition of the fixture `name` with the fixtures and tests
+    /// requesting it, so that overridden copies are reported separately.
+    /// Third-party definitions are only included when something requests
+    /// them. Returns an error message when there is no such fixture.
+    pub fn fixture_uses(&self, name: &str, root_path: &Path) -> Result<Vec<FixtureUses>, String> {
+        let mut defs: Vec<FixtureDefinition> = self
+            .definitions
+            .get(name)
+            .map(|defs| defs.value().clone())
+            .unwrap_or_default();
+        if defs.is_empty() {
+            return Err(format!("No fixture named '{}'", name));
+        }
+        defs.sort_by(|a, b| (&a.file_path, a.line).cmp(&(&b.file_path, b.line)));
+
+        Ok(defs
+            .into_iter()
+            .map(|definition| {
+                let uses = self
+                    .requesters_of(&definition, root_path)
+                    .into_iter()
+                    .map(|(usage, requester)| match requester {
+                        Requester::Fixture(caller) => FixtureUse {
+                            usage,
+                            requester: caller.name,
+                            kind: GraphNodeKind::Fixture,
+                        },
+                        Requester::Test { node_id, .. } => FixtureUse {
+                            usage,
+                            requester: node_id,
+                            kind: GraphNodeKind::Test,
+                        },
+                    })
+                    .collect();
+                FixtureUses { definition, uses }
+            })
+            .filter(|uses| !uses.definition.is_third_party || !uses.uses.is_empty())
+            .collect())
+    }
+
+    /// The tree below `def`, expanding each fixture only once.
+    fn tree_node(
+        &self,
+        def: &FixtureDefinition,
+        reverse: bool,
+        root_path: &Path,
+        expanded: &mut HashSet<(PathBuf, usize)>,
+    ) -> TreeNode {
+        let mut node = TreeNode {
+            name: def.name.clone(),
+            location: format!("{}:{}", display_path(&def.file_path, root_path), def.line),
+            kind: GraphNodeKind::Fixture,
+            scope: Some(def.scope),
+            repeated: false,
+            children: Vec::new(),
+        };
+        let first = expanded.insert((def.file_path.clone(), def.line));
+
+        if !reverse {
+            let dependencies = self.resolve_dependencies(def);
+            node.repeated = !first && !dependencies.is_empty();
+            if first {
+                node.children = dependencies
+                    .iter()
+                    .map(|dep| self.tree_node(dep, reverse, root_path, expanded))
+                    .collect();
+            }
+            return node;
+        }
+
+        let requesters = self.requesters_of(def, root_path);
+        node.repeated = !first && !requesters.is_empty();
+        if first {
+            // A requester may request the fixture several times (a parameter
+            // and `usefixtures`, or a repeated name); list it once
+            let mut seen = HashSet::new();
+            node.children = requesters
+                .into_iter()
+                .filter(|(_, requester)| {
+                    seen.insert(match requester {
+                        Requester::Fixture(caller) => {
+                            NodeKey::Fixture(caller.file_path.clone(), caller.line)
+                        }
+                        Requester::Test { node_id, .. } => NodeKey::Test(node_id.clone()),
+                    })
+                })
+                .map(|(_, requester)| match requester {
+                    Requester::Fixture(caller) => {
+                        self.tree_node(&caller, reverse, root_path, expanded)
+                    }
+                    Requester::Test { node_id, name } => TreeNode {
+                        name,
+                        location: node_id,
+                        kind: GraphNodeKind::Test,
+                        scope: None,
+                        repeated: false,
+                        children: Vec::new(),
+                    },
+                })
+                .collect();
+        }
+        node
+    }
+
+    /// The innermost fixture in `file_path` whose definition spans `line`.
+    pub(crate) fn fixture_at_line(
+        &self,
+        file_path: &Path,
+        line: usize,
+    ) -> Option<FixtureDefinition> {
+        let names: Vec<String> = self
+            .file_definitions
+            .get(file_path)
+            .map(|entry| entry.value().iter().cloned().collect())?;
+
+        names
+            .iter()
+            .filter_map(|name| self.definitions.get(name))
+            .flat_map(|defs| defs.value().clone())
+            .filter(|d| d.file_path == file_path && d.line <= line && line <= d.end_line)
+            .max_by_key(|d| d.line)
+    }
+}
+
+impl FixtureGraph {
+    pub fn render(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Dot => self.to_dot(),
+            GraphFormat::Mermaid => self.to_mermaid(),
+        }
+    }
+
+    /// Render as a Graphviz `digraph`: fixtures are ellipses, tests boxes and
+    /// the root nodes bold.
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph fixtures {\n    rankdir=LR;\n");
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let mut attrs = vec![format!(
+                "label=\"{}\\n{}\"",
+                escape_dot(&node.name),
+                escape_dot(&node.location)
+            )];
+            if node.kind == GraphNodeKind::Test {
+                attrs.push("shape=box".to_string());
+            }
+            if node.is_root {
+                attrs.push("style=bold".to_string());
+            }
+            let _ = writeln!(out, "    n{} [{}];", idx, attrs.join(", "));
+        }
+        for (from, to) in &self.edges {
+            let _ = writeln!(out, "    n{} -> n{};", from, to);
+        }
+        out.push_str("}\n");
+        out
+    }
+
+    /// Render as a Mermaid `flowchart`: fixtures are rounded, tests
+    /// rectangles and the root nodes have a thick border.
+    pub fn to_mermaid(&self) -> String {
+        let mut out = String::from("flowchart LR\n");
+        for (idx, node) in self.nodes.iter().enumerate() {
+            let label = format!(
+                "{}<br/><small>{}</small>",
+                escape_mermaid(&node.name),
+                escape_mermaid(&node.location)
+            );
+            let _ = match node.kind {
+                GraphNodeKind::Fixture => writeln!(out, "    n{}([\"{}\"])", idx, label),
+                GraphNodeKind::Test => writeln!(out, "    n{}[\"{}\"]", idx, label),
+            };
+        }
+        for (from, to) in &self.edges {
+            let _ = writeln!(out, "    n{} --> n{}", from, to);
+        }
+        for (idx, node) in self.nodes.iter().enumerate() {
+            if node.is_root {
+                let _ = writeln!(out, "    style n{} stroke-width:3px", idx);
+            }
+        }
+        out
+    }
+}
+
+impl FixtureTree {
+    /// Render like `cargo tree`: fixtures as `name [scope] file:line`, tests
+    /// as their node ID, and `(*)` after fixtures expanded elsewhere.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for (idx, root) in self.roots.iter().enumerate() {
+            if idx > 0 {
+                out.push('\n');
+            }
+            let _ = writeln!(out, "{}", root.label());
+            render_children(&mut out, &root.children, "");
+        }
+        out
+    }
+}
+
+impl TreeNode {
+    fn label(&self) -> String {
+        let mut label = match self.scope {
+            Some(scope) => format!("{} [{}] {}", self.name, scope.as_str(), self.location),
+            None => self.location.clone(),
+        };
+        if self.repeated {
+            label.push_str(" (*)");
+        }
+        label
+    }
+}
+
+fn render_children(out: &mut String, children: &[TreeNode], prefix: &str) {
+    for (idx, child) in children.iter().enumerate() {
+        let last = idx + 1 == children.len();
+        let (branch, indent) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        let _ = writeln!(out, "{}{}{}", prefix, branch, child.label());
+        render_children(out, &child.children, &format!("{}{}", prefix, indent));
+    }
+}
+
+/// Path of `file_path` relative to `root_path` when inside it.
+fn display_path(file_path: &Path, root_path: &Path) -> String {
+    file_path
+        .strip_prefix(root_path)
+        .unwrap_or(file_path)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// File part of a pytest node ID: relative to `root_path`, `/`-separated.
+fn relative_node_path(file_path: &Path, root_path: &Path) -> String {
+    match file_path.strip_prefix(root_path) {
+        Ok(relative) => relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+        Err(_) => file_path.to_string_lossy().into_owned(),
+    }
+}
+
+fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_mermaid(s: &str) -> String {
+    s.replace('"', "#quot;")
+        .replace('<', "#lt;")
+        .replace('>', "#gt;")
+}
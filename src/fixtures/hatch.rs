@@ -0,0 +1,304 @@
+//! Hatch environment discovery.
+//!
+//! Hatch keeps a project's virtual environments outside of it, in
+//! `<data dir>/env/virtual/<project name>/<project id>/<env name>`, where
+//! the project id is a hash of the project's path and the `default`
+//! environment is named after the project. An environment configured with a
+//! `path` (in `hatch.toml` or `[tool.hatch.envs.<name>]`) lives there
+//! instead, relative to the project root.
+
+use super::env_path;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::{debug, info};
+
+/// A Hatch project: its normalized name and its `envs` configuration.
+#[derive(Debug)]
+pub(crate) struct HatchProject {
+    pub(crate) name: String,
+    pub(crate) envs: toml::Table,
+}
+
+/// A project name as Hatch names its directories: lowercase, with runs of
+/// `-`, `_` and `.` replaced by a single `-`.
+fn normalize_name(name: &str) -> String {
+    let mut normalized = String::with_capacity(name.len());
+    for ch in name.chars() {
+        if matches!(ch, '-' | '_' | '.') {
+            if !normalized.ends_with('-') {
+                normalized.push('-');
+            }
+        } else {
+            normalized.extend(ch.to_lowercase());
+        }
+    }
+    normalized
+}
+
+fn read_toml(path: &Path) -> Option<toml::Table> {
+    let content = std::fs::read_to_string(path).ok()?;
+    toml::from_str(&content).ok()
+}
+
+/// Read the Hatch configuration of the project at `root_path`, when it uses
+/// Hatch: `hatch.toml` environments win over `[tool.hatch.envs]`.
+pub(crate) fn read_hatch_project(root_path: &Path) -> Option<HatchProject> {
+    let pyproject = read_toml(&root_path.join("pyproject.toml")).unwrap_or_default();
+    let hatch_toml = read_toml(&root_path.join("hatch.toml"));
+    let tool_hatch = pyproject
+        .get("tool")
+        .and_then(|tool| tool.get("hatch"))
+        .and_then(toml::Value::as_table);
+    if hatch_toml.is_none() && tool_hatch.is_none() {
+        return None;
+    }
+
+    let name = pyproject
+        .get("project")
+        .and_then(|project| project.get("name"))
+        .and_then(toml::Value::as_str)
+        .map(str::to_string)
+        .or_else(|| Some(root_path.file_name()?.to_string_lossy().into_owned()))?;
+    let mut envs = tool_hatch
+        .and_then(|hatch| hatch.get("envs"))
+        .and_then(toml::Value::as_table)
+        .cloned()
+        .unwrap_or_default();
+    if let Some(hatch_envs) = hatch_toml
+        .as_ref()
+        .and_then(|hatch| hatch.get("envs"))
+        .and_then(toml::Value::as_table)
+    {
+        envs.extend(hatch_envs.clone());
+    }
+    Some(HatchProject {
+        name: normalize_name(&name),
+        envs,
+    })
+}
+
+/// The environments tests are likely run in, in order of preference: a
+/// configured `test` environment, the `default` one, then those of
+/// `hatch test` (`hatch-test`, `hatch-test.py3.12`, ...).
+fn test_env_names(project: &HatchProject) -> Vec<&'static str> {
+    let mut names = Vec::new();
+    if project.envs.contains_key("test") {
+        names.push("test");
+    }
+    names.extend(["default", "hatch-test"]);
+    names
+}
+
+/// The directory Hatch keeps virtual environments in:
+/// `$HATCH_DATA_DIR/env/virtual`, in the platform's data directory by default.
+pub(crate) fn hatch_virtual_dir() -> Option<PathBuf> {
+    let data_dir = if let Some(data_dir) = env_path("HATCH_DATA_DIR") {
+        data_dir
+    } else if cfg!(windows) {
+        env_path("LOCALAPPDATA")?.join("hatch")
+    } else if cfg!(target_os = "macos") {
+        env_path("HOME")?
+            .join("Library")
+            .join("Application Support")
+            .join("hatch")
+    } else if let Some(xdg) = env_path("XDG_DATA_HOME") {
+        xdg.join("hatch")
+    } else {
+        env_path("HOME")?.join(".local").join("share").join("hatch")
+    };
+    Some(data_dir.join("env").join("virtual"))
+}
+
+/// Whether an editable install in the environment at `env_path` points into
+/// `root_path`: Hatch installs the project in development mode with a `.pth`
+/// file holding its source directory.
+fn installs_project(env_path: &Path, root_path: &Path) -> bool {
+    let site_packages_dirs = std::fs::read_dir(env_path.join("lib"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path().join("site-packages"))
+        .chain(std::iter::once(env_path.join("Lib").join("site-packages")));
+    let root = root_path.to_string_lossy();
+    site_packages_dirs
+        .filter_map(|dir| std::fs::read_dir(dir).ok())
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "pth"))
+        .filter_map(|entry| std::fs::read_to_string(entry.path()).ok())
+        .any(|content| content.lines().any(|line| line.starts_with(root.as_ref())))
+}
+
+/// Locate the test environment of `project` (at `root_path`) among the
+/// environments Hatch created in `virtual_dir`.
+pub(crate) fn locate_hatch_environment(
+    root_path: &Path,
+    project: &HatchProject,
+    virtual_dir: Option<&Path>,
+) -> Option<PathBuf> {
+    for env_name in test_env_names(project) {
+        // An environment with an explicit path
+        let configured_path = project
+            .envs
+            .get(env_name)
+            .and_then(|env| env.get("path"))
+            .and_then(toml::Value::as_str);
+        if let Some(path) = configured_path {
+            let env_path = root_path.join(path);
+            if env_path.is_dir() {
+                return Some(env_path);
+            }
+            continue;
+        }
+
+        let Some(virtual_dir) = virtual_dir else {
+            continue;
+        };
+        let venv_name = if env_name == "default" {
+            project.name.as_str()
+        } else {
+            env_name
+        };
+        // One directory per path the project was at; the matrix variants of
+        // an environment are suffixed with `.<variables>`
+        let project_ids = std::fs::read_dir(virtual_dir.join(&project.name));
+        let candidates: Vec<PathBuf> = project_ids
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|project_id| std::fs::read_dir(project_id.path()).ok())
+            .flatten()
+            .flatten()
+            .filter(|env| {
+                let name = env.file_name();
+                let name = name.to_string_lossy();
+                name == venv_name
+                    || name
+                        .strip_prefix(venv_name)
+                        .is_some_and(|rest| rest.starts_with('.'))
+            })
+            .map(|env| env.path())
+            .filter(|path| path.is_dir())
+            .collect();
+        debug!("Hatch environments for {:?}: {:?}", env_name, candidates);
+
+        let modified = |path: &PathBuf| {
+            std::fs::metadata(path)
+                .and_then(|m| m.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH)
+        };
+        let found = candidates
+            .iter()
+            .find(|path| installs_project(path, root_path))
+            .or_else(|| candidates.iter().max_by_key(|path| modified(path)));
+        if let Some(found) = found {
+            return Some(found.clone());
+        }
+    }
+    None
+}
+
+/// Locate the test environment of a Hatch project.
+pub(crate) fn find_hatch_environment(root_path: &Path) -> Option<PathBuf> {
+    let project = read_hatch_project(root_path)?;
+    debug!("Found Hatch project {:?}", project.name);
+    let env_path = locate_hatch_environment(root_path, &project, hatch_virtual_dir().as_deref())?;
+    info!("Using Hatch environment: {:?}", env_path);
+    Some(env_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_normalize_name() {
+        assert_eq!(normalize_name("My_Project"), "my-project");
+        assert_eq!(normalize_name("a.-_b"), "a-b");
+    }
+
+    #[test]
+    fn test_read_hatch_project() {
+        let root = tempdir().unwrap();
+        fs::write(
+            root.path().join("pyproject.toml"),
+            "[project]\nname = \"Demo_Pkg\"\n\n[tool.hatch.envs.test]\ndependencies = [\"pytest\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            root.path().join("hatch.toml"),
+            "[envs.default]\npath = \".hatch\"\n",
+        )
+        .unwrap();
+
+        let project = read_hatch_project(root.path()).unwrap();
+        assert_eq!(project.name, "demo-pkg");
+        assert!(project.envs.contains_key("test"));
+        assert!(project.envs.contains_key("default"));
+        assert_eq!(
+            test_env_names(&project),
+            vec!["test", "default", "hatch-test"]
+        );
+
+        // Not a Hatch project
+        let other = tempdir().unwrap();
+        fs::write(
+            other.path().join("pyproject.toml"),
+            "[project]\nname = \"x\"\n",
+        )
+        .unwrap();
+        assert!(read_hatch_project(other.path()).is_none());
+    }
+
+    #[test]
+    fn test_locate_hatch_environment() {
+        let root = tempdir().unwrap();
+        let data = tempdir().unwrap();
+        fs::write(
+            root.path().join("pyproject.toml"),
+            "[project]\nname = \"demo\"\n\n[tool.hatch.envs.default]\ndependencies = [\"pytest\"]\n",
+        )
+        .unwrap();
+        let project = read_hatch_project(root.path()).unwrap();
+        assert_eq!(
+            locate_hatch_environment(root.path(), &project, Some(data.path())),
+            None
+        );
+
+        // The same project checked out elsewhere, and this checkout
+        let elsewhere = data.path().join("demo").join("AAAAAAAA").join("demo");
+        fs::create_dir_all(&elsewhere).unwrap();
+        let here = data.path().join("demo").join("BBBBBBBB").join("demo");
+        let site_packages = here.join("lib").join("python3.12").join("site-packages");
+        fs::create_dir_all(&site_packages).unwrap();
+        fs::write(
+            site_packages.join("_demo.pth"),
+            format!("{}\n", root.path().join("src").display()),
+        )
+        .unwrap();
+
+        assert_eq!(
+            locate_hatch_environment(root.path(), &project, Some(data.path())),
+            Some(here)
+        );
+    }
+
+    #[test]
+    fn test_locate_hatch_environment_configured_path() {
+        let root = tempdir().unwrap();
+        fs::write(
+            root.path().join("hatch.toml"),
+            "[envs.test]\npath = \".envs/test\"\n",
+        )
+        .unwrap();
+        fs::create_dir_all(root.path().join(".envs/test")).unwrap();
+
+        let project = read_hatch_project(root.path()).unwrap();
+        assert_eq!(
+            locate_hatch_environment(root.path(), &project, None),
+            Some(root.path().join(".envs/test"))
+        );
+    }
+}
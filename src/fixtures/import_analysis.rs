@@ -8,7 +8,7 @@
 //! # Design
 //!
 //! [`parse_import_layout`] is the main entry point.  It tries to produce an
-//! [`ImportLayout`] via the `rustpython-parser` AST, which correctly handles
+//! [`ImportLayout`] via the `ruff_python_parser` AST, which correctly handles
 //! multiline parenthesised imports, inline comments, and every other edge case
 //! that confounds a naive line-scanner.  If the file has syntax errors (common
 //! during active editing) it falls back to a simpler line-scan that mirrors
@@ -22,8 +22,7 @@
 use crate::fixtures::imports::is_stdlib_module;
 use crate::fixtures::string_utils::replace_identifier;
 use crate::fixtures::types::TypeImportSpec;
-use rustpython_parser::ast::{Mod, Stmt};
-use rustpython_parser::Mode;
+use ruff_python_ast::{Mod, Stmt};
 use std::collections::HashMap;
 use tracing::{debug, info, warn};
 
@@ -203,7 +202,7 @@ impl ImportLayout {
 
 /// Parse the import layout of a Python source file.
 ///
-/// Tries AST-based parsing first (via `rustpython-parser`); falls back to a
+/// Tries AST-based parsing first (via `ruff_python_parser`); falls back to a
 /// simpler line-scan if the file has syntax errors (e.g. during active
 /// editing).
 ///
@@ -212,7 +211,7 @@ impl ImportLayout {
 /// - `from_imports` / `bare_imports` — individual import statements,
 /// - `lines` — the file lines (for `TextEdit` character-length lookups).
 pub fn parse_import_layout(content: &str) -> ImportLayout {
-    match rustpython_parser::parse(content, Mode::Module, "") {
+    match crate::fixtures::parse_module(content) {
         Ok(ast) => parse_layout_from_ast(&ast, content),
         Err(e) => {
             warn!("AST parse failed ({e}), using string fallback for import layout");
@@ -591,7 +590,7 @@ fn split_from_import(statement: &str) -> Option<(&str, &str)> {
 
 // ─── AST-based parser ─────────────────────────────────────────────────────────
 
-fn parse_layout_from_ast(ast: &rustpython_parser::ast::Mod, content: &str) -> ImportLayout {
+fn parse_layout_from_ast(ast: &ruff_python_ast::Mod, content: &str) -> ImportLayout {
     let line_starts = build_line_starts(content);
     let offset_to_line = |offset: usize| -> usize {
         line_starts
@@ -623,12 +622,9 @@ fn parse_layout_from_ast(ast: &rustpython_parser::ast::Mod, content: &str) -> Im
                     .as_ref()
                     .map(|m| m.to_string())
                     .unwrap_or_default();
-                if let Some(ref level) = import_from.level {
-                    let level_val = level.to_usize();
-                    if level_val > 0 {
-                        let dots = ".".repeat(level_val);
-                        module = dots + &module;
-                    }
+                if import_from.level > 0 {
+                    let dots = ".".repeat(import_from.level as usize);
+                    module = dots + &module;
                 }
 
                 let names: Vec<ImportedName> = import_from
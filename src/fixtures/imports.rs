@@ -9,7 +9,7 @@
 
 use super::types::TypeImportSpec;
 use super::FixtureDatabase;
-use rustpython_parser::ast::{Expr, Stmt};
+use ruff_python_ast::{Expr, Stmt};
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, OnceLock};
@@ -158,8 +158,8 @@ impl FixtureDatabase {
                 // level indicates how many parent directories to go up:
                 // level=1 means "from . import" (current package)
                 // level=2 means "from .. import" (parent package)
-                if let Some(ref level) = import_from.level {
-                    let dots = ".".repeat(level.to_usize());
+                if import_from.level > 0 {
+                    let dots = ".".repeat(import_from.level as usize);
                     module = dots + &module;
                 }
 
@@ -252,26 +252,20 @@ impl FixtureDatabase {
             modules.clear();
 
             match value {
-                Expr::Constant(c) => {
-                    if let rustpython_parser::ast::Constant::Str(s) = &c.value {
-                        modules.push(s.to_string());
-                    }
+                Expr::StringLiteral(s) => {
+                    modules.push(s.value.to_string());
                 }
                 Expr::List(list) => {
                     for elt in &list.elts {
-                        if let Expr::Constant(c) = elt {
-                            if let rustpython_parser::ast::Constant::Str(s) = &c.value {
-                                modules.push(s.to_string());
-                            }
+                        if let Expr::StringLiteral(s) = elt {
+                            modules.push(s.value.to_string());
                         }
                     }
                 }
                 Expr::Tuple(tuple) => {
                     for elt in &tuple.elts {
-                        if let Expr::Constant(c) = elt {
-                            if let rustpython_parser::ast::Constant::Str(s) = &c.value {
-                                modules.push(s.to_string());
-                            }
+                        if let Expr::StringLiteral(s) = elt {
+                            modules.push(s.value.to_string());
                         }
                     }
                 }
@@ -342,9 +336,18 @@ impl FixtureDatabase {
         self.find_module_file(&remaining, &current_dir)
     }
 
-    /// Resolve an absolute import by searching up the directory tree,
-    /// then falling back to site-packages paths for venv plugin modules.
+    /// Resolve an absolute import in the directories of pytest's `pythonpath`
+    /// option, then by searching up the directory tree (including the `src/`
+    /// directory of each ancestor, for src-layout projects), then in the
+    /// `PYTHONPATH` directories, falling back to site-packages paths for venv
+    /// plugin modules.
     fn resolve_absolute_import(&self, module_path: &str, start_dir: &Path) -> Option<PathBuf> {
+        for dir in self.ini_pythonpath(start_dir) {
+            if let Some(path) = self.find_module_file(module_path, &dir) {
+                return Some(path);
+            }
+        }
+
         let mut current_dir = start_dir.to_path_buf();
 
         loop {
@@ -352,6 +355,13 @@ impl FixtureDatabase {
                 return Some(path);
             }
 
+            let src_dir = current_dir.join("src");
+            if src_dir.is_dir() {
+                if let Some(path) = self.find_module_file(module_path, &src_dir) {
+                    return Some(path);
+                }
+            }
+
             // Go up one directory
             match current_dir.parent() {
                 Some(parent) => current_dir = parent.to_path_buf(),
@@ -359,6 +369,17 @@ impl FixtureDatabase {
             }
         }
 
+        if let Some(pythonpath) = std::env::var_os("PYTHONPATH") {
+            for dir in std::env::split_paths(&pythonpath) {
+                if dir.as_os_str().is_empty() {
+                    continue;
+                }
+                if let Some(path) = self.find_module_file(module_path, &dir) {
+                    return Some(path);
+                }
+            }
+        }
+
         // Fallback: search in site-packages paths (for venv plugin pytest_plugins)
         for sp in self.site_packages_paths.lock().unwrap().iter() {
             if let Some(path) = self.find_module_file(module_path, sp) {
@@ -409,10 +430,15 @@ impl FixtureDatabase {
                     return Some(package_init);
                 }
             } else {
-                // Not the last part - must be a directory
+                // Not the last part - must be a directory, or a package known
+                // only from the cache (e.g. `from .fixtures.db import *` with
+                // unsaved files)
                 current_path = current_path.join(part);
                 if !current_path.is_dir() {
-                    return None;
+                    let package_init = self.get_canonical_path(current_path.join("__init__.py"));
+                    if !self.file_cache.contains_key(&package_init) {
+                        return None;
+                    }
                 }
             }
         }
@@ -496,7 +522,7 @@ impl FixtureDatabase {
 
         let line_index = self.get_line_index(canonical_path, content);
 
-        if let rustpython_parser::ast::Mod::Module(module) = parsed.as_ref() {
+        if let ruff_python_ast::Mod::Module(module) = parsed.as_ref() {
             let imports = self.extract_fixture_imports(&module.body, canonical_path, &line_index);
 
             for import in imports {
@@ -623,10 +649,11 @@ pub(crate) fn is_stdlib_module(module: &str) -> bool {
 /// Try to locate the Python interpreter inside a virtual environment.
 ///
 /// Checks the standard Unix (`bin/python3`, `bin/python`) and Windows
-/// (`Scripts/python3.exe`, `Scripts/python.exe`) layouts in that order.
+/// (`Scripts/python3.exe`, `Scripts/python.exe`) layouts in that order, then
+/// the Windows conda layout (`python.exe` at the root of the environment).
 /// Returns the first path that resolves to an existing regular file (or
 /// symlink to one).
-fn find_venv_python(venv_path: &Path) -> Option<PathBuf> {
+pub(crate) fn find_venv_python(venv_path: &Path) -> Option<PathBuf> {
     // Unix / macOS layout
     for name in &["python3", "python"] {
         let candidate = venv_path.join("bin").join(name);
@@ -641,9 +668,39 @@ fn find_venv_python(venv_path: &Path) -> Option<PathBuf> {
             return Some(candidate);
         }
     }
+    // Windows conda layout
+    let candidate = venv_path.join("python.exe");
+    if candidate.is_file() {
+        return Some(candidate);
+    }
     None
 }
 
+/// The environment prefix of the Python interpreter at `python`: its
+/// `sys.prefix`, or, when it cannot be run, the directory above its `bin`
+/// (`Scripts` on Windows) directory.
+pub(crate) fn python_prefix(python: &Path) -> Option<PathBuf> {
+    match std::process::Command::new(python)
+        .args(["-I", "-c", "import sys; print(sys.prefix)"])
+        .output()
+    {
+        Ok(output) if output.status.success() => {
+            let prefix = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            if !prefix.is_empty() {
+                return Some(PathBuf::from(prefix));
+            }
+        }
+        Ok(output) => debug!("{:?} exited with {}", python, output.status),
+        Err(e) => debug!("Failed to run {:?}: {}", python, e),
+    }
+
+    let dir = python.parent()?;
+    match dir.file_name() {
+        Some(name) if name == "bin" || name == "Scripts" => dir.parent().map(Path::to_path_buf),
+        _ => Some(dir.to_path_buf()),
+    }
+}
+
 /// Attempt to populate [`RUNTIME_STDLIB_MODULES`] by querying the Python
 /// interpreter found inside `venv_path`.
 ///
@@ -883,11 +940,7 @@ impl FixtureDatabase {
                 }
 
                 Stmt::ImportFrom(import_from) => {
-                    let level = import_from
-                        .level
-                        .as_ref()
-                        .map(|l| l.to_usize())
-                        .unwrap_or(0);
+                    let level = import_from.level as usize;
                     let raw_module = import_from
                         .module
                         .as_ref()
@@ -1032,6 +1085,31 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_find_venv_python_windows_conda() {
+        let dir = TempDir::new("fvp_win_conda");
+        touch(&dir.path().join("python.exe"));
+        touch(&dir.path().join("Lib/site-packages/pytest.py"));
+        let result = find_venv_python(dir.path());
+        assert_eq!(result, Some(dir.path().join("python.exe")));
+    }
+
+    #[test]
+    fn test_python_prefix_of_unrunnable_interpreter() {
+        // Empty files can't be run: the prefix is derived from the path
+        let dir = TempDir::new("pp_layout");
+        touch(&dir.path().join("env/bin/python3"));
+        touch(&dir.path().join("conda/python.exe"));
+        assert_eq!(
+            python_prefix(&dir.path().join("env/bin/python3")),
+            Some(dir.path().join("env"))
+        );
+        assert_eq!(
+            python_prefix(&dir.path().join("conda/python.exe")),
+            Some(dir.path().join("conda"))
+        );
+    }
+
     #[test]
     fn test_find_venv_python_not_found() {
         let dir = TempDir::new("fvp_empty");
@@ -0,0 +1,301 @@
+//! Incremental re-analysis of open files.
+//!
+//! Most edits to a large conftest.py touch a single function. When an edit
+//! falls inside one top-level function, only that function is re-parsed and
+//! re-analyzed: what the rest of the file contributed is kept, moved by the
+//! number of lines the edit added or removed. Edits anywhere else (imports,
+//! classes, module-level assignments) analyze the whole file again.
+
+use super::types::TypeImportSpec;
+use super::FixtureDatabase;
+use ruff_python_ast::{Mod, Stmt};
+use ruff_text_size::Ranged;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::debug;
+
+/// Analysis passes that walk the whole module, so their results can't be
+/// attributed to a single function.
+const MODULE_PASSES: &[&str] = &["lazy_fixture", "lf(", "pytest_factoryboy", "pytest_cases"];
+
+/// A top-level function of an open file.
+#[derive(Debug, Clone)]
+pub(crate) struct FunctionSpan {
+    /// Byte range of the statement, decorators included.
+    pub(crate) start: usize,
+    pub(crate) end: usize,
+    /// The names the function contributes to the module-level names.
+    pub(crate) module_level_names: HashSet<String>,
+}
+
+/// What re-analyzing one function of a file needs from its last full analysis.
+#[derive(Debug, Default)]
+pub(crate) struct ModuleOutline {
+    pub(crate) functions: Vec<FunctionSpan>,
+    pub(crate) import_map: Arc<HashMap<String, TypeImportSpec>>,
+    pub(crate) type_aliases: HashMap<String, String>,
+}
+
+/// The byte range of `old` that was replaced to get `new`, as
+/// `(start, old_end, new_end)`.
+fn changed_range(old: &str, new: &str) -> (usize, usize, usize) {
+    let (old, new) = (old.as_bytes(), new.as_bytes());
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    (prefix, old.len() - suffix, new.len() - suffix)
+}
+
+fn count_lines(text: &str) -> usize {
+    memchr::memchr_iter(b'\n', text.as_bytes()).count()
+}
+
+impl FixtureDatabase {
+    /// Record what incremental re-analysis of an open file needs.
+    pub(crate) fn record_module_outline(
+        &self,
+        file_path: &Path,
+        body: &[Stmt],
+        import_map: Arc<HashMap<String, TypeImportSpec>>,
+        type_aliases: HashMap<String, String>,
+    ) {
+        let functions = body
+            .iter()
+            .filter(|stmt| matches!(stmt, Stmt::FunctionDef(_)))
+            .map(|stmt| {
+                let mut module_level_names = HashSet::new();
+                self.collect_module_level_names(stmt, &mut module_level_names);
+                FunctionSpan {
+                    start: stmt.range().start().to_usize(),
+                    end: stmt.range().end().to_usize(),
+                    module_level_names,
+                }
+            })
+            .collect();
+        self.module_outlines.insert(
+            file_path.to_path_buf(),
+            ModuleOutline {
+                functions,
+                import_map,
+                type_aliases,
+            },
+        );
+    }
+
+    /// Re-analyze only the top-level function the edit from `old` to `new`
+    /// falls in. Returns `false`, having changed nothing, when the whole file
+    /// must be analyzed instead.
+    pub(crate) fn reanalyze_edited_function(
+        &self,
+        file_path: &PathBuf,
+        old: &str,
+        new: &str,
+    ) -> bool {
+        if MODULE_PASSES
+            .iter()
+            .any(|pass| old.contains(pass) || new.contains(pass))
+        {
+            return false;
+        }
+        let Some(mut outline) = self.module_outlines.get_mut(file_path) else {
+            return false;
+        };
+
+        let (edit_start, old_end, new_end) = changed_range(old, new);
+        let Some(index) = outline
+            .functions
+            .iter()
+            .position(|f| f.start <= edit_start && old_end <= f.end)
+        else {
+            return false;
+        };
+        let (start, end) = (outline.functions[index].start, outline.functions[index].end);
+        // Where the function ends after the edit
+        let new_function_end = new_end + (end - old_end);
+
+        // Parse the function alone, preceded by blank lines so that its lines
+        // and columns are the ones it has in the file.
+        let padding = count_lines(&old[..start]);
+        let mut source = "\n".repeat(padding);
+        source.push_str(&new[start..new_function_end]);
+        let Ok(Mod::Module(module)) = super::parse_module(&source) else {
+            return false;
+        };
+        let [stmt @ Stmt::FunctionDef(_)] = module.body.as_slice() else {
+            return false;
+        };
+        let mut module_level_names = HashSet::new();
+        self.collect_module_level_names(stmt, &mut module_level_names);
+        if module_level_names != outline.functions[index].module_level_names {
+            return false;
+        }
+
+        debug!("Re-analyzing edited function in {:?}", file_path);
+
+        let first_line = padding + 1;
+        let last_line = first_line + count_lines(&old[start..end]);
+        let line_delta = count_lines(&new[edit_start..new_end]) as isize
+            - count_lines(&old[edit_start..old_end]) as isize;
+        let in_function = |line: usize| (first_line..=last_line).contains(&line);
+        let shift = |line: &mut usize| {
+            if *line > last_line {
+                *line = line.saturating_add_signed(line_delta);
+            }
+        };
+
+        // Forget what the function contributed and move what follows it
+        if let Some(names) = self.file_definitions.get(file_path).map(|n| n.clone()) {
+            for name in names {
+                let Some(mut defs) = self.definitions.get_mut(&name) else {
+                    continue;
+                };
+                defs.retain(|def| def.file_path != *file_path || !in_function(def.line));
+                for def in defs.iter_mut().filter(|def| def.file_path == *file_path) {
+                    shift(&mut def.line);
+                    shift(&mut def.end_line);
+                    if let Some(yield_line) = def.yield_line.as_mut() {
+                        shift(yield_line);
+                    }
+                }
+                let defined_here = defs.iter().any(|def| def.file_path == *file_path);
+                let is_empty = defs.is_empty();
+                drop(defs);
+                if !defined_here {
+                    if let Some(mut file_names) = self.file_definitions.get_mut(file_path) {
+                        file_names.remove(&name);
+                    }
+                }
+                if is_empty {
+                    self.definitions.remove_if(&name, |_, defs| defs.is_empty());
+                }
+            }
+        }
+        let usages_at = self.usages.get_mut(file_path).map(|mut usages| {
+            let at = usages.iter().take_while(|u| u.line < first_line).count();
+            usages.retain(|u| !in_function(u.line));
+            usages.iter_mut().for_each(|u| shift(&mut u.line));
+            (at, usages.len())
+        });
+        let fixture_names: Vec<String> = self
+            .usage_by_fixture
+            .iter()
+            .map(|entry| entry.key().clone())
+            .collect();
+        for name in fixture_names {
+            let Some(mut usages) = self.usage_by_fixture.get_mut(&name) else {
+                continue;
+            };
+            usages.retain(|(path, u)| path != file_path || !in_function(u.line));
+            for (_, usage) in usages.iter_mut().filter(|(path, _)| path == file_path) {
+                shift(&mut usage.line);
+            }
+            let is_empty = usages.is_empty();
+            drop(usages);
+            if is_empty {
+                self.usage_by_fixture
+                    .remove_if(&name, |_, usages| usages.is_empty());
+            }
+        }
+        let undeclared_at = self
+            .undeclared_fixtures
+            .get_mut(file_path)
+            .map(|mut undeclared| {
+                let at = undeclared
+                    .iter()
+                    .take_while(|u| u.line < first_line)
+                    .count();
+                undeclared.retain(|u| !in_function(u.function_line));
+                for u in undeclared.iter_mut() {
+                    shift(&mut u.line);
+                    shift(&mut u.function_line);
+                }
+                (at, undeclared.len())
+            });
+        if let Some(mut scopes) = self.class_scopes.get_mut(file_path) {
+            for scope in scopes.iter_mut() {
+                shift(&mut scope.start_line);
+                shift(&mut scope.end_line);
+            }
+        }
+        self.invalidate_cycle_cache();
+
+        let module_level_names = self
+            .imports
+            .get(file_path)
+            .map(|names| names.clone())
+            .unwrap_or_default();
+        let is_conftest = file_path
+            .file_name()
+            .map(|n| n == "conftest.py")
+            .unwrap_or(false);
+        let line_index = Self::build_line_index(&source);
+        self.visit_stmt(
+            stmt,
+            file_path,
+            is_conftest,
+            &source,
+            &line_index,
+            &outline.import_map,
+            &module_level_names,
+            &outline.type_aliases,
+        );
+
+        // Keep the file's usages in source order
+        if let Some((at, kept)) = usages_at {
+            if let Some(mut usages) = self.usages.get_mut(file_path) {
+                let added: Vec<_> = usages.drain(kept..).collect();
+                usages.splice(at..at, added);
+            }
+        }
+        if let Some((at, kept)) = undeclared_at {
+            if let Some(mut undeclared) = self.undeclared_fixtures.get_mut(file_path) {
+                let added: Vec<_> = undeclared.drain(kept..).collect();
+                undeclared.splice(at..at, added);
+            }
+        }
+
+        self.record_registered_markers(file_path, new);
+
+        let to_file_offset = |offset: usize| offset - padding + start;
+        let function = FunctionSpan {
+            start: to_file_offset(stmt.range().start().to_usize()),
+            end: to_file_offset(stmt.range().end().to_usize()),
+            module_level_names,
+        };
+        outline.functions[index] = function;
+        for later in &mut outline.functions[index + 1..] {
+            later.start = later.start - old_end + new_end;
+            later.end = later.end - old_end + new_end;
+        }
+
+        // The file's AST is re-parsed when a request needs it; its imports
+        // did not change.
+        self.ast_cache.remove(file_path);
+        let import_map = Arc::clone(&outline.import_map);
+        self.name_import_map_cache
+            .insert(file_path.clone(), (Self::hash_content(new), import_map));
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_range() {
+        assert_eq!(changed_range("abc", "abc"), (3, 3, 3));
+        assert_eq!(changed_range("abc", "aXbc"), (1, 1, 2));
+        assert_eq!(changed_range("abcd", "ad"), (1, 3, 1));
+        assert_eq!(changed_range("abc", "abXc"), (2, 2, 3));
+        // Repeated characters: the prefix wins
+        assert_eq!(changed_range("aa", "aaa"), (2, 2, 3));
+        assert_eq!(changed_range("", "x"), (0, 0, 1));
+    }
+}
@@ -0,0 +1,304 @@
+//! Persistent on-disk index cache for fast restarts.
+//!
+//! After a workspace scan, what each analyzed file contributed to the index
+//! (its definitions, usages, undeclared fixtures, imports and class scopes) is
+//! written to `<cache dir>/index-<workspace hash>.json`, keyed by a hash of
+//! the file's contents. The next scan of the workspace loads it, and a file
+//! whose contents hash the same is restored from it instead of being parsed
+//! and analyzed again: only files that changed since pay the analysis cost.
+//! Virtual environment packages are cached the same way.
+
+use super::types::{ClassScope, FixtureDefinition, FixtureUsage, UndeclaredFixture};
+use super::{env_path, FixtureDatabase};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+/// Bumped whenever what is cached changes meaning; caches written by another
+/// version of the server are ignored.
+const CACHE_VERSION: &str = concat!(env!("CARGO_PKG_VERSION"), "-4");
+
+/// What analyzing one file added to the index.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct CachedFile {
+    pub(crate) content_hash: u64,
+    pub(crate) definitions: Vec<FixtureDefinition>,
+    pub(crate) usages: Vec<FixtureUsage>,
+    pub(crate) undeclared: Vec<UndeclaredFixture>,
+    pub(crate) module_level_names: HashSet<String>,
+    pub(crate) class_scopes: Vec<ClassScope>,
+}
+
+/// The cache file of a workspace.
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexCache {
+    version: String,
+    files: HashMap<PathBuf, CachedFile>,
+}
+
+/// 64-bit FNV-1a. Unlike `DefaultHasher`, its output does not change between
+/// builds, so it can be persisted.
+pub(crate) fn stable_hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// The platform's per-user cache directory for the server:
+/// `$XDG_CACHE_HOME/pytest-language-server`, `~/.cache/pytest-language-server`,
+/// `~/Library/Caches/pytest-language-server` on macOS and
+/// `%LOCALAPPDATA%\pytest-language-server` on Windows.
+pub fn default_cache_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env_path("LOCALAPPDATA")?
+    } else if let Some(xdg) = env_path("XDG_CACHE_HOME") {
+        xdg
+    } else if cfg!(target_os = "macos") {
+        env_path("HOME")?.join("Library").join("Caches")
+    } else {
+        env_path("HOME")?.join(".cache")
+    };
+    Some(base.join("pytest-language-server"))
+}
+
+impl FixtureDatabase {
+    /// Persist the index in `cache_dir`, or don't persist it when `None`.
+    pub fn set_cache_dir(&self, cache_dir: Option<PathBuf>) {
+        *self.cache_dir.lock().unwrap() = cache_dir;
+    }
+
+    /// The version cached analyses are written with: the server's, and the
+    /// configured fixture decorators, which change what analysis finds.
+    fn cache_version(&self) -> String {
+        let aliases = self.fixture_decorator_aliases();
+        if aliases.is_empty() {
+            CACHE_VERSION.to_string()
+        } else {
+            format!("{}+{}", CACHE_VERSION, aliases.join(","))
+        }
+    }
+
+    /// The cache file of the workspace at `root_path`.
+    fn index_cache_file(&self, root_path: &Path) -> Option<PathBuf> {
+        let cache_dir = self.cache_dir.lock().unwrap().clone()?;
+        let root_hash = stable_hash(root_path.to_string_lossy().as_bytes());
+        Some(cache_dir.join(format!("index-{:016x}.json", root_hash)))
+    }
+
+    /// Load the cached index of the workspace at `root_path`, if any, for
+    /// the files analyzed by the coming scan to be restored from.
+    pub(crate) fn load_index_cache(&self, root_path: &Path) {
+        let Some(cache_file) = self.index_cache_file(root_path) else {
+            return;
+        };
+        let Ok(data) = std::fs::read(&cache_file) else {
+            debug!("No index cache at {:?}", cache_file);
+            return;
+        };
+        let cache: IndexCache = match serde_json::from_slice(&data) {
+            Ok(cache) => cache,
+            Err(e) => {
+                warn!("Ignoring unreadable index cache {:?}: {}", cache_file, e);
+                return;
+            }
+        };
+        if cache.version != self.cache_version() {
+            info!(
+                "Ignoring index cache {:?} written by version {}",
+                cache_file, cache.version
+            );
+            return;
+        }
+        info!(
+            "Loaded index cache {:?} ({} files)",
+            cache_file,
+            cache.files.len()
+        );
+        for (file_path, cached) in cache.files {
+            self.cached_files.insert(file_path, cached);
+        }
+    }
+
+    /// Write the index of every analyzed file to the cache of the workspace
+    /// at `root_path`, and drop the loaded cache.
+    pub(crate) fn save_index_cache(&self, root_path: &Path) {
+        self.cached_files.clear();
+        let Some(cache_file) = self.index_cache_file(root_path) else {
+            return;
+        };
+
+        let files: HashMap<PathBuf, CachedFile> = self
+            .content_hashes
+            .iter()
+            .map(|entry| {
+                let file_path = entry.key();
+                (
+                    file_path.clone(),
+                    self.cached_file(file_path, *entry.value()),
+                )
+            })
+            .collect();
+        let cache = IndexCache {
+            version: self.cache_version(),
+            files,
+        };
+
+        let cache_dir = cache_file.parent().unwrap_or(Path::new("."));
+        let result = std::fs::create_dir_all(cache_dir).and_then(|()| {
+            // Write then rename, so a concurrent server never reads half a cache
+            let partial = cache_file.with_extension(format!("json.{}", std::process::id()));
+            std::fs::write(&partial, serde_json::to_vec(&cache)?)?;
+            std::fs::rename(&partial, &cache_file)
+        });
+        match result {
+            Ok(()) => info!(
+                "Saved index cache {:?} ({} files)",
+                cache_file,
+                cache.files.len()
+            ),
+            Err(e) => warn!("Failed to save index cache {:?}: {}", cache_file, e),
+        }
+    }
+
+    /// What the index holds for `file_path`.
+    fn cached_file(&self, file_path: &PathBuf, content_hash: u64) -> CachedFile {
+        let definitions = self
+            .file_definitions
+            .get(file_path)
+            .map(|names| {
+                names
+                    .iter()
+                    .filter_map(|name| self.definitions.get(name))
+                    .flat_map(|defs| {
+                        defs.iter()
+                            .filter(|def| def.file_path == *file_path)
+                            .cloned()
+                            .collect::<Vec<_>>()
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        CachedFile {
+            content_hash,
+            definitions,
+            usages: self
+                .usages
+                .get(file_path)
+                .map(|u| u.clone())
+                .unwrap_or_default(),
+            undeclared: self
+                .undeclared_fixtures
+                .get(file_path)
+                .map(|u| u.clone())
+                .unwrap_or_default(),
+            module_level_names: self
+                .imports
+                .get(file_path)
+                .map(|names| names.clone())
+                .unwrap_or_default(),
+            class_scopes: self
+                .class_scopes
+                .get(file_path)
+                .map(|scopes| scopes.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Restore the analysis of `file_path` from the loaded cache when its
+    /// contents did not change since it was cached. Returns `false` when the
+    /// file must be analyzed.
+    pub(crate) fn restore_cached_analysis(
+        &self,
+        file_path: &PathBuf,
+        content: &str,
+        cleanup_previous: bool,
+    ) -> bool {
+        if self.cache_dir.lock().unwrap().is_none() {
+            return false;
+        }
+        // Open files change with every keystroke; they are not cached
+        if self.open_files.contains_key(file_path) {
+            self.content_hashes.remove(file_path);
+            return false;
+        }
+        // A plugin's stub gives its fixtures their return types: editing the
+        // stub alone invalidates the cached analysis too
+        let is_third_party = self.is_third_party_file(file_path);
+        let stub = is_third_party
+            .then(|| self.stub_path_for(file_path))
+            .flatten()
+            .and_then(|stub_path| std::fs::read_to_string(stub_path).ok());
+        let content_hash = match stub {
+            Some(stub) => stable_hash(format!("{}\0{}", content, stub).as_bytes()),
+            None => stable_hash(content.as_bytes()),
+        };
+        self.content_hashes.insert(file_path.clone(), content_hash);
+        let Some((_, cached)) = self
+            .cached_files
+            .remove_if(file_path, |_, cached| cached.content_hash == content_hash)
+        else {
+            return false;
+        };
+        debug!("Restoring cached analysis of {:?}", file_path);
+
+        // Forget the previous analysis, as a full analysis would
+        self.cleanup_usages_for_file(file_path);
+        self.usages.remove(file_path);
+        self.undeclared_fixtures.remove(file_path);
+        self.imports.remove(file_path);
+        self.module_outlines.remove(file_path);
+        if cleanup_previous {
+            self.cleanup_definitions_for_file(file_path);
+        }
+        self.record_registered_markers(file_path, content);
+
+        // Whether a file is third-party or a plugin depends on the
+        // environment, not on its contents
+        let is_plugin = self.plugin_fixture_files.contains_key(file_path);
+        for definition in cached.definitions {
+            self.record_fixture_definition(FixtureDefinition {
+                file_path: file_path.clone(),
+                is_third_party,
+                is_plugin,
+                ..definition
+            });
+        }
+        for usage in cached.usages {
+            self.record_fixture_usage(
+                file_path,
+                usage.name,
+                usage.line,
+                usage.start_char,
+                usage.end_char,
+                usage.is_parameter,
+            );
+        }
+        if !cached.undeclared.is_empty() {
+            self.undeclared_fixtures
+                .insert(file_path.clone(), cached.undeclared);
+        }
+        self.imports
+            .insert(file_path.clone(), cached.module_level_names);
+        if cached.class_scopes.is_empty() {
+            self.class_scopes.remove(file_path);
+        } else {
+            self.class_scopes
+                .insert(file_path.clone(), cached.class_scopes);
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stable_hash() {
+        // FNV-1a reference values
+        assert_eq!(stable_hash(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(stable_hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_ne!(stable_hash(b"def a(): pass"), stable_hash(b"def b(): pass"));
+    }
+}
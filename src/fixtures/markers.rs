@@ -0,0 +1,526 @@
+//! pytest markers: the builtin marks and those registered in the pytest
+//! configuration (`markers` in `pytest.ini`, `pyproject.toml`, `tox.ini` or
+//! `setup.cfg`).
+
+use super::pytest_config::{ini_section, CONFIG_FILES};
+use super::FixtureDatabase;
+use ruff_python_ast::{Expr, Stmt};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::Ordering;
+use tracing::{debug, info, warn};
+
+/// Where a marker comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarkerSource {
+    /// Shipped with pytest.
+    Builtin,
+    /// Registered in the given pytest configuration file.
+    Config(PathBuf),
+    /// Registered with `config.addinivalue_line` in the given conftest.py or plugin.
+    Registered(PathBuf),
+}
+
+/// A marker usable as `@pytest.mark.<name>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkerInfo {
+    pub name: String,
+    /// Call signature, for builtin marks that take arguments (`skipif(condition, *, reason=...)`).
+    pub signature: Option<String>,
+    pub description: Option<String>,
+    pub source: MarkerSource,
+}
+
+/// A `pytest.mark.<name>` use in a Python file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MarkerUsage {
+    pub name: String,
+    /// Line of the marker name (1-based).
+    pub line: usize,
+    pub start_char: usize,
+    pub end_char: usize,
+}
+
+/// A mark shipped with pytest.
+struct BuiltinMarker {
+    name: &'static str,
+    signature: &'static str,
+    description: &'static str,
+}
+
+const BUILTIN_MARKERS: &[BuiltinMarker] = &[
+    BuiltinMarker {
+        name: "skip",
+        signature: "skip(reason=None)",
+        description: "Skip the given test function with an optional reason.",
+    },
+    BuiltinMarker {
+        name: "skipif",
+        signature: "skipif(condition, ..., *, reason=...)",
+        description: "Skip the given test function if any of the conditions evaluate to True.",
+    },
+    BuiltinMarker {
+        name: "xfail",
+        signature: "xfail(condition, ..., *, reason=..., run=True, raises=None, strict=xfail_strict)",
+        description: "Mark the test function as an expected failure if any of the conditions evaluate to True.",
+    },
+    BuiltinMarker {
+        name: "parametrize",
+        signature: "parametrize(argnames, argvalues, indirect=False, ids=None, scope=None)",
+        description: "Call a test function multiple times passing in different arguments in turn.",
+    },
+    BuiltinMarker {
+        name: "usefixtures",
+        signature: "usefixtures(fixturename1, fixturename2, ...)",
+        description: "Mark tests as needing all of the specified fixtures.",
+    },
+    BuiltinMarker {
+        name: "filterwarnings",
+        signature: "filterwarnings(warning)",
+        description: "Add a warning filter to the given test.",
+    },
+];
+
+/// The builtin pytest marks.
+pub fn builtin_markers() -> Vec<MarkerInfo> {
+    BUILTIN_MARKERS
+        .iter()
+        .map(|m| MarkerInfo {
+            name: m.name.to_string(),
+            signature: Some(m.signature.to_string()),
+            description: Some(m.description.to_string()),
+            source: MarkerSource::Builtin,
+        })
+        .collect()
+}
+
+/// Parse one `markers` entry (`slow: marks tests as slow`, `env(name): ...`).
+fn parse_marker_line(line: &str, source: &Path) -> Option<MarkerInfo> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+    let (head, description) = match line.split_once(':') {
+        Some((head, description)) => (head.trim(), Some(description.trim())),
+        None => (line, None),
+    };
+    let name = head.split('(').next().unwrap_or(head).trim();
+    if name.is_empty() || !name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+        return None;
+    }
+    Some(MarkerInfo {
+        name: name.to_string(),
+        signature: (head != name).then(|| head.to_string()),
+        description: description.filter(|d| !d.is_empty()).map(|d| d.to_string()),
+        source: MarkerSource::Config(source.to_path_buf()),
+    })
+}
+
+/// Marker settings read from the pytest configuration.
+#[derive(Debug, Default)]
+pub struct MarkerConfig {
+    /// Markers registered with the `markers` option.
+    pub markers: Vec<MarkerInfo>,
+    /// Whether unregistered markers are errors (`--strict-markers`).
+    pub strict: bool,
+}
+
+/// Whether an ini boolean value is true.
+fn is_truthy(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "true" | "1" | "yes" | "on"
+    )
+}
+
+/// Whether `addopts` enables strict markers.
+fn addopts_strict(addopts: &str) -> bool {
+    addopts
+        .split_whitespace()
+        .any(|opt| opt == "--strict-markers" || opt == "--strict")
+}
+
+fn ini_marker_config(content: &str, section: &str, path: &Path) -> Option<MarkerConfig> {
+    let options = ini_section(content, section)?;
+    let option = |key: &str| options.get(key).map(|lines| lines.join(" "));
+    Some(MarkerConfig {
+        markers: options
+            .get("markers")
+            .into_iter()
+            .flatten()
+            .filter_map(|line| parse_marker_line(line, path))
+            .collect(),
+        strict: option("addopts").is_some_and(|opts| addopts_strict(&opts))
+            || option("strict_markers").is_some_and(|v| is_truthy(&v))
+            || option("strict").is_some_and(|v| is_truthy(&v)),
+    })
+}
+
+/// Marker settings in `[tool.pytest.ini_options]` (or the native
+/// `[tool.pytest]` table of pytest 9), or `None` if neither table exists.
+fn pyproject_marker_config(content: &str, path: &Path) -> Option<MarkerConfig> {
+    let value: toml::Value = match toml::from_str(content) {
+        Ok(value) => value,
+        Err(e) => {
+            warn!("Failed to parse pyproject.toml for markers: {}", e);
+            return None;
+        }
+    };
+    let pytest = value.get("tool")?.get("pytest")?;
+    let table = pytest.get("ini_options").unwrap_or(pytest);
+
+    let strings = |key: &str| -> Vec<String> {
+        match table.get(key) {
+            Some(toml::Value::String(s)) => vec![s.clone()],
+            Some(toml::Value::Array(entries)) => entries
+                .iter()
+                .filter_map(|entry| entry.as_str().map(str::to_string))
+                .collect(),
+            _ => Vec::new(),
+        }
+    };
+    let flag = |key: &str| match table.get(key) {
+        Some(toml::Value::Boolean(b)) => *b,
+        Some(toml::Value::String(s)) => is_truthy(s),
+        _ => false,
+    };
+
+    Some(MarkerConfig {
+        markers: strings("markers")
+            .iter()
+            .filter_map(|line| parse_marker_line(line, path))
+            .collect(),
+        strict: addopts_strict(&strings("addopts").join(" "))
+            || flag("strict_markers")
+            || flag("strict"),
+    })
+}
+
+/// Marker settings of the pytest configuration file `inifile`.
+pub fn load_marker_config(inifile: &Path) -> MarkerConfig {
+    let Ok(content) = std::fs::read_to_string(inifile) else {
+        return MarkerConfig::default();
+    };
+    let file_name = inifile
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+    let config = if file_name == "pyproject.toml" {
+        pyproject_marker_config(&content, inifile)
+    } else {
+        let section = CONFIG_FILES
+            .iter()
+            .find(|(name, _)| *name == file_name)
+            .map_or("pytest", |(_, section)| *section);
+        ini_marker_config(&content, section, inifile)
+    };
+    let config = config.unwrap_or_default();
+    debug!(
+        "Loaded {} markers from {:?} (strict: {})",
+        config.markers.len(),
+        inifile,
+        config.strict
+    );
+    config
+}
+
+/// Markers registered in Python code with
+/// `config.addinivalue_line("markers", "name: description")`, as conftest.py
+/// files and plugins do in `pytest_configure`.
+fn registered_markers(content: &str, path: &Path) -> Vec<MarkerInfo> {
+    const CALL: &str = "addinivalue_line(";
+    let mut markers = Vec::new();
+    let mut rest = content;
+    while let Some(pos) = rest.find(CALL) {
+        rest = &rest[pos + CALL.len()..];
+        let Some((option, after)) = string_literal(rest) else {
+            continue;
+        };
+        if option != "markers" {
+            continue;
+        }
+        let Some(after) = after.trim_start().strip_prefix(',') else {
+            continue;
+        };
+        if let Some(marker) =
+            string_literal(after).and_then(|(line, _)| parse_marker_line(line, path))
+        {
+            markers.push(MarkerInfo {
+                source: MarkerSource::Registered(path.to_path_buf()),
+                ..marker
+            });
+        }
+    }
+    markers
+}
+
+/// A simple (non-triple-quoted, escape-free) string literal at the start of
+/// `text`, returning its value and the text after it.
+fn string_literal(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim_start();
+    let quote = text.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let body = &text[1..];
+    let end = body.find(quote)?;
+    Some((&body[..end], &body[end + 1..]))
+}
+
+impl FixtureDatabase {
+    /// Load the marker settings of the pytest configuration file, if any.
+    pub fn load_pytest_markers(&self, inifile: Option<&Path>) {
+        let config = inifile.map(load_marker_config).unwrap_or_default();
+        info!(
+            "Configured markers: {}, strict markers: {}",
+            config.markers.len(),
+            config.strict
+        );
+        self.strict_markers.store(config.strict, Ordering::Relaxed);
+        *self.configured_markers.lock().unwrap() = config.markers;
+    }
+
+    /// Record the markers a file registers with `addinivalue_line`.
+    pub(crate) fn record_registered_markers(&self, file_path: &Path, content: &str) {
+        let markers = registered_markers(content, file_path);
+        if markers.is_empty() {
+            self.registered_markers.remove(file_path);
+        } else {
+            self.registered_markers
+                .insert(file_path.to_path_buf(), markers);
+        }
+    }
+
+    /// All known markers: pytest's builtin marks, then the configured ones, then
+    /// those registered by conftest.py files and plugins.
+    pub fn get_markers(&self) -> Vec<MarkerInfo> {
+        let mut markers = builtin_markers();
+        let configured = self.configured_markers.lock().unwrap().clone();
+        let mut registered: Vec<MarkerInfo> = self
+            .registered_markers
+            .iter()
+            .flat_map(|entry| entry.value().clone())
+            .collect();
+        registered.sort_by(|a, b| a.name.cmp(&b.name));
+        for marker in configured.into_iter().chain(registered) {
+            if !markers.iter().any(|m| m.name == marker.name) {
+                markers.push(marker);
+            }
+        }
+        markers
+    }
+
+    /// Whether unregistered markers are errors in this workspace.
+    pub fn strict_markers(&self) -> bool {
+        self.strict_markers.load(Ordering::Relaxed)
+    }
+
+    /// `pytest.mark.<name>` uses on decorators and `pytestmark` assignments.
+    pub fn get_marker_usages(&self, file_path: &Path) -> Vec<MarkerUsage> {
+        let Some(content) = self.get_file_content(file_path) else {
+            return Vec::new();
+        };
+        let Some(parsed) = self.get_parsed_ast(file_path, &content) else {
+            return Vec::new();
+        };
+        let line_index = self.get_line_index(file_path, &content);
+
+        let mut usages = Vec::new();
+        if let ruff_python_ast::Mod::Module(module) = parsed.as_ref() {
+            self.collect_marker_usages(&module.body, &line_index, &mut usages);
+        }
+        usages
+    }
+
+    /// The marker use at the given LSP position (0-based line, byte column).
+    pub fn find_marker_at_position(
+        &self,
+        file_path: &Path,
+        line: u32,
+        character: u32,
+    ) -> Option<MarkerUsage> {
+        let line = line as usize + 1;
+        let character = character as usize;
+        self.get_marker_usages(file_path).into_iter().find(|usage| {
+            usage.line == line && usage.start_char <= character && character <= usage.end_char
+        })
+    }
+
+    /// Marker uses pytest would reject under `--strict-markers`; empty unless
+    /// strict markers are enabled.
+    pub fn detect_unknown_markers_in_file(&self, file_path: &Path) -> Vec<MarkerUsage> {
+        if !self.strict_markers() {
+            return Vec::new();
+        }
+        let known = self.get_markers();
+        self.get_marker_usages(file_path)
+            .into_iter()
+            .filter(|usage| !known.iter().any(|m| m.name == usage.name))
+            .collect()
+    }
+
+    fn collect_marker_usages(
+        &self,
+        stmts: &[Stmt],
+        line_index: &[usize],
+        usages: &mut Vec<MarkerUsage>,
+    ) {
+        for stmt in stmts {
+            match stmt {
+                Stmt::FunctionDef(f) => {
+                    for decorator in &f.decorator_list {
+                        self.collect_marks_in_expr(&decorator.expression, line_index, usages);
+                    }
+                }
+                Stmt::ClassDef(c) => {
+                    for decorator in &c.decorator_list {
+                        self.collect_marks_in_expr(&decorator.expression, line_index, usages);
+                    }
+                    self.collect_marker_usages(&c.body, line_index, usages);
+                }
+                Stmt::Assign(assign)
+                    if assign
+                        .targets
+                        .iter()
+                        .any(|t| matches!(t, Expr::Name(n) if n.id.as_str() == "pytestmark")) =>
+                {
+                    self.collect_marks_in_expr(&assign.value, line_index, usages);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Find `pytest.mark.<name>` attributes anywhere in a decorator or
+    /// `pytestmark` expression.
+    fn collect_marks_in_expr(
+        &self,
+        expr: &Expr,
+        line_index: &[usize],
+        usages: &mut Vec<MarkerUsage>,
+    ) {
+        match expr {
+            Expr::Attribute(attr) => {
+                let is_mark = matches!(
+                    attr.value.as_ref(),
+                    Expr::Attribute(inner)
+                        if inner.attr.as_str() == "mark"
+                            && matches!(inner.value.as_ref(), Expr::Name(n) if n.id.as_str() == "pytest")
+                );
+                if is_mark {
+                    let end = attr.range.end().to_usize();
+                    let start = end - attr.attr.len();
+                    usages.push(MarkerUsage {
+                        name: attr.attr.to_string(),
+                        line: self.get_line_from_offset(start, line_index),
+                        start_char: self.get_char_position_from_offset(start, line_index),
+                        end_char: self.get_char_position_from_offset(end, line_index),
+                    });
+                } else {
+                    self.collect_marks_in_expr(&attr.value, line_index, usages);
+                }
+            }
+            Expr::Call(call) => {
+                self.collect_marks_in_expr(&call.func, line_index, usages);
+                for arg in &call.arguments.args {
+                    self.collect_marks_in_expr(arg, line_index, usages);
+                }
+                for keyword in &call.arguments.keywords {
+                    self.collect_marks_in_expr(&keyword.value, line_index, usages);
+                }
+            }
+            Expr::List(list) => {
+                for elt in &list.elts {
+                    self.collect_marks_in_expr(elt, line_index, usages);
+                }
+            }
+            Expr::Tuple(tuple) => {
+                for elt in &tuple.elts {
+                    self.collect_marks_in_expr(elt, line_index, usages);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_marker_line() {
+        let path = Path::new("pytest.ini");
+        let marker = parse_marker_line("slow: marks tests as slow", path).unwrap();
+        assert_eq!(marker.name, "slow");
+        assert_eq!(marker.signature, None);
+        assert_eq!(marker.description.as_deref(), Some("marks tests as slow"));
+
+        let marker = parse_marker_line("env(name): run only on the given env", path).unwrap();
+        assert_eq!(marker.name, "env");
+        assert_eq!(marker.signature.as_deref(), Some("env(name)"));
+
+        let marker = parse_marker_line("integration", path).unwrap();
+        assert_eq!(marker.description, None);
+
+        assert!(parse_marker_line("", path).is_none());
+        assert!(parse_marker_line("not a marker: x", path).is_none());
+    }
+
+    #[test]
+    fn test_ini_marker_config() {
+        let path = Path::new("setup.cfg");
+        let content = "[metadata]\nmarkers = nope\n\n[tool:pytest]\naddopts = -ra\n    --strict-markers\nmarkers =\n    slow: marks tests as slow\n    # a comment\n    network\ntestpaths = tests\n";
+        let config = ini_marker_config(content, "tool:pytest", path).unwrap();
+        let names: Vec<&str> = config.markers.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["slow", "network"]);
+        assert!(config.strict, "--strict-markers on a continuation line");
+
+        assert!(ini_marker_config(content, "pytest", path).is_none());
+        let config = ini_marker_config("[pytest]\n", "pytest", path).unwrap();
+        assert!(config.markers.is_empty());
+        assert!(!config.strict);
+        let config =
+            ini_marker_config("[pytest]\nstrict_markers = true\n", "pytest", path).unwrap();
+        assert!(config.strict);
+    }
+
+    #[test]
+    fn test_pyproject_marker_config() {
+        let path = Path::new("pyproject.toml");
+        let content = r#"
+[tool.pytest.ini_options]
+addopts = "-ra --strict-markers"
+markers = ["slow: marks tests as slow", "network"]
+"#;
+        let config = pyproject_marker_config(content, path).unwrap();
+        let names: Vec<&str> = config.markers.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["slow", "network"]);
+        assert!(config.strict);
+
+        let native = "[tool.pytest]\nmarkers = [\"db\"]\nstrict = true\n";
+        let config = pyproject_marker_config(native, path).unwrap();
+        assert_eq!(config.markers[0].name, "db");
+        assert!(config.strict);
+
+        let lenient = "[tool.pytest.ini_options]\naddopts = [\"-ra\"]\n";
+        assert!(!pyproject_marker_config(lenient, path).unwrap().strict);
+        assert!(pyproject_marker_config("[tool.ruff]\n", path).is_none());
+    }
+
+    #[test]
+    fn test_registered_markers() {
+        let path = Path::new("conftest.py");
+        let content = r#"
+def pytest_configure(config):
+    config.addinivalue_line("markers", "db: needs a database")
+    config.addinivalue_line(
+        "markers", 'env(name): run on an env'
+    )
+    config.addinivalue_line("filterwarnings", "error")
+"#;
+        let markers = registered_markers(content, path);
+        let names: Vec<&str> = markers.iter().map(|m| m.name.as_str()).collect();
+        assert_eq!(names, vec!["db", "env"]);
+        assert_eq!(
+            markers[0].source,
+            MarkerSource::Registered(path.to_path_buf())
+        );
+    }
+}
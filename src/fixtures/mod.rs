@@ -7,24 +7,59 @@
 //! - Providing completion context for fixture suggestions
 
 mod analyzer;
+mod bdd;
+pub mod builtins;
+mod check;
 pub(crate) mod cli;
+mod conda;
 pub mod decorators; // Public for testing
 mod docstring;
+mod factoryboy;
+mod graph;
+mod hatch;
 pub mod import_analysis;
 mod imports;
+mod incremental;
+mod index_cache;
+pub mod markers;
+mod pyenv;
+mod pytest_cases;
+pub mod pytest_config;
 mod resolver;
 mod scanner;
 pub(crate) mod string_utils; // pub(crate) for inlay_hint provider access
+mod stubs;
+mod test_items;
 pub mod types;
 mod undeclared;
+mod uv;
 
 #[allow(unused_imports)] // ParamInsertionInfo re-exported for public API via lib.rs
 pub use types::{
-    CompletionContext, FixtureCycle, FixtureDefinition, FixtureScope, FixtureUsage,
-    ParamInsertionInfo, ScopeMismatch, TypeImportSpec, UndeclaredFixture,
+    AnalysisFailure, CandidateSource, ClassScope, CompletionContext, DeprecatedYieldFixture,
+    FixtureCycle, FixtureDefinition, FixtureOrigin, FixtureParams, FixtureScope, FixtureUsage,
+    IndirectIssue, InjectedFixture, InjectionSource, InvalidIndirect, ParamInsertionInfo,
+    RedundancyReason, RedundantUsefixture, ResolutionCandidate, ResolutionExplanation, ScopeEdit,
+    ScopeMismatch, TestCaseFixtureParam, TestFixtures, TestItem, TestItemKind, TypeImportSpec,
+    UndeclaredFixture, UnusedFixtureParam, VisibleFixture,
 };
 
+pub(crate) use analyzer::panic_message;
+#[allow(unused_imports)] // FixEdit re-exported for public API via lib.rs
+pub use check::{apply_fixes, Finding, Fix, FixEdit, RelatedLocation};
+pub(crate) use docstring::docstring_to_markdown;
+#[allow(unused_imports)] // Graph types re-exported for public API via lib.rs
+pub use graph::{
+    FixtureGraph, FixtureTree, FixtureUse, FixtureUses, GraphFormat, GraphNode, GraphNodeKind,
+    TreeNode,
+};
+pub(crate) use imports::find_venv_python;
+pub use index_cache::default_cache_dir;
+pub use scanner::{ScanPhase, ScanProgress};
+
+use dashmap::mapref::entry::Entry;
 use dashmap::DashMap;
+use ruff_text_size::{Ranged, TextSize};
 use std::collections::hash_map::DefaultHasher;
 use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
@@ -53,7 +88,7 @@ type LineIndexCacheEntry = (u64, Arc<Vec<usize>>);
 
 /// Cache entry for parsed AST: (content_hash, ast).
 /// The content hash is used to invalidate the cache when file content changes.
-type AstCacheEntry = (u64, Arc<rustpython_parser::ast::Mod>);
+type AstCacheEntry = (u64, Arc<ruff_python_ast::Mod>);
 
 /// Cache entry for fixture cycles: (definitions_version, cycles).
 /// The version is incremented when definitions change to invalidate the cache.
@@ -78,7 +113,7 @@ type ImportedFixturesCacheEntry = (u64, u64, Arc<HashMap<String, PathBuf>>);
 /// is called from code-action and inlay-hint providers — i.e. only for files that
 /// are already in `file_cache`.  Entries are evicted alongside `file_cache` entries
 /// in both `cleanup_file_cache` (per-file, on close/delete) and
-/// `evict_cache_if_needed` (bulk, when `file_cache` exceeds `MAX_FILE_CACHE_SIZE`).
+/// `evict_cache_if_needed` (bulk, when `file_cache` exceeds its bounds).
 /// No independent size constant is needed.
 type NameImportMapCacheEntry = (
     u64,
@@ -86,10 +121,13 @@ type NameImportMapCacheEntry = (
 );
 
 /// Maximum number of files to keep in the file content cache.
-/// When exceeded, a batch of entries (in arbitrary map order — not LRU) is
-/// evicted to prevent unbounded memory growth.
+/// When exceeded, the least recently used entries are evicted to prevent
+/// unbounded memory growth.
 const MAX_FILE_CACHE_SIZE: usize = 2000;
 
+/// Default memory budget of the file content cache, in bytes.
+const DEFAULT_FILE_CACHE_BUDGET: usize = 128 * 1024 * 1024;
+
 /// The central database for fixture definitions and usages.
 ///
 /// Uses `DashMap` for lock-free concurrent access during workspace scanning.
@@ -106,7 +144,21 @@ pub struct FixtureDatabase {
     /// Used for efficient O(1) lookup in find_references_for_definition.
     pub usage_by_fixture: Arc<DashMap<String, Vec<(PathBuf, FixtureUsage)>>>,
     /// Cache of file contents for analyzed files (uses Arc for efficient sharing).
+    /// Bounded: the least recently used contents are dropped and re-read from
+    /// disk when needed again.
     pub file_cache: Arc<DashMap<PathBuf, Arc<String>>>,
+    /// Total size of the contents in `file_cache`, in bytes.
+    pub file_cache_bytes: Arc<std::sync::atomic::AtomicUsize>,
+    /// Memory budget of `file_cache`, in bytes.
+    pub file_cache_budget: Arc<std::sync::atomic::AtomicUsize>,
+    /// When each `file_cache` entry was last used, for LRU eviction.
+    file_cache_access: Arc<DashMap<PathBuf, u64>>,
+    /// Clock ticking on every `file_cache` use.
+    file_cache_clock: Arc<std::sync::atomic::AtomicU64>,
+    /// Files open in the editor. Their contents come from the client and are
+    /// authoritative: they are never re-read from disk, evicted from the file
+    /// cache or overwritten by a workspace scan.
+    pub open_files: Arc<DashMap<PathBuf, ()>>,
     /// Map from file path to undeclared fixtures used in function bodies.
     pub undeclared_fixtures: Arc<DashMap<PathBuf, Vec<UndeclaredFixture>>>,
     /// Map from file path to imported names in that file.
@@ -151,6 +203,84 @@ pub struct FixtureDatabase {
     ///
     /// Bounded implicitly: see [`NameImportMapCacheEntry`] for the eviction strategy.
     pub name_import_map_cache: Arc<DashMap<PathBuf, NameImportMapCacheEntry>>,
+    /// Markers registered in the workspace's pytest configuration.
+    pub configured_markers: Arc<std::sync::Mutex<Vec<markers::MarkerInfo>>>,
+    /// Whether the pytest configuration enables `--strict-markers`.
+    pub strict_markers: Arc<std::sync::atomic::AtomicBool>,
+    /// Markers registered with `config.addinivalue_line("markers", ...)`, per file.
+    pub registered_markers: Arc<DashMap<PathBuf, Vec<markers::MarkerInfo>>>,
+    /// Class statements per file, outermost first, for class-scoped fixture visibility.
+    pub class_scopes: Arc<DashMap<PathBuf, Vec<ClassScope>>>,
+    /// pytest rootdir and configuration file, discovered during the workspace scan.
+    pub pytest_root: Arc<std::sync::Mutex<Option<pytest_config::PytestRoot>>>,
+    /// Projects below the workspace root configured by their own pytest
+    /// configuration file, each a separate pytest root.
+    pub nested_pytest_roots: Arc<std::sync::Mutex<Vec<pytest_config::PytestRoot>>>,
+    /// Configured decorators wrapping `pytest.fixture`, by name or dotted path,
+    /// whose functions are indexed as fixtures.
+    pub fixture_decorators: Arc<std::sync::Mutex<Vec<String>>>,
+    /// Configured file name patterns of test files, replacing pytest's
+    /// default `test_*.py` / `*_test.py` when not empty.
+    pub test_file_patterns: Arc<std::sync::Mutex<Vec<glob::Pattern>>>,
+    /// Virtual environment configured for the workspace, overriding discovery.
+    pub venv_path: Arc<std::sync::Mutex<Option<PathBuf>>>,
+    /// Python interpreter configured for the workspace, whose environment
+    /// overrides the configured virtual environment and discovery.
+    pub python_path: Arc<std::sync::Mutex<Option<PathBuf>>>,
+    /// Virtual environments configured for subdirectories of the workspace.
+    pub subproject_venv_paths: Arc<std::sync::Mutex<Vec<(PathBuf, PathBuf)>>>,
+    /// Subprojects with a virtual environment of their own, whose
+    /// third-party fixtures are only visible from their files.
+    pub(crate) subproject_venvs: Arc<std::sync::Mutex<Vec<scanner::SubprojectVenv>>>,
+    /// Top-level functions, imports and type aliases of open files, so an edit
+    /// inside one function re-analyzes only that function.
+    pub(crate) module_outlines: Arc<DashMap<PathBuf, incremental::ModuleOutline>>,
+    /// Directory the index is persisted in between runs; `None` disables the cache.
+    pub cache_dir: Arc<std::sync::Mutex<Option<PathBuf>>>,
+    /// The persisted index loaded for the running scan, per file.
+    pub(crate) cached_files: Arc<DashMap<PathBuf, index_cache::CachedFile>>,
+    /// Stable hash of the contents each file was last analyzed with.
+    pub(crate) content_hashes: Arc<DashMap<PathBuf, u64>>,
+    /// Files whose analysis panicked, see [`AnalysisFailure`].
+    pub analysis_failures: Arc<DashMap<PathBuf, AnalysisFailure>>,
+}
+
+/// Parse Python source as a module. Every analysis goes through here so the
+/// whole server agrees on which files parse.
+pub(crate) fn parse_module(
+    content: &str,
+) -> Result<ruff_python_ast::Mod, ruff_python_parser::ParseError> {
+    ruff_python_parser::parse_module(content)
+        .map(|parsed| ruff_python_ast::Mod::Module(parsed.into_syntax()))
+}
+
+/// The path held by the environment variable `name`, when it is set and not
+/// empty.
+pub(crate) fn env_path(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Where a function or class definition starts: its `def` / `async def` /
+/// `class` keyword. ruff's statement ranges begin at the first decorator.
+pub(crate) fn definition_start(stmt: &ruff_python_ast::Stmt, content: &str) -> TextSize {
+    let (decorators, name) = match stmt {
+        ruff_python_ast::Stmt::FunctionDef(f) => (&f.decorator_list, &f.name),
+        ruff_python_ast::Stmt::ClassDef(c) => (&c.decorator_list, &c.name),
+        _ => return stmt.start(),
+    };
+    if decorators.is_empty() {
+        return stmt.start();
+    }
+    let name_start = name.start().to_usize();
+    let Some(before_name) = content.get(..name_start) else {
+        return stmt.start();
+    };
+    let line_start = before_name.rfind('\n').map_or(0, |i| i + 1);
+    let line = &before_name[line_start..];
+    let indent = line.len() - line.trim_start().len();
+    TextSize::from((line_start + indent) as u32)
 }
 
 impl Default for FixtureDatabase {
@@ -168,6 +298,13 @@ impl FixtureDatabase {
             usages: Arc::new(DashMap::new()),
             usage_by_fixture: Arc::new(DashMap::new()),
             file_cache: Arc::new(DashMap::new()),
+            file_cache_bytes: Arc::new(std::sync::atomic::AtomicUsize::new(0)),
+            file_cache_budget: Arc::new(std::sync::atomic::AtomicUsize::new(
+                DEFAULT_FILE_CACHE_BUDGET,
+            )),
+            file_cache_access: Arc::new(DashMap::new()),
+            file_cache_clock: Arc::new(std::sync::atomic::AtomicU64::new(0)),
+            open_files: Arc::new(DashMap::new()),
             undeclared_fixtures: Arc::new(DashMap::new()),
             imports: Arc::new(DashMap::new()),
             canonical_path_cache: Arc::new(DashMap::new()),
@@ -183,9 +320,66 @@ impl FixtureDatabase {
             workspace_root: Arc::new(std::sync::Mutex::new(None)),
             plugin_fixture_files: Arc::new(DashMap::new()),
             name_import_map_cache: Arc::new(DashMap::new()),
+            configured_markers: Arc::new(std::sync::Mutex::new(Vec::new())),
+            strict_markers: Arc::new(std::sync::atomic::AtomicBool::new(false)),
+            registered_markers: Arc::new(DashMap::new()),
+            class_scopes: Arc::new(DashMap::new()),
+            pytest_root: Arc::new(std::sync::Mutex::new(None)),
+            nested_pytest_roots: Arc::new(std::sync::Mutex::new(Vec::new())),
+            fixture_decorators: Arc::new(std::sync::Mutex::new(Vec::new())),
+            test_file_patterns: Arc::new(std::sync::Mutex::new(Vec::new())),
+            venv_path: Arc::new(std::sync::Mutex::new(None)),
+            python_path: Arc::new(std::sync::Mutex::new(None)),
+            subproject_venv_paths: Arc::new(std::sync::Mutex::new(Vec::new())),
+            subproject_venvs: Arc::new(std::sync::Mutex::new(Vec::new())),
+            module_outlines: Arc::new(DashMap::new()),
+            cache_dir: Arc::new(std::sync::Mutex::new(None)),
+            cached_files: Arc::new(DashMap::new()),
+            content_hashes: Arc::new(DashMap::new()),
+            analysis_failures: Arc::new(DashMap::new()),
         }
     }
 
+    /// Forget everything learned from scanning and analyzing files, keeping
+    /// only the configured virtual environment, before a fresh scan.
+    pub fn clear(&self) {
+        self.definitions.clear();
+        self.file_definitions.clear();
+        self.usages.clear();
+        self.usage_by_fixture.clear();
+        self.file_cache.clear();
+        self.file_cache_access.clear();
+        self.file_cache_bytes
+            .store(0, std::sync::atomic::Ordering::Relaxed);
+        self.undeclared_fixtures.clear();
+        self.imports.clear();
+        self.canonical_path_cache.clear();
+        self.line_index_cache.clear();
+        self.line_index_by_identity.clear();
+        self.ast_cache.clear();
+        self.cycle_cache.clear();
+        self.available_fixtures_cache.clear();
+        self.imported_fixtures_cache.clear();
+        self.site_packages_paths.lock().unwrap().clear();
+        self.editable_install_roots.lock().unwrap().clear();
+        *self.workspace_root.lock().unwrap() = None;
+        self.plugin_fixture_files.clear();
+        self.name_import_map_cache.clear();
+        self.configured_markers.lock().unwrap().clear();
+        self.strict_markers
+            .store(false, std::sync::atomic::Ordering::Relaxed);
+        self.registered_markers.clear();
+        self.class_scopes.clear();
+        self.module_outlines.clear();
+        self.cached_files.clear();
+        self.content_hashes.clear();
+        self.analysis_failures.clear();
+        *self.pytest_root.lock().unwrap() = None;
+        self.nested_pytest_roots.lock().unwrap().clear();
+        self.subproject_venvs.lock().unwrap().clear();
+        self.invalidate_cycle_cache();
+    }
+
     /// Increment the definitions version to invalidate cycle cache.
     /// Called whenever fixture definitions are modified.
     pub(crate) fn invalidate_cycle_cache(&self) {
@@ -218,18 +412,91 @@ impl FixtureDatabase {
     /// Returns None if file cannot be read.
     pub(crate) fn get_file_content(&self, file_path: &Path) -> Option<Arc<String>> {
         if let Some(cached) = self.file_cache.get(file_path) {
+            self.touch_file_content(file_path);
             return Some(Arc::clone(cached.value()));
         }
+        if self.open_files.contains_key(file_path) {
+            // Not analyzed again yet after a rescan; the disk may be stale
+            return None;
+        }
 
         // or_insert (not insert): if an analyze_file with fresher editor-buffer
         // content raced in between the miss above and here, keep that buffer
         // instead of clobbering it with our possibly-stale disk read.
         let content = Arc::new(std::fs::read_to_string(file_path).ok()?);
-        let entry = self
-            .file_cache
-            .entry(file_path.to_path_buf())
-            .or_insert(content);
-        Some(Arc::clone(entry.value()))
+        if !self.retains_contents(file_path) {
+            return Some(content);
+        }
+        let content = match self.file_cache.entry(file_path.to_path_buf()) {
+            Entry::Occupied(entry) => Arc::clone(entry.get()),
+            Entry::Vacant(entry) => {
+                self.file_cache_bytes
+                    .fetch_add(content.len(), std::sync::atomic::Ordering::Relaxed);
+                Arc::clone(entry.insert(content).value())
+            }
+        };
+        self.touch_file_content(file_path);
+        Some(content)
+    }
+
+    /// Cache the contents of `file_path`, replacing any cached before.
+    pub(crate) fn cache_file_content(&self, file_path: PathBuf, content: Arc<String>) {
+        let len = content.len();
+        self.touch_file_content(&file_path);
+        let previous = self.file_cache.insert(file_path, content);
+        self.file_cache_bytes
+            .fetch_add(len, std::sync::atomic::Ordering::Relaxed);
+        if let Some(previous) = previous {
+            self.file_cache_bytes
+                .fetch_sub(previous.len(), std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    /// Drop the cached contents of `file_path`.
+    pub(crate) fn uncache_file_content(&self, file_path: &Path) {
+        self.file_cache_access.remove(file_path);
+        if let Some((_, previous)) = self.file_cache.remove(file_path) {
+            self.file_cache_bytes
+                .fetch_sub(previous.len(), std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+
+    /// Mark the cached contents of `file_path` as just used.
+    fn touch_file_content(&self, file_path: &Path) {
+        let tick = self
+            .file_cache_clock
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        self.file_cache_access.insert(file_path.to_path_buf(), tick);
+    }
+
+    /// Keep the contents cached in `file_cache` under `budget` bytes.
+    pub fn set_file_cache_budget(&self, budget: usize) {
+        self.file_cache_budget
+            .store(budget, std::sync::atomic::Ordering::Relaxed);
+    }
+
+    /// Index the functions decorated with `decorators` (names or dotted paths
+    /// of wrappers of `pytest.fixture`) as fixtures. Files analyzed before
+    /// keep their fixtures until re-analyzed.
+    pub fn set_fixture_decorators(&self, decorators: Vec<String>) {
+        *self.fixture_decorators.lock().unwrap() = decorators;
+    }
+
+    /// The configured decorators wrapping `pytest.fixture`.
+    pub(crate) fn fixture_decorator_aliases(&self) -> Vec<String> {
+        self.fixture_decorators.lock().unwrap().clone()
+    }
+
+    /// Detect test files by the file name `patterns` (e.g. `spec_*.py`)
+    /// instead of pytest's defaults; an empty list restores the defaults.
+    pub fn set_test_file_patterns(&self, patterns: Vec<glob::Pattern>) {
+        *self.test_file_patterns.lock().unwrap() = patterns;
+    }
+
+    /// Whether `file_path` was analyzed, whether or not its contents are
+    /// still cached.
+    pub(crate) fn is_indexed_file(&self, file_path: &Path) -> bool {
+        self.imports.contains_key(file_path) || self.file_cache.contains_key(file_path)
     }
 
     /// Get or compute line index for a file, with content-hash-based caching.
@@ -249,6 +516,9 @@ impl FixtureDatabase {
         // Build line index
         let line_index = Self::build_line_index(content);
         let arc_index = Arc::new(line_index);
+        if !self.retains_contents(file_path) {
+            return arc_index;
+        }
 
         // Store in cache with content hash
         self.line_index_cache.insert(
@@ -259,6 +529,18 @@ impl FixtureDatabase {
         arc_index
     }
 
+    /// The text of 1-based `line` of a file's `content`, without its line
+    /// ending, looked up through the cached line index.
+    pub(crate) fn get_line_text<'a>(
+        &self,
+        file_path: &Path,
+        content: &'a Arc<String>,
+        line: usize,
+    ) -> Option<&'a str> {
+        let index = self.get_line_index_for(file_path, content);
+        Self::line_text(content, &index, line)
+    }
+
     /// Get the line index for a content `Arc`, skipping the O(file) content
     /// hash when the same `Arc` was seen last. Used by per-column position
     /// conversions, which can run hundreds of times per request.
@@ -278,6 +560,9 @@ impl FixtureDatabase {
         }
 
         let index = self.get_line_index(file_path, content);
+        if !self.retains_contents(file_path) {
+            return index;
+        }
         self.line_index_by_identity.insert(
             file_path.to_path_buf(),
             (Arc::downgrade(content), Arc::clone(&index)),
@@ -292,7 +577,7 @@ impl FixtureDatabase {
         &self,
         file_path: &Path,
         content: &str,
-    ) -> Option<Arc<rustpython_parser::ast::Mod>> {
+    ) -> Option<Arc<ruff_python_ast::Mod>> {
         let content_hash = Self::hash_content(content);
 
         // Check cache first - only use if content hash matches
@@ -304,8 +589,11 @@ impl FixtureDatabase {
         }
 
         // Parse the content
-        let parsed = rustpython_parser::parse(content, rustpython_parser::Mode::Module, "").ok()?;
+        let parsed = parse_module(content).ok()?;
         let arc_ast = Arc::new(parsed);
+        if !self.retains_contents(file_path) {
+            return Some(arc_ast);
+        }
 
         // Store in cache with content hash
         self.ast_cache.insert(
@@ -341,7 +629,7 @@ impl FixtureDatabase {
         // Compute from AST (reuses ast_cache internally).
         let map = match self.get_parsed_ast(file_path, content) {
             Some(ast) => {
-                if let rustpython_parser::ast::Mod::Module(module) = ast.as_ref() {
+                if let ruff_python_ast::Mod::Module(module) = ast.as_ref() {
                     self.build_name_to_import_map(&module.body, file_path)
                 } else {
                     HashMap::new()
@@ -363,6 +651,21 @@ impl FixtureDatabase {
         hasher.finish()
     }
 
+    /// Whether `file_path` belongs to a third-party package: installed in
+    /// site-packages, or an editable install outside the workspace.
+    pub(crate) fn is_third_party_file(&self, file_path: &Path) -> bool {
+        file_path.to_string_lossy().contains("site-packages")
+            || self.is_editable_install_third_party(file_path)
+    }
+
+    /// Whether the contents of `file_path`, and what is derived from them
+    /// (line index, AST), are cached. Third-party files are only parsed to
+    /// extract their fixtures, so only those are kept — unless the file is
+    /// open in the editor.
+    pub(crate) fn retains_contents(&self, file_path: &Path) -> bool {
+        self.open_files.contains_key(file_path) || !self.is_third_party_file(file_path)
+    }
+
     /// Check if a file path is inside an editable install that is NOT within the workspace.
     /// Returns true if the file is from an external editable install (third-party).
     pub(crate) fn is_editable_install_third_party(&self, file_path: &Path) -> bool {
@@ -388,6 +691,24 @@ impl FixtureDatabase {
         false
     }
 
+    /// Analyze a document opened in the editor. Its buffer takes precedence
+    /// over the file on disk, which may not exist yet, until it is closed.
+    pub fn open_file(&self, file_path: PathBuf, content: &str) {
+        self.open_files.insert(file_path.clone(), ());
+        self.analyze_file(file_path, content);
+    }
+
+    /// Forget a document closed in the editor. A buffer that was never saved
+    /// leaves nothing behind; a file on disk keeps its analysis.
+    pub fn close_file(&self, file_path: &Path) {
+        self.open_files.remove(file_path);
+        if file_path.exists() {
+            self.cleanup_file_cache(file_path);
+        } else {
+            self.remove_files_under(file_path, &[]);
+        }
+    }
+
     /// Remove all cached data for a file.
     /// Called when a file is closed or deleted to prevent unbounded memory growth.
     pub fn cleanup_file_cache(&self, file_path: &Path) {
@@ -409,7 +730,7 @@ impl FixtureDatabase {
         self.name_import_map_cache.remove(&canonical);
 
         // Remove from file_cache
-        self.file_cache.remove(&canonical);
+        self.uncache_file_content(&canonical);
 
         // Remove from available_fixtures_cache (this file's cached available fixtures)
         self.available_fixtures_cache.remove(&canonical);
@@ -417,6 +738,9 @@ impl FixtureDatabase {
         // Remove from imported_fixtures_cache
         self.imported_fixtures_cache.remove(&canonical);
 
+        // Remove from module_outlines
+        self.module_outlines.remove(&canonical);
+
         // Note: We don't remove from canonical_path_cache because:
         // 1. It's keyed by original path, not canonical path
         // 2. Path->canonical mappings are stable and small
@@ -427,52 +751,72 @@ impl FixtureDatabase {
         // 2. They're cleaned up on next analyze_file call anyway
     }
 
-    /// Evict entries from caches if they exceed the maximum size.
+    /// Evict entries from caches if they exceed the maximum size or the
+    /// memory budget, least recently used first.
     /// Called periodically to prevent unbounded memory growth in very large workspaces.
     /// Most LSPs rely on did_close cleanup for open files; this is a safety net for
-    /// workspace scan files that accumulate over time.
+    /// workspace scan and site-packages files that accumulate over time.
     ///
     /// Only derived, recomputable caches are evicted; `definitions`/`usages` are
-    /// the index itself and are never evicted here.
+    /// the index itself and are never evicted here. Evicted contents are
+    /// re-read from disk by `get_file_content` when needed again.
     ///
     /// `keep` is the file currently being analyzed — it is never evicted, since
     /// its caches were just populated and are about to be used.
-    // ponytail: eviction picks arbitrary entries (DashMap iteration order), not
-    // LRU — add access-time tracking if churn ever shows up in profiles.
     pub(crate) fn evict_cache_if_needed(&self, keep: &Path) {
-        // Only evict if significantly over limit to avoid frequent eviction
-        if self.file_cache.len() > MAX_FILE_CACHE_SIZE {
-            debug!(
-                "File cache size ({}) exceeds limit ({}), evicting entries",
-                self.file_cache.len(),
-                MAX_FILE_CACHE_SIZE
-            );
-
-            // Remove ~25% of entries to avoid frequent re-eviction
-            let to_remove_count = self.file_cache.len() / 4;
-            let to_remove: Vec<PathBuf> = self
-                .file_cache
-                .iter()
-                .filter(|entry| entry.key() != keep)
-                .take(to_remove_count)
-                .map(|entry| entry.key().clone())
-                .collect();
-
-            for path in to_remove {
-                self.file_cache.remove(&path);
-                // Also clean related caches for consistency
-                self.line_index_cache.remove(&path);
-                self.line_index_by_identity.remove(&path);
-                self.ast_cache.remove(&path);
-                self.available_fixtures_cache.remove(&path);
-                self.imported_fixtures_cache.remove(&path);
-                self.name_import_map_cache.remove(&path);
-            }
+        use std::sync::atomic::Ordering;
 
-            debug!(
-                "Cache eviction complete, new size: {}",
-                self.file_cache.len()
-            );
+        let budget = self.file_cache_budget.load(Ordering::Relaxed);
+        if self.file_cache.len() <= MAX_FILE_CACHE_SIZE
+            && self.file_cache_bytes.load(Ordering::Relaxed) <= budget
+        {
+            return;
         }
+        debug!(
+            "File cache ({} files, {} bytes) exceeds its limits ({} files, {} bytes), evicting entries",
+            self.file_cache.len(),
+            self.file_cache_bytes.load(Ordering::Relaxed),
+            MAX_FILE_CACHE_SIZE,
+            budget
+        );
+
+        let mut candidates: Vec<(u64, PathBuf)> = self
+            .file_cache
+            .iter()
+            .filter(|entry| entry.key() != keep && !self.open_files.contains_key(entry.key()))
+            .map(|entry| {
+                let last_use = self
+                    .file_cache_access
+                    .get(entry.key())
+                    .map_or(0, |tick| *tick);
+                (last_use, entry.key().clone())
+            })
+            .collect();
+        candidates.sort_unstable();
+
+        // Go ~25% under the limits to avoid frequent re-eviction
+        let max_files = MAX_FILE_CACHE_SIZE / 4 * 3;
+        let max_bytes = budget / 4 * 3;
+        for (_, path) in candidates {
+            if self.file_cache.len() <= max_files
+                && self.file_cache_bytes.load(Ordering::Relaxed) <= max_bytes
+            {
+                break;
+            }
+            self.uncache_file_content(&path);
+            // Also clean related caches for consistency
+            self.line_index_cache.remove(&path);
+            self.line_index_by_identity.remove(&path);
+            self.ast_cache.remove(&path);
+            self.available_fixtures_cache.remove(&path);
+            self.imported_fixtures_cache.remove(&path);
+            self.name_import_map_cache.remove(&path);
+        }
+
+        debug!(
+            "Cache eviction complete, new size: {} files, {} bytes",
+            self.file_cache.len(),
+            self.file_cache_bytes.load(Ordering::Relaxed)
+        );
     }
 }
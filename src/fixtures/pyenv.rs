@@ -0,0 +1,205 @@
+//! pyenv and asdf interpreter resolution.
+//!
+//! Projects without a virtual environment often pin their interpreter with a
+//! `.python-version` file (pyenv) or a `python` line in `.tool-versions`
+//! (asdf), looked up from the project directory upwards. The pinned version
+//! is installed under `$PYENV_ROOT/versions/<version>` or
+//! `$ASDF_DATA_DIR/installs/python/<version>`, a prefix laid out like a venv
+//! (`bin/python3`, `lib/pythonX.Y/site-packages`), so it can be scanned as
+//! one.
+
+use super::env_path;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+/// The versions pinned for `root_path`, in order of preference, and the file
+/// pinning them.
+pub(crate) fn find_pinned_versions(root_path: &Path) -> Option<(PathBuf, Vec<String>)> {
+    for dir in root_path.ancestors() {
+        let python_version = dir.join(".python-version");
+        if let Ok(content) = std::fs::read_to_string(&python_version) {
+            let versions = parse_python_version(&content);
+            if !versions.is_empty() {
+                return Some((python_version, versions));
+            }
+        }
+        let tool_versions = dir.join(".tool-versions");
+        if let Ok(content) = std::fs::read_to_string(&tool_versions) {
+            let versions = parse_tool_versions(&content);
+            if !versions.is_empty() {
+                return Some((tool_versions, versions));
+            }
+        }
+    }
+    None
+}
+
+/// The versions of a `.python-version` file: one per line, or several on a
+/// line separated by whitespace.
+pub(crate) fn parse_python_version(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .flat_map(str::split_whitespace)
+        .map(str::to_string)
+        .collect()
+}
+
+/// The Python versions of an asdf `.tool-versions` file, from its
+/// `python <version>...` line.
+pub(crate) fn parse_tool_versions(content: &str) -> Vec<String> {
+    content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default())
+        .find_map(|line| {
+            let mut words = line.split_whitespace();
+            (words.next() == Some("python")).then(|| words.map(str::to_string).collect())
+        })
+        .unwrap_or_default()
+}
+
+/// The directories pyenv and asdf install Python versions in.
+pub(crate) fn version_dirs() -> Vec<PathBuf> {
+    let home = env_path("HOME").or_else(|| env_path("USERPROFILE"));
+    let pyenv_root = env_path("PYENV_ROOT").or_else(|| home.as_ref().map(|h| h.join(".pyenv")));
+    let asdf_dir = env_path("ASDF_DATA_DIR").or_else(|| home.as_ref().map(|h| h.join(".asdf")));
+    pyenv_root
+        .map(|root| root.join("versions"))
+        .into_iter()
+        .chain(asdf_dir.map(|dir| dir.join("installs").join("python")))
+        .collect()
+}
+
+/// Numeric components of a version, for ordering: `3.12.10` after `3.12.9`.
+fn version_key(version: &str) -> Vec<u64> {
+    version
+        .split('.')
+        .map(|part| {
+            let digits: String = part.chars().take_while(char::is_ascii_digit).collect();
+            digits.parse().unwrap_or(0)
+        })
+        .collect()
+}
+
+/// The install prefix of `version` in `version_dirs`: the directory named
+/// after it, or, for a prefix such as `3.12`, the latest installed `3.12.x`.
+pub(crate) fn resolve_version(version: &str, version_dirs: &[PathBuf]) -> Option<PathBuf> {
+    if version == "system" {
+        return None;
+    }
+    for dir in version_dirs {
+        let exact = dir.join(version);
+        if exact.is_dir() {
+            return Some(exact);
+        }
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            continue;
+        };
+        let prefix = format!("{}.", version);
+        let latest = entries
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| {
+                name.strip_prefix(&prefix)
+                    .is_some_and(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+            })
+            .max_by_key(|name| version_key(name));
+        if let Some(latest) = latest {
+            return Some(dir.join(latest));
+        }
+    }
+    None
+}
+
+/// Locate the interpreter prefix pinned for a workspace by pyenv's
+/// `.python-version` or asdf's `.tool-versions`.
+pub(crate) fn find_pinned_python(root_path: &Path) -> Option<PathBuf> {
+    let (file, versions) = find_pinned_versions(root_path)?;
+    debug!("Read {:?}: {:?}", file, versions);
+    let version_dirs = version_dirs();
+    for version in &versions {
+        if let Some(prefix) = resolve_version(version, &version_dirs) {
+            info!(
+                "Using Python {} pinned by {:?}: {:?}",
+                version, file, prefix
+            );
+            return Some(prefix);
+        }
+    }
+    warn!(
+        "Python {} pinned by {:?} is not installed",
+        versions.join(", "),
+        file
+    );
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_parse_python_version() {
+        assert_eq!(parse_python_version("3.12.1\n"), vec!["3.12.1"]);
+        assert_eq!(
+            parse_python_version("# pinned\nmy-venv 3.11\n\nsystem\n"),
+            vec!["my-venv", "3.11", "system"]
+        );
+        assert!(parse_python_version("\n").is_empty());
+    }
+
+    #[test]
+    fn test_parse_tool_versions() {
+        let content = "nodejs 20.1.0\npython 3.12.1 3.11.7 # both\nruby 3.3.0\n";
+        assert_eq!(parse_tool_versions(content), vec!["3.12.1", "3.11.7"]);
+        assert!(parse_tool_versions("nodejs 20.1.0\n").is_empty());
+    }
+
+    #[test]
+    fn test_find_pinned_versions_walks_up() {
+        let root = tempdir().unwrap();
+        let project = root.path().join("services").join("api");
+        fs::create_dir_all(&project).unwrap();
+        fs::write(root.path().join(".tool-versions"), "python 3.11.7\n").unwrap();
+
+        let (file, versions) = find_pinned_versions(&project).unwrap();
+        assert_eq!(file, root.path().join(".tool-versions"));
+        assert_eq!(versions, vec!["3.11.7"]);
+
+        // The nearest file wins
+        fs::write(project.join(".python-version"), "3.12.1\n").unwrap();
+        let (file, versions) = find_pinned_versions(&project).unwrap();
+        assert_eq!(file, project.join(".python-version"));
+        assert_eq!(versions, vec!["3.12.1"]);
+    }
+
+    #[test]
+    fn test_resolve_version() {
+        let pyenv = tempdir().unwrap();
+        let asdf = tempdir().unwrap();
+        for version in ["3.12.9", "3.12.10", "3.1.2"] {
+            fs::create_dir_all(pyenv.path().join(version)).unwrap();
+        }
+        fs::create_dir_all(asdf.path().join("3.11.7")).unwrap();
+        let dirs = vec![pyenv.path().to_path_buf(), asdf.path().to_path_buf()];
+
+        assert_eq!(
+            resolve_version("3.12.9", &dirs),
+            Some(pyenv.path().join("3.12.9"))
+        );
+        // A prefix resolves to the latest matching version
+        assert_eq!(
+            resolve_version("3.12", &dirs),
+            Some(pyenv.path().join("3.12.10"))
+        );
+        assert_eq!(
+            resolve_version("3.11", &dirs),
+            Some(asdf.path().join("3.11.7"))
+        );
+        assert_eq!(resolve_version("3.13", &dirs), None);
+        assert_eq!(resolve_version("system", &dirs), None);
+    }
+}
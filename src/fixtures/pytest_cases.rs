@@ -0,0 +1,204 @@
+//! pytest-cases support beyond its decorators (which `decorators` recognizes):
+//! `fixture_union("name", [a, "b"])` defines a fixture made of other fixtures,
+//! and `fixture_ref(a)` references a fixture from `@parametrize` values.
+
+use super::decorators;
+use super::types::FixtureDefinition;
+use super::FixtureDatabase;
+use ruff_python_ast::visitor::{self, Visitor};
+use ruff_python_ast::{Expr, ExprCall, Stmt};
+use ruff_text_size::{Ranged, TextRange};
+use std::path::PathBuf;
+use tracing::info;
+
+/// Whether `func` is `pytest_cases.<name>` or the bare imported `<name>`.
+fn is_pytest_cases_call(func: &Expr, name: &str) -> bool {
+    match func {
+        Expr::Name(n) => n.id.as_str() == name,
+        Expr::Attribute(attr) => {
+            attr.attr.as_str() == name
+                && matches!(attr.value.as_ref(), Expr::Name(n) if n.id.as_str() == "pytest_cases")
+        }
+        _ => false,
+    }
+}
+
+/// A fixture named by a `fixture_union` element or a `fixture_ref` argument:
+/// either the fixture function itself or its name as a string.
+fn fixture_reference(expr: &Expr, content: &str) -> Option<(String, TextRange)> {
+    match expr {
+        Expr::Name(name) => Some((name.id.to_string(), name.range)),
+        Expr::StringLiteral(name) => {
+            let literal = content
+                .get(name.range.start().to_usize()..name.range.end().to_usize())
+                .unwrap_or("");
+            Some((
+                name.value.to_string(),
+                decorators::literal_content_range(literal, name.range),
+            ))
+        }
+        _ => None,
+    }
+}
+
+/// A `fixture_union(name, fixtures)` call: the union's name and the fixtures it
+/// alternates between.
+struct FixtureUnion {
+    name: String,
+    range: TextRange,
+    fixtures: Vec<(String, TextRange)>,
+}
+
+fn fixture_union(call: &ExprCall, content: &str) -> Option<FixtureUnion> {
+    if !is_pytest_cases_call(&call.func, "fixture_union") {
+        return None;
+    }
+    let argument = |position: usize, keyword: &str| {
+        call.arguments.args.get(position).or_else(|| {
+            call.arguments
+                .keywords
+                .iter()
+                .find(|kw| kw.arg.as_ref().is_some_and(|a| a.as_str() == keyword))
+                .map(|kw| &kw.value)
+        })
+    };
+
+    let (name, range) = match argument(0, "name")? {
+        Expr::StringLiteral(name) => {
+            let literal = content
+                .get(name.range.start().to_usize()..name.range.end().to_usize())
+                .unwrap_or("");
+            (
+                name.value.to_string(),
+                decorators::literal_content_range(literal, name.range),
+            )
+        }
+        _ => return None,
+    };
+    let fixtures = match argument(1, "fixtures") {
+        Some(Expr::List(list)) => list.elts.as_slice(),
+        Some(Expr::Tuple(tuple)) => tuple.elts.as_slice(),
+        _ => &[],
+    }
+    .iter()
+    .filter_map(|elt| fixture_reference(elt, content))
+    .collect();
+
+    Some(FixtureUnion {
+        name,
+        range,
+        fixtures,
+    })
+}
+
+/// Collects the fixtures referenced by `fixture_ref(...)` calls anywhere.
+struct FixtureRefCollector<'a> {
+    content: &'a str,
+    refs: Vec<(String, TextRange)>,
+}
+
+impl<'a> Visitor<'a> for FixtureRefCollector<'_> {
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        if let Expr::Call(call) = expr {
+            if is_pytest_cases_call(&call.func, "fixture_ref") {
+                if let Some(reference) = call
+                    .arguments
+                    .args
+                    .first()
+                    .and_then(|arg| fixture_reference(arg, self.content))
+                {
+                    self.refs.push(reference);
+                }
+            }
+        }
+        visitor::walk_expr(self, expr);
+    }
+}
+
+impl FixtureDatabase {
+    /// Record module-level `fixture_union(...)` fixtures and the fixtures
+    /// referenced by `fixture_union` elements and `fixture_ref(...)` calls.
+    pub(crate) fn record_pytest_cases_fixtures(
+        &self,
+        body: &[Stmt],
+        file_path: &PathBuf,
+        content: &str,
+        line_index: &[usize],
+    ) {
+        let mut references = Vec::new();
+
+        for stmt in body {
+            let value = match stmt {
+                Stmt::Assign(assign) => assign.value.as_ref(),
+                Stmt::Expr(expr_stmt) => expr_stmt.value.as_ref(),
+                _ => continue,
+            };
+            let Expr::Call(call) = value else { continue };
+            let Some(union) = fixture_union(call, content) else {
+                continue;
+            };
+
+            info!(
+                "Found pytest-cases fixture_union {} in {:?}",
+                union.name, file_path
+            );
+
+            let line = self.get_line_from_offset(union.range.start().to_usize(), line_index);
+            let end_line = self.get_line_from_offset(call.range().end().to_usize(), line_index);
+            let names: Vec<String> = union.fixtures.iter().map(|(n, _)| n.clone()).collect();
+            self.record_fixture_definition(FixtureDefinition {
+                name: union.name,
+                file_path: file_path.clone(),
+                line,
+                end_line,
+                start_char: self
+                    .get_char_position_from_offset(union.range.start().to_usize(), line_index),
+                end_char: self
+                    .get_char_position_from_offset(union.range.end().to_usize(), line_index),
+                docstring: Some(format!(
+                    "Union fixture generated by pytest-cases; takes the value of each of {} in turn.",
+                    names
+                        .iter()
+                        .map(|n| format!("`{}`", n))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )),
+                is_third_party: self.is_third_party_file(file_path),
+                is_plugin: self.plugin_fixture_files.contains_key(file_path),
+                dependencies: names,
+                ..Default::default()
+            });
+            references.extend(union.fixtures);
+        }
+
+        if content.contains("fixture_ref") {
+            let mut collector = FixtureRefCollector {
+                content,
+                refs: Vec::new(),
+            };
+            collector.visit_body(body);
+            references.extend(collector.refs);
+        }
+
+        for (fixture_name, range) in references {
+            let usage_line = self.get_line_from_offset(range.start().to_usize(), line_index);
+            let start_char =
+                self.get_char_position_from_offset(range.start().to_usize(), line_index);
+            let end_char = self.get_char_position_from_offset(range.end().to_usize(), line_index);
+
+            info!(
+                "Found pytest-cases fixture reference: {} at {:?}:{}:{}",
+                fixture_name, file_path, usage_line, start_char
+            );
+
+            self.record_fixture_usage(
+                file_path,
+                fixture_name,
+                usage_line,
+                start_char,
+                end_char,
+                false, // fixture_union / fixture_ref — not a function parameter
+            );
+        }
+    }
+}
@@ -0,0 +1,342 @@
+//! pytest rootdir and configuration file discovery.
+//!
+//! Mirrors pytest's `determine_setup`: walking up from the invocation
+//! directory, the first directory holding a pytest configuration file
+//! (`pytest.ini`, `.pytest.ini`, `pyproject.toml`, `tox.ini`, `setup.cfg`) is
+//! the rootdir. Without one, the first ancestor holding `setup.py` is used,
+//! and failing that the invocation directory itself.
+//!
+//! A monorepo holds several projects pytest runs separately: directories
+//! below the workspace root holding their own configuration file are roots
+//! of their own, and the files under them get that root's rootdir and
+//! confcutdir instead of the workspace's.
+
+use super::FixtureDatabase;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Configuration files pytest reads, in its discovery order, with the section
+/// holding its settings.
+pub(crate) const CONFIG_FILES: &[(&str, &str)] = &[
+    ("pytest.ini", "pytest"),
+    (".pytest.ini", "pytest"),
+    ("pyproject.toml", "tool.pytest.ini_options"),
+    ("tox.ini", "pytest"),
+    ("setup.cfg", "tool:pytest"),
+];
+
+/// The project layout pytest determines for a workspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PytestRoot {
+    /// pytest's rootdir.
+    pub rootdir: PathBuf,
+    /// The configuration file pytest reads its settings from, if any.
+    pub inifile: Option<PathBuf>,
+    /// The `testpaths` option, resolved against the rootdir.
+    pub testpaths: Vec<PathBuf>,
+    /// The `pythonpath` option, resolved against the rootdir: directories
+    /// pytest puts at the head of `sys.path`.
+    pub pythonpath: Vec<PathBuf>,
+}
+
+impl PytestRoot {
+    /// pytest's default `--confcutdir`: the directory of the configuration file,
+    /// or the rootdir. conftest.py files above it are not loaded.
+    pub fn confcutdir(&self) -> &Path {
+        self.inifile
+            .as_deref()
+            .and_then(Path::parent)
+            .unwrap_or(&self.rootdir)
+    }
+}
+
+/// Values of the options in the `[section]` of an ini-style file, keyed by
+/// option name, or `None` if the file has no such section.
+///
+/// Multi-line values (indented continuation lines) yield one entry per line.
+pub(crate) fn ini_section(content: &str, section: &str) -> Option<HashMap<String, Vec<String>>> {
+    let header = format!("[{}]", section);
+    let mut in_section = false;
+    let mut found_section = false;
+    let mut current: Option<String> = None;
+    let mut options: HashMap<String, Vec<String>> = HashMap::new();
+
+    for raw in content.lines() {
+        let trimmed = raw.trim();
+        if trimmed.starts_with('[') {
+            in_section = trimmed == header;
+            found_section |= in_section;
+            current = None;
+            continue;
+        }
+        if !in_section || trimmed.starts_with('#') || trimmed.starts_with(';') {
+            continue;
+        }
+        if raw.starts_with([' ', '\t']) {
+            if let Some(key) = &current {
+                options
+                    .entry(key.clone())
+                    .or_default()
+                    .push(trimmed.to_string());
+            }
+            continue;
+        }
+        current = None;
+        if let Some((key, value)) = trimmed.split_once('=') {
+            let key = key.trim().to_string();
+            options
+                .entry(key.clone())
+                .or_default()
+                .push(value.trim().to_string());
+            current = Some(key);
+        }
+    }
+
+    found_section.then_some(options)
+}
+
+/// The `[tool.pytest.ini_options]` table (or pytest 9's native `[tool.pytest]`)
+/// of a pyproject.toml, as ini-style option values.
+fn pyproject_section(content: &str) -> Option<HashMap<String, Vec<String>>> {
+    let value: toml::Value = match toml::from_str(content) {
+        Ok(value) => value,
+        Err(e) => {
+            warn!("Failed to parse pyproject.toml: {}", e);
+            return None;
+        }
+    };
+    let pytest = value.get("tool")?.get("pytest")?;
+    let table = pytest.get("ini_options").unwrap_or(pytest).as_table()?;
+
+    let options = table
+        .iter()
+        .map(|(key, value)| {
+            let values = match value {
+                toml::Value::String(s) => vec![s.clone()],
+                toml::Value::Array(entries) => entries
+                    .iter()
+                    .filter_map(|entry| entry.as_str().map(str::to_string))
+                    .collect(),
+                other => vec![other.to_string()],
+            };
+            (key.clone(), values)
+        })
+        .collect();
+    Some(options)
+}
+
+/// The pytest options of a configuration file, or `None` when the file holds
+/// no pytest configuration. `pytest.ini` counts even without a `[pytest]` section.
+fn config_options(file_name: &str, content: &str) -> Option<HashMap<String, Vec<String>>> {
+    let section = CONFIG_FILES
+        .iter()
+        .find(|(name, _)| *name == file_name)
+        .map(|(_, section)| *section)?;
+    if file_name == "pyproject.toml" {
+        return pyproject_section(content);
+    }
+    let options = ini_section(content, section);
+    if options.is_none() && matches!(file_name, "pytest.ini" | ".pytest.ini") {
+        return Some(HashMap::new());
+    }
+    options
+}
+
+/// The root of a run configured by a file in `dir`, or `None` when `dir`
+/// holds no file with pytest configuration.
+pub(crate) fn configured_root(dir: &Path) -> Option<PytestRoot> {
+    CONFIG_FILES.iter().find_map(|(file_name, _)| {
+        let path = dir.join(file_name);
+        let content = std::fs::read_to_string(&path).ok()?;
+        let options = config_options(file_name, &content)?;
+        let paths = |option: &str| {
+            options
+                .get(option)
+                .into_iter()
+                .flatten()
+                .flat_map(|line| line.split_whitespace())
+                .map(|path| dir.join(path))
+                .collect()
+        };
+        Some(PytestRoot {
+            rootdir: dir.to_path_buf(),
+            inifile: Some(path),
+            testpaths: paths("testpaths"),
+            pythonpath: paths("pythonpath"),
+        })
+    })
+}
+
+/// Determine pytest's rootdir and configuration file for a run started in `start`.
+pub fn discover_pytest_root(start: &Path) -> PytestRoot {
+    // A pyproject.toml without pytest settings still marks the rootdir when
+    // no other configuration file is found (pytest >= 8.1)
+    let mut bare_pyproject: Option<PathBuf> = None;
+
+    for dir in start.ancestors() {
+        if let Some(pytest_root) = configured_root(dir) {
+            return pytest_root;
+        }
+        let pyproject = dir.join("pyproject.toml");
+        if bare_pyproject.is_none() && pyproject.is_file() {
+            bare_pyproject = Some(pyproject);
+        }
+    }
+
+    if let Some(path) = bare_pyproject {
+        return PytestRoot {
+            rootdir: path.parent().unwrap_or(start).to_path_buf(),
+            inifile: Some(path),
+            testpaths: Vec::new(),
+            pythonpath: Vec::new(),
+        };
+    }
+
+    let rootdir = start
+        .ancestors()
+        .find(|dir| dir.join("setup.py").is_file())
+        .unwrap_or(start);
+    PytestRoot {
+        rootdir: rootdir.to_path_buf(),
+        inifile: None,
+        testpaths: Vec::new(),
+        pythonpath: Vec::new(),
+    }
+}
+
+impl FixtureDatabase {
+    /// Discover the pytest rootdir and configuration for a workspace and load
+    /// the settings that depend on it.
+    pub fn load_pytest_root(&self, workspace_root: &Path) {
+        let pytest_root = discover_pytest_root(workspace_root);
+        info!(
+            "pytest rootdir: {:?}, configfile: {:?}",
+            pytest_root.rootdir, pytest_root.inifile
+        );
+        self.load_pytest_markers(pytest_root.inifile.as_deref());
+        *self.pytest_root.lock().unwrap() = Some(pytest_root);
+    }
+
+    /// The pytest rootdir and configuration discovered for the workspace.
+    pub fn pytest_root(&self) -> Option<PytestRoot> {
+        self.pytest_root.lock().unwrap().clone()
+    }
+
+    /// Record the projects below the workspace root configured by their own
+    /// file, given the directories holding a configuration file.
+    pub(crate) fn load_nested_pytest_roots(&self, config_dirs: &[PathBuf]) {
+        let workspace_rootdir = self.pytest_root().map(|root| root.rootdir);
+        let mut nested: Vec<PytestRoot> = config_dirs
+            .iter()
+            .filter(|dir| Some(*dir) != workspace_rootdir.as_ref())
+            .filter_map(|dir| configured_root(dir))
+            .collect();
+        nested.sort_by(|a, b| a.rootdir.cmp(&b.rootdir));
+        nested.dedup_by(|a, b| a.rootdir == b.rootdir);
+        for pytest_root in &nested {
+            info!(
+                "Nested pytest rootdir: {:?}, configfile: {:?}",
+                pytest_root.rootdir, pytest_root.inifile
+            );
+        }
+        *self.nested_pytest_roots.lock().unwrap() = nested;
+    }
+
+    /// The pytest root `file_path` belongs to: the closest project configured
+    /// by its own file, or the workspace's.
+    pub fn pytest_root_for(&self, file_path: &Path) -> Option<PytestRoot> {
+        self.nested_pytest_roots
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|root| file_path.starts_with(&root.rootdir))
+            .max_by_key(|root| root.rootdir.components().count())
+            .cloned()
+            .or_else(|| self.pytest_root())
+    }
+
+    /// The directories of the `pythonpath` option of the root `file_path`
+    /// belongs to, searched first when resolving absolute module names, as
+    /// pytest puts them first on `sys.path`.
+    pub(crate) fn ini_pythonpath(&self, file_path: &Path) -> Vec<PathBuf> {
+        self.pytest_root_for(file_path)
+            .map(|pytest_root| pytest_root.pythonpath)
+            .unwrap_or_default()
+    }
+
+    /// The confcutdir that applies to `file_path`, if the file is inside it.
+    /// conftest.py files in directories above it are not loaded by pytest.
+    pub(crate) fn confcutdir_for(&self, file_path: &Path) -> Option<PathBuf> {
+        let pytest_root = self.pytest_root_for(file_path)?;
+        let confcutdir = pytest_root.confcutdir();
+        file_path
+            .starts_with(confcutdir)
+            .then(|| confcutdir.to_path_buf())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config_options() {
+        assert!(config_options("pytest.ini", "").is_some());
+        assert!(config_options("tox.ini", "[tox]\nenvlist = py3\n").is_none());
+        assert!(config_options("setup.cfg", "[tool:pytest]\naddopts = -ra\n").is_some());
+        assert!(config_options("pyproject.toml", "[tool.ruff]\n").is_none());
+
+        let options = config_options(
+            "pyproject.toml",
+            "[tool.pytest.ini_options]\ntestpaths = [\"tests\", \"it\"]\nxfail_strict = true\n",
+        )
+        .unwrap();
+        assert_eq!(options["testpaths"], vec!["tests", "it"]);
+        assert_eq!(options["xfail_strict"], vec!["true"]);
+    }
+
+    #[test]
+    fn test_discover_pytest_root() {
+        let temp = tempfile::tempdir().unwrap();
+        let root = temp.path().canonicalize().unwrap();
+        let project = root.join("project");
+        let tests = project.join("tests").join("unit");
+        std::fs::create_dir_all(&tests).unwrap();
+
+        // No configuration: setup.py marks the rootdir, else the start directory
+        std::fs::write(project.join("setup.py"), "").unwrap();
+        let found = discover_pytest_root(&tests);
+        assert_eq!(found.rootdir, project);
+        assert_eq!(found.inifile, None);
+
+        // A pyproject.toml without pytest settings is the fallback
+        std::fs::write(project.join("pyproject.toml"), "[tool.ruff]\n").unwrap();
+        let found = discover_pytest_root(&tests);
+        assert_eq!(found.inifile, Some(project.join("pyproject.toml")));
+
+        // The closest configuration file wins, with testpaths relative to it
+        std::fs::write(
+            project.join("tests").join("tox.ini"),
+            "[pytest]\ntestpaths =\n    unit\n    functional\npythonpath = . ../src\n",
+        )
+        .unwrap();
+        let found = discover_pytest_root(&tests);
+        assert_eq!(found.rootdir, project.join("tests"));
+        assert_eq!(
+            found.testpaths,
+            vec![
+                project.join("tests").join("unit"),
+                project.join("tests").join("functional")
+            ]
+        );
+        assert_eq!(
+            found.pythonpath,
+            vec![
+                project.join("tests").join("."),
+                project.join("tests").join("../src")
+            ]
+        );
+        assert_eq!(found.confcutdir(), project.join("tests"));
+    }
+}
@@ -3,17 +3,145 @@
 //! This module contains methods for finding fixture definitions,
 //! references, and providing completion context.
 
+use super::builtins;
 use super::decorators;
 use super::types::{
-    CompletionContext, FixtureDefinition, FixtureScope, FixtureUsage, ParamInsertionInfo,
-    UndeclaredFixture,
+    CandidateSource, CompletionContext, DeprecatedYieldFixture, FixtureDefinition, FixtureScope,
+    FixtureUsage, IndirectIssue, InjectedFixture, InjectionSource, InvalidIndirect,
+    ParamInsertionInfo, RedundancyReason, RedundantUsefixture, ResolutionCandidate,
+    ResolutionExplanation, ScopeEdit, TestCaseFixtureParam, TestFixtures, UndeclaredFixture,
+    UnusedFixtureParam,
 };
 use super::FixtureDatabase;
-use rustpython_parser::ast::{Arguments, Expr, Ranged, Stmt};
-use std::collections::HashSet;
+use ruff_python_ast::visitor::{self, Visitor};
+use ruff_python_ast::{Decorator, Expr, Parameters, Stmt, StmtClassDef, StmtFunctionDef};
+use ruff_text_size::{Ranged, TextRange};
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use tracing::{debug, info};
 
+/// Base classes that make a class a `unittest.TestCase` (unittest, Django and
+/// Django REST framework test cases).
+const TESTCASE_BASES: &[&str] = &[
+    "TestCase",
+    "IsolatedAsyncioTestCase",
+    "SimpleTestCase",
+    "TransactionTestCase",
+    "LiveServerTestCase",
+    "StaticLiveServerTestCase",
+    "APITestCase",
+    "APISimpleTestCase",
+    "APITransactionTestCase",
+    "APILiveServerTestCase",
+];
+
+/// Fixtures commonly requested only for their side effects, although they
+/// provide a value: capturing output and recording warnings.
+const SIDE_EFFECT_FIXTURES: &[&str] =
+    &["capsys", "capsysbinary", "capfd", "capfdbinary", "recwarn"];
+
+/// Collects the names a function body references, nested scopes included.
+#[derive(Default)]
+struct NameReferenceCollector {
+    names: HashSet<String>,
+}
+
+impl<'a> Visitor<'a> for NameReferenceCollector {
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        if let Expr::Name(name) = expr {
+            self.names.insert(name.id.to_string());
+        }
+        visitor::walk_expr(self, expr);
+    }
+}
+
+/// Finds a `return` or `yield` of a value in a function body, outside of
+/// nested functions, lambdas and classes.
+#[derive(Default)]
+struct ValueReturnFinder {
+    found: bool,
+}
+
+impl<'a> Visitor<'a> for ValueReturnFinder {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        match stmt {
+            Stmt::FunctionDef(_) | Stmt::ClassDef(_) => {}
+            Stmt::Return(ret) if ret.value.is_some() => self.found = true,
+            _ => visitor::walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        match expr {
+            Expr::Lambda(_) => {}
+            Expr::Yield(yield_expr) if yield_expr.value.is_some() => self.found = true,
+            Expr::YieldFrom(_) => self.found = true,
+            _ => visitor::walk_expr(self, expr),
+        }
+    }
+}
+
+/// Finds the name expression spanning `offset`, the cursor touching either
+/// end of it included.
+struct NameAtOffsetFinder {
+    offset: ruff_text_size::TextSize,
+    found: Option<String>,
+}
+
+impl<'a> Visitor<'a> for NameAtOffsetFinder {
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        match expr {
+            Expr::Name(name) if name.range.contains_inclusive(self.offset) => {
+                self.found = Some(name.id.to_string());
+            }
+            _ => visitor::walk_expr(self, expr),
+        }
+    }
+}
+
+/// Collects the uses of a parameter in a function body, skipping nested
+/// functions and lambdas that shadow it with a parameter of their own.
+struct ParameterUseCollector<'n> {
+    name: &'n str,
+    ranges: Vec<TextRange>,
+}
+
+impl ParameterUseCollector<'_> {
+    fn shadows(&self, parameters: &Parameters) -> bool {
+        FixtureDatabase::all_args(parameters)
+            .map(|arg| &arg.parameter)
+            .chain(parameters.vararg.as_deref())
+            .chain(parameters.kwarg.as_deref())
+            .any(|p| p.name.as_str() == self.name)
+    }
+}
+
+impl<'a> Visitor<'a> for ParameterUseCollector<'_> {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        match stmt {
+            Stmt::FunctionDef(f) if self.shadows(&f.parameters) => {}
+            _ => visitor::walk_stmt(self, stmt),
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        match expr {
+            Expr::Name(name) if name.id.as_str() == self.name => self.ranges.push(name.range),
+            Expr::Lambda(lambda)
+                if lambda
+                    .parameters
+                    .as_deref()
+                    .is_some_and(|parameters| self.shadows(parameters)) => {}
+            _ => visitor::walk_expr(self, expr),
+        }
+    }
+}
+
+/// Whether `def` is installed in one of the `site_packages` directories.
+fn in_site_packages(def: &FixtureDefinition, site_packages: &[PathBuf]) -> bool {
+    site_packages.iter().any(|sp| def.file_path.starts_with(sp))
+}
+
 impl FixtureDatabase {
     /// Find fixture definition for a given position in a file
     pub fn find_fixture_definition(
@@ -30,7 +158,7 @@ impl FixtureDatabase {
         let target_line = (line + 1) as usize; // Convert from 0-based to 1-based
 
         let content = self.get_file_content(file_path)?;
-        let line_content = content.lines().nth(target_line.saturating_sub(1))?;
+        let line_content = self.get_line_text(file_path, &content, target_line)?;
         debug!("Line content: {}", line_content);
 
         let word_at_cursor = self.extract_word_at_position(line_content, character as usize)?;
@@ -60,12 +188,13 @@ impl FixtureDatabase {
                                 return self.find_closest_definition_excluding(
                                     file_path,
                                     &usage.name,
+                                    target_line,
                                     Some(current_def),
                                 );
                             }
                         }
 
-                        return self.find_closest_definition(file_path, &usage.name);
+                        return self.find_closest_definition(file_path, &usage.name, target_line);
                     }
                 }
             }
@@ -113,7 +242,7 @@ impl FixtureDatabase {
         // If not a usage, check if we're on a fixture definition line
         let target_line = (line + 1) as usize; // Convert from 0-based to 1-based
         let content = self.get_file_content(file_path)?;
-        let line_content = content.lines().nth(target_line.saturating_sub(1))?;
+        let line_content = self.get_line_text(file_path, &content, target_line)?;
         let word_at_cursor = self.extract_word_at_position(line_content, character as usize)?;
 
         // Check if this word matches a fixture definition at this line
@@ -148,23 +277,27 @@ impl FixtureDatabase {
         None
     }
 
-    /// Find the closest fixture definition based on pytest priority rules.
+    /// Find the closest fixture definition based on pytest priority rules for a
+    /// request at `line` (1-based), which also sees the fixtures of the test
+    /// classes enclosing that line.
     pub(crate) fn find_closest_definition(
         &self,
         file_path: &Path,
         fixture_name: &str,
+        line: usize,
     ) -> Option<FixtureDefinition> {
-        self.find_closest_definition_with_filter(file_path, fixture_name, |_| true)
+        self.find_closest_definition_with_filter(file_path, fixture_name, line, |_| true)
     }
 
-    /// Find the closest definition, excluding a specific definition.
+    /// Find the closest definition for a request at `line`, excluding a specific definition.
     pub(crate) fn find_closest_definition_excluding(
         &self,
         file_path: &Path,
         fixture_name: &str,
+        line: usize,
         exclude: Option<&FixtureDefinition>,
     ) -> Option<FixtureDefinition> {
-        self.find_closest_definition_with_filter(file_path, fixture_name, |def| {
+        self.find_closest_definition_with_filter(file_path, fixture_name, line, |def| {
             if let Some(excluded) = exclude {
                 def != excluded
             } else {
@@ -173,15 +306,53 @@ impl FixtureDatabase {
         })
     }
 
+    /// Classes of `file_path` whose fixtures are visible at `line`, with their
+    /// priority (higher wins). Tests see the fixtures of every class enclosing
+    /// them, innermost first, and of those classes' base classes in the same file.
+    fn visible_class_ranks(&self, file_path: &Path, line: usize) -> HashMap<String, usize> {
+        let mut ranks = HashMap::new();
+        let Some(scopes) = self.class_scopes.get(file_path) else {
+            return ranks;
+        };
+
+        let enclosing = scopes
+            .iter()
+            .filter(|scope| scope.start_line <= line && line <= scope.end_line);
+        for (depth, class) in enclosing.enumerate() {
+            ranks.insert(class.name.clone(), depth * 2 + 2);
+
+            // Inherited fixtures rank just below the class's own
+            let mut bases = class.bases.clone();
+            let mut seen = HashSet::new();
+            while let Some(base) = bases.pop() {
+                let Some(base_scope) = scopes
+                    .iter()
+                    .find(|scope| scope.name.rsplit('.').next() == Some(base.as_str()))
+                else {
+                    continue;
+                };
+                if seen.insert(base_scope.name.clone()) {
+                    ranks
+                        .entry(base_scope.name.clone())
+                        .or_insert(depth * 2 + 1);
+                    bases.extend(base_scope.bases.iter().cloned());
+                }
+            }
+        }
+        ranks
+    }
+
     /// Internal helper that implements pytest priority rules with a custom filter.
     /// Priority order:
-    /// 1. Same file (highest priority, last definition wins)
+    /// 1. Same file (highest priority): fixtures of the test classes visible at
+    ///    `line` (innermost class first), then module level; last definition wins
     /// 2. Closest conftest.py in parent directories (including imported fixtures)
     /// 3. Third-party fixtures from site-packages
     fn find_closest_definition_with_filter<F>(
         &self,
         file_path: &Path,
         fixture_name: &str,
+        line: usize,
         filter: F,
     ) -> Option<FixtureDefinition>
     where
@@ -195,10 +366,15 @@ impl FixtureDatabase {
             fixture_name, file_path
         );
 
-        if let Some(last_def) = definitions
+        let class_ranks = self.visible_class_ranks(file_path, line);
+        if let Some((_, last_def)) = definitions
             .iter()
             .filter(|def| def.file_path == file_path && filter(def))
-            .max_by_key(|def| def.line)
+            .filter_map(|def| match &def.class_name {
+                None => Some((0, def)),
+                Some(class_name) => class_ranks.get(class_name).map(|rank| (*rank, def)),
+            })
+            .max_by_key(|(rank, def)| (*rank, def.line))
         {
             info!(
                 "Found fixture {} in same file at line {}",
@@ -207,8 +383,13 @@ impl FixtureDatabase {
             return Some(last_def.clone());
         }
 
-        // Priority 2: Search upward through conftest.py files
+        // Fixtures defined in test classes are only visible within their file
+        let filter = |def: &FixtureDefinition| def.class_name.is_none() && filter(def);
+
+        // Priority 2: Search upward through conftest.py files, stopping at
+        // pytest's confcutdir
         let mut current_dir = file_path.parent()?;
+        let confcutdir = self.confcutdir_for(file_path);
 
         debug!(
             "Searching for fixture {} in conftest.py files starting from {:?}",
@@ -260,8 +441,14 @@ impl FixtureDatabase {
             }
 
             match current_dir.parent() {
-                Some(parent) => current_dir = parent,
-                None => break,
+                Some(parent)
+                    if confcutdir
+                        .as_ref()
+                        .is_none_or(|cut| parent.starts_with(cut)) =>
+                {
+                    current_dir = parent
+                }
+                _ => break,
             }
         }
 
@@ -282,13 +469,15 @@ impl FixtureDatabase {
             }
         }
 
-        // Priority 4: Third-party fixtures (site-packages)
+        // Priority 4: Third-party fixtures (site-packages), from the
+        // environment of the file's subproject
         debug!(
             "No fixture {} found in plugins, checking third-party",
             fixture_name
         );
+        let hidden = self.hidden_site_packages(file_path);
         for def in definitions.iter() {
-            if def.is_third_party && filter(def) {
+            if def.is_third_party && !in_site_packages(def, &hidden) && filter(def) {
                 info!(
                     "Found third-party fixture {} in site-packages: {:?}",
                     fixture_name, def.file_path
@@ -319,7 +508,7 @@ impl FixtureDatabase {
         );
 
         let content = self.get_file_content(file_path)?;
-        let line_content = content.lines().nth(target_line.saturating_sub(1))?;
+        let line_content = self.get_line_text(file_path, &content, target_line)?;
         debug!("Line content: {}", line_content);
 
         let word_at_cursor = self.extract_word_at_position(line_content, character as usize);
@@ -432,13 +621,14 @@ impl FixtureDatabase {
                     self.find_closest_definition_excluding(
                         file_path,
                         &usage.name,
+                        usage.line,
                         Some(current_def),
                     )
                 } else {
-                    self.find_closest_definition(file_path, &usage.name)
+                    self.find_closest_definition(file_path, &usage.name, usage.line)
                 }
             } else {
-                self.find_closest_definition(file_path, &usage.name)
+                self.find_closest_definition(file_path, &usage.name, usage.line)
             };
 
             if let Some(resolved_def) = resolved_def {
@@ -508,6 +698,55 @@ impl FixtureDatabase {
         available_fixtures
     }
 
+    /// Get all available fixtures at `line` (1-based) of a file: the file's
+    /// available fixtures plus those of the test classes enclosing the line,
+    /// which take precedence over same-named module-level fixtures.
+    pub fn get_available_fixtures_at(
+        &self,
+        file_path: &Path,
+        line: usize,
+    ) -> std::sync::Arc<Vec<FixtureDefinition>> {
+        let available = self.get_available_fixtures(file_path);
+        let file_path = self.get_canonical_path(file_path.to_path_buf());
+        let class_ranks = self.visible_class_ranks(&file_path, line);
+        if class_ranks.is_empty() {
+            return available;
+        }
+
+        let mut best: HashMap<String, (usize, FixtureDefinition)> = HashMap::new();
+        if let Some(names) = self.file_definitions.get(&file_path) {
+            for name in names.iter() {
+                let Some(definitions) = self.definitions.get(name) else {
+                    continue;
+                };
+                for def in definitions.iter().filter(|def| def.file_path == file_path) {
+                    let Some(rank) = def.class_name.as_ref().and_then(|c| class_ranks.get(c))
+                    else {
+                        continue;
+                    };
+                    let better = best
+                        .get(name)
+                        .is_none_or(|(r, d)| (*rank, def.line) > (*r, d.line));
+                    if better {
+                        best.insert(name.clone(), (*rank, def.clone()));
+                    }
+                }
+            }
+        }
+        if best.is_empty() {
+            return available;
+        }
+
+        let mut fixtures: Vec<FixtureDefinition> = available
+            .iter()
+            .filter(|def| !best.contains_key(&def.name))
+            .cloned()
+            .collect();
+        fixtures.extend(best.into_values().map(|(_, def)| def));
+        fixtures.sort_by(|a, b| a.name.cmp(&b.name));
+        std::sync::Arc::new(fixtures)
+    }
+
     /// Internal method to compute available fixtures without caching.
     ///
     /// Single pass over all definitions: each definition gets a rank encoding
@@ -520,12 +759,15 @@ impl FixtureDatabase {
 
         // Rank ancestor conftests by proximity. Ranks are doubled so that
         // fixtures *imported into* a conftest slot in just after the ones
-        // defined directly in it (rank * 2 + 1).
+        // defined directly in it (rank * 2 + 1). Conftests above pytest's
+        // confcutdir are not loaded.
+        let confcutdir = self.confcutdir_for(file_path);
         let mut conftest_rank: HashMap<PathBuf, usize> = HashMap::new();
         let mut ancestor_conftests: Vec<PathBuf> = Vec::new();
         let mut depth = 1usize;
         let mut dir = file_path.parent();
-        while let Some(d) = dir {
+        while let Some(d) = dir.filter(|d| confcutdir.as_ref().is_none_or(|cut| d.starts_with(cut)))
+        {
             let conftest_path = d.join("conftest.py");
             conftest_rank.insert(conftest_path.clone(), depth * 2);
             ancestor_conftests.push(conftest_path);
@@ -534,6 +776,7 @@ impl FixtureDatabase {
         }
         let plugin_rank = depth * 2;
         let third_party_rank = depth * 2 + 2;
+        let hidden = self.hidden_site_packages(file_path);
 
         let mut best: HashMap<String, (usize, FixtureDefinition)> = HashMap::new();
         let mut consider =
@@ -551,11 +794,18 @@ impl FixtureDatabase {
         for entry in self.definitions.iter() {
             let fixture_name = entry.key();
             for def in entry.value().iter() {
-                let rank = if def.file_path == file_path {
+                let rank = if def.class_name.is_some() {
+                    // Only visible from inside its class (see get_available_fixtures_at)
+                    continue;
+                } else if def.file_path == file_path {
                     0
                 } else if let Some(rank) = conftest_rank.get(&def.file_path) {
                     *rank
                 } else if def.is_third_party {
+                    if in_site_packages(def, &hidden) {
+                        // Installed in another subproject's environment
+                        continue;
+                    }
                     third_party_rank
                 } else if def.is_plugin {
                     plugin_rank
@@ -604,13 +854,23 @@ impl FixtureDatabase {
         let content = self.get_file_content(file_path)?;
         let target_line = (line + 1) as usize;
 
+        // getfixturevalue and lazy_fixture strings are recognized from the line
+        // text alone so they work while the call is still unterminated
+        if let Some(line_text) = self.get_line_text(file_path, &content, target_line) {
+            match Self::fixture_name_string_call(line_text, character as usize) {
+                Some("getfixturevalue") => return Some(CompletionContext::GetfixturevalueArgument),
+                Some(_) => return Some(CompletionContext::LazyFixtureArgument),
+                None => {}
+            }
+        }
+
         // Try AST-based analysis first
         let parsed = self.get_parsed_ast(file_path, &content);
 
         if let Some(parsed) = parsed {
             let line_index = self.get_line_index(file_path, &content);
 
-            if let rustpython_parser::ast::Mod::Module(module) = parsed.as_ref() {
+            if let ruff_python_ast::Mod::Module(module) = parsed.as_ref() {
                 // First check if we're inside a decorator
                 if let Some(ctx) =
                     self.check_decorator_context(&module.body, &content, target_line, &line_index)
@@ -635,6 +895,34 @@ impl FixtureDatabase {
         self.get_completion_context_from_text(&content, target_line)
     }
 
+    /// If `byte_col` sits inside the first string argument of a call taking a
+    /// fixture name on `line_text` (e.g. `request.getfixturevalue("db|` or
+    /// `lazy_fixture("db|`), return the called function's name.
+    fn fixture_name_string_call(line_text: &str, byte_col: usize) -> Option<&'static str> {
+        let before = line_text.get(..byte_col.min(line_text.len()))?;
+        let (call_pos, callee) = decorators::FIXTURE_NAME_CALLS
+            .iter()
+            .filter_map(|callee| {
+                let pattern = format!("{}(", callee);
+                before
+                    .match_indices(&pattern)
+                    .filter(|(pos, _)| {
+                        // `lf(` must not match the tail of e.g. `self(`
+                        !before[..*pos].ends_with(|c: char| c.is_alphanumeric() || c == '_')
+                    })
+                    .last()
+                    .map(|(pos, _)| (pos, *callee))
+            })
+            .max_by_key(|(pos, _)| *pos)?;
+        let args = before[call_pos + callee.len() + 1..].trim_start();
+        let quote = args.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+        // Everything typed after the opening quote must still be part of the name
+        args[quote.len_utf8()..]
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_')
+            .then_some(callee)
+    }
+
     /// Check whether a `@pytest.fixture` decorator appears in the lines immediately
     /// above `def_line_idx` (0-based index into `lines`).
     ///
@@ -656,8 +944,17 @@ impl FixtureDatabase {
                 continue;
             }
             if trimmed.starts_with('@') {
-                // Check for @pytest.fixture or @fixture (with optional parens/args)
-                if trimmed.contains("pytest.fixture") || trimmed.starts_with("@fixture") {
+                // Check for @pytest.fixture (or the pytest-asyncio / pytest-cases
+                // equivalents) or @fixture, with optional parens/args
+                if [
+                    "pytest.fixture",
+                    "pytest_asyncio.fixture",
+                    "pytest_cases.fixture",
+                ]
+                .iter()
+                .any(|decorator| trimmed.contains(decorator))
+                    || trimmed.starts_with("@fixture")
+                {
                     return true;
                 }
                 // Another decorator — keep scanning upward
@@ -1013,13 +1310,13 @@ impl FixtureDatabase {
             // Check decorators on functions and classes
             let decorator_list = match stmt {
                 Stmt::FunctionDef(f) => Some(f.decorator_list.as_slice()),
-                Stmt::AsyncFunctionDef(f) => Some(f.decorator_list.as_slice()),
                 Stmt::ClassDef(c) => Some(c.decorator_list.as_slice()),
                 _ => None,
             };
 
             if let Some(decorator_list) = decorator_list {
                 for decorator in decorator_list {
+                    let decorator = &decorator.expression;
                     let dec_start_line =
                         self.get_line_from_offset(decorator.range().start().to_usize(), line_index);
                     let dec_end_line =
@@ -1135,26 +1432,13 @@ impl FixtureDatabase {
                     if let Some(ctx) = self.get_func_context(
                         &func_def.name,
                         &func_def.decorator_list,
-                        &func_def.args,
-                        &func_def.returns,
-                        &func_def.body,
-                        func_def.range,
-                        content,
-                        target_line,
-                        target_char,
-                        line_index,
-                    ) {
-                        return Some(ctx);
-                    }
-                }
-                Stmt::AsyncFunctionDef(func_def) => {
-                    if let Some(ctx) = self.get_func_context(
-                        &func_def.name,
-                        &func_def.decorator_list,
-                        &func_def.args,
+                        &func_def.parameters,
                         &func_def.returns,
                         &func_def.body,
-                        func_def.range,
+                        TextRange::new(
+                            super::definition_start(stmt, content),
+                            func_def.range.end(),
+                        ),
                         content,
                         target_line,
                         target_char,
@@ -1189,7 +1473,7 @@ impl FixtureDatabase {
     fn find_signature_end_line(
         &self,
         func_start_line: usize,
-        args: &rustpython_parser::ast::Arguments,
+        args: &Parameters,
         returns: &Option<Box<Expr>>,
         body: &[Stmt],
         content: &str,
@@ -1209,7 +1493,7 @@ impl FixtureDatabase {
             .iter()
             .chain(args.posonlyargs.iter())
             .chain(args.kwonlyargs.iter())
-            .map(|a| a.def.range.end().to_usize())
+            .map(|a| a.parameter.range.end().to_usize())
             .chain(args.vararg.as_ref().map(|a| a.range.end().to_usize()))
             .chain(args.kwarg.as_ref().map(|a| a.range.end().to_usize()));
 
@@ -1261,12 +1545,12 @@ impl FixtureDatabase {
     #[allow(clippy::too_many_arguments)]
     fn get_func_context(
         &self,
-        func_name: &rustpython_parser::ast::Identifier,
-        decorator_list: &[Expr],
-        args: &rustpython_parser::ast::Arguments,
+        func_name: &ruff_python_ast::Identifier,
+        decorator_list: &[Decorator],
+        args: &Parameters,
         returns: &Option<Box<Expr>>,
         body: &[Stmt],
-        range: rustpython_parser::text_size::TextRange,
+        range: TextRange,
         content: &str,
         target_line: usize,
         _target_char: usize,
@@ -1279,8 +1563,16 @@ impl FixtureDatabase {
             return None;
         }
 
-        let is_fixture = decorator_list.iter().any(decorators::is_fixture_decorator);
-        let is_test = func_name.as_str().starts_with("test_");
+        let decorator_list: Vec<&Expr> = decorator_list.iter().map(|d| &d.expression).collect();
+        let aliases = self.fixture_decorator_aliases();
+        let is_fixture = decorator_list
+            .iter()
+            .any(|d| decorators::is_fixture_decorator(d, &aliases));
+        // pytest-bdd step functions request fixtures the way tests do
+        let is_test = func_name.as_str().starts_with("test_")
+            || decorator_list
+                .iter()
+                .any(|d| super::bdd::step_decorator(d).is_some());
 
         if !is_test && !is_fixture {
             return None;
@@ -1290,7 +1582,7 @@ impl FixtureDatabase {
         let fixture_scope = if is_fixture {
             let scope = decorator_list
                 .iter()
-                .find_map(decorators::extract_fixture_scope)
+                .find_map(|d| decorators::extract_fixture_scope(d, &aliases))
                 .unwrap_or(super::types::FixtureScope::Function);
             Some(scope)
         } else {
@@ -1299,7 +1591,7 @@ impl FixtureDatabase {
 
         // Collect all parameters
         let params: Vec<String> = FixtureDatabase::all_args(args)
-            .map(|arg| arg.def.arg.to_string())
+            .map(|arg| arg.parameter.name.to_string())
             .collect();
 
         // Find the line where the function signature ends using AST information
@@ -1352,9 +1644,9 @@ impl FixtureDatabase {
         // Preferred: the AST gives accurate `needs_comma` (from the arg list)
         // and lets us scan from the exact `def` byte offset.
         if let Some(ast) = self.get_parsed_ast(file_path, &content) {
-            if let rustpython_parser::ast::Mod::Module(module) = ast.as_ref() {
+            if let ruff_python_ast::Mod::Module(module) = ast.as_ref() {
                 if let Some(info) =
-                    find_insertion_in_stmts(&module.body, function_line, bytes, &line_index)
+                    find_insertion_in_stmts(&module.body, function_line, &content, &line_index)
                 {
                     return Some(info);
                 }
@@ -1433,7 +1725,7 @@ impl FixtureDatabase {
         // Parse the file (using cached AST)
         let parsed = self.get_parsed_ast(file_path, &content)?;
 
-        if let rustpython_parser::ast::Mod::Module(module) = parsed.as_ref() {
+        if let ruff_python_ast::Mod::Module(module) = parsed.as_ref() {
             return self.find_enclosing_function(
                 &module.body,
                 &content,
@@ -1456,64 +1748,252 @@ impl FixtureDatabase {
         let line_index = Self::build_line_index(content);
 
         for stmt in stmts {
-            match stmt {
-                Stmt::FunctionDef(func_def) => {
-                    let func_start_line =
-                        self.get_line_from_offset(func_def.range.start().to_usize(), &line_index);
-                    let func_end_line =
-                        self.get_line_from_offset(func_def.range.end().to_usize(), &line_index);
-
-                    // Check if target is within this function's range
-                    if target_line >= func_start_line && target_line <= func_end_line {
-                        let is_fixture = func_def
-                            .decorator_list
+            if let Stmt::FunctionDef(func_def) = stmt {
+                let func_start_line = self.get_line_from_offset(
+                    super::definition_start(stmt, content).to_usize(),
+                    &line_index,
+                );
+                let func_end_line =
+                    self.get_line_from_offset(func_def.range.end().to_usize(), &line_index);
+
+                // Check if target is within this function's range
+                if target_line >= func_start_line && target_line <= func_end_line {
+                    let aliases = self.fixture_decorator_aliases();
+                    let is_fixture = func_def
+                        .decorator_list
+                        .iter()
+                        .any(|d| decorators::is_fixture_decorator(&d.expression, &aliases));
+                    let is_test = func_def.name.starts_with("test_");
+
+                    // Only return if it's a test or fixture
+                    if is_test || is_fixture {
+                        let params: Vec<String> = func_def
+                            .parameters
+                            .args
                             .iter()
-                            .any(decorators::is_fixture_decorator);
-                        let is_test = func_def.name.starts_with("test_");
-
-                        // Only return if it's a test or fixture
-                        if is_test || is_fixture {
-                            let params: Vec<String> = func_def
-                                .args
-                                .args
-                                .iter()
-                                .map(|arg| arg.def.arg.to_string())
-                                .collect();
-
-                            return Some((func_def.name.to_string(), is_fixture, params));
-                        }
+                            .map(|arg| arg.parameter.name.to_string())
+                            .collect();
+
+                        return Some((func_def.name.to_string(), is_fixture, params));
                     }
                 }
-                Stmt::AsyncFunctionDef(func_def) => {
-                    let func_start_line =
-                        self.get_line_from_offset(func_def.range.start().to_usize(), &line_index);
-                    let func_end_line =
-                        self.get_line_from_offset(func_def.range.end().to_usize(), &line_index);
+            }
+        }
 
-                    if target_line >= func_start_line && target_line <= func_end_line {
-                        let is_fixture = func_def
-                            .decorator_list
-                            .iter()
-                            .any(decorators::is_fixture_decorator);
-                        let is_test = func_def.name.starts_with("test_");
-
-                        if is_test || is_fixture {
-                            let params: Vec<String> = func_def
-                                .args
-                                .args
-                                .iter()
-                                .map(|arg| arg.def.arg.to_string())
-                                .collect();
-
-                            return Some((func_def.name.to_string(), is_fixture, params));
-                        }
+        None
+    }
+
+    // ============ Fixture Overrides ============
+
+    /// Find the definition that `definition` overrides: the one a request for
+    /// the same name would resolve to if `definition` did not exist.
+    ///
+    /// Definitions later in the same file are not considered, since an earlier
+    /// definition cannot override them.
+    pub fn find_overridden_definition(
+        &self,
+        definition: &FixtureDefinition,
+    ) -> Option<FixtureDefinition> {
+        self.find_closest_definition_with_filter(
+            &definition.file_path,
+            &definition.name,
+            definition.line,
+            |def| {
+                def != definition
+                    && !(def.file_path == definition.file_path && def.line >= definition.line)
+            },
+        )
+    }
+
+    /// Find all definitions that override `definition`, directly or through
+    /// intermediate overrides, sorted by path and line.
+    pub fn find_overriding_definitions(
+        &self,
+        definition: &FixtureDefinition,
+    ) -> Vec<FixtureDefinition> {
+        let candidates: Vec<FixtureDefinition> = match self.definitions.get(&definition.name) {
+            Some(defs) => defs.iter().filter(|d| *d != definition).cloned().collect(),
+            None => return Vec::new(),
+        };
+
+        let mut overriding: Vec<FixtureDefinition> = candidates
+            .iter()
+            .filter(|candidate| {
+                // Walk up the override chain; bounded by the number of definitions
+                let mut current = self.find_overridden_definition(candidate);
+                for _ in 0..=candidates.len() {
+                    match current {
+                        Some(ref parent) if parent == definition => return true,
+                        Some(ref parent) => current = self.find_overridden_definition(parent),
+                        None => return false,
                     }
                 }
-                _ => {}
+                false
+            })
+            .cloned()
+            .collect();
+
+        overriding.sort_by(|a, b| a.file_path.cmp(&b.file_path).then(a.line.cmp(&b.line)));
+        overriding
+    }
+
+    // ============ Resolution Explanation ============
+
+    /// Explain how the fixture usage at `line` (0-indexed) / `character`
+    /// (byte column) resolves: every definition with that name, where it
+    /// stands in pytest's lookup order from this file, and which one wins.
+    /// Returns `None` when the position is not on a fixture usage.
+    pub fn explain_fixture_resolution(
+        &self,
+        file_path: &Path,
+        line: u32,
+        character: u32,
+    ) -> Option<ResolutionExplanation> {
+        let target_line = (line + 1) as usize;
+        let character = character as usize;
+        let usage = self.usages.get(file_path).and_then(|usages| {
+            usages
+                .iter()
+                .find(|u| {
+                    u.line == target_line && u.start_char <= character && character < u.end_char
+                })
+                .cloned()
+        })?;
+
+        let requester = self
+            .get_fixture_definition_at_line(file_path, target_line)
+            .filter(|def| def.name == usage.name);
+        let winner = self.find_closest_definition_excluding(
+            file_path,
+            &usage.name,
+            target_line,
+            requester.as_ref(),
+        );
+
+        let definitions: Vec<FixtureDefinition> = self
+            .definitions
+            .get(&usage.name)
+            .map(|defs| defs.value().clone())
+            .unwrap_or_default();
+        let imported_by = self.conftest_imports(file_path, &usage.name);
+
+        let mut candidates: Vec<ResolutionCandidate> = definitions
+            .into_iter()
+            .filter(|def| requester.as_ref() != Some(def))
+            .map(|def| {
+                let source = self.candidate_source(file_path, &def, &imported_by);
+                ResolutionCandidate {
+                    is_winner: winner.as_ref() == Some(&def),
+                    definition: def,
+                    source,
+                }
+            })
+            .collect();
+        candidates.sort_by_key(|c| {
+            let rank = match &c.source {
+                // The last definition in the file wins, so list it first
+                CandidateSource::SameFile => (0, usize::MAX - c.definition.line),
+                CandidateSource::Conftest { distance }
+                | CandidateSource::ImportedByConftest { distance, .. } => (1, *distance),
+                CandidateSource::Plugin => (2, 0),
+                CandidateSource::ThirdParty => (3, 0),
+                CandidateSource::NotVisible => (4, 0),
+            };
+            (
+                !c.is_winner,
+                rank,
+                c.definition.file_path.clone(),
+                c.definition.line,
+            )
+        });
+
+        Some(ResolutionExplanation {
+            fixture_name: usage.name,
+            file_path: file_path.to_path_buf(),
+            line: target_line,
+            requester,
+            candidates,
+        })
+    }
+
+    /// Where `def` stands in pytest's lookup order when requested from `file_path`.
+    pub fn definition_source(&self, file_path: &Path, def: &FixtureDefinition) -> CandidateSource {
+        // Conftest imports are only worth looking up when the definition
+        // isn't visible directly
+        match self.candidate_source(file_path, def, &Default::default()) {
+            source @ (CandidateSource::SameFile | CandidateSource::Conftest { .. }) => source,
+            _ => {
+                let imported_by = self.conftest_imports(file_path, &def.name);
+                self.candidate_source(file_path, def, &imported_by)
             }
         }
+    }
 
-        None
+    /// For each conftest.py above `file_path` that imports `fixture_name`, map
+    /// the imported definition's file to that conftest and its distance.
+    /// The closest conftest wins when several import the same file.
+    fn conftest_imports(
+        &self,
+        file_path: &Path,
+        fixture_name: &str,
+    ) -> std::collections::HashMap<PathBuf, (PathBuf, usize)> {
+        let mut imports = std::collections::HashMap::new();
+        let Some(file_dir) = file_path.parent() else {
+            return imports;
+        };
+        for (distance, dir) in file_dir.ancestors().enumerate() {
+            let conftest = dir.join("conftest.py");
+            if !conftest.exists() && !self.file_cache.contains_key(&conftest) {
+                continue;
+            }
+            let mut visited = HashSet::new();
+            if let Some(source) = self
+                .get_imported_fixtures(&conftest, &mut visited)
+                .remove(fixture_name)
+            {
+                imports.entry(source).or_insert((conftest, distance));
+            }
+        }
+        imports
+    }
+
+    fn candidate_source(
+        &self,
+        file_path: &Path,
+        def: &FixtureDefinition,
+        imported_by: &std::collections::HashMap<PathBuf, (PathBuf, usize)>,
+    ) -> CandidateSource {
+        if def.file_path == file_path {
+            return CandidateSource::SameFile;
+        }
+        if def
+            .file_path
+            .file_name()
+            .is_some_and(|n| n == "conftest.py")
+        {
+            if let (Some(conftest_dir), Some(file_dir)) =
+                (def.file_path.parent(), file_path.parent())
+            {
+                if let Ok(relative) = file_dir.strip_prefix(conftest_dir) {
+                    return CandidateSource::Conftest {
+                        distance: relative.components().count(),
+                    };
+                }
+            }
+        }
+        if let Some((conftest, distance)) = imported_by.get(&def.file_path) {
+            return CandidateSource::ImportedByConftest {
+                conftest: conftest.clone(),
+                distance: *distance,
+            };
+        }
+        if def.is_plugin && !def.is_third_party {
+            return CandidateSource::Plugin;
+        }
+        if def.is_third_party && !in_site_packages(def, &self.hidden_site_packages(file_path)) {
+            return CandidateSource::ThirdParty;
+        }
+        CandidateSource::NotVisible
     }
 
     // ============ Cycle Detection ============
@@ -1604,9 +2084,14 @@ impl FixtureDatabase {
                         if !seen_cycles.contains(&cycle_key_str) {
                             seen_cycles.insert(cycle_key_str);
                             if let Some(fixture_def) = fixture_defs.get(&current) {
+                                let definitions = cycle_path[..cycle_path.len() - 1]
+                                    .iter()
+                                    .filter_map(|name| fixture_defs.get(name).cloned())
+                                    .collect();
                                 cycles.push(FixtureCycle {
                                     cycle_path,
                                     fixture: fixture_def.clone(),
+                                    definitions,
                                 });
                             }
                         }
@@ -1645,9 +2130,14 @@ impl FixtureDatabase {
                         if !seen_cycles.contains(&cycle_key_str) {
                             seen_cycles.insert(cycle_key_str);
                             if let Some(fixture_def) = fixture_defs.get(dep) {
+                                let definitions = cycle_path[..cycle_path.len() - 1]
+                                    .iter()
+                                    .filter_map(|name| fixture_defs.get(name).cloned())
+                                    .collect();
                                 cycles.push(FixtureCycle {
                                     cycle_path,
                                     fixture: fixture_def.clone(),
+                                    definitions,
                                 });
                             }
                         }
@@ -1667,18 +2157,33 @@ impl FixtureDatabase {
     }
 
     /// Detect cycles for fixtures in a specific file.
-    /// Returns cycles where the first fixture in the cycle is defined in the given file.
+    /// Returns a cycle for each fixture of the file taking part in one, rotated
+    /// to start (and end) at that fixture.
     /// Uses cached cycle detection results for efficiency.
     pub fn detect_fixture_cycles_in_file(
         &self,
         file_path: &Path,
     ) -> Vec<super::types::FixtureCycle> {
         let all_cycles = self.detect_fixture_cycles();
-        all_cycles
-            .iter()
-            .filter(|cycle| cycle.fixture.file_path == file_path)
-            .cloned()
-            .collect()
+        let mut cycles = Vec::new();
+        for cycle in all_cycles.iter() {
+            for (start, def) in cycle.definitions.iter().enumerate() {
+                if def.file_path != file_path {
+                    continue;
+                }
+                let mut definitions = cycle.definitions.clone();
+                definitions.rotate_left(start);
+                let mut cycle_path: Vec<String> =
+                    definitions.iter().map(|d| d.name.clone()).collect();
+                cycle_path.push(def.name.clone());
+                cycles.push(super::types::FixtureCycle {
+                    cycle_path,
+                    fixture: def.clone(),
+                    definitions,
+                });
+            }
+        }
+        cycles
     }
 
     // ============ Scope Validation ============
@@ -1733,51 +2238,1248 @@ impl FixtureDatabase {
         mismatches
     }
 
-    /// Resolve a fixture by name for a given file using priority rules.
+    /// Compute the edit that sets `scope="<scope>"` on the decorator of `fixture`.
     ///
-    /// Returns the best matching FixtureDefinition based on pytest's
-    /// fixture shadowing rules: same file > conftest hierarchy > third-party.
-    pub fn resolve_fixture_for_file(
+    /// Locates the decorated function whose `def` is on `fixture.line` in the
+    /// fixture's (cached) AST. Returns `None` for assignment-style fixtures or
+    /// when the file cannot be parsed.
+    pub fn get_fixture_scope_edit(
         &self,
-        file_path: &Path,
-        fixture_name: &str,
-    ) -> Option<FixtureDefinition> {
-        let definitions = self.definitions.get(fixture_name)?;
-
-        // Priority 1: Same file
-        if let Some(def) = definitions.iter().find(|d| d.file_path == file_path) {
-            return Some(def.clone());
-        }
-
-        // Priority 2: conftest.py in parent directories (closest first)
-        let file_path = self.get_canonical_path(file_path.to_path_buf());
-        let mut best_conftest: Option<&FixtureDefinition> = None;
-        let mut best_depth = usize::MAX;
-
-        for def in definitions.iter() {
-            if def.is_third_party {
-                continue;
+        fixture: &FixtureDefinition,
+        scope: FixtureScope,
+    ) -> Option<ScopeEdit> {
+        let content = self.get_file_content(&fixture.file_path)?;
+        let parsed = self.get_parsed_ast(&fixture.file_path, &content)?;
+        let line_index = self.get_line_index(&fixture.file_path, &content);
+
+        let ruff_python_ast::Mod::Module(module) = parsed.as_ref() else {
+            return None;
+        };
+        let aliases = self.fixture_decorator_aliases();
+        let decorator =
+            match self.find_function_at_line(&module.body, fixture.line, &content, &line_index)? {
+                Stmt::FunctionDef(f) => &f.decorator_list,
+                _ => return None,
             }
-            if def.file_path.ends_with("conftest.py") {
-                if let Some(parent) = def.file_path.parent() {
-                    if file_path.starts_with(parent) {
-                        let depth = parent.components().count();
-                        if depth > best_depth {
-                            // Deeper = closer conftest
-                            best_conftest = Some(def);
-                            best_depth = depth;
-                        } else if best_conftest.is_none() {
-                            best_conftest = Some(def);
-                            best_depth = depth;
-                        }
+            .iter()
+            .map(|dec| &dec.expression)
+            .find(|dec| decorators::is_fixture_decorator(dec, &aliases))?;
+        let (range, new_text) = decorators::fixture_scope_edit(decorator, scope, &aliases)?;
+
+        let start = range.start().to_usize();
+        let end = range.end().to_usize();
+        Some(ScopeEdit {
+            start_line: self.get_line_from_offset(start, &line_index),
+            start_char: self.get_char_position_from_offset(start, &line_index),
+            end_line: self.get_line_from_offset(end, &line_index),
+            end_char: self.get_char_position_from_offset(end, &line_index),
+            new_text,
+        })
+    }
+
+    /// Recursively find the function (or async function) whose `def` is on `def_line`.
+    fn find_function_at_line<'a>(
+        &self,
+        stmts: &'a [Stmt],
+        def_line: usize,
+        content: &str,
+        line_index: &[usize],
+    ) -> Option<&'a Stmt> {
+        for stmt in stmts {
+            match stmt {
+                Stmt::FunctionDef(_) => {}
+                Stmt::ClassDef(c) => {
+                    if let Some(found) =
+                        self.find_function_at_line(&c.body, def_line, content, line_index)
+                    {
+                        return Some(found);
                     }
+                    continue;
                 }
+                _ => continue,
+            }
+            let def_start = super::definition_start(stmt, content);
+            if self.get_line_from_offset(def_start.to_usize(), line_index) == def_line {
+                return Some(stmt);
             }
         }
+        None
+    }
 
-        if let Some(def) = best_conftest {
-            return Some(def.clone());
-        }
+    /// Source of a fixture from its first decorator through at most `max_lines`
+    /// lines, dedented, with the docstring left out (hover renders it separately).
+    /// A truncated body ends with an indented `...` line.
+    ///
+    /// Returns `None` for assignment-style fixtures or when the file is not cached.
+    pub fn get_fixture_source_snippet(
+        &self,
+        fixture: &FixtureDefinition,
+        max_lines: usize,
+    ) -> Option<String> {
+        let content = self.get_file_content(&fixture.file_path)?;
+        let parsed = self.get_parsed_ast(&fixture.file_path, &content)?;
+        let line_index = self.get_line_index(&fixture.file_path, &content);
+        let ruff_python_ast::Mod::Module(module) = parsed.as_ref() else {
+            return None;
+        };
+
+        let (range, body) =
+            match self.find_function_at_line(&module.body, fixture.line, &content, &line_index)? {
+                Stmt::FunctionDef(f) => (f.range, &f.body),
+                _ => return None,
+            };
+
+        // The statement's range starts at its first decorator
+        let start_line = self.get_line_from_offset(range.start().to_usize(), &line_index);
+        let end_line = self.get_line_from_offset(range.end().to_usize(), &line_index);
+
+        let docstring_lines = match body.first() {
+            Some(Stmt::Expr(expr)) if matches!(expr.value.as_ref(), Expr::StringLiteral(_)) => {
+                Some(
+                    self.get_line_from_offset(expr.range.start().to_usize(), &line_index)
+                        ..=self.get_line_from_offset(expr.range.end().to_usize(), &line_index),
+                )
+            }
+            _ => None,
+        };
+
+        let mut lines: Vec<&str> = content
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line))
+            .skip(start_line - 1)
+            .take(end_line + 1 - start_line)
+            .filter(|(n, _)| !docstring_lines.as_ref().is_some_and(|r| r.contains(n)))
+            .map(|(_, line)| line)
+            .collect();
+        while lines.last().is_some_and(|l| l.trim().is_empty()) {
+            lines.pop();
+        }
+
+        let indent = lines
+            .iter()
+            .filter(|l| !l.trim().is_empty())
+            .map(|l| l.len() - l.trim_start().len())
+            .min()
+            .unwrap_or(0);
+        let truncated = lines.len() > max_lines;
+        lines.truncate(max_lines);
+
+        let mut snippet: Vec<String> = lines
+            .iter()
+            .map(|l| l.get(indent..).unwrap_or("").to_string())
+            .collect();
+        if truncated {
+            let body_indent = body
+                .first()
+                .map(|stmt| {
+                    self.get_char_position_from_offset(stmt.range().start().to_usize(), &line_index)
+                })
+                .unwrap_or(indent + 4);
+            snippet.push(format!(
+                "{}...",
+                " ".repeat(body_indent.saturating_sub(indent))
+            ));
+        }
+        Some(snippet.join("\n"))
+    }
+
+    // ============ Parametrize Validation ============
+
+    /// Detect `indirect=` names in `@pytest.mark.parametrize` decorators that pytest
+    /// would reject at collection time: names listed in `indirect=[...]` that are not
+    /// argnames of the decorator, and indirect names with no visible fixture.
+    pub fn detect_invalid_indirect_in_file(&self, file_path: &Path) -> Vec<InvalidIndirect> {
+        let Some(content) = self.get_file_content(file_path) else {
+            return Vec::new();
+        };
+        let Some(parsed) = self.get_parsed_ast(file_path, &content) else {
+            return Vec::new();
+        };
+        let line_index = self.get_line_index(file_path, &content);
+
+        let mut invalid = Vec::new();
+        if let ruff_python_ast::Mod::Module(module) = parsed.as_ref() {
+            self.collect_invalid_indirect(
+                &module.body,
+                file_path,
+                &content,
+                &line_index,
+                &mut invalid,
+            );
+        }
+        invalid
+    }
+
+    /// Walk function and class decorators (recursing into class bodies) and collect
+    /// invalid `indirect=` entries.
+    fn collect_invalid_indirect(
+        &self,
+        stmts: &[Stmt],
+        file_path: &Path,
+        content: &str,
+        line_index: &[usize],
+        invalid: &mut Vec<InvalidIndirect>,
+    ) {
+        for stmt in stmts {
+            let decorator_list = match stmt {
+                Stmt::FunctionDef(f) => f.decorator_list.as_slice(),
+                Stmt::ClassDef(c) => c.decorator_list.as_slice(),
+                _ => continue,
+            };
+
+            for decorator in decorator_list {
+                let decorator = &decorator.expression;
+                let argnames = decorators::extract_parametrize_argnames(decorator, content);
+                if argnames.is_empty() {
+                    continue;
+                }
+                let names: Vec<String> = argnames.iter().map(|(name, _)| name.clone()).collect();
+
+                // `indirect=[...]` lists its own names; `indirect=True` marks every argname.
+                let listed = decorators::extract_parametrize_indirect_list(decorator, content);
+                let candidates = if listed.is_empty() {
+                    let indirect =
+                        decorators::extract_parametrize_indirect_names(decorator, &names);
+                    argnames
+                        .into_iter()
+                        .filter(|(name, _)| indirect.contains(name))
+                        .collect()
+                } else {
+                    listed
+                };
+
+                for (name, range) in candidates {
+                    let issue = if !names.contains(&name) {
+                        IndirectIssue::NotAnArgname
+                    } else if self
+                        .find_closest_definition(
+                            file_path,
+                            &name,
+                            self.get_line_from_offset(range.start().to_usize(), line_index),
+                        )
+                        .is_none()
+                    {
+                        IndirectIssue::UnknownFixture
+                    } else {
+                        continue;
+                    };
+
+                    debug!("Invalid indirect parametrize name '{}': {:?}", name, issue);
+                    invalid.push(InvalidIndirect {
+                        name,
+                        file_path: file_path.to_path_buf(),
+                        line: self.get_line_from_offset(range.start().to_usize(), line_index),
+                        start_char: self
+                            .get_char_position_from_offset(range.start().to_usize(), line_index),
+                        end_char: self
+                            .get_char_position_from_offset(range.end().to_usize(), line_index),
+                        issue,
+                    });
+                }
+            }
+
+            if let Stmt::ClassDef(class_def) = stmt {
+                self.collect_invalid_indirect(
+                    &class_def.body,
+                    file_path,
+                    content,
+                    line_index,
+                    invalid,
+                );
+            }
+        }
+    }
+
+    // ============ unittest.TestCase Validation ============
+
+    /// Detect parameters of test methods in `unittest.TestCase` subclasses.
+    ///
+    /// pytest runs these through unittest, which calls test methods without
+    /// arguments: only autouse fixtures apply, and a parameter without a default
+    /// fails at run time with a `TypeError`.
+    pub fn detect_testcase_fixture_params_in_file(
+        &self,
+        file_path: &Path,
+    ) -> Vec<TestCaseFixtureParam> {
+        let Some(content) = self.get_file_content(file_path) else {
+            return Vec::new();
+        };
+        let Some(parsed) = self.get_parsed_ast(file_path, &content) else {
+            return Vec::new();
+        };
+        let ruff_python_ast::Mod::Module(module) = parsed.as_ref() else {
+            return Vec::new();
+        };
+        let line_index = self.get_line_index(file_path, &content);
+
+        let mut classes: Vec<&StmtClassDef> = Vec::new();
+        let mut stack: Vec<&[Stmt]> = vec![&module.body];
+        while let Some(stmts) = stack.pop() {
+            for stmt in stmts {
+                if let Stmt::ClassDef(class_def) = stmt {
+                    classes.push(class_def);
+                    stack.push(&class_def.body);
+                }
+            }
+        }
+
+        // Classes deriving from a TestCase, directly or through classes of this file
+        let base_name = |base: &Expr| match base {
+            Expr::Name(name) => Some(name.id.to_string()),
+            Expr::Attribute(attr) => Some(attr.attr.to_string()),
+            _ => None,
+        };
+        let local: HashSet<&str> = classes.iter().map(|c| c.name.as_str()).collect();
+        let mut testcases: HashSet<&str> = HashSet::new();
+        loop {
+            let found = testcases.len();
+            for class_def in &classes {
+                let is_testcase = class_def.bases().iter().filter_map(base_name).any(|base| {
+                    if local.contains(base.as_str()) {
+                        testcases.contains(base.as_str())
+                    } else {
+                        TESTCASE_BASES.contains(&base.as_str())
+                    }
+                });
+                if is_testcase {
+                    testcases.insert(class_def.name.as_str());
+                }
+            }
+            if testcases.len() == found {
+                break;
+            }
+        }
+
+        let mut params = Vec::new();
+        for class_def in classes
+            .iter()
+            .filter(|c| testcases.contains(c.name.as_str()))
+        {
+            for stmt in &class_def.body {
+                let Stmt::FunctionDef(f) = stmt else {
+                    continue;
+                };
+                let (name, args) = (f.name.as_str(), f.parameters.as_ref());
+                if !name.starts_with("test") {
+                    continue;
+                }
+                // Everything after `self` that unittest cannot fill in
+                for arg in Self::all_args(args)
+                    .skip(1)
+                    .filter(|arg| arg.default.is_none())
+                {
+                    let offset = arg.parameter.range.start().to_usize();
+                    let start_char = self.get_char_position_from_offset(offset, &line_index);
+                    params.push(TestCaseFixtureParam {
+                        name: arg.parameter.name.to_string(),
+                        class_name: class_def.name.to_string(),
+                        file_path: file_path.to_path_buf(),
+                        line: self.get_line_from_offset(offset, &line_index),
+                        start_char,
+                        end_char: start_char + arg.parameter.name.as_str().len(),
+                    });
+                }
+            }
+        }
+        params
+    }
+
+    // ============ yield_fixture Validation ============
+
+    /// Detect fixtures decorated with `@pytest.yield_fixture`, deprecated
+    /// since pytest 6.2 in favor of `@pytest.fixture`, which supports `yield`.
+    pub fn detect_deprecated_yield_fixtures_in_file(
+        &self,
+        file_path: &Path,
+    ) -> Vec<DeprecatedYieldFixture> {
+        let Some(content) = self.get_file_content(file_path) else {
+            return Vec::new();
+        };
+        let Some(parsed) = self.get_parsed_ast(file_path, &content) else {
+            return Vec::new();
+        };
+        let ruff_python_ast::Mod::Module(module) = parsed.as_ref() else {
+            return Vec::new();
+        };
+        let line_index = self.get_line_index(file_path, &content);
+
+        let mut deprecated = Vec::new();
+        let mut stack: Vec<&[Stmt]> = vec![&module.body];
+        while let Some(stmts) = stack.pop() {
+            for stmt in stmts {
+                let func = match stmt {
+                    Stmt::ClassDef(class_def) => {
+                        stack.push(&class_def.body);
+                        continue;
+                    }
+                    Stmt::FunctionDef(func) => func,
+                    _ => continue,
+                };
+                for decorator in &func.decorator_list {
+                    let Some((range, qualified)) =
+                        decorators::deprecated_yield_fixture(&decorator.expression)
+                    else {
+                        continue;
+                    };
+                    let offset = range.start().to_usize();
+                    let start_char = self.get_char_position_from_offset(offset, &line_index);
+                    deprecated.push(DeprecatedYieldFixture {
+                        name: func.name.to_string(),
+                        file_path: file_path.to_path_buf(),
+                        line: self.get_line_from_offset(offset, &line_index),
+                        start_char,
+                        end_char: start_char + "yield_fixture".len(),
+                        replacement: qualified.then(|| (offset, range.end().to_usize())),
+                    });
+                }
+            }
+        }
+        deprecated.sort_by_key(|d| (d.line, d.start_char));
+        deprecated
+    }
+
+    // ============ usefixtures Validation ============
+
+    /// Detect `@pytest.mark.usefixtures` entries of functions that have no
+    /// effect: fixtures the function also takes as parameters, or lists more
+    /// than once.
+    pub fn detect_redundant_usefixtures_in_file(
+        &self,
+        file_path: &Path,
+    ) -> Vec<RedundantUsefixture> {
+        let Some(content) = self.get_file_content(file_path) else {
+            return Vec::new();
+        };
+        let Some(parsed) = self.get_parsed_ast(file_path, &content) else {
+            return Vec::new();
+        };
+        let ruff_python_ast::Mod::Module(module) = parsed.as_ref() else {
+            return Vec::new();
+        };
+        let line_index = self.get_line_index(file_path, &content);
+
+        let mut redundant = Vec::new();
+        let mut stack: Vec<&[Stmt]> = vec![&module.body];
+        while let Some(stmts) = stack.pop() {
+            for stmt in stmts {
+                match stmt {
+                    Stmt::ClassDef(class_def) => stack.push(&class_def.body),
+                    Stmt::FunctionDef(func_def) => self.collect_redundant_usefixtures(
+                        func_def,
+                        file_path,
+                        &content,
+                        &line_index,
+                        &mut redundant,
+                    ),
+                    _ => {}
+                }
+            }
+        }
+        redundant.sort_by_key(|entry| (entry.line, entry.start_char));
+        redundant
+    }
+
+    fn collect_redundant_usefixtures(
+        &self,
+        func_def: &StmtFunctionDef,
+        file_path: &Path,
+        content: &str,
+        line_index: &[usize],
+        redundant: &mut Vec<RedundantUsefixture>,
+    ) {
+        let params: HashSet<&str> = Self::all_args(&func_def.parameters)
+            .map(|arg| arg.parameter.name.as_str())
+            .collect();
+        let mut listed: HashSet<&str> = HashSet::new();
+        for decorator in &func_def.decorator_list {
+            let Expr::Call(call) = &decorator.expression else {
+                continue;
+            };
+            if !decorators::is_usefixtures_decorator(&call.func) {
+                continue;
+            }
+            let args = &call.arguments.args;
+            for (i, arg) in args.iter().enumerate() {
+                let Expr::StringLiteral(literal) = arg else {
+                    continue;
+                };
+                let name = literal.value.to_str();
+                let reason = if params.contains(name) {
+                    RedundancyReason::Parameter
+                } else if !listed.insert(name) {
+                    RedundancyReason::Duplicate
+                } else {
+                    continue;
+                };
+
+                // Drop the entry with the separator before or after it
+                let removal = if args.len() == 1 && call.arguments.keywords.is_empty() {
+                    Self::decorator_line_range(decorator, content)
+                } else if let Some(next) = args.get(i + 1) {
+                    Some((arg.start().to_usize(), next.start().to_usize()))
+                } else if i > 0 {
+                    Some((args[i - 1].end().to_usize(), arg.end().to_usize()))
+                } else {
+                    None
+                };
+
+                let text = content
+                    .get(arg.start().to_usize()..arg.end().to_usize())
+                    .unwrap_or("");
+                let range = decorators::literal_content_range(text, arg.range());
+                let start = range.start().to_usize();
+                redundant.push(RedundantUsefixture {
+                    name: name.to_string(),
+                    file_path: file_path.to_path_buf(),
+                    line: self.get_line_from_offset(start, line_index),
+                    start_char: self.get_char_position_from_offset(start, line_index),
+                    end_char: self
+                        .get_char_position_from_offset(range.end().to_usize(), line_index),
+                    reason,
+                    removal,
+                });
+            }
+        }
+    }
+
+    /// The byte range of the lines of a decorator, newline included, when
+    /// nothing else shares them.
+    fn decorator_line_range(decorator: &Decorator, content: &str) -> Option<(usize, usize)> {
+        let start = decorator.start().to_usize();
+        let end = decorator.end().to_usize();
+        let line_start = content[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = content[end..]
+            .find('\n')
+            .map_or(content.len(), |i| end + i + 1);
+        let alone = content[line_start..start].trim().is_empty()
+            && content[end..line_end].trim().is_empty();
+        alone.then_some((line_start, line_end))
+    }
+
+    /// Resolve the fixture named by an `indirect=[...]` entry of a
+    /// `@pytest.mark.parametrize` decorator at the given LSP position
+    /// (0-based line, byte column).
+    ///
+    /// These entries are not recorded as usages (the argname literal is), so
+    /// position lookups that go through usages miss them.
+    pub fn find_indirect_entry_definition(
+        &self,
+        file_path: &Path,
+        line: u32,
+        character: u32,
+    ) -> Option<FixtureDefinition> {
+        let content = self.get_file_content(file_path)?;
+        let parsed = self.get_parsed_ast(file_path, &content)?;
+        let ruff_python_ast::Mod::Module(module) = parsed.as_ref() else {
+            return None;
+        };
+        let line_index = self.get_line_index(file_path, &content);
+        let target_line = (line + 1) as usize;
+        let cursor = character as usize;
+
+        let mut stack: Vec<&[Stmt]> = vec![&module.body];
+        while let Some(stmts) = stack.pop() {
+            for stmt in stmts {
+                let decorator_list = match stmt {
+                    Stmt::FunctionDef(f) => f.decorator_list.as_slice(),
+                    Stmt::ClassDef(c) => {
+                        stack.push(&c.body);
+                        c.decorator_list.as_slice()
+                    }
+                    _ => continue,
+                };
+                for decorator in decorator_list {
+                    let decorator = &decorator.expression;
+                    for (name, range) in
+                        decorators::extract_parametrize_indirect_list(decorator, &content)
+                    {
+                        let start = range.start().to_usize();
+                        if self.get_line_from_offset(start, &line_index) != target_line {
+                            continue;
+                        }
+                        let start_char = self.get_char_position_from_offset(start, &line_index);
+                        let end_char =
+                            self.get_char_position_from_offset(range.end().to_usize(), &line_index);
+                        if cursor >= start_char && cursor < end_char {
+                            return self.find_closest_definition(file_path, &name, target_line);
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Find the `request.param` at the given LSP position (0-based line, byte
+    /// column) inside the body of a parametrized fixture, returning the
+    /// fixture and the byte columns `request.param` spans on the line.
+    pub fn find_request_param_at_position(
+        &self,
+        file_path: &Path,
+        line: u32,
+        character: u32,
+    ) -> Option<(FixtureDefinition, usize, usize)> {
+        let target_line = (line + 1) as usize;
+        let fixture = self
+            .fixture_at_line(file_path, target_line)
+            .filter(|def| def.params.is_some() && def.line < target_line)?;
+        let content = self.get_file_content(file_path)?;
+        let line_content = self.get_line_text(file_path, &content, target_line)?;
+        let cursor = character as usize;
+
+        let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+        line_content
+            .match_indices("request")
+            .filter(|(start, _)| {
+                !line_content[..*start]
+                    .chars()
+                    .next_back()
+                    .is_some_and(|c| is_ident(c) || c == '.')
+            })
+            .find_map(|(start, _)| {
+                let rest = &line_content[start + "request".len()..];
+                let attr = rest.trim_start().strip_prefix('.')?.trim_start();
+                let tail = attr.strip_prefix("param")?;
+                if tail.starts_with(is_ident) {
+                    return None;
+                }
+                let end = line_content.len() - tail.len();
+                (cursor >= start && cursor < end).then_some((start, end))
+            })
+            .map(|(start, end)| (fixture, start, end))
+    }
+
+    // ============ Plugin Shadowing Validation ============
+
+    /// Detect the fixtures defined in a file that override a fixture of
+    /// pytest or a plugin without requesting it, each paired with the
+    /// definition it shadows. Such an override silently replaces the plugin's
+    /// fixture for every test in its scope, which is usually accidental.
+    pub fn detect_shadowed_plugin_fixtures_in_file(
+        &self,
+        file_path: &Path,
+    ) -> Vec<(FixtureDefinition, FixtureDefinition)> {
+        let Some(names) = self.file_definitions.get(file_path).map(|n| n.clone()) else {
+            return Vec::new();
+        };
+        let mut shadowing: Vec<(FixtureDefinition, FixtureDefinition)> = names
+            .iter()
+            .filter_map(|name| self.definitions.get(name).map(|defs| defs.clone()))
+            .flatten()
+            .filter(|def| {
+                def.file_path == file_path
+                    && !def.is_third_party
+                    && !def.is_plugin
+                    && !def.dependencies.contains(&def.name)
+            })
+            .filter_map(|def| {
+                let overridden = self.find_overridden_definition(&def)?;
+                (overridden.is_third_party || overridden.is_plugin).then_some((def, overridden))
+            })
+            .collect();
+        shadowing.sort_by_key(|(def, _)| (def.line, def.start_char));
+        shadowing
+    }
+
+    // ============ Unused Fixture Validation ============
+
+    /// Detect the fixtures defined in a file that nothing requests (see
+    /// [`Self::is_fixture_used`]). Meaningful once the workspace is indexed.
+    pub fn detect_unused_fixtures_in_file(&self, file_path: &Path) -> Vec<FixtureDefinition> {
+        let Some(names) = self.file_definitions.get(file_path).map(|n| n.clone()) else {
+            return Vec::new();
+        };
+        let mut unused: Vec<FixtureDefinition> = names
+            .iter()
+            .filter_map(|name| self.definitions.get(name).map(|defs| defs.clone()))
+            .flatten()
+            .filter(|def| def.file_path == file_path && !self.is_fixture_used(def))
+            .collect();
+        unused.sort_by_key(|def| (def.line, def.start_char));
+        unused
+    }
+
+    /// Whether anything requests `definition`: a parameter, a `usefixtures`
+    /// entry, an indirect parametrize, a `getfixturevalue` call or a
+    /// same-named fixture overriding it that resolves to it, or it is used
+    /// implicitly (see [`Self::is_implicitly_used`]).
+    pub fn is_fixture_used(&self, definition: &FixtureDefinition) -> bool {
+        self.is_implicitly_used(definition)
+            || !self.find_references_for_definition(definition).is_empty()
+    }
+
+    /// Whether `definition` is used without being requested by name in the
+    /// project: third-party and autouse fixtures, and fixtures overriding one
+    /// of pytest or a plugin, which requests it internally (e.g. a conftest
+    /// `django_db_setup`).
+    pub(crate) fn is_implicitly_used(&self, definition: &FixtureDefinition) -> bool {
+        if definition.is_third_party || definition.autouse {
+            return true;
+        }
+        let overrides_plugin = self.definitions.get(&definition.name).is_some_and(|defs| {
+            defs.iter()
+                .any(|def| def != definition && (def.is_third_party || def.is_plugin))
+        });
+        overrides_plugin
+            || super::builtins::BUILTIN_FIXTURES
+                .iter()
+                .any(|builtin| builtin.name == definition.name)
+    }
+
+    // ============ Unused Parameter Validation ============
+
+    /// Detect fixture parameters of tests and fixtures that their body never
+    /// references.
+    ///
+    /// Fixtures requested for their side effects are left alone: those that
+    /// provide no value (return or yield nothing) and the capture and warning
+    /// recording ones. So are parametrized names, `request` in fixtures
+    /// declaring `params=`, names starting with `_`, placeholder bodies
+    /// (`pass`, `...`) and bodies calling `locals()` or `vars()`.
+    pub fn detect_unused_fixture_params_in_file(
+        &self,
+        file_path: &Path,
+    ) -> Vec<UnusedFixtureParam> {
+        let Some(content) = self.get_file_content(file_path) else {
+            return Vec::new();
+        };
+        let Some(parsed) = self.get_parsed_ast(file_path, &content) else {
+            return Vec::new();
+        };
+        let ruff_python_ast::Mod::Module(module) = parsed.as_ref() else {
+            return Vec::new();
+        };
+        let line_index = self.get_line_index(file_path, &content);
+        let imports_pytest = self
+            .imports
+            .get(file_path)
+            .is_some_and(|names| names.contains("pytest"));
+
+        let mut unused = Vec::new();
+        // Statements with the names parametrized by their enclosing classes
+        let mut stack: Vec<(&[Stmt], HashSet<String>)> = vec![(&module.body, HashSet::new())];
+        while let Some((stmts, class_parametrized)) = stack.pop() {
+            for stmt in stmts {
+                match stmt {
+                    Stmt::ClassDef(class_def) => {
+                        let mut parametrized = class_parametrized.clone();
+                        parametrized.extend(Self::parametrized_names(
+                            &class_def.decorator_list,
+                            &content,
+                        ));
+                        stack.push((&class_def.body, parametrized));
+                    }
+                    Stmt::FunctionDef(func_def) => self.collect_unused_fixture_params(
+                        stmt,
+                        func_def,
+                        &class_parametrized,
+                        imports_pytest,
+                        file_path,
+                        &content,
+                        &line_index,
+                        &mut unused,
+                    ),
+                    _ => {}
+                }
+            }
+        }
+        unused.sort_by_key(|param| (param.line, param.start_char));
+        unused
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn collect_unused_fixture_params(
+        &self,
+        stmt: &Stmt,
+        func_def: &StmtFunctionDef,
+        class_parametrized: &HashSet<String>,
+        imports_pytest: bool,
+        file_path: &Path,
+        content: &str,
+        line_index: &[usize],
+        unused: &mut Vec<UnusedFixtureParam>,
+    ) {
+        let aliases = self.fixture_decorator_aliases();
+        let is_fixture = func_def
+            .decorator_list
+            .iter()
+            .any(|d| decorators::is_fixture_decorator(&d.expression, &aliases));
+        let is_test = func_def.name.starts_with("test") && !is_fixture;
+        if !is_test && !is_fixture {
+            return;
+        }
+        if Self::is_placeholder_body(&func_def.body) {
+            return;
+        }
+
+        let mut parametrized = Self::parametrized_names(&func_def.decorator_list, content);
+        parametrized.extend(class_parametrized.iter().cloned());
+        // `request` is how a fixture declaring `params=` reaches its values
+        if func_def.decorator_list.iter().any(|d| {
+            decorators::extract_fixture_params(&d.expression, &aliases, content, &func_def.name)
+                .is_some()
+        }) {
+            parametrized.insert("request".to_string());
+        }
+        let mut referenced = NameReferenceCollector::default();
+        referenced.visit_body(&func_def.body);
+        if referenced.names.contains("locals") || referenced.names.contains("vars") {
+            return;
+        }
+
+        let args: Vec<&ruff_python_ast::ParameterWithDefault> =
+            Self::all_args(&func_def.parameters).collect();
+        let kwonly_start = func_def.parameters.posonlyargs.len() + func_def.parameters.args.len();
+        for (i, arg) in args.iter().enumerate() {
+            let name = arg.parameter.name.as_str();
+            if name == "self"
+                || name == "cls"
+                || name.starts_with('_')
+                || arg.default.is_some()
+                || parametrized.contains(name)
+                || referenced.names.contains(name)
+                || SIDE_EFFECT_FIXTURES.contains(&name)
+            {
+                continue;
+            }
+
+            let offset = arg.parameter.name.start().to_usize();
+            let line = self.get_line_from_offset(offset, line_index);
+            let start_char = self.get_char_position_from_offset(offset, line_index);
+            let Some(definition) =
+                self.find_fixture_definition(file_path, (line - 1) as u32, start_char as u32)
+            else {
+                continue;
+            };
+            if !self.fixture_provides_value(&definition) {
+                continue;
+            }
+
+            // Drop the parameter with the separator before or after it; a
+            // lone keyword-only parameter would leave a bare `*` behind
+            let removal = if i >= kwonly_start
+                && func_def.parameters.kwonlyargs.len() == 1
+                && func_def.parameters.vararg.is_none()
+            {
+                None
+            } else if let Some(next) = args.get(i + 1) {
+                Some((arg.start().to_usize(), next.start().to_usize()))
+            } else if i > 0 {
+                Some((args[i - 1].end().to_usize(), arg.end().to_usize()))
+            } else {
+                Some((arg.start().to_usize(), arg.end().to_usize()))
+            };
+
+            // usefixtures has no effect on fixtures
+            let usefixtures = (is_test && imports_pytest).then(|| {
+                let def_start = stmt.start().to_usize();
+                let line_start = content[..def_start].rfind('\n').map_or(0, |i| i + 1);
+                let indent = &content[line_start..def_start];
+                (
+                    line_start,
+                    format!("{}@pytest.mark.usefixtures(\"{}\")\n", indent, name),
+                )
+            });
+
+            unused.push(UnusedFixtureParam {
+                name: name.to_string(),
+                file_path: file_path.to_path_buf(),
+                line,
+                start_char,
+                end_char: start_char + name.len(),
+                removal,
+                usefixtures,
+            });
+        }
+    }
+
+    /// Names parametrized directly by `@pytest.mark.parametrize` decorators,
+    /// indirect ones included.
+    fn parametrized_names(decorator_list: &[Decorator], content: &str) -> HashSet<String> {
+        decorator_list
+            .iter()
+            .flat_map(|d| decorators::extract_parametrize_argnames(&d.expression, content))
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    /// Whether a function body is a placeholder: only a docstring, `pass` or
+    /// `...`.
+    fn is_placeholder_body(body: &[Stmt]) -> bool {
+        body.iter().all(|stmt| match stmt {
+            Stmt::Pass(_) => true,
+            Stmt::Expr(expr) => matches!(
+                &*expr.value,
+                Expr::StringLiteral(_) | Expr::EllipsisLiteral(_)
+            ),
+            _ => false,
+        })
+    }
+
+    /// Whether `fixture` returns or yields a value. Fixtures whose source
+    /// cannot be read are assumed to.
+    fn fixture_provides_value(&self, fixture: &FixtureDefinition) -> bool {
+        let Some(content) = self.get_file_content(&fixture.file_path) else {
+            return true;
+        };
+        let Some(parsed) = self.get_parsed_ast(&fixture.file_path, &content) else {
+            return true;
+        };
+        let ruff_python_ast::Mod::Module(module) = parsed.as_ref() else {
+            return true;
+        };
+        let line_index = self.get_line_index(&fixture.file_path, &content);
+        let Some(Stmt::FunctionDef(func_def)) =
+            self.find_function_at_line(&module.body, fixture.line, &content, &line_index)
+        else {
+            return true;
+        };
+        let mut finder = ValueReturnFinder::default();
+        finder.visit_body(&func_def.body);
+        finder.found
+    }
+
+    // ============ Linked Editing ============
+
+    /// Find the occurrences of the parameter of a test or fixture at the
+    /// given LSP position (0-based line, byte column), which may be on the
+    /// parameter itself or on one of its uses in the body. Returns the
+    /// parameter followed by its uses, as `(line, start_char, end_char)` with
+    /// 1-based lines and byte columns.
+    ///
+    /// Nested functions and lambdas declaring a parameter of the same name
+    /// shadow it and are left out.
+    pub fn find_parameter_occurrences(
+        &self,
+        file_path: &Path,
+        line: u32,
+        character: u32,
+    ) -> Option<Vec<(usize, usize, usize)>> {
+        let content = self.get_file_content(file_path)?;
+        let parsed = self.get_parsed_ast(file_path, &content)?;
+        let ruff_python_ast::Mod::Module(module) = parsed.as_ref() else {
+            return None;
+        };
+        let line_index = self.get_line_index(file_path, &content);
+        let offset = line_index.get(line as usize)? + character as usize;
+        let offset = ruff_text_size::TextSize::try_from(offset).ok()?;
+
+        let aliases = self.fixture_decorator_aliases();
+        let mut stack: Vec<&[Stmt]> = vec![&module.body];
+        let func_def = 'search: loop {
+            let stmts = stack.pop()?;
+            for stmt in stmts {
+                match stmt {
+                    Stmt::ClassDef(class_def) => stack.push(&class_def.body),
+                    Stmt::FunctionDef(f) if f.range().contains_inclusive(offset) => {
+                        let is_fixture = f
+                            .decorator_list
+                            .iter()
+                            .any(|d| decorators::is_fixture_decorator(&d.expression, &aliases));
+                        if is_fixture || f.name.starts_with("test") {
+                            break 'search f;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        };
+
+        let params: Vec<&ruff_python_ast::Parameter> = Self::all_args(&func_def.parameters)
+            .map(|arg| &arg.parameter)
+            .filter(|p| p.name.as_str() != "self" && p.name.as_str() != "cls")
+            .collect();
+        let parameter = match params
+            .iter()
+            .find(|p| p.name.range().contains_inclusive(offset))
+        {
+            Some(parameter) => *parameter,
+            None => {
+                let mut finder = NameAtOffsetFinder {
+                    offset,
+                    found: None,
+                };
+                finder.visit_body(&func_def.body);
+                let name = finder.found?;
+                *params.iter().find(|p| p.name.as_str() == name)?
+            }
+        };
+
+        let mut collector = ParameterUseCollector {
+            name: parameter.name.as_str(),
+            ranges: vec![parameter.name.range()],
+        };
+        collector.visit_body(&func_def.body);
+        // A use under the cursor inside a shadowing scope is another variable
+        if !collector
+            .ranges
+            .iter()
+            .any(|range| range.contains_inclusive(offset))
+        {
+            return None;
+        }
+        Some(
+            collector
+                .ranges
+                .into_iter()
+                .map(|range| {
+                    let start = range.start().to_usize();
+                    let start_char = self.get_char_position_from_offset(start, &line_index);
+                    (
+                        self.get_line_from_offset(start, &line_index),
+                        start_char,
+                        start_char + range.len().to_usize(),
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    // ============ Test Fixture Injection ============
+
+    /// List the fixtures injected into the test function whose name is at
+    /// `line` (1-indexed) / `character` (byte column).
+    ///
+    /// Covers autouse fixtures visible from the file, `usefixtures` on the
+    /// module (`pytestmark`), enclosing classes and the test itself, and the
+    /// test's parameters (minus `self`/`cls` and directly parametrized names).
+    /// Returns `None` when the position is not on a test function name.
+    pub fn get_test_fixtures_at_position(
+        &self,
+        file_path: &Path,
+        line: usize,
+        character: usize,
+    ) -> Option<TestFixtures> {
+        let content = self.get_file_content(file_path)?;
+        let parsed = self.get_parsed_ast(file_path, &content)?;
+        let ruff_python_ast::Mod::Module(module) = parsed.as_ref() else {
+            return None;
+        };
+        let line_index = self.get_line_index(file_path, &content);
+
+        // Module-level `pytestmark` applies to every test in the file.
+        let mut usefixtures: Vec<String> = Vec::new();
+        for stmt in &module.body {
+            let value = match stmt {
+                Stmt::Assign(assign)
+                    if assign.targets.iter().any(|target| {
+                        matches!(target, Expr::Name(name) if name.id.as_str() == "pytestmark")
+                    }) =>
+                {
+                    Some(assign.value.as_ref())
+                }
+                Stmt::AnnAssign(ann_assign)
+                    if matches!(
+                        ann_assign.target.as_ref(),
+                        Expr::Name(name) if name.id.as_str() == "pytestmark"
+                    ) =>
+                {
+                    ann_assign.value.as_deref()
+                }
+                _ => None,
+            };
+            if let Some(value) = value {
+                usefixtures.extend(
+                    decorators::extract_usefixtures_from_expr(value, &content)
+                        .into_iter()
+                        .map(|(name, _)| name),
+                );
+            }
+        }
+
+        let (test_name, params) = self.find_test_at_position(
+            &module.body,
+            line,
+            character,
+            &content,
+            &line_index,
+            &mut usefixtures,
+        )?;
+
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut fixtures = Vec::new();
+        let mut push = |name: String, source: InjectionSource| {
+            // A fixture also listed in usefixtures is shown as the parameter
+            // the test reads
+            let source = match source {
+                InjectionSource::Usefixtures if params.contains(&name) => {
+                    InjectionSource::Parameter
+                }
+                source => source,
+            };
+            if seen.insert(name.clone()) {
+                // Builtins are known even when pytest itself was not scanned
+                let definition = self
+                    .find_closest_definition(file_path, &name, line)
+                    .or_else(|| builtins::builtin_definition(&name));
+                fixtures.push(InjectedFixture {
+                    name,
+                    source,
+                    definition,
+                });
+            }
+        };
+
+        let mut autouse: Vec<FixtureDefinition> = self
+            .get_available_fixtures_at(file_path, line)
+            .iter()
+            .filter(|def| def.autouse)
+            .cloned()
+            .collect();
+        autouse.sort_by(|a, b| a.name.cmp(&b.name));
+        for def in autouse {
+            push(def.name, InjectionSource::Autouse);
+        }
+        for name in usefixtures {
+            push(name, InjectionSource::Usefixtures);
+        }
+        for name in &params {
+            push(name.clone(), InjectionSource::Parameter);
+        }
+
+        Some(TestFixtures {
+            test_name,
+            fixtures,
+        })
+    }
+
+    /// Find the test function whose name is at the given position, returning
+    /// its name and fixture parameters. `usefixtures` is extended with names
+    /// from enclosing classes and the test's own decorators.
+    fn find_test_at_position(
+        &self,
+        stmts: &[Stmt],
+        line: usize,
+        character: usize,
+        content: &str,
+        line_index: &[usize],
+        usefixtures: &mut Vec<String>,
+    ) -> Option<(String, Vec<String>)> {
+        for stmt in stmts {
+            let (name, decorator_list, args) = match stmt {
+                Stmt::FunctionDef(f) => (f.name.as_str(), &f.decorator_list, &f.parameters),
+                Stmt::ClassDef(class_def) => {
+                    let outer_len = usefixtures.len();
+                    for decorator in &class_def.decorator_list {
+                        usefixtures.extend(
+                            decorators::extract_usefixtures_names(&decorator.expression, content)
+                                .into_iter()
+                                .map(|(name, _)| name),
+                        );
+                    }
+                    if let Some(found) = self.find_test_at_position(
+                        &class_def.body,
+                        line,
+                        character,
+                        content,
+                        line_index,
+                        usefixtures,
+                    ) {
+                        return Some(found);
+                    }
+                    usefixtures.truncate(outer_len);
+                    continue;
+                }
+                _ => continue,
+            };
+
+            if !name.starts_with("test") {
+                continue;
+            }
+            let def_start = super::definition_start(stmt, content);
+            let def_line = self.get_line_from_offset(def_start.to_usize(), line_index);
+            if def_line != line {
+                continue;
+            }
+            let (start, end) = super::string_utils::find_function_name_position(
+                content, line_index, def_line, name,
+            );
+            if character < start || character > end {
+                continue;
+            }
+
+            // Names parametrized directly receive values, not fixtures.
+            let mut parametrized: HashSet<String> = HashSet::new();
+            for decorator in decorator_list {
+                let decorator = &decorator.expression;
+                usefixtures.extend(
+                    decorators::extract_usefixtures_names(decorator, content)
+                        .into_iter()
+                        .map(|(name, _)| name),
+                );
+                let argnames: Vec<String> =
+                    decorators::extract_parametrize_argnames(decorator, content)
+                        .into_iter()
+                        .map(|(name, _)| name)
+                        .collect();
+                let indirect = decorators::extract_parametrize_indirect_names(decorator, &argnames);
+                parametrized.extend(argnames.into_iter().filter(|n| !indirect.contains(n)));
+            }
+
+            let params = args
+                .posonlyargs
+                .iter()
+                .chain(args.args.iter())
+                .chain(args.kwonlyargs.iter())
+                .map(|arg| arg.parameter.name.to_string())
+                .filter(|arg| arg != "self" && arg != "cls" && !parametrized.contains(arg))
+                .collect();
+            return Some((name.to_string(), params));
+        }
+        None
+    }
+
+    /// Resolve a fixture by name for a given file using priority rules.
+    ///
+    /// Returns the best matching FixtureDefinition based on pytest's
+    /// fixture shadowing rules: same file > conftest hierarchy > third-party.
+    pub fn resolve_fixture_for_file(
+        &self,
+        file_path: &Path,
+        fixture_name: &str,
+    ) -> Option<FixtureDefinition> {
+        let definitions = self.definitions.get(fixture_name)?;
+
+        // Priority 1: Same file
+        if let Some(def) = definitions.iter().find(|d| d.file_path == file_path) {
+            return Some(def.clone());
+        }
+
+        // Priority 2: conftest.py in parent directories (closest first), up
+        // to pytest's confcutdir
+        let file_path = self.get_canonical_path(file_path.to_path_buf());
+        let confcutdir = self.confcutdir_for(&file_path);
+        let mut best_conftest: Option<&FixtureDefinition> = None;
+        let mut best_depth = usize::MAX;
+
+        for def in definitions.iter() {
+            if def.is_third_party {
+                continue;
+            }
+            if def.file_path.ends_with("conftest.py") {
+                if let Some(parent) = def.file_path.parent() {
+                    let loaded = confcutdir
+                        .as_ref()
+                        .is_none_or(|cut| parent.starts_with(cut));
+                    if loaded && file_path.starts_with(parent) {
+                        let depth = parent.components().count();
+                        if depth > best_depth {
+                            // Deeper = closer conftest
+                            best_conftest = Some(def);
+                            best_depth = depth;
+                        } else if best_conftest.is_none() {
+                            best_conftest = Some(def);
+                            best_depth = depth;
+                        }
+                    }
+                }
+            }
+        }
+
+        if let Some(def) = best_conftest {
+            return Some(def.clone());
+        }
 
         // Priority 3: Plugin fixtures (pytest11 entry points)
         if let Some(def) = definitions
@@ -1787,30 +3489,101 @@ impl FixtureDatabase {
             return Some(def.clone());
         }
 
-        // Priority 4: Third-party (site-packages)
-        if let Some(def) = definitions.iter().find(|d| d.is_third_party) {
+        // Priority 4: Third-party (site-packages), from the environment of
+        // the file's subproject
+        let hidden = self.hidden_site_packages(&file_path);
+        if let Some(def) = definitions
+            .iter()
+            .find(|d| d.is_third_party && !in_site_packages(d, &hidden))
+        {
             return Some(def.clone());
         }
 
-        // Fallback: first definition
-        definitions.first().cloned()
+        // Fallback: first definition of the same pytest root; another
+        // project's fixtures are never visible
+        let rootdir = |path: &Path| self.pytest_root_for(path).map(|root| root.rootdir);
+        let file_rootdir = rootdir(&file_path);
+        definitions
+            .iter()
+            .find(|d| rootdir(&d.file_path) == file_rootdir && !in_site_packages(d, &hidden))
+            .cloned()
     }
 
-    /// Find the name of the function/fixture containing a given line.
+    /// Resolve the transitive dependencies of `fixture`.
     ///
-    /// Used for call hierarchy to identify callers.
+    /// Each dependency is resolved from the file of the fixture that requests
+    /// it, so conftest overrides are honoured at every hop; a dependency named
+    /// like its requester resolves to the overridden parent definition.
+    /// Returns a pre-order list of `(depth, definition)` where depth 1 is a
+    /// direct dependency. Every definition is expanded once, which also
+    /// guards against cycles. Unresolvable names are skipped.
+    pub fn resolve_dependency_tree(
+        &self,
+        fixture: &FixtureDefinition,
+    ) -> Vec<(usize, FixtureDefinition)> {
+        let mut tree = Vec::new();
+        let mut expanded: HashSet<(PathBuf, usize)> = HashSet::new();
+        expanded.insert((fixture.file_path.clone(), fixture.line));
+        self.collect_dependency_tree(fixture, 1, &mut expanded, &mut tree);
+        tree
+    }
+
+    /// Resolve the direct dependencies of `fixture` from its own file, in
+    /// declaration order. Unresolvable names are skipped.
+    pub(crate) fn resolve_dependencies(
+        &self,
+        fixture: &FixtureDefinition,
+    ) -> Vec<FixtureDefinition> {
+        fixture
+            .dependencies
+            .iter()
+            .filter_map(|dep_name| {
+                if *dep_name == fixture.name {
+                    self.find_closest_definition_excluding(
+                        &fixture.file_path,
+                        dep_name,
+                        fixture.line,
+                        Some(fixture),
+                    )
+                } else {
+                    self.find_closest_definition(&fixture.file_path, dep_name, fixture.line)
+                }
+            })
+            .collect()
+    }
+
+    fn collect_dependency_tree(
+        &self,
+        fixture: &FixtureDefinition,
+        depth: usize,
+        expanded: &mut HashSet<(PathBuf, usize)>,
+        tree: &mut Vec<(usize, FixtureDefinition)>,
+    ) {
+        for dep in self.resolve_dependencies(fixture) {
+            let first_visit = expanded.insert((dep.file_path.clone(), dep.line));
+            tree.push((depth, dep.clone()));
+            if first_visit {
+                self.collect_dependency_tree(&dep, depth + 1, expanded, tree);
+            }
+        }
+    }
+
+    /// Find the name of the function/fixture containing a given line.
+    #[allow(dead_code)] // Used by library consumers and tests, not the binary
     pub fn find_containing_function(&self, file_path: &Path, line: usize) -> Option<String> {
         let content = self.get_file_content(file_path)?;
 
         // Use cached AST to avoid re-parsing
         let parsed = self.get_parsed_ast(file_path, &content)?;
 
-        if let rustpython_parser::ast::Mod::Module(module) = parsed.as_ref() {
+        if let ruff_python_ast::Mod::Module(module) = parsed.as_ref() {
             // Use cached line index for position calculations
             let line_index = self.get_line_index(file_path, &content);
 
             for stmt in &module.body {
-                if let Some(name) = self.find_function_containing_line(stmt, line, &line_index) {
+                if let Some(name) =
+                    self.find_function_containing_line(stmt, line, &content, &line_index)
+                {
                     return Some(name);
                 }
             }
@@ -1824,22 +3597,15 @@ impl FixtureDatabase {
         &self,
         stmt: &Stmt,
         target_line: usize,
+        content: &str,
         line_index: &[usize],
     ) -> Option<String> {
         match stmt {
             Stmt::FunctionDef(func_def) => {
-                let start_line =
-                    self.get_line_from_offset(func_def.range.start().to_usize(), line_index);
-                let end_line =
-                    self.get_line_from_offset(func_def.range.end().to_usize(), line_index);
-
-                if target_line >= start_line && target_line <= end_line {
-                    return Some(func_def.name.to_string());
-                }
-            }
-            Stmt::AsyncFunctionDef(func_def) => {
-                let start_line =
-                    self.get_line_from_offset(func_def.range.start().to_usize(), line_index);
+                let start_line = self.get_line_from_offset(
+                    super::definition_start(stmt, content).to_usize(),
+                    line_index,
+                );
                 let end_line =
                     self.get_line_from_offset(func_def.range.end().to_usize(), line_index);
 
@@ -1850,9 +3616,12 @@ impl FixtureDatabase {
             Stmt::ClassDef(class_def) => {
                 // Check methods inside the class
                 for class_stmt in &class_def.body {
-                    if let Some(name) =
-                        self.find_function_containing_line(class_stmt, target_line, line_index)
-                    {
+                    if let Some(name) = self.find_function_containing_line(
+                        class_stmt,
+                        target_line,
+                        content,
+                        line_index,
+                    ) {
                         return Some(name);
                     }
                 }
@@ -1964,11 +3733,11 @@ fn byte_offset_to_col(offset: usize, line_index: &[usize]) -> usize {
 fn find_insertion_in_stmts(
     stmts: &[Stmt],
     function_line: usize,
-    bytes: &[u8],
+    content: &str,
     line_index: &[usize],
 ) -> Option<ParamInsertionInfo> {
     for stmt in stmts {
-        if let Some(info) = find_insertion_in_stmt(stmt, function_line, bytes, line_index) {
+        if let Some(info) = find_insertion_in_stmt(stmt, function_line, content, line_index) {
             return Some(info);
         }
     }
@@ -1979,34 +3748,32 @@ fn find_insertion_in_stmts(
 fn find_insertion_in_stmt(
     stmt: &Stmt,
     function_line: usize,
-    bytes: &[u8],
+    content: &str,
     line_index: &[usize],
 ) -> Option<ParamInsertionInfo> {
     match stmt {
         Stmt::FunctionDef(f) => {
-            let def_start = f.range.start().to_usize();
+            let def_start = super::definition_start(stmt, content).to_usize();
             if byte_offset_to_line_1based(def_start, line_index) == function_line {
-                return param_insertion_from_args(def_start, &f.args, bytes, line_index);
+                return param_insertion_from_args(
+                    def_start,
+                    &f.parameters,
+                    content.as_bytes(),
+                    line_index,
+                );
             }
             // Recurse into the function body (handles nested functions).
-            find_insertion_in_stmts(&f.body, function_line, bytes, line_index)
-        }
-        Stmt::AsyncFunctionDef(f) => {
-            let def_start = f.range.start().to_usize();
-            if byte_offset_to_line_1based(def_start, line_index) == function_line {
-                return param_insertion_from_args(def_start, &f.args, bytes, line_index);
-            }
-            find_insertion_in_stmts(&f.body, function_line, bytes, line_index)
+            find_insertion_in_stmts(&f.body, function_line, content, line_index)
         }
         Stmt::ClassDef(c) => {
             // Recurse into the class body to find test methods.
-            find_insertion_in_stmts(&c.body, function_line, bytes, line_index)
+            find_insertion_in_stmts(&c.body, function_line, content, line_index)
         }
         _ => None,
     }
 }
 
-/// Given the byte offset of a `def` keyword and the function's AST `Arguments`,
+/// Given the byte offset of a `def` keyword and the function's AST `Parameters`,
 /// scan the raw source bytes from `def_start` to find the closing `)` and build
 /// a `ParamInsertionInfo`.
 ///
@@ -2014,7 +3781,7 @@ fn find_insertion_in_stmt(
 /// handles all argument forms (`*args`, `**kwargs`, keyword-only, etc.).
 fn param_insertion_from_args(
     def_start: usize,
-    args: &Arguments,
+    args: &Parameters,
     bytes: &[u8],
     line_index: &[usize],
 ) -> Option<ParamInsertionInfo> {
@@ -1,15 +1,32 @@
 //! Workspace and virtual environment scanning for fixture definitions.
 
-use super::imports::try_init_stdlib_from_python;
+use super::conda::find_conda_env;
+use super::hatch::find_hatch_environment;
+use super::imports::{find_venv_python, python_prefix, try_init_stdlib_from_python};
+use super::pyenv::find_pinned_python;
+use super::pytest_config::CONFIG_FILES;
 use super::types::{FixtureDefinition, FixtureScope, TypeImportSpec};
+use super::uv::{find_uv_environment, find_uv_tool_environment};
 use super::FixtureDatabase;
 use glob::Pattern;
+use ignore::WalkBuilder;
 use rayon::prelude::*;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
 use tracing::{debug, error, info, warn};
 use walkdir::WalkDir;
 
+/// A subproject of the workspace with a virtual environment of its own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SubprojectVenv {
+    /// The subproject's directory.
+    pub(crate) dir: PathBuf,
+    /// Its virtual environment.
+    pub(crate) venv: PathBuf,
+    /// The site-packages directory scanned in it.
+    pub(crate) site_packages: Option<PathBuf>,
+}
+
 /// A pytest11 entry point from a dist-info package.
 #[derive(Debug, Clone)]
 pub(crate) struct Pytest11EntryPoint {
@@ -19,6 +36,62 @@ pub(crate) struct Pytest11EntryPoint {
     pub(crate) module_path: String,
 }
 
+/// What a workspace scan is busy with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanPhase {
+    /// Walking the workspace for conftest.py and test files.
+    Discovering,
+    /// Analyzing the files found.
+    Files,
+    /// Looking for pytest plugins in the virtual environment's packages.
+    Packages,
+}
+
+/// Progress of a workspace scan, updated by the scanner and read by whoever
+/// reports it. Cancelling stops the scan at the next file or package.
+#[derive(Debug, Default)]
+pub struct ScanProgress {
+    phase: AtomicU8,
+    done: AtomicUsize,
+    total: AtomicUsize,
+    cancelled: AtomicBool,
+}
+
+impl ScanProgress {
+    /// The current phase, with the items done and the total in that phase.
+    pub fn snapshot(&self) -> (ScanPhase, usize, usize) {
+        let phase = match self.phase.load(Ordering::Acquire) {
+            0 => ScanPhase::Discovering,
+            1 => ScanPhase::Files,
+            _ => ScanPhase::Packages,
+        };
+        (
+            phase,
+            self.done.load(Ordering::Relaxed),
+            self.total.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Ask the scan to stop.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn start_phase(&self, phase: ScanPhase, total: usize) {
+        self.total.store(total, Ordering::Relaxed);
+        self.done.store(0, Ordering::Relaxed);
+        self.phase.store(phase as u8, Ordering::Release);
+    }
+
+    fn advance(&self) {
+        self.done.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
 impl FixtureDatabase {
     /// Directories that should be skipped during workspace scanning.
     /// These are typically large directories that don't contain test files.
@@ -70,14 +143,53 @@ impl FixtureDatabase {
         false
     }
 
+    /// Whether a file name is a test file's: one matching the configured test
+    /// file patterns or, when none are configured, pytest's defaults
+    /// (`test_*.py` or `*_test.py`).
+    pub fn is_test_file_name(&self, name: &str) -> bool {
+        let patterns = self.test_file_patterns.lock().unwrap();
+        if patterns.is_empty() {
+            is_default_test_file_name(name)
+        } else {
+            patterns.iter().any(|pattern| pattern.matches(name))
+        }
+    }
+
     /// Scan a workspace directory for test files and conftest.py files.
-    /// Optionally accepts exclude patterns from configuration.
+    #[allow(dead_code)] // Used by library consumers and tests; the binary scans with configuration
     pub fn scan_workspace(&self, root_path: &Path) {
-        self.scan_workspace_with_excludes(root_path, &[]);
+        self.scan_workspace_with_excludes(root_path, &[], true);
     }
 
     /// Scan a workspace directory with custom exclude patterns.
-    pub fn scan_workspace_with_excludes(&self, root_path: &Path, exclude_patterns: &[Pattern]) {
+    ///
+    /// Exclude patterns are matched against paths relative to the workspace
+    /// root; a matching directory is not descended into. With
+    /// `respect_gitignore`, paths ignored by `.gitignore`, `.ignore` and
+    /// `.git/info/exclude` files are skipped too.
+    pub fn scan_workspace_with_excludes(
+        &self,
+        root_path: &Path,
+        exclude_patterns: &[Pattern],
+        respect_gitignore: bool,
+    ) {
+        self.scan_workspace_with_progress(
+            root_path,
+            exclude_patterns,
+            respect_gitignore,
+            &ScanProgress::default(),
+        );
+    }
+
+    /// Scan a workspace directory like [`Self::scan_workspace_with_excludes`],
+    /// reporting to `progress` and stopping early when it is cancelled.
+    pub fn scan_workspace_with_progress(
+        &self,
+        root_path: &Path,
+        exclude_patterns: &[Pattern],
+        respect_gitignore: bool,
+        progress: &ScanProgress,
+    ) {
         let root_path_buf = root_path
             .canonicalize()
             .unwrap_or_else(|_| root_path.to_path_buf());
@@ -95,23 +207,58 @@ impl FixtureDatabase {
             return;
         }
 
+        self.load_pytest_root(root_path);
+        self.load_index_cache(root_path);
+        progress.start_phase(ScanPhase::Discovering, 0);
+
         // Phase 1: Collect all file paths (sequential, fast)
         let mut files_to_process: Vec<std::path::PathBuf> = Vec::new();
+        let mut config_dirs: Vec<PathBuf> = Vec::new();
         let mut skipped_dirs = 0;
 
-        // Use WalkDir with filter to skip large/irrelevant directories
-        let walker = WalkDir::new(root_path).into_iter().filter_entry(|entry| {
-            // Allow files to pass through
-            if entry.file_type().is_file() {
-                return true;
+        let is_excluded = {
+            let root = root_path.to_path_buf();
+            let patterns = exclude_patterns.to_vec();
+            move |path: &Path| {
+                !patterns.is_empty()
+                    && path.strip_prefix(&root).is_ok_and(|relative| {
+                        let relative_str = relative.to_string_lossy();
+                        patterns.iter().any(|p| p.matches(&relative_str))
+                    })
             }
-            // For directories, check if we should skip them
-            if let Some(dir_name) = entry.file_name().to_str() {
-                !Self::should_skip_directory(dir_name)
-            } else {
+        };
+
+        // Walk the tree honoring ignore files, pruning large/irrelevant and
+        // excluded directories
+        let prune = is_excluded.clone();
+        let walker = WalkBuilder::new(root_path)
+            .hidden(false)
+            .require_git(false)
+            .git_ignore(respect_gitignore)
+            .git_global(respect_gitignore)
+            .git_exclude(respect_gitignore)
+            .ignore(respect_gitignore)
+            .parents(respect_gitignore)
+            .filter_entry(move |entry| {
+                // Allow files to pass through
+                if !entry.file_type().is_some_and(|t| t.is_dir()) || entry.depth() == 0 {
+                    return true;
+                }
+                // For directories, check if we should skip them
+                if entry
+                    .file_name()
+                    .to_str()
+                    .is_some_and(Self::should_skip_directory)
+                {
+                    return false;
+                }
+                if prune(entry.path()) {
+                    debug!("Skipping excluded directory: {:?}", entry.path());
+                    return false;
+                }
                 true
-            }
-        });
+            })
+            .build();
 
         for entry in walker {
             let entry = match entry {
@@ -147,27 +294,21 @@ impl FixtureDatabase {
             }
 
             // Skip files matching user-configured exclude patterns
-            // Patterns are matched against paths relative to workspace root
-            if !exclude_patterns.is_empty() {
-                if let Ok(relative_path) = path.strip_prefix(root_path) {
-                    let relative_str = relative_path.to_string_lossy();
-                    if exclude_patterns.iter().any(|p| p.matches(&relative_str)) {
-                        debug!("Skipping excluded path: {:?}", path);
-                        continue;
-                    }
-                }
+            if is_excluded(path) {
+                debug!("Skipping excluded path: {:?}", path);
+                continue;
             }
 
             // Look for conftest.py or test_*.py or *_test.py files
             if let Some(filename) = path.file_name().and_then(|n| n.to_str()) {
-                if filename == "conftest.py"
-                    || filename.starts_with("test_") && filename.ends_with(".py")
-                    || filename.ends_with("_test.py")
-                {
+                if filename == "conftest.py" || self.is_test_file_name(filename) {
                     files_to_process.push(path.to_path_buf());
+                } else if CONFIG_FILES.iter().any(|(name, _)| *name == filename) {
+                    config_dirs.extend(path.parent().map(Path::to_path_buf));
                 }
             }
         }
+        self.load_nested_pytest_roots(&config_dirs);
 
         if skipped_dirs > 0 {
             debug!("Skipped {} entries in filtered directories", skipped_dirs);
@@ -175,6 +316,7 @@ impl FixtureDatabase {
 
         let total_files = files_to_process.len();
         info!("Found {} test/conftest files to process", total_files);
+        progress.start_phase(ScanPhase::Files, total_files);
 
         // Phase 2: Process files in parallel using rayon
         // Use analyze_file_fresh since this is initial scan (no previous definitions to clean)
@@ -182,21 +324,28 @@ impl FixtureDatabase {
         let permission_denied_count = AtomicUsize::new(0);
 
         files_to_process.par_iter().for_each(|path| {
+            if progress.is_cancelled() {
+                return;
+            }
             debug!("Found test/conftest file: {:?}", path);
-            match std::fs::read_to_string(path) {
-                Ok(content) => {
-                    self.analyze_file_fresh(path.clone(), &content);
-                }
-                Err(err) => {
-                    if err.kind() == std::io::ErrorKind::PermissionDenied {
-                        debug!("Permission denied reading file: {:?}", path);
-                        permission_denied_count.fetch_add(1, Ordering::Relaxed);
-                    } else {
-                        error!("Failed to read file {:?}: {}", path, err);
-                        error_count.fetch_add(1, Ordering::Relaxed);
+            // Open files are analyzed from the editor's buffer instead
+            if !self.open_files.contains_key(path) {
+                match std::fs::read_to_string(path) {
+                    Ok(content) => {
+                        self.analyze_file_fresh(path.clone(), &content);
+                    }
+                    Err(err) => {
+                        if err.kind() == std::io::ErrorKind::PermissionDenied {
+                            debug!("Permission denied reading file: {:?}", path);
+                            permission_denied_count.fetch_add(1, Ordering::Relaxed);
+                        } else {
+                            error!("Failed to read file {:?}: {}", path, err);
+                            error_count.fetch_add(1, Ordering::Relaxed);
+                        }
                     }
                 }
             }
+            progress.advance();
         });
 
         let errors = error_count.load(Ordering::Relaxed);
@@ -217,14 +366,24 @@ impl FixtureDatabase {
             total_files, permission_errors, errors
         );
 
+        if progress.is_cancelled() {
+            warn!("Workspace scan of {:?} cancelled", root_path);
+            return;
+        }
+
         // Phase 3: Scan virtual environment for pytest plugins first
-        // (must happen before import scanning so venv plugin files are in file_cache)
-        self.scan_venv_fixtures(root_path);
+        // (must happen before import scanning so venv plugin files are indexed)
+        self.scan_venv_fixtures(root_path, progress);
+        if progress.is_cancelled() {
+            warn!("Workspace scan of {:?} cancelled", root_path);
+            return;
+        }
 
         // Phase 4: Scan modules imported by conftest.py and venv plugin files
         // This ensures fixtures defined in separate modules (imported via star import
         // or pytest_plugins variable) are discovered
         self.scan_imported_fixture_modules(root_path);
+        self.save_index_cache(root_path);
 
         info!("Total fixtures defined: {}", self.definitions.len());
         info!("Total files with fixture usages: {}", self.usages.len());
@@ -251,26 +410,26 @@ impl FixtureDatabase {
             .iter()
             .map(|e| e.source_root.clone())
             .collect();
-        let mut files_to_check: Vec<std::path::PathBuf> = self
+        // Analyzed files, including those whose contents were evicted from the cache
+        let analyzed_files: HashSet<std::path::PathBuf> = self
             .file_cache
             .iter()
-            .filter(|entry| {
-                let key = entry.key();
+            .map(|entry| entry.key().clone())
+            .chain(self.imports.iter().map(|entry| entry.key().clone()))
+            .collect();
+        let mut files_to_check: Vec<std::path::PathBuf> = analyzed_files
+            .into_iter()
+            .filter(|key| {
                 let is_conftest_or_test = key
                     .file_name()
                     .and_then(|n| n.to_str())
-                    .map(|n| {
-                        n == "conftest.py"
-                            || (n.starts_with("test_") && n.ends_with(".py"))
-                            || n.ends_with("_test.py")
-                    })
+                    .map(|n| n == "conftest.py" || self.is_test_file_name(n))
                     .unwrap_or(false);
                 let is_venv_plugin = site_packages_paths.iter().any(|sp| key.starts_with(sp));
                 let is_editable_plugin = editable_roots.iter().any(|er| key.starts_with(er));
                 let is_entry_point_plugin = self.plugin_fixture_files.contains_key(key);
                 is_conftest_or_test || is_venv_plugin || is_editable_plugin || is_entry_point_plugin
             })
-            .map(|entry| entry.key().clone())
             .collect();
 
         if files_to_check.is_empty() {
@@ -323,7 +482,7 @@ impl FixtureDatabase {
                 let line_index = self.get_line_index(file_path, &content);
 
                 // Extract imports and pytest_plugins
-                if let rustpython_parser::ast::Mod::Module(module) = parsed.as_ref() {
+                if let ruff_python_ast::Mod::Module(module) = parsed.as_ref() {
                     let imports =
                         self.extract_fixture_imports(&module.body, file_path, &line_index);
 
@@ -345,13 +504,13 @@ impl FixtureDatabase {
                                 self.plugin_fixture_files.insert(canonical.clone(), ());
                                 // If already cached, we need to re-analyze so
                                 // existing definitions get is_plugin=true.
-                                if self.file_cache.contains_key(&canonical) {
+                                if self.is_indexed_file(&canonical) {
                                     reanalyze_as_plugin.insert(canonical.clone());
                                 }
                             }
 
                             if !processed_files.contains(&canonical)
-                                && !self.file_cache.contains_key(&canonical)
+                                && !self.is_indexed_file(&canonical)
                             {
                                 new_modules.insert(canonical);
                             }
@@ -376,13 +535,13 @@ impl FixtureDatabase {
                                 self.plugin_fixture_files.insert(canonical.clone(), ());
                                 // If already cached, we need to re-analyze so
                                 // existing definitions get is_plugin=true.
-                                if self.file_cache.contains_key(&canonical) {
+                                if self.is_indexed_file(&canonical) {
                                     reanalyze_as_plugin.insert(canonical.clone());
                                 }
                             }
 
                             if !processed_files.contains(&canonical)
-                                && !self.file_cache.contains_key(&canonical)
+                                && !self.is_indexed_file(&canonical)
                             {
                                 new_modules.insert(canonical);
                             }
@@ -445,49 +604,190 @@ impl FixtureDatabase {
         );
     }
 
-    /// Scan virtual environment for pytest plugin fixtures.
-    fn scan_venv_fixtures(&self, root_path: &Path) {
+    /// Use `venv_path` (absolute, or relative to the workspace root) as the
+    /// workspace's virtual environment instead of discovering it.
+    pub fn set_venv_path(&self, venv_path: Option<PathBuf>) {
+        *self.venv_path.lock().unwrap() = venv_path;
+    }
+
+    /// Use the environment of the Python interpreter at `python_path`
+    /// (absolute, or relative to the workspace root) instead of the configured
+    /// virtual environment or discovering one.
+    pub fn set_python_path(&self, python_path: Option<PathBuf>) {
+        *self.python_path.lock().unwrap() = python_path;
+    }
+
+    /// The configured Python interpreter of a workspace, when it exists.
+    pub(crate) fn configured_python(&self, root_path: &Path) -> Option<PathBuf> {
+        let configured = self.python_path.lock().unwrap().clone()?;
+        let python = root_path.join(configured);
+        if python.is_file() {
+            Some(python)
+        } else {
+            warn!("Configured Python interpreter does not exist: {:?}", python);
+            None
+        }
+    }
+
+    /// The virtual environment of a workspace: the environment of the
+    /// configured Python interpreter, then the configured virtual environment,
+    /// when they exist, otherwise the one found by [`find_venv`].
+    pub(crate) fn workspace_venv(&self, root_path: &Path) -> Option<PathBuf> {
+        if let Some(python) = self.configured_python(root_path) {
+            match python_prefix(&python).filter(|prefix| prefix.is_dir()) {
+                Some(prefix) => {
+                    info!(
+                        "Using environment {:?} of configured Python interpreter {:?}",
+                        prefix, python
+                    );
+                    return Some(prefix);
+                }
+                None => warn!(
+                    "Could not determine the environment of configured Python interpreter {:?}",
+                    python
+                ),
+            }
+        }
+        if let Some(configured) = self.venv_path.lock().unwrap().as_ref() {
+            let venv_path = root_path.join(configured);
+            if venv_path.is_dir() {
+                info!("Using configured virtual environment: {:?}", venv_path);
+                if find_venv_python(&venv_path).is_none() {
+                    warn!(
+                        "Configured virtual environment {:?} has no Python interpreter",
+                        venv_path
+                    );
+                }
+                return Some(venv_path);
+            }
+            warn!(
+                "Configured virtual environment does not exist: {:?}",
+                venv_path
+            );
+        }
+        find_venv(root_path)
+    }
+
+    /// Scan virtual environment for pytest plugin fixtures, then those of
+    /// the subprojects with an environment of their own.
+    fn scan_venv_fixtures(&self, root_path: &Path, progress: &ScanProgress) {
         info!("Scanning for pytest plugins in virtual environment");
 
-        // Try to find virtual environment
-        let venv_paths = vec![
-            root_path.join(".venv"),
-            root_path.join("venv"),
-            root_path.join("env"),
-        ];
+        let workspace_venv = self.workspace_venv(root_path);
+        match &workspace_venv {
+            Some(venv_path) => {
+                self.scan_venv_site_packages(venv_path, progress);
+            }
+            None => {
+                warn!("No virtual environment found - third-party fixtures will not be available")
+            }
+        }
 
-        info!("Checking for venv in: {:?}", root_path);
-        for venv_path in &venv_paths {
-            debug!("Checking venv path: {:?}", venv_path);
-            if venv_path.exists() {
-                info!("Found virtual environment at: {:?}", venv_path);
-                self.scan_venv_site_packages(venv_path);
-                return;
-            } else {
-                debug!("  Does not exist: {:?}", venv_path);
+        let mut subproject_venvs: Vec<SubprojectVenv> = Vec::new();
+        for (dir, venv) in self.subproject_venv_candidates(root_path) {
+            let venv = venv.canonicalize().unwrap_or(venv);
+            let shared = workspace_venv
+                .as_ref()
+                .is_some_and(|workspace| workspace.canonicalize().ok().as_ref() == Some(&venv));
+            if shared || subproject_venvs.iter().any(|s| s.dir == dir) {
+                continue;
             }
+            info!("Subproject {:?} uses virtual environment {:?}", dir, venv);
+            let site_packages = self.scan_venv_site_packages(&venv, progress);
+            subproject_venvs.push(SubprojectVenv {
+                dir,
+                venv,
+                site_packages,
+            });
         }
+        *self.subproject_venvs.lock().unwrap() = subproject_venvs;
+    }
 
-        // Also check for system-wide VIRTUAL_ENV
-        if let Ok(venv) = std::env::var("VIRTUAL_ENV") {
-            info!("Found VIRTUAL_ENV environment variable: {}", venv);
-            let venv_path = std::path::PathBuf::from(venv);
-            if venv_path.exists() {
-                let venv_path = venv_path.canonicalize().unwrap_or(venv_path);
-                info!("Using VIRTUAL_ENV: {:?}", venv_path);
-                self.scan_venv_site_packages(&venv_path);
-                return;
+    /// Map the subdirectory `dir` (relative to the workspace root) to the
+    /// environment `venv` (relative to the workspace root), in addition to
+    /// those detected in the subprojects of a monorepo.
+    pub fn set_subproject_venv_paths(&self, venvs: Vec<(PathBuf, PathBuf)>) {
+        *self.subproject_venv_paths.lock().unwrap() = venvs;
+    }
+
+    /// The subdirectories of the workspace with an environment of their own,
+    /// deepest first: the configured ones, then the projects of a monorepo
+    /// (see [`FixtureDatabase::pytest_root_for`]) holding one.
+    fn subproject_venv_candidates(&self, root_path: &Path) -> Vec<(PathBuf, PathBuf)> {
+        let mut candidates: Vec<(PathBuf, PathBuf)> = Vec::new();
+        for (dir, venv) in self.subproject_venv_paths.lock().unwrap().iter() {
+            let venv_path = root_path.join(venv);
+            if venv_path.is_dir() {
+                candidates.push((root_path.join(dir), venv_path));
             } else {
-                warn!("VIRTUAL_ENV path does not exist: {:?}", venv_path);
+                warn!(
+                    "Configured virtual environment of {:?} does not exist: {:?}",
+                    dir, venv_path
+                );
             }
-        } else {
-            debug!("No VIRTUAL_ENV environment variable set");
         }
+        let nested_roots = self.nested_pytest_roots.lock().unwrap().clone();
+        for pytest_root in nested_roots {
+            if let Some(venv_path) = find_project_venv(&pytest_root.rootdir) {
+                candidates.push((pytest_root.rootdir, venv_path));
+            }
+        }
+        candidates
+    }
 
-        warn!("No virtual environment found - third-party fixtures will not be available");
+    /// The environment of the subproject `file_path` belongs to, if it has
+    /// its own.
+    pub(crate) fn subproject_venv_for(&self, file_path: &Path) -> Option<PathBuf> {
+        self.subproject_venvs
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|subproject| file_path.starts_with(&subproject.dir))
+            .max_by_key(|subproject| subproject.dir.components().count())
+            .map(|subproject| subproject.venv.clone())
+    }
+
+    /// The site-packages directories whose fixtures are not visible from
+    /// `file_path`: those of the environments of other subprojects, and the
+    /// workspace's when the file's subproject has its own.
+    pub(crate) fn hidden_site_packages(&self, file_path: &Path) -> Vec<PathBuf> {
+        let subproject_venvs = self.subproject_venvs.lock().unwrap();
+        if subproject_venvs.is_empty() {
+            return Vec::new();
+        }
+        let own = subproject_venvs
+            .iter()
+            .filter(|subproject| file_path.starts_with(&subproject.dir))
+            .max_by_key(|subproject| subproject.dir.components().count());
+        let mut hidden: Vec<PathBuf> = subproject_venvs
+            .iter()
+            .filter(|subproject| Some(*subproject) != own)
+            .filter_map(|subproject| subproject.site_packages.clone())
+            .collect();
+        if own.is_some() {
+            let subproject_site_packages: Vec<&PathBuf> = subproject_venvs
+                .iter()
+                .filter_map(|subproject| subproject.site_packages.as_ref())
+                .collect();
+            hidden.extend(
+                self.site_packages_paths
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .filter(|sp| !subproject_site_packages.contains(sp))
+                    .cloned(),
+            );
+        }
+        hidden
     }
 
-    fn scan_venv_site_packages(&self, venv_path: &Path) {
+    /// Scan the site-packages of the environment at `venv_path`, returning
+    /// the site-packages directory scanned.
+    fn scan_venv_site_packages(
+        &self,
+        venv_path: &Path,
+        progress: &ScanProgress,
+    ) -> Option<PathBuf> {
         info!("Scanning venv site-packages in: {:?}", venv_path);
 
         // Attempt to replace the built-in stdlib list with the authoritative set
@@ -528,8 +828,8 @@ impl FixtureDatabase {
                                 .lock()
                                 .unwrap()
                                 .push(site_packages.clone());
-                            self.scan_pytest_plugins(&site_packages);
-                            return;
+                            self.scan_pytest_plugins_with_progress(&site_packages, progress);
+                            return Some(site_packages);
                         }
                     }
                 }
@@ -548,11 +848,12 @@ impl FixtureDatabase {
                 .lock()
                 .unwrap()
                 .push(windows_site_packages.clone());
-            self.scan_pytest_plugins(&windows_site_packages);
-            return;
+            self.scan_pytest_plugins_with_progress(&windows_site_packages, progress);
+            return Some(windows_site_packages);
         }
 
         warn!("Could not find site-packages in venv: {:?}", venv_path);
+        None
     }
 
     /// Parse `entry_points.txt` content and extract pytest11 entries.
@@ -816,12 +1117,15 @@ impl FixtureDatabase {
                 check_name: "FixtureRequest".to_string(),
                 import_statement: "from pytest import FixtureRequest".to_string(),
             }],
+            inferred_return_type: None,
             is_third_party: true,
             is_plugin: true,
             dependencies: vec![],
             scope: FixtureScope::Function,
             yield_line: None,
             autouse: false,
+            class_name: None,
+            params: None,
         };
 
         info!("Registering synthetic 'request' fixture definition");
@@ -1065,7 +1369,12 @@ impl FixtureDatabase {
         None
     }
 
+    #[cfg(test)]
     fn scan_pytest_plugins(&self, site_packages: &Path) {
+        self.scan_pytest_plugins_with_progress(site_packages, &ScanProgress::default());
+    }
+
+    fn scan_pytest_plugins_with_progress(&self, site_packages: &Path, progress: &ScanProgress) {
         info!(
             "Scanning for pytest plugins via entry points in: {:?}",
             site_packages
@@ -1080,19 +1389,25 @@ impl FixtureDatabase {
         self.scan_pytest_internal_fixtures(site_packages);
 
         // Iterate over ALL dist-info directories and check for pytest11 entry points
-        for entry in std::fs::read_dir(site_packages).into_iter().flatten() {
-            let entry = match entry {
-                Ok(e) => e,
-                Err(_) => continue,
-            };
-
-            let path = entry.path();
-            let filename = path.file_name().unwrap_or_default().to_string_lossy();
+        let distributions: Vec<PathBuf> = std::fs::read_dir(site_packages)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                // Only process dist metadata directories
+                let filename = path.file_name().unwrap_or_default().to_string_lossy();
+                filename.ends_with(".dist-info") || filename.ends_with(".egg-info")
+            })
+            .collect();
+        progress.start_phase(ScanPhase::Packages, distributions.len());
 
-            // Only process dist metadata directories
-            if !filename.ends_with(".dist-info") && !filename.ends_with(".egg-info") {
-                continue;
+        for path in distributions {
+            if progress.is_cancelled() {
+                break;
             }
+            progress.advance();
+            let filename = path.file_name().unwrap_or_default().to_string_lossy();
 
             // Try to load plugins from this package's entry points
             let scanned = self.load_plugin_from_entry_point(&path, site_packages);
@@ -1140,6 +1455,71 @@ impl FixtureDatabase {
     }
 }
 
+/// Whether a file name matches pytest's default test file patterns
+/// (`test_*.py` or `*_test.py`).
+fn is_default_test_file_name(name: &str) -> bool {
+    (name.starts_with("test_") && name.ends_with(".py")) || name.ends_with("_test.py")
+}
+
+/// Locate the virtual environment of a project: the environment of its uv
+/// project (see [`find_uv_environment`]), `.venv`, `venv` or `env` in
+/// `root_path`, then the test environment of its Hatch project (see
+/// [`find_hatch_environment`]).
+pub(crate) fn find_project_venv(root_path: &Path) -> Option<PathBuf> {
+    if let Some(venv_path) = find_uv_environment(root_path) {
+        return Some(venv_path);
+    }
+
+    let venv_paths = vec![
+        root_path.join(".venv"),
+        root_path.join("venv"),
+        root_path.join("env"),
+    ];
+
+    info!("Checking for venv in: {:?}", root_path);
+    for venv_path in venv_paths {
+        debug!("Checking venv path: {:?}", venv_path);
+        if venv_path.exists() {
+            info!("Found virtual environment at: {:?}", venv_path);
+            return Some(venv_path);
+        } else {
+            debug!("  Does not exist: {:?}", venv_path);
+        }
+    }
+
+    find_hatch_environment(root_path)
+}
+
+/// Locate the virtual environment for a workspace: the project's own (see
+/// [`find_project_venv`]), then the `VIRTUAL_ENV` environment variable, then
+/// a conda environment (see [`find_conda_env`]), then the interpreter pinned
+/// by pyenv or asdf (see [`find_pinned_python`]), then the environment of a
+/// pytest installed as a uv tool.
+pub(crate) fn find_venv(root_path: &Path) -> Option<PathBuf> {
+    if let Some(venv_path) = find_project_venv(root_path) {
+        return Some(venv_path);
+    }
+
+    // Also check for system-wide VIRTUAL_ENV
+    if let Ok(venv) = std::env::var("VIRTUAL_ENV") {
+        info!("Found VIRTUAL_ENV environment variable: {}", venv);
+        let venv_path = PathBuf::from(venv);
+        if venv_path.exists() {
+            let venv_path = venv_path.canonicalize().unwrap_or(venv_path);
+            info!("Using VIRTUAL_ENV: {:?}", venv_path);
+            return Some(venv_path);
+        } else {
+            warn!("VIRTUAL_ENV path does not exist: {:?}", venv_path);
+        }
+    } else {
+        debug!("No VIRTUAL_ENV environment variable set");
+    }
+
+    find_conda_env(root_path)
+        .or_else(|| find_pinned_python(root_path))
+        .or_else(find_uv_tool_environment)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1411,6 +1791,38 @@ extra = something
         );
     }
 
+    #[test]
+    fn test_scan_progress_and_cancellation() {
+        let temp = tempdir().unwrap();
+        fs::write(
+            temp.path().join("conftest.py"),
+            "import pytest\n\n@pytest.fixture\ndef scanned():\n    pass\n",
+        )
+        .unwrap();
+        fs::write(temp.path().join("test_a.py"), "def test_a():\n    pass\n").unwrap();
+
+        let progress = ScanProgress::default();
+        let db = FixtureDatabase::new();
+        db.scan_workspace_with_progress(temp.path(), &[], true, &progress);
+        assert!(db.definitions.contains_key("scanned"));
+        assert_ne!(progress.snapshot().0, ScanPhase::Discovering);
+
+        // The plugin scan counts the distributions it looks at
+        let site_packages = temp.path().join("site-packages");
+        for dist in ["a-1.0.dist-info", "b-1.0.dist-info", "b"] {
+            fs::create_dir_all(site_packages.join(dist)).unwrap();
+        }
+        db.scan_pytest_plugins_with_progress(&site_packages, &progress);
+        assert_eq!(progress.snapshot(), (ScanPhase::Packages, 2, 2));
+
+        // A cancelled scan analyzes nothing more
+        let progress = ScanProgress::default();
+        progress.cancel();
+        let db = FixtureDatabase::new();
+        db.scan_workspace_with_progress(temp.path(), &[], true, &progress);
+        assert!(!db.definitions.contains_key("scanned"));
+    }
+
     #[test]
     fn test_entry_point_plugin_discovery_integration() {
         // Create mock site-packages structure
@@ -1797,6 +2209,47 @@ def capsys():
         );
     }
 
+    #[test]
+    fn test_configured_venv_preferred_over_discovery() {
+        let temp = tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join(".venv")).unwrap();
+        fs::create_dir_all(root.join("envs").join("py312")).unwrap();
+
+        let db = FixtureDatabase::new();
+        assert_eq!(db.workspace_venv(root), Some(root.join(".venv")));
+
+        db.set_venv_path(Some(PathBuf::from("envs/py312")));
+        assert_eq!(
+            db.workspace_venv(root),
+            Some(root.join("envs").join("py312"))
+        );
+
+        // A configured venv that does not exist falls back to discovery
+        db.set_venv_path(Some(PathBuf::from("missing")));
+        assert_eq!(db.workspace_venv(root), Some(root.join(".venv")));
+    }
+
+    #[test]
+    fn test_configured_python_preferred_over_venv() {
+        let temp = tempdir().unwrap();
+        let root = temp.path();
+        fs::create_dir_all(root.join(".venv")).unwrap();
+        let python = root.join("interpreters").join("bin").join("python3");
+        fs::create_dir_all(python.parent().unwrap()).unwrap();
+        // Can't be run, so its environment is derived from its path
+        fs::write(&python, "").unwrap();
+
+        let db = FixtureDatabase::new();
+        db.set_venv_path(Some(PathBuf::from(".venv")));
+        db.set_python_path(Some(PathBuf::from("interpreters/bin/python3")));
+        assert_eq!(db.workspace_venv(root), Some(root.join("interpreters")));
+
+        // An interpreter that does not exist falls back to the venv
+        db.set_python_path(Some(PathBuf::from("missing/python3")));
+        assert_eq!(db.workspace_venv(root), Some(root.join(".venv")));
+    }
+
     #[test]
     fn test_extract_package_name_from_dist_info() {
         assert_eq!(
@@ -108,14 +108,16 @@ pub(crate) fn extract_word_at_position(line: &str, byte_col: usize) -> Option<St
     Some(line[start..end].to_string())
 }
 
-/// Find the character position of a function name in a line of code.
+/// Find the character position of a function name on 1-based `line` of
+/// `content`, looked up through its line index.
 /// Returns (start_char, end_char) positions.
 pub(crate) fn find_function_name_position(
     content: &str,
+    line_index: &[usize],
     line: usize,
     func_name: &str,
 ) -> (usize, usize) {
-    if let Some(line_content) = content.lines().nth(line.saturating_sub(1)) {
+    if let Some(line_content) = super::FixtureDatabase::line_text(content, line_index, line) {
         // Look for "def function_name" pattern
         if let Some(def_pos) = line_content.find("def ") {
             let after_def = &line_content[def_pos + 4..];
@@ -286,10 +288,20 @@ mod tests {
 
     #[test]
     fn test_find_function_name_position() {
-        let content = "def my_function():\n    pass";
-        let (start, end) = find_function_name_position(content, 1, "my_function");
+        let content = "def my_function():\n    pass\nasync def other():\n";
+        let line_index = super::super::FixtureDatabase::build_line_index(content);
+        let (start, end) = find_function_name_position(content, &line_index, 1, "my_function");
         assert_eq!(start, 4);
         assert_eq!(end, 15);
+        assert_eq!(
+            find_function_name_position(content, &line_index, 3, "other"),
+            (10, 15)
+        );
+        // Past the end of the file
+        assert_eq!(
+            find_function_name_position(content, &line_index, 9, "other"),
+            (0, 5)
+        );
     }
 
     #[test]
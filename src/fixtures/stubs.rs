@@ -0,0 +1,179 @@
+//! Type stubs for third-party fixtures: plugins often ship a `.pyi` next to
+//! a module (or a `<package>-stubs` distribution) annotated more precisely
+//! than their runtime code. The return types of fixtures defined in
+//! site-packages are taken from the stub of their module when there is one.
+
+use super::types::TypeImportSpec;
+use super::FixtureDatabase;
+use ruff_python_ast::Stmt;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info};
+
+/// Collect the function statements of a module, top-level and in (nested)
+/// classes, by their qualified name (`func`, `Class.method`).
+fn collect_functions<'a>(
+    stmts: &'a [Stmt],
+    prefix: &str,
+    functions: &mut HashMap<String, &'a Stmt>,
+) {
+    for stmt in stmts {
+        match stmt {
+            Stmt::FunctionDef(func_def) => {
+                functions
+                    .entry(format!("{}{}", prefix, func_def.name))
+                    .or_insert(stmt);
+            }
+            Stmt::ClassDef(class_def) => collect_functions(
+                &class_def.body,
+                &format!("{}{}.", prefix, class_def.name),
+                functions,
+            ),
+            // Stubs commonly branch on `sys.version_info`; the first branch wins
+            Stmt::If(if_stmt) => {
+                collect_functions(&if_stmt.body, prefix, functions);
+                for clause in &if_stmt.elif_else_clauses {
+                    collect_functions(&clause.body, prefix, functions);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl FixtureDatabase {
+    /// The stub of the module at `file_path`: a `.pyi` beside it, or the
+    /// matching file of a `<package>-stubs` directory in the same
+    /// site-packages.
+    pub(crate) fn stub_path_for(&self, file_path: &Path) -> Option<PathBuf> {
+        let sibling = file_path.with_extension("pyi");
+        if sibling.is_file() {
+            return Some(sibling);
+        }
+
+        let site_packages = file_path
+            .ancestors()
+            .find(|dir| dir.file_name().is_some_and(|n| n == "site-packages"))?;
+        let relative = file_path.strip_prefix(site_packages).ok()?;
+        let mut components = relative.components();
+        let package = components.next()?.as_os_str().to_str()?;
+        let stub = site_packages
+            .join(format!("{}-stubs", package))
+            .join(components.as_path())
+            .with_extension("pyi");
+        stub.is_file().then_some(stub)
+    }
+
+    /// Replace the return types of the fixtures defined by `module` (the AST
+    /// of the third-party `file_path`) with the annotations of their stub,
+    /// resolving the imports they need against the stub's own imports.
+    pub(crate) fn apply_stub_return_types(
+        &self,
+        file_path: &Path,
+        module: &[Stmt],
+        content: &str,
+        line_index: &[usize],
+    ) {
+        let Some(stub_path) = self.stub_path_for(file_path) else {
+            return;
+        };
+        let Ok(stub_content) = std::fs::read_to_string(&stub_path) else {
+            return;
+        };
+        let stub = match super::parse_module(&stub_content) {
+            Ok(ruff_python_ast::Mod::Module(stub)) => stub,
+            Ok(_) => return,
+            Err(e) => {
+                debug!("Failed to parse stub {:?}: {}", stub_path, e);
+                return;
+            }
+        };
+        debug!("Applying stub {:?} to {:?}", stub_path, file_path);
+
+        let mut functions = HashMap::new();
+        collect_functions(module, "", &mut functions);
+        let mut stub_functions = HashMap::new();
+        collect_functions(&stub.body, "", &mut stub_functions);
+
+        // Names in the stub resolve like names of the runtime module
+        let import_map = self.build_name_to_import_map(&stub.body, file_path);
+        let mut module_level_names = HashSet::new();
+        for stmt in &stub.body {
+            self.collect_module_level_names(stmt, &mut module_level_names);
+        }
+        let type_aliases = self.collect_type_aliases(&stub.body, &stub_content);
+
+        // Stub annotations and their imports, keyed by the line of the
+        // runtime function
+        let mut stub_types: HashMap<usize, (String, Vec<TypeImportSpec>)> = HashMap::new();
+        for (name, stmt) in &functions {
+            let (Stmt::FunctionDef(func_def), Some(Stmt::FunctionDef(stub_def))) =
+                (stmt, stub_functions.get(name))
+            else {
+                continue;
+            };
+            // The runtime body tells whether the fixture is a generator
+            let Some(return_type) =
+                self.extract_return_type(&stub_def.returns, &func_def.body, &stub_content)
+            else {
+                continue;
+            };
+            let line = self.get_line_from_offset(
+                super::definition_start(stmt, content).to_usize(),
+                line_index,
+            );
+            let return_type = Self::expand_type_aliases(&return_type, &type_aliases);
+            let imports = self.resolve_return_type_imports(
+                &return_type,
+                &import_map,
+                &module_level_names,
+                file_path,
+            );
+            stub_types.insert(line, (return_type, imports));
+        }
+        if stub_types.is_empty() {
+            return;
+        }
+
+        let Some(names) = self.file_definitions.get(file_path).map(|n| n.clone()) else {
+            return;
+        };
+        for name in names {
+            let Some(mut defs) = self.definitions.get_mut(&name) else {
+                continue;
+            };
+            for def in defs.iter_mut().filter(|def| def.file_path == file_path) {
+                let Some((return_type, imports)) = stub_types.get(&def.line) else {
+                    continue;
+                };
+                info!(
+                    "Using stub return type for fixture '{}': {}",
+                    def.name, return_type
+                );
+                def.return_type = Some(return_type.clone());
+                def.return_type_imports = imports.clone();
+                def.inferred_return_type = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_functions_qualifies_methods() {
+        let module = match super::super::parse_module(
+            "def a(): ...\nclass C:\n    def b(self): ...\n    class D:\n        def c(self): ...\nif X:\n    def d(): ...\n",
+        ) {
+            Ok(ruff_python_ast::Mod::Module(module)) => module,
+            _ => panic!("parse failed"),
+        };
+        let mut functions = HashMap::new();
+        collect_functions(&module.body, "", &mut functions);
+        let mut names: Vec<&str> = functions.keys().map(String::as_str).collect();
+        names.sort();
+        assert_eq!(names, vec!["C.D.c", "C.b", "a", "d"]);
+    }
+}
@@ -0,0 +1,133 @@
+//! Collection of test functions and test classes.
+//!
+//! Mirrors pytest's default collection rules: module-level functions named
+//! `test*`, and classes named `Test*` whose `test*` methods (and nested `Test*`
+//! classes) are collected. Fixture functions are never treated as tests.
+
+use super::decorators;
+use super::string_utils::find_function_name_position;
+use super::types::{TestItem, TestItemKind};
+use super::FixtureDatabase;
+use ruff_python_ast::{Decorator, Stmt};
+use ruff_text_size::Ranged;
+use std::path::Path;
+
+impl FixtureDatabase {
+    /// Get the tests defined in a file, in source order.
+    pub fn get_test_items(&self, file_path: &Path) -> Vec<TestItem> {
+        let Some(content) = self.get_file_content(file_path) else {
+            return Vec::new();
+        };
+        let Some(parsed) = self.get_parsed_ast(file_path, &content) else {
+            return Vec::new();
+        };
+        let line_index = self.get_line_index(file_path, &content);
+
+        let ruff_python_ast::Mod::Module(module) = parsed.as_ref() else {
+            return Vec::new();
+        };
+        self.collect_test_items(&module.body, &content, &line_index, &[])
+    }
+
+    /// `class_params` holds the case IDs of each parametrize mark on the
+    /// enclosing classes, which apply to every test inside them.
+    fn collect_test_items(
+        &self,
+        stmts: &[Stmt],
+        content: &str,
+        line_index: &[usize],
+        class_params: &[Vec<String>],
+    ) -> Vec<TestItem> {
+        let mut items = Vec::new();
+        for stmt in stmts {
+            let (name, kind, decorator_list, children) = match stmt {
+                Stmt::FunctionDef(f) if self.is_test_function(&f.name, &f.decorator_list) => (
+                    f.name.as_str(),
+                    TestItemKind::Function,
+                    &f.decorator_list,
+                    Vec::new(),
+                ),
+                Stmt::ClassDef(c) if c.name.starts_with("Test") => {
+                    let mut params = Self::parametrize_ids(&c.decorator_list, content);
+                    params.extend_from_slice(class_params);
+                    (
+                        c.name.as_str(),
+                        TestItemKind::Class,
+                        &c.decorator_list,
+                        self.collect_test_items(&c.body, content, line_index, &params),
+                    )
+                }
+                _ => continue,
+            };
+
+            let range = stmt.range();
+            let def_start = super::definition_start(stmt, content);
+            let line = self.get_line_from_offset(def_start.to_usize(), line_index);
+            let start_line = decorator_list
+                .first()
+                .map(|d| self.get_line_from_offset(d.range().start().to_usize(), line_index))
+                .unwrap_or(line);
+            let (start_char, end_char) =
+                find_function_name_position(content, line_index, line, name);
+            let cases = match kind {
+                TestItemKind::Function => {
+                    let mut params = Self::parametrize_ids(decorator_list, content);
+                    params.extend_from_slice(class_params);
+                    Self::combine_case_ids(&params)
+                }
+                TestItemKind::Class => Vec::new(),
+            };
+            items.push(TestItem {
+                name: name.to_string(),
+                kind,
+                line,
+                start_line,
+                end_line: self.get_line_from_offset(range.end().to_usize(), line_index),
+                start_char,
+                end_char,
+                cases,
+                children,
+            });
+        }
+        items
+    }
+
+    /// Case IDs of each parametrize decorator, closest to the `def` first
+    /// (the order pytest applies them in).
+    fn parametrize_ids(decorator_list: &[Decorator], content: &str) -> Vec<Vec<String>> {
+        decorator_list
+            .iter()
+            .rev()
+            .filter_map(|d| decorators::extract_parametrize_ids(&d.expression, content))
+            .collect()
+    }
+
+    /// Combine the case IDs of stacked parametrize marks into the IDs pytest
+    /// generates: every combination joined with `-`, the first mark varying slowest.
+    fn combine_case_ids(params: &[Vec<String>]) -> Vec<String> {
+        if params.is_empty() {
+            return Vec::new();
+        }
+        params.iter().fold(vec![String::new()], |acc, ids| {
+            acc.iter()
+                .flat_map(|prefix| {
+                    ids.iter().map(move |id| {
+                        if prefix.is_empty() {
+                            id.clone()
+                        } else {
+                            format!("{}-{}", prefix, id)
+                        }
+                    })
+                })
+                .collect()
+        })
+    }
+
+    fn is_test_function(&self, name: &str, decorator_list: &[Decorator]) -> bool {
+        let aliases = self.fixture_decorator_aliases();
+        name.starts_with("test")
+            && !decorator_list
+                .iter()
+                .any(|d| decorators::is_fixture_decorator(&d.expression, &aliases))
+    }
+}
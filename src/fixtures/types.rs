@@ -1,5 +1,6 @@
 //! Data structures for fixture definitions, usages, and related types.
 
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 
 /// Specifies how to import a type referenced in a fixture's return annotation.
@@ -7,7 +8,7 @@ use std::path::PathBuf;
 /// Resolved at analysis time from the fixture file's own imports, this struct
 /// encodes everything needed to add the correct import statement to a consumer
 /// file (e.g. a test file that declares the fixture as a parameter).
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct TypeImportSpec {
     /// The name to look for in the target file's module-level names set.
     ///
@@ -26,7 +27,9 @@ pub struct TypeImportSpec {
 
 /// Pytest fixture scope, ordered from narrowest to broadest.
 /// A fixture with a broader scope cannot depend on a fixture with a narrower scope.
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
 pub enum FixtureScope {
     /// Function scope (default) - created once per test function
     #[default]
@@ -78,7 +81,7 @@ impl FixtureScope {
 ///     ..Default::default()
 /// };
 /// ```
-#[derive(Debug, Clone, Default, PartialEq)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct FixtureDefinition {
     pub name: String,
     pub file_path: PathBuf,
@@ -88,6 +91,7 @@ pub struct FixtureDefinition {
     pub end_char: usize, // Character position where the fixture name ends (on the line)
     pub docstring: Option<String>,
     pub return_type: Option<String>, // The return type annotation (for generators, the yielded type)
+    pub inferred_return_type: Option<String>, // Type inferred from the body of an unannotated fixture
     pub return_type_imports: Vec<TypeImportSpec>, // Import specs needed to use the return type in another file
     pub is_third_party: bool, // Whether this fixture is from a third-party package (site-packages)
     pub is_plugin: bool, // Whether this fixture was discovered via a pytest11 entry point plugin
@@ -95,6 +99,46 @@ pub struct FixtureDefinition {
     pub scope: FixtureScope, // The fixture's scope (function, class, module, package, session)
     pub yield_line: Option<usize>, // Line number of the yield statement (for generator fixtures)
     pub autouse: bool,   // Whether this fixture has autouse=True
+    pub class_name: Option<String>, // Enclosing test class for fixtures defined as methods (e.g. "TestOuter.TestInner")
+    pub params: Option<FixtureParams>, // The `params=` of a parametrized fixture
+}
+
+/// The parameters of a fixture declared with `@pytest.fixture(params=...)`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FixtureParams {
+    /// The ID of each value, as pytest shows them in test IDs. Empty when
+    /// `params` is not a literal list or tuple.
+    pub ids: Vec<String>,
+    /// The source of `params` when it is not a literal (e.g. `BACKENDS`).
+    pub expression: Option<String>,
+}
+
+impl FixtureParams {
+    /// One-line description, e.g. `parametrized ×2: sqlite, postgres`.
+    pub fn summary(&self) -> String {
+        const MAX_IDS: usize = 8;
+        match &self.expression {
+            Some(expression) => format!("parametrized: {}", expression),
+            None if self.ids.len() > MAX_IDS => format!(
+                "parametrized ×{}: {}, …",
+                self.ids.len(),
+                self.ids[..MAX_IDS].join(", ")
+            ),
+            None => format!("parametrized ×{}: {}", self.ids.len(), self.ids.join(", ")),
+        }
+    }
+}
+
+/// A class statement in a Python file, used to scope fixtures defined as
+/// methods to the tests of that class and its subclasses.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClassScope {
+    /// Dotted name including enclosing classes, e.g. `TestOuter.TestInner`.
+    pub name: String,
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Simple names of the base classes (`Base` for both `Base` and `mod.Base`).
+    pub bases: Vec<String>,
 }
 
 /// A fixture usage (reference) in a Python file.
@@ -102,7 +146,7 @@ pub struct FixtureDefinition {
 /// This struct is `#[non_exhaustive]`: new fields may be added in future versions
 /// without a semver-major bump.
 #[non_exhaustive]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FixtureUsage {
     pub name: String,
     pub file_path: PathBuf,
@@ -111,12 +155,13 @@ pub struct FixtureUsage {
     pub end_char: usize,   // Character position where this usage ends (on the line)
     /// `true` when this usage is a function parameter that can receive a type annotation.
     /// `false` for string-based usages inside `@pytest.mark.usefixtures(...)`,
-    /// `pytestmark = pytest.mark.usefixtures(...)`, or `@pytest.mark.parametrize(..., indirect=...)`.
+    /// `pytestmark = pytest.mark.usefixtures(...)`, `@pytest.mark.parametrize(..., indirect=...)`,
+    /// or `request.getfixturevalue(...)`.
     pub is_parameter: bool,
 }
 
 /// An undeclared fixture used in a function body without being declared as a parameter.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(dead_code)] // Fields used for debugging and future features
 pub struct UndeclaredFixture {
     pub name: String,
@@ -135,6 +180,9 @@ pub struct FixtureCycle {
     pub cycle_path: Vec<String>,
     /// The fixture where the cycle was detected (first fixture in the cycle).
     pub fixture: FixtureDefinition,
+    /// The definition of each fixture of the cycle, in `cycle_path` order
+    /// (without the repeated first one).
+    pub definitions: Vec<FixtureDefinition>,
 }
 
 /// A scope mismatch where a broader-scoped fixture depends on a narrower-scoped fixture.
@@ -146,6 +194,250 @@ pub struct ScopeMismatch {
     pub dependency: FixtureDefinition,
 }
 
+/// Why a name passed to `indirect=` in `@pytest.mark.parametrize` is rejected by pytest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndirectIssue {
+    /// The name is listed in `indirect=[...]` but is not one of the decorator's argnames.
+    NotAnArgname,
+    /// The name is indirect but no fixture with that name is visible from the file.
+    UnknownFixture,
+}
+
+/// An invalid `indirect=` entry in a `@pytest.mark.parametrize` decorator.
+///
+/// The range points at the offending string literal's content: the entry in
+/// `indirect=[...]`, or the argname itself when `indirect=True`.
+#[derive(Debug, Clone)]
+pub struct InvalidIndirect {
+    pub name: String,
+    pub file_path: PathBuf,
+    pub line: usize,
+    pub start_char: usize,
+    pub end_char: usize,
+    pub issue: IndirectIssue,
+}
+
+/// A parameter of a `unittest.TestCase` test method that would have to be a
+/// fixture: pytest calls TestCase test methods without arguments.
+#[derive(Debug, Clone)]
+pub struct TestCaseFixtureParam {
+    pub name: String,
+    /// The `TestCase` subclass the method belongs to.
+    pub class_name: String,
+    pub file_path: PathBuf,
+    pub line: usize,
+    pub start_char: usize,
+    pub end_char: usize,
+}
+
+/// Why a `@pytest.mark.usefixtures` entry has no effect.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedundancyReason {
+    /// The fixture is also a parameter of the decorated function.
+    Parameter,
+    /// The fixture is already listed on the same function.
+    Duplicate,
+}
+
+/// A `@pytest.mark.usefixtures` entry of a function that has no effect.
+///
+/// The range points at the string literal's content. `removal` is the byte
+/// range of the file to delete to drop the entry (the whole decorator when
+/// it is its only entry), when it can be dropped without touching anything
+/// else.
+#[derive(Debug, Clone)]
+pub struct RedundantUsefixture {
+    pub name: String,
+    pub file_path: PathBuf,
+    pub line: usize,
+    pub start_char: usize,
+    pub end_char: usize,
+    pub reason: RedundancyReason,
+    pub removal: Option<(usize, usize)>,
+}
+
+/// A fixture parameter of a test or fixture that its body never references.
+///
+/// `removal` is the byte range of the file to delete to drop the parameter
+/// with its separator, when it can be dropped alone. `usefixtures` is the byte
+/// offset and text of a `@pytest.mark.usefixtures(...)` decorator requesting
+/// the fixture instead, offered for tests of files importing pytest.
+#[derive(Debug, Clone)]
+pub struct UnusedFixtureParam {
+    pub name: String,
+    pub file_path: PathBuf,
+    pub line: usize,
+    pub start_char: usize,
+    pub end_char: usize,
+    pub removal: Option<(usize, usize)>,
+    pub usefixtures: Option<(usize, String)>,
+}
+
+/// A fixture decorated with the deprecated `@pytest.yield_fixture`.
+///
+/// The range points at `yield_fixture`. `replacement` is the byte range of
+/// the file to replace with `fixture`, when the decorator is qualified with
+/// `pytest.` (a `yield_fixture` imported from pytest needs its import
+/// changed too).
+#[derive(Debug, Clone)]
+pub struct DeprecatedYieldFixture {
+    pub name: String,
+    pub file_path: PathBuf,
+    pub line: usize,
+    pub start_char: usize,
+    pub end_char: usize,
+    pub replacement: Option<(usize, usize)>,
+}
+
+/// How a fixture is requested by a test function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InjectionSource {
+    /// An `autouse=True` fixture visible from the test's file.
+    Autouse,
+    /// Listed in `usefixtures` on the test, its class, or the module's `pytestmark`.
+    Usefixtures,
+    /// Declared as a parameter of the test function.
+    Parameter,
+}
+
+impl InjectionSource {
+    /// Get display name for the source
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Autouse => "autouse",
+            Self::Usefixtures => "usefixtures",
+            Self::Parameter => "parameter",
+        }
+    }
+}
+
+/// A fixture injected into a test function.
+#[derive(Debug, Clone)]
+pub struct InjectedFixture {
+    pub name: String,
+    pub source: InjectionSource,
+    /// The definition pytest would use, or `None` if the fixture cannot be resolved.
+    pub definition: Option<FixtureDefinition>,
+}
+
+/// The fixtures a test function receives, in pytest's instantiation order
+/// (autouse, then usefixtures, then parameters).
+#[derive(Debug, Clone)]
+pub struct TestFixtures {
+    pub test_name: String,
+    pub fixtures: Vec<InjectedFixture>,
+}
+
+/// Where a candidate definition stands relative to the requesting file,
+/// following pytest's lookup order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CandidateSource {
+    /// Defined in the requesting file itself.
+    SameFile,
+    /// Defined in a `conftest.py` `distance` directories above the requesting
+    /// file (0 = same directory).
+    Conftest { distance: usize },
+    /// Imported into the `conftest.py` at `conftest`, `distance` directories up.
+    ImportedByConftest { conftest: PathBuf, distance: usize },
+    /// Registered through a `pytest11` entry point of a workspace package.
+    Plugin,
+    /// Installed in site-packages.
+    ThirdParty,
+    /// Not reachable from the requesting file (e.g. a sibling directory's conftest).
+    NotVisible,
+}
+
+/// Where a fixture visible from a file comes from, as grouped by
+/// `pytest --fixtures`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum FixtureOrigin {
+    /// Defined by pytest itself.
+    Builtin,
+    /// Registered by a plugin (a `pytest11` entry point or a package
+    /// installed in site-packages).
+    Plugin,
+    /// Defined in (or imported into) a `conftest.py`.
+    Conftest,
+    /// Defined in (or imported into) the requesting module.
+    Module,
+}
+
+impl FixtureOrigin {
+    /// Get display name for the origin
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Builtin => "builtin",
+            Self::Plugin => "plugin",
+            Self::Conftest => "conftest",
+            Self::Module => "module",
+        }
+    }
+}
+
+/// A file whose analysis panicked: its fixtures and usages are missing from
+/// the index until it analyzes cleanly again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnalysisFailure {
+    /// The panic message.
+    pub message: String,
+    /// Whether the user was already told about it.
+    pub reported: bool,
+}
+
+/// A fixture visible from a file: the definition its name resolves to and
+/// the visible definitions of the same name it overrides, closest first.
+#[derive(Debug, Clone)]
+pub struct VisibleFixture {
+    pub definition: FixtureDefinition,
+    pub origin: FixtureOrigin,
+    pub overrides: Vec<FixtureDefinition>,
+}
+
+/// A definition considered while resolving a fixture usage.
+#[derive(Debug, Clone)]
+pub struct ResolutionCandidate {
+    pub definition: FixtureDefinition,
+    pub source: CandidateSource,
+    pub is_winner: bool,
+}
+
+/// Step-by-step account of how a fixture usage resolves.
+#[derive(Debug, Clone)]
+pub struct ResolutionExplanation {
+    pub fixture_name: String,
+    pub file_path: PathBuf,
+    pub line: usize, // 1-based line of the usage
+    /// Set when the usage is a fixture requesting its own name: that fixture is
+    /// skipped so the definition it overrides is used.
+    pub requester: Option<FixtureDefinition>,
+    /// Candidates in priority order, winner (if any) first.
+    pub candidates: Vec<ResolutionCandidate>,
+}
+
+/// Whether a collected test item is a test function or a test class.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TestItemKind {
+    Function,
+    Class,
+}
+
+/// A test function or test class, as pytest would collect it with its default
+/// `test*` / `Test*` naming rules. Classes hold their tests as `children`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestItem {
+    pub name: String,
+    pub kind: TestItemKind,
+    pub line: usize,       // 1-based line of the `def` / `class` keyword
+    pub start_line: usize, // 1-based line of the first decorator, or `line` if undecorated
+    pub end_line: usize,   // 1-based line where the body ends
+    pub start_char: usize, // Character position where the name starts (on `line`)
+    pub end_char: usize,   // Character position where the name ends (on `line`)
+    /// IDs of the parametrized cases (`test_x[1-a]` → `"1-a"`), including those from the
+    /// enclosing classes' parametrize marks. Empty for tests that aren't parametrized.
+    pub cases: Vec<String>,
+    pub children: Vec<TestItem>,
+}
+
 /// Context for code completion.
 #[derive(Debug, Clone, PartialEq)]
 pub enum CompletionContext {
@@ -171,6 +463,11 @@ pub enum CompletionContext {
     UsefixturesDecorator,
     /// Inside @pytest.mark.parametrize(..., indirect=...) - suggest fixture names as strings.
     ParametrizeIndirect,
+    /// Inside the string argument of `request.getfixturevalue("...")` - suggest fixture names.
+    GetfixturevalueArgument,
+    /// Inside the string argument of pytest-lazy-fixture's `lazy_fixture("...")` (or
+    /// pytest-lazy-fixtures' `lf("...")`) - suggest fixture names.
+    LazyFixtureArgument,
 }
 
 /// Information about where to insert a new parameter in a function signature.
@@ -201,3 +498,16 @@ pub struct ParamInsertionInfo {
     /// classic `, <param>` / `<param>` text applies.
     pub multiline_indent: Option<String>,
 }
+
+/// A text replacement that sets a fixture's `scope=` argument on its decorator.
+///
+/// Positions use the internal conventions: 1-indexed lines, byte columns.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScopeEdit {
+    pub start_line: usize,
+    pub start_char: usize,
+    pub end_line: usize,
+    pub end_char: usize,
+    /// Replacement text (e.g. `"module"`, `, scope="module"` or `(scope="module")`).
+    pub new_text: String,
+}
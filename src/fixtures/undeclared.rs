@@ -5,7 +5,7 @@
 
 use super::types::UndeclaredFixture;
 use super::FixtureDatabase;
-use rustpython_parser::ast::{Expr, Stmt};
+use ruff_python_ast::{Expr, InterpolatedStringElement, Stmt};
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use tracing::info;
@@ -23,7 +23,7 @@ pub(crate) struct BodyScanContext<'a> {
 
 /// Collect names bound by walrus (`:=`) expressions anywhere inside `expr`.
 fn collect_walrus_targets(expr: &Expr, names: &mut HashSet<String>) {
-    if let Expr::NamedExpr(named) = expr {
+    if let Expr::Named(named) = expr {
         if let Expr::Name(name) = named.target.as_ref() {
             names.insert(name.id.to_string());
         }
@@ -49,13 +49,17 @@ fn collect_walrus_targets(expr: &Expr, names: &mut HashSet<String>) {
         }
         Expr::Call(e) => {
             collect_walrus_targets(&e.func, names);
-            e.args.iter().for_each(|a| collect_walrus_targets(a, names));
-            e.keywords
+            e.arguments
+                .args
+                .iter()
+                .for_each(|a| collect_walrus_targets(a, names));
+            e.arguments
+                .keywords
                 .iter()
                 .for_each(|kw| collect_walrus_targets(&kw.value, names));
         }
         Expr::Tuple(e) => e.elts.iter().for_each(|v| collect_walrus_targets(v, names)),
-        Expr::IfExp(e) => {
+        Expr::If(e) => {
             collect_walrus_targets(&e.test, names);
             collect_walrus_targets(&e.body, names);
             collect_walrus_targets(&e.orelse, names);
@@ -154,16 +158,6 @@ impl FixtureDatabase {
                     }
                     self.collect_local_variables(&for_stmt.body, line_index, local_vars);
                 }
-                Stmt::AsyncFor(for_stmt) => {
-                    let line =
-                        self.get_line_from_offset(for_stmt.range.start().to_usize(), line_index);
-                    let mut temp_names = HashSet::new();
-                    self.collect_names_from_expr(&for_stmt.target, &mut temp_names);
-                    for name in temp_names {
-                        local_vars.insert(name, line);
-                    }
-                    self.collect_local_variables(&for_stmt.body, line_index, local_vars);
-                }
                 Stmt::While(while_stmt) => {
                     let line =
                         self.get_line_from_offset(while_stmt.range.start().to_usize(), line_index);
@@ -183,23 +177,11 @@ impl FixtureDatabase {
                         local_vars.insert(name, line);
                     }
                     self.collect_local_variables(&if_stmt.body, line_index, local_vars);
-                    self.collect_local_variables(&if_stmt.orelse, line_index, local_vars);
-                }
-                Stmt::With(with_stmt) => {
-                    let line =
-                        self.get_line_from_offset(with_stmt.range.start().to_usize(), line_index);
-                    for item in &with_stmt.items {
-                        if let Some(ref optional_vars) = item.optional_vars {
-                            let mut temp_names = HashSet::new();
-                            self.collect_names_from_expr(optional_vars, &mut temp_names);
-                            for name in temp_names {
-                                local_vars.insert(name, line);
-                            }
-                        }
+                    for clause in &if_stmt.elif_else_clauses {
+                        self.collect_local_variables(&clause.body, line_index, local_vars);
                     }
-                    self.collect_local_variables(&with_stmt.body, line_index, local_vars);
                 }
-                Stmt::AsyncWith(with_stmt) => {
+                Stmt::With(with_stmt) => {
                     let line =
                         self.get_line_from_offset(with_stmt.range.start().to_usize(), line_index);
                     for item in &with_stmt.items {
@@ -245,8 +227,13 @@ impl FixtureDatabase {
                 for stmt in &if_stmt.body {
                     self.visit_stmt_for_names(stmt, ctx);
                 }
-                for stmt in &if_stmt.orelse {
-                    self.visit_stmt_for_names(stmt, ctx);
+                for clause in &if_stmt.elif_else_clauses {
+                    if let Some(ref test) = clause.test {
+                        self.visit_expr_for_names(test, ctx);
+                    }
+                    for stmt in &clause.body {
+                        self.visit_stmt_for_names(stmt, ctx);
+                    }
                 }
             }
             Stmt::While(while_stmt) => {
@@ -275,20 +262,6 @@ impl FixtureDatabase {
                     self.visit_stmt_for_names(stmt, ctx);
                 }
             }
-            Stmt::AsyncFor(for_stmt) => {
-                self.visit_expr_for_names(&for_stmt.iter, ctx);
-                for stmt in &for_stmt.body {
-                    self.visit_stmt_for_names(stmt, ctx);
-                }
-            }
-            Stmt::AsyncWith(with_stmt) => {
-                for item in &with_stmt.items {
-                    self.visit_expr_for_names(&item.context_expr, ctx);
-                }
-                for stmt in &with_stmt.body {
-                    self.visit_stmt_for_names(stmt, ctx);
-                }
-            }
             Stmt::Assert(assert_stmt) => {
                 self.visit_expr_for_names(&assert_stmt.test, ctx);
                 if let Some(ref msg) = assert_stmt.msg {
@@ -313,7 +286,7 @@ impl FixtureDatabase {
                     self.visit_stmt_for_names(stmt, ctx);
                 }
                 for handler in &try_stmt.handlers {
-                    let rustpython_parser::ast::ExceptHandler::ExceptHandler(h) = handler;
+                    let ruff_python_ast::ExceptHandler::ExceptHandler(h) = handler;
                     for stmt in &h.body {
                         self.visit_stmt_for_names(stmt, ctx);
                     }
@@ -388,7 +361,7 @@ impl FixtureDatabase {
             }
             Expr::Call(call) => {
                 self.visit_expr_for_names(&call.func, ctx);
-                for arg in &call.args {
+                for arg in &call.arguments.args {
                     self.visit_expr_for_names(arg, ctx);
                 }
             }
@@ -423,11 +396,11 @@ impl FixtureDatabase {
                 }
             }
             Expr::Dict(dict) => {
-                for k in dict.keys.iter().flatten() {
-                    self.visit_expr_for_names(k, ctx);
-                }
-                for value in &dict.values {
-                    self.visit_expr_for_names(value, ctx);
+                for item in &dict.items {
+                    if let Some(ref key) = item.key {
+                        self.visit_expr_for_names(key, ctx);
+                    }
+                    self.visit_expr_for_names(&item.value, ctx);
                 }
             }
             Expr::Await(await_expr) => {
@@ -438,26 +411,25 @@ impl FixtureDatabase {
                     self.visit_expr_for_names(value, ctx);
                 }
             }
-            Expr::IfExp(if_exp) => {
+            Expr::If(if_exp) => {
                 self.visit_expr_for_names(&if_exp.test, ctx);
                 self.visit_expr_for_names(&if_exp.body, ctx);
                 self.visit_expr_for_names(&if_exp.orelse, ctx);
             }
-            Expr::NamedExpr(named) => {
+            Expr::Named(named) => {
                 // Only the value is a read; the walrus target is a binding.
                 self.visit_expr_for_names(&named.value, ctx);
             }
             Expr::Starred(starred) => {
                 self.visit_expr_for_names(&starred.value, ctx);
             }
-            Expr::JoinedStr(joined) => {
-                for value in &joined.values {
-                    self.visit_expr_for_names(value, ctx);
+            Expr::FString(fstring) => {
+                for element in fstring.value.elements() {
+                    if let InterpolatedStringElement::Interpolation(interpolation) = element {
+                        self.visit_expr_for_names(&interpolation.expression, ctx);
+                    }
                 }
             }
-            Expr::FormattedValue(formatted) => {
-                self.visit_expr_for_names(&formatted.value, ctx);
-            }
             Expr::Set(set) => {
                 for elt in &set.elts {
                     self.visit_expr_for_names(elt, ctx);
@@ -484,7 +456,7 @@ impl FixtureDatabase {
                     self.visit_expr_for_names(&generator.iter, ctx);
                 }
             }
-            Expr::GeneratorExp(comp) => {
+            Expr::Generator(comp) => {
                 for generator in &comp.generators {
                     self.visit_expr_for_names(&generator.iter, ctx);
                 }
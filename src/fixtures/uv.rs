@@ -0,0 +1,153 @@
+//! uv project and tool environment discovery.
+//!
+//! `uv run pytest` runs in the environment of the project's workspace: the
+//! `.venv` at the workspace root (the directory holding `uv.lock`, or the
+//! `pyproject.toml` declaring `[tool.uv.workspace]`), or
+//! `$UV_PROJECT_ENVIRONMENT` when set. From a workspace member, that is the
+//! `.venv` of a parent directory, not of the member. Outside of a project,
+//! a pytest installed with `uv tool install pytest` has its own environment
+//! in uv's tool directory.
+
+use super::env_path;
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+/// The `tool.uv` table of the `pyproject.toml` in `dir`, if any.
+fn uv_table(dir: &Path) -> Option<toml::Value> {
+    let content = std::fs::read_to_string(dir.join("pyproject.toml")).ok()?;
+    let value: toml::Value = toml::from_str(&content).ok()?;
+    value.get("tool")?.get("uv").cloned()
+}
+
+/// The root of the uv workspace `root_path` belongs to: the nearest
+/// directory holding `uv.lock` or declaring `[tool.uv.workspace]`, otherwise
+/// the nearest project configuring `[tool.uv]`.
+pub(crate) fn find_uv_workspace_root(root_path: &Path) -> Option<PathBuf> {
+    let workspace = root_path.ancestors().find(|dir| {
+        dir.join("uv.lock").is_file()
+            || uv_table(dir).is_some_and(|uv| uv.get("workspace").is_some())
+    });
+    workspace
+        .or_else(|| root_path.ancestors().find(|dir| uv_table(dir).is_some()))
+        .map(Path::to_path_buf)
+}
+
+/// The environment uv creates for the workspace at `workspace_root`:
+/// `project_environment` (`$UV_PROJECT_ENVIRONMENT`, absolute or relative to
+/// the workspace root) when set, otherwise its `.venv`.
+pub(crate) fn uv_project_environment(
+    workspace_root: &Path,
+    project_environment: Option<PathBuf>,
+) -> PathBuf {
+    match project_environment {
+        Some(path) => workspace_root.join(path),
+        None => workspace_root.join(".venv"),
+    }
+}
+
+/// Locate the environment `uv run` would use for a workspace, when it is a
+/// uv project and the environment was created.
+pub(crate) fn find_uv_environment(root_path: &Path) -> Option<PathBuf> {
+    let workspace_root = find_uv_workspace_root(root_path)?;
+    debug!("Found uv workspace at: {:?}", workspace_root);
+    let env_path = uv_project_environment(&workspace_root, env_path("UV_PROJECT_ENVIRONMENT"));
+    if env_path.is_dir() {
+        info!("Using uv project environment: {:?}", env_path);
+        Some(env_path)
+    } else {
+        warn!(
+            "uv project environment does not exist: {:?}; run `uv sync`",
+            env_path
+        );
+        None
+    }
+}
+
+/// The directory uv installs tools in: `$UV_TOOL_DIR`, otherwise
+/// `$XDG_DATA_HOME/uv/tools`, `~/.local/share/uv/tools`, or
+/// `%APPDATA%\uv\data\tools` on Windows.
+pub(crate) fn uv_tool_dir() -> Option<PathBuf> {
+    if let Some(tool_dir) = env_path("UV_TOOL_DIR") {
+        return Some(tool_dir);
+    }
+    let data_dir = if cfg!(windows) {
+        env_path("APPDATA")?.join("uv").join("data")
+    } else if let Some(xdg) = env_path("XDG_DATA_HOME") {
+        xdg.join("uv")
+    } else {
+        env_path("HOME")?.join(".local").join("share").join("uv")
+    };
+    Some(data_dir.join("tools"))
+}
+
+/// The environment of a pytest installed with `uv tool install pytest`.
+pub(crate) fn find_uv_tool_environment() -> Option<PathBuf> {
+    let env_path = uv_tool_dir()?.join("pytest");
+    if !env_path.is_dir() {
+        return None;
+    }
+    info!("Using uv tool environment of pytest: {:?}", env_path);
+    Some(env_path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_find_uv_workspace_root_from_member() {
+        let root = tempdir().unwrap();
+        let member = root.path().join("packages").join("api");
+        fs::create_dir_all(&member).unwrap();
+        fs::write(
+            root.path().join("pyproject.toml"),
+            "[tool.uv.workspace]\nmembers = [\"packages/*\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            member.join("pyproject.toml"),
+            "[project]\nname = \"api\"\n\n[tool.uv]\ndev-dependencies = [\"pytest\"]\n",
+        )
+        .unwrap();
+
+        // The workspace declaration wins over the member's own `[tool.uv]`
+        assert_eq!(
+            find_uv_workspace_root(&member),
+            Some(root.path().to_path_buf())
+        );
+    }
+
+    #[test]
+    fn test_find_uv_workspace_root() {
+        let root = tempdir().unwrap();
+        let project = root.path().join("project");
+        fs::create_dir_all(project.join("tests")).unwrap();
+        fs::write(project.join("pyproject.toml"), "[project]\nname = \"p\"\n").unwrap();
+        assert_eq!(find_uv_workspace_root(&project), None);
+
+        fs::write(project.join("uv.lock"), "version = 1\n").unwrap();
+        assert_eq!(
+            find_uv_workspace_root(&project.join("tests")),
+            Some(project.clone())
+        );
+    }
+
+    #[test]
+    fn test_uv_project_environment() {
+        let root = Path::new("/work/repo");
+        assert_eq!(
+            uv_project_environment(root, None),
+            PathBuf::from("/work/repo/.venv")
+        );
+        assert_eq!(
+            uv_project_environment(root, Some(PathBuf::from("build/env"))),
+            PathBuf::from("/work/repo/build/env")
+        );
+        assert_eq!(
+            uv_project_environment(root, Some(PathBuf::from("/opt/env"))),
+            PathBuf::from("/opt/env")
+        );
+    }
+}
@@ -57,13 +57,24 @@ mod providers;
 
 pub use config::Config;
 pub use fixtures::{
-    CompletionContext, FixtureCycle, FixtureDatabase, FixtureDefinition, FixtureScope,
-    FixtureUsage, ParamInsertionInfo, ScopeMismatch, TypeImportSpec, UndeclaredFixture,
+    CandidateSource, CompletionContext, DeprecatedYieldFixture, Finding, Fix, FixEdit,
+    FixtureCycle, FixtureDatabase, FixtureDefinition, FixtureGraph, FixtureOrigin, FixtureParams,
+    FixtureScope, FixtureTree, FixtureUsage, FixtureUse, FixtureUses, GraphFormat, GraphNode,
+    GraphNodeKind, IndirectIssue, InjectedFixture, InjectionSource, InvalidIndirect,
+    ParamInsertionInfo, RedundancyReason, RedundantUsefixture, RelatedLocation,
+    ResolutionCandidate, ResolutionExplanation, ScanPhase, ScanProgress, ScopeEdit, ScopeMismatch,
+    TestCaseFixtureParam, TestFixtures, TestItem, TestItemKind, TreeNode, TypeImportSpec,
+    UndeclaredFixture, UnusedFixtureParam, VisibleFixture,
 };
 
 // Expose decorators module for testing
 #[cfg(test)]
 pub use fixtures::decorators;
 
-// Expose Backend for integration testing
-pub use providers::Backend;
+// Expose Backend and the extension request types for integration testing
+pub use providers::catch_panic::CatchPanic;
+pub use providers::test_discovery::{
+    DiscoverTestsParams, TestNode, TestNodeKind, DISCOVER_TESTS_METHOD,
+};
+pub use providers::trace::TraceService;
+pub use providers::{Backend, PositionEncoding};
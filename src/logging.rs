@@ -0,0 +1,324 @@
+//! Logging of the language server: to stderr or to a log file rotated by size,
+//! as text or JSON lines.
+
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::time::{FormatTime, SystemTime};
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::EnvFilter;
+
+/// Level used when neither `--log-level` nor `RUST_LOG` is set.
+const DEFAULT_LEVEL: &str = "warn";
+
+/// Rotated log files kept besides the current one (`server.log.1` ...).
+const LOG_BACKUPS: usize = 3;
+
+/// Format of the log lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human-readable lines.
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" | "pretty" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => Err(format!(
+                "unknown log format '{}', expected 'text' or 'json'",
+                other
+            )),
+        }
+    }
+}
+
+/// Where and how the server logs.
+#[derive(Debug, Clone)]
+pub struct LogOptions {
+    /// Log file; stderr when `None`.
+    pub file: Option<PathBuf>,
+    /// Level or `RUST_LOG`-style filter directives; `RUST_LOG` when `None`.
+    pub level: Option<String>,
+    pub format: LogFormat,
+    /// Size, in bytes, past which the log file is rotated.
+    pub max_size: u64,
+}
+
+/// Install the global subscriber logging as `options` say.
+pub fn init(options: &LogOptions) -> Result<(), String> {
+    let filter = match &options.level {
+        Some(level) => EnvFilter::try_new(level)
+            .map_err(|e| format!("invalid log level '{}': {}", level, e))?,
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(DEFAULT_LEVEL)),
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_ansi(false)
+        .with_env_filter(filter);
+
+    let result = match (&options.file, options.format) {
+        (None, LogFormat::Text) => builder.with_writer(io::stderr).try_init(),
+        (None, LogFormat::Json) => builder
+            .event_format(JsonFormat)
+            .with_writer(io::stderr)
+            .try_init(),
+        (Some(path), format) => {
+            let file = RotatingFile::open(path, options.max_size)
+                .map_err(|e| format!("cannot open log file {}: {}", path.display(), e))?;
+            let writer = std::sync::Mutex::new(file);
+            match format {
+                LogFormat::Text => builder.with_writer(writer).try_init(),
+                LogFormat::Json => builder
+                    .event_format(JsonFormat)
+                    .with_writer(writer)
+                    .try_init(),
+            }
+        }
+    };
+    result.map_err(|e| e.to_string())
+}
+
+/// A log file renamed to `<name>.1` (shifting older ones up to
+/// [`LOG_BACKUPS`]) when writing to it would grow it past `max_size` bytes.
+struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    file: File,
+    size: u64,
+}
+
+impl RotatingFile {
+    fn open(path: &Path, max_size: u64) -> io::Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            max_size,
+            file,
+            size,
+        })
+    }
+
+    fn backup(&self, index: usize) -> PathBuf {
+        let mut name = self.path.clone().into_os_string();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        for index in (1..LOG_BACKUPS).rev() {
+            let from = self.backup(index);
+            if from.exists() {
+                std::fs::rename(&from, self.backup(index + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, self.backup(1))?;
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    /// Each event is written at once, so it never straddles two files.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Formats events as JSON objects: `timestamp`, `level`, `target`,
+/// `message`, the other `fields` and the enclosing `spans`.
+struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        let mut timestamp = String::new();
+        SystemTime.format_time(&mut Writer::new(&mut timestamp))?;
+
+        let mut fields = JsonFields::default();
+        event.record(&mut fields);
+        let mut fields = fields.0;
+
+        let metadata = event.metadata();
+        let mut object = serde_json::Map::new();
+        object.insert("timestamp".into(), timestamp.into());
+        object.insert("level".into(), metadata.level().as_str().into());
+        object.insert("target".into(), metadata.target().into());
+        if let Some(message) = fields.remove("message") {
+            object.insert("message".into(), message);
+        }
+        if !fields.is_empty() {
+            object.insert("fields".into(), fields.into());
+        }
+        if let Some(scope) = ctx.event_scope() {
+            let spans: Vec<serde_json::Value> =
+                scope.from_root().map(|span| span.name().into()).collect();
+            object.insert("spans".into(), spans.into());
+        }
+        writeln!(writer, "{}", serde_json::Value::Object(object))
+    }
+}
+
+/// Collects the fields of an event as JSON values.
+#[derive(Default)]
+struct JsonFields(serde_json::Map<String, serde_json::Value>);
+
+impl Visit for JsonFields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().into(), format!("{:?}", value).into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().into(), value.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_rotating_file() {
+        let tmp = tempdir().unwrap();
+        let path = tmp.path().join("logs").join("server.log");
+        let mut file = RotatingFile::open(&path, 10).unwrap();
+
+        file.write_all(b"first\n").unwrap();
+        file.write_all(b"second\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "second\n");
+        assert_eq!(
+            std::fs::read_to_string(tmp.path().join("logs/server.log.1")).unwrap(),
+            "first\n"
+        );
+
+        // A single line larger than the limit still goes to a file of its own
+        file.write_all(b"a long third line\n").unwrap();
+        for line in ["fourth\n", "fifth\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fifth\n");
+        let backups: Vec<String> = (1..=LOG_BACKUPS)
+            .map(|index| std::fs::read_to_string(file.backup(index)).unwrap())
+            .collect();
+        assert_eq!(backups, vec!["fourth\n", "a long third line\n", "second\n"]);
+        assert!(!file.backup(LOG_BACKUPS + 1).exists());
+
+        // Appends to an existing log
+        drop(file);
+        let mut file = RotatingFile::open(&path, 100).unwrap();
+        file.write_all(b"sixth\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "fifth\nsixth\n");
+    }
+
+    #[test]
+    fn test_json_format() {
+        use tracing_subscriber::fmt::MakeWriter;
+
+        /// Collects the output in memory.
+        #[derive(Clone, Default)]
+        struct Buffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+        impl Write for Buffer {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        impl<'a> MakeWriter<'a> for Buffer {
+            type Writer = Buffer;
+
+            fn make_writer(&'a self) -> Self::Writer {
+                self.clone()
+            }
+        }
+
+        let buffer = Buffer::default();
+        let subscriber = tracing_subscriber::fmt()
+            .event_format(JsonFormat)
+            .with_writer(buffer.clone())
+            .finish();
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("scan");
+            let _guard = span.enter();
+            tracing::warn!(files = 3, path = "tests", "Scan took {}ms", 12);
+        });
+
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        let json: serde_json::Value = serde_json::from_str(output.trim_end()).unwrap();
+        assert_eq!(json["level"], "WARN");
+        assert_eq!(json["message"], "Scan took 12ms");
+        assert_eq!(
+            json["fields"],
+            serde_json::json!({"files": 3, "path": "tests"})
+        );
+        assert_eq!(json["spans"], serde_json::json!(["scan"]));
+        assert!(json["target"].as_str().unwrap().ends_with("logging::tests"));
+        assert!(json["timestamp"].as_str().is_some());
+    }
+
+    #[test]
+    fn test_log_format_from_str() {
+        assert_eq!("JSON".parse::<LogFormat>(), Ok(LogFormat::Json));
+        assert_eq!("pretty".parse::<LogFormat>(), Ok(LogFormat::Text));
+        assert!("xml".parse::<LogFormat>().is_err());
+    }
+}
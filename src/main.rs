@@ -1,13 +1,20 @@
+mod check;
 mod config;
+mod doctor;
 mod fixtures;
+mod logging;
 mod providers;
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use fixtures::FixtureDatabase;
+use providers::catch_panic::CatchPanic;
+use providers::test_discovery::DISCOVER_TESTS_METHOD;
+use providers::trace::TraceService;
 use providers::Backend;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use tower_lsp_server::ls_types::notification::{Notification, SetTrace, WorkDoneProgressCancel};
 use tower_lsp_server::{LspService, Server};
 use tracing::info;
 
@@ -16,9 +23,110 @@ use tracing::info;
 #[command(name = "pytest-language-server")]
 #[command(version = env!("CARGO_PKG_VERSION"))]
 #[command(about = "A Language Server Protocol implementation for pytest", long_about = None)]
+#[command(args_conflicts_with_subcommands = true)]
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+
+    /// Serve a client connecting to this TCP address ("host:port", or a
+    /// port on 127.0.0.1) instead of stdio
+    #[arg(long, value_name = "ADDR", conflicts_with_all = ["connect", "pipe"])]
+    listen: Option<String>,
+
+    /// Serve over a TCP connection to a client listening at this address
+    /// ("host:port", or a port on 127.0.0.1) instead of stdio
+    #[arg(long, value_name = "ADDR", conflicts_with = "pipe")]
+    connect: Option<String>,
+
+    /// Serve over the named pipe (Unix domain socket outside Windows) the
+    /// client created with this name, like VS Code's pipe transport
+    #[arg(long, value_name = "NAME")]
+    pipe: Option<String>,
+
+    #[command(flatten)]
+    log: LogArgs,
+}
+
+/// Logging options of the language server.
+#[derive(Args)]
+struct LogArgs {
+    /// Write logs to this file, rotated by size, instead of stderr
+    /// [env: PYTEST_LANGUAGE_SERVER_LOG_FILE]
+    #[arg(long, value_name = "PATH")]
+    log_file: Option<PathBuf>,
+
+    /// Log level ("error", "warn", "info", "debug" or "trace") or RUST_LOG
+    /// filter directives [env: PYTEST_LANGUAGE_SERVER_LOG_LEVEL, then
+    /// RUST_LOG; default: warn]
+    #[arg(long, value_name = "LEVEL")]
+    log_level: Option<String>,
+
+    /// Log format: "text" (default) or "json"
+    /// [env: PYTEST_LANGUAGE_SERVER_LOG_FORMAT]
+    #[arg(long, value_name = "FORMAT")]
+    log_format: Option<String>,
+
+    /// Size, in MiB, past which the log file is rotated (default: 10)
+    /// [env: PYTEST_LANGUAGE_SERVER_LOG_MAX_SIZE]
+    #[arg(long, value_name = "MIB")]
+    log_max_size: Option<u64>,
+}
+
+impl LogArgs {
+    /// The logging options, from the command line or else the environment.
+    fn options(&self) -> Result<logging::LogOptions, String> {
+        let env = |name: &str| {
+            std::env::var(format!("PYTEST_LANGUAGE_SERVER_{}", name))
+                .ok()
+                .filter(|value| !value.is_empty())
+        };
+        let format = match self.log_format.clone().or_else(|| env("LOG_FORMAT")) {
+            Some(format) => format.parse()?,
+            None => logging::LogFormat::Text,
+        };
+        let max_size = match self.log_max_size {
+            Some(max_size) => max_size,
+            None => match env("LOG_MAX_SIZE") {
+                Some(max_size) => max_size
+                    .parse()
+                    .map_err(|_| format!("invalid log file size '{}'", max_size))?,
+                None => DEFAULT_LOG_MAX_SIZE_MB,
+            },
+        };
+        Ok(logging::LogOptions {
+            file: self
+                .log_file
+                .clone()
+                .or_else(|| env("LOG_FILE").map(PathBuf::from)),
+            level: self.log_level.clone().or_else(|| env("LOG_LEVEL")),
+            format,
+            max_size: max_size * 1024 * 1024,
+        })
+    }
+}
+
+/// Size, in MiB, past which the log file is rotated by default.
+const DEFAULT_LOG_MAX_SIZE_MB: u64 = 10;
+
+/// How the language server talks to its client.
+enum Transport {
+    Stdio,
+    /// Accept a single client on this address.
+    Listen(String),
+    /// Connect to a client listening on this address.
+    Connect(String),
+    /// Connect to the named pipe or Unix domain socket the client created.
+    Pipe(String),
+}
+
+impl Transport {
+    /// A bare port means localhost, as editors usually configure it.
+    fn address(addr: &str) -> String {
+        match addr.parse::<u16>() {
+            Ok(port) => format!("127.0.0.1:{}", port),
+            Err(_) => addr.to_string(),
+        }
+    }
 }
 
 #[derive(Subcommand)]
@@ -28,6 +136,56 @@ enum Commands {
         #[command(subcommand)]
         command: FixtureCommands,
     },
+    /// Run every diagnostic rule over a directory (exits with code 1 if
+    /// problems are found)
+    Check(CheckArgs),
+    /// Print what the server detects in a workspace (pytest rootdir, Python
+    /// environment, plugins, settings), for troubleshooting
+    Doctor {
+        /// Workspace directory
+        #[arg(default_value = ".")]
+        path: PathBuf,
+    },
+}
+
+#[derive(Args)]
+struct CheckArgs {
+    /// Directory containing test files, or files to check within the
+    /// current directory (as pre-commit passes them)
+    #[arg(default_value = ".")]
+    paths: Vec<PathBuf>,
+
+    /// Output format: "text" (default), "json", "sarif", "github" or "junit"
+    #[arg(long, default_value = "text")]
+    format: String,
+
+    /// Lowest severity failing the check: "error", "warning",
+    /// "information", "hint" (default, any problem) or "none"
+    #[arg(long, default_value = "hint", value_name = "SEVERITY")]
+    fail_on: String,
+
+    /// Also fail when there are more than this many warnings
+    #[arg(long, value_name = "N")]
+    max_warnings: Option<usize>,
+
+    /// Baseline file: record the problems found in it, or with
+    /// --baseline-check, report only the problems not recorded in it
+    #[arg(long, value_name = "FILE")]
+    baseline: Option<PathBuf>,
+
+    /// Report only the problems missing from the --baseline file
+    #[arg(long, requires = "baseline")]
+    baseline_check: bool,
+
+    /// Apply the fixes of the problems that have a safe one, then report
+    /// the remaining problems
+    #[arg(long, conflicts_with = "diff")]
+    fix: bool,
+
+    /// Print the fixes --fix would apply as a unified diff, without
+    /// writing them (exits with code 1 if there are any)
+    #[arg(long)]
+    diff: bool,
 }
 
 #[derive(Subcommand)]
@@ -50,6 +208,52 @@ enum FixtureCommands {
         /// Path to the directory containing test files
         path: PathBuf,
 
+        /// Output format: "text" (default) or "json"
+        #[arg(long, default_value = "text")]
+        format: String,
+    },
+    /// Export the fixture dependency graph as DOT or Mermaid
+    Graph {
+        /// Path to the directory containing test files
+        path: PathBuf,
+
+        /// Output format: "dot" (default) or "mermaid"
+        #[arg(long, default_value = "dot")]
+        format: String,
+
+        /// Only show the graph around this fixture name or test node ID
+        /// (e.g. "db" or "tests/test_api.py::test_get")
+        #[arg(long)]
+        root: Option<String>,
+    },
+    /// Print the dependency tree of a fixture, or with --reverse everything
+    /// that depends on it
+    Tree {
+        /// Fixture name
+        fixture: String,
+
+        /// Start at the definition visible from this test file or directory
+        /// (default: every project definition)
+        #[arg(long, value_name = "PATH")]
+        from: Option<PathBuf>,
+
+        /// Print the fixtures and tests that depend on the fixture instead
+        #[arg(long)]
+        reverse: bool,
+    },
+    /// List every fixture and test requesting a fixture, grouped by the
+    /// definition they resolve to, as `file:line:col` lines
+    Uses {
+        /// Fixture name
+        fixture: String,
+    },
+    /// List the fixtures visible from a test file or directory, like
+    /// `pytest --fixtures`
+    Available {
+        /// Test file or directory to list the fixtures of
+        #[arg(default_value = ".")]
+        path: PathBuf,
+
         /// Output format: "text" (default) or "json"
         #[arg(long, default_value = "text")]
         format: String,
@@ -72,14 +276,73 @@ async fn main() {
             FixtureCommands::Unused { path, format } => {
                 handle_fixtures_unused(path, &format);
             }
+            FixtureCommands::Graph { path, format, root } => {
+                handle_fixtures_graph(path, &format, root.as_deref());
+            }
+            FixtureCommands::Tree {
+                fixture,
+                from,
+                reverse,
+            } => {
+                handle_fixtures_tree(&fixture, from, reverse);
+            }
+            FixtureCommands::Uses { fixture } => {
+                handle_fixtures_uses(&fixture);
+            }
+            FixtureCommands::Available { path, format } => {
+                handle_fixtures_available(path, &format);
+            }
         },
+        Some(Commands::Check(args)) => {
+            handle_check(args);
+        }
+        Some(Commands::Doctor { path }) => {
+            handle_doctor(path);
+        }
         None => {
             // No subcommand provided - start LSP server
-            start_lsp_server().await;
+            let transport = match (cli.listen, cli.connect, cli.pipe) {
+                (Some(addr), _, _) => Transport::Listen(Transport::address(&addr)),
+                (None, Some(addr), _) => Transport::Connect(Transport::address(&addr)),
+                (None, None, Some(name)) => Transport::Pipe(name),
+                (None, None, None) => Transport::Stdio,
+            };
+            let log_options = match cli.log.options() {
+                Ok(log_options) => log_options,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    std::process::exit(2);
+                }
+            };
+            start_lsp_server(transport, &log_options).await;
         }
     }
 }
 
+/// Load the configuration of the project at `path` and scan it. With
+/// `index_cache`, unchanged files are restored from the index the language
+/// server persists (when the configuration enables it), which is updated.
+fn scan_with_config(
+    fixture_db: &FixtureDatabase,
+    path: &Path,
+    index_cache: bool,
+) -> config::Config {
+    let config = config::Config::load(path);
+    fixture_db.set_venv_path(config.venv.clone());
+    fixture_db.set_python_path(config.python_path.clone());
+    fixture_db.set_subproject_venv_paths(config.venvs.clone().into_iter().collect());
+    fixture_db.set_fixture_decorators(config.fixture_decorators.clone());
+    fixture_db.set_test_file_patterns(config.test_file_patterns.clone());
+    if index_cache && config.index_cache {
+        fixture_db.set_cache_dir(match &config.cache_dir {
+            Some(cache_dir) => Some(path.join(cache_dir)),
+            None => fixtures::default_cache_dir(),
+        });
+    }
+    fixture_db.scan_workspace_with_excludes(path, &config.exclude, config.respect_gitignore);
+    config
+}
+
 fn handle_fixtures_list(path: PathBuf, skip_unused: bool, only_unused: bool) {
     // Convert to absolute path
     let absolute_path = if path.is_absolute() {
@@ -108,7 +371,7 @@ fn handle_fixtures_list(path: PathBuf, skip_unused: bool, only_unused: bool) {
 
     // Create a fixture database and scan the directory
     let fixture_db = FixtureDatabase::new();
-    fixture_db.scan_workspace(&canonical_path);
+    scan_with_config(&fixture_db, &canonical_path, false);
 
     // Print the tree
     fixture_db.print_fixtures_tree(&canonical_path, skip_unused, only_unused);
@@ -144,7 +407,7 @@ fn handle_fixtures_unused(path: PathBuf, format: &str) {
 
     // Create a fixture database and scan the directory
     let fixture_db = FixtureDatabase::new();
-    fixture_db.scan_workspace(&canonical_path);
+    scan_with_config(&fixture_db, &canonical_path, false);
 
     // Get unused fixtures
     let unused = fixture_db.get_unused_fixtures();
@@ -213,31 +476,549 @@ fn handle_fixtures_unused(path: PathBuf, format: &str) {
     std::process::exit(1);
 }
 
-async fn start_lsp_server() {
-    // Set up stderr logging with env-filter support
-    // Users can control verbosity with RUST_LOG env var:
-    // RUST_LOG=debug pytest-language-server
-    // RUST_LOG=info pytest-language-server
-    // RUST_LOG=warn pytest-language-server (default)
-    tracing_subscriber::fmt()
-        .with_writer(std::io::stderr)
-        .with_ansi(false)
-        .with_env_filter(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("warn")),
-        )
-        .init();
+fn handle_fixtures_graph(path: PathBuf, format: &str, root: Option<&str>) {
+    let format = match format.parse::<fixtures::GraphFormat>() {
+        Ok(format) => format,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
 
-    info!("pytest-language-server starting");
+    // Convert to absolute path
+    let absolute_path = if path.is_absolute() {
+        path
+    } else {
+        std::env::current_dir()
+            .unwrap_or_else(|_| PathBuf::from("."))
+            .join(&path)
+    };
+
+    if !absolute_path.is_dir() {
+        eprintln!(
+            "Error: Path is not a directory: {}",
+            absolute_path.display()
+        );
+        std::process::exit(1);
+    }
+
+    // Canonicalize the path to resolve symlinks and relative components
+    let canonical_path = absolute_path.canonicalize().unwrap_or(absolute_path);
+
+    // Create a fixture database and scan the directory
+    let fixture_db = FixtureDatabase::new();
+    scan_with_config(&fixture_db, &canonical_path, false);
+
+    match fixture_db.fixture_graph(root, &canonical_path) {
+        Ok(graph) => print!("{}", graph.render(format)),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn handle_fixtures_tree(fixture: &str, from: Option<PathBuf>, reverse: bool) {
+    let current_dir = std::env::current_dir()
+        .and_then(|dir| dir.canonicalize())
+        .unwrap_or_else(|_| PathBuf::from("."));
+    let from = from.map(|from| match current_dir.join(&from).canonicalize() {
+        Ok(canonical_path) => canonical_path,
+        Err(_) => {
+            eprintln!("Error: Path does not exist: {}", from.display());
+            std::process::exit(1);
+        }
+    });
+
+    let fixture_db = FixtureDatabase::new();
+    scan_with_config(&fixture_db, &current_dir, false);
+
+    match fixture_db.fixture_tree(fixture, from.as_deref(), reverse, &current_dir) {
+        Ok(tree) => print!("{}", tree.render()),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn handle_fixtures_uses(fixture: &str) {
+    let current_dir = std::env::current_dir()
+        .and_then(|dir| dir.canonicalize())
+        .unwrap_or_else(|_| PathBuf::from("."));
+
+    let fixture_db = FixtureDatabase::new();
+    scan_with_config(&fixture_db, &current_dir, false);
+
+    let definitions = match fixture_db.fixture_uses(fixture, &current_dir) {
+        Ok(definitions) => definitions,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // One `file:line:col: message` line per location, as editors' quickfix
+    // lists expect
+    let display_path = |file_path: &Path| -> String {
+        file_path
+            .strip_prefix(&current_dir)
+            .unwrap_or(file_path)
+            .to_string_lossy()
+            .into_owned()
+    };
+    for (idx, uses) in definitions.iter().enumerate() {
+        if idx > 0 {
+            println!();
+        }
+        let def = &uses.definition;
+        println!(
+            "{}:{}:{}: {} defined here ({} use(s))",
+            display_path(&def.file_path),
+            def.line,
+            def.start_char + 1,
+            def.name,
+            uses.uses.len()
+        );
+        for fixture_use in &uses.uses {
+            let kind = match fixture_use.kind {
+                fixtures::GraphNodeKind::Fixture => "fixture",
+                fixtures::GraphNodeKind::Test => "test",
+            };
+            println!(
+                "{}:{}:{}: requested by {} {}",
+                display_path(&fixture_use.usage.file_path),
+                fixture_use.usage.line,
+                fixture_use.usage.start_char + 1,
+                kind,
+                fixture_use.requester
+            );
+        }
+    }
+}
+
+fn handle_fixtures_available(path: PathBuf, format: &str) {
+    use colored::Colorize;
 
-    let stdin = tokio::io::stdin();
-    let stdout = tokio::io::stdout();
+    if format != "text" && format != "json" {
+        eprintln!(
+            "Error: Unknown format '{}' (expected \"text\" or \"json\")",
+            format
+        );
+        std::process::exit(1);
+    }
+
+    let current_dir = std::env::current_dir()
+        .and_then(|dir| dir.canonicalize())
+        .unwrap_or_else(|_| PathBuf::from("."));
+    let canonical_path = match current_dir.join(&path).canonicalize() {
+        Ok(canonical_path) => canonical_path,
+        Err(_) => {
+            eprintln!("Error: Path does not exist: {}", path.display());
+            std::process::exit(1);
+        }
+    };
+
+    // Scan from the current directory so that the conftest.py files above
+    // the path are seen
+    let root_path = if canonical_path.starts_with(&current_dir) {
+        current_dir
+    } else if canonical_path.is_dir() {
+        canonical_path.clone()
+    } else {
+        canonical_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| canonical_path.clone())
+    };
+
+    let fixture_db = FixtureDatabase::new();
+    scan_with_config(&fixture_db, &root_path, false);
+    let visible = fixture_db.visible_fixtures(&canonical_path);
+
+    // Workspace files relative to the root, installed ones to site-packages
+    let display_path = |file_path: &Path| -> String {
+        if let Ok(relative) = file_path.strip_prefix(&root_path) {
+            return relative.to_string_lossy().into_owned();
+        }
+        let components: Vec<_> = file_path.components().collect();
+        match components
+            .iter()
+            .rposition(|c| c.as_os_str() == "site-packages")
+        {
+            Some(index) => components[index + 1..]
+                .iter()
+                .collect::<PathBuf>()
+                .to_string_lossy()
+                .into_owned(),
+            None => file_path.to_string_lossy().into_owned(),
+        }
+    };
+    let location = |def: &fixtures::FixtureDefinition| -> String {
+        format!("{}:{}", display_path(&def.file_path), def.line)
+    };
+    let summary = |def: &fixtures::FixtureDefinition| -> Option<String> {
+        def.docstring
+            .as_deref()
+            .and_then(|doc| doc.lines().map(str::trim).find(|line| !line.is_empty()))
+            .map(str::to_string)
+    };
+
+    if format == "json" {
+        let json_output: Vec<serde_json::Value> = visible
+            .iter()
+            .map(|fixture| {
+                let def = &fixture.definition;
+                serde_json::json!({
+                    "name": def.name,
+                    "scope": def.scope.as_str(),
+                    "origin": fixture.origin.as_str(),
+                    "file": display_path(&def.file_path),
+                    "line": def.line,
+                    "autouse": def.autouse,
+                    "docstring": summary(def),
+                    "overrides": fixture
+                        .overrides
+                        .iter()
+                        .map(|overridden| serde_json::json!({
+                            "file": display_path(&overridden.file_path),
+                            "line": overridden.line,
+                        }))
+                        .collect::<Vec<_>>(),
+                })
+            })
+            .collect();
+        match serde_json::to_string_pretty(&json_output) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("error: failed to serialize output as JSON: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Group like pytest: builtins, plugins, then conftest.py files from the
+    // outermost in, and the module last
+    let mut groups: std::collections::BTreeMap<_, Vec<&fixtures::VisibleFixture>> =
+        std::collections::BTreeMap::new();
+    for fixture in &visible {
+        let def = &fixture.definition;
+        let key = match fixture.origin {
+            fixtures::FixtureOrigin::Builtin => (fixture.origin, 0, String::new()),
+            _ => (
+                fixture.origin,
+                def.file_path.components().count(),
+                display_path(&def.file_path),
+            ),
+        };
+        groups.entry(key).or_default().push(fixture);
+    }
+
+    println!(
+        "{} {}",
+        "Fixtures available from".bold(),
+        match display_path(&canonical_path) {
+            path if path.is_empty() => ".".to_string(),
+            path => path,
+        }
+        .bold()
+    );
+    for ((origin, _, file), fixtures) in &groups {
+        println!();
+        if file.is_empty() {
+            println!("{}", origin.as_str().cyan().bold());
+        } else {
+            println!("{} {}", origin.as_str().cyan().bold(), file.dimmed());
+        }
+        for fixture in fixtures {
+            let def = &fixture.definition;
+            let mut line = format!("  {}", def.name.green());
+            if def.scope != fixtures::FixtureScope::Function {
+                line.push_str(&format!(" [{} scope]", def.scope.as_str()));
+            }
+            if def.autouse {
+                line.push_str(&" (autouse)".yellow().to_string());
+            }
+            if !fixture.overrides.is_empty() {
+                let overridden: Vec<String> = fixture.overrides.iter().map(location).collect();
+                line.push_str(
+                    &format!(" overrides {}", overridden.join(", "))
+                        .yellow()
+                        .to_string(),
+                );
+            }
+            println!("{}", line);
+            match summary(def) {
+                Some(doc) => println!("      {}", doc),
+                None => println!("      {}", "no docstring available".dimmed()),
+            }
+        }
+    }
+}
+
+fn handle_check(args: CheckArgs) {
+    let CheckArgs {
+        paths,
+        format,
+        fail_on,
+        max_warnings,
+        baseline,
+        baseline_check,
+        fix,
+        diff,
+    } = args;
+    let format = match format.parse::<check::CheckFormat>() {
+        Ok(format) => format,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    };
+    let fail_on = match fail_on.parse::<check::FailOn>() {
+        Ok(fail_on) => fail_on,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    // Convert to canonical absolute paths
+    let current_dir = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let mut targets = Vec::new();
+    for path in paths {
+        let absolute_path = current_dir.join(path);
+        if !absolute_path.exists() {
+            eprintln!("Error: Path does not exist: {}", absolute_path.display());
+            std::process::exit(2);
+        }
+        targets.push(absolute_path.canonicalize().unwrap_or(absolute_path));
+    }
+
+    // A directory is checked as a workspace of its own. Files are checked
+    // within the current directory, typically the repository pre-commit
+    // runs in, whose index is restored from the cache
+    let (canonical_path, only) = match targets.as_slice() {
+        [dir] if dir.is_dir() => (dir.clone(), None),
+        _ => {
+            let root = current_dir.canonicalize().unwrap_or(current_dir);
+            if let Some(outside) = targets.iter().find(|target| !target.starts_with(&root)) {
+                eprintln!(
+                    "Error: Path is outside of the current directory: {}",
+                    outside.display()
+                );
+                std::process::exit(2);
+            }
+            (root, Some(targets))
+        }
+    };
+
+    // Create a fixture database and scan the directory
+    let fixture_db = FixtureDatabase::new();
+    let config = scan_with_config(&fixture_db, &canonical_path, only.is_some());
+    let files: Vec<PathBuf> = fixture_db
+        .checked_files(&canonical_path)
+        .into_iter()
+        .filter(|file| {
+            only.as_ref()
+                .is_none_or(|only| only.iter().any(|target| file.starts_with(target)))
+        })
+        .collect();
+
+    if diff || fix {
+        let fixed = check::fix_files(&fixture_db, &config, &canonical_path, &files);
+        let count: usize = fixed.iter().map(|file| file.count).sum();
+        if diff {
+            for file in &fixed {
+                print!("{}", file.diff());
+            }
+            eprintln!("{} fixable problem(s) in {} file(s)", count, fixed.len());
+            if !fixed.is_empty() {
+                std::process::exit(1);
+            }
+            return;
+        }
+        for file in &fixed {
+            if let Err(e) = std::fs::write(&file.file_path, &file.fixed) {
+                eprintln!("Error: cannot write {}: {}", file.file_path.display(), e);
+                std::process::exit(2);
+            }
+        }
+        eprintln!("Fixed {} problem(s) in {} file(s)", count, fixed.len());
+    }
+
+    let mut report = check::Report::collect(&fixture_db, &config, &canonical_path, &files);
+    match baseline.as_deref() {
+        Some(baseline_path) if baseline_check => match check::Baseline::load(baseline_path) {
+            Ok(baseline) => report.apply_baseline(&baseline),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(2);
+            }
+        },
+        Some(baseline_path) => {
+            if let Err(e) = check::Baseline::from_report(&report).save(baseline_path) {
+                eprintln!("Error: {}", e);
+                std::process::exit(2);
+            }
+            eprintln!(
+                "Recorded {} problem(s) in {}",
+                report.diagnostics.len(),
+                baseline_path.display()
+            );
+            return;
+        }
+        None => {}
+    }
+    match report.render(format) {
+        Ok(output) => print!("{}", output),
+        Err(e) => {
+            eprintln!("error: failed to serialize output as JSON: {}", e);
+            std::process::exit(2);
+        }
+    }
+
+    // Exit with code 1 to signal problems found (useful for CI)
+    if report.fails(fail_on, max_warnings) {
+        std::process::exit(1);
+    }
+}
+
+fn handle_doctor(path: PathBuf) {
+    // Convert to absolute path
+    let absolute_path = if path.is_absolute() {
+        path
+    } else {
+        std::env::current_dir()
+            .unwrap_or_else(|_| PathBuf::from("."))
+            .join(&path)
+    };
+
+    if !absolute_path.is_dir() {
+        eprintln!(
+            "Error: Path is not a directory: {}",
+            absolute_path.display()
+        );
+        std::process::exit(1);
+    }
+
+    // Canonicalize the path to resolve symlinks and relative components
+    let canonical_path = absolute_path.canonicalize().unwrap_or(absolute_path);
+
+    let fixture_db = FixtureDatabase::new();
+    let config = scan_with_config(&fixture_db, &canonical_path, false);
+    print!("{}", doctor::report(&fixture_db, &config, &canonical_path));
+}
+
+async fn start_lsp_server(transport: Transport, log_options: &logging::LogOptions) {
+    // Logs go to stderr (or the log file), never to stdout where the LSP
+    // messages are
+    if let Err(e) = logging::init(log_options) {
+        eprintln!("Error: {}", e);
+        std::process::exit(2);
+    }
+
+    info!("pytest-language-server starting");
 
     let fixture_db = Arc::new(FixtureDatabase::new());
 
-    let (service, socket) = LspService::new(|client| Backend::new(client, fixture_db.clone()));
+    let (service, socket) = LspService::build(|client| Backend::new(client, fixture_db.clone()))
+        .custom_method(DISCOVER_TESTS_METHOD, Backend::handle_discover_tests)
+        .custom_method(
+            WorkDoneProgressCancel::METHOD,
+            Backend::handle_work_done_progress_cancel,
+        )
+        .custom_method(SetTrace::METHOD, Backend::handle_set_trace)
+        .finish();
+    // A panic fails the request it happened in, not the whole session
+    let client = service.inner().client.clone();
+    let shutdown_requested = Arc::clone(&service.inner().shutdown_requested);
+    let service = CatchPanic::new(TraceService::new(service), client);
 
     info!("LSP server ready");
-    Server::new(stdin, stdout, socket).serve(service).await;
-    // Note: serve() typically won't return - process exit is handled by shutdown()
+    match transport {
+        Transport::Stdio => {
+            let stdin = tokio::io::stdin();
+            let stdout = tokio::io::stdout();
+            Server::new(stdin, stdout, socket).serve(service).await;
+        }
+        Transport::Listen(addr) => {
+            let listener = match tokio::net::TcpListener::bind(&addr).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    eprintln!("Error: cannot listen on {}: {}", addr, e);
+                    std::process::exit(1);
+                }
+            };
+            // Printed whatever the log level: with port 0, this is the only
+            // way to learn the port
+            if let Ok(local_addr) = listener.local_addr() {
+                eprintln!("Listening on {}", local_addr);
+            }
+            let (stream, peer) = match listener.accept().await {
+                Ok(connection) => connection,
+                Err(e) => {
+                    eprintln!("Error: cannot accept a connection on {}: {}", addr, e);
+                    std::process::exit(1);
+                }
+            };
+            info!("Client connected from {}", peer);
+            let (read, write) = stream.into_split();
+            Server::new(read, write, socket).serve(service).await;
+        }
+        Transport::Connect(addr) => {
+            let stream = match tokio::net::TcpStream::connect(&addr).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("Error: cannot connect to {}: {}", addr, e);
+                    std::process::exit(1);
+                }
+            };
+            info!("Connected to {}", addr);
+            let (read, write) = stream.into_split();
+            Server::new(read, write, socket).serve(service).await;
+        }
+        Transport::Pipe(name) => {
+            let stream = match connect_pipe(&name).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("Error: cannot connect to pipe {}: {}", name, e);
+                    std::process::exit(1);
+                }
+            };
+            info!("Connected to pipe {}", name);
+            let (read, write) = tokio::io::split(stream);
+            Server::new(read, write, socket).serve(service).await;
+        }
+    }
+
+    // serve() returns on `exit` or when the client disconnects: a clean exit
+    // only after `shutdown`
+    let clean = shutdown_requested.load(std::sync::atomic::Ordering::Relaxed);
+    info!("Exiting (shutdown requested: {})", clean);
+    std::process::exit(if clean { 0 } else { 1 });
+}
+
+/// Connect to the Unix domain socket the client listens on.
+#[cfg(unix)]
+async fn connect_pipe(name: &str) -> std::io::Result<tokio::net::UnixStream> {
+    tokio::net::UnixStream::connect(name).await
+}
+
+/// Connect to the named pipe the client created, waiting while all its
+/// instances are busy.
+#[cfg(windows)]
+async fn connect_pipe(
+    name: &str,
+) -> std::io::Result<tokio::net::windows::named_pipe::NamedPipeClient> {
+    const ERROR_PIPE_BUSY: i32 = 231;
+
+    loop {
+        match tokio::net::windows::named_pipe::ClientOptions::new().open(name) {
+            Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY) => {
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+            result => return result,
+        }
+    }
 }
@@ -1,10 +1,17 @@
 //! Call hierarchy provider for pytest fixtures.
 //!
 //! Provides fixture dependency visualization:
-//! - Incoming calls: fixtures/tests that use this fixture
-//! - Outgoing calls: fixtures this fixture depends on
+//! - Incoming calls: fixtures/tests that use this fixture, grouped per caller
+//! - Outgoing calls: fixtures this fixture depends on (parameters and
+//!   `request.getfixturevalue(...)` calls)
+//!
+//! Fixture callers are returned as fixture items, so the tree can be expanded
+//! further up the dependency graph; tests are its leaves.
 
 use super::Backend;
+use crate::fixtures::types::FixtureScope;
+use crate::fixtures::{FixtureDefinition, FixtureUsage, TestItem, TestItemKind};
+use std::path::PathBuf;
 use tower_lsp_server::jsonrpc::Result;
 use tower_lsp_server::ls_types::*;
 use tracing::info;
@@ -33,51 +40,10 @@ impl Backend {
                 position.line,
                 byte_col,
             ) {
-                let Some(def_uri) = self.path_to_uri(&definition.file_path) else {
+                let Some(item) = self.fixture_hierarchy_item(&definition) else {
                     return Ok(None);
                 };
 
-                let def_line = Self::internal_line_to_lsp(definition.line);
-                let selection_range = Range {
-                    start: Position {
-                        line: def_line,
-                        character: self.to_lsp_col(
-                            &definition.file_path,
-                            definition.line,
-                            definition.start_char,
-                        ),
-                    },
-                    end: Position {
-                        line: def_line,
-                        character: self.to_lsp_col(
-                            &definition.file_path,
-                            definition.line,
-                            definition.end_char,
-                        ),
-                    },
-                };
-
-                // Range covers the whole fixture definition line
-                let range = Self::create_point_range(def_line, 0);
-
-                let item = CallHierarchyItem {
-                    name: definition.name.clone(),
-                    kind: SymbolKind::FUNCTION,
-                    tags: None,
-                    detail: Some(format!(
-                        "@pytest.fixture{}",
-                        if definition.scope != crate::fixtures::types::FixtureScope::Function {
-                            format!("(scope=\"{}\")", definition.scope.as_str())
-                        } else {
-                            String::new()
-                        }
-                    )),
-                    uri: def_uri,
-                    range,
-                    selection_range,
-                    data: None,
-                };
-
                 info!("Returning call hierarchy item: {:?}", item);
                 return Ok(Some(vec![item]));
             }
@@ -88,7 +54,8 @@ impl Backend {
 
     /// Handle callHierarchy/incomingCalls request.
     ///
-    /// Returns all fixtures and tests that use the given fixture.
+    /// Returns all fixtures and tests that use the given fixture, with one
+    /// entry per caller listing every place it references the fixture.
     pub async fn handle_incoming_calls(
         &self,
         params: CallHierarchyIncomingCallsParams,
@@ -96,22 +63,14 @@ impl Backend {
         let item = &params.item;
         info!("incomingCalls request for: {}", item.name);
 
-        let Some(file_path) = self.uri_to_path(&item.uri) else {
-            return Ok(None);
-        };
-
-        // Get the fixture definition
-        let Some(defs) = self.fixture_db.definitions.get(&item.name) else {
+        let Some(definition) = self.hierarchy_item_definition(item) else {
             return Ok(None);
         };
 
-        // Find the matching definition by file path
-        let Some(definition) = defs.iter().find(|d| d.file_path == file_path) else {
-            return Ok(None);
-        };
+        let workspace_root = self.workspace_root.read().await.clone();
 
         // Find all references to this fixture
-        let references = self.fixture_db.find_references_for_definition(definition);
+        let references = self.fixture_db.find_references_for_definition(&definition);
 
         let mut incoming_calls: Vec<CallHierarchyIncomingCall> = Vec::new();
 
@@ -121,43 +80,22 @@ impl Backend {
                 continue;
             }
 
-            let Some(usage_uri) = self.path_to_uri(&usage.file_path) else {
+            let Some(from_item) = self.caller_hierarchy_item(&usage, workspace_root.as_ref())
+            else {
                 continue;
             };
+            let from_range = self.usage_range(&usage);
 
-            let usage_line = Self::internal_line_to_lsp(usage.line);
-            let from_range = Range {
-                start: Position {
-                    line: usage_line,
-                    character: self.to_lsp_col(&usage.file_path, usage.line, usage.start_char),
-                },
-                end: Position {
-                    line: usage_line,
-                    character: self.to_lsp_col(&usage.file_path, usage.line, usage.end_char),
-                },
-            };
-
-            // Try to find what fixture/test this usage is in
-            let caller_name = self
-                .fixture_db
-                .find_containing_function(&usage.file_path, usage.line)
-                .unwrap_or_else(|| "<unknown>".to_string());
-
-            let from_item = CallHierarchyItem {
-                name: caller_name,
-                kind: SymbolKind::FUNCTION,
-                tags: None,
-                detail: Some(usage.file_path.display().to_string()),
-                uri: usage_uri,
-                range: from_range,
-                selection_range: from_range,
-                data: None,
-            };
-
-            incoming_calls.push(CallHierarchyIncomingCall {
-                from: from_item,
-                from_ranges: vec![from_range],
-            });
+            match incoming_calls
+                .iter_mut()
+                .find(|call| call.from.uri == from_item.uri && call.from.range == from_item.range)
+            {
+                Some(call) => call.from_ranges.push(from_range),
+                None => incoming_calls.push(CallHierarchyIncomingCall {
+                    from: from_item,
+                    from_ranges: vec![from_range],
+                }),
+            }
         }
 
         info!("Found {} incoming calls", incoming_calls.len());
@@ -174,117 +112,215 @@ impl Backend {
         let item = &params.item;
         info!("outgoingCalls request for: {}", item.name);
 
-        let Some(file_path) = self.uri_to_path(&item.uri) else {
-            return Ok(None);
-        };
-
-        // Get the fixture definition
-        let Some(defs) = self.fixture_db.definitions.get(&item.name) else {
-            return Ok(None);
-        };
-
-        // Find the matching definition by file path
-        let Some(definition) = defs.iter().find(|d| d.file_path == file_path) else {
+        let Some(definition) = self.hierarchy_item_definition(item) else {
             return Ok(None);
         };
+        let file_path = &definition.file_path;
+
+        // Every fixture requested inside the definition: parameters (including
+        // multi-line signatures) and request.getfixturevalue(...) calls
+        let usages: Vec<FixtureUsage> = self
+            .fixture_db
+            .usages
+            .get(file_path)
+            .map(|usages| {
+                usages
+                    .iter()
+                    .filter(|u| u.line >= definition.line && u.line <= definition.end_line)
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut dep_names: Vec<&str> = Vec::new();
+        for name in definition
+            .dependencies
+            .iter()
+            .map(String::as_str)
+            .chain(usages.iter().map(|u| u.name.as_str()))
+        {
+            if !dep_names.contains(&name) {
+                dep_names.push(name);
+            }
+        }
 
         let mut outgoing_calls: Vec<CallHierarchyOutgoingCall> = Vec::new();
 
-        // Each dependency is an outgoing call
-        for dep_name in &definition.dependencies {
-            // Resolve the dependency to its definition
-            if let Some(dep_def) = self
-                .fixture_db
-                .resolve_fixture_for_file(&file_path, dep_name)
-            {
-                let Some(dep_uri) = self.path_to_uri(&dep_def.file_path) else {
-                    continue;
-                };
-
-                let dep_line = Self::internal_line_to_lsp(dep_def.line);
-                let to_range = Range {
-                    start: Position {
-                        line: dep_line,
-                        character: self.to_lsp_col(
-                            &dep_def.file_path,
-                            dep_def.line,
-                            dep_def.start_char,
-                        ),
-                    },
-                    end: Position {
-                        line: dep_line,
-                        character: self.to_lsp_col(
-                            &dep_def.file_path,
-                            dep_def.line,
-                            dep_def.end_char,
-                        ),
-                    },
-                };
-
-                let to_item = CallHierarchyItem {
-                    name: dep_def.name.clone(),
-                    kind: SymbolKind::FUNCTION,
-                    tags: None,
-                    detail: Some(format!(
-                        "@pytest.fixture{}",
-                        if dep_def.scope != crate::fixtures::types::FixtureScope::Function {
-                            format!("(scope=\"{}\")", dep_def.scope.as_str())
-                        } else {
-                            String::new()
-                        }
-                    )),
-                    uri: dep_uri,
-                    range: Self::create_point_range(dep_line, 0),
-                    selection_range: to_range,
-                    data: None,
-                };
-
-                // Find where in the fixture the dependency is referenced
-                // (parameter position in the signature)
-                let from_ranges = self
-                    .find_parameter_ranges(&file_path, definition.line, dep_name)
-                    .unwrap_or_else(|| vec![to_range]);
+        for dep_name in dep_names {
+            // A fixture requesting its own name gets the overridden parent fixture
+            let dep_def = if dep_name == definition.name {
+                self.fixture_db.find_closest_definition_excluding(
+                    file_path,
+                    dep_name,
+                    definition.line,
+                    Some(&definition),
+                )
+            } else {
+                self.fixture_db
+                    .resolve_fixture_for_file(file_path, dep_name)
+            };
+            let Some(dep_def) = dep_def else {
+                continue;
+            };
+            let Some(to_item) = self.fixture_hierarchy_item(&dep_def) else {
+                continue;
+            };
 
-                outgoing_calls.push(CallHierarchyOutgoingCall {
-                    to: to_item,
-                    from_ranges,
-                });
+            let mut from_ranges: Vec<Range> = usages
+                .iter()
+                .filter(|u| u.name == dep_name)
+                .map(|u| self.usage_range(u))
+                .collect();
+            if from_ranges.is_empty() {
+                from_ranges.push(to_item.selection_range);
             }
+
+            outgoing_calls.push(CallHierarchyOutgoingCall {
+                to: to_item,
+                from_ranges,
+            });
         }
 
         info!("Found {} outgoing calls", outgoing_calls.len());
         Ok(Some(outgoing_calls))
     }
 
-    /// Find the range(s) where a parameter name appears in a function signature.
-    fn find_parameter_ranges(
+    /// Find the fixture definition a call hierarchy item refers to.
+    ///
+    /// Prefers the definition on the item's selection line, since a file can
+    /// define several fixtures with the same name (e.g. in different classes).
+    fn hierarchy_item_definition(&self, item: &CallHierarchyItem) -> Option<FixtureDefinition> {
+        let file_path = self.uri_to_path(&item.uri)?;
+        let defs = self.fixture_db.definitions.get(&item.name)?;
+        let in_file = || defs.iter().filter(|d| d.file_path == file_path);
+        in_file()
+            .find(|d| Self::internal_line_to_lsp(d.line) == item.selection_range.start.line)
+            .or_else(|| in_file().next())
+            .cloned()
+    }
+
+    /// Build the call hierarchy item for a fixture definition.
+    fn fixture_hierarchy_item(&self, definition: &FixtureDefinition) -> Option<CallHierarchyItem> {
+        let def_uri = self.path_to_uri(&definition.file_path)?;
+        let def_line = Self::internal_line_to_lsp(definition.line);
+        let selection_range = Self::create_range(
+            def_line,
+            self.to_lsp_col(
+                &definition.file_path,
+                definition.line,
+                definition.start_char,
+            ),
+            def_line,
+            self.to_lsp_col(&definition.file_path, definition.line, definition.end_char),
+        );
+
+        Some(CallHierarchyItem {
+            name: definition.name.clone(),
+            kind: SymbolKind::FUNCTION,
+            tags: None,
+            detail: Some(format!(
+                "@pytest.fixture{}",
+                if definition.scope != FixtureScope::Function {
+                    format!("(scope=\"{}\")", definition.scope.as_str())
+                } else {
+                    String::new()
+                }
+            )),
+            uri: def_uri,
+            // Range covers the whole fixture definition
+            range: Self::create_range(
+                def_line,
+                0,
+                Self::internal_line_to_lsp(definition.end_line),
+                0,
+            ),
+            selection_range,
+            data: None,
+        })
+    }
+
+    /// Build the item for whatever requests a fixture at `usage`: the fixture
+    /// whose body contains it, the test (or test class) it is in or decorates,
+    /// or the module itself for `pytestmark` usages.
+    fn caller_hierarchy_item(
         &self,
-        file_path: &std::path::Path,
-        line: usize,
-        param_name: &str,
-    ) -> Option<Vec<Range>> {
-        let content = self.fixture_db.file_cache.get(file_path)?;
-        let lines: Vec<&str> = content.lines().collect();
-
-        // Get the line (0-indexed internally, but definition.line is 1-indexed)
-        let line_content = lines.get(line.saturating_sub(1))?;
-
-        // Find the parameter in the line
-        if let Some(start) = line_content.find(param_name) {
-            let lsp_line = Self::internal_line_to_lsp(line);
-            let range = Range {
-                start: Position {
-                    line: lsp_line,
-                    character: self.to_lsp_col(file_path, line, start),
-                },
-                end: Position {
-                    line: lsp_line,
-                    character: self.to_lsp_col(file_path, line, start + param_name.len()),
-                },
-            };
-            return Some(vec![range]);
+        usage: &FixtureUsage,
+        workspace_root: Option<&PathBuf>,
+    ) -> Option<CallHierarchyItem> {
+        let file_path = &usage.file_path;
+
+        if let Some(caller) = self.fixture_db.fixture_at_line(file_path, usage.line) {
+            return self.fixture_hierarchy_item(&caller);
         }
 
-        None
+        let uri = self.path_to_uri(file_path)?;
+        let display_path = Self::display_path(file_path, workspace_root);
+        let tests = self.fixture_db.get_test_items(file_path);
+
+        let Some((test, in_class)) = Self::test_item_for_usage(&tests, usage.line) else {
+            let module_name = file_path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("<module>")
+                .to_string();
+            return Some(CallHierarchyItem {
+                name: module_name,
+                kind: SymbolKind::MODULE,
+                tags: None,
+                detail: Some(display_path),
+                uri,
+                range: Self::create_point_range(0, 0),
+                selection_range: Self::create_point_range(0, 0),
+                data: None,
+            });
+        };
+
+        let line = Self::internal_line_to_lsp(test.line);
+        let kind = match test.kind {
+            TestItemKind::Class => SymbolKind::CLASS,
+            TestItemKind::Function if in_class => SymbolKind::METHOD,
+            TestItemKind::Function => SymbolKind::FUNCTION,
+        };
+        Some(CallHierarchyItem {
+            name: test.name.clone(),
+            kind,
+            tags: None,
+            detail: Some(display_path),
+            uri,
+            range: Self::create_range(line, 0, Self::internal_line_to_lsp(test.end_line), 0),
+            selection_range: Self::create_range(
+                line,
+                self.to_lsp_col(file_path, test.line, test.start_char),
+                line,
+                self.to_lsp_col(file_path, test.line, test.end_char),
+            ),
+            data: None,
+        })
+    }
+
+    /// The innermost test item that contains `line`, decorators included.
+    /// Also reports whether the item is defined inside a test class.
+    fn test_item_for_usage(items: &[TestItem], line: usize) -> Option<(&TestItem, bool)> {
+        let mut found = None;
+        let mut level = items;
+        while let Some(item) = level
+            .iter()
+            .find(|item| item.start_line <= line && line <= item.end_line)
+        {
+            found = Some((item, found.is_some()));
+            level = &item.children;
+        }
+        found
+    }
+
+    /// LSP range of a fixture usage.
+    fn usage_range(&self, usage: &FixtureUsage) -> Range {
+        let line = Self::internal_line_to_lsp(usage.line);
+        Self::create_range(
+            line,
+            self.to_lsp_col(&usage.file_path, usage.line, usage.start_char),
+            line,
+            self.to_lsp_col(&usage.file_path, usage.line, usage.end_char),
+        )
     }
 }
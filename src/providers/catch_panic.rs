@@ -0,0 +1,222 @@
+//! Panic isolation: a bug in the parser or a provider fails one request or
+//! one file instead of killing the server and dropping the editor session.
+//!
+//! [`CatchPanic`] turns a panic while handling a request into an internal
+//! error response; the analysis of a file catches its own panics and records
+//! them in `FixtureDatabase::analysis_failures`.
+//! Either way the user is told with a `window/showMessage`.
+
+use super::Backend;
+use crate::fixtures::panic_message;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tower_lsp_server::jsonrpc::{Error, ErrorCode, Request, Response};
+use tower_lsp_server::ls_types::MessageType;
+use tower_lsp_server::Client;
+use tower_service::Service;
+use tracing::error;
+
+impl Backend {
+    /// Tell the user about the files whose analysis panicked since the last
+    /// report.
+    pub(crate) fn report_analysis_failures(&self) {
+        let mut failures: Vec<(String, String)> = self
+            .fixture_db
+            .analysis_failures
+            .iter_mut()
+            .filter(|failure| !failure.reported)
+            .map(|mut failure| {
+                failure.reported = true;
+                (failure.key().display().to_string(), failure.message.clone())
+            })
+            .collect();
+        failures.sort();
+        let message = match failures.as_slice() {
+            [] => return,
+            [(path, message)] => format!(
+                "pytest-language-server failed to analyze {}: {}. Its fixtures are unavailable; \
+                 please report this bug.",
+                path, message
+            ),
+            [(path, message), rest @ ..] => format!(
+                "pytest-language-server failed to analyze {} and {} other file(s): {}. Their \
+                 fixtures are unavailable; please report this bug.",
+                path,
+                rest.len(),
+                message
+            ),
+        };
+        show_error(&self.client, message);
+    }
+}
+
+/// Show `message` to the user without waiting for it to be sent, so neither
+/// the analysis nor the response waits on the client.
+fn show_error(client: &Client, message: String) {
+    let client = client.clone();
+    tokio::spawn(async move { client.show_message(MessageType::ERROR, message).await });
+}
+
+/// Wraps the language server so a panic while handling a message answers
+/// requests with an internal error and shows the user a message, rather
+/// than unwinding through the transport and exiting.
+pub struct CatchPanic<S> {
+    inner: S,
+    client: Client,
+}
+
+impl<S> CatchPanic<S> {
+    pub fn new(inner: S, client: Client) -> Self {
+        Self { inner, client }
+    }
+}
+
+impl<S> Service<Request> for CatchPanic<S>
+where
+    S: Service<Request, Response = Option<Response>>,
+    S::Future: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = Option<Response>;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let method = request.method().to_string();
+        let id = request.id().cloned();
+        let mut future = Box::pin(self.inner.call(request));
+        let client = self.client.clone();
+        Box::pin(async move {
+            let result = std::future::poll_fn(|cx| {
+                match std::panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(cx))) {
+                    Ok(Poll::Ready(result)) => Poll::Ready(Ok(result)),
+                    Ok(Poll::Pending) => Poll::Pending,
+                    Err(payload) => Poll::Ready(Err(panic_message(payload.as_ref()))),
+                }
+            })
+            .await;
+            match result {
+                Ok(result) => result,
+                Err(message) => {
+                    error!("Handling '{}' panicked: {}", method, message);
+                    show_error(
+                        &client,
+                        format!(
+                            "pytest-language-server failed to handle '{}': {}. Please report \
+                             this bug.",
+                            method, message
+                        ),
+                    );
+                    Ok(id.map(|id| {
+                        Response::from_error(
+                            id,
+                            Error {
+                                code: ErrorCode::InternalError,
+                                message: format!("Internal error: {}", message).into(),
+                                data: None,
+                            },
+                        )
+                    }))
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::FixtureDatabase;
+    use tower_lsp_server::jsonrpc::Id;
+    use tower_lsp_server::LspService;
+
+    /// Answers requests with `null`, and panics on `crash`.
+    struct Crashing;
+
+    impl Service<Request> for Crashing {
+        type Response = Option<Response>;
+        type Error = std::convert::Infallible;
+        type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: Request) -> Self::Future {
+            Box::pin(async move {
+                if request.method() == "crash" {
+                    panic!("index out of bounds");
+                }
+                Ok(request
+                    .id()
+                    .map(|id| Response::from_ok(id.clone(), serde_json::Value::Null)))
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_catch_panic() {
+        let (service, _socket) = LspService::new(|client| {
+            Backend::new(client, std::sync::Arc::new(FixtureDatabase::new()))
+        });
+        let mut service = CatchPanic::new(Crashing, service.inner().client.clone());
+
+        let response = service
+            .call(Request::build("crash").id(1).finish())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.id(), &Id::Number(1));
+        let error = response.error().unwrap();
+        assert_eq!(error.code, ErrorCode::InternalError);
+        assert_eq!(error.message, "Internal error: index out of bounds");
+
+        // Notifications have no response
+        let response = service.call(Request::build("crash").finish()).await;
+        assert_eq!(response.unwrap(), None);
+
+        // The service keeps working
+        let response = service
+            .call(Request::build("hover").id(2).finish())
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(response.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_report_analysis_failures() {
+        let fixture_db = std::sync::Arc::new(FixtureDatabase::new());
+        let (service, _socket) =
+            LspService::new(|client| Backend::new(client, std::sync::Arc::clone(&fixture_db)));
+        let path = std::path::PathBuf::from("/tmp/test_crash.py");
+        let failure = crate::fixtures::AnalysisFailure {
+            message: "index out of bounds".to_string(),
+            reported: false,
+        };
+        fixture_db.analysis_failures.insert(path.clone(), failure);
+
+        service.inner().report_analysis_failures();
+        assert!(fixture_db.analysis_failures.get(&path).unwrap().reported);
+
+        // A clean analysis clears the failure
+        fixture_db.analyze_file(path.clone(), "def test_ok():\n    pass\n");
+        assert!(fixture_db.analysis_failures.is_empty());
+    }
+
+    #[test]
+    fn test_panic_message() {
+        let payload = std::panic::catch_unwind(|| panic!("static")).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "static");
+        let payload = std::panic::catch_unwind(|| panic!("formatted {}", 42)).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "formatted 42");
+        let payload = std::panic::catch_unwind(|| std::panic::panic_any(42)).unwrap_err();
+        assert_eq!(panic_message(payload.as_ref()), "unknown panic");
+    }
+}
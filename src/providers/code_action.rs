@@ -3,11 +3,22 @@
 //! Provides several code-action kinds:
 //!
 //! 1. **`quickfix`** (diagnostic-driven) – when a diagnostic with code
-//!    `"undeclared-fixture"` is present, offers to add the missing fixture as a
+//!    `PTL001` (`"undeclared-fixture"`) is present, offers to add the missing fixture as a
 //!    typed parameter to the enclosing test/fixture function, together with any
 //!    `import` statement needed to use the fixture's return type annotation in
 //!    the consumer file.
 //!
+//!    A `PTL002` (`"scope-mismatch"`) diagnostic gets quick fixes that rewrite the
+//!    `scope=` argument of the offending fixture's decorator (narrowing the
+//!    requesting fixture) or of each dependency (widening it).
+//!
+//!    A `PTL008` (`"unused-fixture-parameter"`) diagnostic gets quick fixes
+//!    that remove the parameter, or move it to `@pytest.mark.usefixtures`.
+//!
+//!    Diagnostics of other rules get the fix of their finding, when it has
+//!    one (the fixes `check --fix` applies), e.g. removing a `PTL007`
+//!    (`"redundant-usefixtures"`) entry.
+//!
 //! 2. **`source.pytest-ls`** (cursor-based) – when the cursor is on a fixture
 //!    parameter that already exists but lacks a type annotation, offers to
 //!    insert `: ReturnType` (mirroring the inlay-hint text) and any necessary
@@ -29,6 +40,7 @@
 //!   imports into full conformance with your project's configuration.
 
 use super::Backend;
+use crate::config::find_rule;
 use crate::fixtures::import_analysis::{
     adapt_type_for_consumer, can_merge_into, classify_import_statement,
     find_sorted_insert_position, import_line_sort_key, import_sort_key, parse_import_layout,
@@ -36,6 +48,7 @@ use crate::fixtures::import_analysis::{
 };
 use crate::fixtures::string_utils::parameter_has_annotation;
 use crate::fixtures::types::TypeImportSpec;
+use crate::fixtures::Fix;
 use std::collections::{HashMap, HashSet};
 use tower_lsp_server::jsonrpc::Result;
 use tower_lsp_server::ls_types::*;
@@ -471,16 +484,46 @@ impl Backend {
             let undeclared = self.fixture_db.get_undeclared_fixtures(&file_path);
             info!("Found {} undeclared fixtures in file", undeclared.len());
 
+            // Several scope-mismatch diagnostics can share one fixture name range
+            // (one per offending dependency); build that fixture's fixes only once.
+            let mut handled_scope_fixtures: HashSet<(usize, usize)> = HashSet::new();
+
             for diagnostic in &context.diagnostics {
                 info!(
                     "Processing diagnostic: code={:?}, range={:?}",
                     diagnostic.code, diagnostic.range
                 );
 
-                let Some(NumberOrString::String(code)) = &diagnostic.code else {
+                // Diagnostics carry the stable rule code (PTL001); older clients
+                // may still echo the rule name back, so accept either.
+                let Some(rule) = (match &diagnostic.code {
+                    Some(NumberOrString::String(code)) => find_rule(code),
+                    _ => None,
+                }) else {
                     continue;
                 };
-                if code != "undeclared-fixture" {
+                if rule.name == "scope-mismatch" {
+                    let fixes = self.scope_mismatch_actions(
+                        &file_path,
+                        diagnostic,
+                        &mut handled_scope_fixtures,
+                    );
+                    actions.extend(fixes.into_iter().map(CodeActionOrCommand::CodeAction));
+                    continue;
+                }
+                if rule.name == "unused-fixture-parameter" {
+                    let fixes =
+                        self.unused_parameter_actions(&uri, &file_path, &content, diagnostic);
+                    actions.extend(fixes.into_iter().map(CodeActionOrCommand::CodeAction));
+                    continue;
+                }
+                if rule.name != "undeclared-fixture" {
+                    if let Some(action) =
+                        self.finding_fix_action(&uri, &file_path, &content, rule.name, diagnostic)
+                    {
+                        info!("Created code action: {}", action.title);
+                        actions.push(CodeActionOrCommand::CodeAction(action));
+                    }
                     continue;
                 }
 
@@ -816,6 +859,213 @@ impl Backend {
     }
 }
 
+impl Backend {
+    /// Build the quick fix of the finding of `rule` that `diagnostic`
+    /// reports, when the finding has a fix.
+    fn finding_fix_action(
+        &self,
+        uri: &Uri,
+        file_path: &std::path::Path,
+        content: &str,
+        rule: &str,
+        diagnostic: &Diagnostic,
+    ) -> Option<CodeAction> {
+        let line = Self::lsp_line_to_internal(diagnostic.range.start.line);
+        let start_char = self.to_byte_col(file_path, diagnostic.range.start) as usize;
+        let fix = self
+            .fixture_db
+            .check_file(file_path, |name| name == rule)
+            .into_iter()
+            .find(|finding| finding.line == line && finding.start_char == start_char)?
+            .fix?;
+        self.fix_action(uri, file_path, content, &fix, diagnostic, true)
+    }
+
+    /// Build quick fixes for an `unused-fixture-parameter` diagnostic: remove
+    /// the parameter, or request the fixture with `usefixtures` instead.
+    fn unused_parameter_actions(
+        &self,
+        uri: &Uri,
+        file_path: &std::path::Path,
+        content: &str,
+        diagnostic: &Diagnostic,
+    ) -> Vec<CodeAction> {
+        let line = Self::lsp_line_to_internal(diagnostic.range.start.line);
+        let start_char = self.to_byte_col(file_path, diagnostic.range.start) as usize;
+        let Some(param) = self
+            .fixture_db
+            .detect_unused_fixture_params_in_file(file_path)
+            .into_iter()
+            .find(|param| param.line == line && param.start_char == start_char)
+        else {
+            return vec![];
+        };
+
+        let fixes: Vec<Fix> = [param.removal_fix(), param.usefixtures_fix()]
+            .into_iter()
+            .flatten()
+            .collect();
+
+        fixes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, fix)| {
+                self.fix_action(uri, file_path, content, fix, diagnostic, i == 0)
+            })
+            .collect()
+    }
+
+    /// Build a quick fix applying `fix` to the file at `file_path`.
+    fn fix_action(
+        &self,
+        uri: &Uri,
+        file_path: &std::path::Path,
+        content: &str,
+        fix: &Fix,
+        diagnostic: &Diagnostic,
+        is_preferred: bool,
+    ) -> Option<CodeAction> {
+        let position = |offset: usize| {
+            let before = content.get(..offset)?;
+            let line = before.matches('\n').count() + 1;
+            let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+            Some(Position::new(
+                Self::internal_line_to_lsp(line),
+                self.to_lsp_col(file_path, line, offset - line_start),
+            ))
+        };
+        let edits = fix
+            .edits
+            .iter()
+            .map(|edit| {
+                Some(TextEdit {
+                    range: Range::new(position(edit.start)?, position(edit.end)?),
+                    new_text: edit.new_text.clone(),
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(CodeAction {
+            title: format!("{}: {}", TITLE_PREFIX, fix.title),
+            kind: Some(CodeActionKind::QUICKFIX),
+            diagnostics: Some(vec![diagnostic.clone()]),
+            edit: Some(WorkspaceEdit {
+                changes: Some(vec![(uri.clone(), edits)].into_iter().collect()),
+                document_changes: None,
+                change_annotations: None,
+            }),
+            command: None,
+            is_preferred: Some(is_preferred),
+            disabled: None,
+            data: None,
+        })
+    }
+
+    /// Build quick fixes for a `scope-mismatch` diagnostic: narrow the requesting
+    /// fixture to the narrowest scope among its offending dependencies, or widen
+    /// each offending (non-third-party) dependency to the requesting fixture's scope.
+    ///
+    /// `handled` records fixture positions whose fixes were already emitted.
+    fn scope_mismatch_actions(
+        &self,
+        file_path: &std::path::Path,
+        diagnostic: &Diagnostic,
+        handled: &mut HashSet<(usize, usize)>,
+    ) -> Vec<CodeAction> {
+        let diag_line = Self::lsp_line_to_internal(diagnostic.range.start.line);
+        let diag_char = self.to_byte_col(file_path, diagnostic.range.start) as usize;
+        if !handled.insert((diag_line, diag_char)) {
+            return vec![];
+        }
+
+        let mismatches: Vec<_> = self
+            .fixture_db
+            .detect_scope_mismatches_in_file(file_path)
+            .into_iter()
+            .filter(|m| m.fixture.line == diag_line && m.fixture.start_char == diag_char)
+            .collect();
+        let Some(first) = mismatches.first() else {
+            return vec![];
+        };
+        let fixture = &first.fixture;
+
+        let mut fixes = Vec::new();
+
+        // Narrow the requesting fixture so every dependency is at least as wide.
+        let narrowest = mismatches
+            .iter()
+            .map(|m| m.dependency.scope)
+            .min()
+            .unwrap_or_default();
+        if let Some(action) = self.scope_change_action(fixture, narrowest, diagnostic, true) {
+            fixes.push(action);
+        }
+
+        // Or widen each offending dependency to the requesting fixture's scope.
+        for mismatch in &mismatches {
+            if mismatch.dependency.is_third_party {
+                continue;
+            }
+            if let Some(action) =
+                self.scope_change_action(&mismatch.dependency, fixture.scope, diagnostic, false)
+            {
+                fixes.push(action);
+            }
+        }
+
+        fixes
+    }
+
+    /// Build a quick fix that rewrites `target`'s decorator to `scope="<scope>"`.
+    fn scope_change_action(
+        &self,
+        target: &crate::fixtures::FixtureDefinition,
+        scope: crate::fixtures::FixtureScope,
+        diagnostic: &Diagnostic,
+        is_preferred: bool,
+    ) -> Option<CodeAction> {
+        let scope_edit = self.fixture_db.get_fixture_scope_edit(target, scope)?;
+        let target_uri = self.path_to_uri(&target.file_path)?;
+
+        let text_edit = TextEdit {
+            range: Self::create_range(
+                Self::internal_line_to_lsp(scope_edit.start_line),
+                self.to_lsp_col(
+                    &target.file_path,
+                    scope_edit.start_line,
+                    scope_edit.start_char,
+                ),
+                Self::internal_line_to_lsp(scope_edit.end_line),
+                self.to_lsp_col(&target.file_path, scope_edit.end_line, scope_edit.end_char),
+            ),
+            new_text: scope_edit.new_text,
+        };
+
+        let title = format!(
+            "{}: Change scope of '{}' to '{}'",
+            TITLE_PREFIX,
+            target.name,
+            scope.as_str()
+        );
+        info!("Created scope-mismatch quick fix: {}", title);
+
+        Some(CodeAction {
+            title,
+            kind: Some(CodeActionKind::QUICKFIX),
+            diagnostics: Some(vec![diagnostic.clone()]),
+            edit: Some(WorkspaceEdit {
+                changes: Some(vec![(target_uri, vec![text_edit])].into_iter().collect()),
+                document_changes: None,
+                change_annotations: None,
+            }),
+            command: None,
+            is_preferred: Some(is_preferred),
+            disabled: None,
+            data: None,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,8 +1,13 @@
 //! Code Lens provider for pytest fixtures.
 //!
-//! Shows "N usages" above fixture definitions.
+//! Shows "N usages" above fixture definitions, and in test files "Run test"
+//! lenses above each test function and class plus a "Run file" lens at the top.
+//! The run lenses invoke the server's [`RUN_TEST_COMMAND`].
 
+use super::execute_command::RUN_TEST_COMMAND;
 use super::Backend;
+use crate::fixtures::{TestItem, TestItemKind};
+use std::path::Path;
 use tower_lsp_server::jsonrpc::Result;
 use tower_lsp_server::ls_types::*;
 use tracing::info;
@@ -92,6 +97,26 @@ impl Backend {
             }
         }
 
+        let is_test_file = file_path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|name| self.fixture_db.is_test_file_name(name));
+        if is_test_file {
+            let tests = self.fixture_db.get_test_items(&file_path);
+            if !tests.is_empty() {
+                let rootdir = self.pytest_rootdir_for(&file_path).await;
+                let node_id = Self::pytest_node_id(&file_path, rootdir.as_ref(), &[]);
+                let rootdir = rootdir.as_deref();
+                lenses.push(Self::run_test_lens(
+                    0,
+                    "▶ Run file",
+                    node_id.clone(),
+                    rootdir,
+                ));
+                Self::push_run_test_lenses(&tests, &node_id, rootdir, &mut lenses);
+            }
+        }
+
         info!("Returning {} code lenses for {:?}", lenses.len(), file_path);
 
         if lenses.is_empty() {
@@ -100,4 +125,42 @@ impl Backend {
             Ok(Some(lenses))
         }
     }
+
+    /// Add a run lens for each test item, recursing into test classes.
+    fn push_run_test_lenses(
+        items: &[TestItem],
+        parent_id: &str,
+        rootdir: Option<&Path>,
+        lenses: &mut Vec<CodeLens>,
+    ) {
+        for item in items {
+            let node_id = format!("{}::{}", parent_id, item.name);
+            let title = match item.kind {
+                TestItemKind::Function => "▶ Run test",
+                TestItemKind::Class => "▶ Run class",
+            };
+            lenses.push(Self::run_test_lens(
+                Self::internal_line_to_lsp(item.line),
+                title,
+                node_id.clone(),
+                rootdir,
+            ));
+            Self::push_run_test_lenses(&item.children, &node_id, rootdir, lenses);
+        }
+    }
+
+    /// A lens running the test `node_id`, relative to `rootdir`.
+    fn run_test_lens(line: u32, title: &str, node_id: String, rootdir: Option<&Path>) -> CodeLens {
+        let mut arguments = vec![serde_json::Value::String(node_id)];
+        arguments.extend(rootdir.map(|dir| serde_json::Value::String(dir.display().to_string())));
+        CodeLens {
+            range: Self::create_range(line, 0, line, 0),
+            command: Some(Command {
+                title: title.to_string(),
+                command: RUN_TEST_COMMAND.to_string(),
+                arguments: Some(arguments),
+            }),
+            data: None,
+        }
+    }
 }
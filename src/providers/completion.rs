@@ -1,10 +1,21 @@
-//! Completion provider for pytest fixtures.
+//! Completion provider for pytest fixtures and markers.
+//!
+//! Fixture items are returned without documentation; it is filled in lazily by
+//! `completionItem/resolve` for the item the client actually shows, since
+//! formatting it means reading each fixture's source.
 
 use super::Backend;
+use crate::config::CompletionSettings;
+use crate::fixtures::builtins::{
+    builtin_definition, is_builtin_definition, missing_builtin_definitions, plugin_name,
+};
+use crate::fixtures::markers::MarkerSource;
 use crate::fixtures::types::FixtureScope;
 use crate::fixtures::CompletionContext;
 use crate::fixtures::FixtureDefinition;
+use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
+use std::sync::atomic::Ordering;
 use tower_lsp_server::jsonrpc::Result;
 use tower_lsp_server::ls_types::*;
 use tracing::info;
@@ -12,8 +23,8 @@ use tracing::info;
 /// Parameter names that should never appear in fixture completions, they should be handled by another lsp.
 const EXCLUDED_PARAM_NAMES: &[&str] = &["self", "cls"];
 
-/// Per-request completion options bundling fixture scope, self-exclusion name, and
-/// trigger-character insert prefix. Passed through the completion pipeline to avoid
+/// Per-request completion options bundling fixture scope, self-exclusion name,
+/// trigger-character insert prefix and third-party filtering. Passed through the completion pipeline to avoid
 /// threading many individual parameters.
 pub(crate) struct CompletionOpts<'a> {
     /// When editing a fixture, its scope constrains which other fixtures are eligible.
@@ -25,6 +36,9 @@ pub(crate) struct CompletionOpts<'a> {
     /// Prefix prepended to each completion's insert text. Set to `" "` when the
     /// completion was triggered by a comma, otherwise `""`.
     insert_prefix: &'a str,
+    /// Which third-party packages' fixtures are offered (pytest's builtins
+    /// always are).
+    settings: &'a CompletionSettings,
 }
 
 /// Check whether a fixture should be excluded from completions based on scope rules.
@@ -74,6 +88,16 @@ fn is_fixture_excluded(
         return true;
     }
 
+    // Skip third-party fixtures when they are not wanted
+    if fixture.is_third_party
+        && !is_builtin_definition(fixture)
+        && !opts
+            .settings
+            .offers_third_party_package(&plugin_name(fixture))
+    {
+        return true;
+    }
+
     false
 }
 
@@ -82,6 +106,8 @@ fn is_fixture_excluded(
 fn fixture_sort_priority(fixture: &FixtureDefinition, current_file: &std::path::Path) -> u8 {
     if fixture.file_path == current_file {
         0 // Same file
+    } else if is_builtin_definition(fixture) {
+        4 // pytest builtin (check before third-party: scanned builtins are both)
     } else if fixture.is_third_party {
         3 // Third-party (check before is_plugin since some are both)
     } else if fixture.is_plugin {
@@ -98,9 +124,10 @@ fn make_sort_text(priority: u8, fixture_name: &str) -> String {
 }
 
 /// Build a detail string for a fixture completion item.
-/// Format: `(scope) [origin]`
+/// Format: `(scope) [origin] parametrized ×N: ids`
 /// - scope is omitted when it's the default "function"
-/// - origin tag is only added for plugin or third-party fixtures
+/// - origin tag is only added for builtin, plugin or third-party fixtures
+/// - params are only added for parametrized fixtures
 fn make_fixture_detail(fixture: &FixtureDefinition) -> String {
     let mut parts = Vec::new();
 
@@ -110,15 +137,158 @@ fn make_fixture_detail(fixture: &FixtureDefinition) -> String {
     }
 
     // Add origin tag
-    if fixture.is_third_party {
+    if is_builtin_definition(fixture) {
+        parts.push("[builtin]".to_string());
+    } else if fixture.is_third_party {
         parts.push("[third-party]".to_string());
     } else if fixture.is_plugin {
         parts.push("[plugin]".to_string());
     }
 
+    if let Some(params) = &fixture.params {
+        parts.push(params.summary());
+    }
+
     parts.join(" ")
 }
 
+/// Available fixtures plus the pytest builtins that scanning did not find
+/// (e.g. no virtual environment was detected).
+fn with_builtin_fixtures(available: &[FixtureDefinition]) -> Vec<FixtureDefinition> {
+    let mut fixtures = available.to_vec();
+    fixtures.extend(missing_builtin_definitions(available));
+    fixtures
+}
+
+/// Where a new fixture skeleton can be inserted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SkeletonContext {
+    /// Unindented line outside any definition: the skeleton includes the decorator.
+    ModuleLevel,
+    /// Right below an `@pytest.fixture` decorator: only the `def` is inserted.
+    AfterFixtureDecorator,
+}
+
+/// Detect whether a new fixture skeleton makes sense at the cursor, given the
+/// cursor line's text before the cursor and the lines above it.
+///
+/// Only unindented lines holding nothing but a partial identifier qualify.
+fn skeleton_context(line_prefix: &str, lines_above: &[&str]) -> Option<SkeletonContext> {
+    if !line_prefix
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return None;
+    }
+    let previous = lines_above
+        .iter()
+        .rev()
+        .map(|line| line.trim_end())
+        .find(|line| !line.is_empty());
+    match previous {
+        Some(line) if line.starts_with('@') => {
+            let decorator = line[1..].split('(').next().unwrap_or_default();
+            matches!(
+                decorator,
+                "pytest.fixture" | "pytest_asyncio.fixture" | "pytest_cases.fixture" | "fixture"
+            )
+            .then_some(SkeletonContext::AfterFixtureDecorator)
+        }
+        // A block opener or a statement continued on the cursor line
+        Some(line) if line.ends_with([':', ',', '(', '[', '{', '\\']) => None,
+        _ => Some(SkeletonContext::ModuleLevel),
+    }
+}
+
+/// Text of the fixture skeleton, with tabstops for the name, scope, return type
+/// and body when `snippet` is set, or with their default values otherwise.
+fn fixture_skeleton(context: SkeletonContext, snippet: bool) -> String {
+    let (decorator, def) = if snippet {
+        (
+            "@pytest.fixture(scope=\"${2|function,class,module,package,session|}\")\n",
+            "def ${1:fixture_name}() -> ${3:None}:\n    ${0:pass}",
+        )
+    } else {
+        ("@pytest.fixture\n", "def fixture_name() -> None:\n    pass")
+    };
+    match context {
+        SkeletonContext::ModuleLevel => format!("{}{}", decorator, def),
+        SkeletonContext::AfterFixtureDecorator => def.to_string(),
+    }
+}
+
+/// If the cursor follows `pytest.mark.`, the byte offset in `line_prefix` where
+/// the marker name being typed starts.
+fn marker_name_start(line_prefix: &str) -> Option<usize> {
+    let name_start = line_prefix
+        .rfind(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .map(|i| i + 1)
+        .unwrap_or(0);
+    let before = line_prefix[..name_start].strip_suffix("mark.")?;
+    before
+        .strip_suffix("pytest.")
+        .is_some_and(|rest| !rest.ends_with(|c: char| c.is_alphanumeric() || c == '_' || c == '.'))
+        .then_some(name_start)
+}
+
+/// The identifier being typed at the end of `line_prefix`.
+fn typed_word(line_prefix: &str) -> &str {
+    let start = line_prefix
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| c.is_alphanumeric() || *c == '_')
+        .last()
+        .map_or(line_prefix.len(), |(i, _)| i);
+    &line_prefix[start..]
+}
+
+/// Whether the characters of `typed` appear in order in `label`, ignoring
+/// case, the way clients filter completion items.
+fn matches_typed(label: &str, typed: &str) -> bool {
+    let mut label = label.chars().flat_map(char::to_lowercase);
+    typed
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|c| label.any(|l| l == c))
+}
+
+/// Keep the `max_items` best items (in sort text order) matching `typed`.
+/// A truncated list is marked incomplete, so the client asks again as the
+/// user types instead of filtering what it was given.
+fn limit_items(response: CompletionResponse, typed: &str, max_items: usize) -> CompletionResponse {
+    let CompletionResponse::Array(mut items) = response else {
+        return response;
+    };
+    if items.len() <= max_items {
+        return CompletionResponse::Array(items);
+    }
+    items.retain(|item| matches_typed(&item.label, typed));
+    items.sort_by(|a, b| a.sort_text.cmp(&b.sort_text));
+    items.truncate(max_items);
+    CompletionResponse::List(CompletionList {
+        is_incomplete: true,
+        items,
+    })
+}
+
+/// Data attached to fixture completion items, identifying the definition whose
+/// documentation `completionItem/resolve` should fill in.
+#[derive(Debug, Serialize, Deserialize)]
+struct CompletionItemData {
+    name: String,
+    path: PathBuf,
+    line: usize,
+}
+
+fn completion_item_data(fixture: &FixtureDefinition) -> Option<serde_json::Value> {
+    serde_json::to_value(CompletionItemData {
+        name: fixture.name.clone(),
+        path: fixture.file_path.clone(),
+        line: fixture.line,
+    })
+    .ok()
+}
+
 /// A filtered and enriched fixture ready for completion item construction.
 struct EnrichedFixture {
     fixture: FixtureDefinition,
@@ -174,24 +344,41 @@ impl Backend {
         );
 
         if let Some(file_path) = self.uri_to_path(&uri) {
-            // Get the completion context
             let byte_col = self.to_byte_col(&file_path, position);
+
+            if let Some(response) = self.marker_completion(&file_path, position, byte_col).await {
+                return Ok(Some(response));
+            }
+            // "." only triggers marker completion
+            if params
+                .context
+                .as_ref()
+                .and_then(|ctx| ctx.trigger_character.as_deref())
+                == Some(".")
+            {
+                return Ok(None);
+            }
+
+            if let Some(response) = self.fixture_skeleton_completion(&file_path, position, byte_col)
+            {
+                return Ok(Some(response));
+            }
+
+            // Get the completion context
             if let Some(ctx) =
                 self.fixture_db
                     .get_completion_context(&file_path, position.line, byte_col)
             {
                 info!("Completion context: {:?}", ctx);
+                let settings = self.config.read().await.completion.clone();
 
-                // Get workspace root for formatting documentation
-                let workspace_root = self.workspace_root.read().await.clone();
-
-                match ctx {
+                let response = match ctx {
                     CompletionContext::FunctionSignature {
                         function_name,
+                        function_line,
                         is_fixture,
                         declared_params,
                         fixture_scope,
-                        ..
                     } => {
                         // In function signature - suggest fixtures as parameters (filter already declared)
                         // When editing a fixture, exclude itself from suggestions
@@ -203,13 +390,14 @@ impl Backend {
                                 None
                             },
                             insert_prefix,
+                            settings: &settings,
                         };
-                        return Ok(Some(self.create_fixture_completions(
+                        self.create_fixture_completions(
                             &file_path,
                             &declared_params,
-                            workspace_root.as_ref(),
+                            function_line,
                             &opts,
-                        )));
+                        )
                     }
                     CompletionContext::FunctionBody {
                         function_name,
@@ -228,25 +416,65 @@ impl Backend {
                                 None
                             },
                             insert_prefix,
+                            settings: &settings,
                         };
-                        return Ok(Some(self.create_fixture_completions_with_auto_add(
-                            &file_path,
-                            &declared_params,
-                            function_line,
-                            workspace_root.as_ref(),
-                            &opts,
-                        )));
+                        if settings.auto_add_parameter {
+                            self.create_fixture_completions_with_auto_add(
+                                &file_path,
+                                &declared_params,
+                                function_line,
+                                &opts,
+                            )
+                        } else {
+                            self.create_fixture_completions(
+                                &file_path,
+                                &declared_params,
+                                function_line,
+                                &opts,
+                            )
+                        }
+                    }
+                    CompletionContext::UsefixturesDecorator
+                    | CompletionContext::ParametrizeIndirect
+                        if !settings.decorator_strings =>
+                    {
+                        return Ok(None);
                     }
                     CompletionContext::UsefixturesDecorator
-                    | CompletionContext::ParametrizeIndirect => {
-                        // In decorator - suggest fixture names as strings
-                        return Ok(Some(self.create_string_fixture_completions(
+                    | CompletionContext::ParametrizeIndirect
+                    | CompletionContext::GetfixturevalueArgument
+                    | CompletionContext::LazyFixtureArgument => {
+                        // In decorator, getfixturevalue() or lazy_fixture() - suggest
+                        // fixture names as strings
+                        self.create_string_fixture_completions(
                             &file_path,
-                            workspace_root.as_ref(),
+                            Self::lsp_line_to_internal(position.line),
                             insert_prefix,
-                        )));
+                            &settings,
+                        )
                     }
-                }
+                };
+
+                let Some(max_items) = settings.max_items else {
+                    return Ok(Some(response));
+                };
+                let internal_line = Self::lsp_line_to_internal(position.line);
+                let content = self.fixture_db.get_file_content(&file_path);
+                let line_text = content
+                    .as_ref()
+                    .and_then(|content| {
+                        self.fixture_db
+                            .get_line_text(&file_path, content, internal_line)
+                    })
+                    .unwrap_or("");
+                let line_prefix = line_text
+                    .get(..(byte_col as usize).min(line_text.len()))
+                    .unwrap_or("");
+                return Ok(Some(limit_items(
+                    response,
+                    typed_word(line_prefix),
+                    max_items,
+                )));
             } else {
                 info!("No completion context found");
             }
@@ -255,37 +483,176 @@ impl Backend {
         Ok(None)
     }
 
+    /// Complete marker names after `pytest.mark.`: pytest's builtin marks and
+    /// the markers registered in the pytest configuration.
+    async fn marker_completion(
+        &self,
+        file_path: &std::path::Path,
+        position: Position,
+        byte_col: u32,
+    ) -> Option<CompletionResponse> {
+        let content = self.fixture_db.get_file_content(file_path)?;
+        let internal_line = Self::lsp_line_to_internal(position.line);
+        let line_text = self
+            .fixture_db
+            .get_line_text(file_path, &content, internal_line)
+            .unwrap_or("");
+        let line_prefix = line_text.get(..(byte_col as usize).min(line_text.len()))?;
+        let name_start = marker_name_start(line_prefix)?;
+
+        let range = Self::create_range(
+            position.line,
+            self.to_lsp_col(file_path, internal_line, name_start),
+            position.line,
+            position.character,
+        );
+        let workspace_root = self.workspace_root.read().await.clone();
+
+        let items = self
+            .fixture_db
+            .get_markers()
+            .into_iter()
+            .map(|marker| {
+                let (detail, priority) = match &marker.source {
+                    MarkerSource::Builtin => ("[builtin]".to_string(), 1),
+                    MarkerSource::Config(path) | MarkerSource::Registered(path) => {
+                        (Self::display_path(path, workspace_root.as_ref()), 0)
+                    }
+                };
+                let docs = Self::format_marker_documentation(&marker, workspace_root.as_ref());
+                CompletionItem {
+                    label: marker.name.clone(),
+                    kind: Some(CompletionItemKind::PROPERTY),
+                    detail: Some(detail),
+                    documentation: Some(Documentation::MarkupContent(MarkupContent {
+                        kind: MarkupKind::Markdown,
+                        value: docs,
+                    })),
+                    text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                        range,
+                        new_text: marker.name.clone(),
+                    })),
+                    sort_text: Some(make_sort_text(priority, &marker.name)),
+                    ..Default::default()
+                }
+            })
+            .collect();
+
+        Some(CompletionResponse::Array(items))
+    }
+
+    /// Offer a new fixture skeleton at module level or below a fixture decorator.
+    fn fixture_skeleton_completion(
+        &self,
+        file_path: &std::path::Path,
+        position: Position,
+        byte_col: u32,
+    ) -> Option<CompletionResponse> {
+        let content = self.fixture_db.get_file_content(file_path)?;
+        let lines: Vec<&str> = content.lines().collect();
+        let line = position.line as usize;
+        let line_text = lines.get(line).copied().unwrap_or("");
+        let line_prefix = line_text.get(..(byte_col as usize).min(line_text.len()))?;
+        let context = skeleton_context(line_prefix, &lines[..line.min(lines.len())])?;
+
+        let snippet = self.client_snippet_support.load(Ordering::Relaxed);
+        let detail = match context {
+            SkeletonContext::ModuleLevel => "New fixture",
+            SkeletonContext::AfterFixtureDecorator => "New fixture function",
+        };
+        Some(CompletionResponse::Array(vec![CompletionItem {
+            label: "fixture".to_string(),
+            kind: Some(CompletionItemKind::SNIPPET),
+            detail: Some(detail.to_string()),
+            text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                range: Self::create_range(position.line, 0, position.line, position.character),
+                new_text: fixture_skeleton(context, snippet),
+            })),
+            insert_text_format: Some(if snippet {
+                InsertTextFormat::SNIPPET
+            } else {
+                InsertTextFormat::PLAIN_TEXT
+            }),
+            ..Default::default()
+        }]))
+    }
+
+    /// Handle completionItem/resolve request.
+    ///
+    /// Fills in the documentation of fixture items created by
+    /// [`Self::handle_completion`]; other items are returned unchanged.
+    pub async fn handle_completion_resolve(
+        &self,
+        mut item: CompletionItem,
+    ) -> Result<CompletionItem> {
+        info!("completionItem/resolve request: label={}", item.label);
+
+        if item.documentation.is_some() {
+            return Ok(item);
+        }
+        let Some(data) = item
+            .data
+            .clone()
+            .and_then(|data| serde_json::from_value::<CompletionItemData>(data).ok())
+        else {
+            return Ok(item);
+        };
+        let Some(fixture) = self.resolve_completion_fixture(&data) else {
+            info!("Fixture for completion item {} no longer exists", data.name);
+            return Ok(item);
+        };
+
+        let workspace_root = self.workspace_root.read().await.clone();
+        item.documentation = Some(Documentation::MarkupContent(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: self.format_fixture_documentation(&fixture, workspace_root.as_ref()),
+        }));
+        Ok(item)
+    }
+
+    /// Find the definition a completion item was created for.
+    fn resolve_completion_fixture(&self, data: &CompletionItemData) -> Option<FixtureDefinition> {
+        let defined = self
+            .fixture_db
+            .definitions
+            .get(&data.name)
+            .and_then(|defs| {
+                defs.iter()
+                    .find(|def| def.file_path == data.path && def.line == data.line)
+                    .cloned()
+            });
+        // Builtins synthesized because scanning didn't find them
+        defined.or_else(|| builtin_definition(&data.name).filter(|def| def.file_path == data.path))
+    }
+
     /// Create completion items for fixtures (for function signature context)
     /// Filters out already-declared parameters and scope-incompatible fixtures
     pub(crate) fn create_fixture_completions(
         &self,
         file_path: &std::path::Path,
         declared_params: &[String],
-        workspace_root: Option<&PathBuf>,
+        function_line: usize,
         opts: &CompletionOpts<'_>,
     ) -> CompletionResponse {
-        let available = self.fixture_db.get_available_fixtures(file_path);
+        let available = with_builtin_fixtures(
+            &self
+                .fixture_db
+                .get_available_fixtures_at(file_path, function_line),
+        );
         let enriched =
             filter_and_enrich_fixtures(&available, file_path, Some(declared_params), opts);
 
         let items = enriched
             .into_iter()
-            .map(|ef| {
-                let documentation = Some(Documentation::MarkupContent(MarkupContent {
-                    kind: MarkupKind::Markdown,
-                    value: Self::format_fixture_documentation(&ef.fixture, workspace_root),
-                }));
-
-                CompletionItem {
-                    label: ef.fixture.name.clone(),
-                    kind: Some(CompletionItemKind::VARIABLE),
-                    detail: Some(ef.detail),
-                    documentation,
-                    insert_text: Some(format!("{}{}", opts.insert_prefix, ef.fixture.name)),
-                    insert_text_format: Some(InsertTextFormat::PLAIN_TEXT),
-                    sort_text: Some(ef.sort_text),
-                    ..Default::default()
-                }
+            .map(|ef| CompletionItem {
+                label: ef.fixture.name.clone(),
+                kind: Some(CompletionItemKind::VARIABLE),
+                detail: Some(ef.detail),
+                data: completion_item_data(&ef.fixture),
+                insert_text: Some(format!("{}{}", opts.insert_prefix, ef.fixture.name)),
+                insert_text_format: Some(InsertTextFormat::PLAIN_TEXT),
+                sort_text: Some(ef.sort_text),
+                ..Default::default()
             })
             .collect();
 
@@ -299,26 +666,28 @@ impl Backend {
         file_path: &std::path::Path,
         declared_params: &[String],
         function_line: usize,
-        workspace_root: Option<&PathBuf>,
         opts: &CompletionOpts<'_>,
     ) -> CompletionResponse {
-        let available = self.fixture_db.get_available_fixtures(file_path);
+        let available = with_builtin_fixtures(
+            &self
+                .fixture_db
+                .get_available_fixtures_at(file_path, function_line),
+        );
         let enriched =
             filter_and_enrich_fixtures(&available, file_path, Some(declared_params), opts);
 
-        // Get insertion info for adding new parameters
-        let insertion_info = self
-            .fixture_db
-            .get_function_param_insertion_info(file_path, function_line);
+        // Get insertion info for adding new parameters, unless the client
+        // would drop the edit
+        let insertion_info = if self.client_additional_edits_support.load(Ordering::Relaxed) {
+            self.fixture_db
+                .get_function_param_insertion_info(file_path, function_line)
+        } else {
+            None
+        };
 
         let items = enriched
             .into_iter()
             .map(|ef| {
-                let documentation = Some(Documentation::MarkupContent(MarkupContent {
-                    kind: MarkupKind::Markdown,
-                    value: Self::format_fixture_documentation(&ef.fixture, workspace_root),
-                }));
-
                 // Create additional text edit to add the fixture as a parameter
                 let additional_text_edits = insertion_info.as_ref().map(|info| {
                     let text = match &info.multiline_indent {
@@ -353,7 +722,7 @@ impl Backend {
                     label: ef.fixture.name.clone(),
                     kind: Some(CompletionItemKind::VARIABLE),
                     detail: Some(ef.detail),
-                    documentation,
+                    data: completion_item_data(&ef.fixture),
                     insert_text: Some(format!("{}{}", opts.insert_prefix, ef.fixture.name)),
                     insert_text_format: Some(InsertTextFormat::PLAIN_TEXT),
                     additional_text_edits,
@@ -372,35 +741,31 @@ impl Backend {
     pub(crate) fn create_string_fixture_completions(
         &self,
         file_path: &std::path::Path,
-        workspace_root: Option<&PathBuf>,
+        line: usize,
         insert_prefix: &str,
+        settings: &CompletionSettings,
     ) -> CompletionResponse {
-        let available = self.fixture_db.get_available_fixtures(file_path);
+        let available =
+            with_builtin_fixtures(&self.fixture_db.get_available_fixtures_at(file_path, line));
         let no_filter_opts = CompletionOpts {
             fixture_scope: None,
             current_fixture_name: None,
             insert_prefix,
+            settings,
         };
         let enriched = filter_and_enrich_fixtures(&available, file_path, None, &no_filter_opts);
 
         let items = enriched
             .into_iter()
-            .map(|ef| {
-                let documentation = Some(Documentation::MarkupContent(MarkupContent {
-                    kind: MarkupKind::Markdown,
-                    value: Self::format_fixture_documentation(&ef.fixture, workspace_root),
-                }));
-
-                CompletionItem {
-                    label: ef.fixture.name.clone(),
-                    kind: Some(CompletionItemKind::TEXT),
-                    detail: Some(ef.detail),
-                    documentation,
-                    insert_text: Some(format!("{}{}", insert_prefix, ef.fixture.name)),
-                    insert_text_format: Some(InsertTextFormat::PLAIN_TEXT),
-                    sort_text: Some(ef.sort_text),
-                    ..Default::default()
-                }
+            .map(|ef| CompletionItem {
+                label: ef.fixture.name.clone(),
+                kind: Some(CompletionItemKind::TEXT),
+                detail: Some(ef.detail),
+                data: completion_item_data(&ef.fixture),
+                insert_text: Some(format!("{}{}", insert_prefix, ef.fixture.name)),
+                insert_text_format: Some(InsertTextFormat::PLAIN_TEXT),
+                sort_text: Some(ef.sort_text),
+                ..Default::default()
             })
             .collect();
 
@@ -411,6 +776,7 @@ impl Backend {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fixtures::builtins::BUILTIN_FIXTURES;
     use crate::fixtures::types::FixtureScope;
     use crate::fixtures::FixtureDatabase;
     use std::path::PathBuf;
@@ -427,12 +793,15 @@ mod tests {
             docstring: None,
             return_type: None,
             return_type_imports: vec![],
+            inferred_return_type: None,
             is_third_party: false,
             is_plugin: false,
             dependencies: vec![],
             scope,
             yield_line: None,
             autouse: false,
+            class_name: None,
+            params: None,
         }
     }
 
@@ -532,6 +901,7 @@ mod tests {
             fixture_scope: None,
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
         assert!(is_fixture_excluded(&self_fixture, None, &opts));
         assert!(is_fixture_excluded(&cls_fixture, None, &opts));
@@ -547,6 +917,7 @@ mod tests {
             fixture_scope: None,
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
         assert!(is_fixture_excluded(&fixture, Some(&declared), &opts));
         assert!(!is_fixture_excluded(&fixture, None, &opts));
@@ -568,6 +939,7 @@ mod tests {
             fixture_scope: session_scope,
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
         assert!(is_fixture_excluded(&func_fixture, Some(&declared), &opts,));
 
@@ -580,6 +952,7 @@ mod tests {
             fixture_scope: session_scope,
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
         assert!(is_fixture_excluded(
             &make_fixture("db", FixtureScope::Session),
@@ -595,6 +968,70 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_is_fixture_excluded_third_party() {
+        let mut mocker = make_fixture("mocker", FixtureScope::Function);
+        mocker.is_third_party = true;
+        let opts = CompletionOpts {
+            fixture_scope: None,
+            current_fixture_name: None,
+            insert_prefix: "",
+            settings: &CompletionSettings {
+                third_party: false,
+                ..CompletionSettings::default()
+            },
+        };
+        assert!(is_fixture_excluded(&mocker, None, &opts));
+        assert!(!is_fixture_excluded(
+            &make_fixture("db", FixtureScope::Function),
+            None,
+            &opts
+        ));
+
+        // pytest's own fixtures are not third-party ones
+        let tmp_path = BUILTIN_FIXTURES
+            .iter()
+            .find(|b| b.name == "tmp_path")
+            .unwrap()
+            .to_definition();
+        assert!(!is_fixture_excluded(&tmp_path, None, &opts));
+    }
+
+    #[test]
+    fn test_is_fixture_excluded_third_party_packages() {
+        let mut mocker = make_fixture("mocker", FixtureScope::Function);
+        mocker.is_third_party = true;
+        mocker.file_path = PathBuf::from("/venv/lib/site-packages/pytest_mock/plugin.py");
+        let mut settings_fixture = make_fixture("settings", FixtureScope::Function);
+        settings_fixture.is_third_party = true;
+        settings_fixture.file_path =
+            PathBuf::from("/venv/lib/site-packages/pytest_django/fixtures.py");
+
+        let allow_mock = CompletionSettings {
+            third_party_allow: vec!["pytest-mock".to_string()],
+            ..CompletionSettings::default()
+        };
+        let opts = CompletionOpts {
+            fixture_scope: None,
+            current_fixture_name: None,
+            insert_prefix: "",
+            settings: &allow_mock,
+        };
+        assert!(!is_fixture_excluded(&mocker, None, &opts));
+        assert!(is_fixture_excluded(&settings_fixture, None, &opts));
+
+        let deny_django = CompletionSettings {
+            third_party_deny: vec!["pytest-django".to_string()],
+            ..CompletionSettings::default()
+        };
+        let opts = CompletionOpts {
+            settings: &deny_django,
+            ..opts
+        };
+        assert!(!is_fixture_excluded(&mocker, None, &opts));
+        assert!(is_fixture_excluded(&settings_fixture, None, &opts));
+    }
+
     // =========================================================================
     // Unit tests for filter_and_enrich_fixtures
     // =========================================================================
@@ -612,6 +1049,7 @@ mod tests {
             fixture_scope: Some(FixtureScope::Function),
             current_fixture_name: Some("my_fixture"),
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
         let enriched = filter_and_enrich_fixtures(&fixtures, file, None, &opts);
         assert_eq!(enriched.len(), 1);
@@ -622,6 +1060,7 @@ mod tests {
             fixture_scope: None,
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
         let enriched = filter_and_enrich_fixtures(&fixtures, file, None, &test_opts);
         assert_eq!(enriched.len(), 2);
@@ -642,6 +1081,7 @@ mod tests {
             fixture_scope: Some(FixtureScope::Session),
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
         let enriched = filter_and_enrich_fixtures(&fixtures, &file_path, Some(&[]), &opts);
         let names: Vec<&str> = enriched.iter().map(|e| e.fixture.name.as_str()).collect();
@@ -652,6 +1092,7 @@ mod tests {
             fixture_scope: Some(FixtureScope::Module),
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
         let enriched = filter_and_enrich_fixtures(&fixtures, &file_path, Some(&[]), &opts);
         let names: Vec<&str> = enriched.iter().map(|e| e.fixture.name.as_str()).collect();
@@ -662,6 +1103,7 @@ mod tests {
             fixture_scope: Some(FixtureScope::Function),
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
         let enriched = filter_and_enrich_fixtures(&fixtures, &file_path, Some(&[]), &opts);
         assert_eq!(enriched.len(), 4);
@@ -671,6 +1113,7 @@ mod tests {
             fixture_scope: None,
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
         let enriched = filter_and_enrich_fixtures(&fixtures, &file_path, Some(&[]), &opts);
         assert_eq!(enriched.len(), 4);
@@ -690,6 +1133,7 @@ mod tests {
             fixture_scope: None,
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
         let enriched = filter_and_enrich_fixtures(&fixtures, &file_path, Some(&declared), &opts);
         let names: Vec<&str> = enriched.iter().map(|e| e.fixture.name.as_str()).collect();
@@ -712,6 +1156,7 @@ mod tests {
             fixture_scope: None,
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
         let enriched = filter_and_enrich_fixtures(&fixtures, &file_path, None, &opts);
         let names: Vec<&str> = enriched.iter().map(|e| e.fixture.name.as_str()).collect();
@@ -772,6 +1217,39 @@ mod tests {
         assert_eq!(fixture_sort_priority(&fixture, &current), 3);
     }
 
+    #[test]
+    fn test_fixture_sort_priority_builtin_after_third_party() {
+        let current = PathBuf::from("/tmp/test/test_file.py");
+        let mut fixture = make_fixture("tmp_path", FixtureScope::Function);
+        fixture.file_path = PathBuf::from("/tmp/venv/lib/site-packages/_pytest/tmpdir.py");
+        fixture.is_third_party = true;
+
+        assert_eq!(fixture_sort_priority(&fixture, &current), 4);
+        assert_eq!(make_fixture_detail(&fixture), "[builtin]");
+    }
+
+    #[test]
+    fn test_with_builtin_fixtures_fills_only_missing_names() {
+        let mut scanned = make_fixture("tmp_path", FixtureScope::Function);
+        scanned.file_path = PathBuf::from("/venv/site-packages/_pytest/tmpdir.py");
+        let fixtures = with_builtin_fixtures(&[scanned.clone()]);
+
+        assert_eq!(fixtures.len(), BUILTIN_FIXTURES.len());
+        assert_eq!(
+            fixtures.iter().filter(|f| f.name == "tmp_path").count(),
+            1,
+            "scanned builtin must not be duplicated"
+        );
+        assert_eq!(fixtures[0], scanned);
+
+        let factory = fixtures
+            .iter()
+            .find(|f| f.name == "tmp_path_factory")
+            .unwrap();
+        assert_eq!(factory.scope, FixtureScope::Session);
+        assert_eq!(make_fixture_detail(factory), "(session) [builtin]");
+    }
+
     // =========================================================================
     // Unit tests for make_fixture_detail
     // =========================================================================
@@ -816,6 +1294,28 @@ mod tests {
         assert_eq!(detail, "(session) [third-party]");
     }
 
+    #[test]
+    fn test_make_fixture_detail_params() {
+        let mut fixture = make_fixture("f", FixtureScope::Session);
+        fixture.params = Some(crate::fixtures::FixtureParams {
+            ids: vec!["sqlite".to_string(), "postgres".to_string()],
+            expression: None,
+        });
+        assert_eq!(
+            make_fixture_detail(&fixture),
+            "(session) parametrized ×2: sqlite, postgres"
+        );
+
+        fixture.params = Some(crate::fixtures::FixtureParams {
+            ids: Vec::new(),
+            expression: Some("BACKENDS".to_string()),
+        });
+        assert_eq!(
+            make_fixture_detail(&fixture),
+            "(session) parametrized: BACKENDS"
+        );
+    }
+
     // =========================================================================
     // Unit tests for make_sort_text
     // =========================================================================
@@ -840,6 +1340,143 @@ mod tests {
         assert!(a < b);
     }
 
+    // =========================================================================
+    // Tests for limit_items
+    // =========================================================================
+
+    #[test]
+    fn test_typed_word() {
+        assert_eq!(typed_word("    db_con"), "db_con");
+        assert_eq!(typed_word("def test_x(a, "), "");
+        assert_eq!(typed_word("usefixtures(\"dé"), "dé");
+        assert_eq!(typed_word(""), "");
+    }
+
+    #[test]
+    fn test_limit_items() {
+        let item = |label: &str, priority: u8| CompletionItem {
+            label: label.to_string(),
+            sort_text: Some(make_sort_text(priority, label)),
+            ..Default::default()
+        };
+        let response = || {
+            CompletionResponse::Array(vec![
+                item("tmp_path", 4),
+                item("db_session", 1),
+                item("database", 0),
+                item("mocker", 3),
+            ])
+        };
+        let labels = |response: CompletionResponse| match response {
+            CompletionResponse::List(list) => {
+                assert!(list.is_incomplete);
+                list.items.into_iter().map(|i| i.label).collect::<Vec<_>>()
+            }
+            CompletionResponse::Array(_) => panic!("expected an incomplete list"),
+        };
+
+        // Short enough lists are returned as they are
+        assert!(matches!(
+            limit_items(response(), "", 4),
+            CompletionResponse::Array(items) if items.len() == 4
+        ));
+
+        assert_eq!(
+            labels(limit_items(response(), "", 2)),
+            ["database", "db_session"]
+        );
+        // Case-insensitive subsequence matches
+        assert_eq!(labels(limit_items(response(), "B_S", 2)), ["db_session"]);
+        assert_eq!(labels(limit_items(response(), "mk", 1)), ["mocker"]);
+        assert!(labels(limit_items(response(), "xyz", 1)).is_empty());
+    }
+
+    // =========================================================================
+    // Tests for skeleton_context / fixture_skeleton
+    // =========================================================================
+
+    #[test]
+    fn test_skeleton_context_module_level() {
+        let above = ["import pytest", "", "def helper():", "    return 1", ""];
+        assert_eq!(
+            skeleton_context("fix", &above),
+            Some(SkeletonContext::ModuleLevel)
+        );
+        assert_eq!(
+            skeleton_context("", &[]),
+            Some(SkeletonContext::ModuleLevel)
+        );
+    }
+
+    #[test]
+    fn test_skeleton_context_after_fixture_decorator() {
+        for decorator in [
+            "@pytest.fixture",
+            "@pytest.fixture(scope=\"session\")",
+            "@pytest_asyncio.fixture(loop_scope=\"module\")",
+            "@fixture",
+        ] {
+            assert_eq!(
+                skeleton_context("", &["import pytest", decorator]),
+                Some(SkeletonContext::AfterFixtureDecorator),
+                "{}",
+                decorator
+            );
+        }
+        assert_eq!(
+            skeleton_context("", &["@pytest.mark.skip"]),
+            None,
+            "other decorators get no skeleton"
+        );
+    }
+
+    #[test]
+    fn test_skeleton_context_rejects_code_positions() {
+        // Indented, or after other text on the line
+        assert_eq!(skeleton_context("    fix", &[]), None);
+        assert_eq!(skeleton_context("x = fix", &[]), None);
+        // Right after a block opener or inside a continued statement
+        assert_eq!(skeleton_context("", &["def test_x():"]), None);
+        assert_eq!(skeleton_context("", &["VALUES = ["]), None);
+        assert_eq!(
+            skeleton_context("", &["@pytest.fixture(", "    scope=\"module\","]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_marker_name_start() {
+        assert_eq!(marker_name_start("@pytest.mark."), Some(13));
+        assert_eq!(marker_name_start("@pytest.mark.sl"), Some(13));
+        assert_eq!(marker_name_start("pytestmark = [pytest.mark.sk"), Some(26));
+        assert_eq!(marker_name_start("@pytest.fixture"), None);
+        assert_eq!(marker_name_start("@other.pytest.mark."), None);
+        assert_eq!(marker_name_start("@mypytest.mark."), None);
+        assert_eq!(marker_name_start("@pytest.mark.skip("), None);
+    }
+
+    #[test]
+    fn test_fixture_skeleton_snippet() {
+        assert_eq!(
+            fixture_skeleton(SkeletonContext::ModuleLevel, true),
+            "@pytest.fixture(scope=\"${2|function,class,module,package,session|}\")\n\
+             def ${1:fixture_name}() -> ${3:None}:\n    ${0:pass}"
+        );
+        assert_eq!(
+            fixture_skeleton(SkeletonContext::AfterFixtureDecorator, true),
+            "def ${1:fixture_name}() -> ${3:None}:\n    ${0:pass}"
+        );
+    }
+
+    #[test]
+    fn test_fixture_skeleton_plain_text() {
+        assert_eq!(
+            fixture_skeleton(SkeletonContext::ModuleLevel, false),
+            "@pytest.fixture\ndef fixture_name() -> None:\n    pass"
+        );
+        assert!(!fixture_skeleton(SkeletonContext::AfterFixtureDecorator, false).contains('$'));
+    }
+
     // =========================================================================
     // Integration tests with Backend
     // =========================================================================
@@ -926,8 +1563,9 @@ def test_something(func_fixture):
             fixture_scope: None,
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
-        let response = backend.create_fixture_completions(&test_path, &declared, None, &opts);
+        let response = backend.create_fixture_completions(&test_path, &declared, 1, &opts);
         let items = extract_items(&response);
         assert!(!items.is_empty(), "Should return completion items");
         // All items should have VARIABLE kind
@@ -947,8 +1585,9 @@ def test_something(func_fixture):
             fixture_scope: None,
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
-        let response = backend.create_fixture_completions(&test_path, &declared, None, &opts);
+        let response = backend.create_fixture_completions(&test_path, &declared, 1, &opts);
         let items = extract_items(&response);
         let labels: Vec<&str> = items.iter().map(|i| i.label.as_str()).collect();
         assert!(
@@ -966,8 +1605,9 @@ def test_something(func_fixture):
             fixture_scope: Some(FixtureScope::Session),
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
-        let response = backend.create_fixture_completions(&test_path, &declared, None, &opts);
+        let response = backend.create_fixture_completions(&test_path, &declared, 1, &opts);
         let items = extract_items(&response);
         let labels: Vec<&str> = items.iter().map(|i| i.label.as_str()).collect();
         assert!(
@@ -989,8 +1629,9 @@ def test_something(func_fixture):
             fixture_scope: None,
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
-        let response = backend.create_fixture_completions(&test_path, &declared, None, &opts);
+        let response = backend.create_fixture_completions(&test_path, &declared, 1, &opts);
         let items = extract_items(&response);
 
         // Find the session_fixture — it should have scope in detail
@@ -1022,36 +1663,25 @@ def test_something(func_fixture):
             fixture_scope: None,
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
-        let response = backend.create_fixture_completions(&test_path, &declared, None, &opts);
+        let response = backend.create_fixture_completions(&test_path, &declared, 1, &opts);
         let items = extract_items(&response);
 
-        // All items should have documentation
+        // Documentation is deferred to completionItem/resolve; items carry
+        // the data identifying their definition instead
         for item in items {
             assert!(
-                item.documentation.is_some(),
-                "Completion item '{}' should have documentation",
+                item.documentation.is_none(),
+                "Completion item '{}' should not have documentation yet",
                 item.label
             );
+            let data: CompletionItemData =
+                serde_json::from_value(item.data.clone().expect("data should be set")).unwrap();
+            assert_eq!(data.name, item.label);
         }
     }
 
-    #[test]
-    fn test_create_fixture_completions_with_workspace_root() {
-        let (backend, test_path) = setup_backend_with_fixtures();
-        let declared = vec![];
-        let workspace_root = PathBuf::from("/tmp/test_backend");
-        let opts = CompletionOpts {
-            fixture_scope: None,
-            current_fixture_name: None,
-            insert_prefix: "",
-        };
-        let response =
-            backend.create_fixture_completions(&test_path, &declared, Some(&workspace_root), &opts);
-        let items = extract_items(&response);
-        assert!(!items.is_empty());
-    }
-
     // =========================================================================
     // Tests for create_fixture_completions_with_auto_add
     // =========================================================================
@@ -1064,11 +1694,12 @@ def test_something(func_fixture):
             fixture_scope: None,
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
         // function_line is 1-based internal line of `def test_something(func_fixture):`
         // In test_content, test_something is at line 8 (1-indexed)
         let response =
-            backend.create_fixture_completions_with_auto_add(&test_path, &declared, 8, None, &opts);
+            backend.create_fixture_completions_with_auto_add(&test_path, &declared, 8, &opts);
         let items = extract_items(&response);
         assert!(!items.is_empty(), "Should return completion items");
         for item in items {
@@ -1087,9 +1718,10 @@ def test_something(func_fixture):
             fixture_scope: None,
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
         let response =
-            backend.create_fixture_completions_with_auto_add(&test_path, &declared, 8, None, &opts);
+            backend.create_fixture_completions_with_auto_add(&test_path, &declared, 8, &opts);
         let items = extract_items(&response);
         // Items should have additional_text_edits to add parameter
         for item in items {
@@ -1103,6 +1735,30 @@ def test_something(func_fixture):
         }
     }
 
+    #[test]
+    fn test_create_fixture_completions_without_additional_edits_support() {
+        let (backend, test_path) = setup_backend_with_fixtures();
+        backend
+            .client_additional_edits_support
+            .store(false, Ordering::Relaxed);
+        let declared = vec!["func_fixture".to_string()];
+        let opts = CompletionOpts {
+            fixture_scope: None,
+            current_fixture_name: None,
+            insert_prefix: "",
+            settings: &CompletionSettings::default(),
+        };
+        let response =
+            backend.create_fixture_completions_with_auto_add(&test_path, &declared, 8, &opts);
+        let items = extract_items(&response);
+        assert!(!items.is_empty());
+        // Plain inserts only: the client would drop the parameter edit
+        for item in items {
+            assert!(item.additional_text_edits.is_none());
+            assert_eq!(item.insert_text_format, Some(InsertTextFormat::PLAIN_TEXT));
+        }
+    }
+
     #[test]
     fn test_create_fixture_completions_with_auto_add_scope_filter() {
         let (backend, test_path) = setup_backend_with_fixtures();
@@ -1111,9 +1767,10 @@ def test_something(func_fixture):
             fixture_scope: Some(FixtureScope::Session),
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
         let response =
-            backend.create_fixture_completions_with_auto_add(&test_path, &declared, 8, None, &opts);
+            backend.create_fixture_completions_with_auto_add(&test_path, &declared, 8, &opts);
         let items = extract_items(&response);
         let labels: Vec<&str> = items.iter().map(|i| i.label.as_str()).collect();
         assert!(
@@ -1130,9 +1787,10 @@ def test_something(func_fixture):
             fixture_scope: None,
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
         let response =
-            backend.create_fixture_completions_with_auto_add(&test_path, &declared, 8, None, &opts);
+            backend.create_fixture_completions_with_auto_add(&test_path, &declared, 8, &opts);
         let items = extract_items(&response);
         let labels: Vec<&str> = items.iter().map(|i| i.label.as_str()).collect();
         assert!(
@@ -1153,8 +1811,9 @@ def test_something(func_fixture):
             fixture_scope: Some(FixtureScope::Function),
             current_fixture_name: Some("func_fixture"),
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
-        let response = backend.create_fixture_completions(&file_path, &[], None, &opts);
+        let response = backend.create_fixture_completions(&file_path, &[], 1, &opts);
         let items = extract_items(&response);
         let labels: Vec<&str> = items.iter().map(|i| i.label.as_str()).collect();
         assert!(
@@ -1177,8 +1836,9 @@ def test_something(func_fixture):
             fixture_scope: None,
             current_fixture_name: None,
             insert_prefix: " ",
+            settings: &CompletionSettings::default(),
         };
-        let response = backend.create_fixture_completions(&test_path, &declared, None, &opts);
+        let response = backend.create_fixture_completions(&test_path, &declared, 1, &opts);
         let items = extract_items(&response);
         assert!(!items.is_empty());
         for item in items {
@@ -1200,8 +1860,9 @@ def test_something(func_fixture):
             fixture_scope: None,
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
-        let response = backend.create_fixture_completions(&test_path, &declared, None, &opts);
+        let response = backend.create_fixture_completions(&test_path, &declared, 1, &opts);
         let items = extract_items(&response);
         assert!(!items.is_empty());
         for item in items {
@@ -1245,9 +1906,10 @@ def test_empty_params():
             fixture_scope: None,
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
         let response =
-            backend.create_fixture_completions_with_auto_add(&test_path, &declared, 2, None, &opts);
+            backend.create_fixture_completions_with_auto_add(&test_path, &declared, 2, &opts);
         let items = extract_items(&response);
         assert!(!items.is_empty(), "Should return completion items");
 
@@ -1271,7 +1933,12 @@ def test_empty_params():
     #[test]
     fn test_create_string_fixture_completions_returns_items() {
         let (backend, test_path) = setup_backend_with_fixtures();
-        let response = backend.create_string_fixture_completions(&test_path, None, "");
+        let response = backend.create_string_fixture_completions(
+            &test_path,
+            1,
+            "",
+            &CompletionSettings::default(),
+        );
         let items = extract_items(&response);
         assert!(!items.is_empty(), "Should return string completion items");
         // String completions use TEXT kind
@@ -1283,7 +1950,7 @@ def test_empty_params():
             );
             assert!(item.sort_text.is_some());
             assert!(item.detail.is_some());
-            assert!(item.documentation.is_some());
+            assert!(item.data.is_some());
         }
     }
 
@@ -1291,7 +1958,12 @@ def test_empty_params():
     fn test_create_string_fixture_completions_no_scope_filtering() {
         let (backend, test_path) = setup_backend_with_fixtures();
         // String completions should NOT filter by scope
-        let response = backend.create_string_fixture_completions(&test_path, None, "");
+        let response = backend.create_string_fixture_completions(
+            &test_path,
+            1,
+            "",
+            &CompletionSettings::default(),
+        );
         let items = extract_items(&response);
         let labels: Vec<&str> = items.iter().map(|i| i.label.as_str()).collect();
         // Both function and session scoped fixtures should be present
@@ -1307,20 +1979,15 @@ def test_empty_params():
         );
     }
 
-    #[test]
-    fn test_create_string_fixture_completions_with_workspace_root() {
-        let (backend, test_path) = setup_backend_with_fixtures();
-        let workspace_root = PathBuf::from("/tmp/test_backend");
-        let response =
-            backend.create_string_fixture_completions(&test_path, Some(&workspace_root), "");
-        let items = extract_items(&response);
-        assert!(!items.is_empty());
-    }
-
     #[test]
     fn test_create_string_fixture_completions_has_detail_and_sort() {
         let (backend, test_path) = setup_backend_with_fixtures();
-        let response = backend.create_string_fixture_completions(&test_path, None, "");
+        let response = backend.create_string_fixture_completions(
+            &test_path,
+            1,
+            "",
+            &CompletionSettings::default(),
+        );
         let items = extract_items(&response);
 
         let session_item = items.iter().find(|i| i.label == "session_fixture");
@@ -1352,10 +2019,18 @@ def test_empty_params():
             fixture_scope: None,
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
-        let response = backend.create_fixture_completions(&path, &[], None, &opts);
+        let response = backend.create_fixture_completions(&path, &[], 1, &opts);
         let items = extract_items(&response);
-        assert!(items.is_empty(), "Empty DB should return no completions");
+        assert_eq!(items.len(), BUILTIN_FIXTURES.len());
+        assert!(
+            items.iter().all(|item| item
+                .detail
+                .as_deref()
+                .is_some_and(|d| d.ends_with("[builtin]"))),
+            "Empty DB should only offer pytest builtins"
+        );
     }
 
     #[test]
@@ -1367,10 +2042,18 @@ def test_empty_params():
             fixture_scope: None,
             current_fixture_name: None,
             insert_prefix: "",
+            settings: &CompletionSettings::default(),
         };
-        let response = backend.create_fixture_completions_with_auto_add(&path, &[], 1, None, &opts);
+        let response = backend.create_fixture_completions_with_auto_add(&path, &[], 1, &opts);
         let items = extract_items(&response);
-        assert!(items.is_empty(), "Empty DB should return no completions");
+        assert_eq!(items.len(), BUILTIN_FIXTURES.len());
+        assert!(
+            items.iter().all(|item| item
+                .detail
+                .as_deref()
+                .is_some_and(|d| d.ends_with("[builtin]"))),
+            "Empty DB should only offer pytest builtins"
+        );
     }
 
     #[test]
@@ -1378,8 +2061,16 @@ def test_empty_params():
         let db = Arc::new(FixtureDatabase::new());
         let backend = make_backend_with_db(db);
         let path = PathBuf::from("/tmp/empty/test_file.py");
-        let response = backend.create_string_fixture_completions(&path, None, "");
+        let response =
+            backend.create_string_fixture_completions(&path, 1, "", &CompletionSettings::default());
         let items = extract_items(&response);
-        assert!(items.is_empty(), "Empty DB should return no completions");
+        assert_eq!(items.len(), BUILTIN_FIXTURES.len());
+        assert!(
+            items.iter().all(|item| item
+                .detail
+                .as_deref()
+                .is_some_and(|d| d.ends_with("[builtin]"))),
+            "Empty DB should only offer pytest builtins"
+        );
     }
 }
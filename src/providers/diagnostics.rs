@@ -1,111 +1,129 @@
 //! Diagnostics provider for pytest fixtures.
 
 use super::Backend;
+use crate::config::{find_rule, Config, RuleSeverity};
+use crate::fixtures::RelatedLocation;
 use tower_lsp_server::ls_types::*;
 use tracing::info;
 
+/// Rules reporting code that can be removed, which editors fade out.
+const UNNECESSARY_RULES: &[&str] = &[
+    "redundant-usefixtures",
+    "unused-fixture-parameter",
+    "unused-fixture",
+];
+
+/// Rules reporting deprecated code, which editors strike through.
+const DEPRECATED_RULES: &[&str] = &["deprecated-yield-fixture"];
+
 impl Backend {
-    /// Publish diagnostics for undeclared fixtures and circular dependencies in a file
+    /// Publish diagnostics for undeclared fixtures, circular dependencies, scope mismatches,
+    /// invalid parametrize `indirect=` names, unregistered markers, fixture
+    /// parameters on `unittest.TestCase` tests, redundant `usefixtures`
+    /// entries, unused fixture parameters, unused fixtures, fixtures shadowing
+    /// plugin ones and deprecated `yield_fixture` decorators in a file
     pub async fn publish_diagnostics_for_file(&self, uri: &Uri, file_path: &std::path::Path) {
-        let mut diagnostics: Vec<Diagnostic> = Vec::new();
+        self.report_analysis_failures();
 
-        // Get config to check for disabled diagnostics
+        // Get config to check for disabled diagnostics and severity overrides
         let config = self.config.read().await;
         let config = &*config; // Dereference the RwLockReadGuard
 
-        // Collect undeclared fixture diagnostics (if not disabled)
-        if !config.is_diagnostic_disabled("undeclared-fixture") {
-            let undeclared = self.fixture_db.get_undeclared_fixtures(file_path);
-            for fixture in undeclared {
-                let line = Self::internal_line_to_lsp(fixture.line);
-                diagnostics.push(Diagnostic {
-                    range: Self::create_range(
-                        line,
-                        self.to_lsp_col(file_path, fixture.line, fixture.start_char),
-                        line,
-                        self.to_lsp_col(file_path, fixture.line, fixture.end_char),
-                    ),
-                    severity: Some(DiagnosticSeverity::WARNING),
-                    code: Some(NumberOrString::String("undeclared-fixture".to_string())),
-                    code_description: None,
-                    source: Some("pytest-lsp".to_string()),
-                    message: format!(
-                        "Fixture '{}' is used but not declared as a parameter",
-                        fixture.name
-                    ),
-                    related_information: None,
-                    tags: None,
-                    data: None,
-                });
-            }
-        }
+        // Until the workspace scan completes, usages in unscanned files are
+        // unknown; the open files are refreshed once it finishes
+        let scanning = self.scan_progress.lock().unwrap().is_some();
 
-        // Collect circular dependency diagnostics (if not disabled)
-        if !config.is_diagnostic_disabled("circular-dependency") {
-            let cycles = self.fixture_db.detect_fixture_cycles_in_file(file_path);
-            for cycle in cycles {
-                let line = Self::internal_line_to_lsp(cycle.fixture.line);
-                let cycle_str = cycle.cycle_path.join(" → ");
-                diagnostics.push(Diagnostic {
+        let diagnostics: Vec<Diagnostic> = self
+            .fixture_db
+            .check_file(file_path, |rule| {
+                !(scanning && rule == "unused-fixture") && config.effective_severity(rule).is_some()
+            })
+            .into_iter()
+            .filter_map(|finding| {
+                let severity = Self::rule_severity(config, finding.rule)?;
+                let line = Self::internal_line_to_lsp(finding.line);
+                Some(Diagnostic {
                     range: Self::create_range(
                         line,
-                        self.to_lsp_col(file_path, cycle.fixture.line, cycle.fixture.start_char),
+                        self.to_lsp_col(file_path, finding.line, finding.start_char),
                         line,
-                        self.to_lsp_col(file_path, cycle.fixture.line, cycle.fixture.end_char),
+                        self.to_lsp_col(file_path, finding.line, finding.end_char),
                     ),
-                    severity: Some(DiagnosticSeverity::ERROR),
-                    code: Some(NumberOrString::String("circular-dependency".to_string())),
+                    severity: Some(severity),
+                    code: Some(Self::rule_code(finding.rule)),
                     code_description: None,
                     source: Some("pytest-lsp".to_string()),
-                    message: format!("Circular fixture dependency detected: {}", cycle_str),
-                    related_information: None,
-                    tags: None,
+                    message: finding.message,
+                    related_information: self.related_information(&finding.related),
+                    tags: if UNNECESSARY_RULES.contains(&finding.rule) {
+                        Some(vec![DiagnosticTag::UNNECESSARY])
+                    } else if DEPRECATED_RULES.contains(&finding.rule) {
+                        Some(vec![DiagnosticTag::DEPRECATED])
+                    } else {
+                        None
+                    },
                     data: None,
-                });
-            }
-        }
-
-        // Collect scope mismatch diagnostics (if not disabled)
-        if !config.is_diagnostic_disabled("scope-mismatch") {
-            let mismatches = self.fixture_db.detect_scope_mismatches_in_file(file_path);
-            for mismatch in mismatches {
-                let line = Self::internal_line_to_lsp(mismatch.fixture.line);
-                diagnostics.push(Diagnostic {
-                    range: Self::create_range(
-                        line,
-                        self.to_lsp_col(
-                            file_path,
-                            mismatch.fixture.line,
-                            mismatch.fixture.start_char,
-                        ),
-                        line,
-                        self.to_lsp_col(
-                            file_path,
-                            mismatch.fixture.line,
-                            mismatch.fixture.end_char,
-                        ),
-                    ),
-                    severity: Some(DiagnosticSeverity::WARNING),
-                    code: Some(NumberOrString::String("scope-mismatch".to_string())),
-                    code_description: None,
-                    source: Some("pytest-lsp".to_string()),
-                    message: format!(
-                        "{}-scoped fixture '{}' depends on {}-scoped fixture '{}'",
-                        mismatch.fixture.scope.as_str(),
-                        mismatch.fixture.name,
-                        mismatch.dependency.scope.as_str(),
-                        mismatch.dependency.name
-                    ),
-                    related_information: None,
-                    tags: None,
-                    data: None,
-                });
-            }
-        }
+                })
+            })
+            .collect();
 
         info!("Publishing {} diagnostics for {:?}", diagnostics.len(), uri);
         self.client
             .publish_diagnostics(uri.clone(), diagnostics, None)
             .await;
     }
+
+    /// The related locations of a finding, skipping files without a URI.
+    fn related_information(
+        &self,
+        related: &[RelatedLocation],
+    ) -> Option<Vec<DiagnosticRelatedInformation>> {
+        if related.is_empty() {
+            return None;
+        }
+        Some(
+            related
+                .iter()
+                .filter_map(|location| {
+                    let line = Self::internal_line_to_lsp(location.line);
+                    Some(DiagnosticRelatedInformation {
+                        location: Location {
+                            uri: self.path_to_uri(&location.file_path)?,
+                            range: Self::create_range(
+                                line,
+                                self.to_lsp_col(
+                                    &location.file_path,
+                                    location.line,
+                                    location.start_char,
+                                ),
+                                line,
+                                self.to_lsp_col(
+                                    &location.file_path,
+                                    location.line,
+                                    location.end_char,
+                                ),
+                            ),
+                        },
+                        message: location.message.clone(),
+                    })
+                })
+                .collect(),
+        )
+    }
+
+    /// Effective severity for a rule, or `None` when the rule is disabled.
+    fn rule_severity(config: &Config, name: &str) -> Option<DiagnosticSeverity> {
+        Some(match config.effective_severity(name)? {
+            RuleSeverity::Error => DiagnosticSeverity::ERROR,
+            RuleSeverity::Warning => DiagnosticSeverity::WARNING,
+            RuleSeverity::Information => DiagnosticSeverity::INFORMATION,
+            RuleSeverity::Hint | RuleSeverity::Off => DiagnosticSeverity::HINT,
+        })
+    }
+
+    /// Stable `PTLxxx` diagnostic code for a rule name.
+    fn rule_code(name: &str) -> NumberOrString {
+        let id = find_rule(name).map_or(name, |rule| rule.id);
+        NumberOrString::String(id.to_string())
+    }
 }
@@ -0,0 +1,169 @@
+//! Open document contents for incremental text synchronization.
+//!
+//! Clients send `didChange` notifications as range edits; each open document
+//! is kept as a rope so an edit costs a splice rather than a resend of the
+//! whole file. Re-analysis of edits is debounced: the first edit after a
+//! quiet period is analyzed right away, later edits in the same burst once
+//! typing pauses (or as soon as a request needs the document).
+
+use super::{Backend, PositionEncoding};
+use ropey::Rope;
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tower_lsp_server::ls_types::{Position, TextDocumentContentChangeEvent};
+use tracing::info;
+
+/// An open document.
+pub struct Document {
+    /// The document's current contents.
+    pub text: Rope,
+    /// Whether the latest edits have not been analyzed yet.
+    pub(crate) pending: bool,
+    /// When the document was last edited.
+    pub(crate) last_change: Option<Instant>,
+}
+
+impl Document {
+    pub(crate) fn new(text: &str) -> Self {
+        Self {
+            text: Rope::from_str(text),
+            pending: false,
+            last_change: None,
+        }
+    }
+}
+
+/// The char index of an LSP position in `rope`. Lines past the end clamp to
+/// the end of the document, columns past the end of a line to its end.
+fn position_to_char(rope: &Rope, position: Position, encoding: PositionEncoding) -> usize {
+    let line = position.line as usize;
+    if line >= rope.len_lines() {
+        return rope.len_chars();
+    }
+    let line_start = rope.line_to_byte(line);
+    let line_text: Cow<str> = rope.line(line).into();
+    // The column may not point into the line break
+    let line_text = line_text.trim_end_matches(['\n', '\r']);
+    let byte_col = encoding.col_to_byte(line_text, position.character as usize);
+    rope.byte_to_char(line_start + byte_col)
+}
+
+/// Apply `didChange` content changes, in order, to `rope`. A change without a
+/// range replaces the whole document.
+pub(crate) fn apply_content_changes(
+    rope: &mut Rope,
+    changes: &[TextDocumentContentChangeEvent],
+    encoding: PositionEncoding,
+) {
+    for change in changes {
+        match change.range {
+            Some(range) => {
+                let start = position_to_char(rope, range.start, encoding);
+                let end = position_to_char(rope, range.end, encoding).max(start);
+                rope.remove(start..end);
+                rope.insert(start, &change.text);
+            }
+            None => *rope = Rope::from_str(&change.text),
+        }
+    }
+}
+
+impl Backend {
+    /// Analyze the pending edits of an open document, if any. The document
+    /// stays locked while it is analyzed, so the index never goes back to an
+    /// older version of it.
+    pub(crate) fn analyze_pending(&self, file_path: &Path) {
+        let Some(mut document) = self.documents.get_mut(file_path) else {
+            return;
+        };
+        if !document.pending {
+            return;
+        }
+        document.pending = false;
+        info!("Re-analyzing file: {:?}", file_path);
+        self.fixture_db
+            .analyze_file(file_path.to_path_buf(), &document.text.to_string());
+    }
+
+    /// Analyze the pending edits of every open document, so a request sees
+    /// the text the editor has rather than the last debounced analysis.
+    pub(crate) fn flush_pending_analysis(&self) {
+        let pending: Vec<PathBuf> = self
+            .documents
+            .iter()
+            .filter(|entry| entry.pending)
+            .map(|entry| entry.key().clone())
+            .collect();
+        for file_path in pending {
+            self.analyze_pending(&file_path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp_server::ls_types::Range;
+
+    fn edit(start: (u32, u32), end: (u32, u32), text: &str) -> TextDocumentContentChangeEvent {
+        TextDocumentContentChangeEvent {
+            range: Some(Range::new(
+                Position::new(start.0, start.1),
+                Position::new(end.0, end.1),
+            )),
+            range_length: None,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_apply_content_changes() {
+        let mut rope = Rope::from_str("def a():\n    pass\n");
+        apply_content_changes(
+            &mut rope,
+            &[
+                edit((0, 4), (0, 5), "fixture"),
+                edit((1, 4), (1, 8), "return 1"),
+                // Past the end of the document
+                edit((5, 0), (5, 0), "# end\n"),
+            ],
+            PositionEncoding::Utf16,
+        );
+        assert_eq!(rope.to_string(), "def fixture():\n    return 1\n# end\n");
+
+        // A change without a range replaces the document
+        apply_content_changes(
+            &mut rope,
+            &[TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: "x = 1\n".to_string(),
+            }],
+            PositionEncoding::Utf16,
+        );
+        assert_eq!(rope.to_string(), "x = 1\n");
+    }
+
+    #[test]
+    fn test_apply_content_changes_position_encoding() {
+        // "é" is one UTF-16 unit and two UTF-8 bytes; "😀" two units, four
+        // bytes and one code point
+        let text = "s = \"é😀\"\r\nx = 1\r\n";
+
+        let mut rope = Rope::from_str(text);
+        let changes = [edit((0, 8), (0, 99), "!")];
+        apply_content_changes(&mut rope, &changes, PositionEncoding::Utf16);
+        assert_eq!(rope.to_string(), "s = \"é😀!\r\nx = 1\r\n");
+
+        let mut rope = Rope::from_str(text);
+        let changes = [edit((0, 7), (0, 11), "")];
+        apply_content_changes(&mut rope, &changes, PositionEncoding::Utf8);
+        assert_eq!(rope.to_string(), "s = \"é\"\r\nx = 1\r\n");
+
+        let mut rope = Rope::from_str(text);
+        let changes = [edit((0, 6), (0, 7), "")];
+        apply_content_changes(&mut rope, &changes, PositionEncoding::Utf32);
+        assert_eq!(rope.to_string(), "s = \"é\"\r\nx = 1\r\n");
+    }
+}
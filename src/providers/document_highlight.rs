@@ -0,0 +1,103 @@
+//! Document highlight provider for pytest fixtures.
+//!
+//! Highlights every occurrence of the fixture under the cursor in the current
+//! file — its definition, parameters requesting it and string references such as
+//! `usefixtures("...")` — using fixture resolution rather than plain word
+//! matching, so unrelated identifiers with the same name stay unhighlighted.
+//! Uses of a fixture parameter in the function body are highlighted with it,
+//! from the parameter or from any of them.
+
+use super::Backend;
+use tower_lsp_server::jsonrpc::Result;
+use tower_lsp_server::ls_types::*;
+use tracing::info;
+
+impl Backend {
+    /// Handle documentHighlight request.
+    ///
+    /// The definition is reported as a `WRITE` highlight and usages as `READ`.
+    pub async fn handle_document_highlight(
+        &self,
+        params: DocumentHighlightParams,
+    ) -> Result<Option<Vec<DocumentHighlight>>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+
+        info!(
+            "documentHighlight request: uri={:?}, line={}, char={}",
+            uri, position.line, position.character
+        );
+
+        let Some(file_path) = self.uri_to_path(&uri) else {
+            return Ok(None);
+        };
+        let byte_col = self.to_byte_col(&file_path, position);
+        // The parameter under the cursor, or the one a body use refers to,
+        // and its uses in the function body (as linked editing finds them)
+        let occurrences = self
+            .fixture_db
+            .find_parameter_occurrences(&file_path, position.line, byte_col)
+            .unwrap_or_default();
+        let definition = self
+            .fixture_db
+            .find_fixture_or_definition_at_position(&file_path, position.line, byte_col)
+            .or_else(|| {
+                let &(line, start_char, _) = occurrences.first()?;
+                self.fixture_db.find_fixture_or_definition_at_position(
+                    &file_path,
+                    Self::internal_line_to_lsp(line),
+                    start_char as u32,
+                )
+            });
+        if definition.is_none() && occurrences.is_empty() {
+            return Ok(None);
+        }
+
+        let mut highlights = Vec::new();
+        if let Some(definition) = definition.as_ref().filter(|d| d.file_path == file_path) {
+            let line = Self::internal_line_to_lsp(definition.line);
+            highlights.push(DocumentHighlight {
+                range: Self::create_range(
+                    line,
+                    self.to_lsp_col(&file_path, definition.line, definition.start_char),
+                    line,
+                    self.to_lsp_col(&file_path, definition.line, definition.end_char),
+                ),
+                kind: Some(DocumentHighlightKind::WRITE),
+            });
+        }
+
+        let mut usages: Vec<(usize, usize, usize)> = definition
+            .map(|definition| {
+                self.fixture_db
+                    .find_references_for_definition(&definition)
+                    .into_iter()
+                    .filter(|usage| usage.file_path == file_path)
+                    .map(|usage| (usage.line, usage.start_char, usage.end_char))
+                    .collect()
+            })
+            .unwrap_or_default();
+        usages.extend(occurrences);
+        usages.sort();
+
+        for (line, start_char, end_char) in usages {
+            let lsp_line = Self::internal_line_to_lsp(line);
+            let range = Self::create_range(
+                lsp_line,
+                self.to_lsp_col(&file_path, line, start_char),
+                lsp_line,
+                self.to_lsp_col(&file_path, line, end_char),
+            );
+            if highlights.iter().any(|h| h.range == range) {
+                continue;
+            }
+            highlights.push(DocumentHighlight {
+                range,
+                kind: Some(DocumentHighlightKind::READ),
+            });
+        }
+
+        info!("Returning {} document highlights", highlights.len());
+        Ok(Some(highlights))
+    }
+}
@@ -0,0 +1,70 @@
+//! Document links provider for pytest fixtures.
+//!
+//! Makes fixture names written as strings — in `@pytest.mark.usefixtures(...)`,
+//! `pytestmark`, parametrize `indirect=...`, `request.getfixturevalue(...)` and
+//! `lazy_fixture(...)` — clickable links to their definitions, for clients that
+//! don't offer go-to-definition inside string literals.
+
+use super::Backend;
+use tower_lsp_server::jsonrpc::Result;
+use tower_lsp_server::ls_types::*;
+use tracing::info;
+
+impl Backend {
+    /// Handle documentLink request.
+    ///
+    /// Each string-based fixture usage that resolves to a definition becomes a
+    /// link whose target carries a `#L<line>` fragment pointing at the fixture.
+    pub async fn handle_document_link(
+        &self,
+        params: DocumentLinkParams,
+    ) -> Result<Option<Vec<DocumentLink>>> {
+        let uri = params.text_document.uri;
+
+        info!("document_link request: uri={:?}", uri);
+
+        let Some(file_path) = self.uri_to_path(&uri) else {
+            return Ok(None);
+        };
+
+        // Snapshot the usages so no shard guard is held while resolving
+        let usages = match self.fixture_db.usages.get(&file_path) {
+            Some(usages) => usages.value().clone(),
+            None => return Ok(None),
+        };
+
+        let mut links = Vec::new();
+        for usage in usages.iter().filter(|usage| !usage.is_parameter) {
+            let Some(definition) = self.fixture_db.find_fixture_definition(
+                &file_path,
+                Self::internal_line_to_lsp(usage.line),
+                usage.start_char as u32,
+            ) else {
+                continue;
+            };
+            let Some(def_uri) = self.path_to_uri(&definition.file_path) else {
+                continue;
+            };
+            let Ok(target) = format!("{}#L{}", def_uri.as_str(), definition.line).parse::<Uri>()
+            else {
+                continue;
+            };
+
+            let line = Self::internal_line_to_lsp(usage.line);
+            links.push(DocumentLink {
+                range: Self::create_range(
+                    line,
+                    self.to_lsp_col(&file_path, usage.line, usage.start_char),
+                    line,
+                    self.to_lsp_col(&file_path, usage.line, usage.end_char),
+                ),
+                target: Some(target),
+                tooltip: Some(format!("Go to fixture '{}'", usage.name)),
+                data: None,
+            });
+        }
+
+        info!("Returning {} document links", links.len());
+        Ok(Some(links))
+    }
+}
@@ -1,9 +1,11 @@
 //! Document symbols provider for pytest fixtures.
 //!
 //! Provides the textDocument/documentSymbol LSP feature, enabling file outline
-//! and breadcrumb navigation for fixtures in the editor.
+//! and breadcrumb navigation for fixtures and tests in the editor. Test classes
+//! contain their test methods and any fixtures defined on them.
 
 use super::Backend;
+use crate::fixtures::{TestItem, TestItemKind};
 use tower_lsp_server::jsonrpc::Result;
 use tower_lsp_server::ls_types::*;
 use tracing::info;
@@ -11,7 +13,9 @@ use tracing::info;
 impl Backend {
     /// Handle textDocument/documentSymbol request.
     ///
-    /// Returns all fixture definitions in the document as symbols.
+    /// Returns the fixture definitions, test functions and test classes in the
+    /// document as hierarchical symbols. Fixture details show the scope, whether
+    /// the fixture is autouse, and its return type.
     /// This enables outline view and breadcrumb navigation in editors.
     pub async fn handle_document_symbol(
         &self,
@@ -63,16 +67,20 @@ impl Backend {
                 let end_line = Self::internal_line_to_lsp(definition.end_line);
                 let range = Self::create_range(line, 0, end_line, 0);
 
-                // Build detail string with return type if available
-                let detail = definition
-                    .return_type
-                    .as_ref()
-                    .map(|rt| format!("-> {}", rt));
+                // Build detail string: scope, autouse and return type if available
+                let mut detail = format!("fixture ({}", definition.scope.as_str());
+                if definition.autouse {
+                    detail.push_str(", autouse");
+                }
+                detail.push(')');
+                if let Some(rt) = &definition.return_type {
+                    detail.push_str(&format!(" -> {}", rt));
+                }
 
                 #[allow(deprecated)] // deprecated field is required by LSP spec
                 let symbol = DocumentSymbol {
                     name: definition.name.clone(),
-                    detail,
+                    detail: Some(detail),
                     kind: SymbolKind::FUNCTION,
                     tags: None,
                     deprecated: None,
@@ -85,8 +93,23 @@ impl Backend {
             }
         }
 
+        // Fixtures defined on a test class are nested under it
+        let mut tests: Vec<DocumentSymbol> = self
+            .fixture_db
+            .get_test_items(&file_path)
+            .iter()
+            .map(|item| self.test_item_symbol(&file_path, item, false))
+            .collect();
+        for symbol in std::mem::take(&mut symbols) {
+            match Self::find_enclosing_class(&mut tests, &symbol.range) {
+                Some(class) => class.children.get_or_insert_with(Vec::new).push(symbol),
+                None => symbols.push(symbol),
+            }
+        }
+        symbols.extend(tests);
+
         // Sort symbols by line number for consistent ordering
-        symbols.sort_by_key(|s| s.range.start.line);
+        Self::sort_symbols(&mut symbols);
 
         info!(
             "Returning {} document symbols for {:?}",
@@ -100,4 +123,76 @@ impl Backend {
             Ok(Some(DocumentSymbolResponse::Nested(symbols)))
         }
     }
+
+    fn test_item_symbol(
+        &self,
+        file_path: &std::path::Path,
+        item: &TestItem,
+        in_class: bool,
+    ) -> DocumentSymbol {
+        let line = Self::internal_line_to_lsp(item.line);
+        let selection_range = Self::create_range(
+            line,
+            self.to_lsp_col(file_path, item.line, item.start_char),
+            line,
+            self.to_lsp_col(file_path, item.line, item.end_char),
+        );
+        let range = Self::create_range(line, 0, Self::internal_line_to_lsp(item.end_line), 0);
+
+        let (kind, detail) = match item.kind {
+            TestItemKind::Class => (SymbolKind::CLASS, "test class"),
+            TestItemKind::Function if in_class => (SymbolKind::METHOD, "test"),
+            TestItemKind::Function => (SymbolKind::FUNCTION, "test"),
+        };
+        let children: Vec<DocumentSymbol> = item
+            .children
+            .iter()
+            .map(|child| self.test_item_symbol(file_path, child, true))
+            .collect();
+
+        #[allow(deprecated)] // deprecated field is required by LSP spec
+        DocumentSymbol {
+            name: item.name.clone(),
+            detail: Some(detail.to_string()),
+            kind,
+            tags: None,
+            deprecated: None,
+            range,
+            selection_range,
+            children: (!children.is_empty()).then_some(children),
+        }
+    }
+
+    /// Innermost class symbol among `symbols` (recursively) whose range contains `range`.
+    fn find_enclosing_class<'a>(
+        symbols: &'a mut [DocumentSymbol],
+        range: &Range,
+    ) -> Option<&'a mut DocumentSymbol> {
+        let class = symbols
+            .iter_mut()
+            .find(|s| Self::class_contains(s, range))?;
+        let nested = class
+            .children
+            .as_deref()
+            .is_some_and(|children| children.iter().any(|c| Self::class_contains(c, range)));
+        if nested {
+            return Self::find_enclosing_class(class.children.as_mut()?, range);
+        }
+        Some(class)
+    }
+
+    fn class_contains(symbol: &DocumentSymbol, range: &Range) -> bool {
+        symbol.kind == SymbolKind::CLASS
+            && symbol.range.start.line < range.start.line
+            && range.start.line <= symbol.range.end.line
+    }
+
+    fn sort_symbols(symbols: &mut [DocumentSymbol]) {
+        symbols.sort_by_key(|s| s.range.start.line);
+        for symbol in symbols.iter_mut() {
+            if let Some(children) = symbol.children.as_mut() {
+                Self::sort_symbols(children);
+            }
+        }
+    }
 }
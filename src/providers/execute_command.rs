@@ -0,0 +1,470 @@
+//! Execute-command provider.
+//!
+//! Handles the commands the server advertises in `executeCommandProvider`:
+//! - `pytest-lsp.runTest`: run pytest on a node ID (emitted by the "Run test" /
+//!   "Run file" code lenses), streaming its output to the client log and
+//!   reporting the outcome with `window/showMessage`.
+//! - `pytest-lsp.exportFixtureGraph`: return the fixture dependency graph as
+//!   DOT or Mermaid source, optionally rooted at a fixture or a test.
+//! - `pytest-lsp.explainResolution`: explain which definition a fixture usage
+//!   resolves to and why the other candidates lost.
+//! - `pytest-lsp.listAutouseFixtures`: list the autouse fixtures that run for
+//!   a test, with where each comes from and its location.
+
+use super::Backend;
+use crate::fixtures::{
+    find_venv_python, CandidateSource, FixtureDefinition, GraphFormat, InjectionSource,
+    ResolutionExplanation,
+};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tower_lsp_server::jsonrpc::{Error, Result};
+use tower_lsp_server::ls_types::*;
+use tower_lsp_server::Client;
+use tracing::{info, warn};
+
+/// Command that runs pytest on the node ID given as its only argument.
+pub const RUN_TEST_COMMAND: &str = "pytest-lsp.runTest";
+
+/// Command that returns the fixture dependency graph. Arguments (both
+/// optional): the format (`"dot"`, the default, or `"mermaid"`) and the root,
+/// a fixture name or a pytest node ID.
+pub const EXPORT_FIXTURE_GRAPH_COMMAND: &str = "pytest-lsp.exportFixtureGraph";
+
+/// Command that explains how the fixture usage at a position resolves. Its
+/// argument is a `TextDocumentPositionParams` object.
+pub const EXPLAIN_RESOLUTION_COMMAND: &str = "pytest-lsp.explainResolution";
+
+/// Command that lists the autouse fixtures running for the test whose name is
+/// at a position. Its argument is a `TextDocumentPositionParams` object.
+pub const LIST_AUTOUSE_FIXTURES_COMMAND: &str = "pytest-lsp.listAutouseFixtures";
+
+/// Commands handled by the server.
+pub const COMMANDS: &[&str] = &[
+    RUN_TEST_COMMAND,
+    EXPORT_FIXTURE_GRAPH_COMMAND,
+    EXPLAIN_RESOLUTION_COMMAND,
+    LIST_AUTOUSE_FIXTURES_COMMAND,
+];
+
+impl Backend {
+    /// Handle workspace/executeCommand request.
+    pub async fn handle_execute_command(
+        &self,
+        params: ExecuteCommandParams,
+    ) -> Result<Option<LSPAny>> {
+        info!(
+            "execute_command request: command={}, arguments={:?}",
+            params.command, params.arguments
+        );
+
+        match params.command.as_str() {
+            RUN_TEST_COMMAND => {
+                let Some(node_id) = params.arguments.first().and_then(|a| a.as_str()) else {
+                    return Err(Error::invalid_params(format!(
+                        "{} expects a pytest node ID argument",
+                        RUN_TEST_COMMAND
+                    )));
+                };
+                // pytest would parse it as an option (`-p plugin`, `--pyargs`...)
+                if node_id.starts_with('-') {
+                    return Err(Error::invalid_params(format!(
+                        "{} expects a pytest node ID, not an option: {}",
+                        RUN_TEST_COMMAND, node_id
+                    )));
+                }
+
+                let workspace_root = self
+                    .workspace_root
+                    .read()
+                    .await
+                    .clone()
+                    .or_else(|| std::env::current_dir().ok())
+                    .unwrap_or_default();
+                // The rootdir the node ID is relative to, when given
+                let root = match params.arguments.get(1).and_then(|a| a.as_str()) {
+                    Some(rootdir) => PathBuf::from(rootdir),
+                    None => self
+                        .pytest_rootdir()
+                        .await
+                        .unwrap_or_else(|| workspace_root.clone()),
+                };
+                let python = self.python_executable(&workspace_root, &root);
+
+                // Run in the background: the test run can take arbitrarily long
+                // and its progress is reported through notifications.
+                let client = self.client.clone();
+                let node_id = node_id.to_string();
+                tokio::spawn(async move {
+                    run_pytest(client, python, root, node_id).await;
+                });
+                Ok(None)
+            }
+            EXPORT_FIXTURE_GRAPH_COMMAND => {
+                let format = match params.arguments.first().and_then(|a| a.as_str()) {
+                    Some(format) => format
+                        .parse::<GraphFormat>()
+                        .map_err(Error::invalid_params)?,
+                    None => GraphFormat::Dot,
+                };
+                let root = params.arguments.get(1).and_then(|a| a.as_str());
+                let workspace_root = self.workspace_root.read().await.clone().unwrap_or_default();
+
+                let graph = self
+                    .fixture_db
+                    .fixture_graph(root, &workspace_root)
+                    .map_err(Error::invalid_params)?;
+                Ok(Some(LSPAny::String(graph.render(format))))
+            }
+            EXPLAIN_RESOLUTION_COMMAND => {
+                let position = Self::position_argument(params, EXPLAIN_RESOLUTION_COMMAND)?;
+                let Some(file_path) = self.uri_to_path(&position.text_document.uri) else {
+                    return Ok(None);
+                };
+
+                let byte_col = self.to_byte_col(&file_path, position.position);
+                let Some(explanation) = self.fixture_db.explain_fixture_resolution(
+                    &file_path,
+                    position.position.line,
+                    byte_col,
+                ) else {
+                    return Ok(None);
+                };
+
+                let workspace_root = self.workspace_root.read().await.clone();
+                Ok(Some(LSPAny::String(Self::format_resolution_explanation(
+                    &explanation,
+                    workspace_root.as_ref(),
+                ))))
+            }
+            LIST_AUTOUSE_FIXTURES_COMMAND => {
+                let position = Self::position_argument(params, LIST_AUTOUSE_FIXTURES_COMMAND)?;
+                let Some(file_path) = self.uri_to_path(&position.text_document.uri) else {
+                    return Ok(None);
+                };
+
+                let byte_col = self.to_byte_col(&file_path, position.position);
+                let Some(test) = self.fixture_db.get_test_fixtures_at_position(
+                    &file_path,
+                    Self::lsp_line_to_internal(position.position.line),
+                    byte_col as usize,
+                ) else {
+                    return Ok(None);
+                };
+
+                let workspace_root = self.workspace_root.read().await.clone();
+                let fixtures: Vec<serde_json::Value> = test
+                    .fixtures
+                    .iter()
+                    .filter(|fixture| fixture.source == InjectionSource::Autouse)
+                    .filter_map(|fixture| fixture.definition.as_ref())
+                    .map(|def| {
+                        let line = Self::internal_line_to_lsp(def.line);
+                        let location = self.path_to_uri(&def.file_path).map(|uri| Location {
+                            uri,
+                            range: Self::create_range(
+                                line,
+                                self.to_lsp_col(&def.file_path, def.line, def.start_char),
+                                line,
+                                self.to_lsp_col(&def.file_path, def.line, def.end_char),
+                            ),
+                        });
+                        serde_json::json!({
+                            "name": def.name,
+                            "scope": def.scope.as_str(),
+                            "origin": self.fixture_origin_text(&file_path, def, workspace_root.as_ref()),
+                            "location": location,
+                        })
+                    })
+                    .collect();
+                Ok(Some(LSPAny::Array(fixtures)))
+            }
+            command => Err(Error::invalid_params(format!(
+                "Unknown command: {}",
+                command
+            ))),
+        }
+    }
+
+    /// The `TextDocumentPositionParams` argument of a command.
+    fn position_argument(
+        params: ExecuteCommandParams,
+        command: &str,
+    ) -> Result<TextDocumentPositionParams> {
+        params
+            .arguments
+            .into_iter()
+            .next()
+            .and_then(|a| serde_json::from_value::<TextDocumentPositionParams>(a).ok())
+            .ok_or_else(|| {
+                Error::invalid_params(format!(
+                    "{} expects a text document position argument",
+                    command
+                ))
+            })
+    }
+
+    /// Where a fixture requested from `file_path` comes from: its class, for
+    /// fixtures defined in a test class of the file, or its place in
+    /// pytest's lookup order.
+    pub(crate) fn fixture_origin_text(
+        &self,
+        file_path: &Path,
+        def: &FixtureDefinition,
+        workspace_root: Option<&PathBuf>,
+    ) -> String {
+        match &def.class_name {
+            Some(class_name) if def.file_path == file_path => format!("class {}", class_name),
+            _ => Self::candidate_source_text(
+                &self.fixture_db.definition_source(file_path, def),
+                workspace_root,
+            ),
+        }
+    }
+
+    /// Describe a candidate's place in pytest's lookup order.
+    fn candidate_source_text(source: &CandidateSource, workspace_root: Option<&PathBuf>) -> String {
+        match source {
+            CandidateSource::SameFile => "same file".to_string(),
+            CandidateSource::Conftest { distance: 0 } => {
+                "conftest.py in the same directory".to_string()
+            }
+            CandidateSource::Conftest { distance } => format!(
+                "conftest.py {} director{} up",
+                distance,
+                if *distance == 1 { "y" } else { "ies" }
+            ),
+            CandidateSource::ImportedByConftest { conftest, .. } => format!(
+                "imported by {}",
+                Self::display_path(conftest, workspace_root)
+            ),
+            CandidateSource::Plugin => "plugin (pytest11 entry point)".to_string(),
+            CandidateSource::ThirdParty => "third-party (site-packages)".to_string(),
+            CandidateSource::NotVisible => {
+                "not visible from this file (not in the same file, a parent conftest.py or a plugin)"
+                    .to_string()
+            }
+        }
+    }
+
+    /// Render a fixture resolution explanation as plain text.
+    pub fn format_resolution_explanation(
+        explanation: &ResolutionExplanation,
+        workspace_root: Option<&PathBuf>,
+    ) -> String {
+        let location = |path: &Path, line: usize| {
+            format!("{}:{}", Self::display_path(path, workspace_root), line)
+        };
+        let source_text =
+            |source: &CandidateSource| Self::candidate_source_text(source, workspace_root);
+
+        let mut out = format!(
+            "Fixture '{}' requested at {}\n",
+            explanation.fixture_name,
+            location(&explanation.file_path, explanation.line)
+        );
+        if let Some(requester) = &explanation.requester {
+            out.push_str(&format!(
+                "The fixture at {} requests its own name, so it is skipped and the fixture it overrides is used.\n",
+                location(&requester.file_path, requester.line)
+            ));
+        }
+
+        out.push_str("\nCandidates, in pytest's lookup order:\n");
+        if explanation.candidates.is_empty() {
+            out.push_str("  (none)\n");
+        }
+        let winner = explanation.candidates.iter().find(|c| c.is_winner);
+        for candidate in &explanation.candidates {
+            let def = &candidate.definition;
+            let verdict = if candidate.is_winner {
+                "selected".to_string()
+            } else if candidate.source == CandidateSource::NotVisible {
+                "skipped".to_string()
+            } else {
+                match winner {
+                    Some(w)
+                        if w.source == CandidateSource::SameFile
+                            && candidate.source == CandidateSource::SameFile =>
+                    {
+                        format!(
+                            "shadowed by the later definition at line {}",
+                            w.definition.line
+                        )
+                    }
+                    Some(w) => format!(
+                        "shadowed by {}",
+                        location(&w.definition.file_path, w.definition.line)
+                    ),
+                    None => "skipped".to_string(),
+                }
+            };
+            out.push_str(&format!(
+                "  {} {} [{}] {}\n",
+                if candidate.is_winner { "✔" } else { "✘" },
+                location(&def.file_path, def.line),
+                source_text(&candidate.source),
+                verdict
+            ));
+        }
+
+        out.push('\n');
+        match winner {
+            Some(w) => {
+                let reason = match &w.source {
+                    CandidateSource::SameFile => "definitions in the requesting file take precedence over everything else",
+                    CandidateSource::Conftest { .. } | CandidateSource::ImportedByConftest { .. } => {
+                        "the closest conftest.py above the requesting file wins"
+                    }
+                    CandidateSource::Plugin => "no definition in the file or a parent conftest.py, so plugin fixtures apply",
+                    CandidateSource::ThirdParty => "no project or plugin definition is visible, so the installed package's fixture applies",
+                    CandidateSource::NotVisible => "it is the only definition available",
+                };
+                out.push_str(&format!(
+                    "Resolved to {}: {}.\n",
+                    location(&w.definition.file_path, w.definition.line),
+                    reason
+                ));
+            }
+            None => out.push_str("Unresolved: no definition is visible from this file.\n"),
+        }
+        out
+    }
+
+    /// The directory pytest runs from and node IDs are relative to: pytest's
+    /// rootdir, or the workspace root before the workspace has been scanned.
+    pub(crate) async fn pytest_rootdir(&self) -> Option<PathBuf> {
+        match self.fixture_db.pytest_root() {
+            Some(pytest_root) => Some(pytest_root.rootdir),
+            None => self.workspace_root.read().await.clone(),
+        }
+    }
+
+    /// The directory pytest runs the tests of `file_path` from: the rootdir
+    /// of the project of a monorepo it belongs to, otherwise the workspace's.
+    pub(crate) async fn pytest_rootdir_for(&self, file_path: &Path) -> Option<PathBuf> {
+        match self.fixture_db.pytest_root_for(file_path) {
+            Some(pytest_root) => Some(pytest_root.rootdir),
+            None => self.workspace_root.read().await.clone(),
+        }
+    }
+
+    /// Python interpreter used to run pytest from `rootdir`: the one of its
+    /// subproject's own virtual environment, then the configured one, then
+    /// the workspace's virtual environment when one is configured or found,
+    /// otherwise the one on `PATH`.
+    fn python_executable(&self, root: &Path, rootdir: &Path) -> PathBuf {
+        self.fixture_db
+            .subproject_venv_for(rootdir)
+            .and_then(|venv| find_venv_python(&venv))
+            .or_else(|| self.fixture_db.configured_python(root))
+            .or_else(|| {
+                self.fixture_db
+                    .workspace_venv(root)
+                    .and_then(|venv| find_venv_python(&venv))
+            })
+            .unwrap_or_else(|| PathBuf::from(if cfg!(windows) { "python" } else { "python3" }))
+    }
+
+    /// Build the pytest node ID for a file, optionally followed by the
+    /// class/function names leading to a test (`tests/test_x.py::TestA::test_b`).
+    ///
+    /// The path is relative to pytest's rootdir when the file is inside it,
+    /// like the node IDs pytest reports, since that is where the command runs
+    /// pytest from.
+    pub fn pytest_node_id(file_path: &Path, rootdir: Option<&PathBuf>, names: &[&str]) -> String {
+        let mut node_id = match rootdir.and_then(|root| file_path.strip_prefix(root).ok()) {
+            Some(relative) => relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/"),
+            None => file_path.to_string_lossy().into_owned(),
+        };
+        for name in names {
+            node_id.push_str("::");
+            node_id.push_str(name);
+        }
+        node_id
+    }
+}
+
+async fn run_pytest(client: Client, python: PathBuf, root: PathBuf, node_id: String) {
+    info!("Running {:?} -m pytest {} in {:?}", python, node_id, root);
+    client
+        .log_message(
+            MessageType::INFO,
+            format!("Running pytest {} ({})", node_id, python.display()),
+        )
+        .await;
+
+    let child = tokio::process::Command::new(&python)
+        .args(["-m", "pytest", &node_id])
+        .current_dir(&root)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            warn!("Failed to start pytest: {}", e);
+            client
+                .show_message(
+                    MessageType::ERROR,
+                    format!("Failed to run pytest with {}: {}", python.display(), e),
+                )
+                .await;
+            return;
+        }
+    };
+
+    let stdout = child
+        .stdout
+        .take()
+        .map(|out| stream_lines(client.clone(), out));
+    let stderr = child
+        .stderr
+        .take()
+        .map(|err| stream_lines(client.clone(), err));
+    if let Some(task) = stdout {
+        let _ = task.await;
+    }
+    if let Some(task) = stderr {
+        let _ = task.await;
+    }
+
+    let (kind, message) = match child.wait().await {
+        Ok(status) => match status.code() {
+            Some(0) => (MessageType::INFO, format!("{}: passed", node_id)),
+            // pytest's "no tests collected" exit code
+            Some(5) => (
+                MessageType::WARNING,
+                format!("{}: no tests collected", node_id),
+            ),
+            Some(code) => (
+                MessageType::ERROR,
+                format!("{}: failed (exit code {})", node_id, code),
+            ),
+            None => (MessageType::ERROR, format!("{}: interrupted", node_id)),
+        },
+        Err(e) => (MessageType::ERROR, format!("{}: {}", node_id, e)),
+    };
+    info!("pytest finished: {}", message);
+    client.show_message(kind, message).await;
+}
+
+/// Forward each line of a child process stream to the client log.
+fn stream_lines<R>(client: Client, stream: R) -> tokio::task::JoinHandle<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut lines = BufReader::new(stream).lines();
+        while let Ok(Some(line)) = lines.next_line().await {
+            client.log_message(MessageType::LOG, line).await;
+        }
+    })
+}
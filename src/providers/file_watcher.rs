@@ -0,0 +1,257 @@
+//! Native file watching for changes made outside the editor.
+//!
+//! Git checkouts, rebases and code generators rewrite conftest.py and test
+//! files without any `didChange` notification. The workspace folders are
+//! watched recursively and changed files re-analyzed from disk; the venv's
+//! site-packages directories are watched (non-recursively) for packages being
+//! installed or removed, which triggers a full rescan.
+
+use super::Backend;
+use crate::fixtures::FixtureDatabase;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+/// How long to wait for more events before processing a batch, so a checkout
+/// touching hundreds of files is handled in one pass.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// The native watcher and the paths it watches.
+pub struct FileWatcher {
+    watcher: RecommendedWatcher,
+    watched: HashSet<PathBuf>,
+}
+
+impl FileWatcher {
+    fn watch(&mut self, path: &Path, mode: RecursiveMode) {
+        if self.watched.contains(path) {
+            return;
+        }
+        match self.watcher.watch(path, mode) {
+            Ok(()) => {
+                info!("Watching {:?} for changes", path);
+                self.watched.insert(path.to_path_buf());
+            }
+            Err(e) => warn!("Failed to watch {:?}: {}", path, e),
+        }
+    }
+}
+
+/// Whether a change in site-packages installs or removes a distribution.
+fn is_distribution_change(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| {
+            name.ends_with(".dist-info") || name.ends_with(".egg-info") || name.ends_with(".pth")
+        })
+}
+
+impl Backend {
+    /// Start watching the workspace folders and the venv's site-packages,
+    /// creating the watcher on first use. Called after each workspace scan.
+    pub(crate) fn watch_workspace(&self, roots: &[PathBuf]) {
+        let mut guard = self.file_watcher.lock().unwrap();
+        if guard.is_none() {
+            let (tx, rx) = mpsc::unbounded_channel();
+            let watcher = notify::recommended_watcher(move |event| {
+                // The receiver is gone once the server shuts down
+                let _ = tx.send(event);
+            });
+            match watcher {
+                Ok(watcher) => {
+                    *guard = Some(FileWatcher {
+                        watcher,
+                        watched: HashSet::new(),
+                    });
+                    tokio::spawn(self.clone().process_file_events(rx));
+                }
+                Err(e) => {
+                    warn!("Failed to create file watcher: {}", e);
+                    return;
+                }
+            }
+        }
+        let Some(file_watcher) = guard.as_mut() else {
+            return;
+        };
+
+        for root in roots {
+            let root = root.canonicalize().unwrap_or_else(|_| root.clone());
+            file_watcher.watch(&root, RecursiveMode::Recursive);
+        }
+        let site_packages = self.fixture_db.site_packages_paths.lock().unwrap().clone();
+        for path in site_packages {
+            file_watcher.watch(&path, RecursiveMode::NonRecursive);
+        }
+    }
+
+    /// Stop watching removed workspace folders.
+    pub(crate) fn unwatch_workspace(&self, roots: &[PathBuf]) {
+        let mut guard = self.file_watcher.lock().unwrap();
+        let Some(file_watcher) = guard.as_mut() else {
+            return;
+        };
+        for root in roots {
+            let root = root.canonicalize().unwrap_or_else(|_| root.clone());
+            if file_watcher.watched.remove(&root) {
+                if let Err(e) = file_watcher.watcher.unwatch(&root) {
+                    debug!("Failed to unwatch {:?}: {}", root, e);
+                }
+            }
+        }
+    }
+
+    /// Stop watching; the event loop ends once the watcher is dropped.
+    pub(crate) fn stop_watching(&self) {
+        self.file_watcher.lock().unwrap().take();
+    }
+
+    async fn process_file_events(
+        self,
+        mut rx: mpsc::UnboundedReceiver<notify::Result<notify::Event>>,
+    ) {
+        while let Some(first) = rx.recv().await {
+            tokio::time::sleep(WATCH_DEBOUNCE).await;
+            let mut events = vec![first];
+            while let Ok(event) = rx.try_recv() {
+                events.push(event);
+            }
+
+            let mut paths: HashSet<PathBuf> = HashSet::new();
+            for event in events {
+                match event {
+                    Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+                        paths.extend(event.paths);
+                    }
+                    Ok(_) => {}
+                    Err(e) => debug!("File watcher error: {}", e),
+                }
+            }
+            self.apply_file_changes(paths).await;
+        }
+        debug!("File watcher event loop stopped");
+    }
+
+    /// Bring the index up to date with files changed on disk.
+    pub(crate) async fn apply_file_changes(&self, mut paths: HashSet<PathBuf>) {
+        let site_packages = self.fixture_db.site_packages_paths.lock().unwrap().clone();
+        if paths.iter().any(|path| {
+            is_distribution_change(path)
+                && path
+                    .parent()
+                    .is_some_and(|parent| site_packages.iter().any(|sp| sp == parent))
+        }) {
+            info!("Packages changed in the virtual environment — rescanning the workspace");
+            let roots = self.scan_roots.read().await.clone();
+            self.spawn_workspace_scan(roots, true).await;
+            return;
+        }
+
+        let roots: Vec<PathBuf> = self
+            .scan_roots
+            .read()
+            .await
+            .iter()
+            .map(|root| root.canonicalize().unwrap_or_else(|_| root.clone()))
+            .collect();
+        let exclude = self.config.read().await.exclude.clone();
+
+        // Files written into a new directory before the watcher picked it up
+        // produce no events of their own
+        let new_dirs: Vec<PathBuf> = paths.iter().filter(|p| p.is_dir()).cloned().collect();
+        for dir in new_dirs {
+            paths.extend(
+                ignore::WalkBuilder::new(&dir)
+                    .standard_filters(false)
+                    .filter_entry(|entry| {
+                        entry
+                            .file_name()
+                            .to_str()
+                            .is_none_or(|name| !FixtureDatabase::should_skip_directory(name))
+                    })
+                    .build()
+                    .flatten()
+                    .filter(|entry| entry.file_type().is_some_and(|t| t.is_file()))
+                    .map(|entry| entry.into_path()),
+            );
+        }
+
+        let mut changed = false;
+        for path in paths {
+            // Open files are kept up to date by the editor
+            if self.uri_cache.contains_key(&path) {
+                continue;
+            }
+            let Some(root) = roots.iter().find(|root| path.starts_with(root)) else {
+                continue;
+            };
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            let skipped = relative.components().any(|c| {
+                c.as_os_str()
+                    .to_str()
+                    .is_some_and(FixtureDatabase::should_skip_directory)
+            });
+            let relative_str = relative.to_string_lossy();
+            if skipped || exclude.iter().any(|p| p.matches(&relative_str)) {
+                continue;
+            }
+
+            if path.is_file() {
+                if !self.is_indexed_python_file(&path) {
+                    continue;
+                }
+                match std::fs::read_to_string(&path) {
+                    Ok(content) => {
+                        debug!("Re-analyzing {:?} after change on disk", path);
+                        self.fixture_db.analyze_file(path, &content);
+                        changed = true;
+                    }
+                    Err(e) => debug!("Failed to read changed file {:?}: {}", path, e),
+                }
+            } else if !path.exists() {
+                // A deleted file, or a deleted directory with everything below it
+                debug!("Removing {:?} after deletion on disk", path);
+                self.fixture_db.remove_files_under(&path, &[]);
+                changed = true;
+            }
+        }
+
+        if changed {
+            self.refresh_open_files().await;
+        }
+    }
+
+    /// Whether a file on disk belongs in the index: a conftest.py or test
+    /// file, or a module the index already holds (e.g. imported by a conftest).
+    fn is_indexed_python_file(&self, path: &Path) -> bool {
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            return false;
+        };
+        name == "conftest.py"
+            || self.fixture_db.is_test_file_name(name)
+            || (name.ends_with(".py")
+                && (self.fixture_db.file_definitions.contains_key(path)
+                    || self.fixture_db.is_indexed_file(path)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_distribution_change() {
+        assert!(is_distribution_change(Path::new(
+            "/venv/site-packages/pytest_mock-3.14.0.dist-info"
+        )));
+        assert!(is_distribution_change(Path::new(
+            "/venv/site-packages/__editable__.project-1.0.pth"
+        )));
+        assert!(!is_distribution_change(Path::new(
+            "/venv/site-packages/pytest_mock"
+        )));
+    }
+}
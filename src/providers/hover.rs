@@ -1,6 +1,9 @@
-//! Hover provider for pytest fixtures.
+//! Hover provider for pytest fixtures and markers.
 
 use super::Backend;
+use crate::fixtures::builtins::is_builtin_definition;
+use crate::fixtures::{FixtureDefinition, FixtureParams, InjectionSource, TestFixtures};
+use std::path::{Path, PathBuf};
 use tower_lsp_server::jsonrpc::Result;
 use tower_lsp_server::ls_types::*;
 use tracing::info;
@@ -23,9 +26,16 @@ impl Backend {
             );
 
             let byte_col = self.to_byte_col(&file_path, position);
-            if let Some(definition) =
-                self.fixture_db
-                    .find_fixture_definition(&file_path, position.line, byte_col)
+            if let Some(definition) = self
+                .fixture_db
+                .find_fixture_definition(&file_path, position.line, byte_col)
+                .or_else(|| {
+                    self.fixture_db.find_indirect_entry_definition(
+                        &file_path,
+                        position.line,
+                        byte_col,
+                    )
+                })
             {
                 info!("Found fixture definition for hover: {:?}", definition.name);
 
@@ -33,8 +43,10 @@ impl Backend {
                 let workspace_root = self.workspace_root.read().await.clone();
 
                 // Build hover content using shared formatter
-                let content =
-                    Self::format_fixture_documentation(&definition, workspace_root.as_ref());
+                let mut content =
+                    self.format_fixture_documentation(&definition, workspace_root.as_ref());
+                content
+                    .push_str(&self.format_dependency_tree(&definition, workspace_root.as_ref()));
 
                 info!("Returning hover with content");
                 return Ok(Some(Hover {
@@ -44,11 +56,199 @@ impl Backend {
                     }),
                     range: None,
                 }));
-            } else {
-                info!("No fixture found for hover");
             }
+
+            if let Some((fixture, start_char, end_char)) = self
+                .fixture_db
+                .find_request_param_at_position(&file_path, position.line, byte_col)
+            {
+                info!("Found request.param for hover in fixture: {}", fixture.name);
+                let internal_line = Self::lsp_line_to_internal(position.line);
+                return Ok(Some(Hover {
+                    contents: HoverContents::Markup(MarkupContent {
+                        kind: MarkupKind::Markdown,
+                        value: Self::format_request_param(&fixture),
+                    }),
+                    range: Some(Self::create_range(
+                        position.line,
+                        self.to_lsp_col(&file_path, internal_line, start_char),
+                        position.line,
+                        self.to_lsp_col(&file_path, internal_line, end_char),
+                    )),
+                }));
+            }
+
+            if let Some(usage) =
+                self.fixture_db
+                    .find_marker_at_position(&file_path, position.line, byte_col)
+            {
+                info!("Found marker for hover: {}", usage.name);
+                let workspace_root = self.workspace_root.read().await.clone();
+                let value = match self
+                    .fixture_db
+                    .get_markers()
+                    .into_iter()
+                    .find(|m| m.name == usage.name)
+                {
+                    Some(marker) => {
+                        Self::format_marker_documentation(&marker, workspace_root.as_ref())
+                    }
+                    None => format!(
+                        "```python\n@pytest.mark.{}\n```\n\n---\n\nNot a registered marker: add it to the `markers` option of the pytest configuration.",
+                        usage.name
+                    ),
+                };
+                let line = Self::internal_line_to_lsp(usage.line);
+                return Ok(Some(Hover {
+                    contents: HoverContents::Markup(MarkupContent {
+                        kind: MarkupKind::Markdown,
+                        value,
+                    }),
+                    range: Some(Self::create_range(
+                        line,
+                        self.to_lsp_col(&file_path, usage.line, usage.start_char),
+                        line,
+                        self.to_lsp_col(&file_path, usage.line, usage.end_char),
+                    )),
+                }));
+            }
+
+            if let Some(test) = self.fixture_db.get_test_fixtures_at_position(
+                &file_path,
+                Self::lsp_line_to_internal(position.line),
+                byte_col as usize,
+            ) {
+                info!(
+                    "Found test function for hover: {} ({} fixtures)",
+                    test.test_name,
+                    test.fixtures.len()
+                );
+                let workspace_root = self.workspace_root.read().await.clone();
+                return Ok(Some(Hover {
+                    contents: HoverContents::Markup(MarkupContent {
+                        kind: MarkupKind::Markdown,
+                        value: self.format_test_fixtures(
+                            &test,
+                            &file_path,
+                            workspace_root.as_ref(),
+                        ),
+                    }),
+                    range: None,
+                }));
+            }
+
+            info!("No fixture found for hover");
         }
 
         Ok(None)
     }
+
+    /// Render the resolved transitive dependencies of a fixture as a nested
+    /// markdown list, each entry linking to its definition. Empty when the
+    /// fixture has no resolvable dependencies.
+    fn format_dependency_tree(
+        &self,
+        fixture: &FixtureDefinition,
+        workspace_root: Option<&PathBuf>,
+    ) -> String {
+        let tree = self.fixture_db.resolve_dependency_tree(fixture);
+        if tree.is_empty() {
+            return String::new();
+        }
+
+        let mut content = String::from("\n\n---\n\n**Dependencies:**\n\n");
+        for (depth, dep) in &tree {
+            let location = format!(
+                "{}:{}",
+                Self::display_path(&dep.file_path, workspace_root),
+                dep.line
+            );
+            let name = match self.path_to_uri(&dep.file_path) {
+                Some(uri) => format!("[`{}`]({}#L{})", dep.name, uri.as_str(), dep.line),
+                None => format!("`{}`", dep.name),
+            };
+            content.push_str(&format!(
+                "{}- {} ({}) — `{}`\n",
+                "  ".repeat(depth - 1),
+                name,
+                dep.scope.as_str(),
+                location
+            ));
+        }
+        content
+    }
+
+    /// Explain `request.param` inside a parametrized fixture and list the
+    /// values it iterates over.
+    fn format_request_param(fixture: &FixtureDefinition) -> String {
+        let mut content = format!(
+            "```python\nrequest.param\n```\n\n---\n\nThe current value of the `params` of `{}`: the fixture, and every test using it, runs once per value.",
+            fixture.name
+        );
+        match &fixture.params {
+            Some(FixtureParams {
+                expression: Some(expression),
+                ..
+            }) => content.push_str(&format!("\n\n**Params:** `{}`", expression)),
+            Some(params) if !params.ids.is_empty() => {
+                content.push_str("\n\n**Params:**\n");
+                for id in &params.ids {
+                    content.push_str(&format!("\n- `{}`", id));
+                }
+            }
+            _ => {}
+        }
+        content
+    }
+
+    /// Render the fixtures a test receives as a markdown table. Autouse
+    /// fixtures, which nothing in the test mentions, also say where they
+    /// come from.
+    fn format_test_fixtures(
+        &self,
+        test: &TestFixtures,
+        file_path: &Path,
+        workspace_root: Option<&PathBuf>,
+    ) -> String {
+        let mut content = format!("**Fixtures for** `{}`\n\n", test.test_name);
+        if test.fixtures.is_empty() {
+            content.push_str("_This test does not use any fixtures._");
+            return content;
+        }
+
+        content.push_str("| Fixture | Source | Definition | Scope |\n|---|---|---|---|\n");
+        for fixture in &test.fixtures {
+            let (location, scope) = match &fixture.definition {
+                // Synthesized from the builtin registry: there is no file to link
+                Some(def) if is_builtin_definition(def) && def.file_path.is_relative() => {
+                    ("builtin (`pytest`)".to_string(), def.scope.as_str())
+                }
+                Some(def) => {
+                    let label = format!(
+                        "{}:{}",
+                        Self::display_path(&def.file_path, workspace_root),
+                        def.line
+                    );
+                    let location = match self.path_to_uri(&def.file_path) {
+                        Some(uri) => format!("[`{}`]({}#L{})", label, uri.as_str(), def.line),
+                        None => format!("`{}`", label),
+                    };
+                    (location, def.scope.as_str())
+                }
+                None => ("_not found_".to_string(), "—"),
+            };
+            let source = match (&fixture.source, &fixture.definition) {
+                (InjectionSource::Autouse, Some(def)) => format!(
+                    "autouse ({})",
+                    self.fixture_origin_text(file_path, def, workspace_root)
+                ),
+                (source, _) => source.as_str().to_string(),
+            };
+            content.push_str(&format!(
+                "| `{}` | {} | {} | {} |\n",
+                fixture.name, source, location, scope
+            ));
+        }
+        content
+    }
 }
@@ -1,7 +1,11 @@
 //! Go-to-implementation provider for pytest fixtures.
 //!
-//! For generator fixtures (those with yield), "implementation" refers to
-//! the yield statement where the fixture value is produced.
+//! On a fixture definition that takes part in an override chain, the
+//! "implementations" are the definition it overrides and every definition
+//! overriding it further down the conftest tree.
+//!
+//! Otherwise, for generator fixtures (those with yield), "implementation" refers
+//! to the yield statement where the fixture value is produced.
 
 use super::Backend;
 use tower_lsp_server::jsonrpc::Result;
@@ -12,7 +16,10 @@ use tracing::info;
 impl Backend {
     /// Handle goto_implementation request.
     ///
-    /// For fixtures, "implementation" is the yield statement (if present).
+    /// On a fixture definition with overrides, returns the overridden parent
+    /// (first) followed by all overriding definitions.
+    ///
+    /// Otherwise, "implementation" is the yield statement (if present).
     /// This allows jumping to where the fixture value is actually produced
     /// in generator fixtures.
     pub async fn handle_goto_implementation(
@@ -42,6 +49,35 @@ impl Backend {
             ) {
                 info!("Found definition: {:?}", definition);
 
+                let on_definition = definition.file_path == file_path
+                    && definition.line == Self::lsp_line_to_internal(position.line)
+                    && (definition.start_char..=definition.end_char).contains(&(byte_col as usize));
+                if on_definition {
+                    let chain: Vec<Location> = self
+                        .fixture_db
+                        .find_overridden_definition(&definition)
+                        .into_iter()
+                        .chain(self.fixture_db.find_overriding_definitions(&definition))
+                        .filter_map(|def| {
+                            let uri = self.path_to_uri(&def.file_path)?;
+                            let line = Self::internal_line_to_lsp(def.line);
+                            Some(Location {
+                                uri,
+                                range: Self::create_range(
+                                    line,
+                                    self.to_lsp_col(&def.file_path, def.line, def.start_char),
+                                    line,
+                                    self.to_lsp_col(&def.file_path, def.line, def.end_char),
+                                ),
+                            })
+                        })
+                        .collect();
+                    if !chain.is_empty() {
+                        info!("Returning {} override locations", chain.len());
+                        return Ok(Some(GotoImplementationResponse::Array(chain)));
+                    }
+                }
+
                 // Check if the fixture has a yield line (generator fixture)
                 if let Some(yield_line) = definition.yield_line {
                     let Some(def_uri) = self.path_to_uri(&definition.file_path) else {
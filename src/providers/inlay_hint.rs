@@ -1,16 +1,20 @@
 //! Inlay hints provider for pytest fixtures.
 //!
 //! Shows fixture return types inline for fixture parameters in test functions
-//! when the fixture has an explicit return type annotation.
+//! when the fixture has an explicit return type annotation or one inferred
+//! from its body (prefixed with `~`), and optionally
+//! (`inlay_hints.origin`) where each fixture is defined: `same file`,
+//! `conftest ../..` or `[plugin pytest-mock]`.
 //!
 //! The displayed type is adapted to the consumer file's import context via
 //! [`adapt_type_for_consumer`]: if the consumer already has `from pathlib import Path`
 //! the hint shows `Path` rather than `pathlib.Path`, and vice versa.
 
 use super::Backend;
+use crate::fixtures::builtins::plugin_name;
 use crate::fixtures::import_analysis::adapt_type_for_consumer;
 use crate::fixtures::string_utils::parameter_has_annotation;
-use crate::fixtures::FixtureDefinition;
+use crate::fixtures::{CandidateSource, FixtureDefinition};
 use std::collections::HashMap;
 use std::sync::Arc;
 use tower_lsp_server::jsonrpc::Result;
@@ -21,10 +25,13 @@ impl Backend {
     /// Handle inlay hints request.
     ///
     /// Returns type hints for fixture parameters when the fixture has an explicit
-    /// return type annotation. This helps developers understand what type each
-    /// fixture provides without having to navigate to its definition.
+    /// return type annotation, or one inferred from its body. This helps
+    /// developers understand what type each fixture provides without having to
+    /// navigate to its definition.
     ///
     /// Skips parameters that already have a type annotation to avoid redundancy.
+    /// When origin hints are enabled, every fixture parameter also gets a hint
+    /// naming where its resolved definition lives.
     pub async fn handle_inlay_hint(
         &self,
         params: InlayHintParams,
@@ -38,8 +45,13 @@ impl Backend {
             return Ok(None);
         };
 
-        let Some(usages) = self.fixture_db.usages.get(&file_path) else {
-            return Ok(None);
+        let settings = self.config.read().await.inlay_hints;
+        let workspace_root = self.workspace_root.read().await.clone();
+
+        // Snapshot the usages so no shard guard is held while resolving
+        let usages = match self.fixture_db.usages.get(&file_path) {
+            Some(usages) => usages.value().clone(),
+            None => return Ok(None),
         };
 
         // Get current file content to check for existing annotations.
@@ -76,7 +88,9 @@ impl Backend {
         let fixture_map: HashMap<&str, &FixtureDefinition> = available
             .iter()
             .filter_map(|def| {
-                if def.return_type.is_some() {
+                if settings.types
+                    && (def.return_type.is_some() || def.inferred_return_type.is_some())
+                {
                     Some((def.name.as_str(), def))
                 } else {
                     None
@@ -84,8 +98,8 @@ impl Backend {
             })
             .collect();
 
-        // Early return if no fixtures have return types
-        if fixture_map.is_empty() {
+        // Early return if there is nothing to show
+        if fixture_map.is_empty() && !settings.origin {
             return Ok(Some(Vec::new()));
         }
 
@@ -108,16 +122,25 @@ impl Backend {
                 continue;
             }
 
-            // Look up fixture definition from pre-computed map
-            if let Some(def) = fixture_map.get(usage.name.as_str()) {
-                // Check if this parameter already has a type annotation
-                // by looking at the text after the parameter name in the current buffer
-                if parameter_has_annotation(&lines, usage.line, usage.end_char) {
-                    continue;
-                }
-
-                // Safety: fixture_map only contains defs with return_type.is_some()
-                let return_type = def.return_type.as_deref().unwrap();
+            let lsp_line = Self::internal_line_to_lsp(usage.line);
+            let position = Position {
+                line: lsp_line,
+                character: self.to_lsp_col(&file_path, usage.line, usage.end_char),
+            };
+
+            // Look up fixture definition from pre-computed map.
+            // Skip parameters that already have a type annotation, checked
+            // by looking at the text after the parameter name in the current buffer
+            if let Some(def) = fixture_map
+                .get(usage.name.as_str())
+                .filter(|_| !parameter_has_annotation(&lines, usage.line, usage.end_char))
+            {
+                // Safety: fixture_map only contains defs with a return type,
+                // annotated or inferred
+                let (return_type, inferred) = match &def.return_type {
+                    Some(return_type) => (return_type.as_str(), false),
+                    None => (def.inferred_return_type.as_deref().unwrap(), true),
+                };
 
                 // Adapt the type string to the consumer's import style.
                 // e.g. if the consumer has `from pathlib import Path` already,
@@ -130,21 +153,56 @@ impl Backend {
                     &consumer_import_map,
                 );
 
-                let lsp_line = Self::internal_line_to_lsp(usage.line);
-
+                let (label, tooltip) = if inferred {
+                    (
+                        format!(": ~{}", display_type),
+                        format!(
+                            "Fixture '{}' returns {} (inferred from its body)",
+                            usage.name, display_type
+                        ),
+                    )
+                } else {
+                    (
+                        format!(": {}", display_type),
+                        format!("Fixture '{}' returns {}", usage.name, display_type),
+                    )
+                };
                 hints.push(InlayHint {
-                    position: Position {
-                        line: lsp_line,
-                        character: self.to_lsp_col(&file_path, usage.line, usage.end_char),
-                    },
-                    label: InlayHintLabel::String(format!(": {}", display_type)),
+                    position,
+                    label: InlayHintLabel::String(label),
                     kind: Some(InlayHintKind::TYPE),
                     text_edits: None,
+                    tooltip: Some(InlayHintTooltip::String(tooltip)),
+                    padding_left: Some(false),
+                    padding_right: Some(false),
+                    data: None,
+                });
+            }
+
+            if settings.origin {
+                // Resolve per usage so a fixture requesting its own name
+                // points at the definition it overrides
+                let Some(def) = self.fixture_db.find_fixture_definition(
+                    &file_path,
+                    lsp_line,
+                    usage.start_char as u32,
+                ) else {
+                    continue;
+                };
+                let source = self.fixture_db.definition_source(&file_path, &def);
+
+                hints.push(InlayHint {
+                    position,
+                    label: InlayHintLabel::String(Self::fixture_origin_label(&source, &def)),
+                    kind: None,
+                    text_edits: None,
                     tooltip: Some(InlayHintTooltip::String(format!(
-                        "Fixture '{}' returns {}",
-                        usage.name, display_type
+                        "Fixture '{}' defined in {}:{}",
+                        usage.name,
+                        Self::display_path(&def.file_path, workspace_root.as_ref()),
+                        def.line
                     ))),
-                    padding_left: Some(false),
+                    padding_left: Some(true),
                     padding_right: Some(false),
                     data: None,
                 });
@@ -154,4 +212,23 @@ impl Backend {
         info!("Returning {} inlay hints", hints.len());
         Ok(Some(hints))
     }
+
+    /// Short description of where a fixture comes from, relative to the file
+    /// requesting it.
+    fn fixture_origin_label(source: &CandidateSource, def: &FixtureDefinition) -> String {
+        let conftest = |distance: usize| match distance {
+            0 => "conftest .".to_string(),
+            n => format!("conftest {}", vec![".."; n].join("/")),
+        };
+        match source {
+            CandidateSource::SameFile => "same file".to_string(),
+            CandidateSource::Conftest { distance } => conftest(*distance),
+            CandidateSource::ImportedByConftest { distance, .. } => {
+                format!("{} (imported)", conftest(*distance))
+            }
+            CandidateSource::Plugin | CandidateSource::ThirdParty | CandidateSource::NotVisible => {
+                format!("[plugin {}]", plugin_name(def))
+            }
+        }
+    }
 }
@@ -4,16 +4,18 @@
 //! library crate compile the impl, making `Backend` usable in integration
 //! tests via `LspService::new`.
 
-use std::sync::Arc;
-
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
 use tower_lsp_server::jsonrpc::Result;
 use tower_lsp_server::ls_types::request::{GotoImplementationParams, GotoImplementationResponse};
 use tower_lsp_server::ls_types::*;
 use tower_lsp_server::LanguageServer;
-use tracing::{error, info, warn};
+use tracing::{info, warn};
 
-use super::Backend;
-use crate::config;
+use super::document::{apply_content_changes, Document};
+use super::{Backend, PositionEncoding};
+use crate::fixtures::pytest_config::CONFIG_FILES;
 
 impl LanguageServer for Backend {
     async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
@@ -21,19 +23,61 @@ impl LanguageServer for Backend {
 
         // Negotiate position encoding: internal columns are UTF-8 byte offsets,
         // so prefer utf-8 when the client supports it and skip conversion entirely.
-        let client_supports_utf8 = params
+        let position_encoding = PositionEncoding::negotiate(
+            params
+                .capabilities
+                .general
+                .as_ref()
+                .and_then(|g| g.position_encodings.as_deref()),
+        );
+        self.set_position_encoding(position_encoding);
+        if let Some(trace) = params.trace {
+            self.set_trace_value(trace);
+        }
+        if let Some(pid) = params.process_id {
+            self.watch_parent_process(pid);
+        }
+
+        let completion_item = params
             .capabilities
-            .general
+            .text_document
             .as_ref()
-            .and_then(|g| g.position_encodings.as_ref())
-            .is_some_and(|encodings| encodings.contains(&PositionEncodingKind::UTF8));
-        self.client_utf16
-            .store(!client_supports_utf8, std::sync::atomic::Ordering::Relaxed);
-        let position_encoding = if client_supports_utf8 {
-            PositionEncodingKind::UTF8
-        } else {
-            PositionEncodingKind::UTF16
-        };
+            .and_then(|t| t.completion.as_ref())
+            .and_then(|c| c.completion_item.as_ref());
+        let snippet_support = completion_item
+            .and_then(|i| i.snippet_support)
+            .unwrap_or(false);
+        self.client_snippet_support
+            .store(snippet_support, std::sync::atomic::Ordering::Relaxed);
+        // There is no capability for `additionalTextEdits` themselves; a
+        // client that can resolve them lazily handles them. Others may drop
+        // them, which would complete a fixture name without declaring the
+        // parameter
+        let additional_edits_support = completion_item
+            .and_then(|i| i.resolve_support.as_ref())
+            .is_some_and(|r| r.properties.iter().any(|p| p == "additionalTextEdits"));
+        self.client_additional_edits_support.store(
+            additional_edits_support,
+            std::sync::atomic::Ordering::Relaxed,
+        );
+
+        let configuration_support = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|w| w.configuration)
+            .unwrap_or(false);
+        self.client_configuration_support
+            .store(configuration_support, std::sync::atomic::Ordering::Relaxed);
+
+        let work_done_progress = params
+            .capabilities
+            .window
+            .as_ref()
+            .and_then(|w| w.work_done_progress)
+            .unwrap_or(false);
+        self.client_work_done_progress
+            .store(work_done_progress, std::sync::atomic::Ordering::Relaxed);
 
         // Scan the workspace for fixtures on initialization
         // This is done in a background task to avoid blocking the LSP initialization
@@ -67,55 +111,15 @@ impl LanguageServer for Backend {
                 .unwrap_or_else(|_| first_root.clone());
             *self.workspace_root.write().await = Some(canonical_root.clone());
 
-            // Load configuration from pyproject.toml
-            let loaded_config = config::Config::load(&first_root);
-            info!("Loaded config: {:?}", loaded_config);
-            *self.config.write().await = loaded_config;
-
-            // Clone references for the background task
-            let fixture_db = Arc::clone(&self.fixture_db);
-            let client = self.client.clone();
-            let exclude_patterns = self.config.read().await.exclude.clone();
-
-            // Spawn workspace scanning in a background task
-            // This allows the LSP to respond immediately while scanning continues
-            let scan_handle = tokio::spawn(async move {
-                client
-                    .log_message(
-                        MessageType::INFO,
-                        format!("Scanning workspace: {:?}", root_paths),
-                    )
-                    .await;
-
-                // Run the synchronous scan in a blocking task to avoid blocking the async runtime
-                let scan_result = tokio::task::spawn_blocking(move || {
-                    for root_path in &root_paths {
-                        fixture_db.scan_workspace_with_excludes(root_path, &exclude_patterns);
-                    }
-                })
-                .await;
-
-                match scan_result {
-                    Ok(()) => {
-                        info!("Workspace scan complete");
-                        client
-                            .log_message(MessageType::INFO, "Workspace scan complete")
-                            .await;
-                    }
-                    Err(e) => {
-                        error!("Workspace scan failed: {:?}", e);
-                        client
-                            .log_message(
-                                MessageType::ERROR,
-                                format!("Workspace scan failed: {:?}", e),
-                            )
-                            .await;
-                    }
-                }
-            });
+            // Load configuration from pyproject.toml; editor settings sent as
+            // initializationOptions take precedence over it.
+            *self.initialization_options.write().await = params.initialization_options.clone();
+            self.load_config().await;
 
-            // Store the handle so we can cancel it on shutdown
-            *self.scan_task.lock().await = Some(scan_handle);
+            // Scan the workspace in the background; the LSP responds
+            // immediately while scanning continues
+            *self.scan_roots.write().await = root_paths.clone();
+            self.spawn_workspace_scan(root_paths, false).await;
         } else {
             warn!("No root URI provided in initialize - workspace scanning disabled");
             self.client
@@ -133,12 +137,12 @@ impl LanguageServer for Backend {
                 version: Some(env!("CARGO_PKG_VERSION").to_string()),
             }),
             capabilities: ServerCapabilities {
-                position_encoding: Some(position_encoding),
+                position_encoding: Some(position_encoding.kind()),
                 definition_provider: Some(OneOf::Left(true)),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
                 references_provider: Some(OneOf::Left(true)),
                 text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                    TextDocumentSyncKind::FULL,
+                    TextDocumentSyncKind::INCREMENTAL,
                 )),
                 code_action_provider: Some(CodeActionProviderCapability::Options(
                     CodeActionOptions {
@@ -154,11 +158,12 @@ impl LanguageServer for Backend {
                     },
                 )),
                 completion_provider: Some(CompletionOptions {
-                    resolve_provider: Some(false),
+                    resolve_provider: Some(true),
                     trigger_characters: Some(vec![
                         "\"".to_string(),
                         "(".to_string(),
                         ",".to_string(),
+                        ".".to_string(),
                     ]),
                     all_commit_characters: None,
                     work_done_progress_options: WorkDoneProgressOptions {
@@ -172,6 +177,26 @@ impl LanguageServer for Backend {
                     resolve_provider: Some(false),
                 }),
                 inlay_hint_provider: Some(OneOf::Left(true)),
+                execute_command_provider: Some(ExecuteCommandOptions {
+                    commands: super::execute_command::COMMANDS
+                        .iter()
+                        .map(|c| c.to_string())
+                        .collect(),
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                }),
+                document_link_provider: Some(DocumentLinkOptions {
+                    resolve_provider: Some(false),
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                }),
+                document_highlight_provider: Some(OneOf::Left(true)),
+                linked_editing_range_provider: Some(LinkedEditingRangeServerCapabilities::Simple(
+                    true,
+                )),
+                signature_help_provider: Some(SignatureHelpOptions {
+                    trigger_characters: Some(vec!["(".to_string(), ",".to_string()]),
+                    retrigger_characters: Some(vec!["=".to_string()]),
+                    work_done_progress_options: WorkDoneProgressOptions::default(),
+                }),
                 implementation_provider: Some(ImplementationProviderCapability::Simple(true)),
                 call_hierarchy_provider: Some(CallHierarchyServerCapability::Simple(true)),
                 rename_provider: Some(OneOf::Right(RenameOptions {
@@ -180,6 +205,13 @@ impl LanguageServer for Backend {
                         work_done_progress: None,
                     },
                 })),
+                workspace: Some(WorkspaceServerCapabilities {
+                    workspace_folders: Some(WorkspaceFoldersServerCapabilities {
+                        supported: Some(true),
+                        change_notifications: Some(OneOf::Left(true)),
+                    }),
+                    file_operations: None,
+                }),
                 ..Default::default()
             },
         })
@@ -187,6 +219,7 @@ impl LanguageServer for Backend {
 
     async fn initialized(&self, _: InitializedParams) {
         info!("Server initialized notification received");
+        self.client_initialized.store(true, Ordering::Relaxed);
         self.client
             .log_message(MessageType::INFO, "pytest-language-server initialized")
             .await;
@@ -209,14 +242,145 @@ impl LanguageServer for Backend {
             ),
         };
 
-        if let Err(e) = self.client.register_capability(vec![watch_init_py]).await {
+        // Register a file watcher for pytest configuration files so edits
+        // apply without restarting the server.
+        let watch_pytest_config = Registration {
+            id: "watch-pytest-config".to_string(),
+            method: "workspace/didChangeWatchedFiles".to_string(),
+            register_options: Some(
+                serde_json::to_value(DidChangeWatchedFilesRegistrationOptions {
+                    watchers: CONFIG_FILES
+                        .iter()
+                        .map(|(name, _)| FileSystemWatcher {
+                            glob_pattern: GlobPattern::String(format!("**/{}", name)),
+                            kind: None,
+                        })
+                        .collect(),
+                })
+                .unwrap(),
+            ),
+        };
+
+        // Register a file watcher for conftest.py and test files, so changes
+        // made outside the editor (checkouts, generators) are re-indexed.
+        let test_file_patterns: Vec<String> = {
+            let patterns = self.fixture_db.test_file_patterns.lock().unwrap();
+            if patterns.is_empty() {
+                vec!["test_*.py".to_string(), "*_test.py".to_string()]
+            } else {
+                patterns.iter().map(|p| p.as_str().to_string()).collect()
+            }
+        };
+        let watch_test_files = Registration {
+            id: "watch-test-files".to_string(),
+            method: "workspace/didChangeWatchedFiles".to_string(),
+            register_options: Some(
+                serde_json::to_value(DidChangeWatchedFilesRegistrationOptions {
+                    watchers: std::iter::once("conftest.py".to_string())
+                        .chain(test_file_patterns)
+                        .map(|pattern| FileSystemWatcher {
+                            glob_pattern: GlobPattern::String(format!("**/{}", pattern)),
+                            kind: None,
+                        })
+                        .collect(),
+                })
+                .unwrap(),
+            ),
+        };
+
+        if let Err(e) = self
+            .client
+            .register_capability(vec![watch_init_py, watch_pytest_config, watch_test_files])
+            .await
+        {
             // Not fatal — file watching is best-effort.  The user can still
             // manually re-open fixture files to trigger re-analysis.
             info!(
-                "Failed to register __init__.py file watcher (client may not support it): {}",
+                "Failed to register file watchers (client may not support it): {}",
                 e
             );
         }
+
+        // Settings from `workspace/configuration` are applied on top of the
+        // initializationOptions.
+        if let Some(settings) = self.pull_settings().await {
+            let changed = self.settings.read().await.as_ref() != Some(&settings);
+            if changed {
+                *self.settings.write().await = Some(settings);
+                self.reload_config().await;
+            }
+        }
+    }
+
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        info!("did_change_configuration");
+        // Clients supporting `workspace/configuration` are asked for the
+        // current settings; others push them in the notification, either as
+        // our section of their settings or as the settings themselves.
+        let settings = match self.pull_settings().await {
+            Some(settings) => Some(settings),
+            None => Some(params.settings).filter(|settings| settings.is_object()),
+        };
+        if let Some(settings) = settings {
+            *self.settings.write().await = Some(settings);
+        }
+        self.reload_config().await;
+    }
+
+    async fn did_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) {
+        let folder_paths = |folders: &[WorkspaceFolder]| -> Vec<std::path::PathBuf> {
+            folders
+                .iter()
+                .filter_map(|folder| folder.uri.to_file_path().map(|p| p.to_path_buf()))
+                .collect()
+        };
+        let added = folder_paths(&params.event.added);
+        let removed = folder_paths(&params.event.removed);
+        info!(
+            "did_change_workspace_folders: added {:?}, removed {:?}",
+            added, removed
+        );
+
+        let remaining = {
+            let mut roots = self.scan_roots.write().await;
+            roots.retain(|root| !removed.contains(root));
+            let new_roots: Vec<_> = added
+                .iter()
+                .filter(|root| !roots.contains(root))
+                .cloned()
+                .collect();
+            roots.extend(new_roots);
+            roots.clone()
+        };
+
+        // Forget the fixtures of removed folders, keeping files that still
+        // belong to a remaining (e.g. nested) folder
+        let canonical = |path: &std::path::PathBuf| path.canonicalize().unwrap_or(path.clone());
+        let keep: Vec<_> = remaining.iter().map(canonical).collect();
+        for root in &removed {
+            self.fixture_db.remove_files_under(&canonical(root), &keep);
+        }
+        self.unwatch_workspace(&removed);
+
+        // Configuration and relative paths follow the first folder
+        let first_root_removed = self
+            .original_workspace_root
+            .read()
+            .await
+            .as_ref()
+            .is_none_or(|root| removed.contains(root));
+        if first_root_removed {
+            let first_root = remaining.first().cloned();
+            *self.workspace_root.write().await = first_root.as_ref().map(canonical);
+            *self.original_workspace_root.write().await = first_root;
+            self.load_config().await;
+        }
+
+        if added.is_empty() {
+            self.refresh_open_files().await;
+        } else {
+            self.spawn_workspace_scan(added, false).await;
+        }
     }
 
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
@@ -227,9 +391,12 @@ impl LanguageServer for Backend {
             // This ensures we respond with URIs the client recognizes
             self.uri_cache.insert(file_path.clone(), uri.clone());
 
+            self.documents
+                .insert(file_path.clone(), Document::new(&params.text_document.text));
+
             info!("Analyzing file: {:?}", file_path);
             self.fixture_db
-                .analyze_file(file_path.clone(), &params.text_document.text);
+                .open_file(file_path.clone(), &params.text_document.text);
 
             // Publish diagnostics for undeclared fixtures
             self.publish_diagnostics_for_file(&uri, &file_path).await;
@@ -240,14 +407,42 @@ impl LanguageServer for Backend {
         let uri = params.text_document.uri.clone();
         info!("did_change: {:?}", uri);
         if let Some(file_path) = self.uri_to_path(&uri) {
-            if let Some(change) = params.content_changes.first() {
-                info!("Re-analyzing file: {:?}", file_path);
-                self.fixture_db
-                    .analyze_file(file_path.clone(), &change.text);
+            if !params.content_changes.is_empty() {
+                let debounce =
+                    std::time::Duration::from_millis(self.config.read().await.debounce_ms);
+                let in_burst = {
+                    let mut document =
+                        self.documents.entry(file_path.clone()).or_insert_with(|| {
+                            Document::new(
+                                &self
+                                    .fixture_db
+                                    .get_file_content(&file_path)
+                                    .unwrap_or_default(),
+                            )
+                        });
+                    apply_content_changes(
+                        &mut document.text,
+                        &params.content_changes,
+                        self.position_encoding(),
+                    );
+                    document.pending = true;
+                    let now = std::time::Instant::now();
+                    let in_burst = document
+                        .last_change
+                        .is_some_and(|last| now.duration_since(last) < debounce);
+                    document.last_change = Some(now);
+                    in_burst
+                };
+
+                // The first edit after a quiet period is analyzed right away;
+                // later edits of the same burst are coalesced into a single
+                // analysis once typing pauses.
+                if !in_burst {
+                    self.analyze_pending(&file_path);
+                }
 
                 // Debounce the follow-on work (cycle/scope diagnostics and the
-                // inlay-hint refresh round trip) so rapid keystrokes coalesce
-                // into a single pass once typing pauses.
+                // inlay-hint refresh round trip) the same way.
                 let generation = {
                     let mut entry = self.change_generation.entry(file_path.clone()).or_insert(0);
                     *entry += 1;
@@ -256,13 +451,15 @@ impl LanguageServer for Backend {
 
                 let backend = self.clone();
                 tokio::spawn(async move {
-                    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+                    tokio::time::sleep(debounce).await;
 
-                    // A newer change superseded this one — its task will publish.
+                    // A newer change superseded this one — its task will
+                    // analyze and publish.
                     let current = backend.change_generation.get(&file_path).map(|g| *g);
                     if current != Some(generation) {
                         return;
                     }
+                    backend.analyze_pending(&file_path);
 
                     // Publish diagnostics for undeclared fixtures
                     backend.publish_diagnostics_for_file(&uri, &file_path).await;
@@ -295,9 +492,53 @@ impl LanguageServer for Backend {
     }
 
     async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        // Reload the configuration when the file pytest reads its settings
+        // from changes, or a configuration file appears or disappears in the
+        // workspace root or above it (which may move pytest's rootdir).
+        let workspace_root = self.workspace_root.read().await.clone();
+        let inifile = self.fixture_db.pytest_root().and_then(|root| root.inifile);
+        let config_changed = params.changes.iter().any(|event| {
+            self.uri_to_path(&event.uri).is_some_and(|path| {
+                let is_config_file = path
+                    .file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|name| CONFIG_FILES.iter().any(|(config, _)| *config == name));
+                is_config_file
+                    && (Some(&path) == inifile.as_ref()
+                        || path.parent().is_some_and(|dir| {
+                            workspace_root
+                                .as_deref()
+                                .is_some_and(|root| root.starts_with(dir))
+                        }))
+            })
+        });
+        if config_changed {
+            info!("pytest configuration changed — reloading configuration");
+            self.reload_config().await;
+        }
+
+        // conftest.py and test files changed on disk are re-analyzed, deleted
+        // ones evicted.
+        let changed_files: HashSet<PathBuf> = params
+            .changes
+            .iter()
+            .filter_map(|event| self.uri_to_path(&event.uri))
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|name| {
+                        name == "conftest.py" || self.fixture_db.is_test_file_name(name)
+                    })
+            })
+            .collect();
+        if !changed_files.is_empty() {
+            self.apply_file_changes(changed_files).await;
+        }
+
         // Re-analyze fixture files whose `return_type_imports` may have become
         // stale because an `__init__.py` was created or deleted, changing the
         // result of `file_path_to_module_path()`.
+        let mut init_py_changed = false;
         for event in &params.changes {
             if event.typ != FileChangeType::CREATED && event.typ != FileChangeType::DELETED {
                 continue;
@@ -306,6 +547,13 @@ impl LanguageServer for Backend {
             let Some(init_path) = self.uri_to_path(&event.uri) else {
                 continue;
             };
+            if init_path
+                .file_name()
+                .is_none_or(|name| name != "__init__.py")
+            {
+                continue;
+            }
+            init_py_changed = true;
 
             // The __init__.py change affects the directory it lives in and
             // every directory below it.  Any fixture file at or under that
@@ -348,7 +596,7 @@ impl LanguageServer for Backend {
         }
 
         // Refresh inlay hints in case return types changed.
-        if !params.changes.is_empty() {
+        if init_py_changed {
             if let Err(e) = self.client.inlay_hint_refresh().await {
                 info!(
                     "Inlay hint refresh after __init__.py change failed (client may not support it): {}",
@@ -362,10 +610,12 @@ impl LanguageServer for Backend {
         let uri = params.text_document.uri;
         info!("did_close: {:?}", uri);
         if let Some(file_path) = self.uri_to_path(&uri) {
-            // Clean up cached data for this file to prevent unbounded memory growth
-            self.fixture_db.cleanup_file_cache(&file_path);
+            // Clean up cached data for this file to prevent unbounded memory
+            // growth, or everything learned from it if it was never saved
+            self.fixture_db.close_file(&file_path);
             // Clean up URI cache entry
             self.uri_cache.remove(&file_path);
+            self.documents.remove(&file_path);
             // Drop the debounce counter for this file
             self.change_generation.remove(&file_path);
 
@@ -380,6 +630,7 @@ impl LanguageServer for Backend {
         &self,
         params: GotoDefinitionParams,
     ) -> Result<Option<GotoDefinitionResponse>> {
+        self.flush_pending_analysis();
         self.handle_goto_definition(params).await
     }
 
@@ -387,14 +638,17 @@ impl LanguageServer for Backend {
         &self,
         params: GotoImplementationParams,
     ) -> Result<Option<GotoImplementationResponse>> {
+        self.flush_pending_analysis();
         self.handle_goto_implementation(params).await
     }
 
     async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
+        self.flush_pending_analysis();
         self.handle_hover(params).await
     }
 
     async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
+        self.flush_pending_analysis();
         self.handle_references(params).await
     }
 
@@ -402,18 +656,26 @@ impl LanguageServer for Backend {
         &self,
         params: TextDocumentPositionParams,
     ) -> Result<Option<PrepareRenameResponse>> {
+        self.flush_pending_analysis();
         self.handle_prepare_rename(params).await
     }
 
     async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+        self.flush_pending_analysis();
         self.handle_rename(params).await
     }
 
     async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
+        self.flush_pending_analysis();
         self.handle_completion(params).await
     }
 
+    async fn completion_resolve(&self, params: CompletionItem) -> Result<CompletionItem> {
+        self.handle_completion_resolve(params).await
+    }
+
     async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        self.flush_pending_analysis();
         self.handle_code_action(params).await
     }
 
@@ -421,6 +683,7 @@ impl LanguageServer for Backend {
         &self,
         params: DocumentSymbolParams,
     ) -> Result<Option<DocumentSymbolResponse>> {
+        self.flush_pending_analysis();
         self.handle_document_symbol(params).await
     }
 
@@ -428,22 +691,57 @@ impl LanguageServer for Backend {
         &self,
         params: WorkspaceSymbolParams,
     ) -> Result<Option<WorkspaceSymbolResponse>> {
+        self.flush_pending_analysis();
         let result = self.handle_workspace_symbol(params).await?;
         Ok(result.map(WorkspaceSymbolResponse::Flat))
     }
 
     async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
+        self.flush_pending_analysis();
         self.handle_code_lens(params).await
     }
 
     async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        self.flush_pending_analysis();
         self.handle_inlay_hint(params).await
     }
 
+    async fn document_link(&self, params: DocumentLinkParams) -> Result<Option<Vec<DocumentLink>>> {
+        self.flush_pending_analysis();
+        self.handle_document_link(params).await
+    }
+
+    async fn document_highlight(
+        &self,
+        params: DocumentHighlightParams,
+    ) -> Result<Option<Vec<DocumentHighlight>>> {
+        self.flush_pending_analysis();
+        self.handle_document_highlight(params).await
+    }
+
+    async fn linked_editing_range(
+        &self,
+        params: LinkedEditingRangeParams,
+    ) -> Result<Option<LinkedEditingRanges>> {
+        self.flush_pending_analysis();
+        self.handle_linked_editing_range(params).await
+    }
+
+    async fn signature_help(&self, params: SignatureHelpParams) -> Result<Option<SignatureHelp>> {
+        self.flush_pending_analysis();
+        self.handle_signature_help(params).await
+    }
+
+    async fn execute_command(&self, params: ExecuteCommandParams) -> Result<Option<LSPAny>> {
+        self.flush_pending_analysis();
+        self.handle_execute_command(params).await
+    }
+
     async fn prepare_call_hierarchy(
         &self,
         params: CallHierarchyPrepareParams,
     ) -> Result<Option<Vec<CallHierarchyItem>>> {
+        self.flush_pending_analysis();
         self.handle_prepare_call_hierarchy(params).await
     }
 
@@ -451,6 +749,7 @@ impl LanguageServer for Backend {
         &self,
         params: CallHierarchyIncomingCallsParams,
     ) -> Result<Option<Vec<CallHierarchyIncomingCall>>> {
+        self.flush_pending_analysis();
         self.handle_incoming_calls(params).await
     }
 
@@ -458,12 +757,15 @@ impl LanguageServer for Backend {
         &self,
         params: CallHierarchyOutgoingCallsParams,
     ) -> Result<Option<Vec<CallHierarchyOutgoingCall>>> {
+        self.flush_pending_analysis();
         self.handle_outgoing_calls(params).await
     }
 
     async fn shutdown(&self) -> Result<()> {
         info!("Shutdown request received");
 
+        self.stop_watching();
+
         // Cancel the background scan task if it's still running
         if let Some(handle) = self.scan_task.lock().await.take() {
             info!("Aborting background workspace scan task");
@@ -477,15 +779,16 @@ impl LanguageServer for Backend {
         }
 
         info!("Shutdown complete");
+        self.shutdown_requested.store(true, Ordering::Relaxed);
 
-        // tower-lsp doesn't always exit cleanly after the exit notification
-        // (serve() may block on stdin/stdout), so we spawn a task to force
-        // exit after a brief delay to allow the shutdown response to be sent.
+        // The process exits on the `exit` notification that follows. Should
+        // the client never send it, or serve() block on stdin/stdout after
+        // it, exit anyway once the client had time to.
         // Skipped during `cargo test` to avoid terminating the test runner.
         #[cfg(not(test))]
         tokio::spawn(async {
-            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
-            info!("Forcing process exit");
+            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+            warn!("No exit notification after shutdown, exiting");
             std::process::exit(0);
         });
 
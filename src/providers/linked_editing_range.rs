@@ -0,0 +1,56 @@
+//! Linked editing range provider for fixture parameters.
+//!
+//! Links a parameter of a test or fixture to its uses in the function body,
+//! so editing one edits them all: a lighter, local-only alternative to
+//! renaming the fixture across the workspace.
+
+use super::Backend;
+use tower_lsp_server::jsonrpc::Result;
+use tower_lsp_server::ls_types::*;
+use tracing::info;
+
+impl Backend {
+    /// Handle linkedEditingRange request.
+    pub async fn handle_linked_editing_range(
+        &self,
+        params: LinkedEditingRangeParams,
+    ) -> Result<Option<LinkedEditingRanges>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+
+        info!(
+            "linkedEditingRange request: uri={:?}, line={}, char={}",
+            uri, position.line, position.character
+        );
+
+        let Some(file_path) = self.uri_to_path(&uri) else {
+            return Ok(None);
+        };
+        let byte_col = self.to_byte_col(&file_path, position);
+        let Some(occurrences) =
+            self.fixture_db
+                .find_parameter_occurrences(&file_path, position.line, byte_col)
+        else {
+            return Ok(None);
+        };
+
+        let ranges: Vec<Range> = occurrences
+            .into_iter()
+            .map(|(line, start_char, end_char)| {
+                let lsp_line = Self::internal_line_to_lsp(line);
+                Self::create_range(
+                    lsp_line,
+                    self.to_lsp_col(&file_path, line, start_char),
+                    lsp_line,
+                    self.to_lsp_col(&file_path, line, end_char),
+                )
+            })
+            .collect();
+
+        info!("Returning {} linked editing ranges", ranges.len());
+        Ok(Some(LinkedEditingRanges {
+            ranges,
+            word_pattern: None,
+        }))
+    }
+}
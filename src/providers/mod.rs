@@ -3,61 +3,49 @@
 //! This module contains the Backend struct and LSP protocol handlers organized by provider type.
 
 pub mod call_hierarchy;
+pub mod catch_panic;
 pub mod code_action;
 pub mod code_lens;
 pub mod completion;
 pub mod definition;
 pub mod diagnostics;
+mod document;
+pub mod document_highlight;
+pub mod document_link;
 pub mod document_symbol;
+pub mod execute_command;
+pub mod file_watcher;
 pub mod hover;
 pub mod implementation;
 pub mod inlay_hint;
 mod language_server;
+pub mod linked_editing_range;
+mod parent_process;
+mod position_encoding;
+mod progress;
 pub mod references;
 pub mod rename;
+pub mod signature_help;
+pub mod test_discovery;
+pub mod trace;
 pub mod workspace_symbol;
 
 use crate::config::Config;
-use crate::fixtures::FixtureDatabase;
+use crate::fixtures::{FixtureDatabase, ScanProgress};
 use dashmap::DashMap;
+pub use position_encoding::PositionEncoding;
 use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use std::sync::Arc;
 use tower_lsp_server::ls_types::*;
 use tower_lsp_server::Client;
-use tracing::warn;
+use tracing::{error, info, warn};
 
-/// Convert a UTF-16 column to a byte offset within `line`.
-/// Columns past the end of the line clamp to the line's byte length.
-pub(crate) fn utf16_col_to_byte(line: &str, utf16_col: usize) -> usize {
-    if line.is_ascii() {
-        return utf16_col.min(line.len());
-    }
-    let mut units = 0usize;
-    for (byte_idx, ch) in line.char_indices() {
-        if units >= utf16_col {
-            return byte_idx;
-        }
-        units += ch.len_utf16();
-    }
-    line.len()
-}
+/// Maximum number of source lines shown in fixture documentation snippets.
+const HOVER_SNIPPET_MAX_LINES: usize = 15;
 
-/// Convert a byte offset within `line` to a UTF-16 column.
-/// Offsets past the end of the line clamp to the line's UTF-16 length.
-pub(crate) fn byte_col_to_utf16(line: &str, byte_col: usize) -> usize {
-    if line.is_ascii() {
-        return byte_col.min(line.len());
-    }
-    let mut units = 0usize;
-    for (byte_idx, ch) in line.char_indices() {
-        if byte_idx >= byte_col {
-            break;
-        }
-        units += ch.len_utf16();
-    }
-    units
-}
+/// Progress token and state of a running workspace scan.
+pub type ActiveScan = (ProgressToken, Arc<ScanProgress>);
 
 /// The LSP Backend struct containing server state.
 pub struct Backend {
@@ -67,19 +55,49 @@ pub struct Backend {
     pub workspace_root: Arc<tokio::sync::RwLock<Option<PathBuf>>>,
     /// The original workspace root path as provided by the client (may contain symlinks)
     pub original_workspace_root: Arc<tokio::sync::RwLock<Option<PathBuf>>>,
+    /// Workspace folders scanned for fixtures
+    pub scan_roots: Arc<tokio::sync::RwLock<Vec<PathBuf>>>,
+    /// Native watcher re-indexing files changed outside the editor
+    pub file_watcher: Arc<std::sync::Mutex<Option<file_watcher::FileWatcher>>>,
+    /// Progress token and state of the running workspace scan, if any
+    pub scan_progress: Arc<std::sync::Mutex<Option<ActiveScan>>>,
     /// Handle to the background workspace scan task, used for cancellation on shutdown
     pub scan_task: Arc<tokio::sync::Mutex<Option<tokio::task::JoinHandle<()>>>>,
     /// Cache mapping canonical paths to original URIs from the client
     /// This ensures we respond with URIs the client recognizes
     pub uri_cache: Arc<DashMap<PathBuf, Uri>>,
+    /// Contents of the open documents, kept up to date with incremental edits
+    pub documents: Arc<DashMap<PathBuf, document::Document>>,
     /// Configuration loaded from pyproject.toml
     pub config: Arc<tokio::sync::RwLock<Config>>,
-    /// Whether the client uses UTF-16 position encoding (the LSP default).
-    /// Set to false during initialize when the client supports UTF-8, in which
-    /// case our internal byte columns can be sent as-is.
-    pub client_utf16: Arc<AtomicBool>,
-    /// Per-file change generation counters used to debounce diagnostics
-    /// publishing while the user is typing.
+    /// Editor settings sent as initializationOptions, applied on top of
+    /// pyproject.toml whenever the configuration is (re)loaded.
+    pub initialization_options: Arc<tokio::sync::RwLock<Option<serde_json::Value>>>,
+    /// Editor settings from `workspace/configuration` or
+    /// `workspace/didChangeConfiguration`, applied on top of the
+    /// initializationOptions.
+    pub settings: Arc<tokio::sync::RwLock<Option<serde_json::Value>>>,
+    /// The [`PositionEncoding`] negotiated with the client, UTF-16 (the LSP
+    /// default) until initialize.
+    position_encoding: Arc<AtomicU8>,
+    /// Whether the client accepts snippet syntax in completion items.
+    pub client_snippet_support: Arc<AtomicBool>,
+    /// Whether the client applies a completion item's `additionalTextEdits`.
+    /// Assumed until initialize, and cleared for clients describing no
+    /// completion item features at all.
+    pub client_additional_edits_support: Arc<AtomicBool>,
+    /// Whether the client answers `workspace/configuration` requests.
+    pub client_configuration_support: Arc<AtomicBool>,
+    /// Whether the client shows work-done progress (`window.workDoneProgress`).
+    pub client_work_done_progress: Arc<AtomicBool>,
+    /// Whether the client sent `initialized`, after which requests may be sent to it.
+    pub client_initialized: Arc<AtomicBool>,
+    /// The client's trace level (`$/setTrace`), see [`trace`].
+    pub trace: Arc<AtomicU8>,
+    /// Whether `shutdown` was received, making `exit` a clean exit.
+    pub shutdown_requested: Arc<AtomicBool>,
+    /// Per-file change generation counters used to debounce re-analysis and
+    /// diagnostics publishing while the user is typing.
     pub change_generation: Arc<DashMap<PathBuf, u64>>,
 }
 
@@ -90,10 +108,23 @@ impl Clone for Backend {
             fixture_db: Arc::clone(&self.fixture_db),
             workspace_root: Arc::clone(&self.workspace_root),
             original_workspace_root: Arc::clone(&self.original_workspace_root),
+            scan_roots: Arc::clone(&self.scan_roots),
+            file_watcher: Arc::clone(&self.file_watcher),
+            scan_progress: Arc::clone(&self.scan_progress),
             scan_task: Arc::clone(&self.scan_task),
             uri_cache: Arc::clone(&self.uri_cache),
+            documents: Arc::clone(&self.documents),
             config: Arc::clone(&self.config),
-            client_utf16: Arc::clone(&self.client_utf16),
+            initialization_options: Arc::clone(&self.initialization_options),
+            settings: Arc::clone(&self.settings),
+            position_encoding: Arc::clone(&self.position_encoding),
+            client_snippet_support: Arc::clone(&self.client_snippet_support),
+            client_additional_edits_support: Arc::clone(&self.client_additional_edits_support),
+            client_configuration_support: Arc::clone(&self.client_configuration_support),
+            client_work_done_progress: Arc::clone(&self.client_work_done_progress),
+            client_initialized: Arc::clone(&self.client_initialized),
+            trace: Arc::clone(&self.trace),
+            shutdown_requested: Arc::clone(&self.shutdown_requested),
             change_generation: Arc::clone(&self.change_generation),
         }
     }
@@ -107,14 +138,257 @@ impl Backend {
             fixture_db,
             workspace_root: Arc::new(tokio::sync::RwLock::new(None)),
             original_workspace_root: Arc::new(tokio::sync::RwLock::new(None)),
+            scan_roots: Arc::new(tokio::sync::RwLock::new(Vec::new())),
+            file_watcher: Arc::new(std::sync::Mutex::new(None)),
+            scan_progress: Arc::new(std::sync::Mutex::new(None)),
             scan_task: Arc::new(tokio::sync::Mutex::new(None)),
             uri_cache: Arc::new(DashMap::new()),
+            documents: Arc::new(DashMap::new()),
             config: Arc::new(tokio::sync::RwLock::new(Config::default())),
-            client_utf16: Arc::new(AtomicBool::new(true)),
+            initialization_options: Arc::new(tokio::sync::RwLock::new(None)),
+            settings: Arc::new(tokio::sync::RwLock::new(None)),
+            position_encoding: Arc::new(AtomicU8::new(PositionEncoding::Utf16.to_u8())),
+            client_snippet_support: Arc::new(AtomicBool::new(false)),
+            client_additional_edits_support: Arc::new(AtomicBool::new(true)),
+            client_configuration_support: Arc::new(AtomicBool::new(false)),
+            client_work_done_progress: Arc::new(AtomicBool::new(false)),
+            client_initialized: Arc::new(AtomicBool::new(false)),
+            trace: Arc::new(AtomicU8::new(trace::trace_to_u8(TraceValue::Off))),
+            shutdown_requested: Arc::new(AtomicBool::new(false)),
             change_generation: Arc::new(DashMap::new()),
         }
     }
 
+    /// Load the workspace configuration: its `pyproject.toml`, with the
+    /// initializationOptions and then the editor settings applied on top. The
+    /// virtual environment, fixture decorators, test file patterns, index
+    /// cache and file cache settings are handed to the fixture database.
+    pub(crate) async fn load_config(&self) {
+        let Some(root) = self.original_workspace_root.read().await.clone() else {
+            return;
+        };
+        let mut config = Config::load(&root);
+        if let Some(options) = self.initialization_options.read().await.as_ref() {
+            config.apply_settings(options);
+        }
+        if let Some(settings) = self.settings.read().await.as_ref() {
+            config.apply_settings(settings);
+        }
+        info!("Loaded config: {:?}", config);
+        self.fixture_db.set_venv_path(config.venv.clone());
+        self.fixture_db.set_python_path(config.python_path.clone());
+        self.fixture_db
+            .set_subproject_venv_paths(config.venvs.clone().into_iter().collect());
+        self.fixture_db
+            .set_fixture_decorators(config.fixture_decorators.clone());
+        self.fixture_db
+            .set_test_file_patterns(config.test_file_patterns.clone());
+        let cache_dir = match &config.cache_dir {
+            Some(cache_dir) => Some(root.join(cache_dir)),
+            None => crate::fixtures::default_cache_dir(),
+        };
+        self.fixture_db
+            .set_cache_dir(cache_dir.filter(|_| config.index_cache));
+        self.fixture_db
+            .set_file_cache_budget(config.file_cache_mb.saturating_mul(1024 * 1024));
+        *self.config.write().await = config;
+    }
+
+    /// The editor's settings for this server, asked for with
+    /// `workspace/configuration`; `None` when the client does not support the
+    /// request or has no settings for us.
+    pub(crate) async fn pull_settings(&self) -> Option<serde_json::Value> {
+        if !self.client_configuration_support.load(Ordering::Relaxed) {
+            return None;
+        }
+        let items = vec![ConfigurationItem {
+            scope_uri: None,
+            section: Some(crate::config::SETTINGS_SECTION.to_string()),
+        }];
+        match self.client.configuration(items).await {
+            Ok(mut values) => values.pop().filter(|settings| settings.is_object()),
+            Err(e) => {
+                info!("workspace/configuration request failed: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Reload the configuration after `pyproject.toml` or the editor settings
+    /// changed. The workspace is rescanned when scanning settings (`exclude`,
+    /// `respect_gitignore`, `venv`, `python_path`, `venvs`, `fixture_decorators`,
+    /// `test_file_patterns`) changed; otherwise the diagnostics and inlay hints of open files are
+    /// refreshed.
+    pub(crate) async fn reload_config(&self) {
+        let previous = self.config.read().await.clone();
+        self.load_config().await;
+        if let Some(root) = self.workspace_root.read().await.clone() {
+            self.fixture_db.load_pytest_root(&root);
+        }
+
+        let scan_settings_changed = {
+            let config = self.config.read().await;
+            config.exclude != previous.exclude
+                || config.respect_gitignore != previous.respect_gitignore
+                || config.venv != previous.venv
+                || config.python_path != previous.python_path
+                || config.venvs != previous.venvs
+                || config.fixture_decorators != previous.fixture_decorators
+                || config.test_file_patterns != previous.test_file_patterns
+        };
+        if scan_settings_changed {
+            info!("Scan settings changed — rescanning the workspace");
+            let roots = self.scan_roots.read().await.clone();
+            self.spawn_workspace_scan(roots, true).await;
+        } else {
+            self.refresh_open_files().await;
+        }
+    }
+
+    /// Re-publish the diagnostics of every open file and ask the client to
+    /// refresh inlay hints, after the fixtures they see may have changed.
+    pub(crate) async fn refresh_open_files(&self) {
+        let open_files: Vec<(Uri, PathBuf)> = self
+            .uri_cache
+            .iter()
+            .map(|entry| (entry.value().clone(), entry.key().clone()))
+            .collect();
+        if open_files.is_empty() {
+            return;
+        }
+        for (uri, file_path) in open_files {
+            self.publish_diagnostics_for_file(&uri, &file_path).await;
+        }
+        if let Err(e) = self.client.inlay_hint_refresh().await {
+            info!(
+                "Inlay hint refresh failed (client may not support it): {}",
+                e
+            );
+        }
+    }
+
+    /// Scan workspace folders in a background task, so the server keeps
+    /// responding meanwhile. With `rescan`, the database is cleared first and
+    /// open files are re-analyzed from their editor contents afterwards.
+    pub(crate) async fn spawn_workspace_scan(&self, root_paths: Vec<PathBuf>, rescan: bool) {
+        if root_paths.is_empty() {
+            return;
+        }
+        let (exclude_patterns, respect_gitignore) = {
+            let config = self.config.read().await;
+            (config.exclude.clone(), config.respect_gitignore)
+        };
+        let previous_scan = self.scan_task.lock().await.take();
+        let backend = self.clone();
+
+        let scan_handle = tokio::spawn(async move {
+            // Let an earlier scan finish rather than interleave with it
+            if let Some(previous_scan) = previous_scan {
+                let _ = previous_scan.await;
+            }
+
+            backend
+                .client
+                .log_message(
+                    MessageType::INFO,
+                    format!("Scanning workspace: {:?}", root_paths),
+                )
+                .await;
+
+            let fixture_db = Arc::clone(&backend.fixture_db);
+            let scanned_roots = root_paths.clone();
+            // Open documents are re-analyzed from the editor's contents after a rescan
+            let open_files: Vec<(PathBuf, String)> = if rescan {
+                backend
+                    .documents
+                    .iter()
+                    .map(|entry| (entry.key().clone(), entry.text.to_string()))
+                    .collect()
+            } else {
+                Vec::new()
+            };
+
+            let progress = Arc::new(ScanProgress::default());
+            let started = std::time::Instant::now();
+            let mut reporter = progress::ScanReporter::new(&backend, Arc::clone(&progress));
+            let scan_progress = Arc::clone(&progress);
+
+            // Run the synchronous scan in a blocking task to avoid blocking the async runtime
+            let mut scan = tokio::task::spawn_blocking(move || {
+                if rescan {
+                    fixture_db.clear();
+                }
+
+                for root_path in &root_paths {
+                    if progress.is_cancelled() {
+                        break;
+                    }
+                    fixture_db.scan_workspace_with_progress(
+                        root_path,
+                        &exclude_patterns,
+                        respect_gitignore,
+                        &progress,
+                    );
+                }
+                for (path, content) in open_files {
+                    fixture_db.analyze_file(path, &content);
+                }
+            });
+            let scan_result = loop {
+                reporter.update().await;
+                tokio::select! {
+                    result = &mut scan => break result,
+                    _ = tokio::time::sleep(progress::PROGRESS_INTERVAL) => {}
+                }
+            };
+            reporter.finish().await;
+
+            match scan_result {
+                Ok(()) => {
+                    let message = if scan_progress.is_cancelled() {
+                        "Workspace scan cancelled"
+                    } else {
+                        "Workspace scan complete"
+                    };
+                    info!("{}", message);
+                    backend.client.log_message(MessageType::INFO, message).await;
+                    backend.report_analysis_failures();
+                    let definitions: usize = backend
+                        .fixture_db
+                        .definitions
+                        .iter()
+                        .map(|entry| entry.value().len())
+                        .sum();
+                    backend
+                        .log_trace(
+                            format!("{} in {}ms", message, started.elapsed().as_millis()),
+                            Some(format!(
+                                "Roots: {:?}\nFixture definitions: {}",
+                                scanned_roots, definitions
+                            )),
+                        )
+                        .await;
+                }
+                Err(e) => {
+                    error!("Workspace scan failed: {:?}", e);
+                    backend
+                        .client
+                        .log_message(
+                            MessageType::ERROR,
+                            format!("Workspace scan failed: {:?}", e),
+                        )
+                        .await;
+                }
+            }
+
+            backend.watch_workspace(&scanned_roots);
+            backend.refresh_open_files().await;
+        });
+
+        // Store the handle so we can cancel it on shutdown
+        *self.scan_task.lock().await = Some(scan_handle);
+    }
+
     /// Run `f` on the text of a 1-based line (without the trailing newline).
     /// Borrows straight from the cached content Arc — no per-call allocation —
     /// and uses the identity-keyed line index so the file is not re-hashed on
@@ -126,21 +400,30 @@ impl Backend {
         f: impl FnOnce(&str) -> R,
     ) -> Option<R> {
         let content = self.fixture_db.get_file_content(file_path)?;
-        let index = self.fixture_db.get_line_index_for(file_path, &content);
-        let start = *index.get(internal_line.checked_sub(1)?)?;
-        let end = index.get(internal_line).copied().unwrap_or(content.len());
-        Some(f(content[start..end].trim_end_matches(['\r', '\n'])))
+        let line = self
+            .fixture_db
+            .get_line_text(file_path, &content, internal_line)?;
+        Some(f(line))
+    }
+
+    /// The position encoding negotiated with the client.
+    pub fn position_encoding(&self) -> PositionEncoding {
+        PositionEncoding::from_u8(self.position_encoding.load(Ordering::Relaxed))
+    }
+
+    /// Set the position encoding used for LSP columns.
+    pub fn set_position_encoding(&self, encoding: PositionEncoding) {
+        self.position_encoding
+            .store(encoding.to_u8(), Ordering::Relaxed);
     }
 
     /// Convert an inbound LSP position's character to an internal byte column.
     pub(crate) fn to_byte_col(&self, file_path: &std::path::Path, position: Position) -> u32 {
-        if !self.client_utf16.load(Ordering::Relaxed) {
-            return position.character;
-        }
+        let encoding = self.position_encoding();
         self.with_line_text(
             file_path,
             Self::lsp_line_to_internal(position.line),
-            |line| utf16_col_to_byte(line, position.character as usize) as u32,
+            |line| encoding.col_to_byte(line, position.character as usize) as u32,
         )
         .unwrap_or(position.character)
     }
@@ -152,11 +435,12 @@ impl Backend {
         internal_line: usize,
         byte_col: usize,
     ) -> u32 {
-        if !self.client_utf16.load(Ordering::Relaxed) {
+        let encoding = self.position_encoding();
+        if encoding == PositionEncoding::Utf8 {
             return byte_col as u32;
         }
         self.with_line_text(file_path, internal_line, |line| {
-            byte_col_to_utf16(line, byte_col) as u32
+            encoding.byte_to_col(line, byte_col) as u32
         })
         .unwrap_or(byte_col as u32)
     }
@@ -164,6 +448,14 @@ impl Backend {
     /// Convert URI to PathBuf with error logging
     /// Canonicalizes the path to handle symlinks (e.g., /var -> /private/var on macOS)
     pub fn uri_to_path(&self, uri: &Uri) -> Option<PathBuf> {
+        let scheme = uri.scheme().as_str();
+        if scheme.eq_ignore_ascii_case("untitled") {
+            return self.untitled_path(uri);
+        }
+        if !scheme.eq_ignore_ascii_case("file") {
+            warn!("Ignoring URI with unsupported scheme: {:?}", uri);
+            return None;
+        }
         match uri.to_file_path() {
             Some(path) => {
                 // Canonicalize to match how paths are stored in FixtureDatabase
@@ -178,6 +470,24 @@ impl Backend {
         }
     }
 
+    /// The path standing in for an `untitled:` buffer that has not been saved
+    /// yet. A bare name (`untitled:Untitled-1`) is placed in the workspace
+    /// root, so the buffer sees the root conftest.py fixtures; clients that
+    /// already know where the file will be saved send that path instead.
+    fn untitled_path(&self, uri: &Uri) -> Option<PathBuf> {
+        let path = uri.to_file_path()?.into_owned();
+        if path.is_absolute() {
+            return Some(path);
+        }
+        let root = self
+            .workspace_root
+            .try_read()
+            .ok()
+            .and_then(|root| root.clone())
+            .or_else(|| std::env::current_dir().ok())?;
+        Some(root.join(path))
+    }
+
     /// Convert PathBuf to URI with error logging
     /// First checks the URI cache for a previously seen URI, then falls back to creating one
     pub fn path_to_uri(&self, path: &std::path::Path) -> Option<Uri> {
@@ -251,96 +561,106 @@ impl Backend {
         Self::create_range(line, character, line, character)
     }
 
+    /// Path relative to the workspace root for display, falling back to the file name.
+    pub fn display_path(path: &std::path::Path, workspace_root: Option<&PathBuf>) -> String {
+        workspace_root
+            .and_then(|root| path.strip_prefix(root).ok())
+            .and_then(|p| p.to_str())
+            .or_else(|| path.file_name().and_then(|f| f.to_str()))
+            .unwrap_or("unknown")
+            .to_string()
+    }
+
     /// Format fixture documentation for display (used in both hover and completions)
     pub fn format_fixture_documentation(
+        &self,
         fixture: &crate::fixtures::FixtureDefinition,
         workspace_root: Option<&PathBuf>,
     ) -> String {
         let mut content = String::new();
 
-        // Calculate relative path from workspace root
-        let relative_path = if let Some(root) = workspace_root {
-            fixture
-                .file_path
-                .strip_prefix(root)
-                .ok()
-                .and_then(|p| p.to_str())
-                .map(|s| s.to_string())
-                .unwrap_or_else(|| {
-                    fixture
-                        .file_path
-                        .file_name()
-                        .and_then(|f| f.to_str())
-                        .unwrap_or("unknown")
-                        .to_string()
-                })
-        } else {
-            fixture
-                .file_path
-                .file_name()
-                .and_then(|f| f.to_str())
-                .unwrap_or("unknown")
-                .to_string()
-        };
+        let relative_path = Self::display_path(&fixture.file_path, workspace_root);
 
         // Add "from" line with relative path
         content.push_str(&format!("**from** `{}`\n", relative_path));
 
-        // Add code block with fixture signature
-        let return_annotation = if let Some(ref ret_type) = &fixture.return_type {
-            format!(" -> {}", ret_type)
+        // Add code block with the fixture's source, falling back to a
+        // synthesized signature (e.g. assignment-style fixtures)
+        if let Some(snippet) = self
+            .fixture_db
+            .get_fixture_source_snippet(fixture, HOVER_SNIPPET_MAX_LINES)
+        {
+            content.push_str(&format!("```python\n{}\n```", snippet));
+            // The return type may come from elsewhere than the source shown,
+            // e.g. a plugin's type stub
+            if let Some(return_type) = &fixture.return_type {
+                if !Self::snippet_signature(&snippet).contains(return_type.as_str()) {
+                    content.push_str(&format!("\n\n*returns `{}`*", return_type));
+                }
+            }
         } else {
-            String::new()
-        };
+            let return_annotation = if let Some(ref ret_type) = &fixture.return_type {
+                format!(" -> {}", ret_type)
+            } else {
+                String::new()
+            };
 
-        content.push_str(&format!(
-            "```python\n@pytest.fixture\ndef {}(...){}:\n```",
-            fixture.name, return_annotation
-        ));
+            content.push_str(&format!(
+                "```python\n@pytest.fixture\ndef {}(...){}:\n```",
+                fixture.name, return_annotation
+            ));
+        }
+
+        if let Some(params) = &fixture.params {
+            content.push_str(&format!("\n\n*{}*", params.summary()));
+        }
+
+        if let (None, Some(inferred)) = (&fixture.return_type, &fixture.inferred_return_type) {
+            content.push_str(&format!("\n\n*returns `{}` (inferred)*", inferred));
+        }
 
         // Add docstring if present
         if let Some(ref docstring) = fixture.docstring {
             content.push_str("\n\n---\n\n");
-            content.push_str(docstring);
+            content.push_str(&crate::fixtures::docstring_to_markdown(docstring));
         }
 
         content
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::{byte_col_to_utf16, utf16_col_to_byte};
-
-    #[test]
-    fn test_utf16_byte_conversion_ascii() {
-        let line = "def test(fixture):";
-        assert_eq!(utf16_col_to_byte(line, 9), 9);
-        assert_eq!(byte_col_to_utf16(line, 9), 9);
-        // Clamps past end of line.
-        assert_eq!(utf16_col_to_byte(line, 100), line.len());
-        assert_eq!(byte_col_to_utf16(line, 100), line.len());
+    /// The `def` line(s) of a fixture's source snippet, up to the colon that
+    /// ends the signature.
+    fn snippet_signature(snippet: &str) -> &str {
+        let Some(start) = snippet.find("def ") else {
+            return "";
+        };
+        let signature = &snippet[start..];
+        let end = signature.find(":\n").map_or(signature.len(), |end| end + 1);
+        &signature[..end]
     }
 
-    #[test]
-    fn test_utf16_byte_conversion_bmp() {
-        // "é" is 2 bytes in UTF-8 but 1 UTF-16 code unit.
-        let line = "x = 'é'; fixture";
-        assert_eq!(utf16_col_to_byte(line, 9), 10);
-        assert_eq!(byte_col_to_utf16(line, 10), 9);
-    }
+    /// Format marker documentation for display (used in both hover and completions)
+    pub fn format_marker_documentation(
+        marker: &crate::fixtures::markers::MarkerInfo,
+        workspace_root: Option<&PathBuf>,
+    ) -> String {
+        use crate::fixtures::markers::MarkerSource;
 
-    #[test]
-    fn test_utf16_byte_conversion_astral() {
-        // "🎉" is 4 bytes in UTF-8 and 2 UTF-16 code units (surrogate pair);
-        // it starts at byte 5 / UTF-16 unit 5 and ends at byte 9 / unit 7.
-        let line = "s = '🎉'; f";
-        assert_eq!(utf16_col_to_byte(line, 7), 9);
-        assert_eq!(byte_col_to_utf16(line, 9), 7);
-        // "f" is at byte 12 / UTF-16 unit 10.
-        assert_eq!(utf16_col_to_byte(line, 10), 12);
-        assert_eq!(byte_col_to_utf16(line, 12), 10);
-        // A column inside the surrogate pair snaps to the next boundary.
-        assert_eq!(utf16_col_to_byte(line, 6), 9);
+        let mut content = String::new();
+        if let MarkerSource::Config(path) | MarkerSource::Registered(path) = &marker.source {
+            content.push_str(&format!(
+                "**from** `{}`\n",
+                Self::display_path(path, workspace_root)
+            ));
+        }
+        content.push_str(&format!(
+            "```python\n@pytest.mark.{}\n```",
+            marker.signature.as_deref().unwrap_or(&marker.name)
+        ));
+        if let Some(description) = &marker.description {
+            content.push_str("\n\n---\n\n");
+            content.push_str(description);
+        }
+        content
     }
 }
@@ -0,0 +1,87 @@
+//! Exit with the editor: the `processId` of the `initialize` request is
+//! watched, so a server whose editor crashed (and never sent `shutdown` and
+//! `exit`) does not linger as an orphan.
+
+use super::Backend;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How often the parent process is checked.
+const PARENT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Whether a process with this ID is running.
+#[cfg(unix)]
+pub(crate) fn is_process_alive(pid: u32) -> bool {
+    // 0 would check the process group instead
+    let Some(pid) = libc::pid_t::try_from(pid).ok().filter(|pid| *pid > 0) else {
+        return false;
+    };
+    // Signal 0 only checks the process exists; EPERM means it exists but
+    // belongs to another user
+    // SAFETY: kill with signal 0 sends no signal
+    let result = unsafe { libc::kill(pid, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Whether a process with this ID is running.
+#[cfg(windows)]
+pub(crate) fn is_process_alive(pid: u32) -> bool {
+    use windows_sys::Win32::Foundation::{CloseHandle, STILL_ACTIVE};
+    use windows_sys::Win32::System::Threading::{
+        GetExitCodeProcess, OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION,
+    };
+
+    // SAFETY: the handle is checked before use and closed after
+    unsafe {
+        let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+        if handle.is_null() {
+            return false;
+        }
+        let mut code = 0;
+        let alive = GetExitCodeProcess(handle, &mut code) != 0 && code == STILL_ACTIVE as u32;
+        CloseHandle(handle);
+        alive
+    }
+}
+
+impl Backend {
+    /// Exit once the editor process `pid` is gone. A process that cannot be
+    /// seen from the start (e.g. an editor outside the server's container)
+    /// is not watched.
+    pub(crate) fn watch_parent_process(&self, pid: u32) {
+        if !is_process_alive(pid) {
+            warn!("Parent process {} not found, not watching it", pid);
+            return;
+        }
+        info!("Watching parent process {}", pid);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(PARENT_POLL_INTERVAL).await;
+                if !is_process_alive(pid) {
+                    warn!("Parent process {} exited, exiting", pid);
+                    // Exiting without `shutdown` is an error exit
+                    std::process::exit(1);
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_process_alive() {
+        assert!(is_process_alive(std::process::id()));
+
+        let mut child = std::process::Command::new(std::env::current_exe().unwrap())
+            .arg("--list")
+            .stdout(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+        let pid = child.id();
+        child.wait().unwrap();
+        assert!(!is_process_alive(pid));
+    }
+}
@@ -0,0 +1,190 @@
+//! Position encoding negotiation and column conversion.
+//!
+//! Internally columns are UTF-8 byte offsets, as produced by the parser. An
+//! LSP `Position::character` counts in the encoding negotiated at initialize
+//! time (`general.positionEncodings`): UTF-8 bytes, UTF-16 code units (the
+//! protocol default) or UTF-32 code points. Every conversion between the two
+//! goes through [`PositionEncoding::col_to_byte`] and
+//! [`PositionEncoding::byte_to_col`].
+
+use tower_lsp_server::ls_types::PositionEncodingKind;
+
+/// What an LSP column counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PositionEncoding {
+    /// UTF-8 bytes: the same as internal columns, so no conversion is needed.
+    Utf8,
+    /// UTF-16 code units, the LSP default.
+    #[default]
+    Utf16,
+    /// Unicode code points.
+    Utf32,
+}
+
+impl PositionEncoding {
+    /// Pick the encoding for a client advertising `encodings`, in its order
+    /// of preference. UTF-8 wins whenever offered since it needs no
+    /// conversion; otherwise the client's first supported choice is used,
+    /// falling back to UTF-16, which every client must support.
+    pub fn negotiate(encodings: Option<&[PositionEncodingKind]>) -> Self {
+        let encodings = encodings.unwrap_or_default();
+        if encodings.contains(&PositionEncodingKind::UTF8) {
+            return Self::Utf8;
+        }
+        encodings
+            .iter()
+            .find_map(Self::from_kind)
+            .unwrap_or_default()
+    }
+
+    fn from_kind(kind: &PositionEncodingKind) -> Option<Self> {
+        [Self::Utf8, Self::Utf16, Self::Utf32]
+            .into_iter()
+            .find(|encoding| encoding.kind() == *kind)
+    }
+
+    /// The kind advertised in the server capabilities.
+    pub fn kind(self) -> PositionEncodingKind {
+        match self {
+            Self::Utf8 => PositionEncodingKind::UTF8,
+            Self::Utf16 => PositionEncodingKind::UTF16,
+            Self::Utf32 => PositionEncodingKind::UTF32,
+        }
+    }
+
+    pub(crate) fn to_u8(self) -> u8 {
+        self as u8
+    }
+
+    pub(crate) fn from_u8(value: u8) -> Self {
+        match value {
+            0 => Self::Utf8,
+            2 => Self::Utf32,
+            _ => Self::Utf16,
+        }
+    }
+
+    /// Length of `ch` in this encoding's units.
+    fn char_len(self, ch: char) -> usize {
+        match self {
+            Self::Utf8 => ch.len_utf8(),
+            Self::Utf16 => ch.len_utf16(),
+            Self::Utf32 => 1,
+        }
+    }
+
+    /// Convert a column in this encoding to a byte offset within `line`.
+    /// Columns past the end of the line clamp to the line's byte length, and a
+    /// column inside a character snaps to the next character boundary.
+    pub(crate) fn col_to_byte(self, line: &str, col: usize) -> usize {
+        if self == Self::Utf8 || line.is_ascii() {
+            let byte = col.min(line.len());
+            return (byte..=line.len())
+                .find(|&b| line.is_char_boundary(b))
+                .unwrap_or(line.len());
+        }
+        let mut units = 0usize;
+        for (byte_idx, ch) in line.char_indices() {
+            if units >= col {
+                return byte_idx;
+            }
+            units += self.char_len(ch);
+        }
+        line.len()
+    }
+
+    /// Convert a byte offset within `line` to a column in this encoding.
+    /// Offsets past the end of the line clamp to the line's length.
+    pub(crate) fn byte_to_col(self, line: &str, byte_col: usize) -> usize {
+        if self == Self::Utf8 || line.is_ascii() {
+            return byte_col.min(line.len());
+        }
+        let mut units = 0usize;
+        for (byte_idx, ch) in line.char_indices() {
+            if byte_idx >= byte_col {
+                break;
+            }
+            units += self.char_len(ch);
+        }
+        units
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PositionEncoding::{self, Utf16, Utf32, Utf8};
+    use tower_lsp_server::ls_types::PositionEncodingKind;
+
+    #[test]
+    fn test_negotiate() {
+        let negotiate = |kinds: &[PositionEncodingKind]| PositionEncoding::negotiate(Some(kinds));
+        assert_eq!(PositionEncoding::negotiate(None), Utf16);
+        assert_eq!(negotiate(&[]), Utf16);
+        assert_eq!(
+            negotiate(&[PositionEncodingKind::UTF16, PositionEncodingKind::UTF8]),
+            Utf8
+        );
+        assert_eq!(
+            negotiate(&[PositionEncodingKind::UTF32, PositionEncodingKind::UTF16]),
+            Utf32
+        );
+        assert_eq!(
+            negotiate(&[
+                PositionEncodingKind::new("utf-7"),
+                PositionEncodingKind::UTF32
+            ]),
+            Utf32
+        );
+        assert_eq!(negotiate(&[PositionEncodingKind::new("utf-7")]), Utf16);
+    }
+
+    #[test]
+    fn test_u8_round_trip() {
+        for encoding in [Utf8, Utf16, Utf32] {
+            assert_eq!(PositionEncoding::from_u8(encoding.to_u8()), encoding);
+        }
+    }
+
+    #[test]
+    fn test_conversion_ascii() {
+        let line = "def test(fixture):";
+        for encoding in [Utf8, Utf16, Utf32] {
+            assert_eq!(encoding.col_to_byte(line, 9), 9);
+            assert_eq!(encoding.byte_to_col(line, 9), 9);
+            // Clamps past end of line.
+            assert_eq!(encoding.col_to_byte(line, 100), line.len());
+            assert_eq!(encoding.byte_to_col(line, 100), line.len());
+        }
+    }
+
+    #[test]
+    fn test_conversion_bmp() {
+        // "é" is 2 bytes in UTF-8 but 1 UTF-16 code unit and 1 code point.
+        let line = "x = 'é'; fixture";
+        assert_eq!(Utf16.col_to_byte(line, 9), 10);
+        assert_eq!(Utf16.byte_to_col(line, 10), 9);
+        assert_eq!(Utf32.col_to_byte(line, 9), 10);
+        assert_eq!(Utf32.byte_to_col(line, 10), 9);
+        assert_eq!(Utf8.col_to_byte(line, 10), 10);
+        // A byte column inside "é" snaps to the next boundary.
+        assert_eq!(Utf8.col_to_byte(line, 6), 7);
+    }
+
+    #[test]
+    fn test_conversion_astral() {
+        // "🎉" is 4 bytes in UTF-8, 2 UTF-16 code units (surrogate pair) and
+        // 1 code point; it starts at byte 5 and ends at byte 9.
+        let line = "s = '🎉'; f";
+        assert_eq!(Utf16.col_to_byte(line, 7), 9);
+        assert_eq!(Utf16.byte_to_col(line, 9), 7);
+        assert_eq!(Utf32.col_to_byte(line, 6), 9);
+        assert_eq!(Utf32.byte_to_col(line, 9), 6);
+        // "f" is at byte 12, UTF-16 unit 10 and code point 9.
+        assert_eq!(Utf16.col_to_byte(line, 10), 12);
+        assert_eq!(Utf16.byte_to_col(line, 12), 10);
+        assert_eq!(Utf32.col_to_byte(line, 9), 12);
+        assert_eq!(Utf32.byte_to_col(line, 12), 9);
+        // A column inside the surrogate pair snaps to the next boundary.
+        assert_eq!(Utf16.col_to_byte(line, 6), 9);
+    }
+}
@@ -0,0 +1,174 @@
+//! Work-done progress (`$/progress`) for workspace scans.
+//!
+//! The scan runs on a blocking thread and records its progress in a
+//! [`ScanProgress`]; the reporter polls it and forwards it to clients that
+//! support work-done progress, so large repositories show
+//! "Indexing pytest fixtures: 1423/5000 files" instead of appearing hung.
+//! Cancelling the progress in the editor stops the scan.
+
+use super::Backend;
+use crate::fixtures::{ScanPhase, ScanProgress};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tower_lsp_server::ls_types::{NumberOrString, ProgressToken, WorkDoneProgressCancelParams};
+use tower_lsp_server::{Bounded, Cancellable, OngoingProgress};
+use tracing::info;
+
+/// Title of the scan's progress in the editor.
+const SCAN_PROGRESS_TITLE: &str = "Indexing pytest fixtures";
+
+/// How often the scan's progress is reported.
+pub(crate) const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Share of the progress bar taken by analyzing workspace files; the rest is
+/// the virtual environment's packages.
+const FILES_PERCENTAGE: usize = 80;
+
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(1);
+
+/// The message and percentage shown for a scan in `phase`.
+fn progress_message(phase: ScanPhase, done: usize, total: usize) -> (String, u32) {
+    let share = |weight: usize| (done * weight).checked_div(total).unwrap_or(weight);
+    let (message, percentage) = match phase {
+        ScanPhase::Discovering => ("Discovering test files".to_string(), 0),
+        ScanPhase::Files => (format!("{}/{} files", done, total), share(FILES_PERCENTAGE)),
+        ScanPhase::Packages => (
+            format!("{}/{} packages", done, total),
+            FILES_PERCENTAGE + share(100 - FILES_PERCENTAGE),
+        ),
+    };
+    (message, percentage as u32)
+}
+
+/// Reports a workspace scan's progress to the client.
+pub(crate) struct ScanReporter {
+    backend: Backend,
+    progress: Arc<ScanProgress>,
+    token: ProgressToken,
+    ongoing: Option<OngoingProgress<Bounded, Cancellable>>,
+    begun: bool,
+    last: (String, u32),
+}
+
+impl ScanReporter {
+    /// Track `progress` as the current scan, so cancelling its token stops it.
+    pub(crate) fn new(backend: &Backend, progress: Arc<ScanProgress>) -> Self {
+        let token = NumberOrString::String(format!(
+            "pytest-language-server/scan/{}",
+            NEXT_TOKEN.fetch_add(1, Ordering::Relaxed)
+        ));
+        *backend.scan_progress.lock().unwrap() = Some((token.clone(), Arc::clone(&progress)));
+        Self {
+            backend: backend.clone(),
+            progress,
+            token,
+            ongoing: None,
+            begun: false,
+            last: (String::new(), 0),
+        }
+    }
+
+    /// Send the scan's current progress. The progress begins once the client
+    /// is initialized, as it may not be yet when the initial scan starts.
+    pub(crate) async fn update(&mut self) {
+        if !self.begun {
+            if !self
+                .backend
+                .client_work_done_progress
+                .load(Ordering::Relaxed)
+                || !self.backend.client_initialized.load(Ordering::Relaxed)
+            {
+                return;
+            }
+            self.begun = true;
+            let client = &self.backend.client;
+            if let Err(e) = client.create_work_done_progress(self.token.clone()).await {
+                info!("Failed to create work done progress: {}", e);
+                return;
+            }
+            let (message, percentage) = self.current();
+            self.ongoing = Some(
+                client
+                    .progress(self.token.clone(), SCAN_PROGRESS_TITLE)
+                    .with_percentage(percentage)
+                    .with_cancel_button()
+                    .with_message(message.clone())
+                    .begin()
+                    .await,
+            );
+            self.last = (message, percentage);
+            return;
+        }
+
+        let Some(ongoing) = &self.ongoing else {
+            return;
+        };
+        let (message, percentage) = self.current();
+        if (&message, percentage) != (&self.last.0, self.last.1) {
+            ongoing
+                .report_with_message(message.clone(), percentage, None)
+                .await;
+            self.last = (message, percentage);
+        }
+    }
+
+    /// End the progress once the scan is over.
+    pub(crate) async fn finish(self) {
+        self.backend.scan_progress.lock().unwrap().take();
+        if let Some(ongoing) = self.ongoing {
+            let message = if self.progress.is_cancelled() {
+                "Cancelled"
+            } else {
+                "Done"
+            };
+            ongoing.finish_with_message(message).await;
+        }
+    }
+
+    /// The current message, with a percentage that never goes backwards (a
+    /// multi-root scan starts over for each folder).
+    fn current(&self) -> (String, u32) {
+        let (phase, done, total) = self.progress.snapshot();
+        let (message, percentage) = progress_message(phase, done, total);
+        (message, percentage.max(self.last.1))
+    }
+}
+
+impl Backend {
+    /// Handle `window/workDoneProgress/cancel`: stop the scan whose progress
+    /// the user cancelled.
+    pub async fn handle_work_done_progress_cancel(&self, params: WorkDoneProgressCancelParams) {
+        let scan_progress = self.scan_progress.lock().unwrap();
+        if let Some((token, progress)) = scan_progress.as_ref() {
+            if *token == params.token {
+                info!("Workspace scan cancelled by the client");
+                progress.cancel();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_message() {
+        assert_eq!(
+            progress_message(ScanPhase::Discovering, 0, 0),
+            ("Discovering test files".to_string(), 0)
+        );
+        assert_eq!(
+            progress_message(ScanPhase::Files, 1423, 5000),
+            ("1423/5000 files".to_string(), 22)
+        );
+        assert_eq!(
+            progress_message(ScanPhase::Packages, 5, 10),
+            ("5/10 packages".to_string(), 90)
+        );
+        // Empty phases count as complete
+        assert_eq!(progress_message(ScanPhase::Files, 0, 0).1, 80);
+        assert_eq!(progress_message(ScanPhase::Packages, 0, 0).1, 100);
+    }
+}
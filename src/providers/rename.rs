@@ -10,12 +10,9 @@
 
 use super::Backend;
 use crate::fixtures::{decorators, FixtureDatabase};
-use rustpython_parser::ast::{
-    Arguments, Expr, ExprDictComp, ExprGeneratorExp, ExprLambda, ExprListComp, ExprName,
-    ExprSetComp, Ranged, Stmt, StmtAsyncFunctionDef, StmtFunctionDef, Visitor,
-};
-use rustpython_parser::text_size::TextRange;
-use rustpython_parser::{parse, Mode};
+use ruff_python_ast::visitor::{self, Visitor};
+use ruff_python_ast::{Comprehension, Decorator, Expr, Parameters, Stmt};
+use ruff_text_size::{TextRange, TextSize};
 use std::collections::{HashMap, HashSet};
 use tower_lsp_server::jsonrpc::{Error, Result};
 use tower_lsp_server::ls_types::*;
@@ -38,21 +35,17 @@ struct RenameTarget {
 
 /// A function definition with the parts needed for parametrize rename, borrowed from the AST.
 struct FuncCtx<'a> {
-    decorators: &'a [Expr],
-    args: &'a Arguments,
+    decorators: &'a [Decorator],
+    args: &'a Parameters,
     body: &'a [Stmt],
     range: TextRange,
 }
 
 impl FuncCtx<'_> {
     /// Source span covering the decorators and the `def` body, used to locate the cursor.
-    /// (`FunctionDef.range` starts at `def`, so decorators must be folded in explicitly.)
+    /// (`FunctionDef.range` already starts at the first decorator.)
     fn bounds(&self) -> (usize, usize) {
-        let mut start = self.range.start().to_usize();
-        for dec in self.decorators {
-            start = start.min(dec.range().start().to_usize());
-        }
-        (start, self.range.end().to_usize())
+        (self.range.start().to_usize(), self.range.end().to_usize())
     }
 
     fn contains(&self, offset: usize) -> bool {
@@ -67,7 +60,7 @@ impl FuncCtx<'_> {
 }
 
 /// Collects the ranges of every `Name` expression that refers to a target parameter, walking the
-/// function body via the generated `Visitor`.
+/// function body via the AST `Visitor`.
 ///
 /// It is scope-aware: a nested function/lambda whose parameters shadow the target, and a
 /// comprehension whose loop target shadows it, bind a *different* variable, so their inner bodies
@@ -84,7 +77,7 @@ struct NameUsageCollector {
 
 impl NameUsageCollector {
     /// Visit parameter defaults and annotations, which are evaluated in the enclosing scope.
-    fn visit_arg_context(&mut self, args: &Arguments) {
+    fn visit_arg_context(&mut self, args: &Parameters) {
         for arg in args
             .posonlyargs
             .iter()
@@ -92,40 +85,31 @@ impl NameUsageCollector {
             .chain(&args.kwonlyargs)
         {
             if let Some(default) = &arg.default {
-                self.visit_expr((**default).clone());
-            }
-            if let Some(annotation) = &arg.def.annotation {
-                self.visit_expr((**annotation).clone());
+                self.visit_expr(default);
             }
-        }
-        if let Some(va) = &args.vararg {
-            if let Some(annotation) = &va.annotation {
-                self.visit_expr((**annotation).clone());
+            if let Some(annotation) = &arg.parameter.annotation {
+                self.visit_expr(annotation);
             }
         }
-        if let Some(kw) = &args.kwarg {
-            if let Some(annotation) = &kw.annotation {
-                self.visit_expr((**annotation).clone());
+        for variadic in [&args.vararg, &args.kwarg].into_iter().flatten() {
+            if let Some(annotation) = &variadic.annotation {
+                self.visit_expr(annotation);
             }
         }
     }
 
-    fn visit_comprehension(
-        &mut self,
-        elements: Vec<Expr>,
-        generators: Vec<rustpython_parser::ast::Comprehension>,
-    ) {
+    fn visit_comprehension<'a>(&mut self, elements: &[&'a Expr], generators: &'a [Comprehension]) {
         let shadows = generators
             .iter()
             .any(|g| expr_binds_name(&g.target, &self.target));
 
-        for (i, generator) in generators.into_iter().enumerate() {
+        for (i, generator) in generators.iter().enumerate() {
             // The first generator's iterable is evaluated in the enclosing scope.
             if i == 0 || !shadows {
-                self.visit_expr(generator.iter);
+                self.visit_expr(&generator.iter);
             }
             if !shadows {
-                for cond in generator.ifs {
+                for cond in &generator.ifs {
                     self.visit_expr(cond);
                 }
             }
@@ -138,82 +122,64 @@ impl NameUsageCollector {
     }
 }
 
-impl Visitor for NameUsageCollector {
-    fn visit_expr_name(&mut self, node: ExprName) {
-        if node.id.as_str() == self.target {
-            self.ranges.push(node.range);
-        }
-    }
-
-    fn visit_stmt_function_def(&mut self, node: StmtFunctionDef) {
-        for decorator in node.decorator_list {
-            self.visit_expr(decorator);
+impl<'a> Visitor<'a> for NameUsageCollector {
+    fn visit_stmt(&mut self, stmt: &'a Stmt) {
+        let Stmt::FunctionDef(node) = stmt else {
+            visitor::walk_stmt(self, stmt);
+            return;
+        };
+        for decorator in &node.decorator_list {
+            self.visit_expr(&decorator.expression);
         }
-        self.visit_arg_context(&node.args);
-        if let Some(returns) = node.returns {
-            self.visit_expr(*returns);
+        self.visit_arg_context(&node.parameters);
+        if let Some(returns) = &node.returns {
+            self.visit_expr(returns);
         }
-        if !args_bind(&node.args, &self.target) {
-            for stmt in node.body {
-                self.visit_stmt(stmt);
-            }
+        if !args_bind(&node.parameters, &self.target) {
+            self.visit_body(&node.body);
         }
     }
 
-    fn visit_stmt_async_function_def(&mut self, node: StmtAsyncFunctionDef) {
-        for decorator in node.decorator_list {
-            self.visit_expr(decorator);
-        }
-        self.visit_arg_context(&node.args);
-        if let Some(returns) = node.returns {
-            self.visit_expr(*returns);
-        }
-        if !args_bind(&node.args, &self.target) {
-            for stmt in node.body {
-                self.visit_stmt(stmt);
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        match expr {
+            Expr::Name(node) => {
+                if node.id.as_str() == self.target {
+                    self.ranges.push(node.range);
+                }
             }
+            Expr::Lambda(node) => {
+                if let Some(parameters) = &node.parameters {
+                    self.visit_arg_context(parameters);
+                    if args_bind(parameters, &self.target) {
+                        return;
+                    }
+                }
+                self.visit_expr(&node.body);
+            }
+            Expr::ListComp(node) => self.visit_comprehension(&[&node.elt], &node.generators),
+            Expr::SetComp(node) => self.visit_comprehension(&[&node.elt], &node.generators),
+            Expr::Generator(node) => self.visit_comprehension(&[&node.elt], &node.generators),
+            Expr::DictComp(node) => {
+                let mut elements: Vec<&Expr> = node.key.as_deref().into_iter().collect();
+                elements.push(&node.value);
+                self.visit_comprehension(&elements, &node.generators)
+            }
+            _ => visitor::walk_expr(self, expr),
         }
     }
-
-    fn visit_expr_lambda(&mut self, node: ExprLambda) {
-        self.visit_arg_context(&node.args);
-        if !args_bind(&node.args, &self.target) {
-            self.visit_expr(*node.body);
-        }
-    }
-
-    fn visit_expr_list_comp(&mut self, node: ExprListComp) {
-        self.visit_comprehension(vec![*node.elt], node.generators);
-    }
-
-    fn visit_expr_set_comp(&mut self, node: ExprSetComp) {
-        self.visit_comprehension(vec![*node.elt], node.generators);
-    }
-
-    fn visit_expr_generator_exp(&mut self, node: ExprGeneratorExp) {
-        self.visit_comprehension(vec![*node.elt], node.generators);
-    }
-
-    fn visit_expr_dict_comp(&mut self, node: ExprDictComp) {
-        self.visit_comprehension(vec![*node.key, *node.value], node.generators);
-    }
 }
 
 /// Whether any parameter of `args` is named `target`.
-fn args_bind(args: &Arguments, target: &str) -> bool {
+fn args_bind(args: &Parameters, target: &str) -> bool {
     args.posonlyargs
         .iter()
         .chain(&args.args)
         .chain(&args.kwonlyargs)
-        .any(|arg| arg.def.arg.as_str() == target)
-        || args
-            .vararg
-            .as_ref()
-            .is_some_and(|a| a.arg.as_str() == target)
-        || args
-            .kwarg
-            .as_ref()
-            .is_some_and(|a| a.arg.as_str() == target)
+        .any(|arg| arg.parameter.name.as_str() == target)
+        || [&args.vararg, &args.kwarg]
+            .into_iter()
+            .flatten()
+            .any(|a| a.name.as_str() == target)
 }
 
 /// Whether an assignment/comprehension target binds `name` (handles tuple/list/star unpacking).
@@ -298,25 +264,19 @@ impl Backend {
 
     /// Resolve the parametrize parameter at `position` and gather all of its occurrences.
     fn parametrize_rename_target(&self, content: &str, position: Position) -> Option<RenameTarget> {
-        let rustpython_parser::ast::Mod::Module(module) = parse(content, Mode::Module, "").ok()?
+        let ruff_python_ast::Mod::Module(module) = crate::fixtures::parse_module(content).ok()?
         else {
             return None;
         };
 
         let line_index = FixtureDatabase::build_line_index(content);
         let line_start = *line_index.get(position.line as usize)?;
-        let line_end = line_index
-            .get(position.line as usize + 1)
-            .copied()
-            .unwrap_or(content.len());
-        // Trim the line ending and clamp so an out-of-range column resolves to
+        // The line ending is trimmed, so an out-of-range column resolves to
         // the logical end of the line instead of landing on the newline.
-        let line = content[line_start..line_end].trim_end_matches(['\r', '\n']);
-        let cursor_byte_col = if self.client_utf16.load(std::sync::atomic::Ordering::Relaxed) {
-            super::utf16_col_to_byte(line, position.character as usize)
-        } else {
-            (position.character as usize).min(line.len())
-        };
+        let line = FixtureDatabase::line_text(content, &line_index, position.line as usize + 1)?;
+        let cursor_byte_col = self
+            .position_encoding()
+            .col_to_byte(line, position.character as usize);
         let cursor_offset = line_start + cursor_byte_col;
 
         // Innermost *parametrized* function whose decorators or body contain the cursor. Filtering
@@ -328,9 +288,9 @@ impl Backend {
             .into_iter()
             .filter(|f| f.contains(cursor_offset))
             .filter(|f| {
-                f.decorators
-                    .iter()
-                    .any(|d| !decorators::extract_parametrize_argnames(d, content).is_empty())
+                f.decorators.iter().any(|d| {
+                    !decorators::extract_parametrize_argnames(&d.expression, content).is_empty()
+                })
             })
             .min_by_key(FuncCtx::span)?;
 
@@ -338,6 +298,7 @@ impl Backend {
         // to a fixture, so a local-only rename would silently break the test).
         let mut name_to_decorator_ranges: HashMap<String, Vec<TextRange>> = HashMap::new();
         for dec in func.decorators {
+            let dec = &dec.expression;
             let argnames = decorators::extract_parametrize_argnames(dec, content);
             let names: Vec<String> = argnames.iter().map(|(name, _)| name.clone()).collect();
             let indirect = decorators::extract_parametrize_indirect_names(dec, &names);
@@ -357,7 +318,7 @@ impl Backend {
 
         // Signature parameter names, used to confirm the cursor sits on a real parameter.
         let signature_params: HashSet<&str> = FixtureDatabase::all_args(func.args)
-            .map(|arg| arg.def.arg.as_str())
+            .map(|arg| arg.parameter.name.as_str())
             .collect();
 
         // Determine the target name from whichever site the cursor is on.
@@ -381,13 +342,13 @@ impl Backend {
                 .flatten(),
         );
 
-        if let Some(arg) =
-            FixtureDatabase::all_args(func.args).find(|arg| arg.def.arg.as_str() == target_name)
+        if let Some(arg) = FixtureDatabase::all_args(func.args)
+            .find(|arg| arg.parameter.name.as_str() == target_name)
         {
-            let start = arg.def.range.start();
+            let start = arg.parameter.range.start();
             occurrences.push(TextRange::new(
                 start,
-                start + rustpython_parser::text_size::TextSize::from(target_name.len() as u32),
+                start + TextSize::from(target_name.len() as u32),
             ));
         }
 
@@ -395,9 +356,7 @@ impl Backend {
             target: target_name.clone(),
             ranges: Vec::new(),
         };
-        for stmt in func.body {
-            collector.visit_stmt(stmt.clone());
-        }
+        collector.visit_body(func.body);
         occurrences.extend(collector.ranges);
 
         occurrences.sort_by_key(|r| (r.start().to_usize(), r.end().to_usize()));
@@ -418,19 +377,14 @@ impl Backend {
 
     /// Convert a source [`TextRange`] into an LSP [`Range`] using the file's line index.
     fn text_range_to_lsp(&self, tr: &TextRange, content: &str, line_index: &[usize]) -> Range {
-        let utf16 = self.client_utf16.load(std::sync::atomic::Ordering::Relaxed);
+        let encoding = self.position_encoding();
         let to_position = |offset: usize| {
             let line = self.fixture_db.get_line_from_offset(offset, line_index);
             let byte_col = self
                 .fixture_db
                 .get_char_position_from_offset(offset, line_index);
-            let character = if utf16 {
-                let line_start = line_index[line - 1];
-                let line_end = line_index.get(line).copied().unwrap_or(content.len());
-                super::byte_col_to_utf16(&content[line_start..line_end], byte_col) as u32
-            } else {
-                byte_col as u32
-            };
+            let line_text = FixtureDatabase::line_text(content, line_index, line).unwrap_or("");
+            let character = encoding.byte_to_col(line_text, byte_col) as u32;
             Position {
                 line: (line - 1) as u32,
                 character,
@@ -491,16 +445,7 @@ fn collect_functions<'a>(stmts: &'a [Stmt], out: &mut Vec<FuncCtx<'a>>) {
             Stmt::FunctionDef(f) => {
                 out.push(FuncCtx {
                     decorators: &f.decorator_list,
-                    args: &f.args,
-                    body: &f.body,
-                    range: f.range,
-                });
-                collect_functions(&f.body, out);
-            }
-            Stmt::AsyncFunctionDef(f) => {
-                out.push(FuncCtx {
-                    decorators: &f.decorator_list,
-                    args: &f.args,
+                    args: &f.parameters,
                     body: &f.body,
                     range: f.range,
                 });
@@ -0,0 +1,319 @@
+//! Signature help provider for pytest decorators.
+//!
+//! Shows the signature of `@pytest.fixture(...)` and
+//! `@pytest.mark.parametrize(...)` while their arguments are being typed,
+//! tracking the active parameter by position or keyword.
+//!
+//! Works on the source text rather than the AST, since the call being edited
+//! is usually incomplete and doesn't parse.
+
+use super::Backend;
+use tower_lsp_server::jsonrpc::Result;
+use tower_lsp_server::ls_types::*;
+use tracing::info;
+
+/// A parameter of one of the supported signatures.
+struct ParamSpec {
+    name: &'static str,
+    /// Text shown in the signature label (`scope="function"`).
+    label: &'static str,
+    doc: &'static str,
+}
+
+/// A supported decorator signature.
+struct SignatureSpec {
+    name: &'static str,
+    doc: &'static str,
+    params: &'static [ParamSpec],
+    /// Whether arguments can be passed positionally. `pytest.fixture` takes
+    /// keyword-only arguments when used as a decorator factory.
+    positional: bool,
+}
+
+const FIXTURE_SIGNATURE: SignatureSpec = SignatureSpec {
+    name: "fixture",
+    doc: "Decorator to mark a fixture factory function.",
+    params: &[
+        ParamSpec {
+            name: "scope",
+            label: "scope=\"function\"",
+            doc: "How long the fixture value is shared: \"function\" (default), \"class\", \"module\", \"package\" or \"session\".",
+        },
+        ParamSpec {
+            name: "params",
+            label: "params=None",
+            doc: "Values the fixture is parametrized with; each one is available as `request.param` and causes the dependent tests to run once per value.",
+        },
+        ParamSpec {
+            name: "autouse",
+            label: "autouse=False",
+            doc: "If True, the fixture is activated for all tests that can see it, without being requested.",
+        },
+        ParamSpec {
+            name: "ids",
+            label: "ids=None",
+            doc: "IDs for each of `params`, used in test names; either a list of strings or a function computing the ID from a param.",
+        },
+        ParamSpec {
+            name: "name",
+            label: "name=None",
+            doc: "Name the fixture is requested by. Defaults to the decorated function's name.",
+        },
+    ],
+    positional: false,
+};
+
+const PARAMETRIZE_SIGNATURE: SignatureSpec = SignatureSpec {
+    name: "parametrize",
+    doc: "Add new invocations to the test function, one per set of argument values.",
+    params: &[
+        ParamSpec {
+            name: "argnames",
+            label: "argnames",
+            doc: "Comma-separated string (or list/tuple of strings) with the argument names.",
+        },
+        ParamSpec {
+            name: "argvalues",
+            label: "argvalues",
+            doc: "List of values: single values for one argname, tuples of values for several. `pytest.param(...)` sets per-case ids and marks.",
+        },
+        ParamSpec {
+            name: "indirect",
+            label: "indirect=False",
+            doc: "True, or a list of argnames, to pass the values to the fixtures of the same name as `request.param` instead of to the test.",
+        },
+        ParamSpec {
+            name: "ids",
+            label: "ids=None",
+            doc: "IDs for each set of values, used in test names; either a list of strings or a function computing the ID from a value.",
+        },
+        ParamSpec {
+            name: "scope",
+            label: "scope=None",
+            doc: "Scope of the parameter sets, used to group tests by parameter instances.",
+        },
+    ],
+    positional: true,
+};
+
+/// An unclosed bracket found while scanning up to the cursor.
+struct OpenBracket {
+    /// Byte offset of the bracket.
+    offset: usize,
+    bracket: u8,
+    /// Index of the argument the scan is in (for `(` brackets).
+    arg_index: usize,
+    /// Byte offset where the current argument starts.
+    arg_start: usize,
+    /// Keywords of the arguments before the current one.
+    keywords: Vec<String>,
+}
+
+/// The supported call enclosing the cursor and the argument being typed.
+struct CallContext {
+    signature: &'static SignatureSpec,
+    arg_index: usize,
+    /// Keyword of the current argument, once `name=` has been typed.
+    keyword: Option<String>,
+    keywords: Vec<String>,
+}
+
+impl Backend {
+    /// Handle signatureHelp request.
+    pub async fn handle_signature_help(
+        &self,
+        params: SignatureHelpParams,
+    ) -> Result<Option<SignatureHelp>> {
+        let uri = params.text_document_position_params.text_document.uri;
+        let position = params.text_document_position_params.position;
+
+        info!(
+            "signatureHelp request: uri={:?}, line={}, char={}",
+            uri, position.line, position.character
+        );
+
+        let Some(file_path) = self.uri_to_path(&uri) else {
+            return Ok(None);
+        };
+        let Some(content) = self.fixture_db.get_file_content(&file_path) else {
+            return Ok(None);
+        };
+
+        let byte_col = self.to_byte_col(&file_path, position);
+        let line_index = self.fixture_db.get_line_index_for(&file_path, &content);
+        let Some(line_start) = line_index.get(position.line as usize) else {
+            return Ok(None);
+        };
+        let offset = line_start + byte_col as usize;
+        if offset > content.len() {
+            return Ok(None);
+        }
+
+        let Some(call) = find_call_context(&content, offset) else {
+            return Ok(None);
+        };
+        Ok(Some(signature_help(&call)))
+    }
+}
+
+fn signature_help(call: &CallContext) -> SignatureHelp {
+    let spec = call.signature;
+    let prefix = format!("{}(", spec.name);
+    let star = if spec.positional { "" } else { "*, " };
+    let label = format!(
+        "{}{}{})",
+        prefix,
+        star,
+        spec.params
+            .iter()
+            .map(|p| p.label)
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+
+    // Parameter labels as offsets into the signature label (UTF-16, which is
+    // also the byte length here since the labels are ASCII)
+    let mut start = (prefix.len() + star.len()) as u32;
+    let parameters = spec
+        .params
+        .iter()
+        .map(|p| {
+            let end = start + p.label.len() as u32;
+            let info = ParameterInformation {
+                label: ParameterLabel::LabelOffsets([start, end]),
+                documentation: Some(Documentation::String(p.doc.to_string())),
+            };
+            start = end + 2;
+            info
+        })
+        .collect();
+
+    let active = match &call.keyword {
+        Some(keyword) => spec.params.iter().position(|p| p.name == keyword),
+        None if spec.positional => (call.arg_index < spec.params.len()).then_some(call.arg_index),
+        // Keyword-only: suggest the first parameter not passed yet
+        None => spec
+            .params
+            .iter()
+            .position(|p| !call.keywords.iter().any(|k| k == p.name)),
+    }
+    .map(|idx| idx as u32);
+
+    SignatureHelp {
+        signatures: vec![SignatureInformation {
+            label,
+            documentation: Some(Documentation::String(spec.doc.to_string())),
+            parameters: Some(parameters),
+            active_parameter: active,
+        }],
+        active_signature: Some(0),
+        active_parameter: active,
+    }
+}
+
+/// Find the innermost call enclosing `offset` and, if it is `pytest.fixture(...)`
+/// or `pytest.mark.parametrize(...)`, which argument the cursor is in.
+fn find_call_context(content: &str, offset: usize) -> Option<CallContext> {
+    let bytes = content.as_bytes();
+    let mut stack: Vec<OpenBracket> = Vec::new();
+    let mut i = 0;
+
+    while i < offset {
+        match bytes[i] {
+            b'#' => {
+                while i < offset && bytes[i] != b'\n' {
+                    i += 1;
+                }
+                continue;
+            }
+            quote @ (b'"' | b'\'') => {
+                let triple = bytes.get(i + 1) == Some(&quote) && bytes.get(i + 2) == Some(&quote);
+                let quote_len = if triple { 3 } else { 1 };
+                i += quote_len;
+                while i < offset {
+                    if bytes[i] == b'\\' {
+                        i += 2;
+                        continue;
+                    }
+                    if bytes[i] == quote
+                        && (!triple
+                            || (bytes.get(i + 1) == Some(&quote)
+                                && bytes.get(i + 2) == Some(&quote)))
+                    {
+                        break;
+                    }
+                    if !triple && bytes[i] == b'\n' {
+                        break;
+                    }
+                    i += 1;
+                }
+                i += quote_len;
+                continue;
+            }
+            bracket @ (b'(' | b'[' | b'{') => stack.push(OpenBracket {
+                offset: i,
+                bracket,
+                arg_index: 0,
+                arg_start: i + 1,
+                keywords: Vec::new(),
+            }),
+            b')' | b']' | b'}' => {
+                stack.pop();
+            }
+            b',' => {
+                if let Some(top) = stack.last_mut() {
+                    if let Some(keyword) = argument_keyword(&content[top.arg_start..i]) {
+                        top.keywords.push(keyword.to_string());
+                    }
+                    top.arg_index += 1;
+                    top.arg_start = i + 1;
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    // Lists, dicts and tuples inside the arguments still belong to the call:
+    // the innermost `(` preceded by a name is the call being edited
+    let (call, callee) = stack.iter().rev().find_map(|b| {
+        let callee = callee(&content[..b.offset]);
+        (b.bracket == b'(' && callee.iter().any(|part| !part.is_empty())).then_some((b, callee))
+    })?;
+    let signature = match callee.as_slice() {
+        [.., "fixture"] => &FIXTURE_SIGNATURE,
+        [.., "mark", "parametrize"] => &PARAMETRIZE_SIGNATURE,
+        _ => return None,
+    };
+    let keyword = argument_keyword(content.get(call.arg_start..offset.min(content.len()))?)
+        .map(str::to_string);
+
+    Some(CallContext {
+        signature,
+        arg_index: call.arg_index,
+        keyword,
+        keywords: call.keywords.clone(),
+    })
+}
+
+/// Dotted name right before a call's opening parenthesis, split on dots.
+fn callee(before_paren: &str) -> Vec<&str> {
+    let trimmed = before_paren.trim_end();
+    let start = trimmed
+        .char_indices()
+        .rev()
+        .find(|&(_, c)| !(c.is_alphanumeric() || c == '_' || c == '.'))
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    trimmed[start..].split('.').collect()
+}
+
+/// Keyword of an argument's text (`scope="session"` → `scope`).
+fn argument_keyword(arg: &str) -> Option<&str> {
+    let arg = arg.trim_start();
+    let end = arg
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(arg.len());
+    let (name, rest) = arg.split_at(end);
+    let rest = rest.trim_start();
+    (!name.is_empty() && rest.starts_with('=') && !rest.starts_with("==")).then_some(name)
+}
@@ -0,0 +1,191 @@
+//! Test discovery extension request.
+//!
+//! `pytest/discoverTests` returns the test tree as the analyzer sees it — files,
+//! test classes, test functions and parametrized cases — with pytest node IDs
+//! and source ranges, so editor plugins can build test explorers without
+//! running `pytest --collect-only`.
+//!
+//! Params are `{ "textDocument"?: { "uri": ... } }`: with a document only that
+//! file is returned, otherwise every test file in the workspace.
+
+use super::Backend;
+use crate::fixtures::{TestItem, TestItemKind};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tower_lsp_server::jsonrpc::Result;
+use tower_lsp_server::ls_types::*;
+use tracing::info;
+
+/// Method name of the test discovery request.
+pub const DISCOVER_TESTS_METHOD: &str = "pytest/discoverTests";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoverTestsParams {
+    /// Restrict discovery to this file.
+    #[serde(default)]
+    pub text_document: Option<TextDocumentIdentifier>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TestNodeKind {
+    File,
+    Class,
+    Function,
+    Case,
+}
+
+/// A node of the discovered test tree.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestNode {
+    /// pytest node ID (`tests/test_x.py::TestA::test_b[1]`).
+    pub id: String,
+    pub name: String,
+    pub kind: TestNodeKind,
+    pub uri: Uri,
+    /// Range of the definition, decorators included. Absent for files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<Range>,
+    /// Range of the class or function name.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selection_range: Option<Range>,
+    #[serde(default)]
+    pub children: Vec<TestNode>,
+}
+
+impl Backend {
+    /// Handle the `pytest/discoverTests` request.
+    pub async fn handle_discover_tests(
+        &self,
+        params: DiscoverTestsParams,
+    ) -> Result<Vec<TestNode>> {
+        info!("discoverTests request: {:?}", params.text_document);
+
+        let workspace_root = self.workspace_root.read().await.clone();
+
+        let mut files: Vec<PathBuf> = match params.text_document {
+            Some(doc) => self.uri_to_path(&doc.uri).into_iter().collect(),
+            // Every analyzed file, including those whose contents were
+            // evicted from the file cache
+            None => self
+                .fixture_db
+                .file_cache
+                .iter()
+                .map(|entry| entry.key().clone())
+                .chain(
+                    self.fixture_db
+                        .imports
+                        .iter()
+                        .map(|entry| entry.key().clone()),
+                )
+                .filter(|path| {
+                    path.file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(|name| self.fixture_db.is_test_file_name(name))
+                })
+                .filter(|path| {
+                    workspace_root
+                        .as_ref()
+                        .is_none_or(|root| path.starts_with(root))
+                })
+                .collect(),
+        };
+        files.sort();
+        files.dedup();
+
+        let mut nodes: Vec<TestNode> = Vec::new();
+        for path in &files {
+            let rootdir = self.pytest_rootdir_for(path).await;
+            nodes.extend(self.file_test_node(path, rootdir.as_ref()));
+        }
+
+        info!("Discovered tests in {} files", nodes.len());
+        Ok(nodes)
+    }
+
+    /// Build the node for a file, or `None` if it holds no tests.
+    fn file_test_node(&self, file_path: &Path, rootdir: Option<&PathBuf>) -> Option<TestNode> {
+        let items = self.fixture_db.get_test_items(file_path);
+        if items.is_empty() {
+            return None;
+        }
+        let uri = self.path_to_uri(file_path)?;
+        let id = Self::pytest_node_id(file_path, rootdir, &[]);
+        let children = items
+            .iter()
+            .map(|item| self.test_item_node(file_path, &uri, &id, item))
+            .collect();
+
+        Some(TestNode {
+            name: file_path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_else(|| id.clone()),
+            id,
+            kind: TestNodeKind::File,
+            uri,
+            range: None,
+            selection_range: None,
+            children,
+        })
+    }
+
+    fn test_item_node(
+        &self,
+        file_path: &Path,
+        uri: &Uri,
+        parent_id: &str,
+        item: &TestItem,
+    ) -> TestNode {
+        let id = format!("{}::{}", parent_id, item.name);
+        let line = Self::internal_line_to_lsp(item.line);
+        let range = Self::create_range(
+            Self::internal_line_to_lsp(item.start_line),
+            0,
+            Self::internal_line_to_lsp(item.end_line),
+            0,
+        );
+        let selection_range = Self::create_range(
+            line,
+            self.to_lsp_col(file_path, item.line, item.start_char),
+            line,
+            self.to_lsp_col(file_path, item.line, item.end_char),
+        );
+
+        let children = match item.kind {
+            TestItemKind::Class => item
+                .children
+                .iter()
+                .map(|child| self.test_item_node(file_path, uri, &id, child))
+                .collect(),
+            TestItemKind::Function => item
+                .cases
+                .iter()
+                .map(|case| TestNode {
+                    id: format!("{}[{}]", id, case),
+                    name: format!("{}[{}]", item.name, case),
+                    kind: TestNodeKind::Case,
+                    uri: uri.clone(),
+                    range: Some(range),
+                    selection_range: Some(selection_range),
+                    children: Vec::new(),
+                })
+                .collect(),
+        };
+
+        TestNode {
+            id,
+            name: item.name.clone(),
+            kind: match item.kind {
+                TestItemKind::Class => TestNodeKind::Class,
+                TestItemKind::Function => TestNodeKind::Function,
+            },
+            uri: uri.clone(),
+            range: Some(range),
+            selection_range: Some(selection_range),
+            children,
+        }
+    }
+}
@@ -0,0 +1,204 @@
+//! `$/setTrace` and `$/logTrace`: let the editor trace what the server does.
+//!
+//! The client picks the trace level in `initialize` and changes it with
+//! `$/setTrace`. [`TraceService`] wraps the server and, unless the level is
+//! `off`, sends a `$/logTrace` for each request and notification it handles:
+//! a one-line summary at `messages`, with the time taken, the parameters and
+//! the result at `verbose`.
+
+use super::Backend;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tower_lsp_server::jsonrpc::{Request, Response};
+use tower_lsp_server::ls_types::notification::LogTrace;
+use tower_lsp_server::ls_types::{LogTraceParams, SetTraceParams, TraceValue};
+use tower_lsp_server::{Client, ExitedError, LspService};
+use tower_service::Service;
+use tracing::info;
+
+pub(crate) fn trace_to_u8(value: TraceValue) -> u8 {
+    match value {
+        TraceValue::Off => 0,
+        TraceValue::Messages => 1,
+        TraceValue::Verbose => 2,
+    }
+}
+
+fn trace_from_u8(value: u8) -> TraceValue {
+    match value {
+        1 => TraceValue::Messages,
+        2 => TraceValue::Verbose,
+        _ => TraceValue::Off,
+    }
+}
+
+impl Backend {
+    /// The trace level asked for by the client.
+    pub fn trace_value(&self) -> TraceValue {
+        trace_from_u8(self.trace.load(Ordering::Relaxed))
+    }
+
+    /// Set the trace level.
+    pub fn set_trace_value(&self, value: TraceValue) {
+        self.trace.store(trace_to_u8(value), Ordering::Relaxed);
+    }
+
+    /// Handle `$/setTrace`.
+    pub async fn handle_set_trace(&self, params: SetTraceParams) {
+        info!("Trace level set to {:?}", params.value);
+        self.set_trace_value(params.value);
+    }
+
+    /// Send `message` as a `$/logTrace` unless tracing is off; `verbose` is
+    /// only sent at the `verbose` level.
+    pub(crate) async fn log_trace(&self, message: impl Into<String>, verbose: Option<String>) {
+        send_trace(&self.client, self.trace_value(), message.into(), verbose).await;
+    }
+}
+
+async fn send_trace(client: &Client, level: TraceValue, message: String, verbose: Option<String>) {
+    let verbose = match level {
+        TraceValue::Off => return,
+        TraceValue::Messages => None,
+        TraceValue::Verbose => verbose,
+    };
+    client
+        .send_notification::<LogTrace>(LogTraceParams { message, verbose })
+        .await;
+}
+
+/// The `$/logTrace` message and verbose details for a handled message.
+fn trace_message(
+    request: &Request,
+    response: Option<&Response>,
+    elapsed: Duration,
+    level: TraceValue,
+) -> (String, Option<String>) {
+    let name = match request.id() {
+        Some(id) => format!("request '{} - ({})'", request.method(), id),
+        None => format!("notification '{}'", request.method()),
+    };
+    let error = response.and_then(|response| response.error());
+    let mut message = match error {
+        Some(_) => format!("Failed {}", name),
+        None => format!("Handled {}", name),
+    };
+    if level == TraceValue::Verbose {
+        message.push_str(&format!(" in {}ms", elapsed.as_millis()));
+    }
+    if let Some(error) = error {
+        message.push_str(&format!(": {}", error.message));
+    }
+    message.push('.');
+
+    let mut verbose = format!(
+        "Params: {}",
+        request
+            .params()
+            .map(|params| params.to_string())
+            .unwrap_or_else(|| "none".to_string())
+    );
+    if let Some(result) = response.and_then(|response| response.result()) {
+        verbose.push_str(&format!("\n\nResult: {}", result));
+    }
+    (message, Some(verbose))
+}
+
+/// The language server, sending a `$/logTrace` for each message it handles
+/// at the client's trace level.
+pub struct TraceService {
+    inner: LspService<Backend>,
+    client: Client,
+    trace: Arc<AtomicU8>,
+}
+
+impl TraceService {
+    pub fn new(inner: LspService<Backend>) -> Self {
+        let client = inner.inner().client.clone();
+        let trace = Arc::clone(&inner.inner().trace);
+        Self {
+            inner,
+            client,
+            trace,
+        }
+    }
+}
+
+impl Service<Request> for TraceService {
+    type Response = Option<Response>;
+    type Error = ExitedError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        // The level is read again once the message is handled, as
+        // `$/setTrace` changes it
+        let traced = (self.trace.load(Ordering::Relaxed) != 0).then(|| request.clone());
+        let start = Instant::now();
+        let future = self.inner.call(request);
+        let client = self.client.clone();
+        let trace = Arc::clone(&self.trace);
+        Box::pin(async move {
+            let response = future.await?;
+            let level = trace_from_u8(trace.load(Ordering::Relaxed));
+            if let Some(request) = traced {
+                let (message, verbose) =
+                    trace_message(&request, response.as_ref(), start.elapsed(), level);
+                send_trace(&client, level, message, verbose).await;
+            }
+            Ok(response)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tower_lsp_server::jsonrpc::{Error, Id};
+
+    #[test]
+    fn test_trace_message() {
+        let request = Request::build("textDocument/hover")
+            .id(3)
+            .params(serde_json::json!({"position": {"line": 1, "character": 4}}))
+            .finish();
+        let response = Response::from_ok(Id::Number(3), serde_json::json!({"contents": "db"}));
+        let elapsed = Duration::from_millis(12);
+
+        let (message, _) = trace_message(&request, Some(&response), elapsed, TraceValue::Messages);
+        assert_eq!(message, "Handled request 'textDocument/hover - (3)'.");
+
+        let (message, verbose) =
+            trace_message(&request, Some(&response), elapsed, TraceValue::Verbose);
+        assert_eq!(
+            message,
+            "Handled request 'textDocument/hover - (3)' in 12ms."
+        );
+        assert_eq!(
+            verbose.unwrap(),
+            "Params: {\"position\":{\"character\":4,\"line\":1}}\n\nResult: {\"contents\":\"db\"}"
+        );
+
+        let response = Response::from_error(Id::Number(3), Error::invalid_params("no file"));
+        let (message, _) = trace_message(&request, Some(&response), elapsed, TraceValue::Messages);
+        assert_eq!(
+            message,
+            "Failed request 'textDocument/hover - (3)': no file."
+        );
+
+        let notification = Request::build("textDocument/didSave").finish();
+        let (message, verbose) = trace_message(&notification, None, elapsed, TraceValue::Verbose);
+        assert_eq!(
+            message,
+            "Handled notification 'textDocument/didSave' in 12ms."
+        );
+        assert_eq!(verbose.unwrap(), "Params: none");
+    }
+}
@@ -1,7 +1,9 @@
 //! Workspace symbols provider for pytest fixtures.
 //!
 //! Provides the workspace/symbol LSP feature, enabling fuzzy search for
-//! fixtures across the entire workspace.
+//! fixtures across the entire workspace. Each symbol's container is the
+//! workspace-relative path of the defining file followed by the fixture scope,
+//! so same-named fixtures from different conftest files can be told apart.
 
 use super::Backend;
 use tower_lsp_server::jsonrpc::Result;
@@ -11,7 +13,8 @@ use tracing::info;
 impl Backend {
     /// Handle workspace/symbol request.
     ///
-    /// Returns all fixture definitions matching the query string.
+    /// Returns all fixture definitions whose name fuzzily matches the query
+    /// (its characters appear in order, case-insensitively).
     /// This enables "Go to Symbol in Workspace" (Cmd+T / Ctrl+T) in editors.
     #[allow(deprecated)] // SymbolInformation::deprecated is required by LSP spec
     pub async fn handle_workspace_symbol(
@@ -22,6 +25,7 @@ impl Backend {
 
         info!("workspace_symbol request: query={:?}", query);
 
+        let workspace_root = self.workspace_root.read().await.clone();
         let mut symbols: Vec<SymbolInformation> = Vec::new();
 
         // Iterate over all fixture definitions
@@ -32,8 +36,7 @@ impl Backend {
                     continue;
                 }
 
-                // If query is empty, return all fixtures; otherwise filter by name
-                if !query.is_empty() && !definition.name.to_lowercase().contains(&query) {
+                if !fuzzy_match(&definition.name.to_lowercase(), &query) {
                     continue;
                 }
 
@@ -61,19 +64,30 @@ impl Backend {
                     tags: None,
                     deprecated: None,
                     location,
-                    container_name: definition
-                        .file_path
-                        .file_name()
-                        .and_then(|f| f.to_str())
-                        .map(|s| s.to_string()),
+                    container_name: Some(format!(
+                        "{} ({})",
+                        Self::display_path(&definition.file_path, workspace_root.as_ref()),
+                        definition.scope.as_str()
+                    )),
                 };
 
                 symbols.push(symbol);
             }
         }
 
-        // Sort by name for consistent ordering
-        symbols.sort_by(|a, b| a.name.cmp(&b.name));
+        // Sort by name, then location, for consistent ordering
+        symbols.sort_by(|a, b| {
+            a.name
+                .cmp(&b.name)
+                .then_with(|| a.location.uri.as_str().cmp(b.location.uri.as_str()))
+                .then_with(|| {
+                    a.location
+                        .range
+                        .start
+                        .line
+                        .cmp(&b.location.range.start.line)
+                })
+        });
 
         info!("Returning {} workspace symbols", symbols.len());
 
@@ -84,3 +98,10 @@ impl Backend {
         }
     }
 }
+
+/// Whether every character of `query` appears in `name`, in order.
+/// An empty query matches everything.
+fn fuzzy_match(name: &str, query: &str) -> bool {
+    let mut name_chars = name.chars();
+    query.chars().all(|q| name_chars.any(|c| c == q))
+}
@@ -1,5 +1,6 @@
 //! Integration tests for configuration file support.
 
+use pytest_language_server::config::RuleSeverity;
 use pytest_language_server::{Config, FixtureDatabase};
 use std::fs;
 use tempfile::TempDir;
@@ -132,7 +133,7 @@ def test_excluded(excluded_fixture):
     // Load config and scan with excludes
     let config = Config::load(temp_dir.path());
     let db = FixtureDatabase::new();
-    db.scan_workspace_with_excludes(temp_dir.path(), &config.exclude);
+    db.scan_workspace_with_excludes(temp_dir.path(), &config.exclude, true);
 
     // Verify included fixture is found
     assert!(
@@ -203,7 +204,7 @@ def normal_fixture():
 
     let config = Config::load(temp_dir.path());
     let db = FixtureDatabase::new();
-    db.scan_workspace_with_excludes(temp_dir.path(), &config.exclude);
+    db.scan_workspace_with_excludes(temp_dir.path(), &config.exclude, true);
 
     // normal_fixture should be found
     assert!(
@@ -222,6 +223,58 @@ def normal_fixture():
     );
 }
 
+#[test]
+fn test_gitignore_and_excluded_directories_skipped() {
+    let temp_dir = TempDir::new().unwrap();
+
+    fs::write(
+        temp_dir.path().join("pyproject.toml"),
+        r#"
+[tool.pytest-language-server]
+exclude = ["legacy"]
+"#,
+    )
+    .unwrap();
+    fs::write(temp_dir.path().join(".gitignore"), "generated/\n").unwrap();
+
+    for (dir, fixture) in [
+        ("tests", "normal_fixture"),
+        ("generated", "generated_fixture"),
+        ("legacy/nested", "legacy_fixture"),
+    ] {
+        fs::create_dir_all(temp_dir.path().join(dir)).unwrap();
+        create_test_file(
+            &temp_dir.path().join(dir),
+            "conftest.py",
+            &format!(
+                "import pytest\n\n@pytest.fixture\ndef {}():\n    pass\n",
+                fixture
+            ),
+        );
+    }
+
+    let config = Config::load(temp_dir.path());
+    assert!(config.respect_gitignore);
+    let db = FixtureDatabase::new();
+    db.scan_workspace_with_excludes(temp_dir.path(), &config.exclude, true);
+
+    assert!(db.definitions.contains_key("normal_fixture"));
+    assert!(
+        !db.definitions.contains_key("generated_fixture"),
+        "gitignored directories should be skipped"
+    );
+    assert!(
+        !db.definitions.contains_key("legacy_fixture"),
+        "files below an excluded directory should be skipped"
+    );
+
+    // Without respect_gitignore only the exclude patterns apply
+    let db = FixtureDatabase::new();
+    db.scan_workspace_with_excludes(temp_dir.path(), &config.exclude, false);
+    assert!(db.definitions.contains_key("generated_fixture"));
+    assert!(!db.definitions.contains_key("legacy_fixture"));
+}
+
 // ============ Disabled Diagnostics Tests ============
 
 #[test]
@@ -256,6 +309,33 @@ disabled_diagnostics = ["undeclared-fixture", "scope-mismatch", "circular-depend
     assert!(config.is_diagnostic_disabled("circular-dependency"));
 }
 
+// ============ Rule Severity Tests ============
+
+#[test]
+fn test_severity_overrides_from_pyproject() {
+    let temp_dir = create_temp_project(
+        r#"
+[tool.pytest-language-server]
+disabled_diagnostics = ["PTL003"]
+
+[tool.pytest-language-server.severity]
+PTL001 = "information"
+scope-mismatch = "off"
+"#,
+    );
+
+    let config = Config::load(temp_dir.path());
+
+    assert_eq!(
+        config.rule_severity("undeclared-fixture"),
+        Some(RuleSeverity::Information)
+    );
+    assert!(!config.is_diagnostic_disabled("undeclared-fixture"));
+    assert!(config.is_diagnostic_disabled("scope-mismatch"));
+    assert!(config.is_diagnostic_disabled("circular-dependency"));
+    assert!(!config.is_diagnostic_disabled("invalid-indirect"));
+}
+
 // ============ Skip Plugins Tests ============
 
 #[test]
@@ -4,18 +4,26 @@
 
 use ntest::timeout;
 use pytest_language_server::fixtures::decorators;
-use rustpython_parser::{parse, Mode};
+
+fn parse(code: &str) -> ruff_python_ast::Mod {
+    ruff_python_ast::Mod::Module(
+        ruff_python_parser::parse_module(code)
+            .unwrap()
+            .into_syntax(),
+    )
+}
 
 #[test]
 #[timeout(30000)]
 fn test_is_fixture_decorator_simple() {
     let code = "@fixture\ndef my_fixture(): pass";
-    let parsed = parse(code, Mode::Module, "").unwrap();
+    let parsed = parse(code);
 
-    if let rustpython_parser::ast::Mod::Module(module) = parsed {
-        if let rustpython_parser::ast::Stmt::FunctionDef(func_def) = &module.body[0] {
+    if let ruff_python_ast::Mod::Module(module) = parsed {
+        if let ruff_python_ast::Stmt::FunctionDef(func_def) = &module.body[0] {
             assert!(decorators::is_fixture_decorator(
-                &func_def.decorator_list[0]
+                &func_def.decorator_list[0].expression,
+                &[]
             ));
         }
     }
@@ -25,12 +33,13 @@ fn test_is_fixture_decorator_simple() {
 #[timeout(30000)]
 fn test_is_fixture_decorator_pytest_dot() {
     let code = "@pytest.fixture\ndef my_fixture(): pass";
-    let parsed = parse(code, Mode::Module, "").unwrap();
+    let parsed = parse(code);
 
-    if let rustpython_parser::ast::Mod::Module(module) = parsed {
-        if let rustpython_parser::ast::Stmt::FunctionDef(func_def) = &module.body[0] {
+    if let ruff_python_ast::Mod::Module(module) = parsed {
+        if let ruff_python_ast::Stmt::FunctionDef(func_def) = &module.body[0] {
             assert!(decorators::is_fixture_decorator(
-                &func_def.decorator_list[0]
+                &func_def.decorator_list[0].expression,
+                &[]
             ));
         }
     }
@@ -40,28 +49,72 @@ fn test_is_fixture_decorator_pytest_dot() {
 #[timeout(30000)]
 fn test_is_fixture_decorator_with_args() {
     let code = "@pytest.fixture(scope='session')\ndef my_fixture(): pass";
-    let parsed = parse(code, Mode::Module, "").unwrap();
+    let parsed = parse(code);
 
-    if let rustpython_parser::ast::Mod::Module(module) = parsed {
-        if let rustpython_parser::ast::Stmt::FunctionDef(func_def) = &module.body[0] {
+    if let ruff_python_ast::Mod::Module(module) = parsed {
+        if let ruff_python_ast::Stmt::FunctionDef(func_def) = &module.body[0] {
             assert!(decorators::is_fixture_decorator(
-                &func_def.decorator_list[0]
+                &func_def.decorator_list[0].expression,
+                &[]
             ));
         }
     }
 }
 
+#[test]
+#[timeout(30000)]
+fn test_is_fixture_decorator_yield_fixture() {
+    let cases = [
+        ("@pytest.yield_fixture\ndef f(): pass", Some(true)),
+        (
+            "@pytest.yield_fixture(scope='module')\ndef f(): pass",
+            Some(true),
+        ),
+        ("@yield_fixture\ndef f(): pass", Some(false)),
+        ("@other.yield_fixture\ndef f(): pass", None),
+    ];
+
+    for (code, expected) in cases {
+        let parsed = parse(code);
+        if let ruff_python_ast::Mod::Module(module) = parsed {
+            if let ruff_python_ast::Stmt::FunctionDef(func_def) = &module.body[0] {
+                let expr = &func_def.decorator_list[0].expression;
+                let deprecated = decorators::deprecated_yield_fixture(expr);
+                assert_eq!(
+                    deprecated.map(|(_, qualified)| qualified),
+                    expected,
+                    "{}",
+                    code
+                );
+                assert_eq!(
+                    decorators::is_fixture_decorator(expr, &[]),
+                    expected.is_some(),
+                    "{}",
+                    code
+                );
+                if let Some((range, _)) = deprecated {
+                    assert_eq!(
+                        &code[range.start().to_usize()..range.end().to_usize()],
+                        "yield_fixture"
+                    );
+                }
+            }
+        }
+    }
+}
+
 #[test]
 #[timeout(30000)]
 fn test_is_fixture_decorator_pytest_asyncio() {
     // Test @pytest_asyncio.fixture (no parens)
     let code = "@pytest_asyncio.fixture\nasync def my_fixture(): pass";
-    let parsed = parse(code, Mode::Module, "").unwrap();
+    let parsed = parse(code);
 
-    if let rustpython_parser::ast::Mod::Module(module) = parsed {
-        if let rustpython_parser::ast::Stmt::AsyncFunctionDef(func_def) = &module.body[0] {
+    if let ruff_python_ast::Mod::Module(module) = parsed {
+        if let ruff_python_ast::Stmt::FunctionDef(func_def) = &module.body[0] {
             assert!(decorators::is_fixture_decorator(
-                &func_def.decorator_list[0]
+                &func_def.decorator_list[0].expression,
+                &[]
             ));
         }
     }
@@ -72,12 +125,29 @@ fn test_is_fixture_decorator_pytest_asyncio() {
 fn test_is_fixture_decorator_pytest_asyncio_with_args() {
     // Test @pytest_asyncio.fixture(scope='session')
     let code = "@pytest_asyncio.fixture(scope='session')\nasync def my_fixture(): pass";
-    let parsed = parse(code, Mode::Module, "").unwrap();
+    let parsed = parse(code);
+
+    if let ruff_python_ast::Mod::Module(module) = parsed {
+        if let ruff_python_ast::Stmt::FunctionDef(func_def) = &module.body[0] {
+            assert!(decorators::is_fixture_decorator(
+                &func_def.decorator_list[0].expression,
+                &[]
+            ));
+        }
+    }
+}
+
+#[test]
+#[timeout(30000)]
+fn test_is_fixture_decorator_pytest_cases() {
+    let code = "@pytest_cases.fixture(scope='module')\ndef my_fixture(): pass";
+    let parsed = parse(code);
 
-    if let rustpython_parser::ast::Mod::Module(module) = parsed {
-        if let rustpython_parser::ast::Stmt::AsyncFunctionDef(func_def) = &module.body[0] {
+    if let ruff_python_ast::Mod::Module(module) = parsed {
+        if let ruff_python_ast::Stmt::FunctionDef(func_def) = &module.body[0] {
             assert!(decorators::is_fixture_decorator(
-                &func_def.decorator_list[0]
+                &func_def.decorator_list[0].expression,
+                &[]
             ));
         }
     }
@@ -87,12 +157,13 @@ fn test_is_fixture_decorator_pytest_asyncio_with_args() {
 #[timeout(30000)]
 fn test_not_fixture_decorator() {
     let code = "@property\ndef my_prop(): pass";
-    let parsed = parse(code, Mode::Module, "").unwrap();
+    let parsed = parse(code);
 
-    if let rustpython_parser::ast::Mod::Module(module) = parsed {
-        if let rustpython_parser::ast::Stmt::FunctionDef(func_def) = &module.body[0] {
+    if let ruff_python_ast::Mod::Module(module) = parsed {
+        if let ruff_python_ast::Stmt::FunctionDef(func_def) = &module.body[0] {
             assert!(!decorators::is_fixture_decorator(
-                &func_def.decorator_list[0]
+                &func_def.decorator_list[0].expression,
+                &[]
             ));
         }
     }
@@ -102,26 +173,58 @@ fn test_not_fixture_decorator() {
 #[timeout(30000)]
 fn test_extract_custom_fixture_name() {
     let code = "@pytest.fixture(name='custom')\ndef my_fixture(): pass";
-    let parsed = parse(code, Mode::Module, "").unwrap();
+    let parsed = parse(code);
 
-    if let rustpython_parser::ast::Mod::Module(module) = parsed {
-        if let rustpython_parser::ast::Stmt::FunctionDef(func_def) = &module.body[0] {
-            let name = decorators::extract_fixture_name_from_decorator(&func_def.decorator_list[0]);
+    if let ruff_python_ast::Mod::Module(module) = parsed {
+        if let ruff_python_ast::Stmt::FunctionDef(func_def) = &module.body[0] {
+            let name = decorators::extract_fixture_name_from_decorator(
+                &func_def.decorator_list[0].expression,
+                &[],
+            );
             assert_eq!(name, Some("custom".to_string()));
         }
     }
 }
 
+#[test]
+#[timeout(30000)]
+fn test_is_fixture_decorator_alias() {
+    let aliases = ["myproject.testing.db_fixture".to_string()];
+    let cases = [
+        ("@db_fixture\ndef f(): pass", true),
+        ("@db_fixture(scope='session')\ndef f(): pass", true),
+        ("@testing.db_fixture\ndef f(): pass", true),
+        ("@myproject.testing.db_fixture\ndef f(): pass", true),
+        ("@other_db_fixture\ndef f(): pass", false),
+        ("@db_fixture.extra\ndef f(): pass", false),
+    ];
+
+    for (code, expected) in cases {
+        if let ruff_python_ast::Mod::Module(module) = parse(code) {
+            if let ruff_python_ast::Stmt::FunctionDef(func_def) = &module.body[0] {
+                let expr = &func_def.decorator_list[0].expression;
+                assert_eq!(
+                    decorators::is_fixture_decorator(expr, &aliases),
+                    expected,
+                    "{}",
+                    code
+                );
+                assert!(!decorators::is_fixture_decorator(expr, &[]), "{}", code);
+            }
+        }
+    }
+}
+
 #[test]
 #[timeout(30000)]
 fn test_is_usefixtures_decorator() {
     let code = "@pytest.mark.usefixtures('f1')\ndef test_x(): pass";
-    let parsed = parse(code, Mode::Module, "").unwrap();
+    let parsed = parse(code);
 
-    if let rustpython_parser::ast::Mod::Module(module) = parsed {
-        if let rustpython_parser::ast::Stmt::FunctionDef(func_def) = &module.body[0] {
+    if let ruff_python_ast::Mod::Module(module) = parsed {
+        if let ruff_python_ast::Stmt::FunctionDef(func_def) = &module.body[0] {
             assert!(decorators::is_usefixtures_decorator(
-                &func_def.decorator_list[0]
+                &func_def.decorator_list[0].expression
             ));
         }
     }
@@ -131,11 +234,12 @@ fn test_is_usefixtures_decorator() {
 #[timeout(30000)]
 fn test_extract_usefixtures() {
     let code = "@pytest.mark.usefixtures('f1', 'f2')\ndef test_x(): pass";
-    let parsed = parse(code, Mode::Module, "").unwrap();
+    let parsed = parse(code);
 
-    if let rustpython_parser::ast::Mod::Module(module) = parsed {
-        if let rustpython_parser::ast::Stmt::FunctionDef(func_def) = &module.body[0] {
-            let names = decorators::extract_usefixtures_names(&func_def.decorator_list[0], code);
+    if let ruff_python_ast::Mod::Module(module) = parsed {
+        if let ruff_python_ast::Stmt::FunctionDef(func_def) = &module.body[0] {
+            let names =
+                decorators::extract_usefixtures_names(&func_def.decorator_list[0].expression, code);
             assert_eq!(names.len(), 2);
             assert_eq!(names[0].0, "f1");
             assert_eq!(names[1].0, "f2");
@@ -149,11 +253,12 @@ fn test_extract_usefixtures_ranges_cover_exact_names() {
     // Ranges must cover exactly the fixture name for any quote style,
     // including prefixed (r"...") and triple-quoted ("""...""") literals.
     let code = "@pytest.mark.usefixtures('f1', r'f2', \"\"\"f3\"\"\")\ndef test_x(): pass";
-    let parsed = parse(code, Mode::Module, "").unwrap();
+    let parsed = parse(code);
 
-    if let rustpython_parser::ast::Mod::Module(module) = parsed {
-        if let rustpython_parser::ast::Stmt::FunctionDef(func_def) = &module.body[0] {
-            let names = decorators::extract_usefixtures_names(&func_def.decorator_list[0], code);
+    if let ruff_python_ast::Mod::Module(module) = parsed {
+        if let ruff_python_ast::Stmt::FunctionDef(func_def) = &module.body[0] {
+            let names =
+                decorators::extract_usefixtures_names(&func_def.decorator_list[0].expression, code);
             assert_eq!(names.len(), 3);
             for (name, range) in &names {
                 let span = &code[range.start().to_usize()..range.end().to_usize()];
@@ -169,10 +274,10 @@ fn test_extract_usefixtures_ranges_cover_exact_names() {
 #[timeout(30000)]
 fn test_extract_usefixtures_from_expr_direct_call() {
     let code = "pytestmark = pytest.mark.usefixtures('f1', 'f2')";
-    let parsed = parse(code, Mode::Module, "").unwrap();
+    let parsed = parse(code);
 
-    if let rustpython_parser::ast::Mod::Module(module) = parsed {
-        if let rustpython_parser::ast::Stmt::Assign(assign) = &module.body[0] {
+    if let ruff_python_ast::Mod::Module(module) = parsed {
+        if let ruff_python_ast::Stmt::Assign(assign) = &module.body[0] {
             let names = decorators::extract_usefixtures_from_expr(&assign.value, code);
             assert_eq!(names.len(), 2);
             assert_eq!(names[0].0, "f1");
@@ -185,10 +290,10 @@ fn test_extract_usefixtures_from_expr_direct_call() {
 #[timeout(30000)]
 fn test_extract_usefixtures_from_expr_list() {
     let code = "pytestmark = [pytest.mark.usefixtures('f1'), pytest.mark.skip, pytest.mark.usefixtures('f2')]";
-    let parsed = parse(code, Mode::Module, "").unwrap();
+    let parsed = parse(code);
 
-    if let rustpython_parser::ast::Mod::Module(module) = parsed {
-        if let rustpython_parser::ast::Stmt::Assign(assign) = &module.body[0] {
+    if let ruff_python_ast::Mod::Module(module) = parsed {
+        if let ruff_python_ast::Stmt::Assign(assign) = &module.body[0] {
             let names = decorators::extract_usefixtures_from_expr(&assign.value, code);
             assert_eq!(names.len(), 2);
             assert_eq!(names[0].0, "f1");
@@ -201,10 +306,10 @@ fn test_extract_usefixtures_from_expr_list() {
 #[timeout(30000)]
 fn test_extract_usefixtures_from_expr_tuple() {
     let code = "pytestmark = (pytest.mark.usefixtures('f1'), pytest.mark.usefixtures('f2'))";
-    let parsed = parse(code, Mode::Module, "").unwrap();
+    let parsed = parse(code);
 
-    if let rustpython_parser::ast::Mod::Module(module) = parsed {
-        if let rustpython_parser::ast::Stmt::Assign(assign) = &module.body[0] {
+    if let ruff_python_ast::Mod::Module(module) = parsed {
+        if let ruff_python_ast::Stmt::Assign(assign) = &module.body[0] {
             let names = decorators::extract_usefixtures_from_expr(&assign.value, code);
             assert_eq!(names.len(), 2);
             assert_eq!(names[0].0, "f1");
@@ -217,10 +322,10 @@ fn test_extract_usefixtures_from_expr_tuple() {
 #[timeout(30000)]
 fn test_extract_usefixtures_from_expr_no_usefixtures() {
     let code = "pytestmark = [pytest.mark.skip, pytest.mark.slow]";
-    let parsed = parse(code, Mode::Module, "").unwrap();
+    let parsed = parse(code);
 
-    if let rustpython_parser::ast::Mod::Module(module) = parsed {
-        if let rustpython_parser::ast::Stmt::Assign(assign) = &module.body[0] {
+    if let ruff_python_ast::Mod::Module(module) = parsed {
+        if let ruff_python_ast::Stmt::Assign(assign) = &module.body[0] {
             let names = decorators::extract_usefixtures_from_expr(&assign.value, code);
             assert_eq!(names.len(), 0);
         }
@@ -231,12 +336,12 @@ fn test_extract_usefixtures_from_expr_no_usefixtures() {
 #[timeout(30000)]
 fn test_is_parametrize_decorator() {
     let code = "@pytest.mark.parametrize('x', [1])\ndef test_x(x): pass";
-    let parsed = parse(code, Mode::Module, "").unwrap();
+    let parsed = parse(code);
 
-    if let rustpython_parser::ast::Mod::Module(module) = parsed {
-        if let rustpython_parser::ast::Stmt::FunctionDef(func_def) = &module.body[0] {
+    if let ruff_python_ast::Mod::Module(module) = parsed {
+        if let ruff_python_ast::Stmt::FunctionDef(func_def) = &module.body[0] {
             assert!(decorators::is_parametrize_decorator(
-                &func_def.decorator_list[0]
+                &func_def.decorator_list[0].expression
             ));
         }
     }
@@ -246,12 +351,12 @@ fn test_is_parametrize_decorator() {
 #[timeout(30000)]
 fn test_extract_parametrize_indirect() {
     let code = "@pytest.mark.parametrize('f1', ['a'], indirect=True)\ndef test_x(f1): pass";
-    let parsed = parse(code, Mode::Module, "").unwrap();
+    let parsed = parse(code);
 
-    if let rustpython_parser::ast::Mod::Module(module) = parsed {
-        if let rustpython_parser::ast::Stmt::FunctionDef(func_def) = &module.body[0] {
+    if let ruff_python_ast::Mod::Module(module) = parsed {
+        if let ruff_python_ast::Stmt::FunctionDef(func_def) = &module.body[0] {
             let fixtures = decorators::extract_parametrize_indirect_fixtures(
-                &func_def.decorator_list[0],
+                &func_def.decorator_list[0].expression,
                 code,
             );
             assert_eq!(fixtures.len(), 1);
@@ -270,17 +375,17 @@ fn test_extract_parametrize_indirect() {
 /// `extract_parametrize_argnames`, where `source_slice` is the exact substring the returned
 /// range points at — so tests can confirm ranges land on the identifier, not quotes/whitespace.
 fn argnames_with_slices(code: &str) -> Vec<(String, String)> {
-    let parsed = parse(code, Mode::Module, "").unwrap();
-    let rustpython_parser::ast::Mod::Module(module) = parsed else {
+    let parsed = parse(code);
+    let ruff_python_ast::Mod::Module(module) = parsed else {
         panic!("expected module");
     };
-    let rustpython_parser::ast::Stmt::FunctionDef(func_def) = &module.body[0] else {
+    let ruff_python_ast::Stmt::FunctionDef(func_def) = &module.body[0] else {
         panic!("expected function def");
     };
     func_def
         .decorator_list
         .iter()
-        .flat_map(|dec| decorators::extract_parametrize_argnames(dec, code))
+        .flat_map(|dec| decorators::extract_parametrize_argnames(&dec.expression, code))
         .map(|(name, range)| {
             let slice = code[range.start().to_usize()..range.end().to_usize()].to_string();
             (name, slice)
@@ -378,6 +483,23 @@ fn test_extract_parametrize_argnames_stacked() {
     );
 }
 
+#[test]
+#[timeout(30000)]
+fn test_extract_parametrize_argnames_pytest_cases() {
+    let got = argnames_with_slices(
+        "@parametrize_with_cases('a, b', cases='.cases')\ndef test_x(a, b): pass",
+    );
+    assert_eq!(
+        got,
+        vec![
+            ("a".to_string(), "a".to_string()),
+            ("b".to_string(), "b".to_string())
+        ]
+    );
+    let got = argnames_with_slices("@pytest_cases.parametrize('x', [1])\ndef test_x(x): pass");
+    assert_eq!(got, vec![("x".to_string(), "x".to_string())]);
+}
+
 #[test]
 #[timeout(30000)]
 fn test_extract_parametrize_argnames_not_parametrize() {
@@ -423,14 +545,14 @@ fn test_extract_parametrize_argnames_rejects_non_identifier() {
 #[timeout(30000)]
 fn test_indirect_names_keyword_argnames() {
     let code = "@pytest.mark.parametrize(argnames='a,b', argvalues=[(1, 2)], indirect=True)\ndef test_x(a, b): pass";
-    let parsed = parse(code, Mode::Module, "").unwrap();
-    let rustpython_parser::ast::Mod::Module(module) = parsed else {
+    let parsed = parse(code);
+    let ruff_python_ast::Mod::Module(module) = parsed else {
         panic!("expected module");
     };
-    let rustpython_parser::ast::Stmt::FunctionDef(func_def) = &module.body[0] else {
+    let ruff_python_ast::Stmt::FunctionDef(func_def) = &module.body[0] else {
         panic!("expected function def");
     };
-    let dec = &func_def.decorator_list[0];
+    let dec = &func_def.decorator_list[0].expression;
     let names: Vec<String> = decorators::extract_parametrize_argnames(dec, code)
         .into_iter()
         .map(|(n, _)| n)
@@ -444,14 +566,14 @@ fn test_indirect_names_keyword_argnames() {
 #[timeout(30000)]
 fn test_indirect_names_partial_list() {
     let code = "@pytest.mark.parametrize('a,b', [(1, 2)], indirect=['a'])\ndef test_x(a, b): pass";
-    let parsed = parse(code, Mode::Module, "").unwrap();
-    let rustpython_parser::ast::Mod::Module(module) = parsed else {
+    let parsed = parse(code);
+    let ruff_python_ast::Mod::Module(module) = parsed else {
         panic!("expected module");
     };
-    let rustpython_parser::ast::Stmt::FunctionDef(func_def) = &module.body[0] else {
+    let ruff_python_ast::Stmt::FunctionDef(func_def) = &module.body[0] else {
         panic!("expected function def");
     };
-    let dec = &func_def.decorator_list[0];
+    let dec = &func_def.decorator_list[0].expression;
     let names: Vec<String> = decorators::extract_parametrize_argnames(dec, code)
         .into_iter()
         .map(|(n, _)| n)
@@ -463,13 +585,12 @@ fn test_indirect_names_partial_list() {
 
 /// Returns the indirect-name set for the first decorator of a single decorated function.
 fn indirect_names(code: &str) -> std::collections::HashSet<String> {
-    let parsed = parse(code, Mode::Module, "").unwrap();
-    let rustpython_parser::ast::Mod::Module(module) = parsed else {
+    let parsed = parse(code);
+    let ruff_python_ast::Mod::Module(module) = parsed else {
         panic!("expected module");
     };
     let dec = match &module.body[0] {
-        rustpython_parser::ast::Stmt::FunctionDef(f) => &f.decorator_list[0],
-        rustpython_parser::ast::Stmt::AsyncFunctionDef(f) => &f.decorator_list[0],
+        ruff_python_ast::Stmt::FunctionDef(f) => &f.decorator_list[0].expression,
         _ => panic!("expected function def"),
     };
     let names: Vec<String> = decorators::extract_parametrize_argnames(dec, code)
@@ -534,3 +655,56 @@ fn test_indirect_names_ignores_non_string_list_elements() {
     );
     assert!(names.is_empty());
 }
+
+/// Parse `code`, run `extract_parametrize_indirect_list` on the first function's first decorator,
+/// and return each name paired with the source slice its range covers.
+fn indirect_list_with_slices(code: &str) -> Vec<(String, String)> {
+    let parsed = parse(code);
+    let ruff_python_ast::Mod::Module(module) = parsed else {
+        panic!("expected module");
+    };
+    let ruff_python_ast::Stmt::FunctionDef(func_def) = &module.body[0] else {
+        panic!("expected function def");
+    };
+    decorators::extract_parametrize_indirect_list(&func_def.decorator_list[0].expression, code)
+        .into_iter()
+        .map(|(name, range)| {
+            let slice = code[range.start().to_usize()..range.end().to_usize()].to_string();
+            (name, slice)
+        })
+        .collect()
+}
+
+#[test]
+#[timeout(30000)]
+fn test_indirect_list_keeps_non_argnames_with_ranges() {
+    // Entries are returned as written, even when they are not argnames.
+    let got = indirect_list_with_slices(
+        "@pytest.mark.parametrize('a,b', [(1, 2)], indirect=['a', \"typo\"])\ndef test_x(a, b): pass",
+    );
+    assert_eq!(
+        got,
+        vec![
+            ("a".to_string(), "a".to_string()),
+            ("typo".to_string(), "typo".to_string()),
+        ]
+    );
+}
+
+#[test]
+#[timeout(30000)]
+fn test_indirect_list_tuple_and_positional() {
+    let got = indirect_list_with_slices(
+        "@pytest.mark.parametrize('a', [1], ('a',))\ndef test_x(a): pass",
+    );
+    assert_eq!(got, vec![("a".to_string(), "a".to_string())]);
+}
+
+#[test]
+#[timeout(30000)]
+fn test_indirect_list_empty_for_bool() {
+    assert!(indirect_list_with_slices(
+        "@pytest.mark.parametrize('a', [1], indirect=True)\ndef test_x(a): pass"
+    )
+    .is_empty());
+}
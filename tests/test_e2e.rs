@@ -325,6 +325,609 @@ fn test_cli_fixtures_unused_help() {
         .stdout(predicate::str::contains("--format"));
 }
 
+#[test]
+#[timeout(30000)]
+fn test_cli_fixtures_available() {
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("pytest-language-server"));
+    cmd.current_dir("tests/test_project")
+        .arg("fixtures")
+        .arg("available")
+        .arg("subdir/test_override.py")
+        .env("NO_COLOR", "1");
+
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Fixtures available from subdir/test_override.py",
+        ))
+        .stdout(predicate::str::contains("\nbuiltin\n"))
+        .stdout(predicate::str::contains("conftest subdir/conftest.py"))
+        .stdout(predicate::str::contains(
+            "  sample_fixture overrides conftest.py:6\n      Override parent fixture",
+        ))
+        .stdout(predicate::str::contains("  tmp_path\n"));
+}
+
+#[test]
+#[timeout(30000)]
+fn test_cli_fixtures_available_json() {
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("pytest-language-server"));
+    cmd.current_dir("tests/test_project")
+        .arg("fixtures")
+        .arg("available")
+        .arg("subdir")
+        .arg("--format")
+        .arg("json");
+
+    let output = cmd.output().unwrap();
+    assert!(output.status.success());
+    let fixtures: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    let sample = fixtures
+        .iter()
+        .find(|fixture| fixture["name"] == "sample_fixture")
+        .unwrap();
+    assert_eq!(sample["origin"], "conftest");
+    assert_eq!(sample["file"], "subdir/conftest.py");
+    assert_eq!(sample["scope"], "function");
+    assert_eq!(
+        sample["overrides"],
+        serde_json::json!([{"file": "conftest.py", "line": 6}])
+    );
+    assert!(fixtures
+        .iter()
+        .any(|fixture| fixture["name"] == "capsys" && fixture["origin"] == "builtin"));
+}
+
+#[test]
+#[timeout(30000)]
+fn test_cli_fixtures_available_nonexistent_path() {
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("pytest-language-server"));
+    cmd.arg("fixtures")
+        .arg("available")
+        .arg("/nonexistent/path/to/tests");
+
+    cmd.assert()
+        .failure()
+        .stderr(predicate::str::contains("Path does not exist"));
+}
+
+#[test]
+#[timeout(30000)]
+fn test_cli_fixtures_tree() {
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("pytest-language-server"));
+    cmd.current_dir("tests/test_project")
+        .arg("fixtures")
+        .arg("tree")
+        .arg("database")
+        .arg("--from")
+        .arg("subdir/test_override.py");
+
+    cmd.assert().success().stdout(
+        "database [function] subdir/conftest.py:23\n\
+         ├── database [function] conftest.py:24\n\
+         └── shared_resource [function] conftest.py:30\n",
+    );
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("pytest-language-server"));
+    cmd.current_dir("tests/test_project")
+        .arg("fixtures")
+        .arg("tree")
+        .arg("sample_fixture")
+        .arg("--from")
+        .arg("subdir")
+        .arg("--reverse");
+
+    cmd.assert().success().stdout(
+        "sample_fixture [function] subdir/conftest.py:5\n\
+         ├── subdir/test_override.py::test_overridden_fixture\n\
+         └── subdir/test_override.py::test_both_fixtures\n",
+    );
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("pytest-language-server"));
+    cmd.current_dir("tests/test_project")
+        .arg("fixtures")
+        .arg("tree")
+        .arg("no_such_fixture");
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "No fixture named 'no_such_fixture'",
+    ));
+}
+
+#[test]
+#[timeout(30000)]
+fn test_cli_fixtures_tree_reverse_lists_each_requester_once() {
+    let tmp = tempdir().unwrap();
+    std::fs::write(
+        tmp.path().join("conftest.py"),
+        "import pytest\n\n@pytest.fixture\ndef client():\n    return 1\n",
+    )
+    .unwrap();
+    std::fs::write(
+        tmp.path().join("test_client.py"),
+        "import pytest\n\n@pytest.mark.usefixtures(\"client\", \"client\")\ndef test_api(client):\n    pass\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("pytest-language-server"));
+    cmd.current_dir(tmp.path())
+        .arg("fixtures")
+        .arg("tree")
+        .arg("client")
+        .arg("--reverse");
+
+    cmd.assert().success().stdout(
+        "client [function] conftest.py:4\n\
+         └── test_client.py::test_api\n",
+    );
+}
+
+#[test]
+#[timeout(30000)]
+fn test_cli_fixtures_uses() {
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("pytest-language-server"));
+    cmd.current_dir("tests/test_project")
+        .arg("fixtures")
+        .arg("uses")
+        .arg("database");
+
+    cmd.assert().success().stdout(
+        "conftest.py:24:5: database defined here (3 use(s))\n\
+         subdir/conftest.py:23:14: requested by fixture database\n\
+         test_parent_usage.py:9:31: requested by test test_parent_usage.py::test_uses_parent_database\n\
+         test_parent_usage.py:19:47: requested by test test_parent_usage.py::test_multiple_parent_fixtures\n\
+         \n\
+         subdir/conftest.py:23:5: database defined here (3 use(s))\n\
+         subdir/test_hierarchy.py:9:35: requested by test subdir/test_hierarchy.py::test_uses_overridden_database\n\
+         subdir/test_hierarchy.py:26:40: requested by test subdir/test_hierarchy.py::test_multiple_fixtures\n\
+         subdir/test_hierarchy.py:39:34: requested by test subdir/test_hierarchy.py::test_third_usage\n",
+    );
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("pytest-language-server"));
+    cmd.current_dir("tests/test_project")
+        .arg("fixtures")
+        .arg("uses")
+        .arg("no_such_fixture");
+
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "No fixture named 'no_such_fixture'",
+    ));
+}
+
+// MARK: `check` E2E Tests
+
+#[test]
+#[timeout(30000)]
+fn test_cli_check_json_output() {
+    let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+    let output = cmd
+        .arg("check")
+        .arg("tests/test_project")
+        .arg("--format")
+        .arg("json")
+        .output()
+        .expect("Failed to execute command");
+
+    // Should exit with code 1 when problems are found
+    assert_eq!(output.status.code(), Some(1));
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let json: serde_json::Value =
+        serde_json::from_str(&stdout).expect("Output should be valid JSON");
+    assert_eq!(json["version"], 1);
+
+    let diagnostics = json["diagnostics"].as_array().unwrap();
+    let undeclared: Vec<&serde_json::Value> = diagnostics
+        .iter()
+        .filter(|d| d["path"] == "test_undeclared_example.py")
+        .collect();
+    assert!(!undeclared.is_empty());
+    for diagnostic in &undeclared {
+        assert_eq!(diagnostic["code"], "PTL001");
+        assert_eq!(diagnostic["rule"], "undeclared-fixture");
+        assert_eq!(diagnostic["severity"], "warning");
+    }
+    assert_eq!(
+        undeclared[0]["range"],
+        serde_json::json!({
+            "start": { "line": 15, "column": 14 },
+            "end": { "line": 15, "column": 28 }
+        })
+    );
+    let summary = &json["summary"];
+    assert_eq!(
+        ["errors", "warnings", "information", "hints"]
+            .iter()
+            .map(|severity| summary[severity].as_u64().unwrap())
+            .sum::<u64>(),
+        diagnostics.len() as u64
+    );
+}
+
+#[test]
+#[timeout(30000)]
+fn test_cli_check_sarif_output() {
+    let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+    let output = cmd
+        .arg("check")
+        .arg("tests/test_project")
+        .arg("--format")
+        .arg("sarif")
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(output.status.code(), Some(1));
+
+    let sarif: serde_json::Value =
+        serde_json::from_slice(&output.stdout).expect("Output should be valid JSON");
+    assert_eq!(sarif["version"], "2.1.0");
+    let run = &sarif["runs"][0];
+    assert_eq!(run["tool"]["driver"]["name"], "pytest-language-server");
+    let results = run["results"].as_array().unwrap();
+    assert!(results.iter().any(|result| {
+        result["ruleId"] == "PTL001"
+            && result["locations"][0]["physicalLocation"]["artifactLocation"]["uri"]
+                == "test_undeclared_example.py"
+    }));
+    for result in results {
+        assert!(result["partialFingerprints"]["pytestLanguageServer/v1"].is_string());
+    }
+}
+
+#[test]
+#[timeout(30000)]
+fn test_cli_check_github_output() {
+    let workspace = std::env::current_dir().unwrap();
+    let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+    let output = cmd
+        .arg("check")
+        .arg("tests/test_project")
+        .arg("--format")
+        .arg("github")
+        .env("GITHUB_WORKSPACE", &workspace)
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(output.status.code(), Some(1));
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.lines().all(|line| line.starts_with("::")));
+    assert!(
+        stdout.contains("::warning file=tests/test_project/test_undeclared_example.py,line=15,")
+    );
+    assert!(stdout.contains(
+        "title=PTL001 undeclared-fixture::Fixture 'sample_fixture' is used but not declared as a parameter\n"
+    ));
+}
+
+#[test]
+#[timeout(30000)]
+fn test_cli_check_junit_output() {
+    let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+    let output = cmd
+        .arg("check")
+        .arg("tests/test_project")
+        .arg("--format")
+        .arg("junit")
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(output.status.code(), Some(1));
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("<?xml version=\"1.0\" encoding=\"UTF-8\"?>"));
+    assert!(stdout.contains(
+        "<testcase classname=\"pytest-language-server\" name=\"test_undeclared_example.py\" file=\"test_undeclared_example.py\">"
+    ));
+    assert!(stdout.contains("<failure type=\"warning\""));
+    assert!(stdout.trim_end().ends_with("</testsuites>"));
+}
+
+#[test]
+#[timeout(30000)]
+fn test_cli_check_fail_on() {
+    // tests/test_project has warnings, but no errors
+    let run = |args: &[&str]| {
+        let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+        cmd.arg("check")
+            .arg("tests/test_project")
+            .args(args)
+            .output()
+            .expect("Failed to execute command")
+    };
+
+    assert_eq!(run(&[]).status.code(), Some(1));
+    assert_eq!(run(&["--fail-on", "warning"]).status.code(), Some(1));
+    let output = run(&["--fail-on", "error"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(
+        String::from_utf8_lossy(&output.stdout).contains("PTL001"),
+        "Problems are still reported"
+    );
+
+    assert_eq!(
+        run(&["--fail-on", "error", "--max-warnings", "100"])
+            .status
+            .code(),
+        Some(0)
+    );
+    assert_eq!(
+        run(&["--fail-on", "none", "--max-warnings", "0"])
+            .status
+            .code(),
+        Some(1)
+    );
+    assert_eq!(run(&["--fail-on", "fatal"]).status.code(), Some(2));
+}
+
+#[test]
+#[timeout(30000)]
+fn test_cli_check_baseline() {
+    let tmp = tempdir().unwrap();
+    let baseline = tmp.path().join("baseline.json");
+
+    let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+    let output = cmd
+        .arg("check")
+        .arg("tests/test_project")
+        .arg("--baseline")
+        .arg(&baseline)
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(output.status.code(), Some(0));
+    let recorded: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&baseline).unwrap()).unwrap();
+    assert!(!recorded["diagnostics"].as_array().unwrap().is_empty());
+
+    // Every problem is known
+    let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+    let output = cmd
+        .arg("check")
+        .arg("tests/test_project")
+        .arg("--format")
+        .arg("json")
+        .arg("--baseline")
+        .arg(&baseline)
+        .arg("--baseline-check")
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(output.status.code(), Some(0));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["diagnostics"], serde_json::json!([]));
+    assert_eq!(
+        json["summary"]["baselined"],
+        recorded["diagnostics"].as_array().unwrap().len()
+    );
+
+    // A missing baseline is an error
+    let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+    cmd.arg("check")
+        .arg("tests/test_project")
+        .arg("--baseline")
+        .arg(tmp.path().join("missing.json"))
+        .arg("--baseline-check")
+        .assert()
+        .code(2);
+}
+
+#[test]
+#[timeout(30000)]
+fn test_cli_check_fix_and_diff() {
+    let tmp = tempdir().unwrap();
+    let test_file = tmp.path().join("test_a.py");
+    let content = "import pytest\n\n\n@pytest.fixture\ndef db():\n    pass\n\n\n@pytest.mark.usefixtures(\"db\")\ndef test_a(db):\n    pass\n";
+    std::fs::write(&test_file, content).unwrap();
+
+    // --diff previews the fixes without applying them
+    let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+    let output = cmd
+        .arg("check")
+        .arg(tmp.path())
+        .arg("--diff")
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.starts_with("--- a/test_a.py\n+++ b/test_a.py\n"));
+    assert!(stdout.contains("\n-@pytest.mark.usefixtures(\"db\")\n def test_a(db):\n"));
+    assert_eq!(std::fs::read_to_string(&test_file).unwrap(), content);
+
+    // --fix applies them, leaving nothing to report
+    let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+    let output = cmd
+        .arg("check")
+        .arg(tmp.path())
+        .arg("--fix")
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Fixed 1 problem(s) in 1 file(s)"));
+    assert_eq!(
+        std::fs::read_to_string(&test_file).unwrap(),
+        content.replace("@pytest.mark.usefixtures(\"db\")\n", "")
+    );
+
+    let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+    cmd.arg("check")
+        .arg(tmp.path())
+        .arg("--diff")
+        .assert()
+        .code(0);
+}
+
+#[test]
+#[timeout(30000)]
+fn test_cli_check_fix_moves_unused_parameter_to_usefixtures() {
+    let tmp = tempdir().unwrap();
+    let test_file = tmp.path().join("test_a.py");
+    let content = "import pytest\n\n\n@pytest.fixture\ndef db():\n    return 1\n\n\n@pytest.fixture\ndef other():\n    return 2\n\n\ndef test_a(db, other):\n    assert other\n";
+    std::fs::write(&test_file, content).unwrap();
+
+    let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+    let output = cmd
+        .arg("check")
+        .arg(tmp.path())
+        .arg("--fix")
+        .output()
+        .expect("Failed to execute command");
+    assert_eq!(output.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Fixed 1 problem(s) in 1 file(s)"));
+    assert_eq!(
+        std::fs::read_to_string(&test_file).unwrap(),
+        content.replace(
+            "def test_a(db, other):",
+            "@pytest.mark.usefixtures(\"db\")\ndef test_a(other):"
+        )
+    );
+
+    // The fixed file has nothing left to fix
+    let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+    cmd.arg("check")
+        .arg(tmp.path())
+        .arg("--diff")
+        .assert()
+        .code(0);
+}
+
+#[test]
+#[timeout(30000)]
+fn test_cli_check_files() {
+    let tmp = tempdir().unwrap();
+    std::fs::write(
+        tmp.path().join("pyproject.toml"),
+        "[tool.pytest-language-server]\ncache_dir = \".cache\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        tmp.path().join("conftest.py"),
+        "import pytest\n\n\n@pytest.fixture\ndef db():\n    pass\n",
+    )
+    .unwrap();
+    std::fs::write(tmp.path().join("README.md"), "# Project\n").unwrap();
+    std::fs::create_dir(tmp.path().join("tests")).unwrap();
+    for name in ["test_a.py", "test_b.py"] {
+        std::fs::write(
+            tmp.path().join("tests").join(name),
+            "def test_one():\n    result = db\n",
+        )
+        .unwrap();
+    }
+
+    // Only the files given are checked, against the index of the whole
+    // directory, which is cached
+    let check = || {
+        let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+        cmd.current_dir(tmp.path())
+            .arg("check")
+            .arg("tests/test_a.py")
+            .arg("README.md")
+            .arg("--format")
+            .arg("json")
+            .output()
+            .expect("Failed to execute command")
+    };
+    for _ in 0..2 {
+        let output = check();
+        assert_eq!(output.status.code(), Some(1));
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        let paths: Vec<&str> = json["diagnostics"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|d| d["path"].as_str().unwrap())
+            .collect();
+        assert_eq!(paths, vec!["tests/test_a.py"]);
+        assert_eq!(json["summary"]["files"], 1);
+        assert!(
+            std::fs::read_dir(tmp.path().join(".cache"))
+                .unwrap()
+                .count()
+                > 0
+        );
+    }
+
+    // Files outside of the current directory are rejected
+    let other = tempdir().unwrap();
+    std::fs::write(other.path().join("test_c.py"), "").unwrap();
+    let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+    cmd.current_dir(tmp.path())
+        .arg("check")
+        .arg(other.path().join("test_c.py"))
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("outside of the current directory"));
+}
+
+#[test]
+#[timeout(30000)]
+fn test_cli_check_exit_code_zero_without_problems() {
+    let temp_dir = tempdir().unwrap();
+    std::fs::write(
+        temp_dir.path().join("conftest.py"),
+        "import pytest\n\n@pytest.fixture\ndef my_fixture():\n    return 1\n",
+    )
+    .unwrap();
+    std::fs::write(
+        temp_dir.path().join("test_example.py"),
+        "def test_something(my_fixture):\n    assert my_fixture == 1\n",
+    )
+    .unwrap();
+
+    let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+    let output = cmd
+        .arg("check")
+        .arg(temp_dir.path())
+        .arg("--format")
+        .arg("json")
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(0));
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["diagnostics"], serde_json::json!([]));
+    assert_eq!(json["summary"]["files"], 2);
+}
+
+#[test]
+#[timeout(30000)]
+fn test_cli_check_invalid_arguments() {
+    let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+    cmd.arg("check")
+        .arg("tests/test_project")
+        .arg("--format")
+        .arg("xml")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("unknown check format"));
+
+    let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+    cmd.arg("check")
+        .arg("/nonexistent/path/to/project")
+        .assert()
+        .code(2);
+}
+
+// MARK: `doctor` E2E Tests
+
+#[test]
+#[timeout(30000)]
+fn test_cli_doctor() {
+    let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+    cmd.arg("doctor")
+        .arg("tests/test_project")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("\nWorkspace\n"))
+        .stdout(predicate::str::contains("pytest rootdir:"))
+        .stdout(predicate::str::contains("virtual environment:"))
+        .stdout(predicate::str::contains("files analyzed:           22\n"))
+        .stdout(predicate::str::contains("\nProblems\n"));
+
+    let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+    cmd.arg("doctor")
+        .arg("/nonexistent/path")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("Path is not a directory"));
+}
+
 // MARK: Autouse fixtures in `fixtures list` E2E Tests
 
 #[test]
@@ -1572,3 +2175,439 @@ fn test_e2e_editable_install_fixtures_only_unused() {
         stdout
     );
 }
+
+// MARK: TCP and Pipe Transport E2E Tests
+
+/// Send an LSP message over `stream`.
+fn write_message<W: std::io::Write>(stream: &mut W, message: &serde_json::Value) {
+    let message = message.to_string();
+    write!(
+        stream,
+        "Content-Length: {}\r\n\r\n{}",
+        message.len(),
+        message
+    )
+    .unwrap();
+}
+
+/// Read the next LSP message from `reader`.
+fn read_message<R: std::io::BufRead>(reader: &mut R) -> serde_json::Value {
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).unwrap();
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length: ") {
+            length = value.parse().unwrap();
+        }
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+/// Send an LSP `initialize` request over `stream` and return the response.
+fn initialize_over<S: std::io::Read + std::io::Write>(stream: S) -> serde_json::Value {
+    let mut reader = std::io::BufReader::new(stream);
+
+    write_message(
+        reader.get_mut(),
+        &serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": { "processId": null, "rootUri": null, "capabilities": {} },
+        }),
+    );
+    loop {
+        let message = read_message(&mut reader);
+        // Skip the notifications the server may send first
+        if message["id"] == 1 {
+            return message;
+        }
+    }
+}
+
+#[test]
+#[timeout(30000)]
+fn test_lsp_listen_over_tcp() {
+    use std::io::{BufRead, BufReader};
+
+    let mut server =
+        std::process::Command::new(assert_cmd::cargo::cargo_bin!("pytest-language-server"))
+            .arg("--listen")
+            .arg("127.0.0.1:0")
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+
+    let mut stderr = BufReader::new(server.stderr.take().unwrap());
+    let mut line = String::new();
+    stderr.read_line(&mut line).unwrap();
+    let addr = line
+        .trim()
+        .strip_prefix("Listening on ")
+        .unwrap_or_else(|| panic!("Unexpected output: {}", line));
+
+    let stream = std::net::TcpStream::connect(addr).unwrap();
+    let response = initialize_over(stream);
+    server.kill().unwrap();
+    server.wait().unwrap();
+
+    assert!(response["result"]["capabilities"].is_object());
+}
+
+#[test]
+#[timeout(30000)]
+fn test_lsp_connect_over_tcp() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    // A bare port connects to localhost
+    let mut server =
+        std::process::Command::new(assert_cmd::cargo::cargo_bin!("pytest-language-server"))
+            .arg("--connect")
+            .arg(port.to_string())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+
+    let (stream, _) = listener.accept().unwrap();
+    let response = initialize_over(stream);
+    server.kill().unwrap();
+    server.wait().unwrap();
+
+    assert!(response["result"]["capabilities"].is_object());
+}
+
+#[test]
+#[timeout(30000)]
+fn test_lsp_listen_conflicts() {
+    let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+    cmd.arg("--listen")
+        .arg("9257")
+        .arg("--connect")
+        .arg("9258")
+        .assert()
+        .failure();
+
+    let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+    cmd.arg("--listen")
+        .arg("9257")
+        .arg("check")
+        .arg(".")
+        .assert()
+        .failure();
+}
+
+#[cfg(unix)]
+#[test]
+#[timeout(30000)]
+fn test_lsp_pipe() {
+    let tmp = tempdir().unwrap();
+    let socket_path = tmp.path().join("pytest-lsp.sock");
+    let listener = std::os::unix::net::UnixListener::bind(&socket_path).unwrap();
+
+    // As VS Code passes it
+    let mut server =
+        std::process::Command::new(assert_cmd::cargo::cargo_bin!("pytest-language-server"))
+            .arg(format!("--pipe={}", socket_path.display()))
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+
+    let (stream, _) = listener.accept().unwrap();
+    let response = initialize_over(stream);
+    server.kill().unwrap();
+    server.wait().unwrap();
+
+    assert!(response["result"]["capabilities"].is_object());
+}
+
+#[test]
+#[timeout(30000)]
+fn test_lsp_trace() {
+    fn symbol_request<W: std::io::Write>(stream: &mut W, id: i64) {
+        write_message(
+            stream,
+            &serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": "workspace/symbol",
+                "params": { "query": "db" },
+            }),
+        );
+    }
+
+    /// Send `workspace/symbol` requests, one after the response to the other,
+    /// until the server traces one; `$/setTrace` takes effect for the
+    /// requests received after it is handled. Returns the `$/logTrace`
+    /// parameters.
+    fn next_trace<S: std::io::Read + std::io::Write>(
+        reader: &mut std::io::BufReader<S>,
+        id: &mut i64,
+    ) -> serde_json::Value {
+        *id += 1;
+        symbol_request(reader.get_mut(), *id);
+        loop {
+            let message = read_message(reader);
+            if message["method"] == "$/logTrace" {
+                let params = message["params"].clone();
+                if params["message"]
+                    .as_str()
+                    .unwrap()
+                    .contains("workspace/symbol")
+                {
+                    return params;
+                }
+            } else if message["id"] == *id && message.get("method").is_none() {
+                *id += 1;
+                symbol_request(reader.get_mut(), *id);
+            }
+        }
+    }
+
+    fn set_trace<W: std::io::Write>(stream: &mut W, value: &str) {
+        write_message(
+            stream,
+            &serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "$/setTrace",
+                "params": { "value": value },
+            }),
+        );
+    }
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let mut server =
+        std::process::Command::new(assert_cmd::cargo::cargo_bin!("pytest-language-server"))
+            .arg("--connect")
+            .arg(port.to_string())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+    let (stream, _) = listener.accept().unwrap();
+    let mut reader = std::io::BufReader::new(stream);
+
+    initialize_over(reader.get_mut());
+    // Tracing is off by default
+    symbol_request(reader.get_mut(), 2);
+    while read_message(&mut reader)["id"] != 2 {}
+
+    let mut id = 2;
+    set_trace(reader.get_mut(), "verbose");
+    let trace = next_trace(&mut reader, &mut id);
+    let message = trace["message"].as_str().unwrap();
+    assert!(
+        message.starts_with("Handled request 'workspace/symbol - (")
+            && !message.contains("(2)")
+            && message.contains(")' in ")
+            && message.ends_with("ms."),
+        "Unexpected trace: {}",
+        message
+    );
+    assert_eq!(
+        trace["verbose"],
+        "Params: {\"query\":\"db\"}\n\nResult: null"
+    );
+
+    // Requests sent before `messages` is handled are still verbose
+    set_trace(reader.get_mut(), "messages");
+    let trace = loop {
+        let trace = next_trace(&mut reader, &mut id);
+        if trace.get("verbose").is_none() {
+            break trace;
+        }
+    };
+    server.kill().unwrap();
+    server.wait().unwrap();
+
+    let message = trace["message"].as_str().unwrap();
+    assert!(
+        message.starts_with("Handled request 'workspace/symbol - (") && message.ends_with(")'."),
+        "Unexpected trace: {}",
+        message
+    );
+}
+
+// MARK: Lifecycle E2E Tests
+
+/// Start the server over stdio and initialize it with `process_id`.
+fn start_initialized(
+    process_id: Option<u32>,
+) -> (
+    std::process::Child,
+    std::process::ChildStdin,
+    std::io::BufReader<std::process::ChildStdout>,
+) {
+    let mut server =
+        std::process::Command::new(assert_cmd::cargo::cargo_bin!("pytest-language-server"))
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+    let mut stdin = server.stdin.take().unwrap();
+    let mut stdout = std::io::BufReader::new(server.stdout.take().unwrap());
+    write_message(
+        &mut stdin,
+        &serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "initialize",
+            "params": { "processId": process_id, "rootUri": null, "capabilities": {} },
+        }),
+    );
+    while read_message(&mut stdout)["id"] != 1 {}
+    (server, stdin, stdout)
+}
+
+/// Wait up to `timeout` for `server` to exit.
+fn wait_exit(
+    server: &mut std::process::Child,
+    timeout: std::time::Duration,
+) -> Option<std::process::ExitStatus> {
+    let start = std::time::Instant::now();
+    while start.elapsed() < timeout {
+        if let Some(status) = server.try_wait().unwrap() {
+            return Some(status);
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    let _ = server.kill();
+    None
+}
+
+#[test]
+#[timeout(30000)]
+fn test_lsp_shutdown_and_exit() {
+    let (mut server, mut stdin, mut stdout) = start_initialized(None);
+    write_message(
+        &mut stdin,
+        &serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "shutdown"}),
+    );
+    while read_message(&mut stdout)["id"] != 2 {}
+    // Still running until `exit`
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    assert!(server.try_wait().unwrap().is_none());
+
+    write_message(
+        &mut stdin,
+        &serde_json::json!({"jsonrpc": "2.0", "method": "exit"}),
+    );
+    let status = wait_exit(&mut server, std::time::Duration::from_secs(3));
+    assert_eq!(status.and_then(|status| status.code()), Some(0));
+}
+
+#[test]
+#[timeout(30000)]
+fn test_lsp_exit_without_shutdown() {
+    let (mut server, mut stdin, _stdout) = start_initialized(None);
+    write_message(
+        &mut stdin,
+        &serde_json::json!({"jsonrpc": "2.0", "method": "exit"}),
+    );
+    let status = wait_exit(&mut server, std::time::Duration::from_secs(3));
+    assert_eq!(status.and_then(|status| status.code()), Some(1));
+}
+
+#[test]
+#[timeout(30000)]
+fn test_lsp_exits_with_parent() {
+    // Stands for the editor
+    let mut editor =
+        std::process::Command::new(assert_cmd::cargo::cargo_bin!("pytest-language-server"))
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+    // The stdio transport stays open: only the parent's death stops it
+    let (mut server, _stdin, _stdout) = start_initialized(Some(editor.id()));
+    std::thread::sleep(std::time::Duration::from_millis(500));
+    assert!(server.try_wait().unwrap().is_none());
+
+    editor.kill().unwrap();
+    editor.wait().unwrap();
+    let status = wait_exit(&mut server, std::time::Duration::from_secs(10));
+    assert_eq!(status.and_then(|status| status.code()), Some(1));
+}
+
+// MARK: Logging E2E Tests
+
+#[test]
+#[timeout(30000)]
+fn test_lsp_log_file() {
+    use std::io::{BufRead, BufReader};
+
+    let tmp = tempdir().unwrap();
+    let log_file = tmp.path().join("logs").join("server.log");
+
+    let mut server =
+        std::process::Command::new(assert_cmd::cargo::cargo_bin!("pytest-language-server"))
+            .arg("--listen")
+            .arg("127.0.0.1:0")
+            .arg("--log-file")
+            .arg(&log_file)
+            .arg("--log-format")
+            .arg("json")
+            .env("PYTEST_LANGUAGE_SERVER_LOG_LEVEL", "info")
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+
+    let mut stderr = BufReader::new(server.stderr.take().unwrap());
+    let mut line = String::new();
+    stderr.read_line(&mut line).unwrap();
+    let addr = line
+        .trim()
+        .strip_prefix("Listening on ")
+        .unwrap()
+        .to_string();
+
+    let stream = std::net::TcpStream::connect(addr).unwrap();
+    initialize_over(stream);
+    server.kill().unwrap();
+    server.wait().unwrap();
+
+    // Nothing but the address on stderr
+    let mut rest = String::new();
+    std::io::Read::read_to_string(&mut stderr, &mut rest).unwrap();
+    assert_eq!(rest, "");
+
+    let log = std::fs::read_to_string(&log_file).unwrap();
+    let records: Vec<serde_json::Value> = log
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert!(records.iter().any(
+        |record| record["message"] == "pytest-language-server starting"
+            && record["level"] == "INFO"
+    ));
+    assert!(
+        records.iter().all(|record| record["level"] != "DEBUG"),
+        "Only info and above"
+    );
+}
+
+#[test]
+#[timeout(30000)]
+fn test_lsp_invalid_log_options() {
+    let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+    cmd.arg("--log-format")
+        .arg("xml")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("unknown log format 'xml'"));
+
+    let mut cmd = Command::cargo_bin("pytest-language-server").unwrap();
+    cmd.arg("--log-level")
+        .arg("pytest_language_server=loud")
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("invalid log level"));
+}
@@ -9,7 +9,7 @@ use std::path::PathBuf;
 use std::sync::Arc;
 
 use ntest::timeout;
-use pytest_language_server::{Backend, FixtureDatabase};
+use pytest_language_server::{Backend, FixtureDatabase, PositionEncoding};
 use tower_lsp_server::ls_types::request::GotoImplementationParams;
 use tower_lsp_server::ls_types::*;
 use tower_lsp_server::{LanguageServer, LspService};
@@ -216,11 +216,33 @@ async fn test_initialize_capabilities_all_providers_present() {
     assert!(caps.references_provider.is_some());
     assert!(caps.text_document_sync.is_some());
     assert!(caps.code_action_provider.is_some());
-    assert!(caps.completion_provider.is_some());
+    assert_eq!(
+        caps.completion_provider
+            .as_ref()
+            .and_then(|c| c.resolve_provider),
+        Some(true)
+    );
     assert!(caps.document_symbol_provider.is_some());
     assert!(caps.workspace_symbol_provider.is_some());
     assert!(caps.code_lens_provider.is_some());
     assert!(caps.inlay_hint_provider.is_some());
+    assert!(caps.document_link_provider.is_some());
+    assert!(caps.document_highlight_provider.is_some());
+    assert!(caps.linked_editing_range_provider.is_some());
+    assert!(caps.signature_help_provider.is_some());
+    let commands = &caps
+        .execute_command_provider
+        .as_ref()
+        .expect("execute command provider")
+        .commands;
+    for command in [
+        "pytest-lsp.runTest",
+        "pytest-lsp.exportFixtureGraph",
+        "pytest-lsp.explainResolution",
+        "pytest-lsp.listAutouseFixtures",
+    ] {
+        assert!(commands.iter().any(|c| c == command), "missing {}", command);
+    }
     assert!(caps.implementation_provider.is_some());
     assert!(caps.call_hierarchy_provider.is_some());
 }
@@ -282,6 +304,69 @@ async fn test_initialize_background_scan_handle_stored() {
     assert!(handle.is_some(), "scan task handle should be stored");
 }
 
+#[tokio::test]
+#[timeout(30000)]
+async fn test_initialize_additional_edits_support() {
+    use std::sync::atomic::Ordering;
+
+    let backend = make_backend();
+    assert!(backend
+        .client_additional_edits_support
+        .load(Ordering::Relaxed));
+
+    // A client describing no completion item features gets plain inserts
+    backend
+        .initialize(InitializeParams::default())
+        .await
+        .unwrap();
+    assert!(!backend
+        .client_additional_edits_support
+        .load(Ordering::Relaxed));
+
+    // Describing completion item features is not enough
+    let with_item = |resolve_support: Option<CompletionItemCapabilityResolveSupport>| {
+        let mut params = InitializeParams::default();
+        params.capabilities.text_document = Some(TextDocumentClientCapabilities {
+            completion: Some(CompletionClientCapabilities {
+                completion_item: Some(CompletionItemCapability {
+                    resolve_support,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        });
+        params
+    };
+    backend.initialize(with_item(None)).await.unwrap();
+    assert!(!backend
+        .client_additional_edits_support
+        .load(Ordering::Relaxed));
+    backend
+        .initialize(with_item(Some(CompletionItemCapabilityResolveSupport {
+            properties: vec!["documentation".to_string()],
+        })))
+        .await
+        .unwrap();
+    assert!(!backend
+        .client_additional_edits_support
+        .load(Ordering::Relaxed));
+
+    // A client resolving `additionalTextEdits` handles them
+    backend
+        .initialize(with_item(Some(CompletionItemCapabilityResolveSupport {
+            properties: vec![
+                "documentation".to_string(),
+                "additionalTextEdits".to_string(),
+            ],
+        })))
+        .await
+        .unwrap();
+    assert!(backend
+        .client_additional_edits_support
+        .load(Ordering::Relaxed));
+}
+
 // ── initialized ───────────────────────────────────────────────────────────
 
 #[tokio::test]
@@ -410,6 +495,70 @@ async fn test_did_change_updates_fixture_db() {
     );
 }
 
+#[tokio::test]
+#[timeout(30000)]
+async fn test_did_change_applies_incremental_edits() {
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+
+    let file_uri = turi("test_ls_change_incremental", "conftest.py");
+    let file_path = tfile("test_ls_change_incremental", "conftest.py");
+
+    backend
+        .did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri: file_uri.clone(),
+                language_id: "python".to_string(),
+                version: 1,
+                text: "import pytest\n\n@pytest.fixture\ndef old_fixture():\n    return 1\n"
+                    .to_string(),
+            },
+        })
+        .await;
+
+    // Rename the fixture, then add a second one at the end, in one notification
+    let edit = |start: (u32, u32), end: (u32, u32), text: &str| TextDocumentContentChangeEvent {
+        range: Some(Range::new(
+            Position::new(start.0, start.1),
+            Position::new(end.0, end.1),
+        )),
+        range_length: None,
+        text: text.to_string(),
+    };
+    backend
+        .did_change(DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier {
+                uri: file_uri.clone(),
+                version: 2,
+            },
+            content_changes: vec![
+                edit((3, 4), (3, 7), "new"),
+                edit(
+                    (5, 0),
+                    (5, 0),
+                    "\n@pytest.fixture\ndef second_fixture():\n    return 2\n",
+                ),
+            ],
+        })
+        .await;
+
+    assert!(db.definitions.contains_key("new_fixture"));
+    assert!(db.definitions.contains_key("second_fixture"));
+    assert!(!db.definitions.contains_key("old_fixture"));
+    assert_eq!(
+        backend.documents.get(&file_path).unwrap().text.to_string(),
+        "import pytest\n\n@pytest.fixture\ndef new_fixture():\n    return 1\n\n\
+         @pytest.fixture\ndef second_fixture():\n    return 2\n"
+    );
+
+    backend
+        .did_close(DidCloseTextDocumentParams {
+            text_document: TextDocumentIdentifier { uri: file_uri },
+        })
+        .await;
+    assert!(!backend.documents.contains_key(&file_path));
+}
+
 #[tokio::test]
 #[timeout(30000)]
 async fn test_did_change_with_empty_content_changes_is_noop() {
@@ -610,6 +759,347 @@ async fn test_did_change_watched_files_multiple_events() {
     backend.did_change_watched_files(params).await;
 }
 
+// ── did_change_configuration ──────────────────────────────────────────────
+
+/// Initialize `backend` on `root` and wait for the workspace scan to finish.
+async fn initialize_and_scan(backend: &Backend, root: &std::path::Path) {
+    let params = InitializeParams {
+        workspace_folders: Some(vec![WorkspaceFolder {
+            uri: Uri::from_file_path(root).expect("root URI"),
+            name: "ws".to_string(),
+        }]),
+        ..Default::default()
+    };
+    backend.initialize(params).await.unwrap();
+    wait_for_scan(backend).await;
+}
+
+async fn wait_for_scan(backend: &Backend) {
+    let handle = backend.scan_task.lock().await.take();
+    handle.expect("scan task").await.unwrap();
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_did_change_configuration_applies_settings() {
+    use pytest_language_server::config::RuleSeverity;
+
+    let tmp = tempfile::tempdir().expect("tempdir");
+    std::fs::write(
+        tmp.path().join("pyproject.toml"),
+        "[tool.pytest-language-server.severity]\nPTL002 = \"error\"\n",
+    )
+    .unwrap();
+    let backend = make_backend();
+    initialize_and_scan(&backend, tmp.path()).await;
+
+    // Settings pushed under our section are applied on top of pyproject.toml
+    backend
+        .did_change_configuration(DidChangeConfigurationParams {
+            settings: serde_json::json!({
+                "pytest-language-server": { "severity": { "PTL001": "off" } }
+            }),
+        })
+        .await;
+    {
+        let config = backend.config.read().await;
+        assert_eq!(config.rule_severity("PTL001"), Some(RuleSeverity::Off));
+        assert_eq!(config.rule_severity("PTL002"), Some(RuleSeverity::Error));
+        assert!(config.is_diagnostic_disabled("undeclared-fixture"));
+    }
+
+    // A null payload keeps the current settings
+    backend
+        .did_change_configuration(DidChangeConfigurationParams {
+            settings: serde_json::Value::Null,
+        })
+        .await;
+    assert_eq!(
+        backend.config.read().await.rule_severity("PTL001"),
+        Some(RuleSeverity::Off)
+    );
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_initialization_options_merged_with_configuration() {
+    use pytest_language_server::config::RuleSeverity;
+
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let backend = make_backend();
+    let params = InitializeParams {
+        workspace_folders: Some(vec![WorkspaceFolder {
+            uri: Uri::from_file_path(tmp.path()).expect("root URI"),
+            name: "ws".to_string(),
+        }]),
+        initialization_options: Some(serde_json::json!({
+            "severity": { "PTL001": "hint", "PTL002": "error" },
+            "disabled_diagnostics": ["circular-dependency"]
+        })),
+        ..Default::default()
+    };
+    backend.initialize(params).await.unwrap();
+    wait_for_scan(&backend).await;
+    assert_eq!(
+        backend.config.read().await.rule_severity("PTL001"),
+        Some(RuleSeverity::Hint)
+    );
+
+    // Configuration sent later is applied on top of the initializationOptions
+    backend
+        .did_change_configuration(DidChangeConfigurationParams {
+            settings: serde_json::json!({ "severity": { "PTL002": "off" } }),
+        })
+        .await;
+    let config = backend.config.read().await;
+    assert_eq!(config.rule_severity("PTL001"), Some(RuleSeverity::Hint));
+    assert_eq!(config.rule_severity("PTL002"), Some(RuleSeverity::Off));
+    assert!(config.is_diagnostic_disabled("PTL003"));
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_did_change_configuration_rescans_on_exclude_change() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    for (dir, fixture) in [
+        ("tests", "kept_fixture"),
+        ("generated", "generated_fixture"),
+    ] {
+        std::fs::create_dir_all(tmp.path().join(dir)).unwrap();
+        std::fs::write(
+            tmp.path().join(dir).join("conftest.py"),
+            format!(
+                "import pytest\n\n@pytest.fixture\ndef {}():\n    pass\n",
+                fixture
+            ),
+        )
+        .unwrap();
+    }
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+    initialize_and_scan(&backend, tmp.path()).await;
+    assert!(db.definitions.contains_key("generated_fixture"));
+
+    backend
+        .did_change_configuration(DidChangeConfigurationParams {
+            settings: serde_json::json!({ "exclude": ["generated"] }),
+        })
+        .await;
+    wait_for_scan(&backend).await;
+
+    assert!(db.definitions.contains_key("kept_fixture"));
+    assert!(
+        !db.definitions.contains_key("generated_fixture"),
+        "excluded directory should be dropped by the rescan"
+    );
+}
+
+// ── did_change_workspace_folders ──────────────────────────────────────────
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_did_change_workspace_folders_scans_and_evicts() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let root = tmp.path().canonicalize().unwrap();
+    for (dir, fixture) in [("first", "first_fixture"), ("second", "second_fixture")] {
+        std::fs::create_dir_all(root.join(dir)).unwrap();
+        std::fs::write(
+            root.join(dir).join("conftest.py"),
+            format!(
+                "import pytest\n\n@pytest.fixture\ndef {}():\n    pass\n",
+                fixture
+            ),
+        )
+        .unwrap();
+    }
+    let folder = |dir: &str| WorkspaceFolder {
+        uri: Uri::from_file_path(root.join(dir)).expect("folder URI"),
+        name: dir.to_string(),
+    };
+
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+    let result = backend
+        .initialize(InitializeParams {
+            workspace_folders: Some(vec![folder("first")]),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let folders_capability = result
+        .capabilities
+        .workspace
+        .and_then(|w| w.workspace_folders)
+        .expect("workspace folders capability");
+    assert_eq!(
+        folders_capability.change_notifications,
+        Some(OneOf::Left(true))
+    );
+    wait_for_scan(&backend).await;
+    assert!(!db.definitions.contains_key("second_fixture"));
+
+    // An added folder is scanned in the background
+    backend
+        .did_change_workspace_folders(DidChangeWorkspaceFoldersParams {
+            event: WorkspaceFoldersChangeEvent {
+                added: vec![folder("second")],
+                removed: vec![],
+            },
+        })
+        .await;
+    wait_for_scan(&backend).await;
+    assert!(db.definitions.contains_key("first_fixture"));
+    assert!(db.definitions.contains_key("second_fixture"));
+
+    // A removed folder's fixtures are forgotten, and the next folder becomes
+    // the workspace root
+    backend
+        .did_change_workspace_folders(DidChangeWorkspaceFoldersParams {
+            event: WorkspaceFoldersChangeEvent {
+                added: vec![],
+                removed: vec![folder("first")],
+            },
+        })
+        .await;
+    assert!(!db.definitions.contains_key("first_fixture"));
+    assert!(db.definitions.contains_key("second_fixture"));
+    assert!(db
+        .file_cache
+        .iter()
+        .all(|entry| !entry.key().starts_with(root.join("first"))));
+    assert_eq!(
+        *backend.workspace_root.read().await,
+        Some(root.join("second"))
+    );
+    assert_eq!(*backend.scan_roots.read().await, vec![root.join("second")]);
+}
+
+// ── native file watcher ───────────────────────────────────────────────────
+
+/// Poll `condition` until it holds, for changes picked up asynchronously.
+async fn eventually(condition: impl Fn() -> bool) -> bool {
+    for _ in 0..100 {
+        if condition() {
+            return true;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+    condition()
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_file_watcher_reindexes_changes_on_disk() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let root = tmp.path().canonicalize().unwrap();
+    let conftest = root.join("conftest.py");
+    std::fs::write(
+        &conftest,
+        "import pytest\n\n@pytest.fixture\ndef before_checkout():\n    pass\n",
+    )
+    .unwrap();
+
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+    initialize_and_scan(&backend, &root).await;
+    assert!(db.definitions.contains_key("before_checkout"));
+    assert!(backend.file_watcher.lock().unwrap().is_some());
+
+    // A file rewritten outside the editor is re-analyzed
+    std::fs::write(
+        &conftest,
+        "import pytest\n\n@pytest.fixture\ndef after_checkout():\n    pass\n",
+    )
+    .unwrap();
+    assert!(eventually(|| db.definitions.contains_key("after_checkout")).await);
+    assert!(!db.definitions.contains_key("before_checkout"));
+
+    // New test files are indexed, deleted ones forgotten
+    let nested = root.join("tests");
+    std::fs::create_dir_all(&nested).unwrap();
+    std::fs::write(
+        nested.join("test_new.py"),
+        "import pytest\n\n@pytest.fixture\ndef generated():\n    pass\n",
+    )
+    .unwrap();
+    assert!(eventually(|| db.definitions.contains_key("generated")).await);
+
+    std::fs::remove_dir_all(&nested).unwrap();
+    assert!(eventually(|| !db.definitions.contains_key("generated")).await);
+
+    backend.shutdown().await.unwrap();
+    assert!(backend.file_watcher.lock().unwrap().is_none());
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_did_change_watched_files_reindexes_test_files_and_config() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let root = tmp.path().canonicalize().unwrap();
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+    initialize_and_scan(&backend, &root).await;
+
+    let notify = |path: &std::path::Path, typ: FileChangeType| DidChangeWatchedFilesParams {
+        changes: vec![FileEvent {
+            uri: Uri::from_file_path(path).unwrap(),
+            typ,
+        }],
+    };
+
+    // Events are handled before the notification returns
+    let test_file = root.join("test_client.py");
+    std::fs::write(
+        &test_file,
+        "import pytest\n\n@pytest.fixture\ndef from_client():\n    pass\n",
+    )
+    .unwrap();
+    backend
+        .did_change_watched_files(notify(&test_file, FileChangeType::CREATED))
+        .await;
+    assert!(db.definitions.contains_key("from_client"));
+
+    std::fs::remove_file(&test_file).unwrap();
+    backend
+        .did_change_watched_files(notify(&test_file, FileChangeType::DELETED))
+        .await;
+    assert!(!db.definitions.contains_key("from_client"));
+
+    // A new configuration file in the workspace root moves pytest's configfile
+    let tox_ini = root.join("tox.ini");
+    std::fs::write(&tox_ini, "[pytest]\naddopts = -ra\n").unwrap();
+    backend
+        .did_change_watched_files(notify(&tox_ini, FileChangeType::CREATED))
+        .await;
+    assert_eq!(db.pytest_root().and_then(|r| r.inifile), Some(tox_ini));
+
+    backend.shutdown().await.unwrap();
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_work_done_progress_cancel_stops_scan() {
+    use pytest_language_server::ScanProgress;
+
+    let backend = make_backend();
+    let progress = Arc::new(ScanProgress::default());
+    let token = NumberOrString::String("pytest-language-server/scan/test".to_string());
+    *backend.scan_progress.lock().unwrap() = Some((token.clone(), Arc::clone(&progress)));
+
+    // Other progress tokens are not ours
+    backend
+        .handle_work_done_progress_cancel(WorkDoneProgressCancelParams {
+            token: NumberOrString::Number(1),
+        })
+        .await;
+    assert!(!progress.is_cancelled());
+
+    backend
+        .handle_work_done_progress_cancel(WorkDoneProgressCancelParams { token })
+        .await;
+    assert!(progress.is_cancelled());
+}
+
 // ── did_close ─────────────────────────────────────────────────────────────
 
 #[tokio::test]
@@ -848,37 +1338,94 @@ async fn test_goto_implementation_from_usage_resolves_to_yield() {
     assert_eq!(loc.range.start.line, 4);
 }
 
-// ── hover ─────────────────────────────────────────────────────────────────
-
 #[tokio::test]
 #[timeout(30000)]
-async fn test_hover_returns_none_on_empty_file() {
-    let backend = make_backend();
-    let result = backend
-        .hover(HoverParams {
-            text_document_position_params: tdp(turi("test_ls_hover", "test.py"), 0, 0),
-            work_done_progress_params: wdp(),
-        })
-        .await;
-    assert!(result.is_ok());
-    assert!(result.unwrap().is_none(), "no hover for unknown position");
-}
+async fn test_goto_implementation_on_definition_lists_override_chain() {
+    use tower_lsp_server::ls_types::request::GotoImplementationResponse;
 
-#[tokio::test]
-#[timeout(30000)]
-async fn test_hover_returns_content_for_known_fixture() {
     let db = Arc::new(FixtureDatabase::new());
     let backend = make_backend_with_db(Arc::clone(&db));
 
-    let conftest_path = tfile("test_ls_hover2", "conftest.py");
-    db.analyze_file(
-        conftest_path.clone(),
-        "import pytest\n\n@pytest.fixture\ndef hover_fixture():\n    \"\"\"Hover docstring.\"\"\"\n    return 1\n",
-    );
-    let conftest_uri = turi("test_ls_hover2", "conftest.py");
-    backend
-        .uri_cache
-        .insert(conftest_path.clone(), conftest_uri.clone());
+    let fixture = "import pytest\n\n@pytest.fixture\ndef db():\n    yield 1\n";
+    let root = tfile("test_ls_impl_overrides", "conftest.py");
+    let api = tfile("test_ls_impl_overrides", "api/conftest.py");
+    let api_v2 = tfile("test_ls_impl_overrides", "api/v2/conftest.py");
+    let other = tfile("test_ls_impl_overrides", "other/conftest.py");
+    for path in [&root, &api, &api_v2, &other] {
+        db.analyze_file(path.clone(), fixture);
+    }
+    let api_uri = turi("test_ls_impl_overrides", "api/conftest.py");
+    backend.uri_cache.insert(api.clone(), api_uri.clone());
+
+    // On the middle definition: the parent it overrides, then its override.
+    let result = backend
+        .goto_implementation(GotoImplementationParams {
+            text_document_position_params: tdp(api_uri, 3, 5),
+            work_done_progress_params: wdp(),
+            partial_result_params: prp(),
+        })
+        .await
+        .unwrap()
+        .expect("should resolve");
+    let GotoImplementationResponse::Array(locations) = result else {
+        panic!("expected a list of overrides, got {:?}", result);
+    };
+    let paths: Vec<PathBuf> = locations
+        .iter()
+        .map(|loc| loc.uri.to_file_path().unwrap().into_owned())
+        .collect();
+    assert_eq!(paths, vec![root.clone(), api_v2]);
+    assert_eq!(locations[0].range, rng(3, 4, 3, 6));
+
+    // The root definition lists every override below it, in any branch.
+    let root_uri = turi("test_ls_impl_overrides", "conftest.py");
+    backend.uri_cache.insert(root, root_uri.clone());
+    let result = backend
+        .goto_implementation(GotoImplementationParams {
+            text_document_position_params: tdp(root_uri, 3, 5),
+            work_done_progress_params: wdp(),
+            partial_result_params: prp(),
+        })
+        .await
+        .unwrap()
+        .expect("should resolve");
+    let GotoImplementationResponse::Array(locations) = result else {
+        panic!("expected a list of overrides, got {:?}", result);
+    };
+    assert_eq!(locations.len(), 3);
+}
+
+// ── hover ─────────────────────────────────────────────────────────────────
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_hover_returns_none_on_empty_file() {
+    let backend = make_backend();
+    let result = backend
+        .hover(HoverParams {
+            text_document_position_params: tdp(turi("test_ls_hover", "test.py"), 0, 0),
+            work_done_progress_params: wdp(),
+        })
+        .await;
+    assert!(result.is_ok());
+    assert!(result.unwrap().is_none(), "no hover for unknown position");
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_hover_returns_content_for_known_fixture() {
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+
+    let conftest_path = tfile("test_ls_hover2", "conftest.py");
+    db.analyze_file(
+        conftest_path.clone(),
+        "import pytest\n\n@pytest.fixture\ndef hover_fixture():\n    \"\"\"Hover docstring.\"\"\"\n    return 1\n",
+    );
+    let conftest_uri = turi("test_ls_hover2", "conftest.py");
+    backend
+        .uri_cache
+        .insert(conftest_path.clone(), conftest_uri.clone());
 
     let test_path = tfile("test_ls_hover2", "test_example.py");
     db.analyze_file(test_path, "def test_it(hover_fixture):\n    pass\n");
@@ -892,6 +1439,61 @@ async fn test_hover_returns_content_for_known_fixture() {
     assert!(result.is_ok());
 }
 
+#[tokio::test]
+#[timeout(30000)]
+async fn test_hover_marker_description() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let root = tmp.path().canonicalize().unwrap();
+    std::fs::write(
+        root.join("pytest.ini"),
+        "[pytest]\nmarkers =\n    slow: marks tests as slow\n",
+    )
+    .unwrap();
+    let test_path = root.join("test_x.py");
+    std::fs::write(
+        &test_path,
+        "import pytest\n\n@pytest.mark.slow\n@pytest.mark.xfail(reason=\"bug\")\n@pytest.mark.typo\ndef test_x():\n    pass\n",
+    )
+    .unwrap();
+
+    let db = Arc::new(FixtureDatabase::new());
+    db.scan_workspace(&root);
+    let backend = make_backend_with_db(Arc::clone(&db));
+    *backend.workspace_root.write().await = Some(root.clone());
+    let uri = Uri::from_file_path(&test_path).unwrap();
+
+    let hover = |line: u32, character: u32| {
+        backend.hover(HoverParams {
+            text_document_position_params: tdp(uri.clone(), line, character),
+            work_done_progress_params: wdp(),
+        })
+    };
+    let markdown = |hover: Option<Hover>| match hover.expect("hover").contents {
+        HoverContents::Markup(markup) => markup.value,
+        other => panic!("expected markdown, got {:?}", other),
+    };
+
+    let result = hover(2, 15).await.unwrap();
+    assert_eq!(result.as_ref().unwrap().range, Some(rng(2, 13, 2, 17)));
+    let value = markdown(result);
+    assert!(value.contains("**from** `pytest.ini`"), "got: {}", value);
+    assert!(value.contains("marks tests as slow"), "got: {}", value);
+
+    let value = markdown(hover(3, 14).await.unwrap());
+    assert!(
+        value.contains("@pytest.mark.xfail(condition"),
+        "got: {}",
+        value
+    );
+    assert!(value.contains("expected failure"), "got: {}", value);
+
+    let value = markdown(hover(4, 14).await.unwrap());
+    assert!(value.contains("Not a registered marker"), "got: {}", value);
+
+    // `pytest.mark` itself is not a marker
+    assert!(hover(2, 9).await.unwrap().is_none());
+}
+
 // ── references ────────────────────────────────────────────────────────────
 
 #[tokio::test]
@@ -1105,6 +1707,312 @@ async fn test_completion_returns_ok() {
     assert!(result.is_ok());
 }
 
+#[tokio::test]
+#[timeout(30000)]
+async fn test_completion_fixture_skeleton() {
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+
+    let content = "import pytest\n\nfix\n\n@pytest.fixture\n\n\ndef test_x():\n    pass\n";
+    let path = tfile("test_ls_compl_skeleton", "conftest.py");
+    db.analyze_file(path.clone(), content);
+    let uri = turi("test_ls_compl_skeleton", "conftest.py");
+    backend.uri_cache.insert(path, uri.clone());
+
+    let complete = |line: u32, character: u32| {
+        backend.completion(CompletionParams {
+            text_document_position: tdp(uri.clone(), line, character),
+            work_done_progress_params: wdp(),
+            partial_result_params: prp(),
+            context: None,
+        })
+    };
+    let skeleton = |response: Option<CompletionResponse>| {
+        let Some(CompletionResponse::Array(items)) = response else {
+            panic!("expected an array of completions");
+        };
+        assert_eq!(items.len(), 1);
+        let item = items.into_iter().next().unwrap();
+        assert_eq!(item.kind, Some(CompletionItemKind::SNIPPET));
+        let Some(CompletionTextEdit::Edit(edit)) = item.text_edit else {
+            panic!("expected a text edit");
+        };
+        (edit, item.insert_text_format)
+    };
+
+    // Without snippet support (the default) the skeleton is plain text
+    let (edit, format) = skeleton(complete(2, 3).await.unwrap());
+    assert_eq!(format, Some(InsertTextFormat::PLAIN_TEXT));
+    assert_eq!(edit.range, rng(2, 0, 2, 3), "replaces the typed prefix");
+    assert_eq!(
+        edit.new_text,
+        "@pytest.fixture\ndef fixture_name() -> None:\n    pass"
+    );
+
+    let mut params = InitializeParams::default();
+    params.capabilities.text_document = Some(TextDocumentClientCapabilities {
+        completion: Some(CompletionClientCapabilities {
+            completion_item: Some(CompletionItemCapability {
+                snippet_support: Some(true),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        ..Default::default()
+    });
+    backend.initialize(params).await.unwrap();
+
+    let (edit, format) = skeleton(complete(2, 3).await.unwrap());
+    assert_eq!(format, Some(InsertTextFormat::SNIPPET));
+    assert!(edit.new_text.starts_with("@pytest.fixture(scope="));
+    assert!(edit.new_text.contains("${1:fixture_name}"));
+
+    // Below a fixture decorator only the function is inserted
+    let (edit, _) = skeleton(complete(5, 0).await.unwrap());
+    assert!(edit.new_text.starts_with("def ${1:fixture_name}()"));
+
+    // Inside a function there is no skeleton
+    let result = complete(8, 4).await.unwrap();
+    assert!(!matches!(
+        result,
+        Some(CompletionResponse::Array(ref items)) if items.iter().any(|i| i.kind == Some(CompletionItemKind::SNIPPET))
+    ));
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_completion_marker_names() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let root = tmp.path().canonicalize().unwrap();
+    std::fs::write(
+        root.join("pytest.ini"),
+        "[pytest]\nmarkers =\n    slow: marks tests as slow\n    env(name): run on an env\n",
+    )
+    .unwrap();
+    let test_path = root.join("test_x.py");
+    std::fs::write(
+        &test_path,
+        "import pytest\n\n@pytest.mark.sl\ndef test_x():\n    pass\n",
+    )
+    .unwrap();
+
+    let db = Arc::new(FixtureDatabase::new());
+    db.scan_workspace(&root);
+    let backend = make_backend_with_db(Arc::clone(&db));
+    let uri = Uri::from_file_path(&test_path).unwrap();
+
+    let response = backend
+        .completion(CompletionParams {
+            text_document_position: tdp(uri.clone(), 2, 15),
+            work_done_progress_params: wdp(),
+            partial_result_params: prp(),
+            context: Some(CompletionContext {
+                trigger_kind: CompletionTriggerKind::INVOKED,
+                trigger_character: None,
+            }),
+        })
+        .await
+        .unwrap();
+    let Some(CompletionResponse::Array(items)) = response else {
+        panic!("expected an array of completions");
+    };
+    let labels: Vec<&str> = items.iter().map(|i| i.label.as_str()).collect();
+    for name in [
+        "slow",
+        "env",
+        "skip",
+        "skipif",
+        "xfail",
+        "parametrize",
+        "usefixtures",
+    ] {
+        assert!(labels.contains(&name), "{} missing from {:?}", name, labels);
+    }
+
+    let slow = items.iter().find(|i| i.label == "slow").unwrap();
+    assert_eq!(slow.detail.as_deref(), Some("pytest.ini"));
+    let Some(CompletionTextEdit::Edit(edit)) = &slow.text_edit else {
+        panic!("expected a text edit");
+    };
+    assert_eq!(edit.range, rng(2, 13, 2, 15), "replaces the typed name");
+    let Some(Documentation::MarkupContent(docs)) = &slow.documentation else {
+        panic!("expected markdown documentation");
+    };
+    assert!(docs.value.contains("marks tests as slow"));
+
+    let env = items.iter().find(|i| i.label == "env").unwrap();
+    let Some(Documentation::MarkupContent(docs)) = &env.documentation else {
+        panic!("expected markdown documentation");
+    };
+    assert!(docs.value.contains("@pytest.mark.env(name)"));
+
+    // "." elsewhere doesn't trigger fixture completions
+    let response = backend
+        .completion(CompletionParams {
+            text_document_position: tdp(uri, 4, 4),
+            work_done_progress_params: wdp(),
+            partial_result_params: prp(),
+            context: Some(CompletionContext {
+                trigger_kind: CompletionTriggerKind::TRIGGER_CHARACTER,
+                trigger_character: Some(".".to_string()),
+            }),
+        })
+        .await
+        .unwrap();
+    assert!(response.is_none());
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_completion_settings() {
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+
+    db.analyze_file(
+        tfile("test_ls_compl_settings", "conftest.py"),
+        "import pytest\n\n@pytest.fixture\ndef db():\n    pass\n\n@pytest.fixture\ndef database():\n    pass\n",
+    );
+    db.analyze_file(
+        tfile(
+            "test_ls_compl_settings",
+            "venv/lib/python3.12/site-packages/pytest_mock/plugin.py",
+        ),
+        "import pytest\n\n@pytest.fixture\ndef mocker():\n    pass\n",
+    );
+    let uri = turi("test_ls_compl_settings", "test_x.py");
+    open_file(
+        &backend,
+        uri.clone(),
+        "import pytest\n\n@pytest.mark.usefixtures(\"\")\ndef test_x(db):\n    da\n",
+    )
+    .await;
+
+    let complete = |line: u32, character: u32| {
+        backend.completion(CompletionParams {
+            text_document_position: tdp(uri.clone(), line, character),
+            work_done_progress_params: wdp(),
+            partial_result_params: prp(),
+            context: None,
+        })
+    };
+    let items = |response: Option<CompletionResponse>| match response {
+        Some(CompletionResponse::Array(items)) => items,
+        other => panic!("expected an array of completions, got {:?}", other),
+    };
+    let labels = |items: &[CompletionItem]| -> Vec<String> {
+        items.iter().map(|item| item.label.clone()).collect()
+    };
+
+    // Defaults: everything is offered, body completions add the parameter
+    let body = items(complete(4, 6).await.unwrap());
+    assert!(labels(&body).contains(&"mocker".to_string()));
+    let database = body.iter().find(|item| item.label == "database").unwrap();
+    assert!(database.additional_text_edits.is_some());
+    assert!(!items(complete(2, 26).await.unwrap()).is_empty());
+
+    {
+        let mut config = backend.config.write().await;
+        config.completion.auto_add_parameter = false;
+        config.completion.decorator_strings = false;
+        config.completion.third_party = false;
+    }
+    let body = items(complete(4, 6).await.unwrap());
+    let labels_now = labels(&body);
+    assert!(!labels_now.contains(&"mocker".to_string()));
+    assert!(
+        labels_now.contains(&"tmp_path".to_string()),
+        "builtins are kept"
+    );
+    assert!(body.iter().all(|item| item.additional_text_edits.is_none()));
+    assert!(complete(2, 26).await.unwrap().is_none());
+
+    // The best matches for the typed word, in a list completed on typing
+    backend.config.write().await.completion.max_items = Some(1);
+    let Some(CompletionResponse::List(list)) = complete(4, 6).await.unwrap() else {
+        panic!("expected a completion list");
+    };
+    assert!(list.is_incomplete);
+    assert_eq!(labels(&list.items), vec!["database".to_string()]);
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_completion_resolve_fills_documentation() {
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+    *backend.workspace_root.write().await = Some(tfile("test_ls_compl_resolve", ""));
+
+    let conftest = r#"import pytest
+
+@pytest.fixture
+def db():
+    """Database connection."""
+    pass
+"#;
+    db.analyze_file(tfile("test_ls_compl_resolve", "conftest.py"), conftest);
+    let test_path = tfile("test_ls_compl_resolve", "test_x.py");
+    db.analyze_file(test_path.clone(), "def test_x():\n    pass\n");
+    let uri = turi("test_ls_compl_resolve", "test_x.py");
+    backend.uri_cache.insert(test_path, uri.clone());
+
+    let response = backend
+        .completion(CompletionParams {
+            text_document_position: tdp(uri, 0, 11),
+            work_done_progress_params: wdp(),
+            partial_result_params: prp(),
+            context: None,
+        })
+        .await
+        .unwrap()
+        .expect("completions");
+    let CompletionResponse::Array(items) = response else {
+        panic!("expected an array of completions");
+    };
+
+    // Documentation is only computed on resolve
+    assert!(items.iter().all(|item| item.documentation.is_none()));
+
+    let documentation = |item: CompletionItem| match item.documentation {
+        Some(Documentation::MarkupContent(markup)) => markup.value,
+        other => panic!("expected markdown documentation, got {:?}", other),
+    };
+    let item = items.iter().find(|i| i.label == "db").expect("db item");
+    let resolved = backend.completion_resolve(item.clone()).await.unwrap();
+    let docs = documentation(resolved);
+    assert!(docs.contains("**from** `conftest.py`"), "got: {}", docs);
+    assert!(docs.contains("Database connection."), "got: {}", docs);
+
+    // Builtins synthesized without a venv resolve too
+    let item = items
+        .iter()
+        .find(|i| i.label == "tmp_path")
+        .expect("tmp_path item");
+    let resolved = backend.completion_resolve(item.clone()).await.unwrap();
+    assert!(documentation(resolved).contains("tmp_path"));
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_completion_resolve_unknown_item_unchanged() {
+    let backend = make_backend();
+    let item = CompletionItem {
+        label: "gone".to_string(),
+        data: Some(serde_json::json!({"name": "gone", "path": "/nowhere/conftest.py", "line": 3})),
+        ..Default::default()
+    };
+    let resolved = backend.completion_resolve(item.clone()).await.unwrap();
+    assert_eq!(resolved, item);
+
+    let plain = CompletionItem {
+        label: "plain".to_string(),
+        ..Default::default()
+    };
+    assert_eq!(
+        backend.completion_resolve(plain.clone()).await.unwrap(),
+        plain
+    );
+}
+
 // ── code_action ───────────────────────────────────────────────────────────
 
 #[tokio::test]
@@ -1352,6 +2260,525 @@ async fn test_code_lens_skips_third_party_fixtures() {
     );
 }
 
+#[tokio::test]
+#[timeout(30000)]
+async fn test_code_lens_run_test_lenses_in_test_file() {
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+    *backend.workspace_root.write().await = Some(tfile("test_ls_lens_run", ""));
+
+    let test_path = tfile("test_ls_lens_run", "tests/test_example.py");
+    db.analyze_file(
+        test_path.clone(),
+        "def test_one():\n    pass\n\nclass TestGroup:\n    def test_two(self):\n        pass\n",
+    );
+    let test_uri = turi("test_ls_lens_run", "tests/test_example.py");
+    backend.uri_cache.insert(test_path, test_uri.clone());
+
+    let lenses = backend
+        .code_lens(CodeLensParams {
+            text_document: TextDocumentIdentifier { uri: test_uri },
+            work_done_progress_params: wdp(),
+            partial_result_params: prp(),
+        })
+        .await
+        .unwrap()
+        .expect("should return lenses");
+
+    let runs: Vec<(u32, &str, &str)> = lenses
+        .iter()
+        .filter_map(|l| {
+            let command = l.command.as_ref()?;
+            (command.command == "pytest-lsp.runTest").then(|| {
+                let args = command.arguments.as_ref().unwrap();
+                (
+                    l.range.start.line,
+                    command.title.as_str(),
+                    args[0].as_str().unwrap(),
+                )
+            })
+        })
+        .collect();
+    assert_eq!(
+        runs,
+        vec![
+            (0, "▶ Run file", "tests/test_example.py"),
+            (0, "▶ Run test", "tests/test_example.py::test_one"),
+            (3, "▶ Run class", "tests/test_example.py::TestGroup"),
+            (
+                4,
+                "▶ Run test",
+                "tests/test_example.py::TestGroup::test_two"
+            ),
+        ]
+    );
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_execute_command_rejects_unknown_command_and_missing_node_id() {
+    let backend = make_backend();
+
+    let unknown = backend
+        .execute_command(ExecuteCommandParams {
+            command: "pytest-lsp.unknown".to_string(),
+            arguments: vec![],
+            work_done_progress_params: wdp(),
+        })
+        .await;
+    assert!(unknown.is_err());
+
+    let missing = backend
+        .execute_command(ExecuteCommandParams {
+            command: "pytest-lsp.runTest".to_string(),
+            arguments: vec![],
+            work_done_progress_params: wdp(),
+        })
+        .await;
+    assert!(missing.is_err());
+
+    // Options are not node IDs
+    for option in ["-p", "--pyargs"] {
+        let rejected = backend
+            .execute_command(ExecuteCommandParams {
+                command: "pytest-lsp.runTest".to_string(),
+                arguments: vec![serde_json::json!(option)],
+                work_done_progress_params: wdp(),
+            })
+            .await;
+        assert!(rejected.is_err(), "{} should be rejected", option);
+    }
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_execute_command_exports_fixture_graph() {
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+    *backend.workspace_root.write().await = Some(tfile("test_ls_graph", ""));
+
+    db.analyze_file(
+        tfile("test_ls_graph", "conftest.py"),
+        "import pytest\n\n@pytest.fixture\ndef db():\n    pass\n\n@pytest.fixture\ndef user(db):\n    pass\n",
+    );
+    db.analyze_file(
+        tfile("test_ls_graph", "test_a.py"),
+        "def test_user(user):\n    pass\n",
+    );
+
+    let export = |arguments: Vec<serde_json::Value>| {
+        backend.execute_command(ExecuteCommandParams {
+            command: "pytest-lsp.exportFixtureGraph".to_string(),
+            arguments,
+            work_done_progress_params: wdp(),
+        })
+    };
+
+    let dot = export(vec![]).await.unwrap().unwrap();
+    assert_eq!(
+        dot.as_str().unwrap(),
+        "digraph fixtures {\n    rankdir=LR;\n    n0 [label=\"db\\nconftest.py\"];\n    n1 [label=\"user\\nconftest.py\"];\n    n1 -> n0;\n}\n"
+    );
+
+    let mermaid = export(vec!["mermaid".into(), "db".into()])
+        .await
+        .unwrap()
+        .unwrap();
+    let mermaid = mermaid.as_str().unwrap();
+    assert!(mermaid.starts_with("flowchart LR\n"), "{}", mermaid);
+    assert!(mermaid.contains("n2[\"test_user<br/><small>test_a.py::test_user</small>\"]"));
+    assert!(mermaid.contains("n2 --> n1"), "{}", mermaid);
+    assert!(mermaid.contains("style n0 stroke-width:3px"), "{}", mermaid);
+
+    assert!(export(vec!["svg".into()]).await.is_err());
+    assert!(export(vec!["dot".into(), "missing".into()]).await.is_err());
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_execute_command_explains_fixture_resolution() {
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+    *backend.workspace_root.write().await = Some(tfile("test_ls_explain", ""));
+
+    let fixture = "import pytest\n\n@pytest.fixture\ndef db():\n    pass\n";
+    db.analyze_file(tfile("test_ls_explain", "conftest.py"), fixture);
+    db.analyze_file(tfile("test_ls_explain", "tests/conftest.py"), fixture);
+    db.analyze_file(tfile("test_ls_explain", "other/conftest.py"), fixture);
+    db.analyze_file(
+        tfile(
+            "test_ls_explain",
+            "venv/lib/site-packages/plugin/fixtures.py",
+        ),
+        fixture,
+    );
+    let test_path = tfile("test_ls_explain", "tests/api/test_x.py");
+    db.analyze_file(test_path.clone(), "def test_x(db):\n    pass\n");
+    let test_uri = turi("test_ls_explain", "tests/api/test_x.py");
+    backend.uri_cache.insert(test_path, test_uri.clone());
+
+    let explain = |character: u32| {
+        backend.execute_command(ExecuteCommandParams {
+            command: "pytest-lsp.explainResolution".to_string(),
+            arguments: vec![serde_json::to_value(TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: test_uri.clone(),
+                },
+                position: pos(0, character),
+            })
+            .unwrap()],
+            work_done_progress_params: wdp(),
+        })
+    };
+
+    let text = explain(12).await.unwrap().expect("explanation");
+    let sep = std::path::MAIN_SEPARATOR;
+    let expected = format!(
+        "Fixture 'db' requested at tests{sep}api{sep}test_x.py:1\n\
+         \n\
+         Candidates, in pytest's lookup order:\n\
+         \x20 ✔ tests{sep}conftest.py:4 [conftest.py 1 directory up] selected\n\
+         \x20 ✘ conftest.py:4 [conftest.py 2 directories up] shadowed by tests{sep}conftest.py:4\n\
+         \x20 ✘ venv{sep}lib{sep}site-packages{sep}plugin{sep}fixtures.py:4 [third-party (site-packages)] shadowed by tests{sep}conftest.py:4\n\
+         \x20 ✘ other{sep}conftest.py:4 [not visible from this file (not in the same file, a parent conftest.py or a plugin)] skipped\n\
+         \n\
+         Resolved to tests{sep}conftest.py:4: the closest conftest.py above the requesting file wins.\n"
+    );
+    assert_eq!(text.as_str().unwrap(), expected);
+
+    // Not on a fixture usage
+    assert_eq!(explain(2).await.unwrap(), None);
+
+    let missing = backend
+        .execute_command(ExecuteCommandParams {
+            command: "pytest-lsp.explainResolution".to_string(),
+            arguments: vec![],
+            work_done_progress_params: wdp(),
+        })
+        .await;
+    assert!(missing.is_err());
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_execute_command_lists_autouse_fixtures() {
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+    *backend.workspace_root.write().await = Some(tfile("test_ls_autouse", ""));
+
+    db.analyze_file(
+        tfile("test_ls_autouse", "conftest.py"),
+        "import pytest\n\n@pytest.fixture(autouse=True, scope=\"session\")\ndef setup_env():\n    yield\n",
+    );
+    db.analyze_file(
+        tfile(
+            "test_ls_autouse",
+            "venv/lib/site-packages/plugin/fixtures.py",
+        ),
+        "import pytest\n\n@pytest.fixture(autouse=True)\ndef plugin_reset():\n    yield\n",
+    );
+    let content = "import pytest\n\n@pytest.fixture(autouse=True)\ndef clean_db():\n    yield\n\n\
+class TestApi:\n    @pytest.fixture(autouse=True)\n    def login(self):\n        yield\n\n    def test_get(self):\n        pass\n\n\
+class TestOther:\n    @pytest.fixture(autouse=True)\n    def other(self):\n        yield\n";
+    let test_path = tfile("test_ls_autouse", "tests/test_api.py");
+    db.analyze_file(test_path.clone(), content);
+    let test_uri = turi("test_ls_autouse", "tests/test_api.py");
+    backend.uri_cache.insert(test_path, test_uri.clone());
+
+    let list = |line: u32, character: u32| {
+        backend.execute_command(ExecuteCommandParams {
+            command: "pytest-lsp.listAutouseFixtures".to_string(),
+            arguments: vec![serde_json::to_value(TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: test_uri.clone(),
+                },
+                position: pos(line, character),
+            })
+            .unwrap()],
+            work_done_progress_params: wdp(),
+        })
+    };
+
+    let fixtures = list(11, 8).await.unwrap().expect("autouse fixtures");
+    let fixtures = fixtures.as_array().unwrap();
+    let summary: Vec<(&str, &str, &str)> = fixtures
+        .iter()
+        .map(|f| {
+            (
+                f["name"].as_str().unwrap(),
+                f["scope"].as_str().unwrap(),
+                f["origin"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("clean_db", "function", "same file"),
+            ("login", "function", "class TestApi"),
+            ("plugin_reset", "function", "third-party (site-packages)"),
+            ("setup_env", "session", "conftest.py 1 directory up"),
+        ]
+    );
+    assert_eq!(fixtures[1]["location"]["uri"], test_uri.as_str());
+    assert_eq!(fixtures[1]["location"]["range"]["start"]["line"], 8);
+    assert_eq!(fixtures[1]["location"]["range"]["start"]["character"], 8);
+
+    // Not on a test name
+    assert_eq!(list(3, 4).await.unwrap(), None);
+}
+
+// ── document_highlight ───────────────────────────────────────────────────
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_document_highlight_fixture_occurrences() {
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+
+    let content = r#"import pytest
+
+@pytest.fixture
+def db():
+    pass
+
+@pytest.fixture
+def other(db):
+    db = 1
+    return db
+
+@pytest.mark.usefixtures("db")
+def test_x(request):
+    request.getfixturevalue("db")
+"#;
+    let path = tfile("test_ls_highlight", "test_x.py");
+    db.analyze_file(path.clone(), content);
+    let uri = turi("test_ls_highlight", "test_x.py");
+    backend.uri_cache.insert(path, uri.clone());
+
+    let highlight = |line: u32, character: u32| {
+        backend.document_highlight(DocumentHighlightParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                position: pos(line, character),
+            },
+            work_done_progress_params: wdp(),
+            partial_result_params: prp(),
+        })
+    };
+
+    let expected = vec![
+        (rng(3, 4, 3, 6), DocumentHighlightKind::WRITE),
+        (rng(7, 10, 7, 12), DocumentHighlightKind::READ),
+        (rng(11, 26, 11, 28), DocumentHighlightKind::READ),
+        (rng(13, 29, 13, 31), DocumentHighlightKind::READ),
+    ];
+    // Same result from the definition and a string reference
+    for (line, character) in [(3, 5), (13, 30)] {
+        let highlights: Vec<(Range, DocumentHighlightKind)> = highlight(line, character)
+            .await
+            .unwrap()
+            .expect("highlights")
+            .into_iter()
+            .map(|h| (h.range, h.kind.unwrap()))
+            .collect();
+        assert_eq!(highlights, expected, "from {}:{}", line, character);
+    }
+
+    // From a parameter or any of its uses in the body, the body uses are
+    // highlighted too
+    let mut with_body = expected.clone();
+    with_body.insert(2, (rng(8, 4, 8, 6), DocumentHighlightKind::READ));
+    with_body.insert(3, (rng(9, 11, 9, 13), DocumentHighlightKind::READ));
+    for (line, character) in [(7, 11), (8, 5), (9, 12)] {
+        let highlights: Vec<(Range, DocumentHighlightKind)> = highlight(line, character)
+            .await
+            .unwrap()
+            .expect("highlights")
+            .into_iter()
+            .map(|h| (h.range, h.kind.unwrap()))
+            .collect();
+        assert_eq!(highlights, with_body, "from {}:{}", line, character);
+    }
+
+    // Not on a fixture or a parameter
+    assert_eq!(highlight(12, 5).await.unwrap(), None);
+}
+
+// ── linked_editing_range ─────────────────────────────────────────────────
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_linked_editing_range_fixture_parameter() {
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+
+    let content = r#"import pytest
+
+@pytest.fixture
+def db():
+    pass
+
+def test_x(db, other):
+    check = lambda db: db
+    db.connect()
+    assert db
+
+def helper(db):
+    return db
+"#;
+    let path = tfile("test_ls_linked_editing", "test_x.py");
+    db.analyze_file(path.clone(), content);
+    let uri = turi("test_ls_linked_editing", "test_x.py");
+    backend.uri_cache.insert(path, uri.clone());
+
+    let linked = |line: u32, character: u32| {
+        backend.linked_editing_range(LinkedEditingRangeParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                position: pos(line, character),
+            },
+            work_done_progress_params: wdp(),
+        })
+    };
+
+    let expected = vec![rng(6, 11, 6, 13), rng(8, 4, 8, 6), rng(9, 11, 9, 13)];
+    // Same result from the parameter (either end of it) and from a use
+    for (line, character) in [(6, 11), (6, 13), (8, 5), (9, 12)] {
+        let ranges = linked(line, character)
+            .await
+            .unwrap()
+            .expect("linked editing ranges")
+            .ranges;
+        assert_eq!(ranges, expected, "from {}:{}", line, character);
+    }
+
+    // The lambda parameter shadows the test parameter
+    assert_eq!(linked(7, 22).await.unwrap(), None);
+    // Not a test or fixture
+    assert_eq!(linked(12, 12).await.unwrap(), None);
+}
+
+// ── signature_help ───────────────────────────────────────────────────────
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_signature_help_for_fixture_and_parametrize() {
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+
+    let content = r#"import pytest
+
+@pytest.fixture(scope="session", )
+def db():
+    pass
+
+@pytest.mark.parametrize(
+    "a, b",
+    [(1, 2), ("x,(", 3)],
+    ids=["one", "two"],
+)
+def test_x(a, b):
+    print(len(a))
+"#;
+    let path = tfile("test_ls_sighelp", "test_x.py");
+    db.analyze_file(path.clone(), content);
+    let uri = turi("test_ls_sighelp", "test_x.py");
+    backend.uri_cache.insert(path, uri.clone());
+
+    let help = |line: u32, character: u32| {
+        backend.signature_help(SignatureHelpParams {
+            context: None,
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                position: pos(line, character),
+            },
+            work_done_progress_params: wdp(),
+        })
+    };
+    let active = |result: Option<SignatureHelp>| {
+        result.map(|h| {
+            let sig = &h.signatures[0];
+            let param = h.active_parameter.map(|idx| {
+                match &sig.parameters.as_ref().unwrap()[idx as usize].label {
+                    ParameterLabel::LabelOffsets([start, end]) => {
+                        sig.label[*start as usize..*end as usize].to_string()
+                    }
+                    ParameterLabel::Simple(label) => label.clone(),
+                }
+            });
+            (sig.label.clone(), param)
+        })
+    };
+
+    let fixture_sig =
+        "fixture(*, scope=\"function\", params=None, autouse=False, ids=None, name=None)";
+    // Inside the `scope=` argument
+    assert_eq!(
+        active(help(2, 24).await.unwrap()),
+        Some((
+            fixture_sig.to_string(),
+            Some("scope=\"function\"".to_string())
+        ))
+    );
+    // After the comma: the first keyword not passed yet
+    assert_eq!(
+        active(help(2, 33).await.unwrap()),
+        Some((fixture_sig.to_string(), Some("params=None".to_string())))
+    );
+
+    let parametrize_sig = "parametrize(argnames, argvalues, indirect=False, ids=None, scope=None)";
+    // Positional arguments, including inside the list of values (whose
+    // strings contain commas and brackets)
+    for (line, character, param) in [
+        (7, 6, "argnames"),
+        (8, 5, "argvalues"),
+        (8, 18, "argvalues"),
+        (9, 10, "ids=None"),
+    ] {
+        assert_eq!(
+            active(help(line, character).await.unwrap()),
+            Some((parametrize_sig.to_string(), Some(param.to_string()))),
+            "at {}:{}",
+            line,
+            character
+        );
+    }
+
+    // Other calls and positions outside any call get nothing
+    assert_eq!(active(help(12, 14).await.unwrap()), None);
+    assert_eq!(active(help(11, 0).await.unwrap()), None);
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_signature_help_multibyte_char_before_callee() {
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+
+    // A half-typed line with a pasted character right before the name
+    let content = "import pytest\n\nx = …fixture(\ny = \u{a0}pytest.fixture(\n";
+    let path = tfile("test_ls_sighelp_multibyte", "test_x.py");
+    db.analyze_file(path.clone(), content);
+    let uri = turi("test_ls_sighelp_multibyte", "test_x.py");
+    backend.uri_cache.insert(path, uri.clone());
+
+    for (line, character) in [(2, 13), (3, 20)] {
+        let result = backend
+            .signature_help(SignatureHelpParams {
+                context: None,
+                text_document_position_params: TextDocumentPositionParams {
+                    text_document: TextDocumentIdentifier { uri: uri.clone() },
+                    position: pos(line, character),
+                },
+                work_done_progress_params: wdp(),
+            })
+            .await
+            .unwrap();
+        assert!(result.is_some(), "at {}:{}", line, character);
+    }
+}
+
 // ── inlay_hint ────────────────────────────────────────────────────────────
 
 #[tokio::test]
@@ -1459,7 +2886,7 @@ async fn test_inlay_hint_empty_when_no_fixtures_have_return_type() {
     open_file(
         &backend,
         turi("test_ih_no_rt", "conftest.py"),
-        "import pytest\n\n@pytest.fixture\ndef my_fixture():\n    return 42\n",
+        "import pytest\n\n@pytest.fixture\ndef my_fixture():\n    return compute()\n",
     )
     .await;
 
@@ -1608,7 +3035,7 @@ async fn test_inlay_hint_multiple_fixtures_only_typed_get_hints() {
     open_file(
         &backend,
         turi("test_ih_multi", "conftest.py"),
-        "import pytest\n\n@pytest.fixture\ndef typed_fix() -> str:\n    return 'hi'\n\n@pytest.fixture\ndef untyped_fix():\n    return 42\n",
+        "import pytest\n\n@pytest.fixture\ndef typed_fix() -> str:\n    return 'hi'\n\n@pytest.fixture\ndef untyped_fix():\n    return compute()\n",
     )
     .await;
 
@@ -1637,6 +3064,49 @@ async fn test_inlay_hint_multiple_fixtures_only_typed_get_hints() {
     }
 }
 
+// ── Unannotated fixtures: types inferred from the body ────────────────────
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_inlay_hint_marks_inferred_types() {
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+
+    open_file(
+        &backend,
+        turi("test_ih_inferred", "conftest.py"),
+        "import pytest\n\n@pytest.fixture\ndef client(app):\n    yield TestClient(app)\n\n@pytest.fixture\ndef app():\n    return create_app()\n",
+    )
+    .await;
+
+    open_file(
+        &backend,
+        turi("test_ih_inferred", "test_foo.py"),
+        "def test_foo(client, app):\n    pass\n",
+    )
+    .await;
+
+    let hints = get_hints(
+        &backend,
+        turi("test_ih_inferred", "test_foo.py"),
+        rng(0, 0, 5, 0),
+    )
+    .await;
+
+    assert_eq!(hints.len(), 1, "Only the inferable fixture gets a hint");
+    match &hints[0].label {
+        InlayHintLabel::String(label) => assert_eq!(label, ": ~TestClient"),
+        _ => panic!("Expected String label"),
+    }
+    match &hints[0].tooltip {
+        Some(InlayHintTooltip::String(tooltip)) => assert_eq!(
+            tooltip,
+            "Fixture 'client' returns TestClient (inferred from its body)"
+        ),
+        _ => panic!("Expected String tooltip"),
+    }
+}
+
 // ── File known to the backend but not yet in the usages map ───────────────
 
 #[tokio::test]
@@ -1658,9 +3128,80 @@ async fn test_inlay_hint_returns_none_when_file_has_no_usages() {
     // Either None or Some(empty) is acceptable — the key check is no panic/error
     let inner = result.unwrap();
     assert!(
-        inner.is_none() || inner.unwrap().is_empty(),
-        "Un-analysed file should produce no hints"
+        inner.is_none() || inner.unwrap().is_empty(),
+        "Un-analysed file should produce no hints"
+    );
+}
+
+// ── Origin hints (opt-in) ──────────────────────────────────────────────────
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_inlay_hint_origin_mode() {
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+
+    open_file(
+        &backend,
+        turi("test_ih_origin", "conftest.py"),
+        "import pytest\n\n@pytest.fixture\ndef db() -> int:\n    return 1\n",
+    )
+    .await;
+    db.analyze_file(
+        tfile(
+            "test_ih_origin",
+            "venv/lib/python3.12/site-packages/pytest_mock/plugin.py",
+        ),
+        "import pytest\n\n@pytest.fixture\ndef mocker():\n    pass\n",
+    );
+    let test_uri = turi("test_ih_origin", "tests/api/test_x.py");
+    open_file(
+        &backend,
+        test_uri.clone(),
+        "import pytest\n\n@pytest.fixture\ndef local():\n    pass\n\ndef test_x(db, local, mocker):\n    pass\n",
+    )
+    .await;
+
+    let labels = |hints: Vec<InlayHint>| -> Vec<(u32, String)> {
+        hints
+            .into_iter()
+            .map(|h| match h.label {
+                InlayHintLabel::String(label) => (h.position.character, label),
+                _ => panic!("Expected String label"),
+            })
+            .collect()
+    };
+
+    // Off by default: only the type hint
+    let hints = get_hints(&backend, test_uri.clone(), rng(0, 0, 10, 0)).await;
+    assert_eq!(labels(hints), vec![(13, ": int".to_string())]);
+
+    backend.config.write().await.inlay_hints.origin = true;
+    let hints = get_hints(&backend, test_uri.clone(), rng(0, 0, 10, 0)).await;
+    let origin_tooltip = hints
+        .iter()
+        .find(|h| h.position.character == 13 && h.kind.is_none())
+        .and_then(|h| h.tooltip.clone());
+    assert!(
+        matches!(&origin_tooltip, Some(InlayHintTooltip::String(t))
+            if t.starts_with("Fixture 'db' defined in ") && t.ends_with("conftest.py:4")),
+        "{:?}",
+        origin_tooltip
+    );
+    assert_eq!(
+        labels(hints),
+        vec![
+            (13, ": int".to_string()),
+            (13, "conftest ../..".to_string()),
+            (20, "same file".to_string()),
+            (28, "[plugin pytest-mock]".to_string()),
+        ]
     );
+
+    // Type hints can be turned off independently
+    backend.config.write().await.inlay_hints.types = false;
+    let hints = get_hints(&backend, test_uri, rng(0, 0, 10, 0)).await;
+    assert_eq!(labels(hints).len(), 3);
 }
 
 // ── Hint position is at the end of the parameter name ─────────────────────
@@ -2002,6 +3543,137 @@ async fn test_outgoing_calls_skips_unresolvable_dependency() {
     assert!(calls.is_empty(), "unresolved deps should be filtered out");
 }
 
+#[tokio::test]
+#[timeout(30000)]
+async fn test_incoming_calls_groups_usages_per_caller() {
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+
+    let conftest_path = tfile("test_ls_callh_group", "conftest.py");
+    db.analyze_file(
+        conftest_path.clone(),
+        "import pytest\n\n@pytest.fixture\ndef db():\n    return 1\n\n@pytest.fixture\ndef client(db):\n    return db\n",
+    );
+    let conftest_uri = turi("test_ls_callh_group", "conftest.py");
+    backend
+        .uri_cache
+        .insert(conftest_path.clone(), conftest_uri.clone());
+
+    let test_path = tfile("test_ls_callh_group", "test_example.py");
+    db.analyze_file(
+        test_path.clone(),
+        r#"import pytest
+
+pytestmark = pytest.mark.usefixtures("db")
+
+@pytest.mark.usefixtures("db")
+def test_marked():
+    pass
+
+class TestGroup:
+    def test_dynamic(self, request, db):
+        request.getfixturevalue("db")
+"#,
+    );
+    let test_uri = turi("test_ls_callh_group", "test_example.py");
+    backend.uri_cache.insert(test_path, test_uri.clone());
+
+    let calls = backend
+        .incoming_calls(CallHierarchyIncomingCallsParams {
+            item: CallHierarchyItem {
+                name: "db".to_string(),
+                kind: SymbolKind::FUNCTION,
+                tags: None,
+                detail: None,
+                uri: conftest_uri.clone(),
+                range: rng(3, 0, 4, 0),
+                selection_range: rng(3, 4, 3, 6),
+                data: None,
+            },
+            work_done_progress_params: wdp(),
+            partial_result_params: prp(),
+        })
+        .await
+        .unwrap()
+        .expect("some calls");
+
+    let mut callers: Vec<(&str, SymbolKind, usize)> = calls
+        .iter()
+        .map(|c| (c.from.name.as_str(), c.from.kind, c.from_ranges.len()))
+        .collect();
+    callers.sort_by_key(|(name, _, _)| name.to_string());
+    assert_eq!(
+        callers,
+        vec![
+            ("client", SymbolKind::FUNCTION, 1),
+            ("test_dynamic", SymbolKind::METHOD, 2),
+            ("test_example", SymbolKind::MODULE, 1),
+            ("test_marked", SymbolKind::FUNCTION, 1),
+        ]
+    );
+
+    // Fixture callers are full fixture items, so they can be expanded further.
+    let client = calls.iter().find(|c| c.from.name == "client").unwrap();
+    assert_eq!(client.from.uri, conftest_uri);
+    assert_eq!(client.from.selection_range, rng(7, 4, 7, 10));
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_outgoing_calls_include_getfixturevalue_and_overridden_parent() {
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+
+    let conftest_path = tfile("test_ls_callh_out_dyn", "conftest.py");
+    db.analyze_file(
+        conftest_path.clone(),
+        "import pytest\n\n@pytest.fixture\ndef db():\n    return 1\n\n@pytest.fixture\ndef cache():\n    return 2\n",
+    );
+    let nested_path = tfile("test_ls_callh_out_dyn", "sub/conftest.py");
+    db.analyze_file(
+        nested_path.clone(),
+        "import pytest\n\n@pytest.fixture\ndef db(\n    db,\n    request,\n):\n    request.getfixturevalue(\"cache\")\n    return db\n",
+    );
+    let nested_uri = turi("test_ls_callh_out_dyn", "sub/conftest.py");
+    backend.uri_cache.insert(nested_path, nested_uri.clone());
+    let conftest_uri = turi("test_ls_callh_out_dyn", "conftest.py");
+    backend
+        .uri_cache
+        .insert(conftest_path, conftest_uri.clone());
+
+    let calls = backend
+        .outgoing_calls(CallHierarchyOutgoingCallsParams {
+            item: CallHierarchyItem {
+                name: "db".to_string(),
+                kind: SymbolKind::FUNCTION,
+                tags: None,
+                detail: None,
+                uri: nested_uri,
+                range: rng(3, 0, 8, 0),
+                selection_range: rng(3, 4, 3, 6),
+                data: None,
+            },
+            work_done_progress_params: wdp(),
+            partial_result_params: prp(),
+        })
+        .await
+        .unwrap()
+        .expect("some calls");
+
+    let targets: Vec<(&str, &Uri, Vec<Range>)> = calls
+        .iter()
+        .map(|c| (c.to.name.as_str(), &c.to.uri, c.from_ranges.clone()))
+        .collect();
+    assert_eq!(
+        targets,
+        vec![
+            // The overridden parent, referenced from the multi-line signature.
+            ("db", &conftest_uri, vec![rng(4, 4, 4, 6)]),
+            ("cache", &conftest_uri, vec![rng(7, 29, 7, 34)]),
+        ]
+    );
+}
+
 // ── shutdown ──────────────────────────────────────────────────────────────
 
 #[tokio::test]
@@ -2107,6 +3779,134 @@ async fn test_full_file_lifecycle_open_change_close() {
     );
 }
 
+// ── unsaved and untitled buffers ─────────────────────────────────────────
+
+fn did_open_params(uri: Uri, text: &str) -> DidOpenTextDocumentParams {
+    DidOpenTextDocumentParams {
+        text_document: TextDocumentItem {
+            uri,
+            language_id: "python".to_string(),
+            version: 1,
+            text: text.to_string(),
+        },
+    }
+}
+
+/// Labels of the completions offered at `line`/`character` of `uri`.
+async fn completion_labels(backend: &Backend, uri: Uri, line: u32, character: u32) -> Vec<String> {
+    let response = backend
+        .completion(CompletionParams {
+            text_document_position: tdp(uri, line, character),
+            work_done_progress_params: wdp(),
+            partial_result_params: prp(),
+            context: None,
+        })
+        .await
+        .unwrap();
+    match response {
+        Some(CompletionResponse::Array(items)) => items.into_iter().map(|i| i.label).collect(),
+        Some(CompletionResponse::List(list)) => list.items.into_iter().map(|i| i.label).collect(),
+        None => Vec::new(),
+    }
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_unsaved_file_is_analyzed_from_buffer() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let root = tmp.path().canonicalize().unwrap();
+    std::fs::write(
+        root.join("conftest.py"),
+        "import pytest\n\n@pytest.fixture\ndef db_conn():\n    return 1\n\n@pytest.fixture\ndef api_client():\n    return 2\n",
+    )
+    .unwrap();
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+    initialize_and_scan(&backend, &root).await;
+
+    // A test file that does not exist on disk yet
+    let path = root.join("test_new.py");
+    let uri = Uri::from_file_path(&path).unwrap();
+    let text = "import pytest\n\n@pytest.fixture\ndef local_fixture():\n    return 2\n\ndef test_new(db_conn, ):\n    pass\n";
+    backend.did_open(did_open_params(uri.clone(), text)).await;
+
+    let labels = completion_labels(&backend, uri.clone(), 6, 21).await;
+    assert!(labels.contains(&"api_client".to_string()), "{:?}", labels);
+    assert!(
+        labels.contains(&"local_fixture".to_string()),
+        "{:?}",
+        labels
+    );
+
+    let definition = backend
+        .goto_definition(GotoDefinitionParams {
+            text_document_position_params: tdp(uri.clone(), 6, 14),
+            work_done_progress_params: wdp(),
+            partial_result_params: prp(),
+        })
+        .await
+        .unwrap();
+    let Some(GotoDefinitionResponse::Scalar(location)) = definition else {
+        panic!("expected a definition, got {:?}", definition);
+    };
+    assert_eq!(
+        location.uri,
+        Uri::from_file_path(root.join("conftest.py")).unwrap()
+    );
+
+    // Closing the buffer without saving it forgets its fixtures
+    backend
+        .did_close(DidCloseTextDocumentParams {
+            text_document: TextDocumentIdentifier { uri },
+        })
+        .await;
+    assert!(!db.definitions.contains_key("local_fixture"));
+    assert!(db.definitions.contains_key("db_conn"));
+    assert!(!db.usages.contains_key(&path));
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_untitled_buffer_sees_workspace_fixtures() {
+    let tmp = tempfile::tempdir().expect("tempdir");
+    let root = tmp.path().canonicalize().unwrap();
+    std::fs::write(
+        root.join("conftest.py"),
+        "import pytest\n\n@pytest.fixture\ndef db_conn():\n    return 1\n",
+    )
+    .unwrap();
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+    initialize_and_scan(&backend, &root).await;
+
+    let uri: Uri = "untitled:Untitled-1".parse().unwrap();
+    assert_eq!(backend.uri_to_path(&uri), Some(root.join("Untitled-1")));
+    backend
+        .did_open(did_open_params(uri.clone(), "def test_new():\n    pass\n"))
+        .await;
+    let labels = completion_labels(&backend, uri.clone(), 0, 13).await;
+    assert!(labels.contains(&"db_conn".to_string()), "{:?}", labels);
+
+    // Responses refer to the buffer by its untitled URI
+    assert_eq!(
+        backend.path_to_uri(&root.join("Untitled-1")),
+        Some(uri.clone())
+    );
+
+    backend
+        .did_close(DidCloseTextDocumentParams {
+            text_document: TextDocumentIdentifier { uri },
+        })
+        .await;
+    assert!(!db.file_cache.contains_key(&root.join("Untitled-1")));
+
+    // Other schemes (e.g. a diff view's original side) are not files to analyze
+    let git_uri: Uri = format!("git:{}?ref=HEAD", root.join("test_a.py").display())
+        .parse()
+        .unwrap();
+    assert_eq!(backend.uri_to_path(&git_uri), None);
+}
+
 // ── publish_diagnostics_for_file ─────────────────────────────────────────
 //
 // `publish_diagnostics_for_file` pushes diagnostics to the LSP client. With
@@ -2292,9 +4092,33 @@ async fn test_initialize_negotiates_utf8_when_client_supports_it() {
         result.capabilities.position_encoding,
         Some(PositionEncodingKind::UTF8)
     );
-    assert!(!backend
-        .client_utf16
-        .load(std::sync::atomic::Ordering::Relaxed));
+    assert_eq!(backend.position_encoding(), PositionEncoding::Utf8);
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_initialize_negotiates_utf32_in_client_preference_order() {
+    let backend = make_backend();
+    let params = InitializeParams {
+        capabilities: ClientCapabilities {
+            general: Some(GeneralClientCapabilities {
+                position_encodings: Some(vec![
+                    PositionEncodingKind::UTF32,
+                    PositionEncodingKind::UTF16,
+                ]),
+                ..Default::default()
+            }),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let result = backend.initialize(params).await.unwrap();
+    assert_eq!(
+        result.capabilities.position_encoding,
+        Some(PositionEncodingKind::UTF32)
+    );
+    assert_eq!(backend.position_encoding(), PositionEncoding::Utf32);
 }
 
 #[tokio::test]
@@ -2309,9 +4133,7 @@ async fn test_initialize_defaults_to_utf16_encoding() {
         result.capabilities.position_encoding,
         Some(PositionEncodingKind::UTF16)
     );
-    assert!(backend
-        .client_utf16
-        .load(std::sync::atomic::Ordering::Relaxed));
+    assert_eq!(backend.position_encoding(), PositionEncoding::Utf16);
 }
 
 // ── Multi-root workspaces ─────────────────────────────────────────────────
@@ -2430,6 +4252,60 @@ async fn test_did_change_debounce_publishes_after_quiet_period() {
     assert_eq!(generation, Some(2), "both changes must bump the generation");
 }
 
+#[tokio::test]
+#[timeout(30000)]
+async fn test_did_change_coalesces_burst_until_quiet_or_request() {
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+    let uri = turi("test_ls_debounce_burst", "conftest.py");
+    let fixture = |name: &str| {
+        format!(
+            "import pytest\n\n@pytest.fixture\ndef {}():\n    pass\n",
+            name
+        )
+    };
+
+    backend
+        .did_open(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem {
+                uri: uri.clone(),
+                language_id: "python".to_string(),
+                version: 1,
+                text: fixture("opened"),
+            },
+        })
+        .await;
+
+    // The first edit is analyzed right away, the rest of the burst is deferred
+    backend
+        .did_change(did_change_params(uri.clone(), 2, &fixture("first_edit")))
+        .await;
+    assert!(db.definitions.contains_key("first_edit"));
+    backend
+        .did_change(did_change_params(uri.clone(), 3, &fixture("second_edit")))
+        .await;
+    assert!(!db.definitions.contains_key("second_edit"));
+
+    // A request analyzes pending edits before answering
+    backend
+        .document_symbol(DocumentSymbolParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .await
+        .unwrap();
+    assert!(db.definitions.contains_key("second_edit"));
+
+    // Otherwise the burst is analyzed once typing pauses
+    backend
+        .did_change(did_change_params(uri.clone(), 4, &fixture("third_edit")))
+        .await;
+    assert!(!db.definitions.contains_key("third_edit"));
+    tokio::time::sleep(std::time::Duration::from_millis(350)).await;
+    assert!(db.definitions.contains_key("third_edit"));
+}
+
 #[tokio::test]
 #[timeout(30000)]
 async fn test_did_close_while_debounce_pending_clears_generation() {
@@ -2472,3 +4348,86 @@ async fn test_did_close_while_debounce_pending_clears_generation() {
         "closed file must not retain a debounce generation entry"
     );
 }
+
+// ── discover_tests ────────────────────────────────────────────────────────
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_discover_tests_builds_tree_with_cases() {
+    use pytest_language_server::{DiscoverTestsParams, TestNodeKind};
+
+    let db = Arc::new(FixtureDatabase::new());
+    let backend = make_backend_with_db(Arc::clone(&db));
+    *backend.workspace_root.write().await = Some(tfile("test_ls_discover", ""));
+
+    let test_path = tfile("test_ls_discover", "tests/test_example.py");
+    db.analyze_file(
+        test_path.clone(),
+        "import pytest\n\n@pytest.mark.parametrize(\"x\", [1, 2])\ndef test_one(x):\n    pass\n\nclass TestGroup:\n    def test_two(self):\n        pass\n",
+    );
+    db.analyze_file(
+        tfile("test_ls_discover", "tests/conftest.py"),
+        "def test_not_a_test_file():\n    pass\n",
+    );
+    db.analyze_file(
+        tfile("test_ls_discover", "tests/test_empty.py"),
+        "def helper():\n    pass\n",
+    );
+    let test_uri = turi("test_ls_discover", "tests/test_example.py");
+
+    let nodes = backend
+        .handle_discover_tests(DiscoverTestsParams::default())
+        .await
+        .unwrap();
+    assert_eq!(nodes.len(), 1, "only test files with tests: {:?}", nodes);
+
+    let file = &nodes[0];
+    assert_eq!(
+        (file.id.as_str(), file.name.as_str(), file.kind),
+        (
+            "tests/test_example.py",
+            "test_example.py",
+            TestNodeKind::File
+        )
+    );
+    assert_eq!(file.uri, test_uri);
+
+    let test_one = &file.children[0];
+    assert_eq!(test_one.id, "tests/test_example.py::test_one");
+    assert_eq!(test_one.range, Some(rng(2, 0, 4, 0)));
+    assert_eq!(test_one.selection_range, Some(rng(3, 4, 3, 12)));
+    let cases: Vec<(&str, TestNodeKind)> = test_one
+        .children
+        .iter()
+        .map(|c| (c.id.as_str(), c.kind))
+        .collect();
+    assert_eq!(
+        cases,
+        vec![
+            ("tests/test_example.py::test_one[1]", TestNodeKind::Case),
+            ("tests/test_example.py::test_one[2]", TestNodeKind::Case),
+        ]
+    );
+
+    let group = &file.children[1];
+    assert_eq!(group.kind, TestNodeKind::Class);
+    assert_eq!(
+        group.children[0].id,
+        "tests/test_example.py::TestGroup::test_two"
+    );
+    assert_eq!(group.children[0].kind, TestNodeKind::Function);
+
+    // Restricting to a document returns only that file
+    let single = backend
+        .handle_discover_tests(DiscoverTestsParams {
+            text_document: Some(TextDocumentIdentifier { uri: test_uri }),
+        })
+        .await
+        .unwrap();
+    assert_eq!(single, nodes);
+
+    // The wire format is camelCase with lowercase kinds
+    let json = serde_json::to_value(&single[0].children[0].children[0]).unwrap();
+    assert_eq!(json["kind"], "case");
+    assert!(json.get("selectionRange").is_some());
+}
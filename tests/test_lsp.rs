@@ -3,7 +3,7 @@
 //! All tests have a 30-second timeout to prevent hangs from blocking CI.
 
 use ntest::timeout;
-use pytest_language_server::FixtureDefinition;
+use pytest_language_server::{FixtureDefinition, PositionEncoding};
 use std::path::PathBuf;
 use std::sync::Arc;
 use tower_lsp_server::ls_types::*;
@@ -4930,12 +4930,12 @@ def mode() -> Literal["read", "write"]:
     let defs = db.definitions.get("mode").expect("fixture not found");
     let def = &defs[0];
 
-    // The AST stringifies string constants via Debug as `Str("...")`.
+    // String literals keep their source text.
     assert_eq!(
         def.return_type.as_deref(),
-        Some(r#"Literal[Str("read"), Str("write")]"#)
+        Some(r#"Literal["read", "write"]"#)
     );
-    // Only `Literal` should produce an import — `Str`, `read` and `write` are
+    // Only `Literal` should produce an import — `read` and `write` are
     // not in the import map or module-level names so they are silently skipped.
     assert_eq!(
         def.return_type_imports,
@@ -4970,12 +4970,12 @@ def admin_user() -> Annotated[User, "metadata"]:
     let defs = db.definitions.get("admin_user").expect("fixture not found");
     let def = &defs[0];
 
-    // The AST stringifies string constants via Debug as `Str("...")`.
+    // String literals keep their source text.
     assert_eq!(
         def.return_type.as_deref(),
-        Some(r#"Annotated[User, Str("metadata")]"#)
+        Some(r#"Annotated[User, "metadata"]"#)
     );
-    // `Str` and `metadata` are bare identifiers from the constant — they should
+    // `metadata` is a bare identifier from the string literal — it should
     // not appear in the result because they're not in the import map or module-level names.
     assert_eq!(
         def.return_type_imports,
@@ -5233,10 +5233,9 @@ def items() -> Iterable[str]:
 #[test]
 #[timeout(30000)]
 fn test_return_type_imports_forward_ref_quoted() {
-    // `list["User"]` — forward reference with quotes.  The AST stringifies
-    // the string constant as `Str("User")`, so the return type string is
-    // `list[Str("User")]`.  The tokeniser extracts `list`, `Str`, `User`.
-    // `list` is builtin, `Str` is not in the import map, and `User` IS a
+    // `list["User"]` — forward reference with quotes.  The return type
+    // string keeps the quotes, and the tokeniser extracts `list` and `User`.
+    // `list` is builtin, and `User` IS a
     // module-level class definition so it falls back to module-path import.
     use pytest_language_server::FixtureDatabase;
 
@@ -5258,8 +5257,7 @@ def users() -> list["User"]:
     let defs = db.definitions.get("users").expect("fixture not found");
     let def = &defs[0];
 
-    // The AST Debug-formats string constants as `Str("...")`.
-    assert_eq!(def.return_type.as_deref(), Some(r#"list[Str("User")]"#));
+    assert_eq!(def.return_type.as_deref(), Some(r#"list["User"]"#));
     // `User` is locally defined → import generated from module path.
     assert_eq!(def.return_type_imports.len(), 1);
     assert_eq!(def.return_type_imports[0].check_name, "User");
@@ -5371,7 +5369,6 @@ def flexible() -> Union[str, int]:
 #[timeout(30000)]
 fn test_return_type_imports_typing_literal_needs_import() {
     // `Literal[1, 2, 3]` — `Literal` from typing needs an import.
-    // The AST Debug-formats integer constants as `Int(N)`.
     use pytest_language_server::{FixtureDatabase, TypeImportSpec};
 
     let db = FixtureDatabase::new();
@@ -5390,13 +5387,8 @@ def priority() -> Literal[1, 2, 3]:
     let defs = db.definitions.get("priority").expect("fixture not found");
     let def = &defs[0];
 
-    // Integer constants are Debug-formatted as `Int(N)`.
-    assert_eq!(
-        def.return_type.as_deref(),
-        Some("Literal[Int(1), Int(2), Int(3)]")
-    );
-    // `Int` is not in the import map or builtins, so only `Literal` produces
-    // an import spec.
+    assert_eq!(def.return_type.as_deref(), Some("Literal[1, 2, 3]"));
+    // Only `Literal` produces an import spec.
     assert_eq!(
         def.return_type_imports,
         vec![TypeImportSpec {
@@ -5410,7 +5402,7 @@ def priority() -> Literal[1, 2, 3]:
 #[timeout(30000)]
 fn test_return_type_imports_typing_annotated_needs_import() {
     // `Annotated[int, "positive"]` — `Annotated` from typing needs an import,
-    // `int` is builtin, the string constant is Debug-formatted as `Str("positive")`.
+    // `int` is builtin, and the string literal `"positive"` is ignored.
     use pytest_language_server::{FixtureDatabase, TypeImportSpec};
 
     let db = FixtureDatabase::new();
@@ -5432,13 +5424,12 @@ def positive_int() -> Annotated[int, "positive"]:
         .expect("fixture not found");
     let def = &defs[0];
 
-    // String constants are Debug-formatted as `Str("...")`.
     assert_eq!(
         def.return_type.as_deref(),
-        Some(r#"Annotated[int, Str("positive")]"#)
+        Some(r#"Annotated[int, "positive"]"#)
     );
-    // Only `Annotated` should produce an import; `int` is builtin, `Str` and
-    // `positive` are not in the import map or module-level names.
+    // Only `Annotated` should produce an import; `int` is builtin and
+    // `positive` is not in the import map or module-level names.
     assert_eq!(
         def.return_type_imports,
         vec![TypeImportSpec {
@@ -7449,12 +7440,15 @@ async fn test_request_inlay_hint_shown_when_definition_available() {
             check_name: "FixtureRequest".to_string(),
             import_statement: "from pytest import FixtureRequest".to_string(),
         }],
+        inferred_return_type: None,
         is_third_party: true,
         is_plugin: true,
         dependencies: vec![],
         scope: pytest_language_server::FixtureScope::Function,
         yield_line: None,
         autouse: false,
+        class_name: None,
+        params: None,
     };
     db.definitions
         .entry("request".to_string())
@@ -7518,12 +7512,15 @@ async fn test_request_code_action_fix_all_annotates_request_param() {
             check_name: "FixtureRequest".to_string(),
             import_statement: "from pytest import FixtureRequest".to_string(),
         }],
+        inferred_return_type: None,
         is_third_party: true,
         is_plugin: true,
         dependencies: vec![],
         scope: pytest_language_server::FixtureScope::Function,
         yield_line: None,
         autouse: false,
+        class_name: None,
+        params: None,
     };
     db.definitions
         .entry("request".to_string())
@@ -7630,12 +7627,15 @@ async fn test_request_hover_returns_fixture_request_type() {
             check_name: "FixtureRequest".to_string(),
             import_statement: "from pytest import FixtureRequest".to_string(),
         }],
+        inferred_return_type: None,
         is_third_party: true,
         is_plugin: true,
         dependencies: vec![],
         scope: pytest_language_server::FixtureScope::Function,
         yield_line: None,
         autouse: false,
+        class_name: None,
+        params: None,
     };
     db.definitions
         .entry("request".to_string())
@@ -8484,9 +8484,7 @@ async fn test_references_byte_positions_when_utf8_negotiated() {
     db.analyze_file(test_path.clone(), content);
 
     let backend = make_backend_with_db(db);
-    backend
-        .client_utf16
-        .store(false, std::sync::atomic::Ordering::Relaxed);
+    backend.set_position_encoding(PositionEncoding::Utf8);
     let uri = Uri::from_file_path(&test_path).unwrap();
 
     // Byte column 16 is inside `fixture_é` ("def test_ünï(" is 15 bytes).
@@ -8520,6 +8518,119 @@ async fn test_references_byte_positions_when_utf8_negotiated() {
     assert_eq!(param_loc.range.end.character, 25);
 }
 
+#[tokio::test]
+async fn test_references_code_point_positions_when_utf32_negotiated() {
+    // With utf-32, columns count code points, so an astral character such as
+    // "𐐀" is one column (two in UTF-16, four bytes).
+    use pytest_language_server::FixtureDatabase;
+
+    let db = Arc::new(FixtureDatabase::new());
+    let test_path = std::env::temp_dir()
+        .join("test_utf32_positions")
+        .join("test_example.py");
+    let content = "import pytest\n\n@pytest.fixture\ndef fixture_é():\n    return 1\n\ndef test_𐐀(fixture_é):\n    assert fixture_é\n";
+    db.analyze_file(test_path.clone(), content);
+
+    let backend = make_backend_with_db(db);
+    backend.set_position_encoding(PositionEncoding::Utf32);
+    let uri = Uri::from_file_path(&test_path).unwrap();
+
+    // "def test_𐐀(" is 11 code points; column 12 is inside `fixture_é`.
+    let params = ReferenceParams {
+        text_document_position: TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            position: Position {
+                line: 6,
+                character: 12,
+            },
+        },
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+        context: ReferenceContext {
+            include_declaration: true,
+        },
+    };
+
+    let locations = backend
+        .handle_references(params)
+        .await
+        .unwrap()
+        .expect("references should be found for fixture_é");
+
+    // `fixture_é` is 9 code points: 11..20 (12..21 in UTF-16).
+    let param_loc = locations
+        .iter()
+        .find(|l| l.range.start.line == 6)
+        .expect("expected a location on the signature line");
+    assert_eq!(param_loc.range.start.character, 11);
+    assert_eq!(param_loc.range.end.character, 20);
+}
+
+#[tokio::test]
+async fn test_hover_and_definition_utf16_positions_with_cjk_and_emoji() {
+    // CJK identifiers and emoji in strings ahead of the cursor must not shift
+    // the UTF-16 column: "🎉" is two UTF-16 units and four bytes, each CJK
+    // character one unit and three bytes.
+    use pytest_language_server::FixtureDatabase;
+
+    let db = Arc::new(FixtureDatabase::new());
+    let test_path = std::env::temp_dir()
+        .join("test_utf16_cjk_emoji")
+        .join("test_example.py");
+    let content = "import pytest\n\n@pytest.fixture\ndef my_fixture():\n    \"\"\"Fixture 🎉 with 文档.\"\"\"\n    return 1\n\n@pytest.mark.usefixtures(\"🎉\", \"my_fixture\")\ndef test_日本語(my_fixture):\n    pass\n";
+    db.analyze_file(test_path.clone(), content);
+
+    let backend = make_backend_with_db(db);
+    let uri = Uri::from_file_path(&test_path).unwrap();
+    let position_params = |line, character| TextDocumentPositionParams {
+        text_document: TextDocumentIdentifier { uri: uri.clone() },
+        position: Position { line, character },
+    };
+
+    // `def test_日本語(` is 13 UTF-16 units (19 bytes); the parameter spans 13..23.
+    let hover = backend
+        .handle_hover(HoverParams {
+            text_document_position_params: position_params(8, 14),
+            work_done_progress_params: Default::default(),
+        })
+        .await
+        .unwrap()
+        .expect("hover on the parameter");
+    let HoverContents::Markup(markup) = hover.contents else {
+        panic!("expected markup hover");
+    };
+    assert!(markup.value.contains("Fixture 🎉 with 文档."));
+
+    // Column 22 is the last character of the parameter; in bytes it would be
+    // past the end of the line.
+    let definition = backend
+        .handle_goto_definition(GotoDefinitionParams {
+            text_document_position_params: position_params(8, 22),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .await
+        .unwrap();
+    let Some(GotoDefinitionResponse::Scalar(location)) = definition else {
+        panic!("expected a definition, got {:?}", definition);
+    };
+    assert_eq!(location.range.start.line, 3);
+
+    // `@pytest.mark.usefixtures("🎉", "` is 32 UTF-16 units (34 bytes).
+    let definition = backend
+        .handle_goto_definition(GotoDefinitionParams {
+            text_document_position_params: position_params(7, 33),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .await
+        .unwrap();
+    let Some(GotoDefinitionResponse::Scalar(location)) = definition else {
+        panic!("expected a definition, got {:?}", definition);
+    };
+    assert_eq!(location.range.start.line, 3);
+}
+
 #[tokio::test]
 #[timeout(30000)]
 async fn test_rename_parametrize_unicode_identifier() {
@@ -8550,9 +8661,7 @@ def test_something(renamed_ü):
         .join("test_parametrize.py");
     db.analyze_file(path.clone(), content);
     let backend = make_backend_with_db(db);
-    backend
-        .client_utf16
-        .store(false, std::sync::atomic::Ordering::Relaxed);
+    backend.set_position_encoding(PositionEncoding::Utf8);
     let uri = Uri::from_file_path(&path).unwrap();
 
     // Cursor on the parameter in the signature (byte column of "café").
@@ -8580,3 +8689,1070 @@ def test_something(renamed_ü):
     let edits = edits.into_values().next().expect("one file of edits");
     assert_eq!(apply_text_edits(content, &edits), expected);
 }
+
+// =============================================================================
+// Scope-mismatch quick fixes
+// =============================================================================
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_code_action_scope_mismatch_narrow_and_widen() {
+    use pytest_language_server::FixtureDatabase;
+
+    let content = r#"import pytest
+
+
+@pytest.fixture
+def narrow():
+    return 1
+
+
+@pytest.fixture(scope="session")
+def broad(narrow):
+    return narrow
+"#;
+
+    let db = Arc::new(FixtureDatabase::new());
+    let path = std::env::temp_dir()
+        .join("test_ca_scope_mismatch")
+        .join("conftest.py");
+    db.analyze_file(path.clone(), content);
+
+    let mismatches = db.detect_scope_mismatches_in_file(&path);
+    assert_eq!(mismatches.len(), 1);
+    let fixture = &mismatches[0].fixture;
+    let line = (fixture.line - 1) as u32;
+
+    let backend = make_backend_with_db(db);
+    let uri = Uri::from_file_path(&path).unwrap();
+    let diagnostic = Diagnostic {
+        range: Range {
+            start: Position {
+                line,
+                character: fixture.start_char as u32,
+            },
+            end: Position {
+                line,
+                character: fixture.end_char as u32,
+            },
+        },
+        severity: Some(DiagnosticSeverity::WARNING),
+        code: Some(NumberOrString::String("PTL002".to_string())),
+        source: Some("pytest-lsp".to_string()),
+        message: "session-scoped fixture 'broad' depends on function-scoped fixture 'narrow'"
+            .to_string(),
+        code_description: None,
+        related_information: None,
+        tags: None,
+        data: None,
+    };
+
+    let params = CodeActionParams {
+        text_document: TextDocumentIdentifier { uri: uri.clone() },
+        range: diagnostic.range,
+        context: CodeActionContext {
+            diagnostics: vec![diagnostic],
+            only: Some(vec![CodeActionKind::QUICKFIX]),
+            trigger_kind: None,
+        },
+        work_done_progress_params: WorkDoneProgressParams {
+            work_done_token: None,
+        },
+        partial_result_params: PartialResultParams {
+            partial_result_token: None,
+        },
+    };
+
+    let actions = backend
+        .handle_code_action(params)
+        .await
+        .unwrap()
+        .expect("scope mismatch should produce quick fixes");
+    let actions: Vec<&CodeAction> = actions
+        .iter()
+        .filter_map(|a| match a {
+            CodeActionOrCommand::CodeAction(ca) => Some(ca),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(actions.len(), 2, "narrow + widen actions expected");
+
+    let edited = |action: &CodeAction| {
+        let changes = action.edit.as_ref().unwrap().changes.as_ref().unwrap();
+        apply_text_edits(content, changes.values().next().unwrap())
+    };
+
+    let narrow = actions
+        .iter()
+        .find(|a| a.title.contains("'broad' to 'function'"))
+        .expect("narrowing action for the requesting fixture");
+    assert_eq!(narrow.is_preferred, Some(true));
+    assert!(edited(narrow).contains("@pytest.fixture(scope=\"function\")\ndef broad(narrow):"));
+
+    let widen = actions
+        .iter()
+        .find(|a| a.title.contains("'narrow' to 'session'"))
+        .expect("widening action for the dependency");
+    assert!(edited(widen).contains("@pytest.fixture(scope=\"session\")\ndef narrow():"));
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_code_action_redundant_usefixtures() {
+    use pytest_language_server::FixtureDatabase;
+
+    let content = r#"import pytest
+
+
+@pytest.mark.usefixtures("db", "client")
+def test_a(db, client):
+    pass
+"#;
+
+    let db = Arc::new(FixtureDatabase::new());
+    let path = std::env::temp_dir()
+        .join("test_ca_redundant_usefixtures")
+        .join("test_a.py");
+    db.analyze_file(path.clone(), content);
+
+    let backend = make_backend_with_db(db);
+    let uri = Uri::from_file_path(&path).unwrap();
+    let diagnostic = Diagnostic {
+        range: Range {
+            start: Position {
+                line: 3,
+                character: 32,
+            },
+            end: Position {
+                line: 3,
+                character: 38,
+            },
+        },
+        severity: Some(DiagnosticSeverity::HINT),
+        code: Some(NumberOrString::String("PTL007".to_string())),
+        source: Some("pytest-lsp".to_string()),
+        message:
+            "'client' is already requested as a parameter; listing it in usefixtures has no effect"
+                .to_string(),
+        code_description: None,
+        related_information: None,
+        tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+        data: None,
+    };
+
+    let params = CodeActionParams {
+        text_document: TextDocumentIdentifier { uri: uri.clone() },
+        range: diagnostic.range,
+        context: CodeActionContext {
+            diagnostics: vec![diagnostic],
+            only: Some(vec![CodeActionKind::QUICKFIX]),
+            trigger_kind: None,
+        },
+        work_done_progress_params: WorkDoneProgressParams {
+            work_done_token: None,
+        },
+        partial_result_params: PartialResultParams {
+            partial_result_token: None,
+        },
+    };
+
+    let actions = backend
+        .handle_code_action(params)
+        .await
+        .unwrap()
+        .expect("redundant usefixtures entry should have a quick fix");
+    let [CodeActionOrCommand::CodeAction(action)] = actions.as_slice() else {
+        panic!("expected a single code action, got {:?}", actions);
+    };
+    assert_eq!(action.title, "pytest-ls: Remove 'client' from usefixtures");
+    assert_eq!(action.kind, Some(CodeActionKind::QUICKFIX));
+    let changes = action.edit.as_ref().unwrap().changes.as_ref().unwrap();
+    assert!(apply_text_edits(content, changes.values().next().unwrap())
+        .contains("@pytest.mark.usefixtures(\"db\")\ndef test_a(db, client):"));
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_code_action_unused_fixture_parameter() {
+    use pytest_language_server::FixtureDatabase;
+
+    let content = r#"import pytest
+
+
+@pytest.fixture
+def db():
+    return object()
+
+
+def test_a(db):
+    assert True
+"#;
+
+    let db = Arc::new(FixtureDatabase::new());
+    let path = std::env::temp_dir()
+        .join("test_ca_unused_fixture_parameter")
+        .join("test_a.py");
+    db.analyze_file(path.clone(), content);
+
+    let backend = make_backend_with_db(db);
+    let uri = Uri::from_file_path(&path).unwrap();
+    let diagnostic = Diagnostic {
+        range: Range {
+            start: Position {
+                line: 8,
+                character: 11,
+            },
+            end: Position {
+                line: 8,
+                character: 13,
+            },
+        },
+        severity: Some(DiagnosticSeverity::HINT),
+        code: Some(NumberOrString::String("PTL008".to_string())),
+        source: Some("pytest-lsp".to_string()),
+        message: "Fixture 'db' is requested but never used in the function body".to_string(),
+        code_description: None,
+        related_information: None,
+        tags: Some(vec![DiagnosticTag::UNNECESSARY]),
+        data: None,
+    };
+
+    let params = CodeActionParams {
+        text_document: TextDocumentIdentifier { uri: uri.clone() },
+        range: diagnostic.range,
+        context: CodeActionContext {
+            diagnostics: vec![diagnostic],
+            only: Some(vec![CodeActionKind::QUICKFIX]),
+            trigger_kind: None,
+        },
+        work_done_progress_params: WorkDoneProgressParams {
+            work_done_token: None,
+        },
+        partial_result_params: PartialResultParams {
+            partial_result_token: None,
+        },
+    };
+
+    let actions = backend
+        .handle_code_action(params)
+        .await
+        .unwrap()
+        .expect("unused fixture parameter should have quick fixes");
+    let [CodeActionOrCommand::CodeAction(remove), CodeActionOrCommand::CodeAction(convert)] =
+        actions.as_slice()
+    else {
+        panic!("expected two code actions, got {:?}", actions);
+    };
+    let edited = |action: &CodeAction| {
+        let changes = action.edit.as_ref().unwrap().changes.as_ref().unwrap();
+        apply_text_edits(content, changes.values().next().unwrap())
+    };
+
+    assert_eq!(remove.title, "pytest-ls: Remove unused parameter 'db'");
+    assert_eq!(remove.is_preferred, Some(true));
+    assert!(edited(remove).contains("\ndef test_a():\n"));
+
+    assert_eq!(convert.title, "pytest-ls: Request 'db' with usefixtures");
+    assert!(edited(convert).contains("\n@pytest.mark.usefixtures(\"db\")\ndef test_a():\n"));
+}
+
+// =============================================================================
+// Hover: dependency tree
+// =============================================================================
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_hover_shows_dependency_tree() {
+    use pytest_language_server::FixtureDatabase;
+
+    let db = Arc::new(FixtureDatabase::new());
+    let dir = std::env::temp_dir().join("test_hover_dep_tree");
+    let conftest = dir.join("conftest.py");
+    db.analyze_file(
+        conftest.clone(),
+        r#"import pytest
+
+@pytest.fixture(scope="session")
+def db():
+    return 1
+
+@pytest.fixture
+def app(db):
+    return db
+"#,
+    );
+    let test_path = dir.join("test_example.py");
+    let content = "def test_app(app):\n    pass\n";
+    db.analyze_file(test_path.clone(), content);
+
+    let backend = make_backend_with_db(db);
+    let params = HoverParams {
+        text_document_position_params: TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier {
+                uri: Uri::from_file_path(&test_path).unwrap(),
+            },
+            position: position_of(content, "app", 0),
+        },
+        work_done_progress_params: WorkDoneProgressParams {
+            work_done_token: None,
+        },
+    };
+
+    let hover = backend
+        .handle_hover(params)
+        .await
+        .unwrap()
+        .expect("hover on fixture parameter");
+    let HoverContents::Markup(markup) = hover.contents else {
+        panic!("expected markdown hover");
+    };
+
+    assert!(
+        markup
+            .value
+            .contains("```python\n@pytest.fixture\ndef app(db):\n    return db\n```"),
+        "hover embeds the fixture source, got: {}",
+        markup.value
+    );
+    assert!(
+        markup.value.contains("**Dependencies:**"),
+        "got: {}",
+        markup.value
+    );
+    let db_uri = Uri::from_file_path(&conftest).unwrap();
+    assert!(
+        markup
+            .value
+            .contains(&format!("- [`db`]({}#L4) (session)", db_uri.as_str())),
+        "got: {}",
+        markup.value
+    );
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_hover_shows_fixture_params() {
+    use pytest_language_server::FixtureDatabase;
+
+    let db = Arc::new(FixtureDatabase::new());
+    let dir = std::env::temp_dir().join("test_hover_fixture_params");
+    db.analyze_file(
+        dir.join("conftest.py"),
+        "import pytest\n\n@pytest.fixture(params=[\"sqlite\", \"postgres\"], ids=[\"lite\", \"pg\"])\ndef backend(request):\n    return request.param\n",
+    );
+    let test_path = dir.join("test_example.py");
+    let content = "def test_db(backend):\n    pass\n";
+    db.analyze_file(test_path.clone(), content);
+
+    let backend = make_backend_with_db(db);
+    let hover = backend
+        .handle_hover(HoverParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: Uri::from_file_path(&test_path).unwrap(),
+                },
+                position: position_of(content, "backend", 0),
+            },
+            work_done_progress_params: WorkDoneProgressParams {
+                work_done_token: None,
+            },
+        })
+        .await
+        .unwrap()
+        .expect("hover on parametrized fixture");
+    let HoverContents::Markup(markup) = hover.contents else {
+        panic!("expected markdown hover");
+    };
+    assert!(
+        markup.value.contains("```\n\n*parametrized ×2: lite, pg*"),
+        "got: {}",
+        markup.value
+    );
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_hover_shows_inferred_return_type() {
+    use pytest_language_server::FixtureDatabase;
+
+    let db = Arc::new(FixtureDatabase::new());
+    let dir = std::env::temp_dir().join("test_hover_inferred_type");
+    db.analyze_file(
+        dir.join("conftest.py"),
+        "import pytest\n\n@pytest.fixture\ndef client(app):\n    return TestClient(app)\n",
+    );
+    let test_path = dir.join("test_example.py");
+    let content = "def test_api(client):\n    pass\n";
+    db.analyze_file(test_path.clone(), content);
+
+    let backend = make_backend_with_db(db);
+    let hover = backend
+        .handle_hover(HoverParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: Uri::from_file_path(&test_path).unwrap(),
+                },
+                position: position_of(content, "client", 0),
+            },
+            work_done_progress_params: WorkDoneProgressParams {
+                work_done_token: None,
+            },
+        })
+        .await
+        .unwrap()
+        .expect("hover on unannotated fixture");
+    let HoverContents::Markup(markup) = hover.contents else {
+        panic!("expected markdown hover");
+    };
+    assert!(
+        markup
+            .value
+            .contains("```\n\n*returns `TestClient` (inferred)*"),
+        "got: {}",
+        markup.value
+    );
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_hover_renders_docstring_sections() {
+    use pytest_language_server::FixtureDatabase;
+
+    let db = Arc::new(FixtureDatabase::new());
+    let dir = std::env::temp_dir().join("test_hover_docstring_sections");
+    db.analyze_file(
+        dir.join("conftest.py"),
+        r#"import pytest
+
+@pytest.fixture
+def user(db):
+    """Create a user.
+
+    Args:
+        db (Database): Where to store it.
+
+    Returns:
+        User: The new user.
+
+    Examples:
+        >>> user.name
+        'alice'
+    """
+    return db.create_user()
+"#,
+    );
+    let test_path = dir.join("test_example.py");
+    let content = "def test_user(user):\n    pass\n";
+    db.analyze_file(test_path.clone(), content);
+
+    let backend = make_backend_with_db(db);
+    let hover = backend
+        .handle_hover(HoverParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: Uri::from_file_path(&test_path).unwrap(),
+                },
+                position: position_of(content, "user", 0),
+            },
+            work_done_progress_params: WorkDoneProgressParams {
+                work_done_token: None,
+            },
+        })
+        .await
+        .unwrap()
+        .expect("hover on documented fixture");
+    let HoverContents::Markup(markup) = hover.contents else {
+        panic!("expected markdown hover");
+    };
+    assert!(
+        markup.value.contains(
+            "---\n\nCreate a user.\n\n**Args:**\n\n- `db` (`Database`): Where to store it.\n\n**Returns:**\n\n- `User`: The new user.\n\n**Examples:**\n\n```python\n>>> user.name\n'alice'\n```"
+        ),
+        "got: {}",
+        markup.value
+    );
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_hover_shows_stub_return_type() {
+    use pytest_language_server::FixtureDatabase;
+    use tempfile::tempdir;
+
+    let temp = tempdir().unwrap();
+    let plugin_dir = temp
+        .path()
+        .join(".venv/lib/python3.11/site-packages/pytest_mock");
+    std::fs::create_dir_all(&plugin_dir).unwrap();
+    std::fs::write(
+        plugin_dir.join("plugin.pyi"),
+        "from pytest_mock._types import MockerFixture\n\ndef mocker() -> MockerFixture: ...\n",
+    )
+    .unwrap();
+    let plugin_content = "import pytest\n\n@pytest.fixture\ndef mocker():\n    return object()\n";
+    let plugin_path = plugin_dir.join("plugin.py");
+    std::fs::write(&plugin_path, plugin_content).unwrap();
+
+    let db = Arc::new(FixtureDatabase::new());
+    db.analyze_file(plugin_path, plugin_content);
+    let test_path = temp.path().join("test_example.py");
+    let content = "def test_mock(mocker):\n    pass\n";
+    db.analyze_file(test_path.clone(), content);
+
+    let backend = make_backend_with_db(db);
+    let hover = backend
+        .handle_hover(HoverParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: Uri::from_file_path(&test_path).unwrap(),
+                },
+                position: position_of(content, "mocker", 0),
+            },
+            work_done_progress_params: WorkDoneProgressParams {
+                work_done_token: None,
+            },
+        })
+        .await
+        .unwrap()
+        .expect("hover on stubbed fixture");
+    let HoverContents::Markup(markup) = hover.contents else {
+        panic!("expected markdown hover");
+    };
+    // The runtime source has no annotation; the stub's type is shown below it
+    assert!(
+        markup
+            .value
+            .contains("def mocker():\n    return object()\n```\n\n*returns `MockerFixture`*"),
+        "got: {}",
+        markup.value
+    );
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_hover_on_request_param_lists_fixture_params() {
+    use pytest_language_server::FixtureDatabase;
+
+    let db = Arc::new(FixtureDatabase::new());
+    let conftest = std::env::temp_dir()
+        .join("test_hover_request_param")
+        .join("conftest.py");
+    let content = "import pytest\n\n@pytest.fixture(params=[\"sqlite\", \"postgres\"])\ndef backend(request):\n    return request.param\n\n@pytest.fixture\ndef plain(request):\n    return request.param\n";
+    db.analyze_file(conftest.clone(), content);
+
+    let backend = make_backend_with_db(db);
+    let hover_at = |position: Position| {
+        let backend = &backend;
+        let uri = Uri::from_file_path(&conftest).unwrap();
+        async move {
+            backend
+                .handle_hover(HoverParams {
+                    text_document_position_params: TextDocumentPositionParams {
+                        text_document: TextDocumentIdentifier { uri },
+                        position,
+                    },
+                    work_done_progress_params: WorkDoneProgressParams {
+                        work_done_token: None,
+                    },
+                })
+                .await
+                .unwrap()
+        }
+    };
+
+    let hover = hover_at(position_of(content, "param", 0))
+        .await
+        .expect("hover on request.param");
+    let HoverContents::Markup(markup) = hover.contents else {
+        panic!("expected markdown hover");
+    };
+    assert!(
+        markup.value.contains("`params` of `backend`")
+            && markup.value.contains("- `sqlite`\n- `postgres`"),
+        "got: {}",
+        markup.value
+    );
+    assert_eq!(
+        hover.range,
+        Some(Range {
+            start: Position::new(4, 11),
+            end: Position::new(4, 24),
+        })
+    );
+
+    // Not parametrized: request.param is not explained
+    assert!(hover_at(position_of(content, "param", 1)).await.is_none());
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_hover_on_test_name_lists_fixtures() {
+    use pytest_language_server::FixtureDatabase;
+
+    let db = Arc::new(FixtureDatabase::new());
+    let dir = std::env::temp_dir().join("test_hover_test_fixtures");
+    db.analyze_file(
+        dir.join("conftest.py"),
+        "import pytest\n\n@pytest.fixture(scope=\"session\")\ndef db():\n    return 1\n\n@pytest.fixture(autouse=True)\ndef env():\n    yield\n",
+    );
+    let test_path = dir.join("test_example.py");
+    let content = "def test_db(db, other):\n    pass\n";
+    db.analyze_file(test_path.clone(), content);
+
+    let backend = make_backend_with_db(db);
+    let params = HoverParams {
+        text_document_position_params: TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier {
+                uri: Uri::from_file_path(&test_path).unwrap(),
+            },
+            position: position_of(content, "test_db", 0),
+        },
+        work_done_progress_params: WorkDoneProgressParams {
+            work_done_token: None,
+        },
+    };
+
+    let hover = backend
+        .handle_hover(params)
+        .await
+        .unwrap()
+        .expect("hover on test function name");
+    let HoverContents::Markup(markup) = hover.contents else {
+        panic!("expected markdown hover");
+    };
+
+    assert!(markup.value.starts_with("**Fixtures for** `test_db`"));
+    assert!(
+        markup
+            .value
+            .contains("| `db` | parameter | [`conftest.py:4`]("),
+        "got: {}",
+        markup.value
+    );
+    assert!(markup.value.contains("#L4) | session |"));
+    assert!(markup
+        .value
+        .contains("| `other` | parameter | _not found_ | — |"));
+    assert!(
+        markup
+            .value
+            .contains("| `env` | autouse (conftest.py in the same directory) | [`conftest.py:8`]("),
+        "got: {}",
+        markup.value
+    );
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_hover_on_test_name_builtins_and_repeated_requests() {
+    use pytest_language_server::FixtureDatabase;
+
+    // No virtual environment: pytest's own fixtures are not scanned
+    let db = Arc::new(FixtureDatabase::new());
+    let dir = std::env::temp_dir().join("test_hover_test_fixtures_builtins");
+    db.analyze_file(
+        dir.join("conftest.py"),
+        "import pytest\n\n@pytest.fixture\ndef db():\n    return 1\n",
+    );
+    let test_path = dir.join("test_example.py");
+    let content =
+        "import pytest\n\n@pytest.mark.usefixtures(\"db\")\ndef test_db(db, tmp_path):\n    pass\n";
+    db.analyze_file(test_path.clone(), content);
+
+    let backend = make_backend_with_db(db);
+    let hover = backend
+        .handle_hover(HoverParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier {
+                    uri: Uri::from_file_path(&test_path).unwrap(),
+                },
+                position: position_of(content, "test_db", 0),
+            },
+            work_done_progress_params: WorkDoneProgressParams {
+                work_done_token: None,
+            },
+        })
+        .await
+        .unwrap()
+        .expect("hover on test function name");
+    let HoverContents::Markup(markup) = hover.contents else {
+        panic!("expected markdown hover");
+    };
+
+    assert!(
+        markup
+            .value
+            .contains("| `db` | parameter | [`conftest.py:4`]("),
+        "got: {}",
+        markup.value
+    );
+    assert!(
+        markup
+            .value
+            .contains("| `tmp_path` | parameter | builtin (`pytest`) | function |"),
+        "got: {}",
+        markup.value
+    );
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_hover_on_string_fixture_references() {
+    use pytest_language_server::FixtureDatabase;
+
+    let db = Arc::new(FixtureDatabase::new());
+    let dir = std::env::temp_dir().join("test_hover_string_refs");
+    db.analyze_file(
+        dir.join("conftest.py"),
+        "import pytest\n\n@pytest.fixture\ndef db():\n    \"\"\"The database.\"\"\"\n    return 1\n",
+    );
+    let test_path = dir.join("test_example.py");
+    let content = r#"import pytest
+
+@pytest.mark.usefixtures("db")
+def test_marked():
+    pass
+
+@pytest.mark.parametrize("db", [1], indirect=["db"])
+def test_indirect(db):
+    pass
+
+def test_dynamic(request):
+    value = request.getfixturevalue("db")
+"#;
+    db.analyze_file(test_path.clone(), content);
+
+    let backend = make_backend_with_db(db);
+    let uri = Uri::from_file_path(&test_path).unwrap();
+
+    // usefixtures, parametrize argnames, indirect list, getfixturevalue.
+    for occurrence in [0, 1, 2, 4] {
+        let params = HoverParams {
+            text_document_position_params: TextDocumentPositionParams {
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+                position: position_of(content, "db", occurrence),
+            },
+            work_done_progress_params: WorkDoneProgressParams {
+                work_done_token: None,
+            },
+        };
+        let hover = backend
+            .handle_hover(params)
+            .await
+            .unwrap()
+            .unwrap_or_else(|| panic!("hover on string reference #{occurrence}"));
+        let HoverContents::Markup(markup) = hover.contents else {
+            panic!("expected markdown hover");
+        };
+        assert!(
+            markup.value.contains("def db():") && markup.value.contains("The database."),
+            "occurrence {occurrence} got: {}",
+            markup.value
+        );
+    }
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_goto_definition_from_getfixturevalue_string() {
+    use pytest_language_server::FixtureDatabase;
+
+    let db = Arc::new(FixtureDatabase::new());
+    let dir = std::env::temp_dir().join("test_goto_getfixturevalue");
+    db.analyze_file(
+        dir.join("conftest.py"),
+        "import pytest\n\n@pytest.fixture\ndef db():\n    return 1\n",
+    );
+    let nested_conftest = dir.join("api").join("conftest.py");
+    db.analyze_file(
+        nested_conftest.clone(),
+        "import pytest\n\n@pytest.fixture\ndef db():\n    return 2\n",
+    );
+    let test_path = dir.join("api").join("test_example.py");
+    let content = r#"def test_dynamic(request):
+    value = request.getfixturevalue("db")
+    other = request.getfixturevalue('missing')
+"#;
+    db.analyze_file(test_path.clone(), content);
+
+    let backend = make_backend_with_db(db);
+    let uri = Uri::from_file_path(&test_path).unwrap();
+    let goto = |position: Position| GotoDefinitionParams {
+        text_document_position_params: TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier { uri: uri.clone() },
+            position,
+        },
+        work_done_progress_params: WorkDoneProgressParams {
+            work_done_token: None,
+        },
+        partial_result_params: PartialResultParams {
+            partial_result_token: None,
+        },
+    };
+
+    // Both the first and last character of the name resolve, to the closest conftest
+    let start = position_of(content, "db", 0);
+    for position in [start, Position::new(start.line, start.character + 1)] {
+        let response = backend
+            .handle_goto_definition(goto(position))
+            .await
+            .unwrap();
+        let Some(GotoDefinitionResponse::Scalar(location)) = response else {
+            panic!("expected a definition at {position:?}, got {response:?}");
+        };
+        assert_eq!(location.uri, Uri::from_file_path(&nested_conftest).unwrap());
+        assert_eq!(location.range.start.line, 3);
+    }
+
+    // The quote itself is not part of the reference
+    let quote = Position::new(start.line, start.character - 1);
+    assert!(backend
+        .handle_goto_definition(goto(quote))
+        .await
+        .unwrap()
+        .is_none());
+
+    // Unknown fixture names resolve to nothing
+    let missing = position_of(content, "missing", 0);
+    assert!(backend
+        .handle_goto_definition(goto(missing))
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_references_include_string_references() {
+    use pytest_language_server::FixtureDatabase;
+
+    let db = Arc::new(FixtureDatabase::new());
+    let dir = std::env::temp_dir().join("test_references_string_refs");
+    let conftest_path = dir.join("conftest.py");
+    db.analyze_file(
+        conftest_path.clone(),
+        "import pytest\n\n@pytest.fixture\ndef db():\n    return 1\n",
+    );
+    let test_path = dir.join("test_example.py");
+    let content = r#"import pytest
+
+@pytest.mark.usefixtures("db")
+def test_marked():
+    pass
+
+@pytest.mark.parametrize("db", [1], indirect=["db"])
+def test_indirect(db):
+    pass
+
+def test_dynamic(request):
+    value = request.getfixturevalue("db")
+"#;
+    db.analyze_file(test_path.clone(), content);
+
+    let backend = make_backend_with_db(db);
+    let params = ReferenceParams {
+        text_document_position: TextDocumentPositionParams {
+            text_document: TextDocumentIdentifier {
+                uri: Uri::from_file_path(&conftest_path).unwrap(),
+            },
+            position: Position::new(3, 5),
+        },
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+        context: ReferenceContext {
+            include_declaration: false,
+        },
+    };
+    let locations = backend.handle_references(params).await.unwrap().unwrap();
+
+    let mut ranges: Vec<Range> = locations
+        .iter()
+        .filter(|loc| loc.uri == Uri::from_file_path(&test_path).unwrap())
+        .map(|loc| loc.range)
+        .collect();
+    ranges.sort_by_key(|r| (r.start.line, r.start.character));
+
+    // usefixtures, parametrize argname, indirect entry, test parameter, getfixturevalue
+    let expected: Vec<Range> = (0..5)
+        .map(|occurrence| {
+            let start = position_of(content, "db", occurrence);
+            Range::new(start, Position::new(start.line, start.character + 2))
+        })
+        .collect();
+    assert_eq!(ranges, expected);
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_document_links_for_string_references() {
+    use pytest_language_server::FixtureDatabase;
+
+    let db = Arc::new(FixtureDatabase::new());
+    let dir = std::env::temp_dir().join("test_document_links");
+    let conftest_path = dir.join("conftest.py");
+    db.analyze_file(
+        conftest_path.clone(),
+        "import pytest\n\n@pytest.fixture\ndef db():\n    return 1\n",
+    );
+    let test_path = dir.join("test_example.py");
+    let content = r#"import pytest
+
+@pytest.mark.usefixtures("db", "unknown")
+def test_marked(db):
+    value = request.getfixturevalue("db")
+"#;
+    db.analyze_file(test_path.clone(), content);
+
+    let backend = make_backend_with_db(db);
+    let params = DocumentLinkParams {
+        text_document: TextDocumentIdentifier {
+            uri: Uri::from_file_path(&test_path).unwrap(),
+        },
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+    };
+    let mut links = backend.handle_document_link(params).await.unwrap().unwrap();
+    links.sort_by_key(|link| link.range.start.line);
+
+    // Only the two resolvable string references are linked — not the
+    // parameter, and not the unknown fixture.
+    let expected_target = format!(
+        "{}#L4",
+        Uri::from_file_path(&conftest_path).unwrap().as_str()
+    );
+    assert_eq!(links.len(), 2);
+    for (link, occurrence) in links.iter().zip([0, 2]) {
+        let start = position_of(content, "db", occurrence);
+        assert_eq!(
+            link.range,
+            Range::new(start, Position::new(start.line, start.character + 2))
+        );
+        assert_eq!(link.target.as_ref().unwrap().as_str(), expected_target);
+    }
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_workspace_symbol_fuzzy_query_and_container() {
+    use pytest_language_server::FixtureDatabase;
+
+    let db = Arc::new(FixtureDatabase::new());
+    let dir = std::env::temp_dir().join("test_workspace_symbol_fuzzy");
+    db.analyze_file(
+        dir.join("conftest.py"),
+        "import pytest\n\n@pytest.fixture(scope=\"session\")\ndef database_session():\n    return 1\n\n@pytest.fixture\ndef db():\n    return 1\n",
+    );
+    db.analyze_file(
+        dir.join("api").join("conftest.py"),
+        "import pytest\n\n@pytest.fixture(scope=\"module\")\ndef api_db_session():\n    return 2\n",
+    );
+
+    let backend = make_backend_with_db(db);
+    *backend.workspace_root.write().await = Some(dir.clone());
+
+    let params = WorkspaceSymbolParams {
+        query: "DbSe".to_string(),
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+    };
+    let symbols = backend
+        .handle_workspace_symbol(params)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let found: Vec<(&str, Option<&str>)> = symbols
+        .iter()
+        .map(|s| (s.name.as_str(), s.container_name.as_deref()))
+        .collect();
+    let nested = format!(
+        "{} (module)",
+        PathBuf::from("api").join("conftest.py").display()
+    );
+    assert_eq!(
+        found,
+        vec![
+            ("api_db_session", Some(nested.as_str())),
+            ("database_session", Some("conftest.py (session)")),
+        ]
+    );
+}
+
+#[tokio::test]
+#[timeout(30000)]
+async fn test_document_symbol_outlines_tests_and_fixtures() {
+    use pytest_language_server::FixtureDatabase;
+
+    let db = Arc::new(FixtureDatabase::new());
+    let test_path = std::env::temp_dir()
+        .join("test_document_symbol_outline")
+        .join("test_example.py");
+    let content = r#"import pytest
+
+@pytest.fixture(scope="module", autouse=True)
+def setup() -> None:
+    pass
+
+def test_top():
+    pass
+
+class TestGroup:
+    @pytest.fixture
+    def resource(self):
+        return 2
+
+    def test_method(self, resource):
+        pass
+"#;
+    db.analyze_file(test_path.clone(), content);
+
+    let backend = make_backend_with_db(db);
+    let params = DocumentSymbolParams {
+        text_document: TextDocumentIdentifier {
+            uri: Uri::from_file_path(&test_path).unwrap(),
+        },
+        work_done_progress_params: Default::default(),
+        partial_result_params: Default::default(),
+    };
+    let Some(DocumentSymbolResponse::Nested(symbols)) =
+        backend.handle_document_symbol(params).await.unwrap()
+    else {
+        panic!("expected nested document symbols");
+    };
+
+    let outline: Vec<(&str, SymbolKind, Option<&str>)> = symbols
+        .iter()
+        .map(|s| (s.name.as_str(), s.kind, s.detail.as_deref()))
+        .collect();
+    assert_eq!(
+        outline,
+        vec![
+            (
+                "setup",
+                SymbolKind::FUNCTION,
+                Some("fixture (module, autouse) -> None")
+            ),
+            ("test_top", SymbolKind::FUNCTION, Some("test")),
+            ("TestGroup", SymbolKind::CLASS, Some("test class")),
+        ]
+    );
+
+    let children: Vec<(&str, SymbolKind, Option<&str>)> = symbols[2]
+        .children
+        .as_ref()
+        .unwrap()
+        .iter()
+        .map(|s| (s.name.as_str(), s.kind, s.detail.as_deref()))
+        .collect();
+    assert_eq!(
+        children,
+        vec![
+            ("resource", SymbolKind::FUNCTION, Some("fixture (function)")),
+            ("test_method", SymbolKind::METHOD, Some("test")),
+        ]
+    );
+    assert_eq!(
+        symbols[2].selection_range.start,
+        position_of(content, "TestGroup", 0)
+    );
+}
@@ -843,3 +843,33 @@ fn test_concurrent_same_file_modifications() {
         "fix_b must be cleaned up after the final analysis"
     );
 }
+
+/// Positions in a long file are looked up through its line index rather than
+/// by rescanning the file up to each definition.
+#[test]
+#[timeout(10000)]
+fn test_long_file_analysis_positions() {
+    let db = FixtureDatabase::new();
+    let count = 5000;
+    let mut content = String::from("import pytest\n");
+    for i in 0..count {
+        content.push_str(&format!(
+            "\n@pytest.fixture\ndef fixture_{i}(request):\n    return {i}\n"
+        ));
+    }
+    let path = PathBuf::from("/tmp/test_long_file/conftest.py");
+    db.analyze_file(path.clone(), &content);
+
+    assert_eq!(db.definitions.len(), count);
+    let last = db
+        .definitions
+        .get(&format!("fixture_{}", count - 1))
+        .unwrap()[0]
+        .clone();
+    assert_eq!(last.line, 4 * count);
+    assert_eq!(
+        (last.start_char, last.end_char),
+        (4, 4 + "fixture_4999".len())
+    );
+    assert_eq!(db.usages.get(&path).unwrap().len(), count);
+}